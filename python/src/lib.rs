@@ -0,0 +1,266 @@
+//! Python bindings for decomp-toolkit, built with `maturin`.
+//!
+//! ```python
+//! import decomp_toolkit as dtk
+//!
+//! obj = dtk.load_dol("orig/GAMEID/sys/main.dol")
+//! obj.apply_symbols("config/GAMEID/symbols.txt")
+//! obj.analyze()
+//! for sym in obj.symbols():
+//!     print(hex(sym.address), sym.name)
+//! ```
+
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use decomp_toolkit::{
+    analysis::{pipeline::Pipeline, tracker::Tracker},
+    cmd::{dol::ProjectConfig, fingerprint::load_project},
+    obj::{ObjInfo, ObjSection, ObjSectionKind, ObjSymbol},
+    util::{
+        config::{apply_splits_file, apply_symbols_file, write_splits_file, write_symbols_file},
+        dol::process_dol,
+        elf::process_elf,
+        file::{buf_reader, map_file},
+        rel::process_rel,
+        timings::Timings,
+    },
+};
+use pyo3::{prelude::*, types::PyDict};
+
+/// A loaded DOL, REL or ELF.
+#[pyclass(name = "Obj", unsendable)]
+pub struct PyObj {
+    obj: ObjInfo,
+}
+
+/// A copy of a symbol. Changes aren't reflected in the object.
+#[pyclass(name = "Symbol")]
+#[derive(Clone)]
+pub struct PySymbol {
+    #[pyo3(get)]
+    name: String,
+    #[pyo3(get)]
+    demangled_name: Option<String>,
+    #[pyo3(get)]
+    address: u64,
+    #[pyo3(get)]
+    size: u64,
+    #[pyo3(get)]
+    section: Option<usize>,
+    /// `function`, `object`, `section` or `unknown`
+    #[pyo3(get)]
+    kind: String,
+    /// `global`, `local`, `weak` or `unknown`
+    #[pyo3(get)]
+    scope: String,
+}
+
+impl From<&ObjSymbol> for PySymbol {
+    fn from(symbol: &ObjSymbol) -> Self {
+        Self {
+            name: symbol.name.to_string(),
+            demangled_name: symbol.demangled_name.map(String::from),
+            address: symbol.address,
+            size: symbol.size,
+            section: symbol.section,
+            kind: format!("{:?}", symbol.kind).to_ascii_lowercase(),
+            scope: format!("{:?}", symbol.flags.scope()).to_ascii_lowercase(),
+        }
+    }
+}
+
+#[pymethods]
+impl PySymbol {
+    fn __repr__(&self) -> String {
+        format!("Symbol({:?}, address={:#X}, size={:#X})", self.name, self.address, self.size)
+    }
+}
+
+/// A copy of a section's header.
+#[pyclass(name = "Section")]
+#[derive(Clone)]
+pub struct PySection {
+    #[pyo3(get)]
+    index: usize,
+    #[pyo3(get)]
+    name: String,
+    /// `code`, `data`, `rodata` or `bss`
+    #[pyo3(get)]
+    kind: String,
+    #[pyo3(get)]
+    address: u64,
+    #[pyo3(get)]
+    size: u64,
+    #[pyo3(get)]
+    align: u64,
+}
+
+impl PySection {
+    fn new(index: usize, section: &ObjSection) -> Self {
+        Self {
+            index,
+            name: section.name.clone(),
+            kind: match section.kind {
+                ObjSectionKind::Code => "code",
+                ObjSectionKind::Data => "data",
+                ObjSectionKind::ReadOnlyData => "rodata",
+                ObjSectionKind::Bss => "bss",
+            }
+            .to_string(),
+            address: section.address,
+            size: section.size,
+            align: section.align,
+        }
+    }
+}
+
+#[pymethods]
+impl PySection {
+    fn __repr__(&self) -> String {
+        format!("Section({:?}, address={:#X}, size={:#X})", self.name, self.address, self.size)
+    }
+}
+
+#[pymethods]
+impl PyObj {
+    #[getter]
+    fn name(&self) -> &str { &self.obj.name }
+
+    #[getter]
+    fn module_id(&self) -> u32 { self.obj.module_id }
+
+    fn symbols(&self) -> Vec<PySymbol> { self.obj.symbols.iter().map(PySymbol::from).collect() }
+
+    /// Looks up a symbol by name.
+    fn symbol(&self, name: &str) -> PyResult<Option<PySymbol>> {
+        Ok(self.obj.symbols.by_name(name)?.map(|(_, s)| s.into()))
+    }
+
+    /// Symbols starting at an address in a section.
+    fn symbols_at(&self, section: usize, address: u32) -> Vec<PySymbol> {
+        self.obj.symbols.at_section_address(section, address).map(|(_, s)| s.into()).collect()
+    }
+
+    fn sections(&self) -> Vec<PySection> {
+        self.obj.sections.iter().map(|(i, s)| PySection::new(i, s)).collect()
+    }
+
+    /// Section data, or `None` for BSS.
+    fn section_data(&self, index: usize) -> Option<&[u8]> {
+        let section = self.obj.sections.get(index)?;
+        (!section.data.is_empty()).then_some(section.data.as_slice())
+    }
+
+    /// Applies a `symbols.txt`. Returns whether the file exists.
+    fn apply_symbols(&mut self, path: PathBuf) -> PyResult<bool> {
+        Ok(apply_symbols_file(&path, &mut self.obj)?.is_some())
+    }
+
+    /// Applies a `splits.txt`. Returns whether the file exists.
+    fn apply_splits(&mut self, path: PathBuf) -> PyResult<bool> {
+        Ok(apply_splits_file(&path, &mut self.obj)?.is_some())
+    }
+
+    fn write_symbols(&self, path: PathBuf) -> PyResult<()> {
+        Ok(write_symbols_file(&path, &self.obj, None)?)
+    }
+
+    #[pyo3(signature = (path, all = false))]
+    fn write_splits(&self, path: PathBuf, all: bool) -> PyResult<()> {
+        Ok(write_splits_file(&path, &self.obj, all, None)?)
+    }
+
+    /// Runs the analysis `dol split` uses: signatures, function boundary detection with the
+    /// built-in passes and, with `relocations`, data and relocation detection.
+    #[pyo3(signature = (relocations = true))]
+    fn analyze(&mut self, relocations: bool) -> PyResult<()> {
+        Ok(analyze(&mut self.obj, relocations)?)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Obj({:?}, {} symbols)", self.obj.name, self.obj.symbols.count())
+    }
+}
+
+fn analyze(obj: &mut ObjInfo, relocations: bool) -> Result<()> {
+    Pipeline::default().analyze(obj, false, &mut Timings::new())?;
+    if relocations {
+        let mut tracker = Tracker::new(obj);
+        tracker.process(obj)?;
+        tracker.apply(obj, false)?;
+    }
+    Ok(())
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+#[pyfunction]
+fn load_dol(path: PathBuf) -> PyResult<PyObj> {
+    let file = map_file(&path)?;
+    Ok(PyObj { obj: process_dol(file.as_slice(), &file_stem(&path))? })
+}
+
+#[pyfunction]
+fn load_rel(path: PathBuf) -> PyResult<PyObj> {
+    let file = map_file(&path)?;
+    let (_, obj) = process_rel(&mut Cursor::new(file.as_slice()), &file_stem(&path))?;
+    Ok(PyObj { obj })
+}
+
+#[pyfunction]
+fn load_elf(path: PathBuf) -> PyResult<PyObj> { Ok(PyObj { obj: process_elf(&path)? }) }
+
+/// Loads and analyzes the DOL and every module of a project, as `dol split` does.
+#[pyfunction]
+#[pyo3(name = "load_project")]
+fn load_project_objs(config: PathBuf) -> PyResult<Vec<PyObj>> {
+    let (_, objs) = load_project(&config)?;
+    Ok(objs.into_iter().map(|obj| PyObj { obj }).collect())
+}
+
+/// Reads a project config (config.yml) into a dict.
+#[pyfunction]
+fn read_config(py: Python<'_>, path: PathBuf) -> PyResult<PyObject> {
+    let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(&path)?)
+        .with_context(|| format!("While loading '{}'", path.display()))?;
+    let json = serde_json::to_string(&config).map_err(anyhow::Error::from)?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.into())
+}
+
+/// Validates a dict as a project config and writes it.
+#[pyfunction]
+fn write_config(py: Python<'_>, path: PathBuf, config: &PyDict) -> PyResult<()> {
+    let json: String = py.import("json")?.call_method1("dumps", (config,))?.extract()?;
+    let config: ProjectConfig = serde_json::from_str(&json).context("Invalid project config")?;
+    let yaml = serde_yaml::to_string(&config).map_err(anyhow::Error::from)?;
+    fs::write(&path, yaml).with_context(|| format!("Failed to write '{}'", path.display()))?;
+    Ok(())
+}
+
+#[pyfunction]
+fn demangle(name: &str) -> Option<String> {
+    cwdemangle::demangle(name, &cwdemangle::DemangleOptions::default())
+}
+
+#[pymodule]
+#[pyo3(name = "decomp_toolkit")]
+fn module(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyObj>()?;
+    m.add_class::<PySymbol>()?;
+    m.add_class::<PySection>()?;
+    m.add_function(wrap_pyfunction!(load_dol, m)?)?;
+    m.add_function(wrap_pyfunction!(load_rel, m)?)?;
+    m.add_function(wrap_pyfunction!(load_elf, m)?)?;
+    m.add_function(wrap_pyfunction!(load_project_objs, m)?)?;
+    m.add_function(wrap_pyfunction!(read_config, m)?)?;
+    m.add_function(wrap_pyfunction!(write_config, m)?)?;
+    m.add_function(wrap_pyfunction!(demangle, m)?)?;
+    Ok(())
+}
@@ -117,6 +117,34 @@ pub struct AnalyzerState {
     pub jump_tables: BTreeMap<SectionAddress, u32>,
     pub known_symbols: BTreeMap<SectionAddress, ObjSymbol>,
     pub known_sections: BTreeMap<usize, String>,
+    /// Data in code sections found by passes, which is never a function.
+    pub data_ranges: BTreeMap<SectionAddress, u32>, // start -> end
+    /// Exception handler code found by passes. See [`ObjInfo::vector_ranges`].
+    pub vector_ranges: BTreeMap<SectionAddress, u32>, // start -> end
+}
+
+/// Function sizes from extabindex are exact, so they take precedence over the end
+/// found by analysis. (e.g. when unreachable code follows the last `blr`)
+fn known_end(
+    obj: &ObjInfo,
+    addr: SectionAddress,
+    end: Option<SectionAddress>,
+) -> Option<SectionAddress> {
+    match (end, obj.known_functions.get(&addr)) {
+        (Some(end), Some(&Some(size))) => {
+            let known = addr + size;
+            if known != end {
+                log::debug!(
+                    "Using known end {:#010X} for {:#010X} (analysis found {:#010X})",
+                    known,
+                    addr,
+                    end
+                );
+            }
+            Some(known)
+        }
+        _ => end,
+    }
 }
 
 impl AnalyzerState {
@@ -124,6 +152,7 @@ impl AnalyzerState {
         for (&section_index, section_name) in &self.known_sections {
             obj.sections[section_index].rename(section_name.clone())?;
         }
+        obj.vector_ranges.extend(&self.vector_ranges);
         for (&start, FunctionInfo { end, .. }) in self.functions.iter() {
             let Some(end) = end else { continue };
             let section = &obj.sections[start.section];
@@ -143,7 +172,7 @@ impl AnalyzerState {
             };
             obj.add_symbol(
                 ObjSymbol {
-                    name,
+                    name: name.into(),
                     address: start.address as u64,
                     section: Some(start.section),
                     size: (end.address - start.address) as u64,
@@ -184,7 +213,7 @@ impl AnalyzerState {
             };
             obj.add_symbol(
                 ObjSymbol {
-                    name: format!("jumptable_{}", address_str),
+                    name: format!("jumptable_{}", address_str).into(),
                     address: addr.address as u64,
                     section: Some(addr.section),
                     size: size as u64,
@@ -215,11 +244,13 @@ impl AnalyzerState {
         for (_, symbol) in obj.symbols.by_kind(ObjSymbolKind::Function) {
             let Some(section_index) = symbol.section else { continue };
             let addr_ref = SectionAddress::new(section_index, symbol.address as u32);
-            self.functions.insert(addr_ref, FunctionInfo {
-                analyzed: false,
-                end: if symbol.size_known { Some(addr_ref + symbol.size as u32) } else { None },
-                slices: None,
-            });
+            // Keep the size from extab if the symbol doesn't have one
+            let end = if symbol.size_known {
+                Some(addr_ref + symbol.size as u32)
+            } else {
+                self.functions.get(&addr_ref).and_then(|info| info.end)
+            };
+            self.functions.insert(addr_ref, FunctionInfo { analyzed: false, end, slices: None });
         }
         // Also check the beginning of every code section
         for (section_index, section) in obj.sections.by_kind(ObjSectionKind::Code) {
@@ -336,7 +367,7 @@ impl AnalyzerState {
                     }
                 }
                 self.jump_tables.append(&mut slices.jump_table_references.clone());
-                let end = slices.end();
+                let end = known_end(obj, addr, slices.end());
                 let info = self.functions.get_mut(&addr).unwrap();
                 info.analyzed = true;
                 info.end = end;
@@ -383,7 +414,7 @@ impl AnalyzerState {
                 slices.finalize(obj, &self.functions)?;
                 let info = self.functions.entry(addr).or_default();
                 info.analyzed = true;
-                info.end = slices.end();
+                info.end = known_end(obj, addr, slices.end());
                 info.slices = Some(slices);
             } else {
                 let info = self.functions.entry(addr).or_default();
@@ -393,6 +424,9 @@ impl AnalyzerState {
             }
             true
         } else {
+            if obj.known_functions.contains_key(&addr) {
+                log::warn!("Known function @ {:#010X} failed analysis", addr);
+            }
             log::debug!("Not a function @ {:#010X}", addr);
             let info = self.functions.entry(addr).or_default();
             info.analyzed = true;
@@ -419,6 +453,30 @@ impl AnalyzerState {
         for (section_index, section) in obj.sections.by_kind(ObjSectionKind::Code) {
             let section_start = SectionAddress::new(section_index, section.address as u32);
             let section_end = section_start + section.size as u32;
+            // Data embedded in code (object symbols) is never a function
+            let data_ranges = obj
+                .symbols
+                .for_section(section_index)
+                .filter(|(_, s)| s.kind == ObjSymbolKind::Object && s.size > 0)
+                .map(|(_, s)| {
+                    let start = SectionAddress::new(section_index, s.address as u32);
+                    (start, start + s.size as u32)
+                })
+                .chain(
+                    self.data_ranges
+                        .iter()
+                        .filter(|(start, _)| start.section == section_index)
+                        .map(|(&start, &end)| (start, SectionAddress::new(section_index, end))),
+                )
+                .collect_vec();
+            let skip_data = |mut addr: SectionAddress, end: SectionAddress| {
+                while let Some(&(_, data_end)) =
+                    data_ranges.iter().find(|&&(start, data_end)| addr >= start && addr < data_end)
+                {
+                    addr = skip_alignment(section, data_end, end)?;
+                }
+                Some(addr)
+            };
             let mut iter = self.functions.range(section_start..section_end).peekable();
             loop {
                 match (iter.next(), iter.peek()) {
@@ -427,7 +485,9 @@ impl AnalyzerState {
                         if first_end > second {
                             bail!("Overlapping functions {}-{} -> {}", first, first_end, second);
                         }
-                        let addr = match skip_alignment(section, first_end, second) {
+                        let addr = match skip_alignment(section, first_end, second)
+                            .and_then(|addr| skip_data(addr, second))
+                        {
                             Some(addr) => addr,
                             None => continue,
                         };
@@ -446,7 +506,9 @@ impl AnalyzerState {
                     (Some((last, last_info)), None) => {
                         let Some(last_end) = last_info.end else { continue };
                         if last_end < section_end {
-                            let addr = match skip_alignment(section, last_end, section_end) {
+                            let addr = match skip_alignment(section, last_end, section_end)
+                                .and_then(|addr| skip_data(addr, section_end))
+                            {
                                 Some(addr) => addr,
                                 None => continue,
                             };
@@ -487,7 +549,7 @@ pub fn locate_sda_bases(obj: &mut ObjInfo) -> Result<bool> {
         .context(format!("Entry point {:#010X} outside of any section", entry))?;
     let entry_addr = SectionAddress::new(section_index, entry as u32);
 
-    let mut executor = Executor::new(obj);
+    let mut executor = Executor::new();
     executor.push(entry_addr, VM::new(), false);
     let result = executor.run(
         obj,
@@ -546,7 +608,7 @@ pub fn locate_bss_memsets(obj: &mut ObjInfo) -> Result<Vec<(u32, u32)>> {
         .context(format!("Entry point {:#010X} outside of any section", entry))?;
     let entry_addr = SectionAddress::new(section_index, entry as u32);
 
-    let mut executor = Executor::new(obj);
+    let mut executor = Executor::new();
     executor.push(entry_addr, VM::new(), false);
     executor.run(
         obj,
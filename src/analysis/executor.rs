@@ -1,6 +1,6 @@
 use anyhow::{ensure, Result};
-use fixedbitset::FixedBitSet;
 use ppc750cl::Ins;
+use rustc_hash::FxHashSet;
 
 use crate::{
     analysis::{
@@ -11,38 +11,22 @@ use crate::{
     obj::{ObjInfo, ObjSection, ObjSectionKind},
 };
 
-/// Space-efficient implementation for tracking visited code addresses
+/// Sparse set of visited code addresses.
+///
+/// An executor is created per function, and typically only visits a small part of a
+/// section. A per-section bitset would require allocating (and zeroing) memory
+/// proportional to the size of every code section for each function analyzed.
+#[derive(Default)]
 struct VisitedAddresses {
-    inner: Vec<FixedBitSet>,
+    inner: FxHashSet<SectionAddress>,
 }
 
 impl VisitedAddresses {
-    pub fn new(obj: &ObjInfo) -> Self {
-        let mut inner = Vec::with_capacity(obj.sections.count());
-        for (_, section) in obj.sections.iter() {
-            if section.kind == ObjSectionKind::Code {
-                let size = (section.size / 4) as usize;
-                inner.push(FixedBitSet::with_capacity(size));
-            } else {
-                // Empty
-                inner.push(FixedBitSet::new())
-            }
-        }
-        Self { inner }
-    }
-
-    pub fn contains(&self, section_address: u32, address: SectionAddress) -> bool {
-        self.inner[address.section].contains(Self::bit_for(section_address, address.address))
-    }
-
-    pub fn insert(&mut self, section_address: u32, address: SectionAddress) {
-        self.inner[address.section].insert(Self::bit_for(section_address, address.address));
-    }
+    #[inline]
+    pub fn contains(&self, address: SectionAddress) -> bool { self.inner.contains(&address) }
 
     #[inline]
-    fn bit_for(section_address: u32, address: u32) -> usize {
-        ((address - section_address) / 4) as usize
-    }
+    pub fn insert(&mut self, address: SectionAddress) { self.inner.insert(address); }
 }
 
 pub struct VMState {
@@ -51,6 +35,7 @@ pub struct VMState {
 }
 
 /// Helper for branched VM execution, only visiting addresses once.
+#[derive(Default)]
 pub struct Executor {
     vm_stack: Vec<VMState>,
     visited: VisitedAddresses,
@@ -74,9 +59,7 @@ pub enum ExecCbResult<T = ()> {
 }
 
 impl Executor {
-    pub fn new(obj: &ObjInfo) -> Self {
-        Self { vm_stack: vec![], visited: VisitedAddresses::new(obj) }
-    }
+    pub fn new() -> Self { Self { vm_stack: vec![], visited: VisitedAddresses::default() } }
 
     pub fn run<Cb, R>(&mut self, obj: &ObjInfo, mut cb: Cb) -> Result<Option<R>>
     where Cb: FnMut(ExecCbData) -> Result<ExecCbResult<R>> {
@@ -96,14 +79,13 @@ impl Executor {
             }
 
             // Already visited block
-            let section_address = section.address as u32;
-            if self.visited.contains(section_address, state.address) {
+            if self.visited.contains(state.address) {
                 continue;
             }
 
             let mut block_start = state.address;
             loop {
-                self.visited.insert(section_address, state.address);
+                self.visited.insert(state.address);
 
                 let ins = match disassemble(section, state.address.address) {
                     Some(ins) => ins,
@@ -123,7 +105,7 @@ impl Executor {
                         state.address += 4;
                     }
                     ExecCbResult::Jump(addr) => {
-                        if self.visited.contains(section_address, addr) {
+                        if self.visited.contains(addr) {
                             break;
                         }
                         block_start = addr;
@@ -145,7 +127,5 @@ impl Executor {
         }
     }
 
-    pub fn visited(&self, section_address: u32, address: SectionAddress) -> bool {
-        self.visited.contains(section_address, address)
-    }
+    pub fn visited(&self, address: SectionAddress) -> bool { self.visited.contains(address) }
 }
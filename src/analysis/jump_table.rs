@@ -0,0 +1,497 @@
+//! Jump table bounds from the control flow graph.
+//!
+//! A switch's bounds check (`cmplwi rN, max` and a conditional branch to the default case)
+//! doesn't have to sit right before the `bctr`: the scheduler can move it into an earlier
+//! block, and a switch in a loop may have the check hoisted out of it. Rather than relying on
+//! the path the VM happened to take, this searches the `bctr`'s dominators for a comparison of
+//! the index register, following register copies, and only accepts it if the in-range edge
+//! dominates the `bctr` and the index isn't redefined on any path in between.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    num::NonZeroU32,
+};
+
+use ppc750cl::{Argument, Ins, Opcode, GPR};
+
+use crate::{
+    analysis::{cfa::SectionAddress, disassemble, slices::FunctionSlices},
+    obj::{ObjInfo, ObjSection},
+};
+
+/// How far to search backwards from a `bctr`, in instructions.
+const MAX_SEARCH: usize = 0x200;
+
+/// Largest accepted jump table, in entries.
+const MAX_ENTRIES: u32 = 0x4000;
+
+/// The blocks analyzed so far, with their edges and dominators. Kept in [`FunctionSlices`]
+/// between jump tables, and only rebuilt once analysis has found more blocks or branches.
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    entry: SectionAddress,
+    blocks: BTreeMap<SectionAddress, SectionAddress>,
+    branches: BTreeMap<SectionAddress, Vec<SectionAddress>>,
+    preds: BTreeMap<SectionAddress, Vec<SectionAddress>>,
+    dominators: BTreeMap<SectionAddress, BTreeSet<SectionAddress>>,
+}
+
+/// Completed blocks of a function analyzed so far.
+fn completed_blocks(
+    slices: &FunctionSlices,
+    entry: SectionAddress,
+) -> BTreeMap<SectionAddress, SectionAddress> {
+    slices
+        .blocks
+        .iter()
+        .filter_map(|(&start, &end)| end.map(|end| (start, end)))
+        .filter(|(start, _)| start.section == entry.section)
+        .collect()
+}
+
+impl Cfg {
+    /// The graph in `cached` if the blocks and branches haven't changed since it was built,
+    /// otherwise a new one, which replaces it.
+    fn update<'c>(
+        cached: &'c mut Option<Cfg>,
+        section: &ObjSection,
+        slices: &FunctionSlices,
+        entry: SectionAddress,
+    ) -> Option<&'c Cfg> {
+        let blocks = completed_blocks(slices, entry);
+        let current = cached.as_ref().is_some_and(|cfg| {
+            cfg.entry == entry && cfg.blocks == blocks && cfg.branches == slices.branches
+        });
+        if !current {
+            *cached = Self::new(section, slices, entry, blocks);
+        }
+        cached.as_ref()
+    }
+
+    fn new(
+        section: &ObjSection,
+        slices: &FunctionSlices,
+        entry: SectionAddress,
+        blocks: BTreeMap<SectionAddress, SectionAddress>,
+    ) -> Option<Self> {
+        if !blocks.contains_key(&entry) {
+            return None;
+        }
+
+        let mut succs = BTreeMap::<SectionAddress, Vec<SectionAddress>>::new();
+        for (&start, &end) in &blocks {
+            let last = end - 4;
+            let targets = match slices.branches.get(&last) {
+                Some(targets) => targets.clone(),
+                None if disassemble(section, last.address).is_some_and(|ins| ins.is_branch()) => {
+                    vec![]
+                }
+                // Falls through into a block split from this one
+                None => vec![end],
+            };
+            succs.insert(
+                start,
+                targets.into_iter().filter(|target| blocks.contains_key(target)).collect(),
+            );
+        }
+        let mut preds = BTreeMap::<SectionAddress, Vec<SectionAddress>>::new();
+        for (&from, targets) in &succs {
+            for &to in targets {
+                preds.entry(to).or_default().push(from);
+            }
+        }
+
+        // Iterative dominators over the blocks reachable from the entry
+        let mut reachable = BTreeSet::from([entry]);
+        let mut stack = vec![entry];
+        while let Some(block) = stack.pop() {
+            for &succ in &succs[&block] {
+                if reachable.insert(succ) {
+                    stack.push(succ);
+                }
+            }
+        }
+        let mut dominators = reachable
+            .iter()
+            .map(|&block| {
+                (block, if block == entry { BTreeSet::from([entry]) } else { reachable.clone() })
+            })
+            .collect::<BTreeMap<_, _>>();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in reachable.iter().filter(|&&b| b != entry) {
+                let mut set: Option<BTreeSet<SectionAddress>> = None;
+                for pred in preds.get(&block).into_iter().flatten() {
+                    let Some(pred_set) = dominators.get(pred) else { continue };
+                    set = Some(match set {
+                        Some(set) => set.intersection(pred_set).cloned().collect(),
+                        None => pred_set.clone(),
+                    });
+                }
+                let mut set = set.unwrap_or_default();
+                set.insert(block);
+                if dominators[&block] != set {
+                    dominators.insert(block, set);
+                    changed = true;
+                }
+            }
+        }
+        Some(Self { entry, blocks, branches: slices.branches.clone(), preds, dominators })
+    }
+
+    fn block_containing(
+        &self,
+        address: SectionAddress,
+    ) -> Option<(SectionAddress, SectionAddress)> {
+        self.blocks
+            .range(..=address)
+            .next_back()
+            .filter(|(_, &end)| address < end)
+            .map(|(&start, &end)| (start, end))
+    }
+
+    fn dominates(&self, a: SectionAddress, b: SectionAddress) -> bool {
+        self.dominators.get(&b).is_some_and(|set| set.contains(&a))
+    }
+
+    /// The closest strict dominator, whose own dominators are the most numerous.
+    fn immediate_dominator(&self, block: SectionAddress) -> Option<SectionAddress> {
+        self.dominators
+            .get(&block)?
+            .iter()
+            .filter(|&&d| d != block)
+            .max_by_key(|&&d| self.dominators.get(&d).map_or(0, |set| set.len()))
+            .cloned()
+    }
+
+    /// Whether any block on a path from `dominator` to `block` (excluding `dominator`, and
+    /// `block` itself unless it's in a loop) defines `reg`.
+    fn defined_between(
+        &self,
+        section: &ObjSection,
+        dominator: SectionAddress,
+        block: SectionAddress,
+        reg: u8,
+    ) -> bool {
+        let mut visited = BTreeSet::new();
+        let mut stack = self.preds.get(&block).cloned().unwrap_or_default();
+        while let Some(current) = stack.pop() {
+            if current == dominator || !visited.insert(current) {
+                continue;
+            }
+            let end = self.blocks[&current];
+            let mut address = current;
+            while address < end {
+                if disassemble(section, address.address).is_some_and(|ins| defines(&ins, reg)) {
+                    return true;
+                }
+                address += 4;
+            }
+            stack.extend(self.preds.get(&current).into_iter().flatten());
+        }
+        false
+    }
+}
+
+/// Walks backwards from an instruction through its block and then its dominators.
+#[derive(Clone)]
+struct BackwardWalk<'a> {
+    cfg: &'a Cfg,
+    section: &'a ObjSection,
+    block: SectionAddress,
+    address: SectionAddress,
+    steps: usize,
+}
+
+impl<'a> BackwardWalk<'a> {
+    fn new(cfg: &'a Cfg, section: &'a ObjSection, from: SectionAddress) -> Option<Self> {
+        let (block, _) = cfg.block_containing(from)?;
+        Some(Self { cfg, section, block, address: from, steps: 0 })
+    }
+
+    /// The previous instruction while `reg` is the register of interest, or `None` if the
+    /// walk can't continue because another path into the block may define it.
+    fn prev(&mut self, reg: u8) -> Option<Ins> {
+        self.steps += 1;
+        if self.steps > MAX_SEARCH {
+            return None;
+        }
+        if self.address == self.block {
+            let dominator = self.cfg.immediate_dominator(self.block)?;
+            if self.cfg.defined_between(self.section, dominator, self.block, reg) {
+                return None;
+            }
+            self.block = dominator;
+            self.address = self.cfg.blocks[&dominator];
+        }
+        self.address = self.address - 4;
+        disassemble(self.section, self.address.address)
+    }
+}
+
+/// Whether an instruction writes to a GPR, counting calls as clobbering volatile registers.
+fn defines(ins: &Ins, reg: u8) -> bool {
+    if ins.is_branch() && ins.field_LK() && (reg == 0 || (3..=12).contains(&reg)) {
+        return true;
+    }
+    ins.defs()
+        .iter()
+        .any(|field| matches!(field.argument(), Some(Argument::GPR(GPR(r))) if r == reg))
+}
+
+/// Whether an instruction may write to a CR field.
+fn defines_cr(ins: &Ins, crf: u8) -> bool {
+    match ins.op {
+        Opcode::Cmp | Opcode::Cmpi | Opcode::Cmpl | Opcode::Cmpli => ins.field_crfD() as u8 == crf,
+        _ => ins.defs().iter().any(|field| matches!(field.argument(), Some(Argument::CRField(_)))),
+    }
+}
+
+/// Finds the instruction defining `reg` before `walk`'s position.
+fn find_def(walk: &mut BackwardWalk, reg: u8) -> Option<Ins> {
+    loop {
+        let ins = walk.prev(reg)?;
+        if defines(&ins, reg) {
+            return Some(ins);
+        }
+    }
+}
+
+/// Infers the size in bytes of the jump table used by the `bctr` at `ins_addr` from the blocks
+/// of the function analyzed so far. `cfg` caches the function's graph between calls.
+pub fn infer_jump_table_size(
+    obj: &ObjInfo,
+    slices: &FunctionSlices,
+    cfg: &mut Option<Cfg>,
+    function_start: SectionAddress,
+    ins_addr: SectionAddress,
+) -> Option<NonZeroU32> {
+    let section = &obj.sections[function_start.section];
+    let cfg = Cfg::update(cfg, section, slices, function_start)?;
+    let (bctr_block, _) = cfg.block_containing(ins_addr)?;
+
+    // mtctr rX
+    let mut walk = BackwardWalk::new(cfg, section, ins_addr)?;
+    let mtctr = loop {
+        let ins = walk.prev(0)?;
+        if ins.op == Opcode::Mtspr && ins.field_spr() == 9 {
+            break ins;
+        }
+        if walk.address == walk.block {
+            return None;
+        }
+    };
+    // lwzx rX, rTable, rOffset
+    let load = find_def(&mut walk, mtctr.field_rS() as u8)?;
+    if load.op != Opcode::Lwzx {
+        return None;
+    }
+    // slwi rOffset, rIndex, 2
+    let (mut walk, mut reg) =
+        [load.field_rB(), load.field_rA()].into_iter().find_map(|offset| {
+            let mut walk = walk.clone();
+            let ins = find_def(&mut walk, offset as u8)?;
+            (ins.op == Opcode::Rlwinm
+                && ins.field_SH() == 2
+                && ins.field_MB() == 0
+                && ins.field_ME() == 29)
+                .then(|| (walk, ins.field_rS() as u8))
+        })?;
+
+    // cmplwi crN, rIndex, max
+    let (cmp, cmp_address) = loop {
+        let ins = walk.prev(reg)?;
+        if ins.op == Opcode::Cmpli && ins.field_L() == 0 && ins.field_rA() as u8 == reg {
+            break (ins, walk.address);
+        }
+        if !defines(&ins, reg) {
+            continue;
+        }
+        match ins.op {
+            // mr rIndex, rS
+            Opcode::Or if ins.field_rS() == ins.field_rB() => reg = ins.field_rS() as u8,
+            // clrlwi rIndex, rS, n: the bound on rS still holds
+            Opcode::Rlwinm if ins.field_SH() == 0 && ins.field_ME() == 31 => {
+                reg = ins.field_rS() as u8
+            }
+            _ => return None,
+        }
+    };
+
+    // The branch ending the comparison's block, and its in-range edge
+    let crf = cmp.field_crfD() as u8;
+    let (cmp_block, cmp_end) = cfg.block_containing(cmp_address)?;
+    let branch_address = cmp_end - 4;
+    let branch = disassemble(section, branch_address.address)?;
+    if branch.op != Opcode::Bc || branch.field_BI() as u8 >> 2 != crf {
+        return None;
+    }
+    let mut address = cmp_address + 4;
+    while address < branch_address {
+        if disassemble(section, address.address).is_some_and(|ins| defines_cr(&ins, crf)) {
+            return None;
+        }
+        address += 4;
+    }
+    let taken = SectionAddress::new(cmp_block.section, branch.branch_dest()?);
+    let if_true = match branch.field_BO() & 0b11110 {
+        0b01100 => true,
+        0b00100 => false,
+        _ => return None,
+    };
+    let limit = cmp.field_uimm() as u32;
+    let (in_range, max) = match (branch.field_BI() & 3, if_true) {
+        // bgt default
+        (1, true) => (cmp_end, limit),
+        // ble table
+        (1, false) => (taken, limit),
+        // blt table
+        (0, true) => (taken, limit.checked_sub(1)?),
+        // bge default
+        (0, false) => (cmp_end, limit.checked_sub(1)?),
+        _ => return None,
+    };
+    if max >= MAX_ENTRIES || !cfg.dominates(in_range, bctr_block) {
+        return None;
+    }
+    log::debug!(
+        "Jump table bounds for {:#010X} from comparison @ {:#010X}: {} entries",
+        ins_addr,
+        cmp_address,
+        max + 1
+    );
+    NonZeroU32::new((max + 1) * 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::{ObjArchitecture, ObjKind, ObjSectionKind};
+
+    const BASE: u32 = 0x80000000;
+    const DEFAULT: u32 = 0x40;
+
+    fn cmplwi(ra: u32, uimm: u32) -> u32 { (10 << 26) | (ra << 16) | uimm }
+    fn bc(bo: u32, bi: u32, from: u32, to: u32) -> u32 {
+        (16 << 26) | (bo << 21) | (bi << 16) | (to.wrapping_sub(from) & 0xFFFC)
+    }
+    fn bgt(from: u32, to: u32) -> u32 { bc(12, 1, from, to) }
+    fn bge(from: u32, to: u32) -> u32 { bc(4, 0, from, to) }
+    fn blt(from: u32, to: u32) -> u32 { bc(12, 0, from, to) }
+    fn b(from: u32, to: u32) -> u32 { (18 << 26) | (to.wrapping_sub(from) & 0x03FFFFFC) }
+    fn addi(rd: u32, ra: u32, simm: u32) -> u32 { (14 << 26) | (rd << 21) | (ra << 16) | simm }
+    fn slwi_2(ra: u32, rs: u32) -> u32 {
+        (21 << 26) | (rs << 21) | (ra << 16) | (2 << 11) | (29 << 1)
+    }
+    fn lwzx(rd: u32, ra: u32, rb: u32) -> u32 {
+        (31 << 26) | (rd << 21) | (ra << 16) | (rb << 11) | (23 << 1)
+    }
+    fn mtctr(rs: u32) -> u32 { 0x7C0903A6 | (rs << 21) }
+    const BCTR: u32 = 0x4E800420;
+    const BLR: u32 = 0x4E800020;
+
+    /// A function from instructions at offsets from [`BASE`], with a `blr` default case at
+    /// [`DEFAULT`], and its blocks and branches as `(start, end, targets)` offsets.
+    fn infer(code: &[(u32, u32)], blocks: &[(u32, u32, &[u32])], bctr: u32) -> Option<u32> {
+        let mut data = vec![0u8; DEFAULT as usize + 4];
+        for &(offset, ins) in code.iter().chain(&[(DEFAULT, BLR)]) {
+            data[offset as usize..offset as usize + 4].copy_from_slice(&ins.to_be_bytes());
+        }
+        let section = ObjSection::raw(".text", ObjSectionKind::Code, BASE, data);
+        let obj = ObjInfo::new(
+            ObjKind::Executable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            vec![],
+            vec![section],
+        );
+        let address = |offset: u32| SectionAddress::new(0, BASE + offset);
+        let mut slices = FunctionSlices::default();
+        for &(start, end, targets) in blocks.iter().chain(&[(DEFAULT, DEFAULT + 4, &[][..])]) {
+            slices.blocks.insert(address(start), Some(address(end)));
+            if !targets.is_empty() {
+                slices
+                    .branches
+                    .insert(address(end - 4), targets.iter().map(|&t| address(t)).collect());
+            }
+        }
+        let mut cfg = None;
+        let size = infer_jump_table_size(&obj, &slices, &mut cfg, address(0), address(bctr));
+        // The graph is kept for the next jump table
+        assert!(cfg.is_some());
+        size.map(NonZeroU32::get)
+    }
+
+    /// `slwi r0, r3, 2; lwzx r0, r4, r0; mtctr r0; bctr` at `offset`.
+    fn dispatch(offset: u32) -> [(u32, u32); 4] {
+        [
+            (offset, slwi_2(0, 3)),
+            (offset + 4, lwzx(0, 4, 0)),
+            (offset + 8, mtctr(0)),
+            (offset + 12, BCTR),
+        ]
+    }
+
+    #[test]
+    fn hoisted_bound() {
+        // cmplwi r3, 4; bgt default; addi r4, r4, 0x100; <dispatch>
+        let mut code =
+            vec![(0x00, cmplwi(3, 4)), (0x04, bgt(0x04, DEFAULT)), (0x08, addi(4, 4, 0x100))];
+        code.extend(dispatch(0x0C));
+        let blocks: &[(u32, u32, &[u32])] =
+            &[(0x00, 0x08, &[DEFAULT, 0x08]), (0x08, 0x0C, &[]), (0x0C, 0x1C, &[])];
+        assert_eq!(infer(&code, blocks, 0x18), Some(5 * 4));
+    }
+
+    #[test]
+    fn redefined_index() {
+        // cmplwi r3, 4; bgt default; li r3, 7; <dispatch>
+        let mut code =
+            vec![(0x00, cmplwi(3, 4)), (0x04, bgt(0x04, DEFAULT)), (0x08, addi(3, 0, 7))];
+        code.extend(dispatch(0x0C));
+        let blocks: &[(u32, u32, &[u32])] =
+            &[(0x00, 0x08, &[DEFAULT, 0x08]), (0x08, 0x0C, &[]), (0x0C, 0x1C, &[])];
+        assert_eq!(infer(&code, blocks, 0x18), None);
+    }
+
+    #[test]
+    fn bound_in_loop() {
+        // loop: cmplwi r3, 4; bgt default; <dispatch>; case: addi r3, r3, 1; b loop
+        let mut code = vec![(0x00, cmplwi(3, 4)), (0x04, bgt(0x04, DEFAULT))];
+        code.extend(dispatch(0x08));
+        code.extend([(0x18, addi(3, 3, 1)), (0x1C, b(0x1C, 0x00))]);
+        let blocks: &[(u32, u32, &[u32])] =
+            &[(0x00, 0x08, &[DEFAULT, 0x08]), (0x08, 0x18, &[0x18]), (0x18, 0x20, &[0x00])];
+        assert_eq!(infer(&code, blocks, 0x14), Some(5 * 4));
+    }
+
+    #[test]
+    fn bound_before_loop() {
+        // cmplwi r3, 4; bgt default; loop: <dispatch>; case: addi r3, r3, 1; b loop
+        // The loop increments the index past the checked bound.
+        let mut code = vec![(0x00, cmplwi(3, 4)), (0x04, bgt(0x04, DEFAULT))];
+        code.extend(dispatch(0x08));
+        code.extend([(0x18, addi(3, 3, 1)), (0x1C, b(0x1C, 0x08))]);
+        let blocks: &[(u32, u32, &[u32])] =
+            &[(0x00, 0x08, &[DEFAULT, 0x08]), (0x08, 0x18, &[0x18]), (0x18, 0x20, &[0x08])];
+        assert_eq!(infer(&code, blocks, 0x14), None);
+    }
+
+    #[test]
+    fn exclusive_bounds() {
+        // cmplwi r3, 5; bge default; <dispatch>
+        let mut code = vec![(0x00, cmplwi(3, 5)), (0x04, bge(0x04, DEFAULT))];
+        code.extend(dispatch(0x08));
+        let blocks: &[(u32, u32, &[u32])] = &[(0x00, 0x08, &[DEFAULT, 0x08]), (0x08, 0x18, &[])];
+        assert_eq!(infer(&code, blocks, 0x14), Some(5 * 4));
+
+        // cmplwi r3, 5; blt table; b default; table: <dispatch>
+        let mut code =
+            vec![(0x00, cmplwi(3, 5)), (0x04, blt(0x04, 0x0C)), (0x08, b(0x08, DEFAULT))];
+        code.extend(dispatch(0x0C));
+        let blocks: &[(u32, u32, &[u32])] =
+            &[(0x00, 0x08, &[0x0C, 0x08]), (0x08, 0x0C, &[DEFAULT]), (0x0C, 0x1C, &[])];
+        assert_eq!(infer(&code, blocks, 0x18), Some(5 * 4));
+    }
+}
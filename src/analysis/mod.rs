@@ -11,13 +11,37 @@ use crate::{
 
 pub mod cfa;
 pub mod executor;
+pub mod jump_table;
 pub mod objects;
 pub mod pass;
+pub mod pipeline;
 pub mod signatures;
 pub mod slices;
 pub mod tracker;
 pub mod vm;
 
+/// Functions that never return to their caller, by name.
+const NORETURN_FUNCTIONS: &[&str] = &[
+    "OSPanic",
+    "OSFatal",
+    "PPCHalt",
+    "exit",
+    "_exit",
+    "abort",
+    "__assert",
+    "__msl_assertion_failed",
+];
+
+/// Whether a call to the function at `addr` never returns, from a `noreturn` hint or
+/// the function's name.
+pub fn is_noreturn(obj: &ObjInfo, addr: SectionAddress) -> bool {
+    obj.noreturn_functions.contains(&addr)
+        || obj
+            .symbols
+            .at_section_address(addr.section, addr.address)
+            .any(|(_, s)| NORETURN_FUNCTIONS.contains(&s.name.as_str()))
+}
+
 pub fn disassemble(section: &ObjSection, address: u32) -> Option<Ins> {
     read_u32(section, address).map(|code| Ins::new(code, address))
 }
@@ -36,11 +60,11 @@ fn read_unresolved_relocation_address(
     address: u32,
     reloc_kind: Option<ObjRelocKind>,
 ) -> Result<Option<RelocationTarget>> {
-    if let Some(reloc) = obj
-        .unresolved_relocations
-        .iter()
-        .find(|reloc| reloc.section as usize == section.elf_index && reloc.address == address)
-    {
+    let reloc = match u8::try_from(section.elf_index) {
+        Ok(elf_index) => obj.unresolved_relocations.at(elf_index, address)?,
+        Err(_) => None,
+    };
+    if let Some(reloc) = reloc {
         if reloc.module_id != obj.module_id {
             return Ok(Some(RelocationTarget::External));
         }
@@ -234,6 +258,19 @@ pub fn uniq_jump_table_entries(
     if !is_valid_jump_table_addr(obj, addr) {
         return Ok((BTreeSet::new(), 0));
     }
+    // Sizes from analysis hints take precedence, then those of jump tables already found
+    // by function analysis, so that relocation analysis agrees with it
+    let size = obj
+        .known_jump_tables
+        .get(&addr)
+        .and_then(|&size| NonZeroU32::new(size))
+        .or_else(|| {
+            obj.symbols
+                .at_section_address(addr.section, addr.address)
+                .find(|(_, s)| s.size_known && s.name.starts_with("jumptable_"))
+                .and_then(|(_, s)| NonZeroU32::new(s.size as u32))
+        })
+        .or(size);
     let (entries, size) =
         get_jump_table_entries(obj, addr, size, from, function_start, function_end)?;
     Ok((BTreeSet::from_iter(entries.iter().cloned()), size))
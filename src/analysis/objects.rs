@@ -2,9 +2,12 @@ use anyhow::Result;
 
 use crate::{
     obj::{ObjDataKind, ObjInfo, ObjSectionKind, ObjSymbolKind},
-    util::split::is_linker_generated_label,
+    util::{align_up, split::is_linker_generated_label},
 };
 
+/// Largest alignment inferred from the padding before a symbol.
+const MAX_INFERRED_ALIGN: u32 = 32;
+
 pub fn detect_objects(obj: &mut ObjInfo) -> Result<()> {
     for (section_index, section) in
         obj.sections.iter_mut().filter(|(_, s)| s.kind != ObjSectionKind::Code)
@@ -161,3 +164,57 @@ pub fn detect_strings(obj: &mut ObjInfo) -> Result<()> {
     }
     Ok(())
 }
+
+/// Infers the alignment of data symbols from their access width and the padding before them,
+/// for symbols that need more than the default 4 bytes.
+pub fn infer_alignment(obj: &mut ObjInfo) -> Result<()> {
+    let mut symbols_set = Vec::<(usize, u32)>::new();
+    for (section_index, section) in
+        obj.sections.iter().filter(|(_, s)| s.kind != ObjSectionKind::Code)
+    {
+        let mut prev_end = None::<u32>;
+        for (symbol_idx, symbol) in obj.symbols.for_section(section_index) {
+            let address = symbol.address as u32;
+            let end = address + symbol.size as u32;
+            let gap_start = prev_end;
+            prev_end = Some(prev_end.map_or(end, |e| e.max(end)));
+            if symbol.align.is_some()
+                || symbol.kind == ObjSymbolKind::Function
+                || !symbol.size_known
+                || is_linker_generated_label(&symbol.name)
+            {
+                continue;
+            }
+            let access_align = match symbol.data_kind {
+                ObjDataKind::Byte8 | ObjDataKind::Double => 8,
+                _ => 0,
+            };
+            // Padding explained by alignment, unless it's the start of a split
+            let padding_align = match gap_start {
+                Some(gap_start) if gap_start < address && !section.splits.has_split_at(address) => {
+                    let is_padding = section.kind == ObjSectionKind::Bss
+                        || section
+                            .data_range(gap_start, address)
+                            .is_ok_and(|data| data.iter().all(|&b| b == 0));
+                    (1..=MAX_INFERRED_ALIGN.trailing_zeros())
+                        .map(|shift| 1 << shift)
+                        .find(|&align| align_up(gap_start, align) == address)
+                        .filter(|_| is_padding)
+                        .unwrap_or(0)
+                }
+                _ => 0,
+            };
+            let align = access_align.max(padding_align);
+            if align > 4 && address % align == 0 {
+                log::debug!("Inferred {} alignment {}", symbol.name, align);
+                symbols_set.push((symbol_idx, align));
+            }
+        }
+    }
+    for (symbol_idx, align) in symbols_set {
+        let mut symbol = obj.symbols[symbol_idx].clone();
+        symbol.align = Some(align);
+        obj.symbols.replace(symbol_idx, symbol)?;
+    }
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use anyhow::{bail, ensure, Result};
 use flagset::FlagSet;
 use itertools::Itertools;
@@ -19,42 +21,56 @@ pub struct FindTRKInterruptVectorTable {}
 
 pub const TRK_TABLE_HEADER: &str = "Metrowerks Target Resident Kernel for PowerPC";
 pub const TRK_TABLE_SIZE: u32 = 0x1F34; // always?
+/// Each exception vector's handler starts at a multiple of this offset into the table.
+pub const TRK_VECTOR_SIZE: u32 = 0x100;
 
 // TRK_MINNOW_DOLPHIN.a __exception.s
+// The table starts with the header string, followed by a handler for each exception vector.
+// The handlers are copied to the vectors at runtime, so the header is marked as data, and
+// the handlers as vector code. Must run before function detection.
 impl AnalysisPass for FindTRKInterruptVectorTable {
     fn execute(state: &mut AnalyzerState, obj: &ObjInfo) -> Result<()> {
-        for (&start, _) in
-            state.functions.iter().filter(|(_, info)| info.analyzed && info.end.is_none())
-        {
-            let section = &obj.sections[start.section];
-            let data = match section.data_range(start.address, 0) {
-                Ok(ret) => ret,
-                Err(_) => continue,
+        let mut needle = TRK_TABLE_HEADER.as_bytes().to_vec();
+        needle.push(0);
+        for (section_index, section) in obj.sections.by_kind(ObjSectionKind::Code) {
+            let Some(pos) = memmem::find(&section.data, &needle) else {
+                continue;
             };
-            if data.starts_with(TRK_TABLE_HEADER.as_bytes())
-                && data[TRK_TABLE_HEADER.as_bytes().len()] == 0
-            {
-                log::debug!("Found gTRKInterruptVectorTable @ {:#010X}", start);
-                state.known_symbols.insert(start, ObjSymbol {
-                    name: "gTRKInterruptVectorTable".to_string(),
-                    address: start.address as u64,
-                    section: Some(start.section),
-                    size_known: true,
-                    flags: ObjSymbolFlagSet(FlagSet::from(ObjSymbolFlags::Global)),
-                    ..Default::default()
-                });
-                let end = start + TRK_TABLE_SIZE;
-                state.known_symbols.insert(end, ObjSymbol {
-                    name: "gTRKInterruptVectorTableEnd".to_string(),
-                    address: end.address as u64,
-                    section: Some(start.section),
-                    size_known: true,
-                    flags: ObjSymbolFlagSet(FlagSet::from(ObjSymbolFlags::Global)),
-                    ..Default::default()
-                });
+            let start = SectionAddress::new(section_index, section.address as u32 + pos as u32);
+            let end = start + TRK_TABLE_SIZE;
+            if !section.contains_range(start.address..end.address) {
+                log::debug!("Ignoring truncated gTRKInterruptVectorTable @ {:#010X}", start);
+                continue;
+            }
+            log::debug!("Found gTRKInterruptVectorTable @ {:#010X}", start);
+            state.known_symbols.insert(start, ObjSymbol {
+                name: "gTRKInterruptVectorTable".into(),
+                address: start.address as u64,
+                section: Some(start.section),
+                size_known: true,
+                flags: ObjSymbolFlagSet(FlagSet::from(ObjSymbolFlags::Global)),
+                ..Default::default()
+            });
+            state.known_symbols.insert(end, ObjSymbol {
+                name: "gTRKInterruptVectorTableEnd".into(),
+                address: end.address as u64,
+                section: Some(start.section),
+                size_known: true,
+                flags: ObjSymbolFlagSet(FlagSet::from(ObjSymbolFlags::Global)),
+                ..Default::default()
+            });
 
-                return Ok(());
+            state.data_ranges.insert(start, (start + TRK_VECTOR_SIZE).address);
+            state.vector_ranges.insert(start, end.address);
+            let mut vector = start + TRK_VECTOR_SIZE;
+            while vector < end {
+                // Unused vectors are zeroed
+                if section.data_range(vector.address, vector.address + 4)? != [0; 4] {
+                    state.functions.entry(vector).or_default();
+                }
+                vector += TRK_VECTOR_SIZE;
             }
+            return Ok(());
         }
         log::debug!("gTRKInterruptVectorTable not found");
         Ok(())
@@ -87,7 +103,7 @@ impl AnalysisPass for FindSaveRestSleds {
                     slices: None,
                 });
                 state.known_symbols.insert(start, ObjSymbol {
-                    name: func.to_string(),
+                    name: (*func).into(),
                     address: start.address as u64,
                     section: Some(start.section),
                     size: SLED_SIZE as u64,
@@ -99,7 +115,7 @@ impl AnalysisPass for FindSaveRestSleds {
                 for i in 14..=31 {
                     let addr = start + (i - 14) * 4;
                     state.known_symbols.insert(addr, ObjSymbol {
-                        name: format!("{}{}", label, i),
+                        name: format!("{}{}", label, i).into(),
                         address: addr.address as u64,
                         section: Some(start.section),
                         size_known: true,
@@ -126,6 +142,15 @@ impl AnalysisPass for FindRelCtorsDtors {
             _ => bail!("Only one of .ctors and .dtors has been found?"),
         }
 
+        // Absolute relocations within the module, by section and address
+        let mut relocations = BTreeMap::new();
+        for result in obj.unresolved_relocations.iter() {
+            let reloc = result?;
+            if reloc.module_id == obj.module_id && reloc.kind == ObjRelocKind::Absolute {
+                relocations.insert((reloc.section, reloc.address), reloc);
+            }
+        }
+
         let possible_sections = obj
             .sections
             .iter()
@@ -142,12 +167,8 @@ impl AnalysisPass for FindRelCtorsDtors {
                 let section_end = current_address + section.size as u32;
                 // Check that each word has a relocation to a function
                 // And the section ends with a null pointer
-                while let Some(reloc) = obj.unresolved_relocations.iter().find(|reloc| {
-                    reloc.module_id == obj.module_id
-                        && reloc.section == section.elf_index as u8
-                        && reloc.address == current_address
-                        && reloc.kind == ObjRelocKind::Absolute
-                }) {
+                while let Some(reloc) = relocations.get(&(section.elf_index as u8, current_address))
+                {
                     let Some((target_section_index, target_section)) = obj
                         .sections
                         .iter()
@@ -187,7 +208,7 @@ impl AnalysisPass for FindRelCtorsDtors {
         let ctors_section_index = possible_sections[0].0;
         state.known_sections.insert(ctors_section_index, ".ctors".to_string());
         state.known_symbols.insert(SectionAddress::new(ctors_section_index, 0), ObjSymbol {
-            name: "_ctors".to_string(),
+            name: "_ctors".into(),
             section: Some(ctors_section_index),
             size_known: true,
             flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
@@ -197,7 +218,7 @@ impl AnalysisPass for FindRelCtorsDtors {
         let dtors_section_index = possible_sections[1].0;
         state.known_sections.insert(dtors_section_index, ".dtors".to_string());
         state.known_symbols.insert(SectionAddress::new(dtors_section_index, 0), ObjSymbol {
-            name: "_dtors".to_string(),
+            name: "_dtors".into(),
             section: Some(dtors_section_index),
             size_known: true,
             flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
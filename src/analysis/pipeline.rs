@@ -0,0 +1,234 @@
+use std::marker::PhantomData;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    analysis::{
+        cfa::AnalyzerState,
+        pass::{
+            AnalysisPass, FindRelCtorsDtors, FindRelRodataData, FindSaveRestSleds,
+            FindTRKInterruptVectorTable,
+        },
+        signatures::{apply_signatures, apply_signatures_post},
+    },
+    obj::{ObjInfo, ObjKind},
+    util::timings::Timings,
+};
+
+/// Point in the analysis at which a pass runs.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PassStage {
+    /// Before function boundary detection. Passes can seed `state.functions`
+    /// or `state.known_symbols` to guide the analyzer.
+    BeforeFunctions,
+    /// After function boundary detection, before the analyzer state is applied
+    /// to the object.
+    AfterFunctions,
+}
+
+/// A pass of a [`Pipeline`]. Implementing it allows game-specific analysis (engine tables,
+/// unusual jump table layouts, etc.) to be added from crates depending on `decomp_toolkit`.
+///
+/// Unlike [`AnalysisPass`], the pass is an object and can carry its own configuration.
+pub trait PipelinePass: Send + Sync {
+    /// Name used in log messages, errors and timings.
+    fn name(&self) -> &'static str;
+
+    fn stage(&self) -> PassStage { PassStage::AfterFunctions }
+
+    /// Whether the pass should run for this object. Defaults to all objects.
+    fn applies_to(&self, _obj: &ObjInfo) -> bool { true }
+
+    fn execute(&self, state: &mut AnalyzerState, obj: &ObjInfo) -> Result<()>;
+}
+
+/// A built-in [`AnalysisPass`] for one kind of object.
+struct BuiltinPass<P> {
+    name: &'static str,
+    stage: PassStage,
+    kind: ObjKind,
+    _pass: PhantomData<fn() -> P>,
+}
+
+impl<P> PipelinePass for BuiltinPass<P>
+where P: AnalysisPass
+{
+    fn name(&self) -> &'static str { self.name }
+
+    fn stage(&self) -> PassStage { self.stage }
+
+    fn applies_to(&self, obj: &ObjInfo) -> bool { obj.kind == self.kind }
+
+    fn execute(&self, state: &mut AnalyzerState, obj: &ObjInfo) -> Result<()> {
+        P::execute(state, obj)
+    }
+}
+
+/// The passes run around function boundary detection, in order.
+///
+/// [`Pipeline::default`] has the built-in passes. Passes added with [`Pipeline::add`] run
+/// after the built-in passes of the same stage.
+pub struct Pipeline {
+    passes: Vec<Box<dyn PipelinePass>>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        let mut pipeline = Self::empty();
+        pipeline.add_builtin::<FindSaveRestSleds>(
+            "save/restore sleds",
+            PassStage::BeforeFunctions,
+            ObjKind::Executable,
+        );
+        pipeline.add_builtin::<FindTRKInterruptVectorTable>(
+            "TRK vector table",
+            PassStage::BeforeFunctions,
+            ObjKind::Executable,
+        );
+        pipeline.add_builtin::<FindRelCtorsDtors>(
+            "ctors/dtors",
+            PassStage::AfterFunctions,
+            ObjKind::Relocatable,
+        );
+        pipeline.add_builtin::<FindRelRodataData>(
+            "rodata/data",
+            PassStage::AfterFunctions,
+            ObjKind::Relocatable,
+        );
+        pipeline
+    }
+}
+
+impl Pipeline {
+    /// A pipeline without any passes, not even the built-in ones.
+    pub fn empty() -> Self { Self { passes: vec![] } }
+
+    /// Adds a pass, to run after the passes of its stage added before it.
+    pub fn add<P>(&mut self, pass: P) -> &mut Self
+    where P: PipelinePass + 'static {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    fn add_builtin<P>(&mut self, name: &'static str, stage: PassStage, kind: ObjKind)
+    where P: AnalysisPass + 'static {
+        self.add(BuiltinPass::<P> { name, stage, kind, _pass: PhantomData });
+    }
+
+    /// Runs the passes of `stage` that apply to `obj`.
+    pub fn run_stage(
+        &self,
+        stage: PassStage,
+        state: &mut AnalyzerState,
+        obj: &ObjInfo,
+        timings: &mut Timings,
+    ) -> Result<()> {
+        for pass in self.passes.iter().filter(|p| p.stage() == stage && p.applies_to(obj)) {
+            log::debug!("Running analysis pass {}", pass.name());
+            pass.execute(state, obj)
+                .with_context(|| format!("Analysis pass '{}' failed", pass.name()))?;
+            timings.record(pass.name());
+        }
+        Ok(())
+    }
+
+    /// Detects function boundaries, running the passes before and after, and applies the
+    /// result to `obj`.
+    pub fn detect_functions(&self, obj: &mut ObjInfo, timings: &mut Timings) -> Result<()> {
+        let mut state = AnalyzerState::default();
+        self.run_stage(PassStage::BeforeFunctions, &mut state, obj, timings)?;
+        state.detect_functions(obj)?;
+        log::debug!(
+            "Discovered {} functions",
+            state.functions.iter().filter(|(_, i)| i.end.is_some()).count()
+        );
+        timings.record("functions");
+        self.run_stage(PassStage::AfterFunctions, &mut state, obj, timings)?;
+        state.apply(obj)?;
+        timings.record("functions");
+        Ok(())
+    }
+
+    /// Analyzes `obj` as `dol split` does: signatures and, unless `quick`, function boundaries.
+    /// Executables are searched for signatures first, to guide function detection. Modules are
+    /// searched afterwards, once the REL passes have run.
+    pub fn analyze(&self, obj: &mut ObjInfo, quick: bool, timings: &mut Timings) -> Result<()> {
+        let executable = obj.kind == ObjKind::Executable;
+        if executable {
+            apply_signatures(obj)?;
+            timings.record("signatures");
+        }
+        if !quick {
+            self.detect_functions(obj, timings)?;
+        }
+        if !executable {
+            apply_signatures(obj)?;
+        }
+        apply_signatures_post(obj)?;
+        timings.record("signatures");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::obj::ObjArchitecture;
+
+    struct RecordPass {
+        name: &'static str,
+        stage: PassStage,
+        log: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl PipelinePass for RecordPass {
+        fn name(&self) -> &'static str { self.name }
+
+        fn stage(&self) -> PassStage { self.stage }
+
+        fn execute(&self, _state: &mut AnalyzerState, _obj: &ObjInfo) -> Result<()> {
+            self.log.lock().unwrap().push(self.name);
+            Ok(())
+        }
+    }
+
+    fn obj(kind: ObjKind) -> ObjInfo {
+        ObjInfo::new(kind, ObjArchitecture::PowerPc, "test".to_string(), vec![], vec![])
+    }
+
+    #[test]
+    fn builtin_passes_by_kind() {
+        let pipeline = Pipeline::default();
+        let names = |kind| {
+            let obj = obj(kind);
+            pipeline.passes.iter().filter(|p| p.applies_to(&obj)).map(|p| p.name()).collect()
+        };
+        let executable: Vec<_> = names(ObjKind::Executable);
+        assert_eq!(executable, ["save/restore sleds", "TRK vector table"]);
+        let relocatable: Vec<_> = names(ObjKind::Relocatable);
+        assert_eq!(relocatable, ["ctors/dtors", "rodata/data"]);
+    }
+
+    #[test]
+    fn passes_run_by_stage_in_order() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut pipeline = Pipeline::empty();
+        for (name, stage) in [
+            ("after", PassStage::AfterFunctions),
+            ("before 1", PassStage::BeforeFunctions),
+            ("before 2", PassStage::BeforeFunctions),
+        ] {
+            pipeline.add(RecordPass { name, stage, log: log.clone() });
+        }
+        let obj = obj(ObjKind::Executable);
+        let mut state = AnalyzerState::default();
+        let mut timings = Timings::new();
+        pipeline.run_stage(PassStage::BeforeFunctions, &mut state, &obj, &mut timings).unwrap();
+        assert_eq!(*log.lock().unwrap(), ["before 1", "before 2"]);
+        pipeline.run_stage(PassStage::AfterFunctions, &mut state, &obj, &mut timings).unwrap();
+        assert_eq!(*log.lock().unwrap(), ["before 1", "before 2", "after"]);
+        assert_eq!(timings.stages.len(), 3);
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use anyhow::{anyhow, Result};
 use itertools::Itertools;
 
@@ -8,9 +10,15 @@ use crate::{
     },
     obj::{
         ObjInfo, ObjSectionKind, ObjSplit, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags,
-        ObjSymbolKind,
+        ObjSymbolKind, ObjSymbolSource,
+    },
+    util::{
+        config::is_auto_symbol,
+        signatures::{
+            apply_signature, check_signature, check_signatures, check_signatures_str,
+            parse_signatures, FunctionSignature, SignatureSet,
+        },
     },
-    util::signatures::{apply_signature, check_signatures, check_signatures_str, parse_signatures},
 };
 
 const SIGNATURES: &[(&str, &str)] = &[
@@ -203,6 +211,11 @@ const POST_SIGNATURES: &[(&str, &str)] = &[
     ),
 ];
 
+/// Whether a symbol name is one that the built-in signatures can identify.
+pub fn is_signature_symbol(name: &str) -> bool {
+    SIGNATURES.iter().chain(POST_SIGNATURES).any(|&(n, _)| n == name)
+}
+
 fn apply_signature_for_symbol(obj: &mut ObjInfo, name: &str, sig_str: &str) -> Result<()> {
     for symbol_idx in obj.symbols.for_name(name).map(|(i, _)| i).collect_vec() {
         let symbol = &obj.symbols[symbol_idx];
@@ -245,13 +258,14 @@ fn apply_ctors_signatures(obj: &mut ObjInfo) -> Result<()> {
     apply_signature(obj, target, &signature)?;
     obj.symbols.add(
         ObjSymbol {
-            name: "__init_cpp_exceptions_reference".to_string(),
+            name: "__init_cpp_exceptions_reference".into(),
             address,
             section: Some(ctors_section_index),
             size: 4,
             size_known: true,
             flags: ObjSymbolFlagSet(ObjSymbolFlags::Global | ObjSymbolFlags::RelocationIgnore),
             kind: ObjSymbolKind::Object,
+            source: ObjSymbolSource::Signature,
             ..Default::default()
         },
         true,
@@ -265,6 +279,8 @@ fn apply_ctors_signatures(obj: &mut ObjInfo) -> Result<()> {
             autogenerated: true,
             skip: false,
             rename: None,
+            pad: None,
+            end_align: None,
         })?;
     }
     Ok(())
@@ -301,7 +317,7 @@ fn apply_dtors_signatures(obj: &mut ObjInfo) -> Result<()> {
             apply_signature(obj, dgc_target, &signature)?;
             obj.add_symbol(
                 ObjSymbol {
-                    name: "__destroy_global_chain_reference".to_string(),
+                    name: "__destroy_global_chain_reference".into(),
                     address,
                     section: Some(dtors_section_index),
                     size: 4,
@@ -310,6 +326,7 @@ fn apply_dtors_signatures(obj: &mut ObjInfo) -> Result<()> {
                         ObjSymbolFlags::Global | ObjSymbolFlags::RelocationIgnore,
                     ),
                     kind: ObjSymbolKind::Object,
+                    source: ObjSymbolSource::Signature,
                     ..Default::default()
                 },
                 true,
@@ -330,7 +347,7 @@ fn apply_dtors_signatures(obj: &mut ObjInfo) -> Result<()> {
             apply_signature(obj, fce_target, &signature)?;
             obj.add_symbol(
                 ObjSymbol {
-                    name: "__fini_cpp_exceptions_reference".to_string(),
+                    name: "__fini_cpp_exceptions_reference".into(),
                     address: address + 4,
                     section: Some(dtors_section_index),
                     size: 4,
@@ -339,6 +356,7 @@ fn apply_dtors_signatures(obj: &mut ObjInfo) -> Result<()> {
                         ObjSymbolFlags::Global | ObjSymbolFlags::RelocationIgnore,
                     ),
                     kind: ObjSymbolKind::Object,
+                    source: ObjSymbolSource::Signature,
                     ..Default::default()
                 },
                 true,
@@ -361,6 +379,8 @@ fn apply_dtors_signatures(obj: &mut ObjInfo) -> Result<()> {
                 autogenerated: true,
                 skip: false,
                 rename: None,
+                pad: None,
+                end_align: None,
             })?;
         }
     }
@@ -441,12 +461,65 @@ pub fn apply_signatures_post(obj: &mut ObjInfo) -> Result<()> {
     Ok(())
 }
 
+/// Names unnamed functions matching a signature set from a
+/// [`SignatureStore`](crate::util::sigstore::SignatureStore). Runs after function detection,
+/// since the set's functions aren't reached by name. Signatures matching several functions
+/// are skipped. Returns the number of functions named.
+pub fn apply_signature_set(obj: &mut ObjInfo, set: &SignatureSet) -> Result<usize> {
+    // Candidates by function size
+    let mut by_size = BTreeMap::<u32, Vec<(&str, &FunctionSignature)>>::new();
+    for (name, signatures) in set {
+        for signature in signatures {
+            let size = signature.symbols[signature.symbol].size;
+            if size > 0 {
+                by_size.entry(size).or_default().push((name.as_str(), signature));
+            }
+        }
+    }
+
+    let mut found = BTreeMap::<&str, Vec<(SectionAddress, &FunctionSignature)>>::new();
+    for (section_index, section) in obj.sections.by_kind(ObjSectionKind::Code) {
+        for (_, symbol) in obj.symbols.for_section(section_index) {
+            if symbol.kind != ObjSymbolKind::Function || !is_auto_symbol(symbol) {
+                continue;
+            }
+            let Some(candidates) = by_size.get(&(symbol.size as u32)) else {
+                continue;
+            };
+            let data = section.data_range(symbol.address as u32, 0)?;
+            for &(name, signature) in candidates {
+                if check_signature(data, signature)? {
+                    let addr = SectionAddress::new(section_index, symbol.address as u32);
+                    found.entry(name).or_default().push((addr, signature));
+                    break;
+                }
+            }
+        }
+    }
+
+    let mut applied = 0;
+    for (name, matches) in found {
+        let [(addr, signature)] = matches.as_slice() else {
+            log::debug!("Skipping signature {} matching {} functions", name, matches.len());
+            continue;
+        };
+        if obj.symbols.by_name(name)?.is_some() {
+            continue;
+        }
+        match apply_signature(obj, *addr, signature) {
+            Ok(()) => applied += 1,
+            Err(e) => log::warn!("Failed to apply signature {} at {:#010X}: {:#}", name, addr, e),
+        }
+    }
+    Ok(applied)
+}
+
 /// Create _ctors and _dtors symbols if missing
 pub fn update_ctors_dtors(obj: &mut ObjInfo) -> Result<()> {
     if obj.symbols.by_name("_ctors")?.is_none() {
         if let Some((section_index, section)) = obj.sections.by_name(".ctors")? {
             obj.symbols.add_direct(ObjSymbol {
-                name: "_ctors".to_string(),
+                name: "_ctors".into(),
                 address: section.address,
                 section: Some(section_index),
                 size_known: true,
@@ -458,7 +531,7 @@ pub fn update_ctors_dtors(obj: &mut ObjInfo) -> Result<()> {
     if obj.symbols.by_name("_dtors")?.is_none() {
         if let Some((section_index, section)) = obj.sections.by_name(".dtors")? {
             obj.symbols.add_direct(ObjSymbol {
-                name: "_dtors".to_string(),
+                name: "_dtors".into(),
                 address: section.address,
                 section: Some(section_index),
                 size_known: true,
@@ -11,6 +11,8 @@ use crate::{
         cfa::{FunctionInfo, SectionAddress},
         disassemble,
         executor::{ExecCbData, ExecCbResult, Executor},
+        is_noreturn,
+        jump_table::{infer_jump_table_size, Cfg},
         uniq_jump_table_entries,
         vm::{section_address_for, BranchTarget, GprValue, StepResult, VM},
         RelocationTarget,
@@ -32,6 +34,8 @@ pub struct FunctionSlices {
     pub has_rfi: bool,
     pub finalized: bool,
     pub has_r1_load: bool, // Possibly instead of a prologue
+    /// Control flow graph for jump table bounds, as of the last jump table
+    pub jump_table_cfg: Option<Cfg>,
 }
 
 pub enum TailCallResult {
@@ -248,9 +252,7 @@ impl FunctionSlices {
             StepResult::Continue | StepResult::LoadStore { .. } => {
                 let next_address = ins_addr + 4;
                 // If we already visited the next address, connect the blocks and end
-                if executor.visited(section.address as u32, next_address)
-                    || self.blocks.contains_key(&next_address)
-                {
+                if executor.visited(next_address) || self.blocks.contains_key(&next_address) {
                     self.blocks.insert(block_start, Some(next_address));
                     self.branches.insert(ins_addr, vec![next_address]);
                     Ok(ExecCbResult::EndBlock)
@@ -283,6 +285,12 @@ impl FunctionSlices {
                 }
                 BranchTarget::Return => {
                     self.blocks.insert(block_start, Some(ins_addr + 4));
+                    // Analysis hint: the function continues after this blr
+                    if obj.non_terminal_returns.contains(&ins_addr)
+                        && self.add_block_start(ins_addr + 4)
+                    {
+                        return Ok(ExecCbResult::Jump(ins_addr + 4));
+                    }
                     Ok(ExecCbResult::EndBlock)
                 }
                 BranchTarget::Address(RelocationTarget::Address(addr)) => {
@@ -318,6 +326,11 @@ impl FunctionSlices {
                     let next_address = ins_addr + 4;
                     self.blocks.insert(block_start, Some(next_address));
 
+                    // Prefer bounds from the control flow graph over the VM's path
+                    let mut cfg = self.jump_table_cfg.take();
+                    let size = infer_jump_table_size(obj, self, &mut cfg, function_start, ins_addr)
+                        .or(size);
+                    self.jump_table_cfg = cfg;
                     log::debug!("Fetching jump table entries @ {} with size {:?}", address, size);
                     let (entries, size) = uniq_jump_table_entries(
                         obj,
@@ -356,9 +369,21 @@ impl FunctionSlices {
                 // End of block
                 self.blocks.insert(block_start, Some(ins_addr + 4));
 
+                // An unconditional call to a noreturn function doesn't fall through
+                let noreturn = ins.op == Opcode::B
+                    && branches.iter().any(|branch| match branch.target {
+                        BranchTarget::Address(RelocationTarget::Address(addr)) => {
+                            branch.link && is_noreturn(obj, addr)
+                        }
+                        _ => false,
+                    });
+
                 let mut out_branches = vec![];
                 for branch in branches {
                     match branch.target {
+                        BranchTarget::Address(_) if noreturn && !branch.link => {
+                            log::trace!("Call to noreturn function @ {:#010X}", ins_addr);
+                        }
                         BranchTarget::Address(RelocationTarget::Address(addr)) => {
                             let known = self.is_known_function(known_functions, addr);
                             if let Some(fn_addr) = known {
@@ -408,7 +433,7 @@ impl FunctionSlices {
             return Ok(true);
         }
 
-        let mut executor = Executor::new(obj);
+        let mut executor = Executor::new();
         executor.push(start, vm.unwrap_or_else(|| VM::new_from_obj(obj)), false);
         let result = executor.run(obj, |data| {
             self.instruction_callback(data, obj, function_start, function_end, known_functions)
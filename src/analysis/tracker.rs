@@ -17,8 +17,8 @@ use crate::{
         RelocationTarget,
     },
     obj::{
-        ObjDataKind, ObjInfo, ObjKind, ObjReloc, ObjRelocKind, ObjSection, ObjSectionKind,
-        ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind,
+        AddressSpace, ObjDataKind, ObjInfo, ObjKind, ObjReloc, ObjRelocKind, ObjSection,
+        ObjSectionKind, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind,
     },
 };
 
@@ -63,6 +63,20 @@ pub enum DataKind {
     // String16,
 }
 
+/// OS code that's copied to the exception vectors at runtime.
+const OS_VECTOR_FUNCTIONS: [&str; 3] = ["OSExceptionVector", "__OSDBIntegrator", "__OSDBJump"];
+
+/// Whether a function is exception handler code, which runs with arbitrary register state.
+fn is_vector_code(obj: &ObjInfo, addr: SectionAddress, name: &str) -> bool {
+    if OS_VECTOR_FUNCTIONS.contains(&name) {
+        return true;
+    }
+    matches!(
+        obj.vector_ranges.range(..=addr).next_back(),
+        Some((&start, &end)) if start.section == addr.section && addr.address < end
+    )
+}
+
 pub struct Tracker {
     processed_functions: BTreeSet<SectionAddress>,
     sda2_base: Option<u32>, // r2
@@ -454,8 +468,14 @@ impl Tracker {
         // but we still want to track them.
         let mut possible_missed_branches = BTreeMap::new();
 
-        let mut executor = Executor::new(obj);
-        executor.push(function_start, VM::new_with_base(self.sda2_base, self.sda_base), false);
+        let vm = if is_vector_code(obj, function_start, &symbol.name) {
+            // Runs with whatever the interrupted code left in r2 and r13
+            VM::new()
+        } else {
+            VM::new_with_base(self.sda2_base, self.sda_base)
+        };
+        let mut executor = Executor::new();
+        executor.push(function_start, vm, false);
         loop {
             executor.run(obj, |data| -> Result<ExecCbResult<()>> {
                 self.instruction_callback(
@@ -472,8 +492,7 @@ impl Tracker {
             }
             let mut added = false;
             for (addr, vm) in take(&mut possible_missed_branches) {
-                let section = &obj.sections[addr.section];
-                if !executor.visited(section.address as u32, addr) {
+                if !executor.visited(addr) {
                     executor.push(addr, vm, true);
                     added = true;
                 }
@@ -538,6 +557,13 @@ impl Tracker {
             return None;
         }
         if self.known_relocations.contains(&from) {
+            if !obj.is_valid_memory(addr) {
+                log::warn!(
+                    "Known relocation at {} targets {:#010X}, outside of the memory ranges",
+                    from,
+                    addr
+                );
+            }
             let section_index =
                 obj.sections.at_address(addr).ok().map(|(idx, _)| idx).unwrap_or(usize::MAX);
             return Some(SectionAddress::new(section_index, addr));
@@ -550,6 +576,14 @@ impl Tracker {
             || self.sda2_base == Some(addr)
             || self.sda_base == Some(addr)
         {
+            if !obj.is_valid_memory(addr) {
+                log::warn!(
+                    "Ignoring pointer to {:#010X} at {}: outside of the memory ranges",
+                    addr,
+                    from
+                );
+                return None;
+            }
             let section_index =
                 obj.sections.at_address(addr).ok().map(|(idx, _)| idx).unwrap_or(usize::MAX);
             return Some(SectionAddress::new(section_index, addr));
@@ -557,9 +591,21 @@ impl Tracker {
         // if addr > 0x80000000 && addr < 0x80003100 {
         //     return true;
         // }
+        // Hardware registers and uncached mirrors are never relocations
+        if AddressSpace::of(addr) == AddressSpace::Hardware || AddressSpace::is_uncached(addr) {
+            return None;
+        }
         if let Ok((section_index, section)) = obj.sections.at_address(addr) {
             // References to code sections will never be unaligned
             if section.kind != ObjSectionKind::Code || addr & 3 == 0 {
+                if !obj.is_valid_memory(addr) {
+                    log::warn!(
+                        "Ignoring pointer to {:#010X} at {}: outside of the memory ranges",
+                        addr,
+                        from
+                    );
+                    return None;
+                }
                 return Some(SectionAddress::new(section_index, addr));
             }
         }
@@ -714,7 +760,7 @@ impl Tracker {
                     )
                 };
                 let symbol_idx = obj.symbols.add_direct(ObjSymbol {
-                    name,
+                    name: name.into(),
                     address: target.address as u64,
                     section: Some(target.section),
                     data_kind,
@@ -809,7 +855,7 @@ fn data_kind_from_op(op: Opcode) -> DataKind {
 fn generate_special_symbol(obj: &mut ObjInfo, addr: u32, name: &str) -> Result<usize> {
     obj.add_symbol(
         ObjSymbol {
-            name: name.to_string(),
+            name: name.into(),
             address: addr as u64,
             size: 0,
             size_known: true,
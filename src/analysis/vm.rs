@@ -3,8 +3,8 @@ use std::num::NonZeroU32;
 use ppc750cl::{Argument, Ins, Opcode, GPR};
 
 use crate::{
-    analysis::{cfa::SectionAddress, relocation_target_for, RelocationTarget},
-    obj::{ObjInfo, ObjKind},
+    analysis::{cfa::SectionAddress, read_u32, relocation_target_for, RelocationTarget},
+    obj::{ObjInfo, ObjKind, ObjRelocKind, ObjSectionKind},
 };
 
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
@@ -22,6 +22,8 @@ pub enum GprValue {
     Range { min: u32, max: u32, step: u32 },
     /// GPR value is loaded from an address with a max offset (jump table)
     LoadIndexed { address: RelocationTarget, max_offset: Option<NonZeroU32> },
+    /// GPR value is a data address loaded from a pointer in a data section
+    LoadedAddress(RelocationTarget),
 }
 
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
@@ -56,7 +58,7 @@ impl Gpr {
     fn address(&self, obj: &ObjInfo, ins_addr: SectionAddress) -> Option<RelocationTarget> {
         match self.value {
             GprValue::Constant(value) => section_address_for(obj, ins_addr, value),
-            GprValue::Address(target) => Some(target),
+            GprValue::Address(target) | GprValue::LoadedAddress(target) => Some(target),
             _ => None,
         }
     }
@@ -124,6 +126,40 @@ pub enum StepResult {
     Branch(Vec<Branch>),
 }
 
+/// Follows a pointer stored in a data section to another data section. Some compilers
+/// place switch tables out of line and load the table address from a pointer.
+fn load_data_pointer(obj: &ObjInfo, address: SectionAddress) -> GprValue {
+    let is_data = |section_index: usize| {
+        matches!(
+            obj.sections.get(section_index).map(|s| s.kind),
+            Some(ObjSectionKind::Data | ObjSectionKind::ReadOnlyData)
+        )
+    };
+    if !is_data(address.section) {
+        return GprValue::Unknown;
+    }
+    let target = match relocation_target_for(obj, address, Some(ObjRelocKind::Absolute)) {
+        Ok(Some(RelocationTarget::Address(target))) => target,
+        // Without a relocation, only an aligned word holding an address in a known section
+        // is taken as a pointer, so that integer tables aren't followed
+        Ok(None) if obj.kind == ObjKind::Executable && address.address % 4 == 0 => {
+            let Some(value) = read_u32(&obj.sections[address.section], address.address) else {
+                return GprValue::Unknown;
+            };
+            let Ok((section_index, _)) = obj.sections.at_address(value) else {
+                return GprValue::Unknown;
+            };
+            SectionAddress::new(section_index, value)
+        }
+        _ => return GprValue::Unknown,
+    };
+    if is_data(target.section) {
+        GprValue::LoadedAddress(RelocationTarget::Address(target))
+    } else {
+        GprValue::Unknown
+    }
+}
+
 pub fn section_address_for(
     obj: &ObjInfo,
     ins_addr: SectionAddress,
@@ -549,7 +585,14 @@ impl VM {
                     self.gpr[source].set_direct(GprValue::Unknown);
                 }
                 if is_load_op(op) {
-                    self.gpr[ins.field_rD()].set_direct(GprValue::Unknown);
+                    let value = match &result {
+                        StepResult::LoadStore {
+                            address: RelocationTarget::Address(address),
+                            ..
+                        } if op == Opcode::Lwz => load_data_pointer(obj, *address),
+                        _ => GprValue::Unknown,
+                    };
+                    self.gpr[ins.field_rD()].set_direct(value);
                 }
                 return result;
             }
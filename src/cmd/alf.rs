@@ -7,10 +7,12 @@ use anyhow::Result;
 use argp::FromArgs;
 
 use crate::{
+    analysis::pipeline::Pipeline,
     cmd,
     util::{
         alf::AlfFile,
         file::{buf_writer, map_file},
+        output::{is_json, print_json},
         reader::{Endian, FromReader},
     },
 };
@@ -64,13 +66,26 @@ fn hashes(args: HashesArgs) -> Result<()> {
         let mut reader = file.as_reader();
         AlfFile::from_reader(&mut reader, Endian::Little)?
     };
+    let mut symbols = alf_file.symbols.clone();
+    symbols.sort_by_key(|s| s.address);
+    if args.output.is_none() && is_json() {
+        for symbol in symbols {
+            print_json(&serde_json::json!({
+                "address": symbol.address,
+                "section": symbol.section,
+                "kind": format!("{:?}", symbol.kind).to_ascii_lowercase(),
+                "name": symbol.name,
+                "demangled_name": symbol.demangled_name,
+                "size": symbol.size,
+            }))?;
+        }
+        return Ok(());
+    }
     let mut w: Box<dyn Write> = if let Some(output) = args.output {
         Box::new(buf_writer(output)?)
     } else {
         Box::new(stdout())
     };
-    let mut symbols = alf_file.symbols.clone();
-    symbols.sort_by_key(|s| s.address);
     for symbol in symbols {
         writeln!(
             w,
@@ -88,5 +103,5 @@ fn hashes(args: HashesArgs) -> Result<()> {
 }
 
 fn info(args: InfoArgs) -> Result<()> {
-    cmd::dol::info(cmd::dol::InfoArgs { dol_file: args.file, selfile: None })
+    cmd::dol::info(cmd::dol::InfoArgs { dol_file: args.file, selfile: None }, &Pipeline::default())
 }
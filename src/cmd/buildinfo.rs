@@ -0,0 +1,497 @@
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use argp::FromArgs;
+use memchr::memmem;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    obj::{ObjInfo, ObjSectionKind},
+    util::{
+        dol::process_dol,
+        file::{buf_reader, map_file},
+        output::{is_json, print_json},
+    },
+};
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Commands for build stamps embedded in a binary.
+#[argp(subcommand, name = "buildinfo")]
+pub struct Args {
+    #[argp(subcommand)]
+    command: SubCommand,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argp(subcommand)]
+enum SubCommand {
+    Extract(ExtractArgs),
+    Verify(VerifyArgs),
+    Set(SetArgs),
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Prints the build stamps found in a binary.
+#[argp(subcommand, name = "extract")]
+pub struct ExtractArgs {
+    #[argp(positional)]
+    /// binary file (.dol or any other file)
+    binary: PathBuf,
+    #[argp(option, short = 'c')]
+    /// build stamp config (YAML)
+    config: PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Checks the build stamps in a binary against their expected values.
+#[argp(subcommand, name = "verify")]
+pub struct VerifyArgs {
+    #[argp(positional)]
+    /// binary file (.dol or any other file)
+    binary: PathBuf,
+    #[argp(option, short = 'c')]
+    /// build stamp config (YAML)
+    config: PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Sets a build stamp in a binary.
+#[argp(subcommand, name = "set")]
+pub struct SetArgs {
+    #[argp(positional)]
+    /// binary file (.dol or any other file)
+    binary: PathBuf,
+    #[argp(positional)]
+    /// path to build info string
+    build_info: Option<PathBuf>,
+    #[argp(option, short = 's')]
+    /// build info string, instead of a file
+    string: Option<String>,
+    #[argp(switch)]
+    /// write the current date in the stamp's date_format, instead of a string
+    date: bool,
+    #[argp(option, short = 'c')]
+    /// build stamp config (YAML); defaults to the MetroidBuildInfo tag
+    config: Option<PathBuf>,
+    #[argp(option, short = 'n')]
+    /// name of the stamp to set (default: the first one)
+    name: Option<String>,
+    #[argp(switch)]
+    /// truncate strings longer than the stamp, instead of failing
+    truncate: bool,
+    #[argp(option, short = 'o')]
+    /// write the patched binary here, instead of modifying it in place
+    output: Option<PathBuf>,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Sets the MetroidBuildInfo tag value in a given binary (same as `buildinfo set`).
+#[argp(subcommand, name = "metroidbuildinfo")]
+pub struct MetroidArgs {
+    #[argp(positional)]
+    /// path to source binary
+    binary: PathBuf,
+    #[argp(positional)]
+    /// path to build info string
+    build_info: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BuildInfoConfig {
+    pub stamps: Vec<BuildStamp>,
+}
+
+/// A string embedded in a binary, located by one of `tag`, `address` or `offset`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BuildStamp {
+    pub name: String,
+    /// The stamp immediately follows this marker string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Virtual address of the stamp, for DOL files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<u32>,
+    /// File offset of the stamp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    #[serde(default)]
+    pub encoding: StampEncoding,
+    /// Maximum length of the stamp in bytes, not including the terminator.
+    /// When setting a stamp without one, the new string must fit in the current one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    /// Expected value, checked by `buildinfo verify`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    /// Byte that fills the rest of the stamp after a shorter string, before the terminator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pad: Option<u8>,
+    /// Format for `buildinfo set --date`, using `%Y`, `%y`, `%m`, `%b`, `%d`, `%e`, `%H`, `%M`
+    /// and `%S`. Defaults to the format of `__DATE__ " " __TIME__`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_format: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StampEncoding {
+    #[default]
+    Ascii,
+    Sjis,
+    /// Big endian UTF-16.
+    Utf16,
+}
+
+impl StampEncoding {
+    fn terminator(self) -> &'static [u8] {
+        match self {
+            StampEncoding::Ascii | StampEncoding::Sjis => &[0],
+            StampEncoding::Utf16 => &[0, 0],
+        }
+    }
+}
+
+/// The Metroid Prime build stamp, used by `buildinfo set` without a config.
+fn metroid_stamp() -> BuildStamp {
+    BuildStamp {
+        name: "MetroidBuildInfo".to_string(),
+        tag: Some("!#$MetroidBuildInfo!#$".to_string()),
+        address: None,
+        offset: None,
+        encoding: StampEncoding::Ascii,
+        max_length: Some(35),
+        expected: None,
+        pad: None,
+        date_format: None,
+    }
+}
+
+const DEFAULT_DATE_FORMAT: &str = "%b %e %Y %H:%M:%S";
+const MONTHS: [&str; 12] =
+    ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Formats a UNIX timestamp (UTC).
+fn format_date(format: &str, timestamp: u64) -> Result<String> {
+    let days = (timestamp / 86400) as i64;
+    let secs = timestamp % 86400;
+    // Civil date from days since the epoch
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&year.to_string()),
+            Some('y') => out.push_str(&format!("{:02}", year % 100)),
+            Some('m') => out.push_str(&format!("{:02}", month)),
+            Some('b') => out.push_str(MONTHS[month as usize - 1]),
+            Some('d') => out.push_str(&format!("{:02}", day)),
+            Some('e') => out.push_str(&format!("{:2}", day)),
+            Some('H') => out.push_str(&format!("{:02}", secs / 3600)),
+            Some('M') => out.push_str(&format!("{:02}", secs / 60 % 60)),
+            Some('S') => out.push_str(&format!("{:02}", secs % 60)),
+            Some('%') => out.push('%'),
+            Some(c) => bail!("Unknown date format specifier '%{c}'"),
+            None => bail!("Incomplete date format specifier"),
+        }
+    }
+    Ok(out)
+}
+
+/// The build time: `SOURCE_DATE_EPOCH` if set, for reproducible builds, or the current time.
+fn build_timestamp() -> Result<u64> {
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(value) => value.trim().parse().context("Invalid SOURCE_DATE_EPOCH"),
+        Err(_) => Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()),
+    }
+}
+
+#[derive(Serialize)]
+struct StampRecord<'a> {
+    name: &'a str,
+    offset: usize,
+    value: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    matches: Option<bool>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    match args.command {
+        SubCommand::Extract(c_args) => extract(c_args),
+        SubCommand::Verify(c_args) => verify(c_args),
+        SubCommand::Set(c_args) => set(c_args),
+    }
+}
+
+/// The former `metroidbuildinfo` command, kept for existing build scripts.
+pub fn run_metroid(args: MetroidArgs) -> Result<()> {
+    set(SetArgs {
+        binary: args.binary,
+        build_info: Some(args.build_info),
+        string: None,
+        date: false,
+        config: None,
+        name: None,
+        truncate: false,
+        output: None,
+    })
+}
+
+fn load_config(path: &Path) -> Result<BuildInfoConfig> {
+    let config: BuildInfoConfig = serde_yaml::from_reader(&mut buf_reader(path)?)
+        .with_context(|| format!("While loading '{}'", path.display()))?;
+    for stamp in &config.stamps {
+        let locations = [stamp.tag.is_some(), stamp.address.is_some(), stamp.offset.is_some()];
+        ensure!(
+            locations.iter().filter(|&&b| b).count() == 1,
+            "Stamp '{}' needs exactly one of tag, address or offset",
+            stamp.name
+        );
+    }
+    Ok(config)
+}
+
+fn load_dol(path: &Path, data: &[u8]) -> Result<Option<ObjInfo>> {
+    let extension = path.extension().and_then(OsStr::to_str).unwrap_or_default();
+    if !extension.eq_ignore_ascii_case("dol") {
+        return Ok(None);
+    }
+    Ok(Some(process_dol(data, "")?))
+}
+
+/// Finds the file offset of a stamp.
+fn locate(data: &[u8], dol: Option<&ObjInfo>, stamp: &BuildStamp) -> Result<usize> {
+    let offset = if let Some(tag) = &stamp.tag {
+        let idx = memmem::find(data, tag.as_bytes())
+            .ok_or_else(|| anyhow!("Failed to find tag '{}' for stamp '{}'", tag, stamp.name))?;
+        idx + tag.len()
+    } else if let Some(address) = stamp.address {
+        let dol = dol.ok_or_else(|| {
+            anyhow!("Stamp '{}' has an address, but the binary isn't a DOL", stamp.name)
+        })?;
+        let (_, section) = dol.sections.at_address(address)?;
+        ensure!(
+            section.kind != ObjSectionKind::Bss,
+            "Stamp '{}' address {:#010X} is in {}",
+            stamp.name,
+            address,
+            section.name
+        );
+        (section.file_offset + (address as u64 - section.address)) as usize
+    } else if let Some(offset) = stamp.offset {
+        offset as usize
+    } else {
+        bail!("Stamp '{}' needs one of tag, address or offset", stamp.name);
+    };
+    ensure!(offset < data.len(), "Stamp '{}' is past the end of the file", stamp.name);
+    Ok(offset)
+}
+
+/// Reads the stamp at `offset`, returning the string and its length in bytes.
+fn read_stamp(data: &[u8], offset: usize, stamp: &BuildStamp) -> Result<(String, usize)> {
+    let mut data = &data[offset..];
+    if let Some(max_length) = stamp.max_length {
+        data = &data[..data.len().min(max_length)];
+    }
+    match stamp.encoding {
+        StampEncoding::Ascii | StampEncoding::Sjis => {
+            let len = memchr::memchr(0, data).unwrap_or(data.len());
+            let bytes = &data[..len];
+            let string = if stamp.encoding == StampEncoding::Ascii {
+                ensure!(bytes.is_ascii(), "Stamp '{}' isn't ASCII", stamp.name);
+                String::from_utf8_lossy(bytes).into_owned()
+            } else {
+                encoding_rs::SHIFT_JIS
+                    .decode_without_bom_handling_and_without_replacement(bytes)
+                    .ok_or_else(|| anyhow!("Stamp '{}' isn't valid Shift-JIS", stamp.name))?
+                    .into_owned()
+            };
+            Ok((string, len))
+        }
+        StampEncoding::Utf16 => {
+            let units = data
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .take_while(|&c| c != 0)
+                .collect::<Vec<_>>();
+            let string = String::from_utf16(&units)
+                .map_err(|_| anyhow!("Stamp '{}' isn't valid UTF-16", stamp.name))?;
+            Ok((string, units.len() * 2))
+        }
+    }
+}
+
+fn encode_stamp(value: &str, stamp: &BuildStamp) -> Result<Vec<u8>> {
+    match stamp.encoding {
+        StampEncoding::Ascii => {
+            ensure!(value.is_ascii(), "Build string '{value}' isn't ASCII");
+            Ok(value.as_bytes().to_vec())
+        }
+        StampEncoding::Sjis => {
+            let (bytes, _, had_errors) = encoding_rs::SHIFT_JIS.encode(value);
+            ensure!(!had_errors, "Build string '{value}' can't be encoded as Shift-JIS");
+            Ok(bytes.into_owned())
+        }
+        StampEncoding::Utf16 => Ok(value.encode_utf16().flat_map(|c| c.to_be_bytes()).collect()),
+    }
+}
+
+fn print_stamp(
+    stamp: &BuildStamp,
+    offset: usize,
+    value: &str,
+    matches: Option<bool>,
+) -> Result<()> {
+    if is_json() {
+        print_json(&StampRecord {
+            name: &stamp.name,
+            offset,
+            value,
+            expected: stamp.expected.as_deref(),
+            matches,
+        })?;
+        return Ok(());
+    }
+    match (matches, &stamp.expected) {
+        (Some(false), Some(expected)) => {
+            println!("{}: {:?} (expected {:?})", stamp.name, value, expected)
+        }
+        (Some(true), _) => println!("{}: {:?} OK", stamp.name, value),
+        _ => println!("{}: {:?}", stamp.name, value),
+    }
+    Ok(())
+}
+
+fn extract(args: ExtractArgs) -> Result<()> {
+    let config = load_config(&args.config)?;
+    let file = map_file(&args.binary)?;
+    let data = file.as_slice();
+    let dol = load_dol(&args.binary, data)?;
+    for stamp in &config.stamps {
+        let offset = locate(data, dol.as_ref(), stamp)?;
+        let (value, _) = read_stamp(data, offset, stamp)?;
+        print_stamp(stamp, offset, &value, None)?;
+    }
+    Ok(())
+}
+
+fn verify(args: VerifyArgs) -> Result<()> {
+    let config = load_config(&args.config)?;
+    let file = map_file(&args.binary)?;
+    let data = file.as_slice();
+    let dol = load_dol(&args.binary, data)?;
+    let mut mismatches = 0;
+    for stamp in &config.stamps {
+        let offset = locate(data, dol.as_ref(), stamp)?;
+        let (value, _) = read_stamp(data, offset, stamp)?;
+        let matches = stamp.expected.as_ref().map(|expected| *expected == value);
+        if matches == Some(false) {
+            mismatches += 1;
+        }
+        print_stamp(stamp, offset, &value, matches)?;
+    }
+    ensure!(mismatches == 0, "{} build stamp(s) didn't match", mismatches);
+    Ok(())
+}
+
+fn set(args: SetArgs) -> Result<()> {
+    let stamp = match &args.config {
+        Some(path) => {
+            let config = load_config(path)?;
+            match &args.name {
+                Some(name) => config.stamps.into_iter().find(|s| s.name == *name),
+                None => config.stamps.into_iter().next(),
+            }
+            .ok_or_else(|| anyhow!("Stamp not found in '{}'", path.display()))?
+        }
+        None => metroid_stamp(),
+    };
+    let build_string = match (&args.build_info, &args.string, args.date) {
+        (Some(path), None, false) => {
+            let string = fs::read_to_string(path).with_context(|| {
+                format!("Failed to read build info string from '{}'", path.display())
+            })?;
+            string.trim_end().to_string()
+        }
+        (None, Some(string), false) => string.clone(),
+        (None, None, true) => format_date(
+            stamp.date_format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT),
+            build_timestamp()?,
+        )?,
+        _ => bail!("Expected one of a build info file, -s or --date"),
+    };
+
+    let mut data = fs::read(&args.binary)
+        .with_context(|| format!("Failed to read binary '{}'", args.binary.display()))?;
+    let dol = load_dol(&args.binary, &data)?;
+    let start = locate(&data, dol.as_ref(), &stamp)?;
+    let max_length = match stamp.max_length {
+        Some(max_length) => max_length,
+        None => read_stamp(&data, start, &stamp)?.1,
+    };
+    let mut value = build_string.clone();
+    let mut bytes = encode_stamp(&value, &stamp)?;
+    if bytes.len() > max_length {
+        ensure!(
+            args.truncate,
+            "Build string '{build_string}' is greater than maximum size of {max_length}"
+        );
+        while bytes.len() > max_length {
+            value.pop();
+            bytes = encode_stamp(&value, &stamp)?;
+        }
+        warn!("Truncated build string '{}' to '{}'", build_string, value);
+    }
+
+    // Pad to the full length, so no trailing characters of a longer string are left
+    let pad_length =
+        if stamp.encoding == StampEncoding::Utf16 { max_length & !1 } else { max_length };
+    bytes.resize(pad_length.max(bytes.len()), stamp.pad.unwrap_or(0));
+    bytes.extend_from_slice(stamp.encoding.terminator());
+    let end = start + bytes.len();
+    ensure!(end <= data.len(), "Stamp '{}' is past the end of the file", stamp.name);
+    data[start..end].copy_from_slice(&bytes);
+
+    let out_path = args.output.as_ref().unwrap_or(&args.binary);
+    fs::write(out_path, data)
+        .with_context(|| format!("Failed to write binary '{}'", out_path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_dates() {
+        let format = "%Y-%m-%d %H:%M:%S";
+        assert_eq!(format_date(format, 0).unwrap(), "1970-01-01 00:00:00");
+        assert_eq!(format_date(format, 951782400).unwrap(), "2000-02-29 00:00:00");
+        assert_eq!(format_date(format, 1700000000).unwrap(), "2023-11-14 22:13:20");
+        assert_eq!(format_date("%b %e %y, 100%%", 951782400).unwrap(), "Feb 29 00, 100%");
+        assert_eq!(format_date("%e", 0).unwrap(), " 1");
+        assert_eq!(format_date(DEFAULT_DATE_FORMAT, 4102444799).unwrap(), "Dec 31 2099 23:59:59");
+        assert!(format_date("%q", 0).is_err());
+        assert!(format_date("%", 0).is_err());
+    }
+}
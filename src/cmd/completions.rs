@@ -0,0 +1,405 @@
+use std::{env, ffi::OsStr, fmt::Write, path::Path, process::Command, str::FromStr};
+
+use anyhow::{anyhow, bail, Context, Result};
+use argp::{FromArgValue, FromArgs};
+
+use crate::util::output::ensure_text_output;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl FromStr for Shell {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Self::Bash),
+            "zsh" => Ok(Self::Zsh),
+            "fish" => Ok(Self::Fish),
+            "powershell" | "pwsh" => Ok(Self::PowerShell),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromArgValue for Shell {
+    fn from_arg_value(value: &OsStr) -> Result<Self, String> {
+        String::from_arg_value(value)
+            .and_then(|s| Self::from_str(&s).map_err(|_| "Invalid shell".to_string()))
+    }
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Generates shell completion scripts.
+#[argp(subcommand, name = "completions")]
+pub struct Args {
+    #[argp(positional)]
+    /// shell to generate completions for (bash, zsh, fish, powershell)
+    shell: Shell,
+}
+
+#[derive(Debug, Clone)]
+struct Flag {
+    names: Vec<String>,
+    description: String,
+}
+
+#[derive(Debug, Clone)]
+struct CommandInfo {
+    /// Space-separated command path, e.g. `dtk dol split`
+    path: String,
+    description: String,
+    flags: Vec<Flag>,
+    subcommands: Vec<CommandInfo>,
+}
+
+impl CommandInfo {
+    fn walk<'a>(&'a self, out: &mut Vec<&'a CommandInfo>) {
+        out.push(self);
+        for subcommand in &self.subcommands {
+            subcommand.walk(out);
+        }
+    }
+
+    fn name(&self) -> &str { self.path.rsplit(' ').next().unwrap_or(&self.path) }
+
+    /// Words that can follow this command: subcommands, then flags.
+    fn words(&self) -> Vec<&str> {
+        self.subcommands
+            .iter()
+            .map(|c| c.name())
+            .chain(self.flags.iter().flat_map(|f| f.names.iter().map(|s| s.as_str())))
+            .collect()
+    }
+}
+
+pub fn run(args: Args) -> Result<()> {
+    ensure_text_output("completions")?;
+    let exe = env::current_exe().context("Failed to locate dtk executable")?;
+    let root = collect_command(&exe, "dtk", &[], String::new())?;
+    let mut commands = vec![];
+    root.walk(&mut commands);
+    let script = match args.shell {
+        Shell::Bash => generate_bash(&commands),
+        Shell::Zsh => generate_zsh(&commands),
+        Shell::Fish => generate_fish(&commands),
+        Shell::PowerShell => generate_powershell(&commands),
+    }?;
+    print!("{script}");
+    Ok(())
+}
+
+/// argp has no introspection API, so the command tree is recovered by running
+/// `--help` for every (sub)command and parsing its output.
+fn collect_command(
+    exe: &Path,
+    path: &str,
+    args: &[&str],
+    description: String,
+) -> Result<CommandInfo> {
+    let output = Command::new(exe)
+        .args(args)
+        .arg("--help")
+        .output()
+        .with_context(|| format!("Failed to run '{} --help'", path))?;
+    if !output.status.success() {
+        bail!("'{} --help' failed ({})", path, output.status);
+    }
+    let help = String::from_utf8(output.stdout).map_err(|e| anyhow!("Invalid help output: {e}"))?;
+
+    let (mut info, subcommands) = parse_command(path, description, &help);
+    for (name, description) in subcommands {
+        let mut sub_args = args.to_vec();
+        sub_args.push(&name);
+        let sub_path = format!("{path} {name}");
+        info.subcommands.push(collect_command(exe, &sub_path, &sub_args, description)?);
+    }
+    Ok(info)
+}
+
+/// Reads a command's flags from its help output. Returns the command, without subcommands,
+/// and the `(name, description)` of each subcommand.
+fn parse_command(
+    path: &str,
+    description: String,
+    help: &str,
+) -> (CommandInfo, Vec<(String, String)>) {
+    let mut info =
+        CommandInfo { path: path.to_string(), description, flags: vec![], subcommands: vec![] };
+    let mut subcommands = vec![];
+    for (section, name, description) in parse_help(help) {
+        match section {
+            "Options" => info.flags.push(Flag {
+                names: name
+                    .split_whitespace()
+                    .map(|s| s.trim_end_matches(','))
+                    .filter(|s| s.starts_with('-'))
+                    .map(|s| s.to_string())
+                    .collect(),
+                description,
+            }),
+            "Commands" => subcommands.push((name.to_string(), description)),
+            _ => {}
+        }
+    }
+    (info, subcommands)
+}
+
+/// Returns `(section, first column, description)` for each entry in a help listing.
+fn parse_help(help: &str) -> Vec<(&str, &str, String)> {
+    let mut entries = vec![];
+    let mut section = "";
+    let mut indent = None;
+    for line in help.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_indent = line.len() - line.trim_start().len();
+        if line_indent == 0 {
+            section = line.trim_end().trim_end_matches(':');
+            indent = None;
+            continue;
+        }
+        // Wrapped description lines are indented further than entries
+        if *indent.get_or_insert(line_indent) != line_indent {
+            continue;
+        }
+        let entry = line.trim();
+        let (name, description) = match entry.split_once("  ") {
+            Some((name, description)) => (name.trim(), description.trim().to_string()),
+            None => (entry, String::new()),
+        };
+        entries.push((section, name, description));
+    }
+    entries
+}
+
+fn generate_bash(commands: &[&CommandInfo]) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "_dtk() {{")?;
+    writeln!(out, "    local cur cmd_path i")?;
+    writeln!(out, "    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"")?;
+    writeln!(out, "    cmd_path=\"dtk\"")?;
+    writeln!(out, "    for ((i = 1; i < COMP_CWORD; i++)); do")?;
+    writeln!(out, "        case \"$cmd_path ${{COMP_WORDS[i]}}\" in")?;
+    writeln!(out, "            {})", command_paths(commands, "\"", "|"))?;
+    writeln!(out, "                cmd_path=\"$cmd_path ${{COMP_WORDS[i]}}\" ;;")?;
+    writeln!(out, "        esac")?;
+    writeln!(out, "    done")?;
+    writeln!(out, "    case \"$cmd_path\" in")?;
+    for command in commands {
+        writeln!(
+            out,
+            "        \"{}\") COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;",
+            command.path,
+            command.words().join(" ")
+        )?;
+    }
+    writeln!(out, "    esac")?;
+    writeln!(out, "}}")?;
+    writeln!(out, "complete -o default -F _dtk dtk")?;
+    Ok(out)
+}
+
+fn generate_zsh(commands: &[&CommandInfo]) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "#compdef dtk")?;
+    writeln!(out)?;
+    writeln!(out, "_dtk() {{")?;
+    writeln!(out, "    local cmd_path=\"dtk\" i")?;
+    writeln!(out, "    for ((i = 2; i < CURRENT; i++)); do")?;
+    writeln!(out, "        case \"$cmd_path ${{words[i]}}\" in")?;
+    writeln!(out, "            {})", command_paths(commands, "\"", "|"))?;
+    writeln!(out, "                cmd_path=\"$cmd_path ${{words[i]}}\" ;;")?;
+    writeln!(out, "        esac")?;
+    writeln!(out, "    done")?;
+    writeln!(out, "    case \"$cmd_path\" in")?;
+    for command in commands {
+        if command.subcommands.is_empty() {
+            writeln!(
+                out,
+                "        \"{}\") if [[ $PREFIX == -* ]]; then compadd -- {}; else _files; fi ;;",
+                command.path,
+                command.words().join(" ")
+            )?;
+        } else {
+            writeln!(
+                out,
+                "        \"{}\") compadd -- {} ;;",
+                command.path,
+                command.words().join(" ")
+            )?;
+        }
+    }
+    writeln!(out, "    esac")?;
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+    writeln!(out, "_dtk \"$@\"")?;
+    Ok(out)
+}
+
+fn generate_fish(commands: &[&CommandInfo]) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "function __dtk_path")?;
+    writeln!(out, "    set -l p dtk")?;
+    writeln!(out, "    for t in (commandline -opc)[2..-1]")?;
+    writeln!(out, "        switch \"$p $t\"")?;
+    writeln!(out, "            case {}", command_paths(commands, "'", " "))?;
+    writeln!(out, "                set p \"$p $t\"")?;
+    writeln!(out, "        end")?;
+    writeln!(out, "    end")?;
+    writeln!(out, "    echo $p")?;
+    writeln!(out, "end")?;
+    writeln!(out)?;
+    for command in commands {
+        let condition = format!("test (__dtk_path) = '{}'", command.path);
+        for subcommand in &command.subcommands {
+            writeln!(
+                out,
+                "complete -c dtk -f -n \"{condition}\" -a {} -d '{}'",
+                subcommand.name(),
+                fish_escape(&subcommand.description)
+            )?;
+        }
+        for flag in &command.flags {
+            let mut line = format!("complete -c dtk -n \"{condition}\"");
+            for name in &flag.names {
+                match name.strip_prefix("--") {
+                    Some(long) => write!(line, " -l {long}")?,
+                    None => write!(line, " -s {}", name.trim_start_matches('-'))?,
+                }
+            }
+            writeln!(out, "{line} -d '{}'", fish_escape(&flag.description))?;
+        }
+    }
+    Ok(out)
+}
+
+fn generate_powershell(commands: &[&CommandInfo]) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "Register-ArgumentCompleter -Native -CommandName dtk -ScriptBlock {{")?;
+    writeln!(out, "    param($wordToComplete, $commandAst, $cursorPosition)")?;
+    writeln!(out, "    $completions = @{{")?;
+    for command in commands {
+        let words = command
+            .words()
+            .iter()
+            .map(|w| format!("'{}'", powershell_escape(w)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(out, "        '{}' = @({words})", command.path)?;
+    }
+    writeln!(out, "    }}")?;
+    writeln!(out, "    $path = 'dtk'")?;
+    writeln!(
+        out,
+        "    foreach ($element in $commandAst.CommandElements | Select-Object -Skip 1) {{"
+    )?;
+    writeln!(out, "        if ($element.Extent.EndOffset -ge $cursorPosition) {{ break }}")?;
+    writeln!(out, "        $next = \"$path $($element.ToString())\"")?;
+    writeln!(out, "        if ($completions.ContainsKey($next)) {{ $path = $next }}")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "    $completions[$path] |")?;
+    writeln!(out, "        Where-Object {{ $_ -like \"$wordToComplete*\" }} |")?;
+    writeln!(out, "        ForEach-Object {{")?;
+    writeln!(out, "            [System.Management.Automation.CompletionResult]::new($_)")?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "}}")?;
+    Ok(out)
+}
+
+/// Quoted command paths (excluding the root), joined for a shell `case` pattern.
+fn command_paths(commands: &[&CommandInfo], quote: &str, separator: &str) -> String {
+    commands
+        .iter()
+        .skip(1)
+        .map(|c| format!("{quote}{}{quote}", c.path))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn fish_escape(s: &str) -> String { s.replace('\\', "\\\\").replace('\'', "\\'") }
+
+fn powershell_escape(s: &str) -> String { s.replace('\'', "''") }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Captured from `dtk dol --help` and `dtk dol split --help`
+    const DOL_HELP: &str = "\
+Usage: dtk dol <command> [<args>]
+
+Commands for processing DOL files.
+
+Options:
+  -h, --help        Show this help message and exit.
+
+Commands:
+  info              Views DOL file information.
+  split             Splits a DOL into relocatable objects.
+";
+
+    const SPLIT_HELP: &str = "\
+Usage: dtk dol split <config> <out_dir> [--no-update] [-j <jobs>]
+
+Splits a DOL into relocatable objects.
+
+Arguments:
+  <config>          input configuration file
+  <out_dir>         output directory
+
+Options:
+  --no-update       skip updating the splits & symbol files (for testing)
+  -j, --jobs <jobs>  number of worker threads, defaults to the number of
+                    available cores
+  -h, --help        Show this help message and exit.
+";
+
+    #[test]
+    fn parse_help_sections() {
+        let entries = parse_help(DOL_HELP);
+        assert_eq!(entries, [
+            ("Options", "-h, --help", "Show this help message and exit.".to_string()),
+            ("Commands", "info", "Views DOL file information.".to_string()),
+            ("Commands", "split", "Splits a DOL into relocatable objects.".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn parse_help_skips_wrapped_lines() {
+        let entries = parse_help(SPLIT_HELP);
+        let names = entries.iter().map(|(section, name, _)| (*section, *name)).collect::<Vec<_>>();
+        assert_eq!(names, [
+            ("Arguments", "<config>"),
+            ("Arguments", "<out_dir>"),
+            ("Options", "--no-update"),
+            ("Options", "-j, --jobs <jobs>"),
+            ("Options", "-h, --help"),
+        ]);
+        assert_eq!(entries[3].2, "number of worker threads, defaults to the number of");
+    }
+
+    #[test]
+    fn parse_command_flags() {
+        let (info, subcommands) = parse_command("dtk dol", String::new(), DOL_HELP);
+        assert_eq!(subcommands.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), [
+            "info", "split"
+        ]);
+        assert_eq!(info.flags.len(), 1);
+        assert_eq!(info.flags[0].names, ["-h", "--help"]);
+
+        let (info, subcommands) =
+            parse_command("dtk dol split", "Splits a DOL".to_string(), SPLIT_HELP);
+        assert!(subcommands.is_empty());
+        assert_eq!(info.name(), "split");
+        let names = info.flags.iter().map(|f| f.names.clone()).collect::<Vec<_>>();
+        assert_eq!(names, [vec!["--no-update"], vec!["-j", "--jobs"], vec!["-h", "--help"]]);
+        assert_eq!(info.words(), ["--no-update", "-j", "--jobs", "-h", "--help"]);
+    }
+}
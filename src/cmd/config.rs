@@ -0,0 +1,156 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use argp::FromArgs;
+use tracing::info;
+
+use crate::{
+    cmd::dol::{ModuleConfig, ProjectConfig},
+    util::{
+        config::{
+            config_include_paths, format_splits, format_symbols, is_symbols_text,
+            read_splits_sections,
+        },
+        file::buf_reader,
+        output::{is_json, print_json},
+    },
+};
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Commands for processing project configuration files.
+#[argp(subcommand, name = "config")]
+pub struct Args {
+    #[argp(subcommand)]
+    command: SubCommand,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argp(subcommand)]
+enum SubCommand {
+    Fmt(FmtArgs),
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Formats symbols.txt and splits.txt files in the canonical style.
+#[argp(subcommand, name = "fmt")]
+pub struct FmtArgs {
+    #[argp(positional)]
+    /// symbols.txt or splits.txt files, or project configs (config.yml) to format all of
+    files: Vec<PathBuf>,
+    #[argp(switch)]
+    /// don't write files; fail if any would change
+    check: bool,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    match args.command {
+        SubCommand::Fmt(c_args) => fmt(c_args),
+    }
+}
+
+enum FileKind {
+    Symbols,
+    Splits,
+    Detect,
+}
+
+struct FmtFile {
+    path: PathBuf,
+    kind: FileKind,
+    /// Section order for symbols files.
+    sections: Vec<String>,
+}
+
+fn fmt(args: FmtArgs) -> Result<()> {
+    let mut files = Vec::<FmtFile>::new();
+    for path in &args.files {
+        let is_project = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("yml") || ext.eq_ignore_ascii_case("yaml"));
+        if is_project {
+            project_files(path, &mut files)?;
+        } else {
+            files.push(FmtFile { path: path.clone(), kind: FileKind::Detect, sections: vec![] });
+        }
+    }
+
+    let mut unformatted = 0;
+    for file in &files {
+        let text = fs::read_to_string(&file.path)
+            .with_context(|| format!("Failed to read '{}'", file.path.display()))?;
+        let is_symbols = match file.kind {
+            FileKind::Symbols => true,
+            FileKind::Splits => false,
+            FileKind::Detect => is_symbols_text(&text),
+        };
+        let formatted =
+            if is_symbols { format_symbols(&text, &file.sections) } else { format_splits(&text) }
+                .with_context(|| format!("While formatting '{}'", file.path.display()))?;
+        if formatted == text {
+            continue;
+        }
+        unformatted += 1;
+        if args.check {
+            if is_json() {
+                print_json(&serde_json::json!({ "unformatted": file.path }))?;
+            } else {
+                println!("{}", file.path.display());
+            }
+        } else {
+            fs::write(&file.path, formatted)
+                .with_context(|| format!("Failed to write '{}'", file.path.display()))?;
+            info!("Formatted {}", file.path.display());
+        }
+    }
+    if args.check && unformatted > 0 {
+        bail!("{} of {} files are not formatted", unformatted, files.len());
+    }
+    info!(
+        "{} of {} files {}",
+        unformatted,
+        files.len(),
+        if args.check { "need formatting" } else { "formatted" }
+    );
+    Ok(())
+}
+
+/// Collects the symbols and splits files of every module of a project, with their includes.
+fn project_files(config_path: &Path, out: &mut Vec<FmtFile>) -> Result<()> {
+    let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(config_path)?)
+        .with_context(|| format!("While loading '{}'", config_path.display()))?;
+    let modules = std::iter::once(&config.base)
+        .chain(config.modules.iter())
+        .chain(config.dols.iter().map(|dol| &dol.base));
+    for module_config in modules {
+        module_files(module_config, out)?;
+    }
+    Ok(())
+}
+
+fn module_files(module_config: &ModuleConfig, out: &mut Vec<FmtFile>) -> Result<()> {
+    let mut sections = vec![];
+    if let Some(splits) = &module_config.splits {
+        if let Some(defs) = read_splits_sections(splits)? {
+            sections = defs.into_iter().map(|def| def.name).collect();
+        }
+        for path in config_include_paths(splits)?.into_iter().chain([splits.clone()]) {
+            push_file(out, path, FileKind::Splits, vec![]);
+        }
+    }
+    if let Some(symbols) = &module_config.symbols {
+        for path in config_include_paths(symbols)?.into_iter().chain([symbols.clone()]) {
+            push_file(out, path, FileKind::Symbols, sections.clone());
+        }
+    }
+    Ok(())
+}
+
+fn push_file(out: &mut Vec<FmtFile>, path: PathBuf, kind: FileKind, sections: Vec<String>) {
+    // Shared files are only formatted once; missing ones are created by `dol split`
+    if path.is_file() && !out.iter().any(|f| f.path == path) {
+        out.push(FmtFile { path, kind, sections });
+    }
+}
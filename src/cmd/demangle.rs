@@ -2,6 +2,8 @@ use anyhow::{anyhow, Result};
 use argp::FromArgs;
 use cwdemangle::{demangle, DemangleOptions};
 
+use crate::util::output::{is_json, print_json};
+
 #[derive(FromArgs, PartialEq, Eq, Debug)]
 /// Demangle a CodeWarrior C++ symbol.
 #[argp(subcommand, name = "demangle")]
@@ -18,7 +20,11 @@ pub fn run(args: Args) -> Result<()> {
     let options = DemangleOptions { omit_empty_parameters: !args.keep_void };
     match demangle(args.symbol.as_str(), &options) {
         Some(symbol) => {
-            println!("{symbol}");
+            if is_json() {
+                print_json(&serde_json::json!({ "symbol": args.symbol, "demangled": symbol }))?;
+            } else {
+                println!("{symbol}");
+            }
             Ok(())
         }
         None => Err(anyhow!("Failed to demangle symbol")),
@@ -0,0 +1,390 @@
+use std::{
+    fmt::Write as _,
+    io::{stdout, Cursor, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use argp::FromArgs;
+use owo_colors::{OwoColorize, Stream};
+use ppc750cl::{Argument, Ins, Opcode};
+use serde::Serialize;
+
+use crate::{
+    analysis::{
+        cfa::AnalyzerState,
+        pass::{AnalysisPass, FindSaveRestSleds},
+        tracker::Tracker,
+    },
+    cmd::dol::wait_for_changes,
+    obj::{ObjInfo, ObjReloc, ObjRelocKind, ObjSectionKind},
+    util::{
+        config::apply_symbols_file,
+        diff::{align, match_percent, reloc_mask, DiffOp, RelocTarget},
+        dol::process_dol,
+        elf::process_elf,
+        file::map_file,
+        output::{is_json, print_json},
+        rel::process_rel,
+    },
+};
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Shows a side-by-side instruction diff of a function.
+#[argp(subcommand, name = "diff")]
+pub struct Args {
+    #[argp(positional)]
+    /// original code: a split object, or a DOL or REL with --symbols
+    target: PathBuf,
+    #[argp(positional)]
+    /// rebuilt object or linked ELF
+    base: PathBuf,
+    #[argp(positional)]
+    /// function symbol name
+    function: String,
+    #[argp(option, short = 's')]
+    /// symbols file (symbols.txt) for a DOL or REL target
+    symbols: Option<PathBuf>,
+    #[argp(option)]
+    /// width of each column (default 48)
+    width: Option<usize>,
+    #[argp(switch, short = 'w')]
+    /// re-run the diff when the rebuilt object changes
+    watch: bool,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let target = load_target(&args)?;
+    if !args.watch {
+        return diff(&args, &target);
+    }
+    let watched = [args.base.clone()];
+    loop {
+        if let Err(e) = diff(&args, &target) {
+            log::error!("{e:?}");
+        }
+        log::info!("Watching {} for changes", args.base.display());
+        wait_for_changes(&watched);
+    }
+}
+
+/// Loads the original code, running relocation analysis on a DOL so that its references can be
+/// compared by name.
+fn load_target(args: &Args) -> Result<ObjInfo> {
+    let file = map_file(&args.target)?;
+    let data = file.as_slice();
+    if data.starts_with(b"\x7FELF") {
+        return process_elf(&args.target);
+    }
+    let name =
+        args.target.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let is_rel = args.target.extension().is_some_and(|e| e.eq_ignore_ascii_case("rel"));
+    let mut obj = if is_rel {
+        process_rel(&mut Cursor::new(data), &name)?.1
+    } else {
+        process_dol(data, &name)?
+    };
+    let Some(symbols_path) = &args.symbols else {
+        bail!("A symbols file (-s) is required to diff a DOL or REL");
+    };
+    if apply_symbols_file(symbols_path, &mut obj)?.is_none() {
+        bail!("Symbols file '{}' not found", symbols_path.display());
+    }
+    if !is_rel {
+        let mut state = AnalyzerState::default();
+        FindSaveRestSleds::execute(&mut state, &obj)?;
+        state.detect_functions(&obj)?;
+        state.apply(&mut obj)?;
+        let mut tracker = Tracker::new(&obj);
+        tracker.process(&obj)?;
+        tracker.apply(&mut obj, false)?;
+    }
+    Ok(obj)
+}
+
+fn diff(args: &Args, target: &ObjInfo) -> Result<()> {
+    let base = process_elf(&args.base)?;
+    let left = function_lines(target, &args.function, &args.target)?;
+    let right = function_lines(&base, &args.function, &args.base)?;
+    let width = args.width.unwrap_or(48);
+
+    let ops = align(&left, &right);
+    if is_json() {
+        return print_json(&diff_record(&args.function, &left, &right, &ops));
+    }
+    let mut out = String::new();
+    let mut differences = 0;
+    for op in &ops {
+        let (l, r, marker) = match *op {
+            DiffOp::Equal(i, j) => (Some(&left[i]), Some(&right[j]), ' '),
+            DiffOp::Replace(i, j) => {
+                differences += 1;
+                let marker = if left[i].op == right[j].op { '|' } else { 'r' };
+                (Some(&left[i]), Some(&right[j]), marker)
+            }
+            DiffOp::Delete(i) => {
+                differences += 1;
+                (Some(&left[i]), None, '<')
+            }
+            DiffOp::Insert(j) => {
+                differences += 1;
+                (None, Some(&right[j]), '>')
+            }
+        };
+        let l = l.map(|l| format!("{:>5X}: {}", l.offset, l.text)).unwrap_or_default();
+        let r = r.map(|r| format!("{:>5X}: {}", r.offset, r.text)).unwrap_or_default();
+        let l = format!("{:<width$.width$}", l);
+        let r = format!("{:.width$}", r);
+        match marker {
+            ' ' => writeln!(out, "{l} {marker} {r}")?,
+            '|' => writeln!(
+                out,
+                "{} {marker} {}",
+                l.if_supports_color(Stream::Stdout, |t| t.yellow()),
+                r.if_supports_color(Stream::Stdout, |t| t.yellow())
+            )?,
+            'r' => writeln!(
+                out,
+                "{} {marker} {}",
+                l.if_supports_color(Stream::Stdout, |t| t.blue()),
+                r.if_supports_color(Stream::Stdout, |t| t.blue())
+            )?,
+            '<' => {
+                writeln!(out, "{} {marker} {r}", l.if_supports_color(Stream::Stdout, |t| t.red()))?
+            }
+            _ => writeln!(
+                out,
+                "{l} {marker} {}",
+                r.if_supports_color(Stream::Stdout, |t| t.green())
+            )?,
+        }
+    }
+
+    let percent = match_percent(&left, &right);
+    let summary = format!(
+        "{}: {} differences, {:.2}% match ({} vs {} instructions)",
+        args.function,
+        differences,
+        percent,
+        left.len(),
+        right.len()
+    );
+    if differences == 0 {
+        writeln!(out, "{}", summary.if_supports_color(Stream::Stdout, |t| t.green()))?;
+    } else {
+        writeln!(out, "{}", summary.if_supports_color(Stream::Stdout, |t| t.red()))?;
+    }
+    let mut w = stdout().lock();
+    w.write_all(out.as_bytes())?;
+    w.flush()?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct DiffRecord<'a> {
+    function: &'a str,
+    differences: usize,
+    match_percent: f64,
+    target_instructions: usize,
+    base_instructions: usize,
+    lines: Vec<DiffLineRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct DiffLineRecord<'a> {
+    /// `equal`, `replace`, `delete` or `insert`
+    op: &'static str,
+    target: Option<LineRecord<'a>>,
+    base: Option<LineRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct LineRecord<'a> {
+    offset: u32,
+    text: &'a str,
+}
+
+impl<'a> LineRecord<'a> {
+    fn new(line: &'a Line<'_>) -> Self { Self { offset: line.offset, text: &line.text } }
+}
+
+fn diff_record<'a>(
+    function: &'a str,
+    left: &'a [Line<'_>],
+    right: &'a [Line<'_>],
+    ops: &[DiffOp],
+) -> DiffRecord<'a> {
+    let lines = ops
+        .iter()
+        .map(|op| match *op {
+            DiffOp::Equal(i, j) => ("equal", Some(i), Some(j)),
+            DiffOp::Replace(i, j) => ("replace", Some(i), Some(j)),
+            DiffOp::Delete(i) => ("delete", Some(i), None),
+            DiffOp::Insert(j) => ("insert", None, Some(j)),
+        })
+        .map(|(op, i, j)| DiffLineRecord {
+            op,
+            target: i.map(|i| LineRecord::new(&left[i])),
+            base: j.map(|j| LineRecord::new(&right[j])),
+        })
+        .collect::<Vec<_>>();
+    DiffRecord {
+        function,
+        differences: lines.iter().filter(|l| l.op != "equal").count(),
+        match_percent: match_percent(left, right),
+        target_instructions: left.len(),
+        base_instructions: right.len(),
+        lines,
+    }
+}
+
+/// What a relocated or branch field refers to, for comparison.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target<'a> {
+    /// A relocation target.
+    Symbol(RelocTarget<'a>),
+    /// An offset within the function itself.
+    Local(u32),
+}
+
+/// A disassembled instruction, with its relocated or branch field cleared for comparison.
+struct Line<'a> {
+    offset: u32,
+    op: Opcode,
+    word: u32,
+    target: Option<Target<'a>>,
+    text: String,
+}
+
+impl PartialEq for Line<'_> {
+    fn eq(&self, other: &Self) -> bool { self.word == other.word && self.target == other.target }
+}
+
+fn function_lines<'a>(obj: &'a ObjInfo, name: &str, path: &Path) -> Result<Vec<Line<'a>>> {
+    let (_, symbol) = obj
+        .symbols
+        .by_name(name)?
+        .ok_or_else(|| anyhow!("Function '{}' not found in '{}'", name, path.display()))?;
+    let section = symbol
+        .section
+        .and_then(|i| obj.sections.get(i))
+        .ok_or_else(|| anyhow!("Function '{}' has no section", name))?;
+    ensure!(section.kind == ObjSectionKind::Code, "'{}' isn't in a code section", name);
+    ensure!(symbol.size > 0, "Function '{}' in '{}' has no size", name, path.display());
+    let start = symbol.address as u32;
+    let end = start + symbol.size as u32;
+    let data = section.data_range(start, end)?;
+
+    let mut lines = Vec::with_capacity(data.len() / 4);
+    for (i, word) in data.chunks_exact(4).enumerate() {
+        let address = start + i as u32 * 4;
+        let code = u32::from_be_bytes(word.try_into().unwrap());
+        let ins = Ins::new(code, address);
+        let reloc = section.relocations.at(address);
+        let local_dest = match reloc {
+            Some(reloc)
+                if matches!(reloc.kind, ObjRelocKind::PpcRel24 | ObjRelocKind::PpcRel14) =>
+            {
+                let target = &obj.symbols[reloc.target_symbol];
+                let dest = (target.address as i64 + reloc.addend) as u32;
+                (target.section == symbol.section && (start..end).contains(&dest)).then_some(dest)
+            }
+            Some(_) => None,
+            None => ins.branch_dest().filter(|dest| (start..end).contains(dest)),
+        };
+        let (word, target) = if let Some(dest) = local_dest {
+            let mask = if ins.op == Opcode::B { 0x03FFFFFC } else { 0xFFFC };
+            (code & !mask, Some(Target::Local(dest - start)))
+        } else if let Some(reloc) = reloc {
+            (code & !reloc_mask(reloc.kind), Some(Target::Symbol(RelocTarget::new(obj, reloc))))
+        } else {
+            (code, None)
+        };
+        let text = format_ins(&ins, obj, reloc, local_dest.map(|dest| dest - start));
+        lines.push(Line { offset: address - start, op: ins.op, word, target, text });
+    }
+    Ok(lines)
+}
+
+/// Formats an instruction with simplified mnemonics, writing relocated fields as their target.
+fn format_ins(ins: &Ins, obj: &ObjInfo, reloc: Option<&ObjReloc>, local: Option<u32>) -> String {
+    let sins = ins.simplified();
+    let mut out = format!("{}{}", sins.mnemonic, sins.ins.suffix());
+    let mut writing_offset = false;
+    for (i, arg) in sins.args.iter().enumerate() {
+        if !writing_offset {
+            out.push_str(if i == 0 { " " } else { ", " });
+        }
+        let replacement = match arg {
+            Argument::BranchDest(_) => local
+                .map(|offset| format!(".L_{offset:X}"))
+                .or_else(|| reloc.map(|reloc| format_reloc(obj, reloc))),
+            Argument::Uimm(_) | Argument::Simm(_) | Argument::Offset(_) => {
+                reloc.map(|reloc| format_reloc(obj, reloc))
+            }
+            _ => None,
+        };
+        match replacement {
+            Some(replacement) => out.push_str(&replacement),
+            None => write!(out, "{arg}").unwrap(),
+        }
+        if matches!(arg, Argument::Offset(_)) {
+            out.push('(');
+            writing_offset = true;
+            continue;
+        }
+        if writing_offset {
+            out.push(')');
+            writing_offset = false;
+        }
+    }
+    out
+}
+
+fn format_reloc(obj: &ObjInfo, reloc: &ObjReloc) -> String {
+    let symbol = &obj.symbols[reloc.target_symbol];
+    let mut out = symbol.name.to_string();
+    if reloc.addend != 0 {
+        write!(out, "{:+#X}", reloc.addend).unwrap();
+    }
+    out.push_str(match reloc.kind {
+        ObjRelocKind::Absolute | ObjRelocKind::PpcRel24 | ObjRelocKind::PpcRel14 => "",
+        ObjRelocKind::PpcAddr16Hi => "@h",
+        ObjRelocKind::PpcAddr16Ha => "@ha",
+        ObjRelocKind::PpcAddr16Lo => "@l",
+        ObjRelocKind::PpcEmbSda21 => "@sda21",
+        ObjRelocKind::PpcRel32 => "-.",
+        ObjRelocKind::PpcRel16Hi => "-.@h",
+        ObjRelocKind::PpcRel16Ha => "-.@ha",
+        ObjRelocKind::PpcRel16Lo => "-.@l",
+        ObjRelocKind::PpcDtpMod32 => "@dtpmod",
+        ObjRelocKind::PpcDtpRel32 => "@dtprel",
+    });
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(offset: u32, word: u32, text: &str) -> Line<'static> {
+        Line { offset, op: Opcode::Addi, word, target: None, text: text.to_string() }
+    }
+
+    #[test]
+    fn diff_records() {
+        let left = [line(0, 0x38600000, "li r3, 0x0"), line(4, 0x38800000, "li r4, 0x0")];
+        let right = [line(0, 0x38600000, "li r3, 0x0"), line(4, 0x38800001, "li r4, 0x1")];
+        let ops = [DiffOp::Equal(0, 0), DiffOp::Replace(1, 1), DiffOp::Insert(1)];
+        let record = diff_record("fn", &left, &right, &ops);
+        assert_eq!(record.differences, 2);
+        assert_eq!(record.target_instructions, 2);
+        let ops = record.lines.iter().map(|l| l.op).collect::<Vec<_>>();
+        assert_eq!(ops, ["equal", "replace", "insert"]);
+        assert_eq!(record.lines[1].base.as_ref().unwrap().text, "li r4, 0x1");
+        assert!(record.lines[2].target.is_none());
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["lines"][0]["target"]["offset"], 0);
+        assert_eq!(json["function"], "fn");
+    }
+}
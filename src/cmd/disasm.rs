@@ -0,0 +1,131 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::{bail, ensure, Result};
+use argp::FromArgs;
+
+use crate::{
+    analysis::{cfa::SectionAddress, pipeline::Pipeline, tracker::Tracker},
+    obj::{ObjArchitecture, ObjInfo, ObjKind, ObjSection, ObjSectionKind},
+    util::{
+        asm::{write_asm, AsmFlavor, AsmOptions, GekkoMode},
+        config::{apply_symbols_file, parse_address},
+        file::{buf_writer, map_file},
+        hints::apply_hints_file,
+        timings::Timings,
+    },
+};
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Disassembles a raw binary file loaded at a fixed address.
+#[argp(subcommand, name = "disasm")]
+pub struct Args {
+    #[argp(positional)]
+    /// input binary file
+    bin_file: PathBuf,
+    #[argp(positional)]
+    /// output file (.s)
+    out_file: PathBuf,
+    #[argp(option, short = 'a', from_str_fn(parse_address))]
+    /// load address of the binary (e.g. 0x80003100)
+    address: u32,
+    #[argp(option, short = 'e', from_str_fn(parse_address))]
+    /// additional function entry address (can be repeated)
+    entry: Vec<u32>,
+    #[argp(option, short = 's')]
+    /// symbols file (symbols.txt) using section .text (or .data)
+    symbols: Option<PathBuf>,
+    #[argp(option)]
+    /// analysis hints file
+    hints: Option<PathBuf>,
+    #[argp(switch)]
+    /// treat the binary as data instead of code
+    data: bool,
+    #[argp(option, short = 'f')]
+    /// assembly syntax flavor (gnu, macro, codewarrior, listing)
+    flavor: Option<AsmFlavor>,
+    #[argp(switch)]
+    /// prefix register names with % (e.g. %r3)
+    register_prefix: bool,
+    #[argp(switch)]
+    /// disable simplified mnemonics (e.g. addi instead of li)
+    raw_mnemonics: bool,
+    #[argp(option)]
+    /// how Gekko instructions are written (mnemonic, raw)
+    gekko: Option<GekkoMode>,
+    #[argp(switch)]
+    /// write psq_* GQR operands as qr0-qr7
+    gqr_names: bool,
+    #[argp(switch)]
+    /// fail on Gekko instructions with reserved bits set
+    strict_gekko: bool,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let data = {
+        let file = map_file(&args.bin_file)?;
+        file.as_slice().to_vec()
+    };
+    ensure!(!data.is_empty(), "Input file is empty");
+    ensure!(
+        args.data || (args.address % 4 == 0 && data.len() % 4 == 0),
+        "Code must be 4-byte aligned (address {:#010X}, size {:#X})",
+        args.address,
+        data.len()
+    );
+    let end = args.address as u64 + data.len() as u64;
+    ensure!(end <= 0x1_0000_0000, "Binary extends past the end of the address space");
+    for &entry in &args.entry {
+        ensure!(
+            entry >= args.address && (entry as u64) < end,
+            "Entry {:#010X} is outside of the binary ({:#010X}-{:#010X})",
+            entry,
+            args.address,
+            end
+        );
+    }
+
+    let name =
+        args.bin_file.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let (section_name, kind) =
+        if args.data { (".data", ObjSectionKind::Data) } else { (".text", ObjSectionKind::Code) };
+    let mut obj = ObjInfo::new(ObjKind::Executable, ObjArchitecture::PowerPc, name, vec![], vec![
+        ObjSection::raw(section_name, kind, args.address, data),
+    ]);
+
+    if let Some(symbols_path) = &args.symbols {
+        if apply_symbols_file(symbols_path, &mut obj)?.is_none() {
+            bail!("Symbols file '{}' not found", symbols_path.display());
+        }
+    }
+    if let Some(hints_path) = &args.hints {
+        apply_hints_file(hints_path, &mut obj)?;
+    }
+
+    if !args.data {
+        // The start of the section is always analyzed as a function
+        for &entry in &args.entry {
+            obj.known_functions.entry(SectionAddress::new(0, entry)).or_insert(None);
+        }
+
+        Pipeline::default().detect_functions(&mut obj, &mut Timings::new())?;
+
+        log::debug!("Performing relocation analysis");
+        let mut tracker = Tracker::new(&obj);
+        tracker.process(&obj)?;
+        tracker.apply(&mut obj, false)?;
+    }
+
+    let options = AsmOptions {
+        flavor: args.flavor.unwrap_or_default(),
+        register_prefix: args.register_prefix,
+        raw_mnemonics: args.raw_mnemonics,
+        gekko: args.gekko.unwrap_or_default(),
+        gqr_names: args.gqr_names,
+        strict_gekko: args.strict_gekko,
+        ..Default::default()
+    };
+    let mut w = buf_writer(&args.out_file)?;
+    write_asm(&mut w, &obj, &options)?;
+    w.flush()?;
+    Ok(())
+}
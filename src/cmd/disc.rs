@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+use anyhow::{ensure, Context, Result};
+use argp::FromArgs;
+use tracing::info;
+
+use crate::util::{
+    config::parse_hex,
+    file::map_file,
+    gcm::{build_gcm, read_gcm_layout, GcmOptions},
+};
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Commands for processing GameCube disc images.
+#[argp(subcommand, name = "disc")]
+pub struct Args {
+    #[argp(subcommand)]
+    command: SubCommand,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argp(subcommand)]
+enum SubCommand {
+    Build(BuildArgs),
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Builds a GCM/ISO image from an extracted disc.
+#[argp(subcommand, name = "build")]
+pub struct BuildArgs {
+    #[argp(positional)]
+    /// extracted disc directory (containing sys/ and files/)
+    dir: PathBuf,
+    #[argp(option, short = 'o')]
+    /// output disc image
+    output: PathBuf,
+    #[argp(option, from_str_fn(parse_align))]
+    /// file data alignment (default: 0x8000)
+    align: Option<u32>,
+    #[argp(option)]
+    /// original disc image to keep the offsets of unchanged files from
+    original: Option<PathBuf>,
+}
+
+fn parse_align(s: &str) -> Result<u32, String> {
+    match parse_hex(s) {
+        Ok(align) if align.is_power_of_two() => Ok(align),
+        Ok(_) => Err(format!("Alignment '{s}' is not a power of two")),
+        Err(e) => Err(format!("Invalid alignment '{s}': {e}")),
+    }
+}
+
+pub fn run(args: Args) -> Result<()> {
+    match args.command {
+        SubCommand::Build(c_args) => build(c_args),
+    }
+}
+
+fn build(args: BuildArgs) -> Result<()> {
+    let sys_dir = args.dir.join("sys");
+    let files_dir = args.dir.join("files");
+    ensure!(sys_dir.is_dir(), "Missing directory '{}'", sys_dir.display());
+    ensure!(files_dir.is_dir(), "Missing directory '{}'", files_dir.display());
+    let original = match &args.original {
+        Some(path) => {
+            let file = map_file(path)?;
+            Some(
+                read_gcm_layout(file.as_slice())
+                    .with_context(|| format!("Failed to read disc image '{}'", path.display()))?,
+            )
+        }
+        None => None,
+    };
+    let options = GcmOptions { align: args.align.unwrap_or(0x8000), original: original.as_ref() };
+    build_gcm(&sys_dir, &files_dir, &args.output, &options)?;
+    info!("Wrote {}", args.output.display());
+    Ok(())
+}
@@ -1,19 +1,22 @@
 use std::{
     borrow::Cow,
     cmp::min,
-    collections::{btree_map::Entry, hash_map, BTreeMap, HashMap},
+    collections::{btree_map::Entry, hash_map, BTreeMap, BTreeSet, HashMap, HashSet},
     ffi::OsStr,
     fs,
     fs::DirBuilder,
-    io::{Cursor, Write},
+    io::{stdin, stdout, Cursor, Write},
     mem::take,
     path::{Path, PathBuf},
-    time::Instant,
+    thread,
+    time::{Duration, Instant},
 };
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use argp::FromArgs;
+use filetime::FileTime;
 use itertools::Itertools;
+use path_slash::PathBufExt;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info, info_span};
@@ -22,36 +25,54 @@ use xxhash_rust::xxh3::xxh3_64;
 use crate::{
     analysis::{
         cfa::{AnalyzerState, SectionAddress},
-        objects::{detect_objects, detect_strings},
-        pass::{
-            AnalysisPass, FindRelCtorsDtors, FindRelRodataData, FindSaveRestSleds,
-            FindTRKInterruptVectorTable,
-        },
-        signatures::{apply_signatures, apply_signatures_post, update_ctors_dtors},
+        objects::{detect_objects, detect_strings, infer_alignment},
+        pipeline::{PassStage, Pipeline},
+        signatures::{apply_signature_set, update_ctors_dtors},
         tracker::Tracker,
     },
-    cmd::shasum::file_sha1_string,
+    cmd::{fingerprint::load_project, shasum::file_sha1_string},
     obj::{
-        best_match_for_reloc, ObjInfo, ObjKind, ObjReloc, ObjRelocKind, ObjSectionKind, ObjSymbol,
-        ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind, ObjSymbolScope, SymbolIndex,
+        best_match_for_reloc, MemoryRange, ObjArchitecture, ObjInfo, ObjKind, ObjReloc,
+        ObjRelocKind, ObjSection, ObjSectionKind, ObjSplit, ObjSymbol, ObjSymbolFlagSet,
+        ObjSymbolFlags, ObjSymbolKind, ObjSymbolScope, ObjUnit, ObjUnitLanguage, ObjUnitStatus,
+        Platform, SymbolIndex, SymbolName,
     },
     util::{
-        asm::write_asm,
+        asm::{write_asm, AsmOptions},
         bin2c::bin2c,
-        comment::MWComment,
+        bss::{propose_bss_splits, BssProposal},
+        comment::{MWComment, MWCommentConfig},
+        compile_commands::compile_command,
+        compiler::{detect_unit_compilers, CompilerFamily, UnitCompiler},
         config::{
-            apply_splits_file, apply_symbols_file, is_auto_symbol, write_splits_file,
-            write_symbols_file,
+            apply_splits_file, apply_symbols_file, check_config_files, config_include_paths,
+            is_auto_symbol, parse_hex, write_splits_file, write_symbols_file, FunctionSizes,
         },
         dep::DepFile,
-        dol::process_dol,
-        elf::{process_elf, write_elf},
+        dol::{apply_sda_bases, process_dol, DolImage, DolSectionKind, DolSlot},
+        elf::{process_elf, process_elf_data, write_elf},
         file::{buf_reader, buf_writer, map_file, touch, verify_hash, FileIterator, FileReadInfo},
+        fingerprint::{match_objects, MatchMethod},
+        header::{generate_header, header_guard},
+        hints::apply_hints_file,
         lcf::{asm_path_for_unit, generate_ldscript, obj_path_for_unit},
-        map::apply_map_file,
-        rel::{process_rel, process_rel_header, update_rel_section_alignment},
+        map::{apply_map_file, write_map},
+        output::{coded_error, ensure_text_output, is_json, print_json, take_warnings, ErrorCode},
+        progress::item_progress,
+        rel::{
+            process_rel, process_rel_header, set_module_name, update_rel_section_alignment,
+            DisplayModule, RelLayout,
+        },
+        rpl::is_rpl,
         rso::{process_rso, DOL_SECTION_ABS, DOL_SECTION_ETI, DOL_SECTION_NAMES},
-        split::{is_linker_generated_object, split_obj, update_splits},
+        scaffold::{generate_scaffold, source_path_for_unit, ScaffoldStyle},
+        sigstore::{read_pinned_set, set_path, SignatureStore},
+        split::{
+            is_gap_unit, is_linker_generated_object, is_strip_remnant_symbol, split_obj,
+            update_splits,
+        },
+        suggest::{suggest_splits, SplitSuggestion, SuggestionSource},
+        timings::Timings,
         IntoCow, ToCow,
     },
 };
@@ -68,10 +89,21 @@ pub struct Args {
 #[argp(subcommand)]
 enum SubCommand {
     Info(InfoArgs),
+    Extract(ExtractArgs),
     Split(SplitArgs),
     Diff(DiffArgs),
     Apply(ApplyArgs),
+    ImportElf(ImportElfArgs),
     Config(ConfigArgs),
+    Check(CheckArgs),
+    Header(HeaderArgs),
+    Bss(BssArgs),
+    Compiler(CompilerArgs),
+    Suggest(SuggestArgs),
+    Map(MapArgs),
+    Gdb(GdbArgs),
+    CompileCommands(CompileCommandsArgs),
+    Edit(EditArgs),
 }
 
 #[derive(FromArgs, PartialEq, Eq, Debug)]
@@ -86,6 +118,18 @@ pub struct InfoArgs {
     pub selfile: Option<PathBuf>,
 }
 
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Dumps each DOL section to a binary file.
+#[argp(subcommand, name = "extract")]
+pub struct ExtractArgs {
+    #[argp(positional)]
+    /// DOL file
+    dol_file: PathBuf,
+    #[argp(positional)]
+    /// output directory
+    out_dir: PathBuf,
+}
+
 #[derive(FromArgs, PartialEq, Eq, Debug)]
 /// Splits a DOL into relocatable objects.
 #[argp(subcommand, name = "split")]
@@ -102,6 +146,15 @@ pub struct SplitArgs {
     #[argp(option, short = 'j')]
     /// number of threads to use (default: number of logical CPUs)
     jobs: Option<usize>,
+    #[argp(switch, short = 'w')]
+    /// watch the configuration and input files, and re-run when they change
+    watch: bool,
+    #[argp(switch)]
+    /// report the time spent in each analysis pass, per module
+    timings: bool,
+    #[argp(switch)]
+    /// report the objects, symbols and warnings that would change, without writing any files
+    dry_run: bool,
 }
 
 #[derive(FromArgs, PartialEq, Eq, Debug)]
@@ -128,6 +181,24 @@ pub struct ApplyArgs {
     elf_file: PathBuf,
 }
 
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Imports symbols and file boundaries from a debug ELF shipped with the DOL.
+#[argp(subcommand, name = "import-elf")]
+pub struct ImportElfArgs {
+    #[argp(positional)]
+    /// input configuration file
+    config: PathBuf,
+    #[argp(positional)]
+    /// debug ELF
+    elf_file: PathBuf,
+    #[argp(switch)]
+    /// don't import file boundaries into splits.txt
+    no_splits: bool,
+    #[argp(switch)]
+    /// replace existing user-named symbols
+    force: bool,
+}
+
 #[derive(FromArgs, PartialEq, Eq, Debug)]
 /// Generates a project configuration file from a DOL (& RELs).
 #[argp(subcommand, name = "config")]
@@ -140,6 +211,182 @@ pub struct ConfigArgs {
     out_file: PathBuf,
 }
 
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Checks the project's splits and symbols files for consistency, without analysis.
+#[argp(subcommand, name = "check")]
+pub struct CheckArgs {
+    #[argp(positional)]
+    /// input configuration file
+    config: PathBuf,
+    #[argp(switch)]
+    /// apply safe fixes, using function detection on the binaries, and write the files back
+    fix: bool,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Generates a header of extern declarations for the project's global symbols.
+#[argp(subcommand, name = "header")]
+pub struct HeaderArgs {
+    #[argp(positional)]
+    /// input configuration file
+    config: PathBuf,
+    #[argp(option, short = 'o')]
+    /// output header file (default: stdout)
+    out_file: Option<PathBuf>,
+    #[argp(option, short = 't')]
+    /// YAML file mapping symbol names to declarations
+    types: Option<PathBuf>,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Proposes BSS splits from the units that reference each symbol.
+#[argp(subcommand, name = "bss")]
+pub struct BssArgs {
+    #[argp(positional)]
+    /// input configuration file
+    config: PathBuf,
+    #[argp(option, short = 'm')]
+    /// minimum confidence to write a split, from 0 to 1 (default 0.5)
+    min_confidence: Option<f32>,
+    #[argp(switch)]
+    /// add the proposed splits to splits.txt
+    write: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Detects the likely compiler of each unit from its code.
+#[argp(subcommand, name = "compiler")]
+pub struct CompilerArgs {
+    #[argp(positional)]
+    /// input configuration file
+    config: PathBuf,
+    #[argp(option, short = 'm')]
+    /// minimum confidence to write a compiler, from 0 to 1 (default 0.8)
+    min_confidence: Option<f32>,
+    #[argp(switch)]
+    /// set the compiler of units without one in splits.txt
+    write: bool,
+    #[argp(option)]
+    /// compiler to write for MWCC 1.x code (default GC/1.2.5)
+    mwcc1: Option<String>,
+    #[argp(option)]
+    /// compiler to write for MWCC 2.x code (default GC/2.6)
+    mwcc2: Option<String>,
+    #[argp(option)]
+    /// compiler to write for GCC code (default ProDG/3.9.3)
+    gcc: Option<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Walks through the code and data not covered by a split, suggesting splits to add.
+#[argp(subcommand, name = "suggest")]
+pub struct SuggestArgs {
+    #[argp(positional)]
+    /// input configuration file
+    config: PathBuf,
+    #[argp(option, short = 's')]
+    /// only suggest splits in this section (e.g. .text)
+    section: Option<String>,
+    #[argp(switch, short = 'l')]
+    /// list the suggestions without prompting
+    list: bool,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Generates CodeWarrior-style link maps from the project's splits and symbols.
+#[argp(subcommand, name = "map")]
+pub struct MapArgs {
+    #[argp(positional)]
+    /// input configuration file
+    config: PathBuf,
+    #[argp(positional)]
+    /// output directory for the .map files
+    out_dir: PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Writes ELFs with the project's symbols at their original addresses, for debuggers.
+#[argp(subcommand, name = "gdb")]
+pub struct GdbArgs {
+    #[argp(positional)]
+    /// input configuration file
+    config: PathBuf,
+    #[argp(positional)]
+    /// output directory for the .elf files
+    out_dir: PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Generates a clang compilation database (compile_commands.json) for the project's sources.
+#[argp(subcommand, name = "compile-commands")]
+pub struct CompileCommandsArgs {
+    #[argp(positional)]
+    /// input configuration file
+    config: PathBuf,
+    #[argp(option, short = 's')]
+    /// source directory (default: scaffold_dir from the config, or src)
+    source_dir: Option<PathBuf>,
+    #[argp(option, short = 'I')]
+    /// include directory (can be repeated; default: include)
+    include: Vec<PathBuf>,
+    #[argp(option, short = 'o')]
+    /// output file (default: compile_commands.json)
+    out_file: Option<PathBuf>,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Appends or resizes DOL sections, keeping the header valid.
+#[argp(subcommand, name = "edit")]
+pub struct EditArgs {
+    #[argp(positional)]
+    /// DOL file
+    dol_file: PathBuf,
+    #[argp(option, short = 'o')]
+    /// output DOL (default: overwrite the input)
+    out_file: Option<PathBuf>,
+    #[argp(option, from_str_fn(parse_section_append))]
+    /// append a section, as text=file.bin or data=file.bin@0x80500000 (can be repeated)
+    add: Vec<SectionAppend>,
+    #[argp(option, from_str_fn(parse_section_resize))]
+    /// resize a section, as text1=0x2000, data3=0x100 or bss=0x8000 (can be repeated)
+    resize: Vec<(DolSlot, u32)>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct SectionAppend {
+    kind: DolSectionKind,
+    file: PathBuf,
+    address: Option<u32>,
+}
+
+fn parse_section_append(s: &str) -> Result<SectionAppend, String> {
+    let Some((kind, file)) = s.split_once('=') else {
+        return Err(format!("Expected text=file or data=file, got '{s}'"));
+    };
+    let kind = match kind {
+        "text" => DolSectionKind::Text,
+        "data" => DolSectionKind::Data,
+        _ => return Err(format!("Invalid section kind '{kind}': expected text or data")),
+    };
+    let (file, address) = match file.rsplit_once('@') {
+        Some((file, address)) => {
+            let address =
+                parse_hex(address).map_err(|e| format!("Invalid address '{address}': {e}"))?;
+            (file, Some(address))
+        }
+        None => (file, None),
+    };
+    Ok(SectionAppend { kind, file: PathBuf::from(file), address })
+}
+
+fn parse_section_resize(s: &str) -> Result<(DolSlot, u32), String> {
+    let Some((slot, size)) = s.split_once('=') else {
+        return Err(format!("Expected section=size, got '{s}'"));
+    };
+    let size = parse_hex(size).map_err(|e| format!("Invalid size '{size}': {e}"))?;
+    Ok((slot.parse()?, size))
+}
+
 #[inline]
 fn bool_true() -> bool { true }
 
@@ -205,19 +452,55 @@ pub struct ProjectConfig {
     /// If not present, no `.comment` sections will be written.
     #[serde(skip_serializing_if = "is_default")]
     pub mw_comment_version: Option<u8>,
+    /// Overrides for the `.comment` section header.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub mw_comment: MWCommentConfig,
     /// Disables some time-consuming analysis passes.
     /// Useful when the symbols file is already created.
     #[serde(default, skip_serializing_if = "is_default")]
     pub quick_analysis: bool,
     #[serde(default, skip_serializing_if = "is_default")]
     pub modules: Vec<ModuleConfig>,
+    /// Additional executables on the same disc, such as a boot loader or another game DOL.
+    /// Each has its own splits and symbols, and is split into `out_dir/<name>`.
+    /// Names of functions identical to the main DOL's are shared.
+    /// Modules link to the main DOL only.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub dols: Vec<DolConfig>,
+    /// Display names for module IDs, used in logs and diagnostics.
+    /// A path may be given, in which case its file name is used.
+    /// Modules listed in `modules` are named after their object automatically.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub module_names: BTreeMap<u32, String>,
     // Analysis options
     #[serde(default = "bool_true", skip_serializing_if = "is_true")]
     pub detect_objects: bool,
     #[serde(default = "bool_true", skip_serializing_if = "is_true")]
     pub detect_strings: bool,
+    /// Records the alignment of data symbols that need more than 4 bytes, inferred from
+    /// their access width and the padding before them.
+    #[serde(default = "bool_true", skip_serializing_if = "is_true")]
+    pub infer_alignment: bool,
     #[serde(default = "bool_true", skip_serializing_if = "is_true")]
     pub write_asm: bool,
+    /// Writes an object for each REL defining the DOL symbols it imports,
+    /// so the REL's objects can be linked without the DOL.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub write_import_stubs: bool,
+    /// Packages the objects of units with a `library` into a static library per library,
+    /// with a symbol index, as the original SDK and MSL libraries were linked.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub write_archives: bool,
+    /// Options for the assembly output.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub asm: AsmOptions,
+    /// If specified, skeleton C/C++ source files are generated in this directory
+    /// for each unit that doesn't already have one.
+    #[serde(with = "path_slash_serde_option", default, skip_serializing_if = "is_default")]
+    pub scaffold_dir: Option<PathBuf>,
+    /// Function stub style for generated source files.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub scaffold_style: ScaffoldStyle,
     /// Specifies the start of the common BSS section.
     #[serde(skip_serializing_if = "is_default")]
     pub common_start: Option<u32>,
@@ -228,9 +511,28 @@ pub struct ProjectConfig {
     /// Fills gaps between symbols with
     #[serde(default = "bool_true", skip_serializing_if = "is_true")]
     pub fill_gaps: bool,
+    /// Target console, which determines the default memory ranges.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub platform: Platform,
+    /// Valid pointer targets, overriding the platform's MEM1, MEM2 and locked cache ranges.
+    /// Detected pointers outside of them are ignored with a warning.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub memory_ranges: Option<Vec<MemoryRange>>,
+    /// Shared signature store: a directory or an HTTP(S) URL.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub signature_store: Option<String>,
+    /// Directory that `dtk signatures pull -c` copies `signature_sets` to, and that analysis
+    /// reads them from. Required for a remote `signature_store`. Without it, sets are read
+    /// from a local store directly.
+    #[serde(with = "path_slash_serde_option", default, skip_serializing_if = "is_default")]
+    pub signature_cache: Option<PathBuf>,
+    /// Signature sets to apply after function detection, by library version
+    /// (e.g. `sdk-2002-12`, `msl-2.4.2`).
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub signature_sets: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct ModuleConfig {
     /// Object name. If not specified, the file name without extension will be used.
     #[serde(skip_serializing_if = "is_default")]
@@ -255,6 +557,29 @@ pub struct ModuleConfig {
     pub links: Option<Vec<String>>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub extract: Vec<ExtractConfig>,
+    /// Analysis hints file, for facts the analyzer can't infer.
+    #[serde(with = "path_slash_serde_option", default, skip_serializing_if = "is_default")]
+    pub hints: Option<PathBuf>,
+    /// REL layout options for `rel make`. When set on the DOL, applies to every module.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub rel_layout: Option<RelLayout>,
+    /// Value of `_SDA_BASE_` (r13). Only applies to DOLs.
+    /// If not specified, it's recovered from the register setup in `__init_registers`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub sda_base: Option<u32>,
+    /// Value of `_SDA2_BASE_` (r2). Only applies to DOLs.
+    /// If not specified, it's recovered from the register setup in `__init_registers`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub sda2_base: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DolConfig {
+    #[serde(flatten)]
+    pub base: ModuleConfig,
+    /// Specifies the start of the common BSS section.
+    #[serde(skip_serializing_if = "is_default")]
+    pub common_start: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -292,14 +617,49 @@ impl ModuleConfig {
     }
 }
 
+impl ProjectConfig {
+    /// Registers the configured module names for use in logs and diagnostics.
+    pub fn register_module_names(&self) {
+        set_module_name(0, self.base.file_name(), false);
+        for (&module_id, name) in &self.module_names {
+            let name = Path::new(name).file_name().map(|n| n.to_string_lossy());
+            set_module_name(module_id, name.unwrap_or_default(), true);
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OutputUnit {
     #[serde(with = "path_slash_serde")]
     pub object: PathBuf,
     pub name: String,
     pub autogenerated: bool,
+    /// Generated to cover data not claimed by any split.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub gap: bool,
     pub code_size: u32,
     pub data_size: u32,
+    /// Size of likely dead-strip remnants (`stripped_` placeholders), which aren't counted in
+    /// `code_size` and `data_size`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub stripped_size: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiler: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cflags: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<ObjUnitLanguage>,
+    /// Source file path, relative to the project source directory.
+    #[serde(with = "path_slash_serde_option", default, skip_serializing_if = "is_default")]
+    pub source: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ObjUnitStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scratch: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -310,6 +670,21 @@ pub struct OutputModule {
     pub ldscript: PathBuf,
     pub entry: Option<String>,
     pub units: Vec<OutputUnit>,
+    /// Object defining the DOL symbols imported by this module.
+    #[serde(with = "path_slash_serde_option", default, skip_serializing_if = "Option::is_none")]
+    pub import_stubs: Option<PathBuf>,
+    /// Static libraries packaging the objects of units with a `library`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub archives: Vec<OutputArchive>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct OutputArchive {
+    pub name: String,
+    #[serde(with = "path_slash_serde")]
+    pub path: PathBuf,
+    /// Names of the units in the archive.
+    pub units: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
@@ -323,16 +698,34 @@ pub struct OutputConfig {
     #[serde(flatten)]
     pub base: OutputModule,
     pub modules: Vec<OutputModule>,
+    /// Additional executables.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dols: Vec<OutputModule>,
     pub links: Vec<OutputLink>,
 }
 
-pub fn run(args: Args) -> Result<()> {
+pub fn run(args: Args) -> Result<()> { run_with_pipeline(args, &Pipeline::default()) }
+
+/// Runs a `dol` subcommand, analyzing objects with `pipeline`. Used by tools that add their
+/// own passes to the [default pipeline](Pipeline::default).
+pub fn run_with_pipeline(args: Args, pipeline: &Pipeline) -> Result<()> {
     match args.command {
-        SubCommand::Info(c_args) => info(c_args),
-        SubCommand::Split(c_args) => split(c_args),
+        SubCommand::Info(c_args) => info(c_args, pipeline),
+        SubCommand::Extract(c_args) => extract(c_args),
+        SubCommand::Split(c_args) => split(c_args, pipeline),
         SubCommand::Diff(c_args) => diff(c_args),
         SubCommand::Apply(c_args) => apply(c_args),
+        SubCommand::ImportElf(c_args) => import_elf(c_args),
         SubCommand::Config(c_args) => config(c_args),
+        SubCommand::Check(c_args) => check(c_args, pipeline),
+        SubCommand::Header(c_args) => header(c_args),
+        SubCommand::Bss(c_args) => bss(c_args, pipeline),
+        SubCommand::Compiler(c_args) => compiler(c_args, pipeline),
+        SubCommand::Suggest(c_args) => suggest(c_args, pipeline),
+        SubCommand::Map(c_args) => map(c_args, pipeline),
+        SubCommand::Gdb(c_args) => gdb(c_args),
+        SubCommand::CompileCommands(c_args) => compile_commands(c_args),
+        SubCommand::Edit(c_args) => edit(c_args),
     }
 }
 
@@ -388,8 +781,8 @@ fn apply_selfile(obj: &mut ObjInfo, buf: &[u8]) -> Result<()> {
         if let Some((existing_symbol_idx, existing_symbol)) = existing_symbol {
             log::debug!("Mapping symbol {} to {}", symbol.name, existing_symbol.name);
             obj.symbols.replace(existing_symbol_idx, ObjSymbol {
-                name: symbol.name.clone(),
-                demangled_name: symbol.demangled_name.clone(),
+                name: symbol.name,
+                demangled_name: symbol.demangled_name,
                 address: address as u64,
                 section,
                 size: existing_symbol.size,
@@ -400,13 +793,14 @@ fn apply_selfile(obj: &mut ObjInfo, buf: &[u8]) -> Result<()> {
                 data_kind: existing_symbol.data_kind,
                 name_hash: existing_symbol.name_hash,
                 demangled_name_hash: existing_symbol.demangled_name_hash,
+                source: symbol.source,
             })?;
         } else {
             log::debug!("Creating symbol {} at {:#010X}", symbol.name, address);
             obj.symbols.add(
                 ObjSymbol {
-                    name: symbol.name.clone(),
-                    demangled_name: symbol.demangled_name.clone(),
+                    name: symbol.name,
+                    demangled_name: symbol.demangled_name,
                     address: address as u64,
                     section,
                     flags: ObjSymbolFlagSet(ObjSymbolFlags::Global | ObjSymbolFlags::ForceActive),
@@ -419,38 +813,185 @@ fn apply_selfile(obj: &mut ObjInfo, buf: &[u8]) -> Result<()> {
     Ok(())
 }
 
-pub fn info(args: InfoArgs) -> Result<()> {
-    let mut obj = {
+#[derive(Serialize)]
+struct ExtractManifest {
+    entry: Option<u64>,
+    sections: Vec<ExtractSection>,
+}
+
+#[derive(Serialize)]
+struct ExtractSection {
+    name: String,
+    kind: &'static str,
+    address: u64,
+    size: u64,
+    file_offset: u64,
+    /// Not written for BSS sections
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+}
+
+fn extract(args: ExtractArgs) -> Result<()> {
+    let obj = {
         let file = map_file(&args.dol_file)?;
         process_dol(file.as_slice(), "")?
     };
-    apply_signatures(&mut obj)?;
+    DirBuilder::new().recursive(true).create(&args.out_dir)?;
+    let mut manifest = ExtractManifest { entry: obj.entry, sections: vec![] };
+    for (_, section) in obj.sections.iter() {
+        let kind = match section.kind {
+            ObjSectionKind::Code => "code",
+            ObjSectionKind::Data => "data",
+            ObjSectionKind::ReadOnlyData => "rodata",
+            ObjSectionKind::Bss => "bss",
+        };
+        let file = if section.kind == ObjSectionKind::Bss {
+            None
+        } else {
+            let mut file_name = format!("{}.bin", section.name.trim_start_matches('.'));
+            if manifest.sections.iter().any(|s| s.file.as_ref() == Some(&file_name)) {
+                file_name =
+                    format!("{}_{:08X}.bin", section.name.trim_start_matches('.'), section.address);
+            }
+            let out_path = args.out_dir.join(&file_name);
+            fs::write(&out_path, &section.data)
+                .with_context(|| format!("Failed to write '{}'", out_path.display()))?;
+            Some(file_name)
+        };
+        manifest.sections.push(ExtractSection {
+            name: section.name.clone(),
+            kind,
+            address: section.address,
+            size: section.size,
+            file_offset: section.file_offset,
+            file,
+        });
+    }
+    let manifest_path = args.out_dir.join("manifest.json");
+    let mut w = buf_writer(&manifest_path)?;
+    serde_json::to_writer_pretty(&mut w, &manifest)?;
+    w.flush()?;
+    info!("Wrote {} sections to {}", manifest.sections.len(), args.out_dir.display());
+    Ok(())
+}
 
-    let mut state = AnalyzerState::default();
-    FindSaveRestSleds::execute(&mut state, &obj)?;
-    state.detect_functions(&obj)?;
-    log::debug!(
-        "Discovered {} functions",
-        state.functions.iter().filter(|(_, i)| i.end.is_some()).count()
-    );
+/// A section listed by `dol info` and `rel info`.
+#[derive(Serialize)]
+pub struct InfoSection<'a> {
+    pub name: &'a str,
+    pub kind: &'static str,
+    pub address: u64,
+    pub size: u64,
+    pub file_offset: u64,
+    pub index: usize,
+}
+
+impl<'a> InfoSection<'a> {
+    pub fn new(section: &'a ObjSection) -> Self {
+        let kind = match section.kind {
+            ObjSectionKind::Code => "code",
+            ObjSectionKind::Data => "data",
+            ObjSectionKind::ReadOnlyData => "rodata",
+            ObjSectionKind::Bss => "bss",
+        };
+        Self {
+            name: &section.name,
+            kind,
+            address: section.address,
+            size: section.size,
+            file_offset: section.file_offset,
+            index: section.elf_index,
+        }
+    }
+}
+
+/// A symbol listed by `dol info` and `rel info`.
+#[derive(Serialize)]
+pub struct InfoSymbol<'a> {
+    /// `None` for absolute symbols
+    pub section: Option<&'a str>,
+    pub address: u64,
+    /// `None` if the size isn't known
+    pub size: Option<u64>,
+    pub name: &'a str,
+}
+
+impl<'a> InfoSymbol<'a> {
+    /// Skips generated (`@...` and automatic) symbols.
+    pub fn new(obj: &'a ObjInfo, symbol: &'a ObjSymbol) -> Option<Self> {
+        if symbol.name.starts_with('@') || is_auto_symbol(symbol) {
+            return None;
+        }
+        Some(Self {
+            section: symbol.section.map(|section| obj.sections[section].name.as_str()),
+            address: symbol.address,
+            size: symbol.size_known.then_some(symbol.size),
+            name: &symbol.name,
+        })
+    }
+
+    pub fn print(&self, indent: &str) {
+        let size_str = match (self.size, self.section) {
+            (Some(size), _) => format!("{:#X}", size).into_cow(),
+            (None, None) => "ABS".to_cow(),
+            (None, Some(_)) => "?".to_cow(),
+        };
+        println!(
+            "{indent}{: >10} | {: <#10X} | {: <10} | {: <10}",
+            self.section.unwrap_or("ABS"),
+            self.address,
+            size_str,
+            self.name
+        );
+    }
+}
 
-    FindTRKInterruptVectorTable::execute(&mut state, &obj)?;
-    state.apply(&mut obj)?;
+#[derive(Serialize)]
+struct DolInfoRecord<'a> {
+    name: &'a str,
+    entry: Option<u64>,
+    sections: Vec<InfoSection<'a>>,
+    symbols: Vec<InfoSymbol<'a>>,
+    /// Functions found in the exception table
+    known_functions: usize,
+}
 
-    apply_signatures_post(&mut obj)?;
+pub fn info(args: InfoArgs, pipeline: &Pipeline) -> Result<()> {
+    let mut obj = {
+        let file = map_file(&args.dol_file)?;
+        process_dol(file.as_slice(), "")?
+    };
+    pipeline.analyze(&mut obj, false, &mut Timings::new())?;
 
     if let Some(selfile) = &args.selfile {
         let file = map_file(selfile)?;
         apply_selfile(&mut obj, file.as_slice())?;
     }
 
+    let sections = obj.sections.iter().map(|(_, s)| InfoSection::new(s)).collect::<Vec<_>>();
+    let symbols = obj
+        .symbols
+        .iter_ordered()
+        .chain(obj.symbols.iter_abs())
+        .filter_map(|(_, s)| InfoSymbol::new(&obj, s))
+        .collect::<Vec<_>>();
+    if is_json() {
+        return print_json(&DolInfoRecord {
+            name: &obj.name,
+            entry: obj.entry,
+            sections,
+            symbols,
+            known_functions: obj.known_functions.len(),
+        });
+    }
+
     println!("{}:", obj.name);
     if let Some(entry) = obj.entry {
         println!("Entry point: {:#010X}", entry);
     }
     println!("\nSections:");
     println!("\t{: >10} | {: <10} | {: <10} | {: <10}", "Name", "Address", "Size", "File Off");
-    for (_, section) in obj.sections.iter() {
+    for section in &sections {
         println!(
             "\t{: >10} | {:#010X} | {: <#10X} | {: <#10X}",
             section.name, section.address, section.size, section.file_offset
@@ -458,26 +999,8 @@ pub fn info(args: InfoArgs) -> Result<()> {
     }
     println!("\nDiscovered symbols:");
     println!("\t{: >10} | {: <10} | {: <10} | {: <10}", "Section", "Address", "Size", "Name");
-    for (_, symbol) in obj.symbols.iter_ordered().chain(obj.symbols.iter_abs()) {
-        if symbol.name.starts_with('@') || is_auto_symbol(symbol) {
-            continue;
-        }
-        let section_str = if let Some(section) = symbol.section {
-            obj.sections[section].name.as_str()
-        } else {
-            "ABS"
-        };
-        let size_str = if symbol.size_known {
-            format!("{:#X}", symbol.size).into_cow()
-        } else if symbol.section.is_none() {
-            "ABS".to_cow()
-        } else {
-            "?".to_cow()
-        };
-        println!(
-            "\t{: >10} | {: <#10X} | {: <10} | {: <10}",
-            section_str, symbol.address, size_str, symbol.name
-        );
+    for symbol in &symbols {
+        symbol.print("\t");
     }
     println!("\n{} discovered functions from exception table", obj.known_functions.len());
     Ok(())
@@ -488,6 +1011,38 @@ struct ModuleInfo<'a> {
     config: &'a ModuleConfig,
     symbols_cache: Option<FileReadInfo>,
     splits_cache: Option<FileReadInfo>,
+    common_start: Option<u32>,
+    timings: Timings,
+    changes: ModuleChanges,
+}
+
+/// Output changes found by `dol split --dry-run`.
+#[derive(Serialize, Default)]
+struct ModuleChanges {
+    module: String,
+    new_objects: Vec<String>,
+    changed_objects: Vec<String>,
+    removed_objects: Vec<String>,
+    moved_symbols: Vec<MovedSymbol>,
+    ldscript_changed: bool,
+    #[serde(skip)]
+    link_order: Vec<String>,
+    /// Unit of each symbol in the split objects
+    #[serde(skip)]
+    units: HashMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct MovedSymbol {
+    name: String,
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize)]
+struct DryRunReport<'a> {
+    modules: Vec<&'a ModuleChanges>,
+    new_warnings: Vec<String>,
 }
 
 type ModuleMapByName<'a> = BTreeMap<String, ModuleInfo<'a>>;
@@ -498,27 +1053,28 @@ fn update_symbols(
     modules: &[&ModuleInfo<'_>],
     create_symbols: bool,
 ) -> Result<()> {
-    log::debug!("Updating symbols for module {}", obj.module_id);
+    log::debug!("Updating symbols for {}", DisplayModule(obj.module_id));
 
     // Find all references to this module from other modules
-    for (source_module_id, rel_reloc) in obj
+    let module_id = obj.module_id;
+    for (source_module_id, result) in obj
         .unresolved_relocations
-        .iter()
-        .map(|r| (obj.module_id, r))
+        .iter_module(module_id)
+        .map(|r| (module_id, r))
         .chain(modules.iter().flat_map(|info| {
-            info.obj.unresolved_relocations.iter().map(|r| (info.obj.module_id, r))
+            info.obj.unresolved_relocations.iter_module(module_id).map(|r| (info.obj.module_id, r))
         }))
-        .filter(|(_, r)| r.module_id == obj.module_id)
     {
+        let rel_reloc = result?;
         if source_module_id == obj.module_id {
             // Skip if already resolved
             let (_, source_section) =
                 obj.sections.get_elf_index(rel_reloc.section as usize).ok_or_else(|| {
                     anyhow!(
-                        "Failed to locate REL section {} in module ID {}: source module {}, {:?}",
+                        "Failed to locate REL section {} in {}: source {}, {:?}",
                         rel_reloc.section,
-                        obj.module_id,
-                        source_module_id,
+                        DisplayModule(obj.module_id),
+                        DisplayModule(source_module_id),
                         rel_reloc
                     )
                 })?;
@@ -530,10 +1086,10 @@ fn update_symbols(
         let (target_section_index, target_section) =
             obj.sections.get_elf_index(rel_reloc.target_section as usize).ok_or_else(|| {
                 anyhow!(
-                    "Failed to locate REL section {} in module ID {}: source module {}, {:?}",
+                    "Failed to locate REL section {} in {}: source {}, {:?}",
                     rel_reloc.target_section,
-                    obj.module_id,
-                    source_module_id,
+                    DisplayModule(obj.module_id),
+                    DisplayModule(source_module_id),
                     rel_reloc
                 )
             })?;
@@ -568,7 +1124,7 @@ fn update_symbols(
                 )
             };
             obj.symbols.add_direct(ObjSymbol {
-                name,
+                name: name.into(),
                 address: rel_reloc.addend as u64,
                 section: Some(target_section_index),
                 flags: ObjSymbolFlagSet(ObjSymbolFlags::ForceActive.into()),
@@ -585,17 +1141,19 @@ fn create_relocations(
     modules: &ModuleMapById<'_>,
     dol_obj: &ObjInfo,
 ) -> Result<()> {
-    log::debug!("Creating relocations for module {}", obj.module_id);
+    log::debug!("Creating relocations for {}", DisplayModule(obj.module_id));
 
     // Resolve all relocations in this module
-    for rel_reloc in take(&mut obj.unresolved_relocations) {
+    let relocations = take(&mut obj.unresolved_relocations);
+    for result in relocations.iter() {
+        let rel_reloc = result?;
         // Skip if already resolved
         let (_, source_section) =
             obj.sections.get_elf_index(rel_reloc.section as usize).ok_or_else(|| {
                 anyhow!(
-                    "Failed to locate REL section {} in module ID {}: {:?}",
+                    "Failed to locate REL section {} in {}: {:?}",
                     rel_reloc.section,
-                    obj.module_id,
+                    DisplayModule(obj.module_id),
                     rel_reloc
                 )
             })?;
@@ -610,7 +1168,7 @@ fn create_relocations(
         } else {
             &modules
                 .get(&rel_reloc.module_id)
-                .ok_or_else(|| anyhow!("Failed to locate module {}", rel_reloc.module_id))?
+                .ok_or_else(|| anyhow!("Failed to locate {}", DisplayModule(rel_reloc.module_id)))?
                 .obj
         };
 
@@ -620,8 +1178,8 @@ fn create_relocations(
             target_obj.sections.get_elf_index(rel_reloc.target_section as usize).ok_or_else(
                 || {
                     anyhow!(
-                        "Failed to locate module {} section {}",
-                        rel_reloc.module_id,
+                        "Failed to locate {} section {}",
+                        DisplayModule(rel_reloc.module_id),
                         rel_reloc.target_section
                     )
                 },
@@ -634,9 +1192,8 @@ fn create_relocations(
         )?
         else {
             bail!(
-                "Couldn't find module {} ({}) symbol in section {} at {:#010X}",
-                rel_reloc.module_id,
-                target_obj.name,
+                "Couldn't find {} symbol in section {} at {:#010X}",
+                DisplayModule(rel_reloc.module_id),
                 rel_reloc.target_section,
                 rel_reloc.addend
             );
@@ -661,12 +1218,92 @@ fn create_relocations(
     Ok(())
 }
 
+/// Creates an object defining every DOL symbol referenced by a module at its absolute address.
+fn import_stubs(obj: &ObjInfo, dol_obj: &ObjInfo) -> Result<Option<ObjInfo>> {
+    let mut symbol_indexes = BTreeSet::new();
+    for (_, section) in obj.sections.iter() {
+        for (_, reloc) in section.relocations.iter() {
+            if reloc.module == Some(0) {
+                symbol_indexes.insert(reloc.target_symbol);
+            }
+        }
+    }
+    if symbol_indexes.is_empty() {
+        return Ok(None);
+    }
+    let symbols = symbol_indexes
+        .into_iter()
+        .map(|symbol_index| {
+            let symbol = &dol_obj.symbols[symbol_index];
+            ObjSymbol {
+                name: symbol.name,
+                demangled_name: symbol.demangled_name,
+                address: symbol.address,
+                section: None,
+                size: symbol.size,
+                size_known: symbol.size_known,
+                flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
+                kind: symbol.kind,
+                ..Default::default()
+            }
+        })
+        .collect_vec();
+    let mut stubs = ObjInfo::new(
+        ObjKind::Relocatable,
+        ObjArchitecture::PowerPc,
+        format!("{}_dol_stubs", obj.name),
+        symbols,
+        vec![],
+    );
+    stubs.mw_comment = obj.mw_comment.clone();
+    Ok(Some(stubs))
+}
+
+/// Names the auto-named functions of `to` after identical functions in `from`,
+/// returning the number of functions renamed.
+fn share_symbol_names(from: &ObjInfo, to: &mut ObjInfo) -> Result<usize> {
+    let (from_fns, to_fns, matches) = match_objects(from, to)?;
+    let mut count = 0;
+    for m in matches.iter().filter(|m| m.method == MatchMethod::Exact) {
+        let from_address = from_fns[m.from].address;
+        let to_address = to_fns[m.to].address;
+        let Some((_, from_symbol)) = from
+            .symbols
+            .at_section_address(from_address.section, from_address.address)
+            .find(|(_, s)| s.kind == ObjSymbolKind::Function && !is_auto_symbol(s))
+        else {
+            continue;
+        };
+        if to.symbols.by_name(&from_symbol.name)?.is_some() {
+            continue;
+        }
+        let Some((to_index, to_symbol)) = to
+            .symbols
+            .at_section_address(to_address.section, to_address.address)
+            .find(|(_, s)| s.kind == ObjSymbolKind::Function)
+        else {
+            continue;
+        };
+        if !is_auto_symbol(to_symbol) {
+            continue;
+        }
+        let symbol = ObjSymbol {
+            name: from_symbol.name,
+            demangled_name: from_symbol.demangled_name,
+            ..to_symbol.clone()
+        };
+        to.symbols.replace(to_index, symbol)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
 fn resolve_external_relocations(
     obj: &mut ObjInfo,
     modules: &ModuleMapById<'_>,
     dol_obj: Option<&ObjInfo>,
 ) -> Result<()> {
-    log::debug!("Resolving relocations for module {}", obj.module_id);
+    log::debug!("Resolving relocations for {}", DisplayModule(obj.module_id));
 
     #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
     struct RelocRef {
@@ -683,22 +1320,22 @@ fn resolve_external_relocations(
                     hash_map::Entry::Occupied(e) => *e.get(),
                     hash_map::Entry::Vacant(e) => {
                         let target_obj = if module_id == obj.module_id {
-                            bail!("Relocation to self in module {}", obj.module_id)
+                            bail!("Relocation to self in {}", DisplayModule(obj.module_id))
                         } else if module_id == 0 {
                             dol_obj.unwrap()
                         } else {
                             &modules
                                 .get(&module_id)
                                 .ok_or_else(|| {
-                                    anyhow!("Failed to locate module {}", reloc.module.unwrap())
+                                    anyhow!("Failed to locate {}", DisplayModule(module_id))
                                 })?
                                 .obj
                         };
 
                         let target_symbol = &target_obj.symbols[reloc.target_symbol];
                         let symbol_idx = obj.symbols.add_direct(ObjSymbol {
-                            name: target_symbol.name.clone(),
-                            demangled_name: target_symbol.demangled_name.clone(),
+                            name: target_symbol.name,
+                            demangled_name: target_symbol.demangled_name,
                             ..Default::default()
                         })?;
 
@@ -716,65 +1353,136 @@ fn resolve_external_relocations(
     Ok(())
 }
 
-struct AnalyzeResult {
-    obj: ObjInfo,
-    dep: Vec<PathBuf>,
-    symbols_cache: Option<FileReadInfo>,
-    splits_cache: Option<FileReadInfo>,
+pub struct AnalyzeResult {
+    pub obj: ObjInfo,
+    pub dep: Vec<PathBuf>,
+    pub symbols_cache: Option<FileReadInfo>,
+    pub splits_cache: Option<FileReadInfo>,
+    pub timings: Timings,
 }
 
-fn load_analyze_dol(config: &ProjectConfig) -> Result<AnalyzeResult> {
-    log::debug!("Loading {}", config.base.object.display());
-    let mut obj = {
-        let file = map_file(&config.base.object)?;
-        if let Some(hash_str) = &config.base.hash {
-            verify_hash(file.as_slice(), hash_str)?;
+/// Path that analysis reads a configured signature set from.
+pub fn signature_set_path(config: &ProjectConfig, key: &str) -> Result<PathBuf> {
+    if let Some(cache) = &config.signature_cache {
+        return set_path(cache, key);
+    }
+    match config.signature_store.as_deref().map(SignatureStore::new) {
+        Some(SignatureStore::Local(dir)) => set_path(&dir, key),
+        Some(SignatureStore::Remote(url)) => {
+            bail!("Remote signature store '{}' requires a signature_cache", url)
         }
-        process_dol(file.as_slice(), config.base.name().as_ref())?
-    };
-    let mut dep = vec![config.base.object.clone()];
-
-    if let Some(comment_version) = config.mw_comment_version {
-        obj.mw_comment = Some(MWComment::new(comment_version)?);
+        None => bail!("signature_sets requires a signature_store"),
     }
+}
 
-    if let Some(map_path) = &config.base.map {
-        apply_map_file(map_path, &mut obj, config.common_start, config.mw_comment_version)?;
-        dep.push(map_path.clone());
+/// Applies the signature sets configured in `signature_sets`, adding their files to `dep`.
+fn apply_signature_sets(
+    config: &ProjectConfig,
+    obj: &mut ObjInfo,
+    dep: &mut Vec<PathBuf>,
+) -> Result<()> {
+    for key in &config.signature_sets {
+        let path = signature_set_path(config, key)?;
+        if config.signature_cache.is_some() && !path.is_file() {
+            bail!(
+                "Signature set '{}' not found; pull it with `dtk signatures pull -c <config>`",
+                path.display()
+            );
+        }
+        let set = read_pinned_set(&path)?;
+        let applied = apply_signature_set(obj, &set)?;
+        debug!("Applied {} signatures from {} to {}", applied, key, obj.name);
+        dep.push(path);
     }
+    Ok(())
+}
 
-    let splits_cache = if let Some(splits_path) = &config.base.splits {
-        dep.push(splits_path.clone());
+pub fn load_analyze_dol(config: &ProjectConfig, pipeline: &Pipeline) -> Result<AnalyzeResult> {
+    load_analyze_executable(config, &config.base, config.common_start, true, pipeline)
+}
+
+/// Loads and analyzes the main DOL or one of the additional `dols`.
+/// The selfile only applies to the main DOL.
+pub fn load_analyze_executable(
+    config: &ProjectConfig,
+    module_config: &ModuleConfig,
+    common_start: Option<u32>,
+    with_selfile: bool,
+    pipeline: &Pipeline,
+) -> Result<AnalyzeResult> {
+    log::debug!("Loading {}", module_config.object.display());
+    let mut timings = Timings::new();
+    let mut obj = {
+        let file = map_file(&module_config.object)?;
+        if let Some(hash_str) = &module_config.hash {
+            verify_hash(file.as_slice(), hash_str)?;
+        }
+        if is_rpl(file.as_slice()) {
+            let mut obj = process_elf_data(file.as_slice())?;
+            obj.name = module_config.name().to_string();
+            obj
+        } else {
+            process_dol(file.as_slice(), module_config.name().as_ref())?
+        }
+    };
+    let mut dep = vec![module_config.object.clone()];
+    obj.memory_ranges =
+        config.memory_ranges.clone().unwrap_or_else(|| config.platform.memory_ranges());
+    for (_, section) in obj.sections.iter() {
+        let last = section.address + section.size.max(1) - 1;
+        if !obj.is_valid_memory(section.address as u32) || !obj.is_valid_memory(last as u32) {
+            log::warn!(
+                "Section {} {:#010X}-{:#010X} is outside of the memory ranges",
+                section.name,
+                section.address,
+                section.address + section.size
+            );
+        }
+    }
+
+    if let Some(comment_version) = config.mw_comment_version {
+        let mut comment = MWComment::new(comment_version)?;
+        config.mw_comment.apply(&mut comment)?;
+        obj.mw_comment = Some(comment);
+    }
+
+    if let Some(map_path) = &module_config.map {
+        apply_map_file(map_path, &mut obj, common_start, config.mw_comment_version)?;
+        dep.push(map_path.clone());
+    }
+
+    let splits_cache = if let Some(splits_path) = &module_config.splits {
+        dep.push(splits_path.clone());
+        dep.extend(config_include_paths(splits_path)?);
         apply_splits_file(splits_path, &mut obj)?
     } else {
         None
     };
 
-    let symbols_cache = if let Some(symbols_path) = &config.base.symbols {
+    let symbols_cache = if let Some(symbols_path) = &module_config.symbols {
         dep.push(symbols_path.clone());
+        dep.extend(config_include_paths(symbols_path)?);
         apply_symbols_file(symbols_path, &mut obj)?
     } else {
         None
     };
 
+    if let Some(hints_path) = &module_config.hints {
+        apply_hints_file(hints_path, &mut obj)?;
+        dep.push(hints_path.clone());
+    }
+    apply_sda_bases(&mut obj, module_config.sda_base, module_config.sda2_base)?;
+    timings.record("load");
+
     if !config.symbols_known {
         // TODO move before symbols?
-        debug!("Performing signature analysis");
-        apply_signatures(&mut obj)?;
-
-        if !config.quick_analysis {
-            let mut state = AnalyzerState::default();
-            debug!("Detecting function boundaries");
-            FindSaveRestSleds::execute(&mut state, &obj)?;
-            state.detect_functions(&obj)?;
-            FindTRKInterruptVectorTable::execute(&mut state, &obj)?;
-            state.apply(&mut obj)?;
-        }
-
-        apply_signatures_post(&mut obj)?;
+        debug!("Performing signature analysis and detecting function boundaries");
+        pipeline.analyze(&mut obj, config.quick_analysis, &mut timings)?;
+        apply_signature_sets(config, &mut obj, &mut dep)?;
+        timings.record("signatures");
     }
 
-    if let Some(selfile) = &config.selfile {
+    if let Some(selfile) = config.selfile.as_ref().filter(|_| with_selfile) {
         log::info!("Loading {}", selfile.display());
         let file = map_file(selfile)?;
         if let Some(hash) = &config.selfile_hash {
@@ -786,8 +1494,9 @@ fn load_analyze_dol(config: &ProjectConfig) -> Result<AnalyzeResult> {
 
     // Create _ctors and _dtors symbols if missing
     update_ctors_dtors(&mut obj)?;
+    timings.record("load");
 
-    Ok(AnalyzeResult { obj, dep, symbols_cache, splits_cache })
+    Ok(AnalyzeResult { obj, dep, symbols_cache, splits_cache, timings })
 }
 
 fn split_write_obj(
@@ -796,33 +1505,42 @@ fn split_write_obj(
     base_dir: &Path,
     out_dir: &Path,
     no_update: bool,
+    dry_run: bool,
 ) -> Result<OutputModule> {
+    let timings = &mut module.timings;
+    timings.restart();
     debug!("Performing relocation analysis");
     let mut tracker = Tracker::new(&module.obj);
     tracker.process(&module.obj)?;
 
     debug!("Applying relocations");
     tracker.apply(&mut module.obj, false)?;
+    timings.record("relocations");
 
     if !config.symbols_known && config.detect_objects {
         debug!("Detecting object boundaries");
         detect_objects(&mut module.obj)?;
+        timings.record("objects");
     }
 
     if config.detect_strings {
         debug!("Detecting strings");
         detect_strings(&mut module.obj)?;
+        timings.record("strings");
+    }
+
+    if config.infer_alignment {
+        debug!("Inferring symbol alignment");
+        infer_alignment(&mut module.obj)?;
+        timings.record("alignment");
     }
 
     debug!("Adjusting splits");
     let module_id = module.obj.module_id;
-    update_splits(
-        &mut module.obj,
-        if module_id == 0 { config.common_start } else { None },
-        config.fill_gaps,
-    )?;
+    update_splits(&mut module.obj, module.common_start, config.fill_gaps)?;
+    timings.record("splits");
 
-    if !no_update {
+    if !no_update && !dry_run {
         debug!("Writing configuration");
         if let Some(symbols_path) = &module.config.symbols {
             write_symbols_file(symbols_path, &module.obj, module.symbols_cache)?;
@@ -830,26 +1548,36 @@ fn split_write_obj(
         if let Some(splits_path) = &module.config.splits {
             write_splits_file(splits_path, &module.obj, false, module.splits_cache)?;
         }
+        timings.record("write config");
     }
 
     debug!("Splitting {} objects", module.obj.link_order.len());
-    let split_objs = split_obj(&module.obj)?;
+    let mut split_objs = split_obj(&module.obj)?;
+    // Units with their own `comment` version get a fresh header
+    for out_obj in &mut split_objs {
+        if let Some(comment) = &mut out_obj.mw_comment {
+            config.mw_comment.apply(comment)?;
+        }
+    }
+    timings.record("split");
 
     debug!("Writing object files");
-    DirBuilder::new()
-        .recursive(true)
-        .create(out_dir)
-        .with_context(|| format!("Failed to create out dir '{}'", out_dir.display()))?;
+    if !dry_run {
+        DirBuilder::new()
+            .recursive(true)
+            .create(out_dir)
+            .with_context(|| format!("Failed to create out dir '{}'", out_dir.display()))?;
+    }
     let obj_dir = out_dir.join("obj");
     let entry = if module.obj.kind == ObjKind::Executable {
         module.obj.entry.and_then(|e| {
             let (section_index, _) = module.obj.sections.at_address(e as u32).ok()?;
             let symbols =
                 module.obj.symbols.at_section_address(section_index, e as u32).collect_vec();
-            best_match_for_reloc(symbols, ObjRelocKind::PpcRel24).map(|(_, s)| s.name.clone())
+            best_match_for_reloc(symbols, ObjRelocKind::PpcRel24).map(|(_, s)| s.name.to_string())
         })
     } else {
-        module.obj.symbols.by_name("_prolog")?.map(|(_, s)| s.name.clone())
+        module.obj.symbols.by_name("_prolog")?.map(|(_, s)| s.name.to_string())
     };
     let mut out_config = OutputModule {
         name: module.config.name().to_string(),
@@ -857,21 +1585,75 @@ fn split_write_obj(
         ldscript: out_dir.join("ldscript.lcf"),
         units: Vec::with_capacity(split_objs.len()),
         entry,
+        import_stubs: None,
+        archives: vec![],
     };
+    let mut out_objs = Vec::with_capacity(split_objs.len());
     for (unit, split_obj) in module.obj.link_order.iter().zip(&split_objs) {
         let out_obj = write_elf(split_obj)?;
         let out_path = obj_dir.join(obj_path_for_unit(&unit.name));
+        let (mut stripped_code, mut stripped_data) = (0, 0);
+        for symbol in split_obj.symbols.iter().filter(|s| is_strip_remnant_symbol(&s.name)) {
+            match symbol.section.map(|i| split_obj.sections[i].kind) {
+                Some(ObjSectionKind::Code) => stripped_code += symbol.size as u32,
+                Some(_) => stripped_data += symbol.size as u32,
+                None => {}
+            }
+        }
         out_config.units.push(OutputUnit {
             object: out_path.clone(),
             name: unit.name.clone(),
             autogenerated: unit.autogenerated,
-            code_size: split_obj.code_size(),
-            data_size: split_obj.data_size(),
+            gap: unit.autogenerated && is_gap_unit(&unit.name),
+            code_size: split_obj.code_size() - stripped_code,
+            data_size: split_obj.data_size() - stripped_data,
+            stripped_size: stripped_code + stripped_data,
+            compiler: unit.compiler.clone(),
+            cflags: unit.cflags.clone(),
+            lang: unit.lang,
+            source: source_path_for_unit(unit),
+            status: unit.status,
+            preset: unit.preset,
+            scratch: unit.scratch,
+            library: unit.library.clone(),
         });
+        if dry_run {
+            let changes = &mut module.changes;
+            changes.link_order.push(unit.name.clone());
+            if !out_path.is_file() {
+                changes.new_objects.push(unit.name.clone());
+            } else if !file_matches(&out_path, &out_obj)? {
+                changes.changed_objects.push(unit.name.clone());
+            }
+            for name in attributed_symbols(split_obj) {
+                changes.units.insert(name.to_string(), unit.name.clone());
+            }
+            continue;
+        }
         if let Some(parent) = out_path.parent() {
             DirBuilder::new().recursive(true).create(parent)?;
         }
         write_if_changed(&out_path, &out_obj)?;
+        out_objs.push(out_obj);
+    }
+    timings.record("write objects");
+
+    if config.write_archives && !dry_run {
+        debug!("Writing static libraries");
+        out_config.archives =
+            write_archives(&module.obj, &split_objs, &out_objs, &out_dir.join("lib"))?;
+        timings.record("archives");
+    }
+    if dry_run {
+        let ldscript_string = generate_ldscript(
+            &module.obj,
+            read_ldscript_template(module.config)?.as_deref(),
+            &module.config.force_active,
+        )?;
+        module.changes.module = out_config.name.clone();
+        module.changes.ldscript_changed = !out_config.ldscript.is_file()
+            || !file_matches(&out_config.ldscript, ldscript_string.as_bytes())?;
+        return Ok(out_config);
     }
 
     // Write extracted files
@@ -906,16 +1688,11 @@ fn split_write_obj(
     }
 
     // Generate ldscript.lcf
-    let ldscript_template = if let Some(template) = &module.config.ldscript_template {
-        Some(fs::read_to_string(template).with_context(|| {
-            format!("Failed to read linker script template '{}'", template.display())
-        })?)
-    } else {
-        None
-    };
+    let ldscript_template = read_ldscript_template(module.config)?;
     let ldscript_string =
         generate_ldscript(&module.obj, ldscript_template.as_deref(), &module.config.force_active)?;
     write_if_changed(&out_config.ldscript, ldscript_string.as_bytes())?;
+    timings.record("ldscript");
 
     if config.write_asm {
         debug!("Writing disassembly");
@@ -924,41 +1701,250 @@ fn split_write_obj(
             let out_path = asm_dir.join(asm_path_for_unit(&unit.name));
 
             let mut w = buf_writer(&out_path)?;
-            write_asm(&mut w, split_obj)
+            write_asm(&mut w, split_obj, &config.asm)
                 .with_context(|| format!("Failed to write {}", out_path.display()))?;
             w.flush()?;
         }
+        timings.record("asm");
+    }
+
+    if let Some(scaffold_dir) = &config.scaffold_dir {
+        debug!("Writing source scaffolding");
+        let asm_dir = out_dir.join("asm");
+        for (unit, split_obj) in module.obj.link_order.iter().zip(&split_objs) {
+            let Some(source_path) = source_path_for_unit(unit) else {
+                continue;
+            };
+            let out_path = scaffold_dir.join(source_path);
+            // Never overwrite existing sources
+            if out_path.exists() {
+                continue;
+            }
+            let asm_path = asm_dir.join(asm_path_for_unit(&unit.name));
+            let source = generate_scaffold(
+                &module.obj,
+                split_obj,
+                unit,
+                &asm_path.to_slash_lossy(),
+                config.scaffold_style,
+            )?;
+            if let Some(parent) = out_path.parent() {
+                DirBuilder::new().recursive(true).create(parent)?;
+            }
+            fs::write(&out_path, source)
+                .with_context(|| format!("Failed to write file '{}'", out_path.display()))?;
+        }
+        timings.record("scaffold");
     }
     Ok(out_config)
 }
 
-fn write_if_changed(path: &Path, contents: &[u8]) -> Result<()> {
-    if path.is_file() {
-        let old_file = map_file(path)?;
-        // If the file is the same size, check if the contents are the same
-        // Avoid writing if unchanged, since it will update the file's mtime
-        if old_file.len() == contents.len() as u64
-            && xxh3_64(old_file.as_slice()) == xxh3_64(contents)
+fn read_ldscript_template(module_config: &ModuleConfig) -> Result<Option<String>> {
+    let Some(template) = &module_config.ldscript_template else { return Ok(None) };
+    let text = fs::read_to_string(template).with_context(|| {
+        format!("Failed to read linker script template '{}'", template.display())
+    })?;
+    Ok(Some(text))
+}
+
+/// Packages the split objects of units with a `library` into `<library>.a` in `dir`, from the
+/// objects already written for them. Members are named by object path, in link order, and
+/// indexed by the global symbols they define. Archives of libraries that no longer exist are
+/// removed.
+fn write_archives(
+    obj: &ObjInfo,
+    split_objs: &[ObjInfo],
+    out_objs: &[Vec<u8>],
+    dir: &Path,
+) -> Result<Vec<OutputArchive>> {
+    let mut libraries = BTreeMap::<&str, Vec<(&ObjUnit, &ObjInfo, &[u8])>>::new();
+    for ((unit, split_obj), data) in obj.link_order.iter().zip(split_objs).zip(out_objs) {
+        if let Some(library) = &unit.library {
+            libraries.entry(library.as_str()).or_default().push((unit, split_obj, data));
+        }
+    }
+    let mut archives = Vec::with_capacity(libraries.len());
+    for (library, members) in libraries {
+        let mut identifiers = Vec::with_capacity(members.len());
+        let mut symbol_table = BTreeMap::new();
+        for (unit, split_obj, _) in &members {
+            let identifier = obj_path_for_unit(&unit.name).to_slash_lossy().as_bytes().to_vec();
+            let symbols = split_obj
+                .symbols
+                .iter()
+                .filter(|s| {
+                    s.section.is_some()
+                        && s.flags.is_global()
+                        && s.kind != ObjSymbolKind::Section
+                        && !s.name.is_empty()
+                })
+                .map(|s| s.name.as_bytes().to_vec())
+                .collect_vec();
+            symbol_table.insert(identifier.clone(), symbols);
+            identifiers.push(identifier);
+        }
+        let mut builder = ar::GnuBuilder::new_with_symbol_table(
+            Vec::new(),
+            true,
+            identifiers.clone(),
+            ar::GnuSymbolTableFormat::Size32,
+            symbol_table,
+        )?;
+        for (identifier, (_, _, data)) in identifiers.into_iter().zip(&members) {
+            builder.append(&ar::Header::new(identifier, data.len() as u64), *data)?;
+        }
+        let path = dir.join(format!("{library}.a"));
+        DirBuilder::new().recursive(true).create(dir)?;
+        write_if_changed(&path, &builder.into_inner()?)?;
+        archives.push(OutputArchive {
+            name: library.to_string(),
+            path,
+            units: members.iter().map(|(unit, _, _)| unit.name.clone()).collect(),
+        });
+    }
+
+    if dir.is_dir() {
+        for entry in fs::read_dir(dir)
+            .with_context(|| format!("Failed to read directory '{}'", dir.display()))?
         {
-            return Ok(());
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "a")
+                && path.is_file()
+                && !archives.iter().any(|a| a.path == path)
+            {
+                debug!("Removing stale archive '{}'", path.display());
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove '{}'", path.display()))?;
+            }
+        }
+    }
+    Ok(archives)
+}
+
+/// Names of the symbols an object defines, for tracking which unit a symbol is split into.
+fn attributed_symbols(obj: &ObjInfo) -> impl Iterator<Item = &str> {
+    obj.symbols
+        .iter()
+        .filter(|s| s.section.is_some() && s.kind != ObjSymbolKind::Section && !s.name.is_empty())
+        .map(|s| s.name.as_str())
+}
+
+/// Finds the units removed and the symbols moved since the previous split.
+fn diff_previous_output(
+    changes: &mut ModuleChanges,
+    previous: Option<&OutputModule>,
+) -> Result<()> {
+    let Some(previous) = previous else { return Ok(()) };
+    // Symbols defined by several units can't be tracked
+    let mut old_units = HashMap::<String, Option<String>>::new();
+    for unit in &previous.units {
+        if !changes.link_order.contains(&unit.name) {
+            changes.removed_objects.push(unit.name.clone());
+        }
+        if !unit.object.is_file() {
+            continue;
+        }
+        let file = map_file(&unit.object)?;
+        let obj = process_elf_data(file.as_slice())
+            .with_context(|| format!("While reading '{}'", unit.object.display()))?;
+        for name in attributed_symbols(&obj) {
+            match old_units.entry(name.to_string()) {
+                hash_map::Entry::Vacant(e) => {
+                    e.insert(Some(unit.name.clone()));
+                }
+                hash_map::Entry::Occupied(mut e) => {
+                    if e.get().as_ref() != Some(&unit.name) {
+                        e.insert(None);
+                    }
+                }
+            }
+        }
+    }
+    for (name, unit) in &changes.units {
+        if let Some(Some(old_unit)) = old_units.get(name) {
+            if old_unit != unit {
+                changes.moved_symbols.push(MovedSymbol {
+                    name: name.clone(),
+                    from: old_unit.clone(),
+                    to: unit.clone(),
+                });
+            }
+        }
+    }
+    changes.moved_symbols.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(())
+}
+
+fn print_dry_run(changes: &[&ModuleChanges], new_warnings: Vec<String>) -> Result<()> {
+    if is_json() {
+        return print_json(&DryRunReport { modules: changes.to_vec(), new_warnings });
+    }
+    for changes in changes {
+        let lists = [
+            ("New", &changes.new_objects),
+            ("Changed", &changes.changed_objects),
+            ("Removed", &changes.removed_objects),
+        ];
+        if lists.iter().all(|(_, l)| l.is_empty())
+            && changes.moved_symbols.is_empty()
+            && !changes.ldscript_changed
+        {
+            continue;
+        }
+        println!("{}:", changes.module);
+        for (label, units) in lists {
+            for unit in units {
+                println!("  {label}: {unit}");
+            }
+        }
+        for symbol in &changes.moved_symbols {
+            println!("  Moved: {} ({} -> {})", symbol.name, symbol.from, symbol.to);
+        }
+        if changes.ldscript_changed {
+            println!("  Changed: ldscript.lcf");
         }
     }
+    for warning in &new_warnings {
+        println!("New warning: {warning}");
+    }
+    Ok(())
+}
+
+/// Whether a file's contents equal `contents`.
+fn file_matches(path: &Path, contents: &[u8]) -> Result<bool> {
+    let old_file = map_file(path)?;
+    Ok(old_file.len() == contents.len() as u64 && xxh3_64(old_file.as_slice()) == xxh3_64(contents))
+}
+
+fn write_if_changed(path: &Path, contents: &[u8]) -> Result<()> {
+    // Avoid writing if unchanged, since it will update the file's mtime
+    if path.is_file() && file_matches(path, contents)? {
+        return Ok(());
+    }
     fs::write(path, contents)
         .with_context(|| format!("Failed to write file '{}'", path.display()))?;
     Ok(())
 }
 
-fn load_analyze_rel(config: &ProjectConfig, module_config: &ModuleConfig) -> Result<AnalyzeResult> {
+pub fn load_analyze_rel(
+    config: &ProjectConfig,
+    module_config: &ModuleConfig,
+    pipeline: &Pipeline,
+) -> Result<AnalyzeResult> {
     debug!("Loading {}", module_config.object.display());
+    let mut timings = Timings::new();
     let file = map_file(&module_config.object)?;
     if let Some(hash_str) = &module_config.hash {
         verify_hash(file.as_slice(), hash_str)?;
     }
     let (header, mut module_obj) =
         process_rel(&mut Cursor::new(file.as_slice()), module_config.name().as_ref())?;
+    set_module_name(header.module_id, module_config.file_name(), false);
 
     if let Some(comment_version) = config.mw_comment_version {
-        module_obj.mw_comment = Some(MWComment::new(comment_version)?);
+        let mut comment = MWComment::new(comment_version)?;
+        config.mw_comment.apply(&mut comment)?;
+        module_obj.mw_comment = Some(comment);
     }
 
     let mut dep = vec![module_config.object.clone()];
@@ -969,6 +1955,7 @@ fn load_analyze_rel(config: &ProjectConfig, module_config: &ModuleConfig) -> Res
 
     let splits_cache = if let Some(splits_path) = &module_config.splits {
         dep.push(splits_path.clone());
+        dep.extend(config_include_paths(splits_path)?);
         apply_splits_file(splits_path, &mut module_obj)?
     } else {
         None
@@ -976,22 +1963,23 @@ fn load_analyze_rel(config: &ProjectConfig, module_config: &ModuleConfig) -> Res
 
     let symbols_cache = if let Some(symbols_path) = &module_config.symbols {
         dep.push(symbols_path.clone());
+        dep.extend(config_include_paths(symbols_path)?);
         apply_symbols_file(symbols_path, &mut module_obj)?
     } else {
         None
     };
 
+    if let Some(hints_path) = &module_config.hints {
+        apply_hints_file(hints_path, &mut module_obj)?;
+        dep.push(hints_path.clone());
+    }
+    timings.record("load");
+
     if !config.symbols_known {
-        debug!("Analyzing module {}", module_obj.module_id);
-        if !config.quick_analysis {
-            let mut state = AnalyzerState::default();
-            state.detect_functions(&module_obj)?;
-            FindRelCtorsDtors::execute(&mut state, &module_obj)?;
-            FindRelRodataData::execute(&mut state, &module_obj)?;
-            state.apply(&mut module_obj)?;
-        }
-        apply_signatures(&mut module_obj)?;
-        apply_signatures_post(&mut module_obj)?;
+        debug!("Analyzing {}", DisplayModule(module_obj.module_id));
+        pipeline.analyze(&mut module_obj, config.quick_analysis, &mut timings)?;
+        apply_signature_sets(config, &mut module_obj, &mut dep)?;
+        timings.record("signatures");
     }
 
     // Create _ctors and _dtors symbols if missing
@@ -999,21 +1987,68 @@ fn load_analyze_rel(config: &ProjectConfig, module_config: &ModuleConfig) -> Res
 
     // Determine REL section alignment
     update_rel_section_alignment(&mut module_obj, &header)?;
+    timings.record("load");
 
-    Ok(AnalyzeResult { obj: module_obj, dep, symbols_cache, splits_cache })
+    Ok(AnalyzeResult { obj: module_obj, dep, symbols_cache, splits_cache, timings })
 }
 
-fn split(args: SplitArgs) -> Result<()> {
+fn split(args: SplitArgs, pipeline: &Pipeline) -> Result<()> {
     if let Some(jobs) = args.jobs {
-        rayon::ThreadPoolBuilder::new().num_threads(jobs).build_global().unwrap();
+        if rayon::ThreadPoolBuilder::new().num_threads(jobs).build_global().is_err() {
+            log::warn!("Thread count already set, ignoring -j {}", jobs);
+        }
+    }
+    if !args.watch {
+        split_once(&args)?;
+        return Ok(());
+    }
+
+    let mut watched = vec![args.config.clone()];
+    loop {
+        match split_once(&args) {
+            Ok(dependencies) => {
+                watched = dependencies;
+                watched.push(args.config.clone());
+            }
+            // Keep watching the previous inputs, so the error can be fixed
+            Err(e) => log::error!("{e:?}"),
+        }
+        info!("Watching {} files for changes", watched.iter().unique().count());
+        wait_for_changes(&watched);
+    }
+}
+
+/// Blocks until any of the given files are modified, created or removed.
+pub fn wait_for_changes(paths: &[PathBuf]) {
+    fn mtimes(paths: &[PathBuf]) -> Vec<Option<FileTime>> {
+        paths
+            .iter()
+            .map(|p| fs::metadata(p).ok().map(|m| FileTime::from_last_modification_time(&m)))
+            .collect()
     }
 
+    let initial = mtimes(paths);
+    loop {
+        thread::sleep(Duration::from_millis(500));
+        if mtimes(paths) != initial {
+            // Wait for editors to finish writing
+            thread::sleep(Duration::from_millis(100));
+            return;
+        }
+    }
+}
+
+/// Runs the split once, returning the input files it depends on.
+fn split_once(args: &SplitArgs) -> Result<Vec<PathBuf>> {
     let command_start = Instant::now();
+    // Only report warnings from this run
+    take_warnings();
     info!("Loading {}", args.config.display());
     let mut config: ProjectConfig = {
         let mut config_file = buf_reader(&args.config)?;
         serde_yaml::from_reader(&mut config_file)?
     };
+    config.register_module_names();
 
     for module_config in config.modules.iter_mut() {
         let file = map_file(&module_config.object)?;
@@ -1027,7 +2062,7 @@ fn split(args: SplitArgs) -> Result<()> {
     let out_config_path = args.out_dir.join("config.json");
     let mut dep = DepFile::new(out_config_path.clone());
 
-    let module_count = config.modules.len() + 1;
+    let module_count = config.modules.len() + config.dols.len() + 1;
     let num_threads = min(rayon::current_num_threads(), module_count);
     info!(
         "Loading and analyzing {} module{} (using {} thread{})",
@@ -1038,15 +2073,18 @@ fn split(args: SplitArgs) -> Result<()> {
     );
     let mut dol_result: Option<Result<AnalyzeResult>> = None;
     let mut modules_result: Option<Result<Vec<AnalyzeResult>>> = None;
+    let mut dols_result: Option<Result<Vec<AnalyzeResult>>> = None;
     let start = Instant::now();
+    let progress = item_progress(module_count as u64, "Analyzing");
     rayon::scope(|s| {
         // DOL
         s.spawn(|_| {
             let _span = info_span!("module", name = %config.base.name()).entered();
             dol_result =
-                Some(load_analyze_dol(&config).with_context(|| {
+                Some(load_analyze_dol(&config, pipeline).with_context(|| {
                     format!("While loading object '{}'", config.base.file_name())
                 }));
+            progress.inc(1);
         });
         // Modules
         s.spawn(|_| {
@@ -1056,14 +2094,42 @@ fn split(args: SplitArgs) -> Result<()> {
                     .par_iter()
                     .map(|module_config| {
                         let _span = info_span!("module", name = %module_config.name()).entered();
-                        load_analyze_rel(&config, module_config).with_context(|| {
-                            format!("While loading object '{}'", module_config.file_name())
-                        })
+                        let result = load_analyze_rel(&config, module_config, pipeline)
+                            .with_context(|| {
+                                format!("While loading object '{}'", module_config.file_name())
+                            });
+                        progress.inc(1);
+                        result
+                    })
+                    .collect(),
+            );
+        });
+        // Additional DOLs
+        s.spawn(|_| {
+            dols_result = Some(
+                config
+                    .dols
+                    .par_iter()
+                    .map(|dol_config| {
+                        let _span = info_span!("module", name = %dol_config.base.name()).entered();
+                        let result = load_analyze_executable(
+                            &config,
+                            &dol_config.base,
+                            dol_config.common_start,
+                            false,
+                            pipeline,
+                        )
+                        .with_context(|| {
+                            format!("While loading object '{}'", dol_config.base.file_name())
+                        });
+                        progress.inc(1);
+                        result
                     })
                     .collect(),
             );
         });
     });
+    progress.finish_and_clear();
     let duration = start.elapsed();
     let mut dol = {
         let result = dol_result.unwrap()?;
@@ -1073,10 +2139,28 @@ fn split(args: SplitArgs) -> Result<()> {
             config: &config.base,
             symbols_cache: result.symbols_cache,
             splits_cache: result.splits_cache,
+            common_start: config.common_start,
+            timings: result.timings,
+            changes: Default::default(),
         }
     };
     let mut function_count = dol.obj.symbols.by_kind(ObjSymbolKind::Function).count();
 
+    let mut dols = Vec::<ModuleInfo>::with_capacity(config.dols.len());
+    for (idx, result) in dols_result.unwrap()?.into_iter().enumerate() {
+        function_count += result.obj.symbols.by_kind(ObjSymbolKind::Function).count();
+        dep.extend(result.dep);
+        dols.push(ModuleInfo {
+            obj: result.obj,
+            config: &config.dols[idx].base,
+            symbols_cache: result.symbols_cache,
+            splits_cache: result.splits_cache,
+            common_start: config.dols[idx].common_start,
+            timings: result.timings,
+            changes: Default::default(),
+        });
+    }
+
     let mut modules = ModuleMapByName::new();
     for (idx, result) in modules_result.unwrap()?.into_iter().enumerate() {
         function_count += result.obj.symbols.by_kind(ObjSymbolKind::Function).count();
@@ -1087,10 +2171,21 @@ fn split(args: SplitArgs) -> Result<()> {
                 config: &config.modules[idx],
                 symbols_cache: result.symbols_cache,
                 splits_cache: result.splits_cache,
+                common_start: None,
+                timings: result.timings,
+                changes: Default::default(),
             }),
             Entry::Occupied(_) => bail!("Duplicate module name {}", result.obj.name),
         };
     }
+    for other in &dols {
+        let name = other.config.name();
+        ensure!(
+            name != config.base.name() && !modules.values().any(|m| m.config.name() == name),
+            "Duplicate module name {}",
+            name
+        );
+    }
     info!(
         "Initial analysis completed in {}.{:03}s (found {} functions)",
         duration.as_secs(),
@@ -1138,6 +2233,7 @@ fn split(args: SplitArgs) -> Result<()> {
         Ok(map)
     }
 
+    let mut import_stubs_objs = BTreeMap::<String, ObjInfo>::new();
     if !modules.is_empty() {
         let module_names = modules.keys().cloned().collect_vec();
 
@@ -1158,6 +2254,15 @@ fn split(args: SplitArgs) -> Result<()> {
             modules.insert(module_name.clone(), module);
         }
 
+        // Collect DOL imports before they're replaced with extern symbols
+        if config.write_import_stubs {
+            for module in modules.values() {
+                if let Some(stubs) = import_stubs(&module.obj, &dol.obj)? {
+                    import_stubs_objs.insert(module.config.name().into_owned(), stubs);
+                }
+            }
+        }
+
         // Replace external relocations with internal ones, creating extern symbols
         for module_name in &module_names {
             let mut module = modules.remove(module_name).unwrap();
@@ -1167,32 +2272,51 @@ fn split(args: SplitArgs) -> Result<()> {
         }
     }
 
+    // Share names of identical functions between the DOLs
+    for other in &mut dols {
+        let count = share_symbol_names(&dol.obj, &mut other.obj)?
+            + share_symbol_names(&other.obj, &mut dol.obj)?;
+        debug!("Shared {} function names with {}", count, other.config.name());
+    }
+
     // Create out dirs
-    DirBuilder::new().recursive(true).create(&args.out_dir)?;
-    touch(&args.out_dir)?;
-    let include_dir = args.out_dir.join("include");
-    DirBuilder::new().recursive(true).create(&include_dir)?;
-    fs::write(include_dir.join("macros.inc"), include_str!("../../assets/macros.inc"))?;
+    if !args.dry_run {
+        DirBuilder::new().recursive(true).create(&args.out_dir)?;
+        touch(&args.out_dir)?;
+        let include_dir = args.out_dir.join("include");
+        DirBuilder::new().recursive(true).create(&include_dir)?;
+        fs::write(include_dir.join("macros.inc"), include_str!("../../assets/macros.inc"))?;
+    }
 
     info!("Rebuilding relocations and splitting");
     let mut dol_result: Option<Result<OutputModule>> = None;
     let mut modules_result: Option<Result<Vec<OutputModule>>> = None;
+    let mut dols_result: Option<Result<Vec<OutputModule>>> = None;
     let start = Instant::now();
+    let progress = item_progress((modules.len() + dols.len()) as u64 + 1, "Splitting");
     rayon::scope(|s| {
         // DOL
         s.spawn(|_| {
             let _span =
                 info_span!("module", name = %config.base.name(), id = dol.obj.module_id).entered();
             dol_result = Some(
-                split_write_obj(&mut dol, &config, &args.out_dir, &args.out_dir, args.no_update)
-                    .with_context(|| {
-                        format!(
-                            "While processing object '{}' (module ID {})",
-                            config.base.file_name(),
-                            dol.obj.module_id
-                        )
-                    }),
+                split_write_obj(
+                    &mut dol,
+                    &config,
+                    &args.out_dir,
+                    &args.out_dir,
+                    args.no_update,
+                    args.dry_run,
+                )
+                .with_context(|| {
+                    format!(
+                        "While processing object '{}' (module ID {})",
+                        config.base.file_name(),
+                        dol.obj.module_id
+                    )
+                }),
             );
+            progress.inc(1);
         });
         // Modules
         s.spawn(|_| {
@@ -1204,7 +2328,7 @@ fn split(args: SplitArgs) -> Result<()> {
                             info_span!("module", name = %module.config.name(), id = module.obj.module_id)
                                 .entered();
                         let out_dir = args.out_dir.join(module.config.name().as_ref());
-                        split_write_obj(module, &config, &args.out_dir, &out_dir, args.no_update).with_context(
+                        let result = split_write_obj(module, &config, &args.out_dir, &out_dir, args.no_update, args.dry_run).with_context(
                             || {
                                 format!(
                                     "While processing object '{}' (module {} ID {})",
@@ -1213,14 +2337,87 @@ fn split(args: SplitArgs) -> Result<()> {
                                     module.obj.module_id
                                 )
                             },
+                        );
+                        progress.inc(1);
+                        result
+                    })
+                    .collect(),
+            );
+        });
+        // Additional DOLs
+        s.spawn(|_| {
+            dols_result = Some(
+                dols.par_iter_mut()
+                    .map(|module| {
+                        let _span = info_span!("module", name = %module.config.name()).entered();
+                        let out_dir = args.out_dir.join(module.config.name().as_ref());
+                        let result = split_write_obj(
+                            module,
+                            &config,
+                            &args.out_dir,
+                            &out_dir,
+                            args.no_update,
+                            args.dry_run,
                         )
+                        .with_context(|| {
+                            format!("While processing object '{}'", module.config.file_name())
+                        });
+                        progress.inc(1);
+                        result
                     })
                     .collect(),
             );
         });
     });
+    progress.finish_and_clear();
     let duration = start.elapsed();
     let mut modules_config = modules_result.unwrap()?;
+    let warnings_path = args.out_dir.join("warnings.txt");
+    if args.dry_run {
+        dol_result.unwrap()?;
+        dols_result.unwrap()?;
+        // Compare against the previous run's output
+        let previous = if out_config_path.is_file() {
+            let file = buf_reader(&out_config_path)?;
+            let previous: OutputConfig = serde_json::from_reader(file)
+                .with_context(|| format!("While reading '{}'", out_config_path.display()))?;
+            Some(previous)
+        } else {
+            None
+        };
+        let all_modules = std::iter::once(&mut dol).chain(modules.values_mut()).chain(&mut dols);
+        for module in all_modules {
+            let changes = &mut module.changes;
+            let previous_module = previous.as_ref().and_then(|p| {
+                std::iter::once(&p.base)
+                    .chain(&p.modules)
+                    .chain(&p.dols)
+                    .find(|m| m.name == changes.module)
+            });
+            diff_previous_output(changes, previous_module)?;
+        }
+        let old_warnings = fs::read_to_string(&warnings_path).unwrap_or_default();
+        let old_warnings = old_warnings.lines().collect::<HashSet<_>>();
+        let new_warnings = take_warnings()
+            .into_iter()
+            .filter(|w| !old_warnings.contains(w.as_str()))
+            .unique()
+            .collect();
+        let changes = std::iter::once(&dol)
+            .chain(modules.values())
+            .chain(&dols)
+            .map(|m| &m.changes)
+            .collect_vec();
+        print_dry_run(&changes, new_warnings)?;
+        return Ok(dep.dependencies);
+    }
+    for module_config in &mut modules_config {
+        if let Some(stubs) = import_stubs_objs.get(&module_config.name) {
+            let out_path = args.out_dir.join(&module_config.name).join("dol_stubs.o");
+            write_if_changed(&out_path, &write_elf(stubs)?)?;
+            module_config.import_stubs = Some(out_path);
+        }
+    }
     modules_config.sort_by(|a, b| {
         // Sort by module ID, then name
         a.module_id.cmp(&b.module_id).then(a.name.cmp(&b.name))
@@ -1229,10 +2426,11 @@ fn split(args: SplitArgs) -> Result<()> {
         version: env!("CARGO_PKG_VERSION").to_string(),
         base: dol_result.unwrap()?,
         modules: modules_config,
+        dols: dols_result.unwrap()?,
         links: vec![],
     };
     let mut object_count = out_config.base.units.len();
-    for module in &out_config.modules {
+    for module in out_config.modules.iter().chain(&out_config.dols) {
         object_count += module.units.len();
     }
     info!(
@@ -1261,6 +2459,13 @@ fn split(args: SplitArgs) -> Result<()> {
         out_file.flush()?;
     }
 
+    // Keep this run's warnings, so a dry run can tell which are new
+    let mut warnings = take_warnings().into_iter().unique().join("\n");
+    if !warnings.is_empty() {
+        warnings.push('\n');
+    }
+    write_if_changed(&warnings_path, warnings.as_bytes())?;
+
     // Write dep file
     {
         let dep_path = args.out_dir.join("dep");
@@ -1274,8 +2479,35 @@ fn split(args: SplitArgs) -> Result<()> {
     //     validate(&obj, file, &state)?;
     // }
 
+    if args.timings {
+        let all_modules = std::iter::once(&dol).chain(modules.values()).chain(&dols);
+        for module in all_modules {
+            print_timings(module.config.file_name().as_ref(), &module.timings)?;
+        }
+    }
+
     let duration = command_start.elapsed();
     info!("Total time: {}.{:03}s", duration.as_secs(), duration.subsec_millis());
+    Ok(dep.dependencies)
+}
+
+fn print_timings(module: &str, timings: &Timings) -> Result<()> {
+    if is_json() {
+        let stages = timings
+            .stages
+            .iter()
+            .map(|(name, d)| serde_json::json!({ "stage": name, "seconds": d.as_secs_f64() }))
+            .collect_vec();
+        return print_json(&serde_json::json!({
+            "module": module,
+            "seconds": timings.total().as_secs_f64(),
+            "stages": stages,
+        }));
+    }
+    println!("{}: {:.3}s", module, timings.total().as_secs_f64());
+    for (name, duration) in &timings.stages {
+        println!("  {:<20} {:>8.3}s", name, duration.as_secs_f64());
+    }
     Ok(())
 }
 
@@ -1302,11 +2534,11 @@ where P: AsRef<Path> {
             );
         }
     }
-    let mut real_functions = BTreeMap::<SectionAddress, String>::new();
+    let mut real_functions = BTreeMap::<SectionAddress, SymbolName>::new();
     for (section_index, _section) in real_obj.sections.by_kind(ObjSectionKind::Code) {
         for (_symbol_idx, symbol) in real_obj.symbols.for_section(section_index) {
             let symbol_addr = SectionAddress::new(section_index, symbol.address as u32);
-            real_functions.insert(symbol_addr, symbol.name.clone());
+            real_functions.insert(symbol_addr, symbol.name);
             match state.functions.get(&symbol_addr) {
                 Some(info) => {
                     if let Some(end) = info.end {
@@ -1434,6 +2666,7 @@ fn diff(args: DiffArgs) -> Result<()> {
     log::info!("Loading {}", args.config.display());
     let mut config_file = buf_reader(&args.config)?;
     let config: ProjectConfig = serde_yaml::from_reader(&mut config_file)?;
+    config.register_module_names();
 
     log::info!("Loading {}", config.base.object.display());
     let mut obj = {
@@ -1472,7 +2705,7 @@ fn diff(args: DiffArgs) -> Result<()> {
             });
         let mut found = false;
         if let Some((_, linked_sym)) = linked_sym {
-            if linked_sym.name.starts_with(&orig_sym.name) {
+            if linked_sym.name.starts_with(orig_sym.name.as_str()) {
                 if linked_sym.size != orig_sym.size &&
                     // TODO validate common symbol sizes
                     // (need to account for inflation bug)
@@ -1584,6 +2817,7 @@ fn apply(args: ApplyArgs) -> Result<()> {
     log::info!("Loading {}", args.config.display());
     let mut config_file = buf_reader(&args.config)?;
     let config: ProjectConfig = serde_yaml::from_reader(&mut config_file)?;
+    config.register_module_names();
 
     log::info!("Loading {}", config.base.object.display());
     let mut obj = {
@@ -1626,7 +2860,7 @@ fn apply(args: ApplyArgs) -> Result<()> {
         if let Some((_, linked_sym)) = linked_sym {
             let mut updated_sym = orig_sym.clone();
             let is_globalized = linked_sym.name.ends_with(&format!("_{:08X}", linked_sym.address));
-            if (is_globalized && !linked_sym.name.starts_with(&orig_sym.name))
+            if (is_globalized && !linked_sym.name.starts_with(orig_sym.name.as_str()))
                 || (!is_globalized && linked_sym.name != orig_sym.name)
             {
                 log::info!(
@@ -1635,7 +2869,7 @@ fn apply(args: ApplyArgs) -> Result<()> {
                     orig_sym.kind,
                     linked_sym.name
                 );
-                updated_sym.name = linked_sym.name.clone();
+                updated_sym.name = linked_sym.name;
             }
             if linked_sym.size != orig_sym.size {
                 log::info!(
@@ -1707,8 +2941,8 @@ fn apply(args: ApplyArgs) -> Result<()> {
                 linked_sym.address
             );
             obj.symbols.add_direct(ObjSymbol {
-                name: linked_sym.name.clone(),
-                demangled_name: linked_sym.demangled_name.clone(),
+                name: linked_sym.name,
+                demangled_name: linked_sym.demangled_name,
                 address: linked_sym.address,
                 section: Some(orig_section_index),
                 size: linked_sym.size,
@@ -1719,6 +2953,7 @@ fn apply(args: ApplyArgs) -> Result<()> {
                 data_kind: linked_sym.data_kind,
                 name_hash: linked_sym.name_hash,
                 demangled_name_hash: linked_sym.demangled_name_hash,
+                source: linked_sym.source,
             })?;
         }
     }
@@ -1738,31 +2973,767 @@ fn apply(args: ApplyArgs) -> Result<()> {
     Ok(())
 }
 
+/// Finds where each section of an ELF was loaded in the DOL, by content, returning the DOL
+/// section index and address delta for each ELF section. BSS sections, which have no content,
+/// use the delta shared by most of the other sections.
+fn correlate_elf_sections(obj: &ObjInfo, elf_obj: &ObjInfo) -> Vec<Option<(usize, i64)>> {
+    let mut result = vec![None; elf_obj.sections.count()];
+    for (elf_index, elf_section) in elf_obj.sections.iter() {
+        if elf_section.kind == ObjSectionKind::Bss {
+            continue;
+        }
+        let data = elf_section.data.as_slice();
+        // Same address first, then anywhere with identical content
+        let same_address = obj.sections.iter().find(|(_, s)| {
+            s.kind != ObjSectionKind::Bss
+                && s.address <= elf_section.address
+                && elf_section.address + elf_section.size <= s.address + s.size
+                && s.data_range(
+                    elf_section.address as u32,
+                    (elf_section.address + elf_section.size) as u32,
+                )
+                .ok()
+                    == Some(data)
+        });
+        let found = same_address.map(|(index, s)| (index, s.address as i64)).or_else(|| {
+            obj.sections
+                .iter()
+                .filter(|(_, s)| s.kind != ObjSectionKind::Bss)
+                .flat_map(|(index, s)| {
+                    memchr::memmem::find_iter(&s.data, data)
+                        .map(move |offset| (index, (s.address + offset as u64) as i64))
+                })
+                .exactly_one()
+                .ok()
+        });
+        match found {
+            Some((index, address)) => {
+                result[elf_index] = Some((index, address - elf_section.address as i64))
+            }
+            None => log::warn!(
+                "Section {} ({:#X} bytes) not found in {}",
+                elf_section.name,
+                elf_section.size,
+                obj.name
+            ),
+        }
+    }
+
+    let delta = result.iter().flatten().map(|&(_, delta)| delta).counts();
+    let delta = delta.into_iter().max_by_key(|&(_, count)| count).map(|(delta, _)| delta);
+    for (elf_index, elf_section) in elf_obj.sections.iter() {
+        if elf_section.kind != ObjSectionKind::Bss {
+            continue;
+        }
+        let Some(delta) = delta else { break };
+        let start = (elf_section.address as i64 + delta) as u64;
+        result[elf_index] = obj
+            .sections
+            .iter()
+            .find(|(_, s)| {
+                s.kind == ObjSectionKind::Bss
+                    && s.address <= start
+                    && start + elf_section.size <= s.address + s.size
+            })
+            .map(|(index, _)| (index, delta));
+        if result[elf_index].is_none() {
+            log::warn!("Section {} not found in {}", elf_section.name, obj.name);
+        }
+    }
+    result
+}
+
+fn import_elf(args: ImportElfArgs) -> Result<()> {
+    log::info!("Loading {}", args.config.display());
+    let mut config_file = buf_reader(&args.config)?;
+    let config: ProjectConfig = serde_yaml::from_reader(&mut config_file)?;
+    config.register_module_names();
+
+    log::info!("Loading {}", config.base.object.display());
+    let mut obj = {
+        let file = map_file(&config.base.object)?;
+        if let Some(hash_str) = &config.base.hash {
+            verify_hash(file.as_slice(), hash_str)?;
+        }
+        process_dol(file.as_slice(), config.base.name().as_ref())?
+    };
+
+    let Some(symbols_path) = &config.base.symbols else {
+        bail!("No symbols file specified in config");
+    };
+    let splits_path = if args.no_splits {
+        None
+    } else {
+        let Some(splits_path) = &config.base.splits else {
+            bail!("No splits file specified in config");
+        };
+        Some(splits_path)
+    };
+    let symbols_cache = apply_symbols_file(symbols_path, &mut obj)?;
+    let splits_cache = match splits_path {
+        Some(splits_path) => apply_splits_file(splits_path, &mut obj)?,
+        None => None,
+    };
+
+    log::info!("Loading {}", args.elf_file.display());
+    let elf_obj = process_elf(&args.elf_file)?;
+    ensure!(elf_obj.kind == ObjKind::Executable, "Expected a linked ELF");
+    let section_map = correlate_elf_sections(&obj, &elf_obj);
+
+    // Symbols, with their sizes
+    let mut symbol_count = 0;
+    for elf_sym in elf_obj.symbols.iter() {
+        let Some(elf_section_index) = elf_sym.section else { continue };
+        if elf_sym.kind == ObjSymbolKind::Section
+            || elf_sym.name.is_empty()
+            || is_linker_generated_object(&elf_sym.name)
+        {
+            continue;
+        }
+        let Some((section_index, delta)) = section_map[elf_section_index] else { continue };
+        let address = (elf_sym.address as i64 + delta) as u64;
+        if !obj.sections[section_index].contains(address as u32) {
+            continue;
+        }
+        obj.add_symbol(
+            ObjSymbol { address, section: Some(section_index), ..elf_sym.clone() },
+            args.force,
+        )?;
+        symbol_count += 1;
+    }
+
+    // File boundaries
+    let mut split_count = 0;
+    if splits_path.is_some() {
+        let mut elf_splits = elf_obj.sections.all_splits().peekable();
+        while let Some((elf_section_index, elf_section, elf_start, split)) = elf_splits.next() {
+            let elf_end = match elf_splits.peek() {
+                Some(&(next_index, _, next_start, _)) if next_index == elf_section_index => {
+                    next_start
+                }
+                _ => (elf_section.address + elf_section.size) as u32,
+            };
+            let Some((section_index, delta)) = section_map[elf_section_index] else { continue };
+            let start = (elf_start as i64 + delta) as u32;
+            let end = (elf_end as i64 + delta) as u32;
+            if start >= end {
+                continue;
+            }
+            let section = &obj.sections[section_index];
+            if let Some((_, existing)) = section
+                .splits
+                .for_range(..end)
+                .find(|(_, s)| (s.end == 0 || s.end > start) && s.unit != split.unit)
+            {
+                log::warn!(
+                    "Skipping {} {} {:#010X}-{:#010X}: overlaps {}",
+                    split.unit,
+                    section.name,
+                    start,
+                    end,
+                    existing.unit
+                );
+                continue;
+            }
+            obj.add_split(section_index, start, ObjSplit { end, ..split.clone() })?;
+            split_count += 1;
+        }
+        for unit in &elf_obj.link_order {
+            if !obj.link_order.iter().any(|u| u.name == unit.name)
+                && obj.sections.all_splits().any(|(_, _, _, s)| s.unit == unit.name)
+            {
+                obj.link_order.push(unit.clone());
+            }
+        }
+    }
+
+    write_symbols_file(symbols_path, &obj, symbols_cache)?;
+    if let Some(splits_path) = splits_path {
+        write_splits_file(splits_path, &obj, false, splits_cache)?;
+    }
+    log::info!("Imported {} symbols and {} file splits", symbol_count, split_count);
+    Ok(())
+}
+
+/// Runs function detection for `dol check --fix`. Function sizes from the symbols file are
+/// dropped first, so that each function ends where its code does.
+fn detect_function_sizes(
+    module_config: &ModuleConfig,
+    is_module: bool,
+    pipeline: &Pipeline,
+) -> Result<FunctionSizes> {
+    let file = map_file(&module_config.object)?;
+    let mut obj = if is_module {
+        process_rel(&mut Cursor::new(file.as_slice()), module_config.name().as_ref())?.1
+    } else if is_rpl(file.as_slice()) {
+        process_elf_data(file.as_slice())?
+    } else {
+        process_dol(file.as_slice(), module_config.name().as_ref())?
+    };
+    if let Some(symbols_path) = &module_config.symbols {
+        apply_symbols_file(symbols_path, &mut obj)?;
+    }
+    let sized = obj
+        .symbols
+        .by_kind(ObjSymbolKind::Function)
+        .filter(|(_, s)| s.size_known)
+        .map(|(i, _)| i)
+        .collect_vec();
+    for symbol_index in sized {
+        let symbol = ObjSymbol { size: 0, size_known: false, ..obj.symbols[symbol_index].clone() };
+        obj.symbols.replace(symbol_index, symbol)?;
+    }
+    let mut state = AnalyzerState::default();
+    pipeline.run_stage(PassStage::BeforeFunctions, &mut state, &obj, &mut Timings::new())?;
+    state.detect_functions(&obj)?;
+    Ok(state
+        .functions
+        .iter()
+        .filter_map(|(start, info)| {
+            let end = info.end?;
+            let section = obj.sections[start.section].name.clone();
+            Some(((section, start.address), end.address - start.address))
+        })
+        .collect())
+}
+
+fn check(args: CheckArgs, pipeline: &Pipeline) -> Result<()> {
+    let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(&args.config)?)?;
+    let mut count = 0usize;
+    let mut fixed = 0usize;
+    let base = std::iter::once((&config.base, false));
+    for (module_config, is_module) in base.chain(config.modules.iter().map(|m| (m, true))) {
+        let detected = if args.fix {
+            let detected = detect_function_sizes(module_config, is_module, pipeline)
+                .with_context(|| format!("While analyzing '{}'", module_config.object.display()))?;
+            Some(detected)
+        } else {
+            None
+        };
+        let result = check_config_files(
+            module_config.splits.as_deref(),
+            module_config.symbols.as_deref(),
+            detected.as_ref(),
+        )
+        .with_context(|| format!("While checking module '{}'", module_config.name()))?;
+        for fix in &result.fixes {
+            if is_json() {
+                print_json(&serde_json::json!({ "fixed": fix }))?;
+            } else {
+                println!("{}:{}: fixed: {}", fix.file.display(), fix.line, fix.message);
+            }
+        }
+        for problem in &result.problems {
+            if is_json() {
+                print_json(problem)?;
+            } else {
+                println!("{problem}");
+            }
+        }
+        count += result.problems.len();
+        fixed += result.fixes.len();
+    }
+    if args.fix && !is_json() {
+        println!("Applied {fixed} fix(es)");
+    }
+    if count > 0 {
+        return Err(coded_error(
+            ErrorCode::InvalidConfig,
+            format!("Found {count} problem(s) in '{}'", args.config.display()),
+        ));
+    }
+    if !is_json() {
+        println!("{}: OK", args.config.display());
+    }
+    Ok(())
+}
+
+fn header(args: HeaderArgs) -> Result<()> {
+    let types: HashMap<String, String> = match &args.types {
+        Some(path) => serde_yaml::from_reader(&mut buf_reader(path)?)
+            .with_context(|| format!("While loading '{}'", path.display()))?,
+        None => HashMap::new(),
+    };
+    let (config, objs) = load_project(&args.config)?;
+    let object_names = std::iter::once(config.base.file_name())
+        .chain(config.modules.iter().map(|m| m.file_name()))
+        .collect::<Vec<_>>();
+    let objs = object_names.iter().map(|name| name.as_ref()).zip(&objs).collect::<Vec<_>>();
+    let guard = match &args.out_file {
+        Some(path) => header_guard(&path.file_name().unwrap_or_default().to_string_lossy()),
+        None => "SYMBOLS_H".to_string(),
+    };
+    if args.out_file.is_none() {
+        ensure_text_output("dol header without an output file")?;
+    }
+    let header = generate_header(&objs, &types, &guard)?;
+    match &args.out_file {
+        Some(path) => {
+            let mut w = buf_writer(path)?;
+            w.write_all(header.as_bytes())?;
+            w.flush()?;
+            info!("Wrote {}", path.display());
+        }
+        None => print!("{header}"),
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct BssRecord<'a> {
+    object: &'a str,
+    #[serde(flatten)]
+    proposal: &'a BssProposal,
+}
+
+fn bss(args: BssArgs, pipeline: &Pipeline) -> Result<()> {
+    let min_confidence = args.min_confidence.unwrap_or(0.5);
+    let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(&args.config)?)
+        .with_context(|| format!("While loading '{}'", args.config.display()))?;
+    config.register_module_names();
+    let mut results = vec![(&config.base, load_analyze_dol(&config, pipeline)?)];
+    for module_config in &config.modules {
+        let result = load_analyze_rel(&config, module_config, pipeline)
+            .with_context(|| format!("While loading '{}'", module_config.object.display()))?;
+        results.push((module_config, result));
+    }
+    for (module_config, mut result) in results {
+        let object = module_config.file_name();
+        let obj = &mut result.obj;
+        let mut tracker = Tracker::new(obj);
+        tracker.process(obj)?;
+        tracker.apply(obj, false)?;
+        let proposals = propose_bss_splits(obj);
+        let write = args.write && module_config.splits.is_some();
+        let mut written = 0;
+        for proposal in &proposals {
+            if is_json() {
+                print_json(&BssRecord { object: &object, proposal })?;
+            } else {
+                println!(
+                    "{} {}:{:#010X}-{:#010X} {} ({} symbols, {:.2}{})",
+                    object,
+                    proposal.section,
+                    proposal.start,
+                    proposal.end,
+                    proposal.unit,
+                    proposal.symbols,
+                    proposal.confidence,
+                    if proposal.conflict { ", conflict" } else { "" }
+                );
+            }
+            if write && !proposal.conflict && proposal.confidence >= min_confidence {
+                obj.add_split(proposal.section_index, proposal.start, ObjSplit {
+                    unit: proposal.unit.clone(),
+                    end: proposal.end,
+                    align: None,
+                    common: false,
+                    autogenerated: false,
+                    skip: false,
+                    rename: None,
+                    pad: None,
+                    end_align: None,
+                })?;
+                written += 1;
+            }
+        }
+        if written > 0 {
+            if let Some(splits_path) = &module_config.splits {
+                write_splits_file(splits_path, obj, false, result.splits_cache)?;
+            }
+        }
+        info!("{}: proposed {} BSS splits, wrote {}", object, proposals.len(), written);
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct CompilerRecord<'a> {
+    object: &'a str,
+    #[serde(flatten)]
+    detected: &'a UnitCompiler,
+    /// Compiler already set for the unit
+    current: Option<&'a str>,
+}
+
+fn compiler(args: CompilerArgs, pipeline: &Pipeline) -> Result<()> {
+    let min_confidence = args.min_confidence.unwrap_or(0.8);
+    let compiler_name = |family: CompilerFamily| match family {
+        CompilerFamily::Mwcc1 => args.mwcc1.as_deref().unwrap_or("GC/1.2.5"),
+        CompilerFamily::Mwcc2 => args.mwcc2.as_deref().unwrap_or("GC/2.6"),
+        CompilerFamily::Gcc => args.gcc.as_deref().unwrap_or("ProDG/3.9.3"),
+    };
+    let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(&args.config)?)
+        .with_context(|| format!("While loading '{}'", args.config.display()))?;
+    config.register_module_names();
+    let mut results = vec![(&config.base, load_analyze_dol(&config, pipeline)?)];
+    for module_config in &config.modules {
+        let result = load_analyze_rel(&config, module_config, pipeline)
+            .with_context(|| format!("While loading '{}'", module_config.object.display()))?;
+        results.push((module_config, result));
+    }
+    for (module_config, mut result) in results {
+        let object = module_config.file_name();
+        let obj = &mut result.obj;
+        let detected = detect_unit_compilers(obj);
+        let write = args.write && module_config.splits.is_some();
+        let mut written = 0;
+        for unit_compiler in &detected {
+            let Some(unit) = obj.link_order.iter_mut().find(|u| u.name == unit_compiler.unit)
+            else {
+                continue;
+            };
+            if is_json() {
+                print_json(&CompilerRecord {
+                    object: &object,
+                    detected: unit_compiler,
+                    current: unit.compiler.as_deref(),
+                })?;
+            } else {
+                println!(
+                    "{} {}: {} ({} functions, {:.2}){}",
+                    object,
+                    unit.name,
+                    unit_compiler.family.name(),
+                    unit_compiler.functions,
+                    unit_compiler.confidence,
+                    match &unit.compiler {
+                        Some(current) => format!(", set to {current}"),
+                        None => String::new(),
+                    }
+                );
+            }
+            if write
+                && !unit.autogenerated
+                && unit.compiler.is_none()
+                && unit_compiler.confidence >= min_confidence
+            {
+                unit.compiler = Some(compiler_name(unit_compiler.family).to_string());
+                written += 1;
+            }
+        }
+        if written > 0 {
+            if let Some(splits_path) = &module_config.splits {
+                write_splits_file(splits_path, obj, false, result.splits_cache)?;
+            }
+        }
+        info!("{}: detected {} unit compilers, wrote {}", object, detected.len(), written);
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct SuggestionRecord<'a> {
+    object: &'a str,
+    #[serde(flatten)]
+    suggestion: &'a SplitSuggestion,
+}
+
+/// A response to a split suggestion.
+enum SuggestionAnswer {
+    Accept { unit: String, end: u32 },
+    Skip,
+    Quit,
+}
+
+fn suggest(args: SuggestArgs, pipeline: &Pipeline) -> Result<()> {
+    if !args.list {
+        ensure_text_output("dol suggest without --list")?;
+    }
+    let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(&args.config)?)
+        .with_context(|| format!("While loading '{}'", args.config.display()))?;
+    config.register_module_names();
+    let mut results = vec![(&config.base, load_analyze_dol(&config, pipeline)?)];
+    for module_config in &config.modules {
+        let result = load_analyze_rel(&config, module_config, pipeline)
+            .with_context(|| format!("While loading '{}'", module_config.object.display()))?;
+        results.push((module_config, result));
+    }
+    for (module_config, mut result) in results {
+        let object = module_config.file_name();
+        let obj = &mut result.obj;
+        let mut tracker = Tracker::new(obj);
+        tracker.process(obj)?;
+        tracker.apply(obj, false)?;
+        let in_section = |s: &SplitSuggestion| args.section.iter().all(|name| *name == s.section);
+
+        if args.list {
+            for suggestion in suggest_splits(obj).iter().filter(|s| in_section(s)) {
+                if is_json() {
+                    print_json(&SuggestionRecord { object: &object, suggestion })?;
+                } else {
+                    print_suggestion(&object, suggestion);
+                }
+            }
+            continue;
+        }
+        let Some(splits_path) = &module_config.splits else {
+            log::warn!("{}: no splits file configured, skipping", object);
+            continue;
+        };
+
+        // Suggestions are recomputed after each split, since it provides new references
+        let mut skipped = HashSet::new();
+        let mut added = 0;
+        let mut quit = false;
+        while let Some(suggestion) = suggest_splits(obj)
+            .into_iter()
+            .find(|s| in_section(s) && !skipped.contains(&(s.section_index, s.start)))
+        {
+            print_suggestion(&object, &suggestion);
+            let (unit, end) = match prompt_suggestion(&suggestion)? {
+                SuggestionAnswer::Accept { unit, end } => (unit, end),
+                SuggestionAnswer::Skip => {
+                    skipped.insert((suggestion.section_index, suggestion.start));
+                    continue;
+                }
+                SuggestionAnswer::Quit => {
+                    quit = true;
+                    break;
+                }
+            };
+            let split = ObjSplit {
+                unit: unit.clone(),
+                end,
+                align: None,
+                common: false,
+                autogenerated: false,
+                skip: false,
+                rename: None,
+                pad: None,
+                end_align: None,
+            };
+            if let Err(e) = obj.add_split(suggestion.section_index, suggestion.start, split) {
+                log::error!("{e:#}");
+                skipped.insert((suggestion.section_index, suggestion.start));
+                continue;
+            }
+            if !obj.link_order.iter().any(|u| u.name == unit) {
+                // Place new units next to their neighbors in the link order
+                let index = suggestion
+                    .prev_unit
+                    .as_ref()
+                    .and_then(|prev| obj.link_order.iter().position(|u| u.name == *prev))
+                    .map(|i| i + 1)
+                    .or_else(|| {
+                        let next = suggestion.next_unit.as_ref()?;
+                        obj.link_order.iter().position(|u| u.name == *next)
+                    })
+                    .unwrap_or(obj.link_order.len());
+                obj.link_order.insert(index, ObjUnit { name: unit, ..Default::default() });
+            }
+            added += 1;
+        }
+        if added > 0 {
+            write_splits_file(splits_path, obj, false, result.splits_cache)?;
+        }
+        info!("{}: added {} splits", object, added);
+        if quit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn print_suggestion(object: &str, suggestion: &SplitSuggestion) {
+    println!(
+        "{} {}:{:#010X}-{:#010X} {} ({}{})",
+        object,
+        suggestion.section,
+        suggestion.start,
+        suggestion.end,
+        suggestion.unit,
+        suggestion.source.as_str(),
+        if suggestion.conflict { ", conflict" } else { "" }
+    );
+    println!(
+        "  between {} and {}",
+        suggestion.prev_unit.as_deref().unwrap_or("(start)"),
+        suggestion.next_unit.as_deref().unwrap_or("(end)")
+    );
+    if !suggestion.references.is_empty() {
+        let references = suggestion
+            .references
+            .iter()
+            .map(|(unit, count)| format!("{unit} ({count})"))
+            .join(", ");
+        println!("  references: {references}");
+    }
+    const MAX_SYMBOLS: usize = 8;
+    for symbol in suggestion.symbols.iter().take(MAX_SYMBOLS) {
+        let signature = if symbol.signature { " [signature]" } else { "" };
+        println!("  {:#010X} {}{}", symbol.address, symbol.name, signature);
+    }
+    if suggestion.symbols.len() > MAX_SYMBOLS {
+        println!("  ... and {} more symbols", suggestion.symbols.len() - MAX_SYMBOLS);
+    }
+    for string in suggestion.strings.iter().take(MAX_SYMBOLS) {
+        println!("  {string:?}");
+    }
+}
+
+/// Asks whether to add a suggestion, optionally with another unit name and end address.
+fn prompt_suggestion(suggestion: &SplitSuggestion) -> Result<SuggestionAnswer> {
+    loop {
+        print!("Add split? [Y]es, [n]o, [q]uit, or <unit> [end]: ");
+        stdout().flush()?;
+        let mut line = String::new();
+        if stdin().read_line(&mut line)? == 0 {
+            return Ok(SuggestionAnswer::Quit);
+        }
+        let (unit, end) = match line.trim() {
+            "" | "y" | "Y" => {
+                if suggestion.source == SuggestionSource::Placeholder || suggestion.conflict {
+                    println!("Enter a unit name for this split");
+                    continue;
+                }
+                (suggestion.unit.clone(), suggestion.end)
+            }
+            "n" | "N" => return Ok(SuggestionAnswer::Skip),
+            "q" | "Q" => return Ok(SuggestionAnswer::Quit),
+            input => {
+                let mut parts = input.split_whitespace();
+                let unit = parts.next().unwrap_or_default().to_string();
+                let end = match parts.next().map(parse_hex).transpose() {
+                    Ok(end) => end.unwrap_or(suggestion.end),
+                    Err(e) => {
+                        println!("Invalid end address: {e}");
+                        continue;
+                    }
+                };
+                (unit, end)
+            }
+        };
+        if end <= suggestion.start {
+            println!("End address must be after {:#010X}", suggestion.start);
+            continue;
+        }
+        return Ok(SuggestionAnswer::Accept { unit, end });
+    }
+}
+
+fn map(args: MapArgs, pipeline: &Pipeline) -> Result<()> {
+    let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(&args.config)?)
+        .with_context(|| format!("While loading '{}'", args.config.display()))?;
+    config.register_module_names();
+    let mut results =
+        vec![(&config.base, load_analyze_dol(&config, pipeline)?, config.common_start)];
+    for module_config in &config.modules {
+        let result = load_analyze_rel(&config, module_config, pipeline)
+            .with_context(|| format!("While loading '{}'", module_config.object.display()))?;
+        results.push((module_config, result, None));
+    }
+    DirBuilder::new().recursive(true).create(&args.out_dir)?;
+    for (module_config, mut result, common_start) in results {
+        let obj = &mut result.obj;
+        let mut tracker = Tracker::new(obj);
+        tracker.process(obj)?;
+        tracker.apply(obj, false)?;
+        // Every symbol needs a unit
+        update_splits(obj, common_start, true)?;
+        let out_path = args.out_dir.join(format!("{}.map", module_config.name()));
+        let mut w = buf_writer(&out_path)?;
+        write_map(&mut w, obj)?;
+        w.flush()?;
+        info!("Wrote {}", out_path.display());
+    }
+    Ok(())
+}
+
+fn gdb(args: GdbArgs) -> Result<()> {
+    let (config, objs) = load_project(&args.config)?;
+    DirBuilder::new().recursive(true).create(&args.out_dir)?;
+    let module_configs = std::iter::once(&config.base).chain(&config.modules);
+    for (module_config, obj) in module_configs.zip(&objs) {
+        let out_path = args.out_dir.join(format!("{}.elf", module_config.name()));
+        let mut w = buf_writer(&out_path)?;
+        w.write_all(&write_elf(obj)?)?;
+        w.flush()?;
+        info!("Wrote {}", out_path.display());
+    }
+    Ok(())
+}
+
+fn compile_commands(args: CompileCommandsArgs) -> Result<()> {
+    let (config, objs) = load_project(&args.config)?;
+    let source_dir = args
+        .source_dir
+        .or_else(|| config.scaffold_dir.clone())
+        .unwrap_or_else(|| PathBuf::from("src"));
+    let include_dirs =
+        if args.include.is_empty() { vec![PathBuf::from("include")] } else { args.include };
+    let directory = std::env::current_dir()?;
+    let mut commands = vec![];
+    for obj in &objs {
+        for unit in &obj.link_order {
+            // Compile commands are for sources that exist, not ones still to be written
+            let Some(command) = compile_command(unit, &directory, &source_dir, &include_dirs)
+            else {
+                continue;
+            };
+            if Path::new(&command.file).is_file() {
+                commands.push(command);
+            }
+        }
+    }
+    let out_path = args.out_file.unwrap_or_else(|| PathBuf::from("compile_commands.json"));
+    let mut w = buf_writer(&out_path)?;
+    serde_json::to_writer_pretty(&mut w, &commands)?;
+    w.flush()?;
+    info!("Wrote {} entries to {}", commands.len(), out_path.display());
+    Ok(())
+}
+
+fn edit(args: EditArgs) -> Result<()> {
+    let data = fs::read(&args.dol_file)
+        .with_context(|| format!("Failed to read '{}'", args.dol_file.display()))?;
+    let mut image = DolImage::new(&data)?;
+    for &(slot, size) in &args.resize {
+        let old_size = image.range(slot).map_or(0, |(start, end)| end - start);
+        image.resize_section(slot, size)?;
+        info!("Resized {} from {:#X} to {:#X}", slot, old_size, size);
+    }
+    for append in &args.add {
+        let contents = fs::read(&append.file)
+            .with_context(|| format!("Failed to read '{}'", append.file.display()))?;
+        let (slot, address) = image.append_section(append.kind, contents, append.address)?;
+        let (_, end) = image.range(slot).unwrap();
+        info!("Added {} at {:#010X}-{:#010X} from {}", slot, address, end, append.file.display());
+    }
+    let out_path = args.out_file.as_ref().unwrap_or(&args.dol_file);
+    fs::write(out_path, image.write()?)
+        .with_context(|| format!("Failed to write '{}'", out_path.display()))?;
+    Ok(())
+}
+
 fn config(args: ConfigArgs) -> Result<()> {
     let mut config = ProjectConfig {
-        base: ModuleConfig {
-            name: None,
-            object: Default::default(),
-            hash: None,
-            splits: None,
-            symbols: None,
-            map: None,
-            force_active: vec![],
-            ldscript_template: None,
-            links: None,
-            extract: vec![],
-        },
+        base: ModuleConfig::default(),
         selfile: None,
         selfile_hash: None,
         mw_comment_version: None,
+        mw_comment: Default::default(),
         quick_analysis: false,
         modules: vec![],
+        dols: vec![],
+        module_names: Default::default(),
         detect_objects: true,
         detect_strings: true,
+        infer_alignment: true,
         write_asm: true,
+        write_import_stubs: false,
+        write_archives: false,
+        asm: Default::default(),
+        scaffold_dir: None,
+        scaffold_style: Default::default(),
         common_start: None,
         symbols_known: false,
         fill_gaps: true,
+        platform: Default::default(),
+        memory_ranges: None,
+        signature_store: None,
+        signature_cache: None,
+        signature_sets: vec![],
     };
 
     let mut modules = Vec::<(u32, ModuleConfig)>::new();
@@ -1772,22 +3743,33 @@ fn config(args: ConfigArgs) -> Result<()> {
 
         match path.extension() {
             Some(ext) if ext.eq_ignore_ascii_case(OsStr::new("dol")) => {
-                config.base.object = path;
-                config.base.hash = Some(file_sha1_string(&mut entry.as_reader())?);
+                let hash = Some(file_sha1_string(&mut entry.as_reader())?);
+                // Record the SDA bases recovered from `__init_registers`
+                let obj = process_dol(entry.as_slice(), "")?;
+                if config.base.hash.is_none() {
+                    config.base.object = path;
+                    config.base.hash = hash;
+                    config.base.sda_base = obj.sda_base;
+                    config.base.sda2_base = obj.sda2_base;
+                } else {
+                    config.dols.push(DolConfig {
+                        base: ModuleConfig {
+                            object: path,
+                            hash,
+                            sda_base: obj.sda_base,
+                            sda2_base: obj.sda2_base,
+                            ..config.base.clone()
+                        },
+                        common_start: None,
+                    });
+                }
             }
             Some(ext) if ext.eq_ignore_ascii_case(OsStr::new("rel")) => {
                 let header = process_rel_header(&mut entry.as_reader())?;
                 modules.push((header.module_id, ModuleConfig {
-                    name: None,
                     object: path,
                     hash: Some(file_sha1_string(&mut entry.as_reader())?),
-                    splits: None,
-                    symbols: None,
-                    map: None,
-                    force_active: vec![],
-                    ldscript_template: None,
-                    links: None,
-                    extract: vec![],
+                    ..Default::default()
                 }));
             }
             Some(ext) if ext.eq_ignore_ascii_case(OsStr::new("sel")) => {
@@ -1796,16 +3778,9 @@ fn config(args: ConfigArgs) -> Result<()> {
             }
             Some(ext) if ext.eq_ignore_ascii_case(OsStr::new("rso")) => {
                 config.modules.push(ModuleConfig {
-                    name: None,
                     object: path,
                     hash: Some(file_sha1_string(&mut entry.as_reader())?),
-                    splits: None,
-                    symbols: None,
-                    map: None,
-                    force_active: vec![],
-                    ldscript_template: None,
-                    links: None,
-                    extract: vec![],
+                    ..Default::default()
                 });
             }
             _ => bail!("Unknown file extension: '{}'", path.display()),
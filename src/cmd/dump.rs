@@ -0,0 +1,197 @@
+use std::{
+    fs,
+    io::{stdout, Write},
+    path::PathBuf,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use argp::FromArgs;
+use serde::Serialize;
+
+use crate::{
+    obj::ObjSectionKind,
+    util::{
+        config::parse_address,
+        dol::process_dol,
+        file::map_file,
+        output::{is_json, print_json},
+        rel::process_rel,
+    },
+};
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Dumps memory by virtual address from a DOL and loaded RELs.
+#[argp(subcommand, name = "dump")]
+pub struct Args {
+    #[argp(positional)]
+    /// DOL file
+    dol_file: PathBuf,
+    #[argp(positional, from_str_fn(parse_address))]
+    /// start address (e.g. 0x80003100)
+    address: u32,
+    #[argp(option, short = 'n', from_str_fn(parse_address))]
+    /// number of bytes (default 0x100)
+    size: Option<u32>,
+    #[argp(option, short = 'r', from_str_fn(parse_rel_load))]
+    /// REL file and its load address, as file.rel:0x80500000 (can be repeated)
+    rel: Vec<RelLoad>,
+    #[argp(switch)]
+    /// write raw bytes instead of a hexdump
+    raw: bool,
+    #[argp(option, short = 'o')]
+    /// output file (default stdout)
+    output: Option<PathBuf>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct RelLoad {
+    path: PathBuf,
+    address: u32,
+}
+
+fn parse_rel_load(s: &str) -> Result<RelLoad, String> {
+    let Some((path, address)) = s.rsplit_once(':') else {
+        return Err(format!("Expected file.rel:address, got '{s}'"));
+    };
+    Ok(RelLoad { path: PathBuf::from(path), address: parse_address(address)? })
+}
+
+/// A loaded section. `data` is empty for BSS, which reads as zeroes.
+#[derive(Serialize)]
+struct Region {
+    module: String,
+    section: String,
+    start: u32,
+    end: u32,
+    #[serde(skip)]
+    data: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct DumpRecord<'a> {
+    address: u32,
+    /// Hex-encoded bytes
+    data: String,
+    regions: Vec<&'a Region>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let size = args.size.unwrap_or(0x100);
+    let end = args.address.checked_add(size).context("Address range overflows")?;
+    let mut regions = vec![];
+    {
+        let file = map_file(&args.dol_file)?;
+        let obj = process_dol(file.as_slice(), "")?;
+        let module = args.dol_file.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        for (_, section) in obj.sections.iter() {
+            regions.push(Region {
+                module: module.clone(),
+                section: section.name.clone(),
+                start: section.address as u32,
+                end: (section.address + section.size) as u32,
+                data: section.data.clone(),
+            });
+        }
+    }
+    for rel in &args.rel {
+        let file = map_file(&rel.path)?;
+        let (_, obj) = process_rel(&mut file.as_reader(), "")
+            .with_context(|| format!("Failed to process REL '{}'", rel.path.display()))?;
+        let module = rel.path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        // Sections are used in place after loading; BSS is allocated elsewhere
+        for (_, section) in obj.sections.iter() {
+            if section.kind == ObjSectionKind::Bss {
+                continue;
+            }
+            let start = rel.address + section.file_offset as u32;
+            regions.push(Region {
+                module: module.clone(),
+                section: section.name.clone(),
+                start,
+                end: start + section.size as u32,
+                data: section.data.clone(),
+            });
+        }
+    }
+    regions.sort_by_key(|r| r.start);
+    for pair in regions.windows(2) {
+        ensure!(
+            pair[0].end <= pair[1].start,
+            "{} {} overlaps {} {}",
+            pair[0].module,
+            pair[0].section,
+            pair[1].module,
+            pair[1].section
+        );
+    }
+
+    // Byte values by address, `None` where nothing is loaded
+    let mut bytes = Vec::with_capacity(size as usize);
+    for address in args.address..end {
+        let region = regions.iter().find(|r| address >= r.start && address < r.end);
+        bytes.push(region.map(|r| r.data.get((address - r.start) as usize).copied().unwrap_or(0)));
+    }
+    let used = regions.iter().filter(|r| r.start < end && r.end > args.address).collect::<Vec<_>>();
+
+    if is_json() {
+        let data = bytes.iter().map(|b| b.map_or("??".to_string(), |b| format!("{b:02x}")));
+        return print_json(&DumpRecord {
+            address: args.address,
+            data: data.collect(),
+            regions: used,
+        });
+    }
+    let mut out = Vec::new();
+    if args.raw {
+        for (i, byte) in bytes.iter().enumerate() {
+            match byte {
+                Some(byte) => out.push(*byte),
+                None => bail!("Nothing is loaded at {:#010X}", args.address + i as u32),
+            }
+        }
+    } else {
+        let mut current = None;
+        for (i, chunk) in bytes.chunks(16).enumerate() {
+            let address = args.address + i as u32 * 16;
+            let region = used.iter().position(|r| r.end > address && r.start < address + 16);
+            if region != current {
+                if let Some(r) = region.map(|i| used[i]) {
+                    writeln!(
+                        out,
+                        "// {} {} ({:#010X}-{:#010X})",
+                        r.module, r.section, r.start, r.end
+                    )?;
+                }
+                current = region;
+            }
+            write!(out, "{address:08X} ")?;
+            for (j, byte) in chunk.iter().enumerate() {
+                if j == 8 {
+                    write!(out, " ")?;
+                }
+                match byte {
+                    Some(byte) => write!(out, " {byte:02X}")?,
+                    None => write!(out, " ??")?,
+                }
+            }
+            for j in chunk.len()..16 {
+                write!(out, "{}   ", if j == 8 { " " } else { "" })?;
+            }
+            let text = chunk
+                .iter()
+                .map(|b| match b {
+                    Some(b) if b.is_ascii_graphic() || *b == b' ' => *b as char,
+                    _ => '.',
+                })
+                .collect::<String>();
+            writeln!(out, "  |{text}|")?;
+        }
+    }
+    match &args.output {
+        Some(path) => {
+            fs::write(path, out).with_context(|| format!("Failed to write '{}'", path.display()))?
+        }
+        None => stdout().write_all(&out)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use argp::FromArgs;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    cmd::fingerprint::{function_name, load_project},
+    util::{
+        fingerprint::{find_duplicates, fingerprint_functions, MIN_CONFIDENT_WORDS},
+        output::{is_json, print_json},
+    },
+};
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Finds identical or near-identical functions across the DOL and RELs of a project.
+#[argp(subcommand, name = "duplicates")]
+pub struct Args {
+    #[argp(positional)]
+    /// project config (config.yml)
+    config: PathBuf,
+    #[argp(option, short = 's')]
+    /// minimum similarity to group functions, from 0 to 1 (default 0.9; 1 for identical only)
+    min_similarity: Option<f32>,
+    #[argp(option, short = 'n')]
+    /// minimum function size in instructions (default 8)
+    min_words: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct GroupRecord<'a> {
+    size: u32,
+    similarity: f32,
+    functions: Vec<MemberRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct MemberRecord<'a> {
+    object: &'a str,
+    section: &'a str,
+    address: u32,
+    size: u32,
+    name: Option<&'a str>,
+    unit: Option<&'a str>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let min_similarity = args.min_similarity.unwrap_or(0.9);
+    let min_words = args.min_words.unwrap_or(MIN_CONFIDENT_WORDS);
+    let (config, objs) = load_project(&args.config)?;
+    let object_names = std::iter::once(config.base.file_name())
+        .chain(config.modules.iter().map(|m| m.file_name()))
+        .collect::<Vec<_>>();
+    let fingerprints = objs.iter().map(fingerprint_functions).collect::<Result<Vec<_>>>()?;
+    let groups = find_duplicates(&fingerprints, min_words, min_similarity);
+    for group in &groups {
+        let functions = group
+            .members
+            .iter()
+            .map(|&(obj_index, index)| {
+                let obj = &objs[obj_index];
+                let f = &fingerprints[obj_index][index];
+                let section = &obj.sections[f.address.section];
+                MemberRecord {
+                    object: &object_names[obj_index],
+                    section: &section.name,
+                    address: f.address.address,
+                    size: f.size,
+                    name: function_name(obj, f.address),
+                    unit: section
+                        .splits
+                        .for_address(f.address.address)
+                        .map(|(_, split)| split.unit.as_str()),
+                }
+            })
+            .collect::<Vec<_>>();
+        let record = GroupRecord {
+            size: functions.iter().map(|f| f.size).max().unwrap_or(0),
+            similarity: group.similarity,
+            functions,
+        };
+        if is_json() {
+            print_json(&record)?;
+            continue;
+        }
+        println!(
+            "{} functions, {:#X} bytes, {}",
+            record.functions.len(),
+            record.size,
+            if record.similarity >= 1.0 {
+                "identical".to_string()
+            } else {
+                format!("{:.2} similar", record.similarity)
+            }
+        );
+        for f in &record.functions {
+            print!("  {} {}:{:#010X} {}", f.object, f.section, f.address, f.name.unwrap_or("?"));
+            match f.unit {
+                Some(unit) => println!(" ({})", unit),
+                None => println!(),
+            }
+        }
+    }
+    let duplicated = groups.iter().map(|g| g.members.len() - 1).sum::<usize>();
+    let bytes = groups
+        .iter()
+        .flat_map(|g| g.members.iter().skip(1))
+        .map(|&(obj_index, index)| fingerprints[obj_index][index].size as u64)
+        .sum::<u64>();
+    info!("Found {} groups; {} duplicate functions, {:#X} bytes", groups.len(), duplicated, bytes);
+    Ok(())
+}
@@ -1,13 +1,15 @@
 use std::{
-    collections::{btree_map, BTreeMap},
+    collections::{btree_map, BTreeMap, BTreeSet},
+    fmt::Write as FmtWrite,
     io::{stdout, Cursor, Read, Write},
-    path::PathBuf,
+    path::{Component, Path, PathBuf},
     str::from_utf8,
 };
 
 use anyhow::{anyhow, bail, Context, Result};
 use argp::FromArgs;
 use object::{elf, Object, ObjectSection, ObjectSymbol, RelocationKind, RelocationTarget, Section};
+use path_slash::PathBufExt;
 use syntect::{
     highlighting::{Color, HighlightIterator, HighlightState, Highlighter, Theme, ThemeSet},
     parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet},
@@ -15,10 +17,15 @@ use syntect::{
 
 use crate::util::{
     dwarf::{
-        process_compile_unit, process_cu_tag, process_overlay_branch, read_debug_section,
-        should_skip_tag, tag_type_string, AttributeKind, TagKind,
+        c_declaration_string, c_enum_def_string, c_identifier, c_struct_def_string, c_type_string,
+        c_union_def_string, process_compile_unit, process_cu_tag, process_overlay_branch,
+        read_debug_section, should_skip_tag, tag_type_dependencies, tag_type_string, AttributeKind,
+        DwarfInfo, Tag, TagKind, TagType, TypeDependencies, TypeId, TypedefMap, UserDefinedType,
+        VariableTag,
     },
     file::{buf_writer, map_file},
+    header::header_guard,
+    output::ensure_text_output,
 };
 
 #[derive(FromArgs, PartialEq, Debug)]
@@ -48,6 +55,9 @@ pub struct DumpArgs {
     #[argp(switch)]
     /// Disable color output.
     no_color: bool,
+    #[argp(switch)]
+    /// Write a header and source file per compile unit to the output directory.
+    split: bool,
 }
 
 pub fn run(args: Args) -> Result<()> {
@@ -57,6 +67,14 @@ pub fn run(args: Args) -> Result<()> {
 }
 
 fn dump(args: DumpArgs) -> Result<()> {
+    if args.out.is_none() {
+        ensure_text_output("dwarf dump without an output file")?;
+    }
+    if args.split {
+        let out_dir = args.out.as_ref().ok_or_else(|| anyhow!("--split requires -o"))?;
+        return dump_split(&args.in_file, out_dir);
+    }
+
     // Load syntect
     let theme_set: ThemeSet =
         syntect::dumps::from_binary(include_bytes!("../../assets/syntax/default.themedump"));
@@ -125,14 +143,8 @@ fn dump(args: DumpArgs) -> Result<()> {
     Ok(())
 }
 
-fn dump_debug_section<W>(
-    w: &mut W,
-    obj_file: &object::File<'_>,
-    debug_section: Section,
-) -> Result<()>
-where
-    W: Write + ?Sized,
-{
+/// Reads the `.debug` section, with relocations applied.
+fn read_debug_info(obj_file: &object::File<'_>, debug_section: Section) -> Result<DwarfInfo> {
     let mut data = debug_section.uncompressed_data()?.into_owned();
 
     // Apply relocations to data
@@ -154,7 +166,327 @@ where
     }
 
     let mut reader = Cursor::new(&*data);
-    let info = read_debug_section(&mut reader, obj_file.endianness().into())?;
+    read_debug_section(&mut reader, obj_file.endianness().into())
+}
+
+/// Types and declarations of one compile unit, split into a header and a source file.
+#[derive(Default)]
+struct UnitOutput {
+    /// Type definitions first seen in this unit
+    header: String,
+    /// Variables, functions and anonymous types
+    source: String,
+    defined: BTreeSet<TypeId>,
+    header_deps: TypeDependencies,
+    source_deps: TypeDependencies,
+}
+
+fn root_tags(info: &DwarfInfo) -> Vec<&Tag> {
+    let mut out = vec![];
+    let Some((_, mut tag)) = info.tags.first_key_value() else {
+        return out;
+    };
+    loop {
+        out.push(tag);
+        match tag.next_sibling(&info.tags) {
+            Some(next) => tag = next,
+            None => break,
+        }
+    }
+    out
+}
+
+/// A unit's source path relative to the directory common to all units.
+fn unit_path(name: &str, prefix: &str) -> String {
+    let name = normalize_unit_name(name);
+    name.strip_prefix(prefix).unwrap_or(&name).to_string()
+}
+
+/// A unit name as a relative path that stays inside the output directory: drive letters,
+/// root directories, `.` and `..` are dropped.
+fn normalize_unit_name(name: &str) -> String {
+    let name = name.replace('\\', "/");
+    let name = match name.split_once(':') {
+        Some((drive, rest)) if drive.len() == 1 => rest,
+        _ => &name,
+    };
+    let components = Path::new(name)
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(s) => Some(s.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if components.is_empty() {
+        return "unknown".to_string();
+    }
+    components.join("/")
+}
+
+fn header_path(path: &str) -> String {
+    let header = Path::new(path).with_extension("h").to_slash_lossy().into_owned();
+    if header == path {
+        format!("{}_types.h", path.trim_end_matches(".h"))
+    } else {
+        header
+    }
+}
+
+/// Dumps each compile unit to a header with the types it defines first and a source file with
+/// its variables and functions. Types used by value are included from the header of the unit
+/// defining them, and types used through pointers are forward declared.
+fn dump_split(in_file: &Path, out_dir: &Path) -> Result<()> {
+    let file = map_file(in_file)?;
+    let buf = file.as_slice();
+    let mut infos = vec![];
+    if buf.starts_with(b"!<arch>\n") {
+        let mut archive = ar::Archive::new(buf);
+        while let Some(result) = archive.next_entry() {
+            let mut e = match result {
+                Ok(e) => e,
+                Err(e) => bail!("Failed to read archive entry: {:?}", e),
+            };
+            let name = String::from_utf8_lossy(e.header().identifier()).to_string();
+            let mut data = vec![0u8; e.header().size() as usize];
+            e.read_exact(&mut data)?;
+            let obj_file = object::read::File::parse(&*data)?;
+            let Some(debug_section) = obj_file.section_by_name(".debug") else {
+                log::warn!("Object '{}' missing .debug section", name);
+                continue;
+            };
+            infos.push(read_debug_info(&obj_file, debug_section)?);
+        }
+    } else {
+        let obj_file = object::read::File::parse(buf)?;
+        let debug_section = obj_file
+            .section_by_name(".debug")
+            .ok_or_else(|| anyhow!("Failed to locate .debug section"))?;
+        infos.push(read_debug_info(&obj_file, debug_section)?);
+    }
+
+    // Strip the directory common to all units
+    let mut prefix: Option<String> = None;
+    for info in &infos {
+        for tag in root_tags(info).into_iter().filter(|t| t.kind == TagKind::CompileUnit) {
+            let name = normalize_unit_name(&process_compile_unit(tag)?.name);
+            let dir = name.rsplit_once('/').map(|(dir, _)| format!("{dir}/")).unwrap_or_default();
+            prefix = Some(match prefix {
+                None => dir,
+                Some(prefix) => {
+                    let mut common = String::new();
+                    for (a, b) in prefix.split_inclusive('/').zip(dir.split_inclusive('/')) {
+                        if a != b {
+                            break;
+                        }
+                        common.push_str(a);
+                    }
+                    common
+                }
+            });
+        }
+    }
+    let prefix = prefix.unwrap_or_default();
+
+    let mut units = BTreeMap::<String, UnitOutput>::new();
+    let mut order = Vec::<String>::new();
+    let mut owners = BTreeMap::<TypeId, String>::new();
+    for info in &infos {
+        for tag in root_tags(info).into_iter().filter(|t| t.kind == TagKind::CompileUnit) {
+            let unit = process_compile_unit(tag)?;
+            let path = unit_path(&unit.name, &prefix);
+            if !units.contains_key(&path) {
+                order.push(path.clone());
+            }
+            let out = units.entry(path.clone()).or_default();
+            let mut typedefs = TypedefMap::new();
+            for child in tag.children(&info.tags) {
+                let tag_type = match process_cu_tag(info, child) {
+                    Ok(tag_type) => tag_type,
+                    Err(e) => {
+                        log::error!(
+                            "Failed to process tag {} (unit {}): {}",
+                            child.key,
+                            unit.name,
+                            e
+                        );
+                        writeln!(
+                            out.source,
+                            "// ERROR: Failed to process tag {} ({:?})",
+                            child.key, child.kind
+                        )?;
+                        continue;
+                    }
+                };
+                let id = TypeId::of_tag(&tag_type);
+                let emit = !should_skip_tag(&tag_type)
+                    && match &id {
+                        Some(id) => {
+                            *owners.entry(id.clone()).or_insert_with(|| path.clone()) == path
+                                && out.defined.insert(id.clone())
+                        }
+                        None => true,
+                    };
+                if emit {
+                    let (text, deps) = if id.is_some() {
+                        (&mut out.header, &mut out.header_deps)
+                    } else {
+                        (&mut out.source, &mut out.source_deps)
+                    };
+                    match tag_type_string(info, &typedefs, &tag_type) {
+                        Ok(s) => writeln!(text, "{}\n", s)?,
+                        Err(e) => {
+                            log::error!(
+                                "Failed to emit tag {} (unit {}): {}",
+                                child.key,
+                                unit.name,
+                                e
+                            );
+                            writeln!(
+                                text,
+                                "// ERROR: Failed to emit tag {} ({:?})\n",
+                                child.key, child.kind
+                            )?;
+                        }
+                    }
+                    match tag_type_dependencies(info, &typedefs, &tag_type) {
+                        Ok(d) => deps.extend(d),
+                        Err(e) => log::warn!(
+                            "Failed to find types used by tag {} (unit {}): {}",
+                            child.key,
+                            unit.name,
+                            e
+                        ),
+                    }
+                }
+
+                if let TagKind::Typedef = child.kind {
+                    if let Some(ud_type_ref) = child.reference_attribute(AttributeKind::UserDefType)
+                    {
+                        typedefs.entry(ud_type_ref).or_default().push(child.key);
+                    }
+                }
+            }
+        }
+    }
+
+    // Header includes, by unit. Includes that would be circular are left out.
+    let mut includes = BTreeMap::<&str, BTreeSet<&str>>::new();
+    for (path, out) in &units {
+        let set = includes.entry(path.as_str()).or_default();
+        for id in &out.header_deps.definitions {
+            match owners.get(id) {
+                Some(owner) if owner != path => {
+                    set.insert(owner.as_str());
+                }
+                _ => {}
+            }
+        }
+    }
+    let mut circular = BTreeSet::<(&str, &str)>::new();
+    let mut visited = BTreeSet::<&str>::new();
+    for path in &order {
+        find_circular_includes(path, &includes, &mut vec![], &mut visited, &mut circular);
+    }
+
+    for path in &order {
+        let out = &units[path];
+        let header = header_path(path);
+        let guard = header_guard(&header);
+        let mut w = buf_writer(out_dir.join(&header))?;
+        writeln!(w, "#ifndef {guard}\n#define {guard}\n")?;
+        let mut any = false;
+        for &owner in &includes[path.as_str()] {
+            if circular.contains(&(path.as_str(), owner)) {
+                writeln!(w, "// Circular: #include \"{}\"", header_path(owner))?;
+            } else {
+                writeln!(w, "#include \"{}\"", header_path(owner))?;
+            }
+            any = true;
+        }
+        any |= write_forward_declarations(&mut w, &out.header_deps, &owners, path)?;
+        if any {
+            writeln!(w)?;
+        }
+        write!(w, "{}", out.header)?;
+        writeln!(w, "#endif // {guard}")?;
+        w.flush()?;
+
+        let mut w = buf_writer(out_dir.join(path))?;
+        writeln!(w, "#include \"{}\"", header)?;
+        let source_includes = out
+            .source_deps
+            .definitions
+            .iter()
+            .filter_map(|id| owners.get(id))
+            .filter(|&owner| owner != path)
+            .collect::<BTreeSet<_>>();
+        for owner in source_includes {
+            writeln!(w, "#include \"{}\"", header_path(owner))?;
+        }
+        write_forward_declarations(&mut w, &out.source_deps, &owners, path)?;
+        writeln!(w)?;
+        write!(w, "{}", out.source)?;
+        w.flush()?;
+    }
+    log::info!("Wrote {} units to {}", order.len(), out_dir.display());
+    Ok(())
+}
+
+/// Forward declares the types only used through pointers that another unit defines, or that
+/// no unit defines. Returns whether any were written.
+fn write_forward_declarations<W>(
+    w: &mut W,
+    deps: &TypeDependencies,
+    owners: &BTreeMap<TypeId, String>,
+    path: &str,
+) -> Result<bool>
+where
+    W: Write + ?Sized,
+{
+    let mut any = false;
+    for id in deps.declarations.difference(&deps.definitions) {
+        if owners.get(id).is_some_and(|owner| owner == path) {
+            continue;
+        }
+        if let Some(decl) = id.forward_declaration() {
+            writeln!(w, "{}", decl)?;
+            any = true;
+        }
+    }
+    Ok(any)
+}
+
+/// Finds includes closing a cycle, visiting units depth-first.
+fn find_circular_includes<'a>(
+    path: &'a str,
+    includes: &BTreeMap<&'a str, BTreeSet<&'a str>>,
+    stack: &mut Vec<&'a str>,
+    visited: &mut BTreeSet<&'a str>,
+    circular: &mut BTreeSet<(&'a str, &'a str)>,
+) {
+    if !visited.insert(path) {
+        return;
+    }
+    stack.push(path);
+    for &include in includes.get(path).into_iter().flatten() {
+        if stack.contains(&include) {
+            circular.insert((path, include));
+        } else {
+            find_circular_includes(include, includes, stack, visited, circular);
+        }
+    }
+    stack.pop();
+}
+
+pub fn dump_debug_section<W>(
+    w: &mut W,
+    obj_file: &object::File<'_>,
+    debug_section: Section,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    let info = read_debug_info(obj_file, debug_section)?;
 
     for (&addr, tag) in &info.tags {
         log::debug!("{}: {:?}", addr, tag);
@@ -282,6 +614,157 @@ where
     Ok(())
 }
 
+/// Types, variables and functions of a `.debug` section as C, for tools that only parse C.
+#[derive(Default)]
+struct CContext {
+    /// Definition and the types it uses by value, by type
+    types: BTreeMap<TypeId, (String, BTreeSet<TypeId>)>,
+    /// Types in the order they were first seen
+    order: Vec<TypeId>,
+    declared: BTreeSet<String>,
+    declarations: Vec<String>,
+}
+
+impl CContext {
+    fn add(&mut self, info: &DwarfInfo, typedefs: &TypedefMap, tag: &Tag) -> Result<()> {
+        let tag_type = process_cu_tag(info, tag)?;
+        if should_skip_tag(&tag_type) {
+            return Ok(());
+        }
+        let Some(id) = TypeId::of_tag(&tag_type) else {
+            if let Some((name, text)) = c_declaration_string(info, typedefs, &tag_type)? {
+                if self.declared.insert(name) {
+                    self.declarations.push(text);
+                }
+            }
+            return Ok(());
+        };
+        if self.types.contains_key(&id) {
+            return Ok(());
+        }
+        let (text, deps) = match &tag_type {
+            TagType::Typedef(t) => {
+                let ts = c_type_string(info, typedefs, &t.kind)?;
+                let text = format!("typedef {} {}{};", ts.prefix, c_identifier(&t.name), ts.suffix);
+                // Unlike in C++ output, the named type is used by value
+                let variable = TagType::Variable(VariableTag {
+                    name: None,
+                    mangled_name: None,
+                    kind: t.kind.clone(),
+                    address: None,
+                    local: false,
+                });
+                (text, tag_type_dependencies(info, typedefs, &variable)?)
+            }
+            TagType::UserDefined(UserDefinedType::Structure(t)) => (
+                format!("{};", c_struct_def_string(info, typedefs, t)?),
+                tag_type_dependencies(info, typedefs, &tag_type)?,
+            ),
+            TagType::UserDefined(UserDefinedType::Union(t)) => (
+                format!("{};", c_union_def_string(info, typedefs, t)?),
+                tag_type_dependencies(info, typedefs, &tag_type)?,
+            ),
+            TagType::UserDefined(UserDefinedType::Enumeration(t)) => {
+                let name = c_identifier(id.name());
+                let text = format!("{};\ntypedef enum {name} {name};", c_enum_def_string(t)?);
+                (text, TypeDependencies::default())
+            }
+            _ => return Ok(()),
+        };
+        self.order.push(id.clone());
+        self.types.insert(id, (text, deps.definitions));
+        Ok(())
+    }
+
+    fn write<W>(&self, w: &mut W) -> Result<()>
+    where W: Write + ?Sized {
+        // Structures and unions are declared first, so that they can be used through pointers
+        // and referred to by their name alone
+        let mut names = BTreeSet::new();
+        for id in &self.order {
+            let keyword = match id {
+                TypeId::Struct(_) | TypeId::Class(_) => "struct",
+                TypeId::Union(_) => "union",
+                TypeId::Enum(name) => {
+                    names.insert(c_identifier(name));
+                    continue;
+                }
+                TypeId::Typedef(_) => continue,
+            };
+            let name = c_identifier(id.name());
+            if names.insert(name.clone()) {
+                writeln!(w, "typedef {keyword} {name} {name};")?;
+            }
+        }
+        writeln!(w)?;
+        let mut written = BTreeSet::new();
+        for id in &self.order {
+            self.write_type(w, id, &names, &mut written)?;
+        }
+        for declaration in &self.declarations {
+            writeln!(w, "{declaration}")?;
+        }
+        Ok(())
+    }
+
+    /// Writes a type definition after the types it uses by value.
+    fn write_type<'a, W>(
+        &'a self,
+        w: &mut W,
+        id: &TypeId,
+        names: &BTreeSet<String>,
+        written: &mut BTreeSet<&'a TypeId>,
+    ) -> Result<()>
+    where
+        W: Write + ?Sized,
+    {
+        let Some((id, (text, deps))) = self.types.get_key_value(id) else {
+            return Ok(());
+        };
+        if !written.insert(id) {
+            return Ok(());
+        }
+        for dep in deps {
+            self.write_type(w, dep, names, written)?;
+        }
+        // C code typedefs structures to their own name, which is already declared
+        if !matches!(id, TypeId::Typedef(name) if names.contains(&c_identifier(name))) {
+            writeln!(w, "{text}\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the types, variables and functions of a `.debug` section as C, for tools that only
+/// parse C, such as m2c. Classes become structures with their bases as leading members,
+/// references become pointers, and functions and variables are declared by their mangled
+/// names. Each type is written once, after the types it uses by value.
+pub fn write_c_context<W>(
+    w: &mut W,
+    obj_file: &object::File<'_>,
+    debug_section: Section,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    let info = read_debug_info(obj_file, debug_section)?;
+    let mut context = CContext::default();
+    for tag in root_tags(&info).into_iter().filter(|t| t.kind == TagKind::CompileUnit) {
+        let mut typedefs = TypedefMap::new();
+        for child in tag.children(&info.tags) {
+            if let Err(e) = context.add(&info, &typedefs, child) {
+                log::warn!("Leaving tag {} out of the C context: {}", child.key, e);
+            }
+            if let TagKind::Typedef = child.kind {
+                if let Some(ud_type_ref) = child.reference_attribute(AttributeKind::UserDefType) {
+                    typedefs.entry(ud_type_ref).or_default().push(child.key);
+                }
+            }
+        }
+    }
+    context.write(w)
+}
+
 struct HighlightWriter<'a> {
     line: String,
     highlighter: Highlighter<'a>,
@@ -359,3 +842,23 @@ impl Write for HighlightWriter<'_> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_names_stay_relative() {
+        assert_eq!(normalize_unit_name("C:\\Dolphin\\src\\os\\OS.c"), "Dolphin/src/os/OS.c");
+        assert_eq!(normalize_unit_name("/home/user/game/main.cpp"), "home/user/game/main.cpp");
+        assert_eq!(normalize_unit_name("src/./JSystem//JKRHeap.cpp"), "src/JSystem/JKRHeap.cpp");
+        assert_eq!(normalize_unit_name("..\\..\\..\\etc\\passwd"), "etc/passwd");
+        assert_eq!(normalize_unit_name("src/../../main.c"), "src/main.c");
+        assert_eq!(normalize_unit_name("main.c"), "main.c");
+        assert_eq!(normalize_unit_name("C:\\"), "unknown");
+        assert_eq!(normalize_unit_name(".."), "unknown");
+
+        assert_eq!(unit_path("C:\\game\\src\\os\\OS.c", "game/src/"), "os/OS.c");
+        assert_eq!(unit_path("other/../main.c", "game/src/"), "other/main.c");
+    }
+}
@@ -14,15 +14,17 @@ use object::{
     FileFlags, Object, ObjectSection, ObjectSymbol, RelocationKind, RelocationTarget, SectionFlags,
     SectionIndex, SectionKind, SymbolFlags, SymbolIndex, SymbolKind, SymbolScope, SymbolSection,
 };
+use serde::Serialize;
 
 use crate::{
-    obj::ObjKind,
+    obj::{ObjKind, ObjSymbol, ObjSymbolKind, ObjSymbols},
     util::{
-        asm::write_asm,
-        comment::{CommentSym, MWComment},
+        asm::{write_asm, AsmFlavor, AsmOptions, GekkoMode},
+        comment::{CommentSym, MWComment, MWFloatKind},
         config::{write_splits_file, write_symbols_file},
         elf::{process_elf, write_elf},
-        file::{buf_writer, process_rsp},
+        file::{buf_writer, map_file, process_rsp},
+        output::{is_json, print_json},
         reader::{Endian, FromReader},
         signatures::{compare_signature, generate_signature, FunctionSignature},
         split::split_obj,
@@ -41,6 +43,7 @@ pub struct Args {
 #[derive(FromArgs, PartialEq, Debug)]
 #[argp(subcommand)]
 enum SubCommand {
+    Clean(CleanArgs),
     Config(ConfigArgs),
     Disasm(DisasmArgs),
     Fixup(FixupArgs),
@@ -59,6 +62,24 @@ pub struct DisasmArgs {
     #[argp(positional)]
     /// output file (.o) or directory (.elf)
     out: PathBuf,
+    #[argp(option, short = 'f')]
+    /// assembly syntax flavor (gnu, macro, codewarrior, listing)
+    flavor: Option<AsmFlavor>,
+    #[argp(switch)]
+    /// prefix register names with % (e.g. %r3)
+    register_prefix: bool,
+    #[argp(switch)]
+    /// disable simplified mnemonics (e.g. addi instead of li)
+    raw_mnemonics: bool,
+    #[argp(option)]
+    /// how Gekko instructions are written (mnemonic, raw)
+    gekko: Option<GekkoMode>,
+    #[argp(switch)]
+    /// write psq_* GQR operands as qr0-qr7
+    gqr_names: bool,
+    #[argp(switch)]
+    /// fail on Gekko instructions with reserved bits set
+    strict_gekko: bool,
 }
 
 #[derive(FromArgs, PartialEq, Eq, Debug)]
@@ -73,6 +94,18 @@ pub struct FixupArgs {
     out_file: PathBuf,
 }
 
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Repairs the symbol table of an object for linking with CodeWarrior.
+#[argp(subcommand, name = "clean")]
+pub struct CleanArgs {
+    #[argp(positional)]
+    /// input file
+    in_file: PathBuf,
+    #[argp(positional)]
+    /// output file
+    out_file: PathBuf,
+}
+
 #[derive(FromArgs, PartialEq, Eq, Debug)]
 /// Splits an executable ELF into relocatable objects.
 #[argp(subcommand, name = "split")]
@@ -123,6 +156,7 @@ pub struct InfoArgs {
 
 pub fn run(args: Args) -> Result<()> {
     match args.command {
+        SubCommand::Clean(c_args) => clean(c_args),
         SubCommand::Config(c_args) => config(c_args),
         SubCommand::Disasm(c_args) => disasm(c_args),
         SubCommand::Fixup(c_args) => fixup(c_args),
@@ -145,6 +179,15 @@ fn config(args: ConfigArgs) -> Result<()> {
 fn disasm(args: DisasmArgs) -> Result<()> {
     log::info!("Loading {}", args.elf_file.display());
     let obj = process_elf(&args.elf_file)?;
+    let options = AsmOptions {
+        flavor: args.flavor.unwrap_or_default(),
+        register_prefix: args.register_prefix,
+        raw_mnemonics: args.raw_mnemonics,
+        gekko: args.gekko.unwrap_or_default(),
+        gqr_names: args.gqr_names,
+        strict_gekko: args.strict_gekko,
+        ..Default::default()
+    };
     match obj.kind {
         ObjKind::Executable => {
             log::info!("Splitting {} objects", obj.link_order.len());
@@ -161,7 +204,7 @@ fn disasm(args: DisasmArgs) -> Result<()> {
                 log::info!("Writing {}", out_path.display());
 
                 let mut w = buf_writer(out_path)?;
-                write_asm(&mut w, split_obj)?;
+                write_asm(&mut w, split_obj, &options)?;
                 w.flush()?;
 
                 writeln!(files_out, "{}", file_name_from_unit(&unit.name, ".o"))?;
@@ -170,7 +213,7 @@ fn disasm(args: DisasmArgs) -> Result<()> {
         }
         ObjKind::Relocatable => {
             let mut w = buf_writer(args.out)?;
-            write_asm(&mut w, &obj)?;
+            write_asm(&mut w, &obj, &options)?;
             w.flush()?;
         }
     }
@@ -235,9 +278,9 @@ const ASM_SUFFIX: &str = " (asm)";
 // }
 
 fn fixup(args: FixupArgs) -> Result<()> {
-    let in_buf = fs::read(&args.in_file)
-        .with_context(|| format!("Failed to open input file: '{}'", args.in_file.display()))?;
-    let in_file = object::read::File::parse(&*in_buf).context("Failed to parse input ELF")?;
+    let in_buf = map_file(&args.in_file)?;
+    let in_file =
+        object::read::File::parse(in_buf.as_slice()).context("Failed to parse input ELF")?;
     let mut out_file =
         object::write::Object::new(in_file.format(), in_file.architecture(), in_file.endianness());
     out_file.flags =
@@ -401,6 +444,151 @@ fn fixup(args: FixupArgs) -> Result<()> {
     Ok(())
 }
 
+fn clean(args: CleanArgs) -> Result<()> {
+    {
+        let in_buf = map_file(&args.in_file)?;
+        let in_file =
+            object::read::File::parse(in_buf.as_slice()).context("Failed to parse input ELF")?;
+        for section in in_file.sections() {
+            if section.size() == 0
+                || matches!(
+                    section.kind(),
+                    SectionKind::Text
+                        | SectionKind::Data
+                        | SectionKind::ReadOnlyData
+                        | SectionKind::UninitializedData
+                        | SectionKind::Metadata
+                )
+                || section.name().is_ok_and(|name| name == ".comment")
+            {
+                continue;
+            }
+            log::warn!("Dropping section {}", section.name().unwrap_or("[invalid]"));
+        }
+    }
+
+    let mut obj = process_elf(&args.in_file)?;
+    ensure!(obj.kind == ObjKind::Relocatable, "Can only clean relocatable objects");
+
+    let mut symbols: Vec<ObjSymbol> = vec![];
+    let mut symbol_map = vec![0; obj.symbols.count()];
+    let mut addend_fixups = vec![0i64; obj.symbols.count()];
+    let mut section_symbols = HashMap::<usize, usize>::new();
+    let mut key_to_index = HashMap::<(&str, Option<usize>, u64), usize>::new();
+    let (mut duplicates, mut merged_sections) = (0, 0);
+    for (index, symbol) in obj.symbols.iter().enumerate() {
+        // One section symbol per section, at its start
+        if symbol.kind == ObjSymbolKind::Section {
+            let section_index =
+                symbol.section.ok_or_else(|| anyhow!("Section symbol without section"))?;
+            addend_fixups[index] = symbol.address as i64;
+            if let Some(&existing) = section_symbols.get(&section_index) {
+                symbol_map[index] = existing;
+                merged_sections += 1;
+                continue;
+            }
+            section_symbols.insert(section_index, symbols.len());
+            symbol_map[index] = symbols.len();
+            symbols.push(ObjSymbol { address: 0, size: 0, ..symbol.clone() });
+            continue;
+        }
+        // Identical definitions are merged, keeping the strongest binding and largest size
+        if symbol.section.is_some() && !symbol.name.is_empty() {
+            match key_to_index.entry((symbol.name.as_str(), symbol.section, symbol.address)) {
+                hash_map::Entry::Occupied(e) => {
+                    let existing = &mut symbols[*e.get()];
+                    if existing.kind == ObjSymbolKind::Unknown {
+                        existing.kind = symbol.kind;
+                    }
+                    if existing.flags.is_local()
+                        || (existing.flags.is_weak() && symbol.flags.is_global())
+                    {
+                        existing.flags.set_scope(symbol.flags.scope());
+                    }
+                    existing.size = existing.size.max(symbol.size);
+                    symbol_map[index] = *e.get();
+                    duplicates += 1;
+                    continue;
+                }
+                hash_map::Entry::Vacant(e) => {
+                    e.insert(symbols.len());
+                }
+            }
+        }
+        symbol_map[index] = symbols.len();
+        symbols.push(symbol.clone());
+    }
+
+    // Sizes must not run into the following symbol or past the end of the section
+    let mut fixed_sizes = 0;
+    for (section_index, section) in obj.sections.iter() {
+        let mut starts = symbols
+            .iter()
+            .filter(|s| s.section == Some(section_index) && s.kind != ObjSymbolKind::Section)
+            .map(|s| s.address)
+            .collect::<Vec<_>>();
+        starts.sort_unstable();
+        starts.dedup();
+        let section_end = section.address + section.size;
+        for symbol in symbols.iter_mut().filter(|s| {
+            s.section == Some(section_index)
+                && matches!(s.kind, ObjSymbolKind::Function | ObjSymbolKind::Object)
+        }) {
+            // Zero-sized data may be intentional, such as a label at the end of a table
+            if symbol.size == 0 && symbol.kind == ObjSymbolKind::Object
+                || symbol.size != 0 && symbol.address + symbol.size <= section_end
+            {
+                continue;
+            }
+            let next = starts.iter().find(|&&a| a > symbol.address).copied();
+            let new_size = next.unwrap_or(section_end).min(section_end) - symbol.address;
+            if new_size != symbol.size {
+                log::debug!(
+                    "Fixing size of {}: {:#X} -> {:#X}",
+                    symbol.name,
+                    symbol.size,
+                    new_size
+                );
+                symbol.size = new_size;
+                symbol.size_known = true;
+                fixed_sizes += 1;
+            }
+        }
+    }
+
+    // Section symbols, then defined symbols by address, then undefined symbols.
+    // Locals are written before globals.
+    let mut order = (0..symbols.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| {
+        let s = &symbols[i];
+        (s.section.is_none(), s.kind != ObjSymbolKind::Section, s.section, s.address)
+    });
+    let mut new_index = vec![0; symbols.len()];
+    for (new, &old) in order.iter().enumerate() {
+        new_index[old] = new;
+    }
+    let sorted = order.iter().map(|&i| symbols[i].clone()).collect();
+
+    for (_, section) in obj.sections.iter_mut() {
+        for (_, reloc) in section.relocations.iter_mut() {
+            reloc.addend += addend_fixups[reloc.target_symbol];
+            reloc.target_symbol = new_index[symbol_map[reloc.target_symbol]];
+        }
+    }
+    obj.symbols = ObjSymbols::new(obj.kind, sorted);
+
+    log::info!(
+        "Merged {} duplicate symbols and {} section symbols, fixed {} sizes",
+        duplicates,
+        merged_sections,
+        fixed_sizes
+    );
+    let out = write_elf(&obj)?;
+    fs::write(&args.out_file, out)
+        .with_context(|| format!("Failed to write '{}'", args.out_file.display()))?;
+    Ok(())
+}
+
 fn to_write_symbol_section(
     section: SymbolSection,
     section_ids: &[Option<SectionId>],
@@ -482,119 +670,210 @@ fn signatures(args: SignaturesArgs) -> Result<()> {
     Ok(())
 }
 
+#[derive(Serialize)]
+struct ElfInfoRecord {
+    kind: String,
+    section_count: usize,
+    symbol_count: usize,
+    relocation_count: usize,
+    sections: Vec<ElfInfoSection>,
+    symbols: Vec<ElfInfoSymbol>,
+    /// Metrowerks metadata from the `.comment` section
+    comment: Option<ElfInfoComment>,
+}
+
+#[derive(Serialize)]
+struct ElfInfoSection {
+    name: String,
+    kind: String,
+    size: u64,
+    file_offset: u64,
+    index: usize,
+}
+
+#[derive(Serialize)]
+struct ElfInfoSymbol {
+    /// `None` for absolute symbols
+    section: Option<String>,
+    address: u64,
+    size: u64,
+    name: String,
+}
+
+#[derive(Serialize)]
+struct ElfInfoComment {
+    version: u8,
+    compiler_version: String,
+    pool_data: bool,
+    float: MWFloatKind,
+    processor: u16,
+    incompatible_return_small_structs: bool,
+    incompatible_sfpe_double_params: bool,
+    unsafe_global_reg_vars: bool,
+    symbols: Vec<ElfInfoCommentSymbol>,
+}
+
+#[derive(Serialize)]
+struct ElfInfoCommentSymbol {
+    align: u32,
+    vis_flags: u8,
+    active_flags: u8,
+    name: String,
+}
+
 fn info(args: InfoArgs) -> Result<()> {
-    let in_buf = fs::read(&args.input)
-        .with_context(|| format!("Failed to open input file: '{}'", args.input.display()))?;
-    let in_file = object::read::File::parse(&*in_buf).context("Failed to parse input ELF")?;
+    let in_buf = map_file(&args.input)?;
+    let in_file =
+        object::read::File::parse(in_buf.as_slice()).context("Failed to parse input ELF")?;
 
-    println!("ELF type: {:?}", in_file.kind());
-    println!("Section count: {}", in_file.sections().count());
-    println!("Symbol count: {}", in_file.symbols().count());
-    println!(
-        "Relocation count: {}",
-        in_file.sections().map(|s| s.relocations().count()).sum::<usize>()
-    );
+    let mut sections = vec![];
+    for section in in_file.sections().skip(1) {
+        let kind = match section.kind() {
+            SectionKind::Text => "code".to_string(),
+            SectionKind::Data => "data".to_string(),
+            SectionKind::ReadOnlyData => "rodata".to_string(),
+            SectionKind::UninitializedData => "bss".to_string(),
+            SectionKind::Metadata => continue, // "metadata".to_string()
+            SectionKind::Other => "other".to_string(),
+            _ => format!("unknown: {:?}", section.kind()),
+        };
+        sections.push(ElfInfoSection {
+            name: section.name()?.to_string(),
+            kind,
+            size: section.size(),
+            file_offset: section.file_range().unwrap_or_default().0,
+            index: section.index().0,
+        });
+    }
+    let mut symbols = vec![];
+    for symbol in in_file.symbols().filter(|s| s.is_definition()) {
+        let section = match symbol.section_index() {
+            Some(section) => Some(in_file.section_by_index(section)?.name()?.to_string()),
+            None => None,
+        };
+        symbols.push(ElfInfoSymbol {
+            section,
+            address: symbol.address(),
+            size: symbol.size(),
+            name: symbol.name()?.to_string(),
+        });
+    }
+    let comment = match in_file.section_by_name(".comment") {
+        Some(comment_section) => {
+            read_comment_info(&in_file, &comment_section.uncompressed_data()?)?
+        }
+        None => None,
+    };
+    let record = ElfInfoRecord {
+        kind: format!("{:?}", in_file.kind()),
+        section_count: in_file.sections().count(),
+        symbol_count: in_file.symbols().count(),
+        relocation_count: in_file.sections().map(|s| s.relocations().count()).sum(),
+        sections,
+        symbols,
+        comment,
+    };
+    if is_json() {
+        return print_json(&record);
+    }
+
+    println!("ELF type: {}", record.kind);
+    println!("Section count: {}", record.section_count);
+    println!("Symbol count: {}", record.symbol_count);
+    println!("Relocation count: {}", record.relocation_count);
 
     println!("\nSections:");
     println!(
         "{: >15} | {: <10} | {: <10} | {: <10} | {: <10}",
         "Name", "Type", "Size", "File Off", "Index"
     );
-    for section in in_file.sections().skip(1) {
-        let kind_str = match section.kind() {
-            SectionKind::Text => "code".to_cow(),
-            SectionKind::Data => "data".to_cow(),
-            SectionKind::ReadOnlyData => "rodata".to_cow(),
-            SectionKind::UninitializedData => "bss".to_cow(),
-            SectionKind::Metadata => continue, // "metadata".to_cow()
-            SectionKind::Other => "other".to_cow(),
-            _ => format!("unknown: {:?}", section.kind()).into_cow(),
-        };
+    for section in &record.sections {
         println!(
             "{: >15} | {: <10} | {: <#10X} | {: <#10X} | {: <10}",
-            section.name()?,
-            kind_str,
-            section.size(),
-            section.file_range().unwrap_or_default().0,
-            section.index().0
+            section.name, section.kind, section.size, section.file_offset, section.index
         );
     }
 
     println!("\nSymbols:");
     println!("{: >15} | {: <10} | {: <10} | {: <10}", "Section", "Address", "Size", "Name");
-    for symbol in in_file.symbols().filter(|s| s.is_definition()) {
-        let section_str = if let Some(section) = symbol.section_index() {
-            in_file.section_by_index(section)?.name()?.to_string().into_cow()
-        } else {
-            "ABS".to_cow()
-        };
-        let size_str = if symbol.section_index().is_none() {
+    for symbol in &record.symbols {
+        let size_str = if symbol.section.is_none() {
             "ABS".to_cow()
         } else {
-            format!("{:#X}", symbol.size()).into_cow()
+            format!("{:#X}", symbol.size).into_cow()
         };
         println!(
             "{: >15} | {: <#10X} | {: <10} | {: <10}",
-            section_str,
-            symbol.address(),
+            symbol.section.as_deref().unwrap_or("ABS"),
+            symbol.address,
             size_str,
-            symbol.name()?
+            symbol.name
         );
     }
 
-    if let Some(comment_section) = in_file.section_by_name(".comment") {
-        let data = comment_section.uncompressed_data()?;
-        if !data.is_empty() {
-            let mut reader = Cursor::new(&*data);
-            let header = MWComment::from_reader(&mut reader, Endian::Big)
-                .context("While reading .comment section")?;
-            println!("\nMetrowerks metadata (.comment):");
-            println!("\tVersion: {}", header.version);
-            println!(
-                "\tCompiler version: {}.{}.{}.{}",
-                header.compiler_version[0],
-                header.compiler_version[1],
-                header.compiler_version[2],
-                header.compiler_version[3]
-            );
-            println!("\tPool data: {}", header.pool_data);
-            println!("\tFloat: {:?}", header.float);
-            println!(
-                "\tProcessor: {}",
-                if header.processor == 0x16 {
-                    "Gekko".to_cow()
-                } else {
-                    format!("{:#X}", header.processor).into_cow()
-                }
-            );
-            println!(
-                "\tIncompatible return small structs: {}",
-                header.incompatible_return_small_structs
-            );
-            println!(
-                "\tIncompatible sfpe double params: {}",
-                header.incompatible_sfpe_double_params
-            );
-            println!("\tUnsafe global reg vars: {}", header.unsafe_global_reg_vars);
-            println!("\n{: >10} | {: <6} | {: <6} | {: <10}", "Align", "Vis", "Active", "Symbol");
-            for symbol in in_file.symbols() {
-                let comment_sym = CommentSym::from_reader(&mut reader, Endian::Big)?;
-                if symbol.is_definition() {
-                    println!(
-                        "{: >10} | {: <#6X} | {: <#6X} | {: <10}",
-                        comment_sym.align,
-                        comment_sym.vis_flags,
-                        comment_sym.active_flags,
-                        symbol.name()?
-                    );
-                }
+    if let Some(comment) = &record.comment {
+        println!("\nMetrowerks metadata (.comment):");
+        println!("\tVersion: {}", comment.version);
+        println!("\tCompiler version: {}", comment.compiler_version);
+        println!("\tPool data: {}", comment.pool_data);
+        println!("\tFloat: {:?}", comment.float);
+        println!(
+            "\tProcessor: {}",
+            if comment.processor == 0x16 {
+                "Gekko".to_cow()
+            } else {
+                format!("{:#X}", comment.processor).into_cow()
             }
-            ensure!(
-                data.len() - reader.position() as usize == 0,
-                ".comment section data not fully read"
+        );
+        println!(
+            "\tIncompatible return small structs: {}",
+            comment.incompatible_return_small_structs
+        );
+        println!("\tIncompatible sfpe double params: {}", comment.incompatible_sfpe_double_params);
+        println!("\tUnsafe global reg vars: {}", comment.unsafe_global_reg_vars);
+        println!("\n{: >10} | {: <6} | {: <6} | {: <10}", "Align", "Vis", "Active", "Symbol");
+        for symbol in &comment.symbols {
+            println!(
+                "{: >10} | {: <#6X} | {: <#6X} | {: <10}",
+                symbol.align, symbol.vis_flags, symbol.active_flags, symbol.name
             );
         }
     }
 
     Ok(())
 }
+
+/// Reads the `.comment` section, or `None` if it's empty.
+fn read_comment_info(in_file: &object::read::File, data: &[u8]) -> Result<Option<ElfInfoComment>> {
+    if data.is_empty() {
+        return Ok(None);
+    }
+    let mut reader = Cursor::new(data);
+    let header = MWComment::from_reader(&mut reader, Endian::Big)
+        .context("While reading .comment section")?;
+    let mut symbols = vec![];
+    for symbol in in_file.symbols() {
+        let comment_sym = CommentSym::from_reader(&mut reader, Endian::Big)?;
+        if symbol.is_definition() {
+            symbols.push(ElfInfoCommentSymbol {
+                align: comment_sym.align,
+                vis_flags: comment_sym.vis_flags,
+                active_flags: comment_sym.active_flags,
+                name: symbol.name()?.to_string(),
+            });
+        }
+    }
+    ensure!(data.len() - reader.position() as usize == 0, ".comment section data not fully read");
+    let [major, minor, patch, build] = header.compiler_version;
+    Ok(Some(ElfInfoComment {
+        version: header.version,
+        compiler_version: format!("{major}.{minor}.{patch}.{build}"),
+        pool_data: header.pool_data,
+        float: header.float,
+        processor: header.processor,
+        incompatible_return_small_structs: header.incompatible_return_small_structs,
+        incompatible_sfpe_double_params: header.incompatible_sfpe_double_params,
+        unsafe_global_reg_vars: header.unsafe_global_reg_vars,
+        symbols,
+    }))
+}
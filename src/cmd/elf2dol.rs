@@ -1,13 +1,26 @@
 use std::{
+    ffi::OsStr,
     io::{Seek, SeekFrom, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use anyhow::{anyhow, bail, ensure, Result};
-use argp::FromArgs;
+use argp::{FromArgValue, FromArgs};
 use object::{Architecture, Endianness, Object, ObjectKind, ObjectSection, SectionKind};
 
-use crate::util::file::{buf_writer, map_file};
+use crate::{
+    cmd::{dol::ProjectConfig, shasum::file_sha1_string},
+    obj::{ObjInfo, ObjSymbolKind},
+    util::{
+        config::{apply_splits_file, apply_symbols_file, parse_address},
+        dol::{process_dol, DolFile, DolHeader as RawDolHeader},
+        elf::process_elf,
+        file::{buf_reader, buf_writer, map_file},
+        output::{coded_error, is_json, print_json, ErrorCode},
+        reader::{Endian, FromReader},
+    },
+};
 
 #[derive(FromArgs, PartialEq, Eq, Debug)]
 /// Converts an ELF file to a DOL file.
@@ -19,6 +32,56 @@ pub struct Args {
     #[argp(positional)]
     /// path to output DOL
     dol_file: PathBuf,
+    #[argp(option)]
+    /// original DOL to verify the output against
+    verify: Option<PathBuf>,
+    #[argp(option, short = 'c')]
+    /// project config (config.yml) to use for symbols when reporting differences
+    config: Option<PathBuf>,
+    #[argp(option)]
+    /// comma-separated section names, giving the order of the header's text and data slots
+    section_order: Option<String>,
+    #[argp(switch)]
+    /// leave zero-size sections out of the header
+    drop_empty: bool,
+    #[argp(option, from_str_fn(parse_address))]
+    /// byte used to pad sections to their alignment (default 0)
+    pad_value: Option<u32>,
+    #[argp(option, from_str_fn(parse_address))]
+    /// file alignment of sections (default 0x20)
+    align: Option<u32>,
+    #[argp(option)]
+    /// BSS range in the header: span (all BSS sections and anything between) or first
+    bss: Option<BssMode>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum BssMode {
+    /// From the start of the first BSS section to the end of the last.
+    /// Small data sections placed between them are covered as well.
+    #[default]
+    Span,
+    /// Only the first BSS section.
+    First,
+}
+
+impl FromStr for BssMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "span" => Ok(Self::Span),
+            "first" => Ok(Self::First),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromArgValue for BssMode {
+    fn from_arg_value(value: &OsStr) -> Result<Self, String> {
+        String::from_arg_value(value)
+            .and_then(|s| Self::from_str(&s).map_err(|_| "Invalid BSS mode".to_string()))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -55,18 +118,41 @@ pub fn run(args: Args) -> Result<()> {
         kind => bail!("Unexpected ELF type: {kind:?}"),
     }
 
+    let align = args.align.unwrap_or(32);
+    ensure!(align.is_power_of_two(), "Alignment {align:#X} is not a power of two");
+    let pad_value = match args.pad_value {
+        Some(value) => u8::try_from(value).map_err(|_| anyhow!("Invalid pad value {value:#X}"))?,
+        None => 0,
+    };
+    let section_order = args
+        .section_order
+        .as_deref()
+        .map(|s| s.split(',').map(|name| name.trim()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let ordered_sections = |kind: SectionKind| {
+        let mut sections = obj_file
+            .sections()
+            .filter(|s| section_kind(s) == kind && is_alloc(s.flags()))
+            .filter(|s| !args.drop_empty || s.size() > 0)
+            .collect::<Vec<_>>();
+        // Unlisted sections keep their ELF order, after the listed ones
+        sections.sort_by_key(|s| {
+            let name = s.name().unwrap_or_default();
+            section_order.iter().position(|&n| n == name).unwrap_or(usize::MAX)
+        });
+        sections
+    };
+
     let mut header = DolHeader { entry_point: obj_file.entry() as u32, ..Default::default() };
     let mut offset = 0x100u32;
     let mut out = buf_writer(&args.dol_file)?;
     out.seek(SeekFrom::Start(offset as u64))?;
 
     // Text sections
-    for section in
-        obj_file.sections().filter(|s| section_kind(s) == SectionKind::Text && is_alloc(s.flags()))
-    {
+    for section in ordered_sections(SectionKind::Text) {
         log::debug!("Processing text section '{}'", section.name().unwrap_or("[error]"));
         let address = section.address() as u32;
-        let size = align32(section.size() as u32);
+        let size = align_to(section.size() as u32, align);
         *header.text_sections.get_mut(header.text_section_count).ok_or_else(|| {
             anyhow!(
                 "Too many text sections (while processing '{}')",
@@ -74,17 +160,15 @@ pub fn run(args: Args) -> Result<()> {
             )
         })? = DolSection { offset, address, size };
         header.text_section_count += 1;
-        write_aligned(&mut out, section.data()?, size)?;
+        write_aligned(&mut out, section.data()?, size, pad_value)?;
         offset += size;
     }
 
     // Data sections
-    for section in
-        obj_file.sections().filter(|s| section_kind(s) == SectionKind::Data && is_alloc(s.flags()))
-    {
+    for section in ordered_sections(SectionKind::Data) {
         log::debug!("Processing data section '{}'", section.name().unwrap_or("[error]"));
         let address = section.address() as u32;
-        let size = align32(section.size() as u32);
+        let size = align_to(section.size() as u32, align);
         *header.data_sections.get_mut(header.data_section_count).ok_or_else(|| {
             anyhow!(
                 "Too many data sections (while processing '{}')",
@@ -92,7 +176,7 @@ pub fn run(args: Args) -> Result<()> {
             )
         })? = DolSection { offset, address, size };
         header.data_section_count += 1;
-        write_aligned(&mut out, section.data()?, size)?;
+        write_aligned(&mut out, section.data()?, size, pad_value)?;
         offset += size;
     }
 
@@ -105,6 +189,8 @@ pub fn run(args: Args) -> Result<()> {
         let size = section.size() as u32;
         if header.bss_address == 0 {
             header.bss_address = address;
+        } else if args.bss.unwrap_or_default() == BssMode::First {
+            break;
         }
         header.bss_size = (address + size) - header.bss_address;
     }
@@ -141,21 +227,173 @@ pub fn run(args: Args) -> Result<()> {
 
     // Done!
     out.flush()?;
+
+    if let Some(orig_path) = &args.verify {
+        verify_dol(&args, orig_path)?;
+    }
     Ok(())
 }
 
-#[inline]
-const fn align32(x: u32) -> u32 { (x + 31) & !31 }
+/// Compares the output DOL against the original, reporting the differing sections
+/// and the first differing address in each.
+fn verify_dol(args: &Args, orig_path: &Path) -> Result<()> {
+    let out_file = map_file(&args.dol_file)?;
+    let orig_file = map_file(orig_path)?;
+    let out_hash = file_sha1_string(&mut out_file.as_reader())?;
+    let orig_hash = file_sha1_string(&mut orig_file.as_reader())?;
+    if out_hash == orig_hash {
+        if is_json() {
+            print_json(&serde_json::json!({
+                "file": args.dol_file,
+                "verified": true,
+                "sha1": out_hash,
+            }))?;
+        } else {
+            println!("{}: OK", args.dol_file.display());
+        }
+        return Ok(());
+    }
+
+    let context = match load_context(args, orig_file.as_slice()) {
+        Ok(obj) => Some(obj),
+        Err(e) => {
+            log::warn!("Failed to load symbols for context: {e:#}");
+            None
+        }
+    };
+    let out_header = DolFile::from_reader(&mut out_file.as_reader(), Endian::Big)?.header;
+    let orig_header = DolFile::from_reader(&mut orig_file.as_reader(), Endian::Big)?.header;
+    let mut differences = vec![];
+    if out_header.entry_point != orig_header.entry_point {
+        differences.push(format!(
+            "Entry point: expected {:#010X}, but was {:#010X}",
+            orig_header.entry_point, out_header.entry_point
+        ));
+    }
+    if (out_header.bss_addr, out_header.bss_size) != (orig_header.bss_addr, orig_header.bss_size) {
+        differences.push(format!(
+            "BSS: expected {:#010X} size {:#X}, but was {:#010X} size {:#X}",
+            orig_header.bss_addr, orig_header.bss_size, out_header.bss_addr, out_header.bss_size
+        ));
+    }
+    for ((name, orig_slot), (_, out_slot)) in
+        header_slots(&orig_header).zip(header_slots(&out_header))
+    {
+        let (orig_offset, orig_address, orig_size) = orig_slot;
+        let (out_offset, out_address, out_size) = out_slot;
+        if orig_size == 0 && out_size == 0 {
+            continue;
+        }
+        if orig_address != out_address || orig_size != out_size {
+            differences.push(format!(
+                "{name}: expected {orig_address:#010X} size {orig_size:#X}, \
+                 but was {out_address:#010X} size {out_size:#X}"
+            ));
+            if orig_address != out_address {
+                continue;
+            }
+        }
+        let size = orig_size.min(out_size) as usize;
+        let (Some(orig_data), Some(out_data)) = (
+            orig_file.as_slice().get(orig_offset as usize..orig_offset as usize + size),
+            out_file.as_slice().get(out_offset as usize..out_offset as usize + size),
+        ) else {
+            differences.push(format!("{name}: section data out of bounds"));
+            continue;
+        };
+        if let Some(pos) = orig_data.iter().zip(out_data).position(|(a, b)| a != b) {
+            let address = orig_address + pos as u32;
+            let mut message = format!(
+                "{name}: first difference at {address:#010X} (file offset {:#X})",
+                orig_offset as usize + pos
+            );
+            if let Some(symbol) = context.as_ref().and_then(|obj| symbol_context(obj, address)) {
+                message.push_str(&format!(" in {symbol}"));
+            }
+            differences.push(message);
+        }
+    }
+
+    for difference in &differences {
+        if is_json() {
+            print_json(&serde_json::json!({ "file": args.dol_file, "difference": difference }))?;
+        } else {
+            println!("{}: {}", args.dol_file.display(), difference);
+        }
+    }
+    Err(coded_error(
+        ErrorCode::ChecksumMismatch,
+        format!(
+            "'{}' doesn't match '{}': expected {}, but was {}",
+            args.dol_file.display(),
+            orig_path.display(),
+            orig_hash,
+            out_hash
+        ),
+    ))
+}
 
-const ZERO_BUF: [u8; 32] = [0u8; 32];
+/// Named `(offset, address, size)` entries of a DOL header's section table.
+fn header_slots(header: &RawDolHeader) -> impl Iterator<Item = (String, (u32, u32, u32))> + '_ {
+    let text = (0..MAX_TEXT_SECTIONS).map(|i| {
+        (format!("text{i}"), (header.text_offs[i], header.text_addrs[i], header.text_sizes[i]))
+    });
+    let data = (0..MAX_DATA_SECTIONS).map(|i| {
+        (format!("data{i}"), (header.data_offs[i], header.data_addrs[i], header.data_sizes[i]))
+    });
+    text.chain(data)
+}
+
+/// Symbols for describing differences: the original DOL with the project's
+/// splits and symbols applied, or otherwise the input ELF.
+fn load_context(args: &Args, orig_buf: &[u8]) -> Result<ObjInfo> {
+    let Some(config_path) = &args.config else {
+        return process_elf(&args.elf_file);
+    };
+    let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(config_path)?)?;
+    let mut obj = process_dol(orig_buf, config.base.name().as_ref())?;
+    if let Some(splits_path) = &config.base.splits {
+        apply_splits_file(splits_path, &mut obj)?;
+    }
+    if let Some(symbols_path) = &config.base.symbols {
+        apply_symbols_file(symbols_path, &mut obj)?;
+    }
+    Ok(obj)
+}
+
+/// Describes an address as `symbol+offset`, along with its section and unit if known.
+fn symbol_context(obj: &ObjInfo, address: u32) -> Option<String> {
+    let (section_index, section) = obj.sections.at_address(address).ok()?;
+    let (_, symbol) = obj
+        .symbols
+        .for_section_range(section_index, ..=address)
+        .filter(|(_, s)| s.kind != ObjSymbolKind::Section)
+        .next_back()?;
+    let mut context =
+        format!("{}+{:#X} ({})", symbol.name, address - symbol.address as u32, section.name);
+    if let Some((_, split)) = section.splits.for_address(address) {
+        context.push_str(&format!(" [{}]", split.unit));
+    }
+    Some(context)
+}
+
+#[inline]
+const fn align_to(x: u32, align: u32) -> u32 { (x + (align - 1)) & !(align - 1) }
 
 #[inline]
-fn write_aligned<T>(out: &mut T, bytes: &[u8], aligned_size: u32) -> std::io::Result<()>
-where T: Write + ?Sized {
+fn write_aligned<T>(
+    out: &mut T,
+    bytes: &[u8],
+    aligned_size: u32,
+    pad_value: u8,
+) -> std::io::Result<()>
+where
+    T: Write + ?Sized,
+{
     out.write_all(bytes)?;
     let padding = aligned_size - bytes.len() as u32;
     if padding > 0 {
-        out.write_all(&ZERO_BUF[0..padding as usize])?;
+        out.write_all(&vec![pad_value; padding as usize])?;
     }
     Ok(())
 }
@@ -0,0 +1,127 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use argp::FromArgs;
+use rayon::prelude::*;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    analysis::{cfa::SectionAddress, pipeline::Pipeline},
+    cmd::dol::{load_analyze_dol, load_analyze_rel, ProjectConfig},
+    obj::{ObjInfo, ObjSymbolKind},
+    util::{
+        file::buf_reader,
+        fingerprint::{match_objects, MatchMethod},
+        output::{is_json, print_json},
+    },
+};
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Matches functions between two versions of a game by their normalized instructions.
+#[argp(subcommand, name = "fingerprint")]
+pub struct Args {
+    #[argp(positional)]
+    /// project config (config.yml) of the version to match from
+    from_config: PathBuf,
+    #[argp(positional)]
+    /// project config (config.yml) of the version to match to
+    to_config: PathBuf,
+    #[argp(option, short = 'm')]
+    /// minimum confidence to report, from 0 to 1 (default 0.5)
+    min_confidence: Option<f32>,
+}
+
+#[derive(Serialize)]
+struct MatchRecord<'a> {
+    object: &'a str,
+    section: &'a str,
+    from_address: u32,
+    from_name: Option<&'a str>,
+    to_address: u32,
+    to_name: Option<&'a str>,
+    confidence: f32,
+    method: MatchMethod,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let min_confidence = args.min_confidence.unwrap_or(0.5);
+    let (from_config, from_objs) = load_project(&args.from_config)?;
+    let (_, to_objs) = load_project(&args.to_config)?;
+    for (idx, from_obj) in from_objs.iter().enumerate() {
+        let object = if idx == 0 { &from_config.base } else { &from_config.modules[idx - 1] };
+        let object = object.file_name();
+        let Some(to_obj) = to_objs.iter().find(|o| o.module_id == from_obj.module_id) else {
+            info!("{}: no module with ID {} to match", object, from_obj.module_id);
+            continue;
+        };
+        let (from, to, matches) = match_objects(from_obj, to_obj)?;
+        for m in matches.iter().filter(|m| m.confidence >= min_confidence) {
+            let from_fn = &from[m.from];
+            let to_fn = &to[m.to];
+            let section = &from_obj.sections[from_fn.address.section].name;
+            let from_name = function_name(from_obj, from_fn.address);
+            let to_name = function_name(to_obj, to_fn.address);
+            if is_json() {
+                print_json(&MatchRecord {
+                    object: &object,
+                    section,
+                    from_address: from_fn.address.address,
+                    from_name,
+                    to_address: to_fn.address.address,
+                    to_name,
+                    confidence: m.confidence,
+                    method: m.method,
+                })?;
+            } else {
+                println!(
+                    "{} {}:{:#010X} {} -> {:#010X} {} {:.2} {}",
+                    object,
+                    section,
+                    from_fn.address.address,
+                    from_name.unwrap_or("?"),
+                    to_fn.address.address,
+                    to_name.unwrap_or("?"),
+                    m.confidence,
+                    m.method.as_str()
+                );
+            }
+        }
+        info!(
+            "{}: matched {} of {} functions ({} exact)",
+            object,
+            matches.len(),
+            from.len(),
+            matches.iter().filter(|m| m.method == MatchMethod::Exact).count()
+        );
+    }
+    Ok(())
+}
+
+/// Loads and analyzes the DOL and every module of a project, in config order.
+pub fn load_project(config_path: &Path) -> Result<(ProjectConfig, Vec<ObjInfo>)> {
+    let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(config_path)?)
+        .with_context(|| format!("While loading '{}'", config_path.display()))?;
+    config.register_module_names();
+    let pipeline = Pipeline::default();
+    let mut objs = vec![load_analyze_dol(&config, &pipeline)?.obj];
+    let modules = config
+        .modules
+        .par_iter()
+        .map(|module_config| {
+            load_analyze_rel(&config, module_config, &pipeline)
+                .map(|result| result.obj)
+                .with_context(|| format!("While loading '{}'", module_config.object.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    objs.extend(modules);
+    Ok((config, objs))
+}
+
+/// Name of the function at the given address.
+pub fn function_name(obj: &ObjInfo, address: SectionAddress) -> Option<&str> {
+    obj.symbols
+        .at_section_address(address.section, address.address)
+        .find(|(_, s)| s.kind == ObjSymbolKind::Function)
+        .map(|(_, s)| s.name.as_str())
+}
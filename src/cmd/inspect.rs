@@ -0,0 +1,633 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    ffi::OsStr,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{bail, Result};
+use argp::{FromArgValue, FromArgs};
+use object::elf;
+use serde::Serialize;
+
+use crate::util::{
+    dol::DolFile,
+    file::{map_file, read_c_string, read_string},
+    output::{is_json, print_json},
+    reader::{Endian, FromReader},
+    rel::{
+        process_rel_header, process_rel_sections, RelImport, RelRelocRaw, R_DOLPHIN_END,
+        R_DOLPHIN_MRKREF, R_DOLPHIN_NOP, R_DOLPHIN_SECTION,
+    },
+    rso::{symbol_hash, RsoHeader, RsoRelocation, RsoSectionHeader, RsoSymbol, RsoSymbolKind},
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InspectFormat {
+    Dol,
+    Rel,
+    Rso,
+}
+
+impl FromStr for InspectFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dol" => Ok(Self::Dol),
+            "rel" => Ok(Self::Rel),
+            "rso" => Ok(Self::Rso),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromArgValue for InspectFormat {
+    fn from_arg_value(value: &OsStr) -> Result<Self, String> {
+        String::from_arg_value(value)
+            .and_then(|s| Self::from_str(&s).map_err(|_| "Invalid input format".to_string()))
+    }
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Prints the raw header and tables of a DOL, REL or RSO.
+#[argp(subcommand, name = "inspect")]
+pub struct Args {
+    #[argp(positional)]
+    /// input file
+    in_file: PathBuf,
+    #[argp(option)]
+    /// input format (dol, rel, rso), detected from the extension if not specified
+    input_format: Option<InspectFormat>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let format = match args.input_format {
+        Some(format) => format,
+        None => {
+            let extension = args.in_file.extension().and_then(OsStr::to_str).unwrap_or_default();
+            match extension.to_ascii_lowercase().as_str() {
+                "dol" => InspectFormat::Dol,
+                "rel" => InspectFormat::Rel,
+                "rso" | "sel" => InspectFormat::Rso,
+                _ => bail!(
+                    "Failed to detect format of '{}', use --input-format to specify it",
+                    args.in_file.display()
+                ),
+            }
+        }
+    };
+    let file = map_file(&args.in_file)?;
+    let mut reader = file.as_reader();
+    let record = match format {
+        InspectFormat::Dol => InspectRecord::Dol(inspect_dol(&mut reader)?),
+        InspectFormat::Rel => InspectRecord::Rel(inspect_rel(&mut reader)?),
+        InspectFormat::Rso => InspectRecord::Rso(inspect_rso(&mut reader)?),
+    };
+    if is_json() {
+        return print_json(&InspectOutput { file: &args.in_file, record: &record });
+    }
+    println!("{}:", args.in_file.display());
+    match &record {
+        InspectRecord::Dol(dol) => print_dol(dol),
+        InspectRecord::Rel(rel) => print_rel(rel),
+        InspectRecord::Rso(rso) => print_rso(rso),
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct InspectOutput<'a> {
+    file: &'a Path,
+    #[serde(flatten)]
+    record: &'a InspectRecord,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "format", rename_all = "lowercase")]
+enum InspectRecord {
+    Dol(DolRecord),
+    Rel(RelRecord),
+    Rso(RsoRecord),
+}
+
+#[derive(Serialize)]
+struct DolRecord {
+    entry_point: u32,
+    bss_address: u32,
+    bss_size: u32,
+    /// Used text and data slots
+    sections: Vec<DolSlotRecord>,
+}
+
+#[derive(Serialize)]
+struct DolSlotRecord {
+    slot: String,
+    file_offset: u32,
+    address: u32,
+    size: u32,
+    past_end: bool,
+}
+
+#[derive(Serialize)]
+struct RelRecord {
+    module_id: u32,
+    version: u32,
+    num_sections: u32,
+    section_info_offset: u32,
+    name_offset: u32,
+    name_size: u32,
+    bss_size: u32,
+    rel_offset: u32,
+    imp_offset: u32,
+    imp_size: u32,
+    imp_past_end: bool,
+    prolog: SectionOffset,
+    epilog: SectionOffset,
+    unresolved: SectionOffset,
+    align: Option<u32>,
+    bss_align: Option<u32>,
+    fix_size: Option<u32>,
+    sections: Vec<SectionRecord>,
+    imports: Vec<ImportRecord>,
+}
+
+#[derive(Serialize)]
+struct RsoRecord {
+    name: String,
+    name_offset: u32,
+    name_size: u32,
+    version: u32,
+    num_sections: u32,
+    section_info_offset: u32,
+    bss_size: u32,
+    prolog: SectionOffset,
+    epilog: SectionOffset,
+    unresolved: SectionOffset,
+    tables: Vec<TableRecord>,
+    export_names_offset: u32,
+    import_names_offset: u32,
+    sections: Vec<SectionRecord>,
+    internal_relocations: Vec<RelocCount>,
+    external_relocations: Vec<RelocCount>,
+    exports: Vec<ExportRecord>,
+    imports: Vec<RsoImportRecord>,
+}
+
+#[derive(Serialize)]
+struct SectionOffset {
+    section: u8,
+    offset: u32,
+}
+
+#[derive(Serialize)]
+struct SectionRecord {
+    index: u32,
+    offset: u32,
+    size: u32,
+    /// `x` for executable sections, `bss` for uninitialized ones
+    flags: &'static str,
+    past_end: bool,
+}
+
+#[derive(Serialize)]
+struct TableRecord {
+    name: &'static str,
+    offset: u32,
+    size: u32,
+    past_end: bool,
+}
+
+#[derive(Serialize)]
+struct ImportRecord {
+    module_id: u32,
+    offset: u32,
+    /// `R_DOLPHIN_SECTION` entries
+    sections: usize,
+    relocations: Vec<RelocCount>,
+}
+
+#[derive(Serialize)]
+struct RelocCount {
+    kind: Cow<'static, str>,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct ExportRecord {
+    section: u32,
+    offset: u32,
+    hash: u32,
+    name: String,
+    hash_mismatch: bool,
+}
+
+#[derive(Serialize)]
+struct RsoImportRecord {
+    section: u32,
+    offset: u32,
+    name: String,
+}
+
+/// Whether a range extends past the end of the file.
+fn past_end(offset: u32, size: u32, file_size: u64) -> bool {
+    offset as u64 + size as u64 > file_size
+}
+
+/// Marks ranges that extend past the end of the file.
+fn bounds_note(past_end: bool) -> &'static str {
+    if past_end {
+        " (past end of file)"
+    } else {
+        ""
+    }
+}
+
+fn reloc_type_name(kind: u32) -> Cow<'static, str> {
+    Cow::Borrowed(match kind {
+        elf::R_PPC_NONE => "R_PPC_NONE",
+        elf::R_PPC_ADDR32 => "R_PPC_ADDR32",
+        elf::R_PPC_ADDR24 => "R_PPC_ADDR24",
+        elf::R_PPC_ADDR16 => "R_PPC_ADDR16",
+        elf::R_PPC_ADDR16_LO => "R_PPC_ADDR16_LO",
+        elf::R_PPC_ADDR16_HI => "R_PPC_ADDR16_HI",
+        elf::R_PPC_ADDR16_HA => "R_PPC_ADDR16_HA",
+        elf::R_PPC_ADDR14 => "R_PPC_ADDR14",
+        elf::R_PPC_ADDR14_BRTAKEN => "R_PPC_ADDR14_BRTAKEN",
+        elf::R_PPC_ADDR14_BRNTAKEN => "R_PPC_ADDR14_BRNTAKEN",
+        elf::R_PPC_REL24 => "R_PPC_REL24",
+        elf::R_PPC_REL14 => "R_PPC_REL14",
+        elf::R_PPC_REL14_BRTAKEN => "R_PPC_REL14_BRTAKEN",
+        elf::R_PPC_REL14_BRNTAKEN => "R_PPC_REL14_BRNTAKEN",
+        elf::R_PPC_UADDR32 => "R_PPC_UADDR32",
+        elf::R_PPC_UADDR16 => "R_PPC_UADDR16",
+        elf::R_PPC_REL32 => "R_PPC_REL32",
+        R_DOLPHIN_NOP => "R_DOLPHIN_NOP",
+        R_DOLPHIN_SECTION => "R_DOLPHIN_SECTION",
+        R_DOLPHIN_END => "R_DOLPHIN_END",
+        R_DOLPHIN_MRKREF => "R_DOLPHIN_MRKREF",
+        kind => return Cow::Owned(format!("type {kind}")),
+    })
+}
+
+fn reloc_counts(counts: BTreeMap<u32, usize>) -> Vec<RelocCount> {
+    counts
+        .into_iter()
+        .map(|(kind, count)| RelocCount { kind: reloc_type_name(kind), count })
+        .collect()
+}
+
+fn print_reloc_counts(counts: &[RelocCount]) {
+    for count in counts {
+        println!("\t\t{: <20} {}", count.kind, count.count);
+    }
+}
+
+fn section_flags(exec: bool, offset: u32, size: u32) -> &'static str {
+    if exec {
+        "x"
+    } else if offset == 0 && size > 0 {
+        "bss"
+    } else {
+        ""
+    }
+}
+
+fn print_sections(sections: &[SectionRecord]) {
+    println!("\nSections:");
+    println!("\t{: >5} | {: <10} | {: <10} | {: <5}", "Index", "Offset", "Size", "Flags");
+    for section in sections {
+        println!(
+            "\t{: >5} | {: <#10X} | {: <#10X} | {: <5}{}",
+            section.index,
+            section.offset,
+            section.size,
+            section.flags,
+            bounds_note(section.past_end)
+        );
+    }
+}
+
+fn print_entry_points(prolog: &SectionOffset, epilog: &SectionOffset, unresolved: &SectionOffset) {
+    for (name, entry) in [("_prolog", prolog), ("_epilog", epilog), ("_unresolved", unresolved)] {
+        println!("\t{name}: section {} offset {:#X}", entry.section, entry.offset);
+    }
+}
+
+fn inspect_dol<R>(reader: &mut R) -> Result<DolRecord>
+where R: Read + Seek + ?Sized {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let dol = DolFile::from_reader(reader, Endian::Big)?;
+    let header = &dol.header;
+    let text = header
+        .text_offs
+        .iter()
+        .zip(&header.text_addrs)
+        .zip(&header.text_sizes)
+        .enumerate()
+        .map(|(i, v)| (format!("text{i}"), v));
+    let data = header
+        .data_offs
+        .iter()
+        .zip(&header.data_addrs)
+        .zip(&header.data_sizes)
+        .enumerate()
+        .map(|(i, v)| (format!("data{i}"), v));
+    let sections = text
+        .chain(data)
+        .filter(|(_, ((&offset, &address), &size))| offset != 0 || address != 0 || size != 0)
+        .map(|(slot, ((&file_offset, &address), &size))| DolSlotRecord {
+            slot,
+            file_offset,
+            address,
+            size,
+            past_end: past_end(file_offset, size, file_size),
+        })
+        .collect();
+    Ok(DolRecord {
+        entry_point: header.entry_point,
+        bss_address: header.bss_addr,
+        bss_size: header.bss_size,
+        sections,
+    })
+}
+
+fn print_dol(dol: &DolRecord) {
+    println!("DOL header:");
+    println!("\tEntry point: {:#010X}", dol.entry_point);
+    println!("\tBSS: {:#010X} size {:#X}", dol.bss_address, dol.bss_size);
+    println!("\nSections:");
+    println!("\t{: >6} | {: <10} | {: <10} | {: <10}", "Slot", "File Off", "Address", "Size");
+    for section in &dol.sections {
+        println!(
+            "\t{: >6} | {: <#10X} | {:#010X} | {: <#10X}{}",
+            section.slot,
+            section.file_offset,
+            section.address,
+            section.size,
+            bounds_note(section.past_end)
+        );
+    }
+}
+
+fn inspect_rel<R>(reader: &mut R) -> Result<RelRecord>
+where R: Read + Seek + ?Sized {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let header = process_rel_header(reader)?;
+    let sections = process_rel_sections(reader, &header)?
+        .iter()
+        .enumerate()
+        .map(|(idx, section)| SectionRecord {
+            index: idx as u32,
+            offset: section.offset(),
+            size: section.size(),
+            flags: section_flags(section.exec(), section.offset(), section.size()),
+            past_end: past_end(section.offset(), section.size(), file_size),
+        })
+        .collect();
+
+    let mut imports = vec![];
+    reader.seek(SeekFrom::Start(header.imp_offset as u64))?;
+    while reader.stream_position()? < (header.imp_offset + header.imp_size) as u64 {
+        imports.push(RelImport::from_reader(reader, Endian::Big)?);
+    }
+    let mut import_records = vec![];
+    for import in imports {
+        reader.seek(SeekFrom::Start(import.offset as u64))?;
+        let mut counts = BTreeMap::<u32, usize>::new();
+        let mut sections = 0;
+        loop {
+            let reloc = RelRelocRaw::from_reader(reader, Endian::Big)?;
+            match reloc.kind as u32 {
+                R_DOLPHIN_END => break,
+                R_DOLPHIN_SECTION => sections += 1,
+                kind => *counts.entry(kind).or_default() += 1,
+            }
+        }
+        import_records.push(ImportRecord {
+            module_id: import.module_id,
+            offset: import.offset,
+            sections,
+            relocations: reloc_counts(counts),
+        });
+    }
+    Ok(RelRecord {
+        module_id: header.module_id,
+        version: header.version,
+        num_sections: header.num_sections,
+        section_info_offset: header.section_info_offset,
+        name_offset: header.name_offset,
+        name_size: header.name_size,
+        bss_size: header.bss_size,
+        rel_offset: header.rel_offset,
+        imp_offset: header.imp_offset,
+        imp_size: header.imp_size,
+        imp_past_end: past_end(header.imp_offset, header.imp_size, file_size),
+        prolog: SectionOffset { section: header.prolog_section, offset: header.prolog_offset },
+        epilog: SectionOffset { section: header.epilog_section, offset: header.epilog_offset },
+        unresolved: SectionOffset {
+            section: header.unresolved_section,
+            offset: header.unresolved_offset,
+        },
+        align: header.align,
+        bss_align: header.bss_align,
+        fix_size: header.fix_size,
+        sections,
+        imports: import_records,
+    })
+}
+
+fn print_rel(rel: &RelRecord) {
+    println!("REL header:");
+    println!("\tModule ID: {}", rel.module_id);
+    println!("\tVersion: {}", rel.version);
+    println!("\tSections: {} at {:#X}", rel.num_sections, rel.section_info_offset);
+    println!("\tName: offset {:#X} size {:#X}", rel.name_offset, rel.name_size);
+    println!("\tBSS size: {:#X}", rel.bss_size);
+    println!("\tRelocations: {:#X}", rel.rel_offset);
+    println!(
+        "\tImports: {:#X} size {:#X}{}",
+        rel.imp_offset,
+        rel.imp_size,
+        bounds_note(rel.imp_past_end)
+    );
+    print_entry_points(&rel.prolog, &rel.epilog, &rel.unresolved);
+    if let Some(align) = rel.align {
+        println!("\tAlignment: {align:#X}");
+    }
+    if let Some(bss_align) = rel.bss_align {
+        println!("\tBSS alignment: {bss_align:#X}");
+    }
+    if let Some(fix_size) = rel.fix_size {
+        println!("\tFix size: {fix_size:#X}");
+    }
+
+    print_sections(&rel.sections);
+
+    println!("\nImports:");
+    for import in &rel.imports {
+        println!(
+            "\tModule {} at {:#X}: {} relocations in {} sections",
+            import.module_id,
+            import.offset,
+            import.relocations.iter().map(|c| c.count).sum::<usize>(),
+            import.sections
+        );
+        print_reloc_counts(&import.relocations);
+    }
+}
+
+fn inspect_rso<R>(reader: &mut R) -> Result<RsoRecord>
+where R: Read + Seek + ?Sized {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let header = RsoHeader::from_reader(reader, Endian::Big)?;
+    let name = match header.name_offset {
+        0 => String::new(),
+        _ => read_string(reader, header.name_offset as u64, header.name_size as usize)?,
+    };
+    let tables = [
+        ("Internal relocations", header.internal_rel_offset, header.internal_rel_size),
+        ("External relocations", header.external_rel_offset, header.external_rel_size),
+        ("Exports", header.export_table_offset, header.export_table_size),
+        ("Imports", header.import_table_offset, header.import_table_size),
+    ];
+
+    let mut sections = vec![];
+    reader.seek(SeekFrom::Start(header.section_info_offset as u64))?;
+    for idx in 0..header.num_sections {
+        let section = RsoSectionHeader::from_reader(reader, Endian::Big)?;
+        sections.push(SectionRecord {
+            index: idx,
+            offset: section.offset(),
+            size: section.size(),
+            flags: section_flags(section.exec(), section.offset(), section.size()),
+            past_end: past_end(section.offset(), section.size(), file_size),
+        });
+    }
+
+    let mut relocations = vec![];
+    for (_, offset, size) in &tables[..2] {
+        let mut counts = BTreeMap::<u32, usize>::new();
+        reader.seek(SeekFrom::Start(*offset as u64))?;
+        while reader.stream_position()? < (offset + size) as u64 {
+            let reloc = RsoRelocation::from_reader(reader, Endian::Big)?;
+            *counts.entry(reloc.rel_type() as u32).or_default() += 1;
+        }
+        relocations.push(reloc_counts(counts));
+    }
+    let external_relocations = relocations.pop().unwrap();
+    let internal_relocations = relocations.pop().unwrap();
+
+    let mut exports = vec![];
+    reader.seek(SeekFrom::Start(header.export_table_offset as u64))?;
+    while reader.stream_position()? < (header.export_table_offset + header.export_table_size) as u64
+    {
+        let symbol = RsoSymbol::from_reader_args(reader, Endian::Big, RsoSymbolKind::Export)?;
+        let name =
+            read_c_string(reader, (header.export_table_name_offset + symbol.name_offset) as u64)?;
+        let hash = symbol.hash.unwrap_or_default();
+        exports.push(ExportRecord {
+            section: symbol.section_index,
+            offset: symbol.offset,
+            hash,
+            hash_mismatch: hash != symbol_hash(&name),
+            name,
+        });
+    }
+
+    let mut imports = vec![];
+    reader.seek(SeekFrom::Start(header.import_table_offset as u64))?;
+    while reader.stream_position()? < (header.import_table_offset + header.import_table_size) as u64
+    {
+        let symbol = RsoSymbol::from_reader_args(reader, Endian::Big, RsoSymbolKind::Import)?;
+        let name =
+            read_c_string(reader, (header.import_table_name_offset + symbol.name_offset) as u64)?;
+        imports.push(RsoImportRecord {
+            section: symbol.section_index,
+            offset: symbol.offset,
+            name,
+        });
+    }
+
+    Ok(RsoRecord {
+        name,
+        name_offset: header.name_offset,
+        name_size: header.name_size,
+        version: header.version,
+        num_sections: header.num_sections,
+        section_info_offset: header.section_info_offset,
+        bss_size: header.bss_size,
+        prolog: SectionOffset { section: header.prolog_section, offset: header.prolog_offset },
+        epilog: SectionOffset { section: header.epilog_section, offset: header.epilog_offset },
+        unresolved: SectionOffset {
+            section: header.unresolved_section,
+            offset: header.unresolved_offset,
+        },
+        tables: tables
+            .iter()
+            .map(|&(name, offset, size)| TableRecord {
+                name,
+                offset,
+                size,
+                past_end: past_end(offset, size, file_size),
+            })
+            .collect(),
+        export_names_offset: header.export_table_name_offset,
+        import_names_offset: header.import_table_name_offset,
+        sections,
+        internal_relocations,
+        external_relocations,
+        exports,
+        imports,
+    })
+}
+
+fn print_rso(rso: &RsoRecord) {
+    println!("RSO header:");
+    println!("\tName: {} (offset {:#X} size {:#X})", rso.name, rso.name_offset, rso.name_size);
+    println!("\tVersion: {}", rso.version);
+    println!("\tSections: {} at {:#X}", rso.num_sections, rso.section_info_offset);
+    println!("\tBSS size: {:#X}", rso.bss_size);
+    print_entry_points(&rso.prolog, &rso.epilog, &rso.unresolved);
+    for table in &rso.tables {
+        println!(
+            "\t{}: {:#X} size {:#X}{}",
+            table.name,
+            table.offset,
+            table.size,
+            bounds_note(table.past_end)
+        );
+    }
+    println!("\tExport names: {:#X}", rso.export_names_offset);
+    println!("\tImport names: {:#X}", rso.import_names_offset);
+
+    print_sections(&rso.sections);
+
+    for (table, counts) in
+        rso.tables.iter().zip([&rso.internal_relocations, &rso.external_relocations])
+    {
+        println!("\n{}: {}", table.name, counts.iter().map(|c| c.count).sum::<usize>());
+        print_reloc_counts(counts);
+    }
+
+    println!("\nExports:");
+    println!("\t{: >7} | {: <10} | {: <10} | {: <10}", "Section", "Offset", "Hash", "Name");
+    for symbol in &rso.exports {
+        let note = if symbol.hash_mismatch { " (hash mismatch)" } else { "" };
+        println!(
+            "\t{: >7} | {: <#10X} | {:#010X} | {}{}",
+            symbol.section, symbol.offset, symbol.hash, symbol.name, note
+        );
+    }
+
+    println!("\nImports:");
+    println!("\t{: >7} | {: <10} | {: <10}", "Section", "Offset", "Name");
+    for symbol in &rso.imports {
+        println!("\t{: >7} | {: <#10X} | {}", symbol.section, symbol.offset, symbol.name);
+    }
+}
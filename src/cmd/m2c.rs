@@ -0,0 +1,190 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use argp::FromArgs;
+use object::Object;
+
+use crate::{
+    cmd::{dwarf::write_c_context, fingerprint::load_project},
+    util::{
+        file::{buf_reader, buf_writer, map_file},
+        header::generate_c_declarations,
+        output::ensure_text_output,
+    },
+};
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Decompiles a function with m2c.
+#[argp(subcommand, name = "m2c")]
+pub struct Args {
+    #[argp(positional)]
+    /// unit assembly file (from `dol split` or `elf disasm`)
+    asm_file: PathBuf,
+    #[argp(positional)]
+    /// function symbol name
+    function: String,
+    #[argp(option, short = 'o')]
+    /// unit source file to write the draft C into, replacing the function's stub (default: stdout)
+    out: Option<PathBuf>,
+    #[argp(option, short = 'c')]
+    /// C context file (types and declarations)
+    context: Option<PathBuf>,
+    #[argp(option)]
+    /// ELF with DWARF 1.1 info to include in the context
+    dwarf: Option<PathBuf>,
+    #[argp(option)]
+    /// project configuration file whose global symbols are declared in the context
+    config: Option<PathBuf>,
+    #[argp(option, short = 't')]
+    /// YAML file mapping symbol names to declarations (with --config)
+    types: Option<PathBuf>,
+    #[argp(option)]
+    /// m2c target (default: ppc-mwcc-c)
+    target: Option<String>,
+    #[argp(option)]
+    /// path to m2c (default: m2c)
+    m2c: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    if args.out.is_none() {
+        ensure_text_output("m2c without an output file")?;
+    }
+    let context_path = write_context(&args)?;
+    let result = run_m2c(&args, context_path.as_deref());
+    if let Some(path) = &context_path {
+        fs::remove_file(path).ok();
+    }
+    let output = result?;
+
+    match &args.out {
+        Some(out_path) => {
+            let source = if out_path.is_file() {
+                fs::read_to_string(out_path)
+                    .with_context(|| format!("Failed to read '{}'", out_path.display()))?
+            } else {
+                String::new()
+            };
+            let source = insert_function(&source, &args.function, &output);
+            fs::write(out_path, source)
+                .with_context(|| format!("Failed to write '{}'", out_path.display()))?;
+            log::info!("Wrote {} to {}", args.function, out_path.display());
+        }
+        None => print!("{output}"),
+    }
+    Ok(())
+}
+
+/// Combines the project's symbol declarations, the user context and DWARF info, as C, into a
+/// temporary context file. Symbols come first, so that later declarations override them.
+fn write_context(args: &Args) -> Result<Option<PathBuf>> {
+    if args.context.is_none() && args.dwarf.is_none() && args.config.is_none() {
+        return Ok(None);
+    }
+    let path = std::env::temp_dir().join(format!("dtk-m2c-{}.c", std::process::id()));
+    let mut w = buf_writer(&path)?;
+    if let Some(config_path) = &args.config {
+        let types: HashMap<String, String> = match &args.types {
+            Some(path) => serde_yaml::from_reader(&mut buf_reader(path)?)
+                .with_context(|| format!("While loading '{}'", path.display()))?,
+            None => HashMap::new(),
+        };
+        let (config, objs) = load_project(config_path)?;
+        let object_names = std::iter::once(config.base.file_name())
+            .chain(config.modules.iter().map(|m| m.file_name()))
+            .collect::<Vec<_>>();
+        let objs = object_names.iter().map(|name| name.as_ref()).zip(&objs).collect::<Vec<_>>();
+        w.write_all(generate_c_declarations(&objs, &types)?.as_bytes())?;
+        writeln!(w)?;
+    }
+    if let Some(context) = &args.context {
+        let data = fs::read(context)
+            .with_context(|| format!("Failed to read context '{}'", context.display()))?;
+        w.write_all(&data)?;
+        writeln!(w)?;
+    }
+    if let Some(dwarf) = &args.dwarf {
+        let file = map_file(dwarf)?;
+        let obj_file = object::read::File::parse(file.as_slice())?;
+        match obj_file.section_by_name(".debug") {
+            Some(debug_section) => write_c_context(&mut w, &obj_file, debug_section)?,
+            None => log::warn!("'{}' has no .debug section, skipping DWARF", dwarf.display()),
+        }
+    }
+    w.flush()?;
+    Ok(Some(path))
+}
+
+fn run_m2c(args: &Args, context: Option<&Path>) -> Result<String> {
+    let m2c = args.m2c.as_deref().unwrap_or(Path::new("m2c"));
+    let mut command = if m2c.extension().is_some_and(|e| e == "py") {
+        let mut command = Command::new("python3");
+        command.arg(m2c);
+        command
+    } else {
+        Command::new(m2c)
+    };
+    command
+        .arg("--target")
+        .arg(args.target.as_deref().unwrap_or("ppc-mwcc-c"))
+        .arg("--function")
+        .arg(&args.function);
+    if let Some(context) = context {
+        command.arg("--context").arg(context);
+    }
+    command.arg(&args.asm_file);
+
+    log::debug!("Running {:?}", command);
+    let output =
+        command.output().with_context(|| format!("Failed to run m2c '{}'", m2c.display()))?;
+    if !output.status.success() {
+        bail!(
+            "m2c failed ({}):\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        );
+    }
+    String::from_utf8(output.stdout).map_err(|e| anyhow!("Invalid m2c output: {e}"))
+}
+
+/// Replaces the function's INCLUDE_ASM/GLOBAL_ASM stub with `code`, or appends it
+/// if no stub is present.
+fn insert_function(source: &str, function: &str, code: &str) -> String {
+    let include_asm = format!(", {function});");
+    let global_asm = format!(", \"{function}\")");
+    let mut out = String::with_capacity(source.len() + code.len());
+    let mut replaced = false;
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if !replaced
+            && ((trimmed.starts_with("INCLUDE_ASM(") && trimmed.ends_with(&include_asm))
+                || (trimmed.starts_with("#pragma GLOBAL_ASM(") && trimmed.ends_with(&global_asm)))
+        {
+            out.push_str(code);
+            if !code.ends_with('\n') {
+                out.push('\n');
+            }
+            replaced = true;
+        } else {
+            out.push_str(line);
+        }
+    }
+    if !replaced {
+        if !out.is_empty() {
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+            if !out.ends_with("\n\n") {
+                out.push('\n');
+            }
+        }
+        out.push_str(code);
+    }
+    out
+}
@@ -3,10 +3,12 @@ use std::path::PathBuf;
 use anyhow::{bail, ensure, Result};
 use argp::FromArgs;
 use cwdemangle::{demangle, DemangleOptions};
+use serde::Serialize;
 
 use crate::util::{
     file::map_file,
-    map::{process_map, SymbolEntry, SymbolRef},
+    map::{process_map, MapInfo, SymbolEntry, SymbolKind, SymbolRef, SymbolVisibility},
+    output::{is_json, print_json},
 };
 
 #[derive(FromArgs, PartialEq, Debug)]
@@ -55,10 +57,69 @@ pub fn run(args: Args) -> Result<()> {
     }
 }
 
+/// A map entry. Only `name` and `demangled` are set for entries that aren't in a section.
+#[derive(Serialize)]
+struct EntryRecord {
+    name: String,
+    demangled: Option<String>,
+    kind: Option<SymbolKind>,
+    visibility: Option<SymbolVisibility>,
+    section: Option<String>,
+    address: Option<u32>,
+    unit: Option<String>,
+}
+
+impl EntryRecord {
+    fn new(entries: &MapInfo, symbol_ref: &SymbolRef) -> Self {
+        match entries.get_section_symbol(symbol_ref) {
+            Some((section, entry)) => Self::from_entry(section, entry),
+            None => Self {
+                name: symbol_ref.name.clone(),
+                demangled: demangle(&symbol_ref.name, &DemangleOptions::default()),
+                kind: None,
+                visibility: None,
+                section: None,
+                address: None,
+                unit: None,
+            },
+        }
+    }
+
+    fn from_entry(section: String, entry: &SymbolEntry) -> Self {
+        Self {
+            name: entry.name.clone(),
+            demangled: entry.demangled.clone(),
+            kind: Some(entry.kind),
+            visibility: Some(entry.visibility),
+            section: Some(section),
+            address: Some(entry.address),
+            unit: entry.unit.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SymbolRecord {
+    #[serde(flatten)]
+    symbol: EntryRecord,
+    references: Vec<EntryRecord>,
+    referenced_from: Vec<EntryRecord>,
+    /// Units the symbol is generated in
+    generated_in: Vec<String>,
+}
+
 fn entries(args: EntriesArgs) -> Result<()> {
     let file = map_file(&args.map_file)?;
     let entries = process_map(&mut file.as_reader(), None, None)?;
     match entries.unit_entries.get_vec(&args.unit) {
+        Some(vec) if is_json() => {
+            let records = vec
+                .iter()
+                .filter(|symbol_ref| !symbol_ref.name.starts_with('@'))
+                .map(|symbol_ref| EntryRecord::new(&entries, symbol_ref))
+                .collect::<Vec<_>>();
+            print_json(&serde_json::json!({ "unit": args.unit, "entries": records }))?;
+        }
         Some(vec) => {
             println!("Entries for {}:", args.unit);
             for symbol_ref in vec {
@@ -115,6 +176,17 @@ fn symbol(args: SymbolArgs) -> Result<()> {
         symbol.unit.as_deref().unwrap_or("(generated)"),
     );
     let symbol_ref = SymbolRef { name: symbol.name.clone(), unit: symbol.unit.clone() };
+    if is_json() {
+        let records = |refs: Option<&Vec<SymbolRef>>| -> Vec<EntryRecord> {
+            refs.into_iter().flatten().map(|x| EntryRecord::new(&entries, x)).collect()
+        };
+        return print_json(&SymbolRecord {
+            symbol: EntryRecord::from_entry(section, &symbol),
+            references: records(entries.entry_references.get_vec(&symbol_ref)),
+            referenced_from: records(entries.entry_referenced_from.get_vec(&symbol_ref)),
+            generated_in: entries.unit_references.get_vec(&symbol_ref).cloned().unwrap_or_default(),
+        });
+    }
     if let Some(vec) = entries.entry_references.get_vec(&symbol_ref) {
         println!("\nKnown references:");
         for x in vec {
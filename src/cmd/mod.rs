@@ -1,15 +1,33 @@
 pub mod alf;
 pub mod ar;
+pub mod buildinfo;
+pub mod completions;
+pub mod config;
 pub mod demangle;
+pub mod diff;
+pub mod disasm;
+pub mod disc;
 pub mod dol;
+pub mod dump;
+pub mod duplicates;
 pub mod dwarf;
 pub mod elf;
 pub mod elf2dol;
+pub mod fingerprint;
+pub mod inspect;
+pub mod m2c;
 pub mod map;
-pub mod metroidbuildinfo;
 pub mod nlzss;
+pub mod nm;
+pub mod port;
+pub mod ram;
 pub mod rarc;
 pub mod rel;
+pub mod rename;
+pub mod report;
 pub mod rso;
 pub mod shasum;
+pub mod signatures;
+pub mod strings;
+pub mod verify_functions;
 pub mod yaz0;
@@ -0,0 +1,347 @@
+use std::{
+    ffi::OsStr,
+    io::{Cursor, Read},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use anyhow::{bail, Context, Result};
+use argp::{FromArgValue, FromArgs};
+use cwdemangle::{demangle, DemangleOptions};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+    cmd::dol::ProjectConfig,
+    obj::{ObjInfo, ObjKind, ObjSectionKind, ObjSymbol, ObjSymbolKind},
+    util::{
+        config::apply_symbols_file,
+        dol::process_dol,
+        elf::process_elf_data,
+        file::{buf_reader, map_file},
+        output::{is_json, print_json},
+        rel::process_rel,
+        rso::process_rso,
+    },
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InputFormat {
+    Elf,
+    Dol,
+    Rel,
+    Rso,
+    Ar,
+    /// A project `config.yml`, listing every module with its symbols file applied.
+    Config,
+}
+
+impl FromStr for InputFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "elf" => Ok(Self::Elf),
+            "dol" => Ok(Self::Dol),
+            "rel" => Ok(Self::Rel),
+            "rso" => Ok(Self::Rso),
+            "ar" => Ok(Self::Ar),
+            "config" => Ok(Self::Config),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromArgValue for InputFormat {
+    fn from_arg_value(value: &OsStr) -> Result<Self, String> {
+        String::from_arg_value(value)
+            .and_then(|s| Self::from_str(&s).map_err(|_| "Invalid input format".to_string()))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SymbolFilter {
+    Function,
+    Object,
+    Section,
+    Unknown,
+}
+
+impl FromStr for SymbolFilter {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "function" => Ok(Self::Function),
+            "object" => Ok(Self::Object),
+            "section" => Ok(Self::Section),
+            "unknown" => Ok(Self::Unknown),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromArgValue for SymbolFilter {
+    fn from_arg_value(value: &OsStr) -> Result<Self, String> {
+        String::from_arg_value(value)
+            .and_then(|s| Self::from_str(&s).map_err(|_| "Invalid symbol type".to_string()))
+    }
+}
+
+impl SymbolFilter {
+    fn matches(self, kind: ObjSymbolKind) -> bool {
+        matches!(
+            (self, kind),
+            (SymbolFilter::Function, ObjSymbolKind::Function)
+                | (SymbolFilter::Object, ObjSymbolKind::Object)
+                | (SymbolFilter::Section, ObjSymbolKind::Section)
+                | (SymbolFilter::Unknown, ObjSymbolKind::Unknown)
+        )
+    }
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Lists symbols from an ELF, DOL, REL, RSO or static library.
+#[argp(subcommand, name = "nm")]
+pub struct Args {
+    #[argp(positional)]
+    /// input file
+    in_file: PathBuf,
+    #[argp(option)]
+    /// input format (elf, dol, rel, rso, ar, config), detected if not specified
+    input_format: Option<InputFormat>,
+    #[argp(option, short = 's')]
+    /// symbols file (symbols.txt) to apply to a DOL, REL or RSO
+    symbols: Option<PathBuf>,
+    #[argp(option)]
+    /// only list symbols in this section
+    section: Option<String>,
+    #[argp(option, short = 't', long = "type")]
+    /// only list symbols of this type (function, object, section, unknown)
+    kind: Option<SymbolFilter>,
+    #[argp(option, short = 'r')]
+    /// only list symbols with names matching this regex
+    regex: Option<String>,
+    #[argp(switch, short = 'C')]
+    /// demangle symbol names
+    demangle: bool,
+    #[argp(switch, short = 'g')]
+    /// only list global symbols
+    global: bool,
+}
+
+#[derive(Serialize)]
+struct SymbolRecord<'a> {
+    object: &'a str,
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    demangled: Option<String>,
+    section: Option<&'a str>,
+    address: u64,
+    size: Option<u64>,
+    kind: &'static str,
+    scope: &'static str,
+    r#type: char,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let regex = args.regex.as_deref().map(Regex::new).transpose()?;
+    let objects = load_objects(&args)?;
+    let show_headers = objects.len() > 1 && !is_json();
+    for (name, obj) in &objects {
+        if show_headers {
+            println!("\n{name}:");
+        }
+        for (_, symbol) in obj.symbols.iter_ordered().chain(obj.symbols.iter_abs()) {
+            let section = symbol.section.map(|idx| obj.sections[idx].name.as_str());
+            if let Some(filter) = &args.section {
+                if section != Some(filter.as_str()) {
+                    continue;
+                }
+            }
+            match args.kind {
+                Some(filter) if !filter.matches(symbol.kind) => continue,
+                // Section symbols are noise unless asked for
+                None if symbol.kind == ObjSymbolKind::Section => continue,
+                _ => {}
+            }
+            if args.global && symbol.flags.is_local() {
+                continue;
+            }
+            if regex.as_ref().is_some_and(|r| !r.is_match(&symbol.name)) {
+                continue;
+            }
+
+            let demangled = if args.demangle {
+                symbol
+                    .demangled_name
+                    .map(String::from)
+                    .or_else(|| demangle(&symbol.name, &DemangleOptions::default()))
+            } else {
+                None
+            };
+            let type_char = symbol_type(obj, symbol);
+            if is_json() {
+                print_json(&SymbolRecord {
+                    object: name,
+                    name: &symbol.name,
+                    demangled,
+                    section,
+                    address: symbol.address,
+                    size: symbol.size_known.then_some(symbol.size),
+                    kind: match symbol.kind {
+                        ObjSymbolKind::Unknown => "unknown",
+                        ObjSymbolKind::Function => "function",
+                        ObjSymbolKind::Object => "object",
+                        ObjSymbolKind::Section => "section",
+                    },
+                    scope: if symbol.flags.is_local() {
+                        "local"
+                    } else if symbol.flags.is_weak() {
+                        "weak"
+                    } else {
+                        "global"
+                    },
+                    r#type: type_char,
+                })?;
+            } else {
+                let size = if symbol.size_known {
+                    format!("{:08X}", symbol.size)
+                } else {
+                    "????????".to_string()
+                };
+                println!(
+                    "{:08X} {} {} {}",
+                    symbol.address,
+                    size,
+                    type_char,
+                    demangled.as_deref().unwrap_or(&symbol.name)
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// nm-style type letter. Lowercase for local symbols.
+fn symbol_type(obj: &ObjInfo, symbol: &ObjSymbol) -> char {
+    let c = if symbol.flags.is_common() {
+        'C'
+    } else if let Some(section_index) = symbol.section {
+        if symbol.flags.is_weak() {
+            return if symbol.kind == ObjSymbolKind::Object { 'V' } else { 'W' };
+        }
+        match obj.sections[section_index].kind {
+            ObjSectionKind::Code => 'T',
+            ObjSectionKind::Data => 'D',
+            ObjSectionKind::ReadOnlyData => 'R',
+            ObjSectionKind::Bss => 'B',
+        }
+    } else if obj.kind == ObjKind::Relocatable && symbol.address == 0 {
+        'U'
+    } else {
+        'A'
+    };
+    if symbol.flags.is_local() {
+        c.to_ascii_lowercase()
+    } else {
+        c
+    }
+}
+
+fn detect_format(args: &Args, buf: &[u8]) -> Result<InputFormat> {
+    if buf.starts_with(b"\x7FELF") {
+        return Ok(InputFormat::Elf);
+    } else if buf.starts_with(b"!<arch>\n") {
+        return Ok(InputFormat::Ar);
+    }
+    let extension = args.in_file.extension().and_then(OsStr::to_str).unwrap_or_default();
+    Ok(match extension.to_ascii_lowercase().as_str() {
+        "dol" => InputFormat::Dol,
+        "rel" => InputFormat::Rel,
+        "rso" | "sel" => InputFormat::Rso,
+        _ => bail!(
+            "Failed to detect format of '{}', use --input-format to specify it",
+            args.in_file.display()
+        ),
+    })
+}
+
+fn load_objects(args: &Args) -> Result<Vec<(String, ObjInfo)>> {
+    let is_config = args.in_file.extension().is_some_and(|e| e == "yml" || e == "yaml");
+    if args.input_format == Some(InputFormat::Config) || (args.input_format.is_none() && is_config)
+    {
+        return load_config(args);
+    }
+
+    let file = map_file(&args.in_file)?;
+    let buf = file.as_slice();
+    let format = match args.input_format {
+        Some(format) => format,
+        None => detect_format(args, buf)?,
+    };
+    let name = args.in_file.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let mut obj = match format {
+        InputFormat::Elf => process_elf_data(buf)?,
+        InputFormat::Dol => process_dol(buf, &name)?,
+        InputFormat::Rel => process_rel(&mut Cursor::new(buf), &name)?.1,
+        InputFormat::Rso => process_rso(&mut Cursor::new(buf))?,
+        InputFormat::Ar => {
+            if args.symbols.is_some() {
+                bail!("Symbols files can't be applied to archives");
+            }
+            return load_archive(buf);
+        }
+        InputFormat::Config => unreachable!(),
+    };
+    if let Some(symbols_path) = &args.symbols {
+        if format == InputFormat::Elf {
+            bail!("Symbols files can't be applied to ELF files");
+        }
+        if apply_symbols_file(symbols_path, &mut obj)?.is_none() {
+            bail!("Symbols file '{}' not found", symbols_path.display());
+        }
+    }
+    Ok(vec![(name, obj)])
+}
+
+fn load_archive(buf: &[u8]) -> Result<Vec<(String, ObjInfo)>> {
+    let mut objects = vec![];
+    let mut archive = ar::Archive::new(buf);
+    while let Some(result) = archive.next_entry() {
+        let mut e = match result {
+            Ok(e) => e,
+            Err(e) => bail!("Failed to read archive entry: {:?}", e),
+        };
+        let name = String::from_utf8_lossy(e.header().identifier()).to_string();
+        let mut data = vec![0u8; e.header().size() as usize];
+        e.read_exact(&mut data)?;
+        let obj = process_elf_data(&data)
+            .with_context(|| format!("Failed to process archive member '{name}'"))?;
+        objects.push((name, obj));
+    }
+    Ok(objects)
+}
+
+fn load_config(args: &Args) -> Result<Vec<(String, ObjInfo)>> {
+    if args.symbols.is_some() {
+        bail!("Symbols files are read from the project config");
+    }
+    let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(&args.in_file)?)?;
+    config.register_module_names();
+    let mut objects = vec![];
+    for (idx, module_config) in std::iter::once(&config.base).chain(&config.modules).enumerate() {
+        let file = map_file(&module_config.object)?;
+        let name = module_config.name().into_owned();
+        let mut obj = if idx == 0 {
+            process_dol(file.as_slice(), &name)?
+        } else {
+            process_rel(&mut Cursor::new(file.as_slice()), &name)?.1
+        };
+        if let Some(symbols_path) = &module_config.symbols {
+            apply_symbols_file(symbols_path, &mut obj)?;
+        }
+        objects.push((module_config.file_name().into_owned(), obj));
+    }
+    Ok(objects)
+}
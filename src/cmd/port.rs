@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use argp::FromArgs;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    analysis::tracker::Tracker,
+    cmd::fingerprint::load_project,
+    util::{
+        config::{write_splits_file, write_symbols_file},
+        output::{is_json, print_json},
+        port::{port_object, ReviewReason},
+    },
+};
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Ports a project's symbols and splits to another version of the game.
+#[argp(subcommand, name = "port")]
+pub struct Args {
+    #[argp(positional)]
+    /// project config (config.yml) of the version to port from
+    from_config: PathBuf,
+    #[argp(positional)]
+    /// project config (config.yml) of the version to port to
+    to_config: PathBuf,
+    #[argp(positional)]
+    /// output directory for the ported symbols.txt and splits.txt files
+    out_dir: PathBuf,
+    #[argp(option, short = 'm')]
+    /// minimum confidence to port a symbol, from 0 to 1 (default 0.5)
+    min_confidence: Option<f32>,
+}
+
+#[derive(Serialize)]
+struct ReviewRecord<'a> {
+    object: &'a str,
+    name: &'a str,
+    section: &'a str,
+    from_address: u32,
+    to_address: Option<u32>,
+    confidence: Option<f32>,
+    reason: ReviewReason,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let min_confidence = args.min_confidence.unwrap_or(0.5);
+    let (from_config, mut from_objs) = load_project(&args.from_config)?;
+    let (_, mut to_objs) = load_project(&args.to_config)?;
+    for obj in from_objs.iter_mut().chain(to_objs.iter_mut()) {
+        let mut tracker = Tracker::new(obj);
+        tracker.process(obj)?;
+        tracker.apply(obj, false)?;
+    }
+    for (idx, from_obj) in from_objs.iter().enumerate() {
+        let (object, out_dir) = if idx == 0 {
+            (from_config.base.file_name(), args.out_dir.clone())
+        } else {
+            let module_config = &from_config.modules[idx - 1];
+            (module_config.file_name(), args.out_dir.join(module_config.name().as_ref()))
+        };
+        let Some(to_obj) = to_objs.iter_mut().find(|o| o.module_id == from_obj.module_id) else {
+            info!("{}: no module with ID {} to port to", object, from_obj.module_id);
+            continue;
+        };
+        let result = port_object(from_obj, to_obj, min_confidence)?;
+        for item in &result.review {
+            if is_json() {
+                print_json(&ReviewRecord {
+                    object: &object,
+                    name: &item.name,
+                    section: &item.section,
+                    from_address: item.from_address,
+                    to_address: item.to_address,
+                    confidence: item.confidence,
+                    reason: item.reason,
+                })?;
+            } else {
+                let to_address = match item.to_address {
+                    Some(address) => format!("{:#010X}", address),
+                    None => "?".to_string(),
+                };
+                println!(
+                    "{} {}:{:#010X} {} -> {} {}",
+                    object,
+                    item.section,
+                    item.from_address,
+                    item.name,
+                    to_address,
+                    item.reason.as_str()
+                );
+            }
+        }
+        write_symbols_file(out_dir.join("symbols.txt"), to_obj, None)?;
+        write_splits_file(out_dir.join("splits.txt"), to_obj, false, None)?;
+        info!(
+            "{}: ported {} symbols and {} splits, {} for review",
+            object,
+            result.symbols,
+            result.splits,
+            result.review.len()
+        );
+    }
+    Ok(())
+}
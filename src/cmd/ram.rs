@@ -0,0 +1,210 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use argp::FromArgs;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{
+    cmd::fingerprint::load_project,
+    obj::{ObjInfo, ObjSectionKind},
+    util::{
+        config::parse_address,
+        file::map_file,
+        output::{is_json, print_json},
+        ram::{find_linked_modules, LinkedModule, Memory, MEM1_ADDRESS, MEM2_ADDRESS},
+    },
+};
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Maps addresses in a RAM dump to the project's modules and symbols.
+#[argp(subcommand, name = "ram")]
+pub struct Args {
+    #[argp(positional)]
+    /// project config (config.yml)
+    config: PathBuf,
+    #[argp(positional)]
+    /// MEM1 dump (e.g. Dolphin's mem1.raw)
+    mem1: PathBuf,
+    #[argp(positional, from_str_fn(parse_address))]
+    /// addresses to look up
+    addresses: Vec<u32>,
+    #[argp(option)]
+    /// MEM2 dump (Wii)
+    mem2: Option<PathBuf>,
+    #[argp(option, short = 's')]
+    /// symbol to print the current value of (can be repeated)
+    symbol: Vec<String>,
+}
+
+/// A module found in the dump.
+#[derive(Serialize)]
+struct LoadedModule {
+    name: String,
+    module_id: u32,
+    address: u32,
+    /// Address of each REL section (BSS included), indexed like the REL's section table.
+    #[serde(skip)]
+    sections: Vec<(u32, u32)>,
+}
+
+#[derive(Serialize)]
+struct AddressRecord<'a> {
+    address: u32,
+    module: Option<&'a str>,
+    section: Option<&'a str>,
+    offset: Option<u32>,
+    symbol: Option<&'a str>,
+    symbol_offset: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct SymbolRecord<'a> {
+    name: &'a str,
+    module: &'a str,
+    address: u32,
+    /// Hex-encoded bytes
+    data: Option<String>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let (config, objs) = load_project(&args.config)?;
+    let mut memory =
+        Memory { regions: vec![(MEM1_ADDRESS, map_file(&args.mem1)?.as_slice().to_vec())] };
+    if let Some(mem2) = &args.mem2 {
+        memory.regions.push((MEM2_ADDRESS, map_file(mem2)?.as_slice().to_vec()));
+    }
+
+    // The DOL is loaded at its section addresses
+    let dol = &objs[0];
+    let mut modules = vec![LoadedModule {
+        name: config.base.file_name(),
+        module_id: 0,
+        address: 0,
+        sections: vec![],
+    }];
+    if let Some((_, section)) = dol.sections.iter().find(|(_, s)| s.kind == ObjSectionKind::Code) {
+        let len = section.data.len().min(0x100) as u32;
+        if memory.read(section.address as u32, len) != Some(&section.data[..len as usize]) {
+            warn!("{} doesn't match the dump at {:#010X}", section.name, section.address);
+        }
+    }
+    let module_ids = objs.iter().map(|o| o.module_id).collect::<Vec<_>>();
+    for linked in find_linked_modules(&memory, &module_ids) {
+        let LinkedModule { address, module_id, sections } = linked;
+        let Some(idx) = objs.iter().position(|o| o.module_id == module_id).filter(|&i| i > 0)
+        else {
+            warn!("Unknown module {} at {:#010X}", module_id, address);
+            continue;
+        };
+        modules.push(LoadedModule {
+            name: config.modules[idx - 1].file_name(),
+            module_id,
+            address,
+            sections,
+        });
+    }
+    if is_json() {
+        print_json(&modules[1..])?;
+    } else {
+        for module in &modules[1..] {
+            println!("{:#010X} {} (ID {})", module.address, module.name, module.module_id);
+        }
+    }
+
+    // Runtime address of a symbol
+    let symbol_address =
+        |module: &LoadedModule, obj: &ObjInfo, symbol_section: usize, address: u64| {
+            let section = &obj.sections[symbol_section];
+            if module.module_id == 0 {
+                return Some(address as u32);
+            }
+            let &(start, _) = module.sections.get(section.elf_index)?;
+            Some(start + (address - section.address) as u32)
+        };
+
+    for &address in &args.addresses {
+        let mut record = AddressRecord {
+            address,
+            module: None,
+            section: None,
+            offset: None,
+            symbol: None,
+            symbol_offset: None,
+        };
+        'modules: for module in &modules {
+            let Some(obj) = objs.iter().find(|o| o.module_id == module.module_id) else { continue };
+            for (section_index, section) in obj.sections.iter() {
+                let Some(start) = symbol_address(module, obj, section_index, section.address)
+                else {
+                    continue;
+                };
+                if !(start..start + section.size as u32).contains(&address) {
+                    continue;
+                }
+                let offset = address - start;
+                record.module = Some(module.name.as_str());
+                record.section = Some(section.name.as_str());
+                record.offset = Some(offset);
+                let target = section.address as u32 + offset;
+                if let Some((_, symbol)) = obj
+                    .symbols
+                    .for_section_range(section_index, ..=target)
+                    .rev()
+                    .find(|(_, s)| s.size == 0 || target < (s.address + s.size) as u32)
+                {
+                    record.symbol = Some(symbol.name.as_str());
+                    record.symbol_offset = Some(target - symbol.address as u32);
+                }
+                break 'modules;
+            }
+        }
+        if is_json() {
+            print_json(&record)?;
+            continue;
+        }
+        match (record.module, record.section, record.offset) {
+            (Some(module), Some(section), Some(offset)) => {
+                let symbol = match (record.symbol, record.symbol_offset) {
+                    (Some(name), Some(0)) => format!(" {name}"),
+                    (Some(name), Some(offset)) => format!(" {name}+{offset:#X}"),
+                    _ => String::new(),
+                };
+                println!("{address:#010X} {module} {section}+{offset:#X}{symbol}");
+            }
+            _ => println!("{address:#010X} ?"),
+        }
+    }
+
+    for name in &args.symbol {
+        let found = modules.iter().find_map(|module| {
+            let obj = objs.iter().find(|o| o.module_id == module.module_id)?;
+            let (_, symbol) = obj.symbols.by_name(name).ok()??;
+            let address = symbol_address(module, obj, symbol.section?, symbol.address)?;
+            Some((module, symbol, address))
+        });
+        let Some((module, symbol, address)) = found else {
+            bail!("Symbol '{}' not found in a loaded module", name);
+        };
+        let data = memory.read(address, symbol.size.max(4) as u32);
+        if is_json() {
+            print_json(&SymbolRecord {
+                name,
+                module: &module.name,
+                address,
+                data: data.map(|d| d.iter().map(|b| format!("{b:02x}")).collect()),
+            })?;
+            continue;
+        }
+        println!("{} ({} {:#010X}):", name, module.name, address);
+        let Some(data) = data else {
+            println!("    not in the dump");
+            continue;
+        };
+        for chunk in data.chunks(16) {
+            let hex = chunk.iter().map(|b| format!("{b:02X}")).collect::<Vec<_>>().join(" ");
+            println!("    {hex}");
+        }
+    }
+    Ok(())
+}
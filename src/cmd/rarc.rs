@@ -5,6 +5,8 @@ use argp::FromArgs;
 
 use crate::util::{
     file::{decompress_if_needed, map_file},
+    output::{is_json, print_json},
+    progress::item_progress,
     rarc::{Node, RarcReader},
 };
 
@@ -67,7 +69,15 @@ fn list(args: ListArgs) -> Result<()> {
             }
             Node::File { name, offset, size } => {
                 let path = current_path.join(name.name);
-                println!("{}: {} bytes, offset {:#X}", path.display(), size, offset);
+                if is_json() {
+                    print_json(&serde_json::json!({
+                        "path": path,
+                        "size": size,
+                        "offset": offset,
+                    }))?;
+                } else {
+                    println!("{}: {} bytes, offset {:#X}", path.display(), size, offset);
+                }
             }
             Node::CurrentDirectory => {}
             Node::ParentDirectory => {}
@@ -81,6 +91,8 @@ fn extract(args: ExtractArgs) -> Result<()> {
     let rarc = RarcReader::new(&mut file.as_reader())
         .with_context(|| format!("Failed to process RARC file '{}'", args.file.display()))?;
 
+    let file_count = rarc.nodes().filter(|n| matches!(n, Node::File { .. })).count();
+    let progress = item_progress(file_count as u64, "Extracting");
     let mut current_path = PathBuf::new();
     for node in rarc.nodes() {
         match node {
@@ -102,10 +114,12 @@ fn extract(args: ExtractArgs) -> Result<()> {
                 }
                 fs::write(&output_path, file_data)
                     .with_context(|| format!("Failed to write file '{}'", output_path.display()))?;
+                progress.inc(1);
             }
             Node::CurrentDirectory => {}
             Node::ParentDirectory => {}
         }
     }
+    progress.finish_and_clear();
     Ok(())
 }
@@ -1,7 +1,7 @@
 use std::{
-    collections::{btree_map, BTreeMap},
-    fs,
-    io::Write,
+    collections::{btree_map, hash_map, BTreeMap},
+    fmt, fs,
+    io::{Cursor, Write},
     path::PathBuf,
     time::Instant,
 };
@@ -13,33 +13,36 @@ use object::{
     SectionIndex, SymbolIndex,
 };
 use rayon::prelude::*;
-use rustc_hash::FxHashMap;
-use tracing::{info, info_span};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Serialize;
+use tracing::{debug, info, info_span, warn};
 
 use crate::{
     analysis::{
-        cfa::{AnalyzerState, SectionAddress},
-        pass::{
-            AnalysisPass, FindRelCtorsDtors, FindRelRodataData, FindSaveRestSleds,
-            FindTRKInterruptVectorTable,
-        },
+        cfa::SectionAddress,
+        pipeline::Pipeline,
         signatures::{apply_signatures, apply_signatures_post},
         tracker::Tracker,
     },
     array_ref_mut,
-    cmd::dol::{ModuleConfig, ProjectConfig},
+    cmd::dol::{InfoSection, InfoSymbol, ModuleConfig, ProjectConfig},
     obj::{ObjInfo, ObjReloc, ObjRelocKind, ObjSection, ObjSectionKind, ObjSymbol},
     util::{
-        config::{is_auto_symbol, read_splits_sections, SectionDef},
+        config::{parse_address, read_splits_sections, SectionDef},
         dol::process_dol,
         elf::{to_obj_reloc_kind, write_elf},
         file::{buf_reader, buf_writer, map_file, process_rsp, verify_hash, FileIterator},
         nested::NestedMap,
+        output::{is_json, print_json},
+        ram::{find_linked_modules, Memory, MEM1_ADDRESS, MEM2_ADDRESS},
         rel::{
-            print_relocations, process_rel, process_rel_header, process_rel_sections, write_rel,
-            RelHeader, RelReloc, RelSectionHeader, RelWriteInfo, PERMITTED_SECTIONS,
+            check_rel, print_relocations, process_rel, process_rel_header, process_rel_imports,
+            process_rel_sections, rel_reloc_stats, replace_rel_relocations, set_module_name,
+            write_rel, DisplayModule, RelHeader, RelImport, RelLayout, RelLayoutPreset, RelReloc,
+            RelRelocReader, RelSectionHeader, RelWriteInfo, PERMITTED_SECTIONS,
         },
-        IntoCow, ToCow,
+        rel_recover::{recover_rel, RecoverOptions},
+        timings::Timings,
     },
 };
 
@@ -54,9 +57,13 @@ pub struct Args {
 #[derive(FromArgs, PartialEq, Debug)]
 #[argp(subcommand)]
 enum SubCommand {
+    Check(CheckArgs),
+    Edit(EditArgs),
     Info(InfoArgs),
     Make(MakeArgs),
     Merge(MergeArgs),
+    Recover(RecoverArgs),
+    Stats(StatsArgs),
 }
 
 #[derive(FromArgs, PartialEq, Eq, Debug)]
@@ -71,6 +78,125 @@ pub struct InfoArgs {
     relocations: bool,
 }
 
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Validates the internal consistency of REL files.
+#[argp(subcommand, name = "check")]
+pub struct CheckArgs {
+    #[argp(positional)]
+    /// REL file(s)
+    rel_files: Vec<PathBuf>,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Adds, deletes or retargets individual relocations of a REL.
+#[argp(subcommand, name = "edit")]
+pub struct EditArgs {
+    #[argp(positional)]
+    /// REL file
+    rel_file: PathBuf,
+    #[argp(option, short = 'o')]
+    /// output REL (default: overwrite the input)
+    out_file: Option<PathBuf>,
+    #[argp(option, from_str_fn(parse_reloc_edit))]
+    /// add a relocation, as section:offset:type=module:section:addend (can be repeated)
+    add: Vec<RelocEdit>,
+    #[argp(option, from_str_fn(parse_reloc_site))]
+    /// delete the relocations at section:offset[:type] (can be repeated)
+    delete: Vec<RelocSite>,
+    #[argp(option, from_str_fn(parse_reloc_edit))]
+    /// retarget the relocations at section:offset[:type]=module:section:addend (can be repeated)
+    retarget: Vec<RelocEdit>,
+}
+
+/// Relocations at an offset in a REL section, optionally of one type.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+struct RelocSite {
+    section: u8,
+    offset: u32,
+    kind: Option<ObjRelocKind>,
+}
+
+impl RelocSite {
+    fn matches(&self, reloc: &RelReloc) -> bool {
+        reloc.section == self.section
+            && reloc.address == self.offset
+            && self.kind.map_or(true, |kind| kind == reloc.kind)
+    }
+}
+
+impl fmt::Display for RelocSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{:#X}", self.section, self.offset)?;
+        if let Some(kind) = self.kind {
+            write!(f, ":{:?}", kind)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+struct RelocEdit {
+    site: RelocSite,
+    module_id: u32,
+    target_section: u8,
+    addend: u32,
+}
+
+fn parse_reloc_kind(s: &str) -> Result<ObjRelocKind, String> {
+    let name = s.to_ascii_uppercase();
+    Ok(match name.strip_prefix("R_PPC_").unwrap_or(&name) {
+        "ADDR32" => ObjRelocKind::Absolute,
+        "ADDR16_LO" => ObjRelocKind::PpcAddr16Lo,
+        "ADDR16_HI" => ObjRelocKind::PpcAddr16Hi,
+        "ADDR16_HA" => ObjRelocKind::PpcAddr16Ha,
+        "REL24" => ObjRelocKind::PpcRel24,
+        "REL14" => ObjRelocKind::PpcRel14,
+        _ => return Err(format!("Unsupported relocation type '{s}'")),
+    })
+}
+
+fn parse_reloc_site(s: &str) -> Result<RelocSite, String> {
+    let mut parts = s.split(':');
+    let (Some(section), Some(offset)) = (parts.next(), parts.next()) else {
+        return Err(format!("Expected section:offset[:type], got '{s}'"));
+    };
+    let kind = parts.next().map(parse_reloc_kind).transpose()?;
+    if parts.next().is_some() {
+        return Err(format!("Expected section:offset[:type], got '{s}'"));
+    }
+    Ok(RelocSite {
+        section: section.parse().map_err(|e| format!("Invalid section '{section}': {e}"))?,
+        offset: parse_address(offset)?,
+        kind,
+    })
+}
+
+fn parse_reloc_edit(s: &str) -> Result<RelocEdit, String> {
+    let Some((site, target)) = s.split_once('=') else {
+        return Err(format!("Expected section:offset[:type]=module:section:addend, got '{s}'"));
+    };
+    let [module_id, target_section, addend] = target.split(':').collect::<Vec<_>>()[..] else {
+        return Err(format!("Expected module:section:addend, got '{target}'"));
+    };
+    Ok(RelocEdit {
+        site: parse_reloc_site(site)?,
+        module_id: module_id.parse().map_err(|e| format!("Invalid module '{module_id}': {e}"))?,
+        target_section: target_section
+            .parse()
+            .map_err(|e| format!("Invalid section '{target_section}': {e}"))?,
+        addend: parse_address(addend)?,
+    })
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Summarizes the relocations of REL files.
+#[argp(subcommand, name = "stats")]
+pub struct StatsArgs {
+    #[argp(positional)]
+    /// REL file(s)
+    rel_files: Vec<PathBuf>,
+}
+
 #[derive(FromArgs, PartialEq, Eq, Debug)]
 /// Merges a DOL + REL(s) into an ELF.
 #[argp(subcommand, name = "merge")]
@@ -84,6 +210,50 @@ pub struct MergeArgs {
     #[argp(option, short = 'o')]
     /// output ELF
     out_file: PathBuf,
+    #[argp(option, short = 'a', from_str_fn(parse_load_address))]
+    /// load address of a REL, as name:0x80500000 or id:0x80500000 (can be repeated)
+    address: Vec<LoadAddress>,
+    #[argp(option)]
+    /// MEM1 dump to take the RELs' addresses from
+    mem1: Option<PathBuf>,
+    #[argp(option)]
+    /// MEM2 dump (Wii)
+    mem2: Option<PathBuf>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct LoadAddress {
+    /// REL file name (without extension) or module ID
+    module: String,
+    address: u32,
+}
+
+fn parse_load_address(s: &str) -> Result<LoadAddress, String> {
+    let Some((module, address)) = s.rsplit_once(':') else {
+        return Err(format!("Expected name:address, got '{s}'"));
+    };
+    Ok(LoadAddress { module: module.to_string(), address: parse_address(address)? })
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Reconstructs a REL from a memory dump of the loaded module.
+#[argp(subcommand, name = "recover")]
+pub struct RecoverArgs {
+    #[argp(positional)]
+    /// dump of the module, starting at its header
+    dump_file: PathBuf,
+    #[argp(option, short = 'o')]
+    /// output REL
+    out_file: PathBuf,
+    #[argp(option, short = 'a', from_str_fn(parse_address))]
+    /// (optional) load address of the module (default: from the header)
+    address: Option<u32>,
+    #[argp(option)]
+    /// (optional) the module as stored on disc, to take relocations from
+    original: Option<PathBuf>,
+    #[argp(option)]
+    /// (optional) DOL file, for finding references into the DOL
+    dol: Option<PathBuf>,
 }
 
 #[derive(FromArgs, PartialEq, Eq, Debug)]
@@ -105,12 +275,22 @@ pub struct MakeArgs {
     #[argp(switch, short = 'q')]
     /// only print errors
     quiet: bool,
+    #[argp(option)]
+    /// (optional) REL layout preset (makerel, ascending, original), overriding the config
+    layout: Option<RelLayoutPreset>,
+    #[argp(switch)]
+    /// lay out RELs for OSLinkFixed, overriding the config
+    fixed: bool,
 }
 
 pub fn run(args: Args) -> Result<()> {
     match args.command {
+        SubCommand::Check(c_args) => check(c_args),
+        SubCommand::Edit(c_args) => edit(c_args),
         SubCommand::Info(c_args) => info(c_args),
         SubCommand::Merge(c_args) => merge(c_args),
+        SubCommand::Recover(c_args) => recover(c_args),
+        SubCommand::Stats(c_args) => stats(c_args),
         SubCommand::Make(c_args) => make(c_args),
     }
 }
@@ -170,12 +350,13 @@ fn load_rel(module_config: &ModuleConfig) -> Result<RelInfo> {
     let mut reader = file.as_reader();
     let header = process_rel_header(&mut reader)?;
     let sections = process_rel_sections(&mut reader, &header)?;
+    let imports = process_rel_imports(&mut reader, &header)?;
     let section_defs = if let Some(splits_path) = &module_config.splits {
         read_splits_sections(splits_path)?
     } else {
         None
     };
-    Ok((header, sections, section_defs))
+    Ok((header, sections, section_defs, imports))
 }
 
 fn resolve_relocations(
@@ -192,7 +373,7 @@ fn resolve_relocations(
             continue;
         }
         let section_index =
-            if let Some((_, sections, _)) = existing_headers.get(&(module_id as u32)) {
+            if let Some((_, sections, _, _)) = existing_headers.get(&(module_id as u32)) {
                 match_section_index(module, section.index(), sections)?
             } else {
                 section.index().0
@@ -223,7 +404,7 @@ fn resolve_relocations(
                 (module_id, reloc_target)
             };
             let target_section_index = target_symbol.section_index().unwrap();
-            let target_section = if let Some((_, sections, _)) =
+            let target_section = if let Some((_, sections, _, _)) =
                 existing_headers.get(&(target_module_id as u32))
             {
                 match_section_index(&modules[target_module_id].0, target_section_index, sections)?
@@ -246,15 +427,21 @@ fn resolve_relocations(
     Ok(resolved)
 }
 
-type RelInfo = (RelHeader, Vec<RelSectionHeader>, Option<Vec<SectionDef>>);
+type RelInfo = (RelHeader, Vec<RelSectionHeader>, Option<Vec<SectionDef>>, Vec<RelImport>);
 
 fn make(args: MakeArgs) -> Result<()> {
     let total = Instant::now();
 
     // Load existing REL headers (if specified)
     let mut existing_headers = BTreeMap::<u32, RelInfo>::new();
+    let mut layouts = BTreeMap::<u32, RelLayout>::new();
+    let mut default_layout = RelLayout::default();
     if let Some(config_path) = &args.config {
         let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(config_path)?)?;
+        config.register_module_names();
+        if let Some(layout) = &config.base.rel_layout {
+            default_layout = layout.clone();
+        }
         for module_config in &config.modules {
             if !args.names.is_empty() && !args.names.iter().any(|n| n == &module_config.name()) {
                 continue;
@@ -263,6 +450,10 @@ fn make(args: MakeArgs) -> Result<()> {
             let info = load_rel(module_config).with_context(|| {
                 format!("While loading REL '{}'", module_config.object.display())
             })?;
+            set_module_name(info.0.module_id, module_config.file_name(), false);
+            if let Some(layout) = &module_config.rel_layout {
+                layouts.insert(info.0.module_id, layout.clone());
+            }
             match existing_headers.entry(info.0.module_id) {
                 btree_map::Entry::Vacant(e) => e.insert(info),
                 btree_map::Entry::Occupied(_) => {
@@ -292,11 +483,29 @@ fn make(args: MakeArgs) -> Result<()> {
     // Create symbol map
     let start = Instant::now();
     let mut symbol_map = FxHashMap::<&[u8], (usize, SymbolIndex)>::default();
+    let mut weak_symbols = FxHashSet::<&[u8]>::default();
     for (module_id, (module, path)) in modules.iter().enumerate() {
         let _span = info_span!("file", path = %path.display()).entered();
         for symbol in module.symbols() {
             if symbol.is_definition() && symbol.scope() == object::SymbolScope::Dynamic {
-                symbol_map.entry(symbol.name_bytes()?).or_insert((module_id, symbol.index()));
+                let name = symbol.name_bytes()?;
+                match symbol_map.entry(name) {
+                    hash_map::Entry::Vacant(e) => {
+                        e.insert((module_id, symbol.index()));
+                    }
+                    // A strong definition replaces the first weak one
+                    hash_map::Entry::Occupied(mut e)
+                        if !symbol.is_weak() && weak_symbols.contains(name) =>
+                    {
+                        e.insert((module_id, symbol.index()));
+                    }
+                    hash_map::Entry::Occupied(_) => continue,
+                }
+                if symbol.is_weak() {
+                    weak_symbols.insert(name);
+                } else {
+                    weak_symbols.remove(name);
+                }
             }
         }
     }
@@ -336,6 +545,10 @@ fn make(args: MakeArgs) -> Result<()> {
         modules.iter().enumerate().skip(1).zip(relocations)
     {
         let _span = info_span!("file", path = %path.display()).entered();
+        let mut layout = layouts.get(&(module_id as u32)).unwrap_or(&default_layout).clone();
+        if let Some(preset) = args.layout {
+            layout.preset = preset;
+        }
         let mut info = RelWriteInfo {
             module_id: module_id as u32,
             version: 3,
@@ -346,8 +559,14 @@ fn make(args: MakeArgs) -> Result<()> {
             section_count: None,
             quiet: args.no_warn,
             section_align: None,
+            module_order: layout.module_order(),
+            import_order: layout.import_order(),
+            original_imports: vec![],
+            pad_value: layout.pad_value,
+            fixed: layout.fixed || args.fixed,
         };
-        if let Some((header, _, section_defs)) = existing_headers.get(&(module_id as u32)) {
+        if let Some((header, _, section_defs, imports)) = existing_headers.get(&(module_id as u32))
+        {
             info.version = header.version;
             info.name_offset = Some(header.name_offset);
             info.name_size = Some(header.name_size);
@@ -358,6 +577,12 @@ fn make(args: MakeArgs) -> Result<()> {
                 .as_ref()
                 .map(|defs| defs.iter().map(|def| def.align).collect())
                 .unwrap_or_default();
+            info.original_imports = imports.clone();
+        } else if layout.needs_original() {
+            bail!(
+                "REL layout for {} requires the original REL in the project config",
+                DisplayModule(module_id as u32)
+            );
         }
         let rel_path = path.with_extension("rel");
         let mut w = buf_writer(&rel_path)?;
@@ -376,18 +601,68 @@ fn make(args: MakeArgs) -> Result<()> {
     Ok(())
 }
 
+#[derive(Serialize)]
+struct RelInfoRecord<'a> {
+    module_id: u32,
+    version: u32,
+    /// Section count of the original ELF
+    num_sections: u32,
+    sections: Vec<InfoSection<'a>>,
+    symbols: Vec<InfoSymbol<'a>>,
+    /// Only written with `--relocations`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    relocations: Option<Vec<RelocRecord>>,
+}
+
+#[derive(Serialize)]
+struct RelocRecord {
+    section: u8,
+    address: u32,
+    kind: ObjRelocKind,
+    module_id: u32,
+    target_section: u8,
+    addend: u32,
+}
+
 fn info(args: InfoArgs) -> Result<()> {
     let file = map_file(args.rel_file)?;
     let (header, mut module_obj) = process_rel(&mut file.as_reader(), "")?;
+    Pipeline::default().analyze(&mut module_obj, false, &mut Timings::new())?;
 
-    let mut state = AnalyzerState::default();
-    state.detect_functions(&module_obj)?;
-    FindRelCtorsDtors::execute(&mut state, &module_obj)?;
-    FindRelRodataData::execute(&mut state, &module_obj)?;
-    state.apply(&mut module_obj)?;
-
-    apply_signatures(&mut module_obj)?;
-    apply_signatures_post(&mut module_obj)?;
+    let sections = module_obj.sections.iter().map(|(_, s)| InfoSection::new(s)).collect::<Vec<_>>();
+    let symbols = module_obj
+        .symbols
+        .iter_ordered()
+        .filter_map(|(_, s)| InfoSymbol::new(&module_obj, s))
+        .collect::<Vec<_>>();
+    if is_json() {
+        let relocations = if args.relocations {
+            let relocations = RelRelocReader::new(file.as_reader(), &header)
+                .exact_addresses()
+                .map(|r| {
+                    r.map(|r| RelocRecord {
+                        section: r.section,
+                        address: r.address,
+                        kind: r.kind,
+                        module_id: r.module_id,
+                        target_section: r.target_section,
+                        addend: r.addend,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Some(relocations)
+        } else {
+            None
+        };
+        return print_json(&RelInfoRecord {
+            module_id: header.module_id,
+            version: header.version,
+            num_sections: header.num_sections,
+            sections,
+            symbols,
+            relocations,
+        });
+    }
 
     println!("REL module ID: {}", header.module_id);
     println!("REL version: {}", header.version);
@@ -397,40 +672,16 @@ fn info(args: InfoArgs) -> Result<()> {
         "{: >10} | {: <10} | {: <10} | {: <10} | {: <10}",
         "Name", "Type", "Size", "File Off", "Index"
     );
-    for (_, section) in module_obj.sections.iter() {
-        let kind_str = match section.kind {
-            ObjSectionKind::Code => "code",
-            ObjSectionKind::Data => "data",
-            ObjSectionKind::ReadOnlyData => "rodata",
-            ObjSectionKind::Bss => "bss",
-        };
+    for section in &sections {
         println!(
             "{: >10} | {: <10} | {: <#10X} | {: <#10X} | {: <10}",
-            section.name, kind_str, section.size, section.file_offset, section.elf_index
+            section.name, section.kind, section.size, section.file_offset, section.index
         );
     }
     println!("\nDiscovered symbols:");
     println!("{: >10} | {: <10} | {: <10} | {: <10}", "Section", "Address", "Size", "Name");
-    for (_, symbol) in module_obj.symbols.iter_ordered() {
-        if symbol.name.starts_with('@') || is_auto_symbol(symbol) {
-            continue;
-        }
-        let section_str = if let Some(section) = symbol.section {
-            module_obj.sections[section].name.as_str()
-        } else {
-            "ABS"
-        };
-        let size_str = if symbol.size_known {
-            format!("{:#X}", symbol.size).into_cow()
-        } else if symbol.section.is_none() {
-            "ABS".to_cow()
-        } else {
-            "?".to_cow()
-        };
-        println!(
-            "{: >10} | {: <#10X} | {: <10} | {: <10}",
-            section_str, symbol.address, size_str, symbol.name
-        );
+    for symbol in &symbols {
+        symbol.print("");
     }
 
     if args.relocations {
@@ -441,9 +692,317 @@ fn info(args: InfoArgs) -> Result<()> {
     Ok(())
 }
 
+fn check(args: CheckArgs) -> Result<()> {
+    let mut failed = 0;
+    for path in process_rsp(&args.rel_files)? {
+        let file = map_file(&path)?;
+        let problems = check_rel(&mut file.as_reader())
+            .with_context(|| format!("Failed to check '{}'", path.display()))?;
+        if is_json() {
+            print_json(&serde_json::json!({
+                "file": path.to_string_lossy(),
+                "problems": problems,
+            }))?;
+        } else if problems.is_empty() {
+            println!("{}: OK", path.display());
+        } else {
+            println!("{}: {} problem(s)", path.display(), problems.len());
+            for problem in &problems {
+                println!("    {problem}");
+            }
+        }
+        if !problems.is_empty() {
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} REL file(s) failed validation");
+    }
+    Ok(())
+}
+
+fn edit(args: EditArgs) -> Result<()> {
+    let data = fs::read(&args.rel_file)
+        .with_context(|| format!("Failed to read '{}'", args.rel_file.display()))?;
+    let mut reader = Cursor::new(data.as_slice());
+    let header = process_rel_header(&mut reader)?;
+    let sections = process_rel_sections(&mut reader, &header)?;
+    let mut relocations = RelRelocReader::new(&mut reader, &header)
+        .exact_addresses()
+        .collect::<Result<Vec<RelReloc>>>()?;
+
+    for site in &args.delete {
+        let count = relocations.len();
+        relocations.retain(|r| !site.matches(r));
+        ensure!(relocations.len() < count, "No relocation at {}", site);
+        info!("Deleted {} relocation(s) at {}", count - relocations.len(), site);
+    }
+    for edit in &args.retarget {
+        let mut count = 0;
+        for reloc in relocations.iter_mut().filter(|r| edit.site.matches(r)) {
+            reloc.module_id = edit.module_id;
+            reloc.target_section = edit.target_section;
+            reloc.addend = edit.addend;
+            count += 1;
+        }
+        ensure!(count > 0, "No relocation at {}", edit.site);
+        info!("Retargeted {} relocation(s) at {}", count, edit.site);
+    }
+    for edit in &args.add {
+        let site = edit.site;
+        let Some(kind) = site.kind else { bail!("Relocation type required to add at {}", site) };
+        let section = sections
+            .get(site.section as usize)
+            .filter(|s| s.offset() != 0)
+            .ok_or_else(|| anyhow!("Section {} doesn't exist or has no data", site.section))?;
+        let size = match kind {
+            ObjRelocKind::Absolute
+            | ObjRelocKind::PpcRel24
+            | ObjRelocKind::PpcRel14
+            | ObjRelocKind::PpcRel32
+            | ObjRelocKind::PpcDtpMod32
+            | ObjRelocKind::PpcDtpRel32 => 4,
+            _ => 2,
+        };
+        ensure!(
+            site.offset + size <= section.size(),
+            "Offset {:#X} is outside of section {} (size {:#X})",
+            site.offset,
+            site.section,
+            section.size()
+        );
+        ensure!(
+            !relocations.iter().any(|r| r.section == site.section && r.address == site.offset),
+            "A relocation already exists at {}; use --retarget instead",
+            site
+        );
+        relocations.push(RelReloc {
+            kind,
+            section: site.section,
+            address: site.offset,
+            module_id: edit.module_id,
+            target_section: edit.target_section,
+            addend: edit.addend,
+            original_section: site.section,
+            original_target_section: edit.target_section,
+        });
+        info!("Added {:?} relocation at {}", kind, site);
+    }
+
+    let out = replace_rel_relocations(&data, relocations)
+        .with_context(|| format!("Failed to rewrite '{}'", args.rel_file.display()))?;
+    let out_path = args.out_file.as_ref().unwrap_or(&args.rel_file);
+    fs::write(out_path, out)
+        .with_context(|| format!("Failed to write '{}'", out_path.display()))?;
+    Ok(())
+}
+
+fn recover(args: RecoverArgs) -> Result<()> {
+    let dump = map_file(&args.dump_file)?;
+    let original = args.original.as_ref().map(map_file).transpose()?;
+    let mut dol_ranges = vec![];
+    if let Some(dol_path) = &args.dol {
+        let file = map_file(dol_path)?;
+        let obj = process_dol(file.as_slice(), "")?;
+        for (_, section) in obj.sections.iter() {
+            dol_ranges.push(section.address as u32..(section.address + section.size) as u32);
+        }
+    }
+    if original.is_none() && dol_ranges.is_empty() {
+        warn!("Without --original or --dol, only references within the module are recovered");
+    }
+    let options = RecoverOptions {
+        address: args.address,
+        original: original.as_ref().map(|file| file.as_slice()),
+        dol_ranges,
+    };
+    let result = recover_rel(dump.as_slice(), &options)
+        .with_context(|| format!("Failed to recover '{}'", args.dump_file.display()))?;
+    fs::write(&args.out_file, &result.data)
+        .with_context(|| format!("Failed to write '{}'", args.out_file.display()))?;
+    if is_json() {
+        return print_json(&serde_json::json!({
+            "address": result.address,
+            "bss_address": result.bss_address,
+            "relocations": result.relocations,
+            "modified": result.modified,
+            "mismatched": result.mismatched,
+        }));
+    }
+    info!(
+        "Module loaded at {:#010X}{}, {} relocations",
+        result.address,
+        result.bss_address.map(|a| format!(" with BSS at {a:#010X}")).unwrap_or_default(),
+        result.relocations
+    );
+    if original.is_some() {
+        info!("{} words were changed at runtime", result.modified.len());
+        for (section, offset) in &result.modified {
+            debug!("Modified: section {} offset {:#X}", section, offset);
+        }
+    }
+    if result.mismatched > 0 {
+        warn!(
+            "{} relocations don't match their target; is the load address right?",
+            result.mismatched
+        );
+    }
+    Ok(())
+}
+
+fn stats(args: StatsArgs) -> Result<()> {
+    const SHADES: &[u8] = b" .:-=+*#%@";
+    for path in process_rsp(&args.rel_files)? {
+        let file = map_file(&path)?;
+        let stats = rel_reloc_stats(&mut file.as_reader())
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        if is_json() {
+            print_json(&serde_json::json!({
+                "file": path.to_string_lossy(),
+                "stats": stats,
+            }))?;
+            continue;
+        }
+        println!("{}: {} relocations", path.display(), stats.total);
+        println!("  By type:");
+        for (kind, count) in &stats.by_kind {
+            println!("    {: <16} {: >8}", kind, count);
+        }
+        println!("  By target module:");
+        for (&module_id, count) in &stats.by_module {
+            println!("    {: <32} {: >8}", DisplayModule(module_id).to_string(), count);
+        }
+        println!("  By target section:");
+        for target in &stats.by_target_section {
+            let name = format!("{} section {}", DisplayModule(target.module_id), target.section);
+            println!("    {: <32} {: >8}", name, target.count);
+        }
+        println!("  Density:");
+        for section in &stats.sections {
+            let max = section.density.iter().copied().max().unwrap_or(0).max(1);
+            let heatmap = section
+                .density
+                .iter()
+                .map(|&n| {
+                    let shade = if n == 0 { 0 } else { 1 + n * (SHADES.len() - 2) / max };
+                    SHADES[shade] as char
+                })
+                .collect::<String>();
+            println!(
+                "    section {: <3} |{}| {} in {:#X} bytes",
+                section.index, heatmap, section.count, section.size
+            );
+        }
+    }
+    Ok(())
+}
+
 #[inline]
 const fn align32(x: u32) -> u32 { (x + 31) & !31 }
 
+/// Assigns an address to each REL section, by module ID and section index. Sections are placed
+/// where they were linked in the dump, or from the module's given load address as `OSLink` would
+/// (with BSS after the module); the rest are placed after the arena start.
+fn merge_layout(
+    args: &MergeArgs,
+    obj: &ObjInfo,
+    module_map: &BTreeMap<u32, ObjInfo>,
+    arena_lo: u32,
+) -> Result<BTreeMap<u32, BTreeMap<u32, u32>>> {
+    let mut linked = BTreeMap::<u32, Vec<(u32, u32)>>::new();
+    if let Some(mem1) = &args.mem1 {
+        let mut memory =
+            Memory { regions: vec![(MEM1_ADDRESS, map_file(mem1)?.as_slice().to_vec())] };
+        if let Some(mem2) = &args.mem2 {
+            memory.regions.push((MEM2_ADDRESS, map_file(mem2)?.as_slice().to_vec()));
+        }
+        let module_ids = module_map.keys().copied().collect::<Vec<_>>();
+        for module in find_linked_modules(&memory, &module_ids) {
+            if module_map.contains_key(&module.module_id) {
+                log::info!("Module {} is linked at {:#010X}", module.module_id, module.address);
+                linked.insert(module.module_id, module.sections);
+            }
+        }
+    }
+    let mut load_addresses = BTreeMap::<u32, u32>::new();
+    for load in &args.address {
+        let Some(module) = module_map
+            .values()
+            .find(|m| m.name == load.module || m.module_id.to_string() == load.module)
+        else {
+            bail!("Unknown module '{}'", load.module);
+        };
+        load_addresses.insert(module.module_id, load.address);
+    }
+
+    // Occupied ranges, starting with the DOL
+    let mut used =
+        obj.sections.iter().map(|(_, s)| (s.address as u32, (s.address + s.size) as u32)).collect();
+    let mut section_map: BTreeMap<u32, BTreeMap<u32, u32>> = BTreeMap::new();
+    let mut unplaced = vec![];
+    for module in module_map.values() {
+        let mut bss_address = None;
+        for (_, section) in module.sections.iter() {
+            let index = section.elf_index as u32;
+            let address = if let Some(&base) = load_addresses.get(&module.module_id) {
+                if section.kind == ObjSectionKind::Bss {
+                    let end = module
+                        .sections
+                        .iter()
+                        .filter(|(_, s)| s.kind != ObjSectionKind::Bss)
+                        .map(|(_, s)| (s.file_offset + s.size) as u32)
+                        .max()
+                        .unwrap_or(0);
+                    let address = bss_address.unwrap_or(align32(base + end));
+                    bss_address = Some(address + align32(section.size as u32));
+                    address
+                } else {
+                    base + section.file_offset as u32
+                }
+            } else if let Some(&(address, _)) = linked
+                .get(&module.module_id)
+                .and_then(|sections| sections.get(index as usize))
+                .filter(|(address, _)| *address != 0)
+            {
+                address
+            } else {
+                unplaced.push((module.module_id, index, section.size as u32));
+                continue;
+            };
+            claim_range(&mut used, address, section.size as u32).with_context(|| {
+                format!("While placing {} of module {}", section.name, module.module_id)
+            })?;
+            section_map.nested_insert(module.module_id, index, address)?;
+        }
+    }
+    let mut offset = align32(arena_lo + 0x2000);
+    for (module_id, index, size) in unplaced {
+        while let Some(&(_, end)) =
+            used.iter().find(|&&(start, end)| start < offset + size.max(1) && offset < end)
+        {
+            offset = align32(end);
+        }
+        claim_range(&mut used, offset, size)?;
+        section_map.nested_insert(module_id, index, offset)?;
+        offset += align32(size);
+    }
+    Ok(section_map)
+}
+
+fn claim_range(used: &mut Vec<(u32, u32)>, address: u32, size: u32) -> Result<()> {
+    if size == 0 {
+        return Ok(());
+    }
+    let end = address.checked_add(size).context("Section address overflows")?;
+    if let Some(&(start, other_end)) =
+        used.iter().find(|&&(start, other_end)| start < end && address < other_end)
+    {
+        bail!("{:#010X}-{:#010X} overlaps {:#010X}-{:#010X}", address, end, start, other_end);
+    }
+    used.push((address, end));
+    Ok(())
+}
+
 fn merge(args: MergeArgs) -> Result<()> {
     log::info!("Loading {}", args.dol_file.display());
     let mut obj = {
@@ -471,11 +1030,11 @@ fn merge(args: MergeArgs) -> Result<()> {
     }
 
     log::info!("Merging {} REL(s)", processed);
-    let mut section_map: BTreeMap<u32, BTreeMap<u32, u32>> = BTreeMap::new();
-    let mut offset = align32(arena_lo + 0x2000);
+    let section_map = merge_layout(&args, &obj, &module_map, arena_lo)?;
     for module in module_map.values() {
         for (mod_section_index, mod_section) in module.sections.iter() {
             ensure!(mod_section.relocations.is_empty(), "Unsupported relocations during merge");
+            let offset = section_map[&module.module_id][&(mod_section.elf_index as u32)];
             let section_idx = obj.sections.push(ObjSection {
                 name: format!("{}:{}", mod_section.name, module.module_id),
                 kind: mod_section.kind,
@@ -490,11 +1049,10 @@ fn merge(args: MergeArgs) -> Result<()> {
                 section_known: mod_section.section_known,
                 splits: mod_section.splits.clone(),
             });
-            section_map.nested_insert(module.module_id, mod_section.elf_index as u32, offset)?;
             for (_, mod_symbol) in module.symbols.for_section(mod_section_index) {
                 obj.symbols.add_direct(ObjSymbol {
-                    name: mod_symbol.name.clone(),
-                    demangled_name: mod_symbol.demangled_name.clone(),
+                    name: mod_symbol.name,
+                    demangled_name: mod_symbol.demangled_name,
                     address: mod_symbol.address + offset as u64,
                     section: Some(section_idx),
                     size: mod_symbol.size,
@@ -505,15 +1063,16 @@ fn merge(args: MergeArgs) -> Result<()> {
                     data_kind: mod_symbol.data_kind,
                     name_hash: mod_symbol.name_hash,
                     demangled_name_hash: mod_symbol.demangled_name_hash,
+                    source: mod_symbol.source,
                 })?;
             }
-            offset += align32(mod_section.size as u32);
         }
     }
 
     log::info!("Applying REL relocations");
     for module in module_map.values() {
-        for rel_reloc in &module.unresolved_relocations {
+        for result in module.unresolved_relocations.iter() {
+            let rel_reloc = result?;
             let source_addr = (section_map[&module.module_id][&(rel_reloc.section as u32)]
                 + rel_reloc.address)
                 & !3;
@@ -537,7 +1096,6 @@ fn merge(args: MergeArgs) -> Result<()> {
             } else {
                 // Create a new label
                 let symbol_idx = obj.symbols.add_direct(ObjSymbol {
-                    name: String::new(),
                     address: target_addr as u64,
                     section: Some(target_section_index),
                     ..Default::default()
@@ -557,17 +1115,7 @@ fn merge(args: MergeArgs) -> Result<()> {
     link_relocations(&mut obj)?;
 
     log::info!("Detecting function boundaries");
-    let mut state = AnalyzerState::default();
-    FindSaveRestSleds::execute(&mut state, &obj)?;
-    state.detect_functions(&obj)?;
-    log::info!(
-        "Discovered {} functions",
-        state.functions.iter().filter(|(_, i)| i.is_function()).count()
-    );
-
-    FindTRKInterruptVectorTable::execute(&mut state, &obj)?;
-    state.apply(&mut obj)?;
-
+    Pipeline::default().detect_functions(&mut obj, &mut Timings::new())?;
     apply_signatures_post(&mut obj)?;
 
     log::info!("Performing relocation analysis");
@@ -620,7 +1168,24 @@ fn link_relocations(obj: &mut ObjInfo) -> Result<()> {
                     );
                     ins = (ins & !0xfffc) | (diff as u32 & 0xfffc);
                 }
-                ObjRelocKind::PpcEmbSda21 => {
+                ObjRelocKind::PpcRel32 => {
+                    ins = target_address.wrapping_sub(source_address);
+                }
+                ObjRelocKind::PpcRel16Hi => {
+                    let diff = target_address.wrapping_sub(source_address);
+                    ins = (ins & 0xffff0000) | ((diff >> 16) & 0xffff);
+                }
+                ObjRelocKind::PpcRel16Ha => {
+                    let diff = target_address.wrapping_sub(source_address);
+                    ins = (ins & 0xffff0000) | ((diff.wrapping_add(0x8000) >> 16) & 0xffff);
+                }
+                ObjRelocKind::PpcRel16Lo => {
+                    let diff = target_address.wrapping_sub(source_address);
+                    ins = (ins & 0xffff0000) | (diff & 0xffff);
+                }
+                ObjRelocKind::PpcEmbSda21
+                | ObjRelocKind::PpcDtpMod32
+                | ObjRelocKind::PpcDtpRel32 => {
                     // Unused in RELs
                 }
             };
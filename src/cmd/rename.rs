@@ -0,0 +1,256 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use argp::FromArgs;
+use cwdemangle::{demangle, DemangleOptions};
+use tracing::{info, warn};
+
+use crate::{
+    cmd::dol::ProjectConfig,
+    util::{
+        asm::needs_quotes,
+        config::{config_include_paths, defines_symbol, rename_symbol_text},
+        file::buf_reader,
+        scaffold::is_c_identifier,
+    },
+};
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Renames a symbol across the project's symbols files, assembly and sources.
+#[argp(subcommand, name = "rename")]
+pub struct Args {
+    #[argp(positional)]
+    /// current symbol name
+    old: String,
+    #[argp(positional)]
+    /// new symbol name
+    new: String,
+    #[argp(option, short = 'c')]
+    /// project config (default: config.yml)
+    config: Option<PathBuf>,
+    #[argp(option, short = 'b')]
+    /// `dol split` output directory, to update the assembly in (re-run `dol split` for the rest)
+    build_dir: Option<PathBuf>,
+    #[argp(option, short = 's')]
+    /// C/C++ source directory to update (can be repeated)
+    source: Vec<PathBuf>,
+}
+
+const SOURCE_EXTENSIONS: &[&str] = &["c", "cp", "cpp", "cxx", "h", "hpp", "hxx", "inc"];
+
+pub fn run(args: Args) -> Result<()> {
+    let config_path = args.config.clone().unwrap_or_else(|| PathBuf::from("config.yml"));
+    let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(&config_path)?)
+        .with_context(|| format!("While loading '{}'", config_path.display()))?;
+    if args.old == args.new {
+        bail!("Old and new names are the same");
+    }
+    validate_name(&args.old, &args.new)?;
+
+    // Symbols files, including shared includes
+    let mut symbols_files = vec![];
+    let modules = std::iter::once(&config.base)
+        .chain(config.modules.iter())
+        .chain(config.dols.iter().map(|dol| &dol.base));
+    for module_config in modules {
+        if module_config.force_active.contains(&args.old)
+            || module_config.extract.iter().any(|e| e.symbol == args.old)
+        {
+            warn!(
+                "'{}' is referenced by {} in '{}'; update it manually",
+                args.old,
+                module_config.file_name(),
+                config_path.display()
+            );
+        }
+        let Some(symbols) = &module_config.symbols else { continue };
+        for path in config_include_paths(symbols)?.into_iter().chain([symbols.clone()]) {
+            if path.is_file() && !symbols_files.contains(&path) {
+                symbols_files.push(path);
+            }
+        }
+    }
+    let mut renamed = vec![];
+    for path in &symbols_files {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        if defines_symbol(&text, &args.new) {
+            bail!("'{}' already defines '{}'", path.display(), args.new);
+        }
+        if let Some(text) = rename_symbol_text(&text, &args.old, &args.new) {
+            renamed.push((path, text));
+        }
+    }
+    if renamed.is_empty() {
+        bail!("No symbols file defines '{}'", args.old);
+    }
+    if renamed.len() > 1 {
+        warn!("'{}' is defined by {} symbols files; renaming it in all", args.old, renamed.len());
+    }
+    for (path, text) in renamed {
+        fs::write(path, text).with_context(|| format!("Failed to write '{}'", path.display()))?;
+        info!("Updated {}", path.display());
+    }
+
+    if let Some(build_dir) = &args.build_dir {
+        let quote = |name: &str| {
+            if needs_quotes(&config.asm, name) {
+                format!("\"{name}\"")
+            } else {
+                name.to_string()
+            }
+        };
+        let (old, new) = (quote(&args.old), quote(&args.new));
+        let is_name_char =
+            |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '$' | '.' | '?');
+        let count = rewrite_files(&build_dir.join("asm"), &["s"], &old, &new, is_name_char)?;
+        info!("Updated {} assembly files; re-run `dol split` to update its other outputs", count);
+    }
+
+    if !args.source.is_empty() {
+        // Sources refer to C++ symbols by their demangled names
+        if !is_c_identifier(&args.old) || !is_c_identifier(&args.new) {
+            warn!("Not updating sources, since '{}' or '{}' is a C++ symbol", args.old, args.new);
+        } else {
+            let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+            let mut count = 0;
+            for dir in &args.source {
+                count += rewrite_files(dir, SOURCE_EXTENSIONS, &args.old, &args.new, is_name_char)?;
+            }
+            info!("Updated {} source files", count);
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a new name is a C identifier or a valid mangled name, and that a C++ symbol
+/// keeps its signature.
+fn validate_name(old: &str, new: &str) -> Result<()> {
+    if is_c_identifier(new) {
+        if !is_c_identifier(old) && demangle(old, &DemangleOptions::default()).is_some() {
+            warn!("Renaming C++ symbol '{}' to unmangled name '{}'", old, new);
+        }
+        return Ok(());
+    }
+    let Some(new_demangled) = demangle(new, &DemangleOptions::default()) else {
+        bail!("'{}' is neither a C identifier nor a valid mangled name", new);
+    };
+    if let Some(old_demangled) = demangle(old, &DemangleOptions::default()) {
+        let signature = |s: &str| s.split_once('(').map(|(_, args)| args.to_string());
+        if signature(&old_demangled) != signature(&new_demangled) {
+            warn!("Signature changes from '{}' to '{}'", old_demangled, new_demangled);
+        }
+    }
+    Ok(())
+}
+
+/// Replaces whole-name occurrences of `old` in the files under `dir` with the given extensions,
+/// returning the number of files changed.
+fn rewrite_files(
+    dir: &Path,
+    extensions: &[&str],
+    old: &str,
+    new: &str,
+    is_name_char: impl Fn(char) -> bool + Copy,
+) -> Result<usize> {
+    let mut count = 0;
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read '{}'", dir.display()))? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            count += rewrite_files(&path, extensions, old, new, is_name_char)?;
+            continue;
+        }
+        let matches_extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)));
+        if !matches_extension {
+            continue;
+        }
+        // Skip files that aren't UTF-8, such as Shift-JIS sources
+        let Ok(text) = fs::read_to_string(&path) else { continue };
+        if let Some(text) = replace_name(&text, old, new, is_name_char) {
+            fs::write(&path, text)
+                .with_context(|| format!("Failed to write '{}'", path.display()))?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Replaces occurrences of `old` not surrounded by name characters.
+fn replace_name(
+    text: &str,
+    old: &str,
+    new: &str,
+    is_name_char: impl Fn(char) -> bool,
+) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, _) in text.match_indices(old) {
+        let end = start + old.len();
+        if text[..start].chars().next_back().is_some_and(&is_name_char)
+            || text[end..].chars().next().is_some_and(&is_name_char)
+        {
+            continue;
+        }
+        out.push_str(&text[last..start]);
+        out.push_str(new);
+        last = end;
+    }
+    if last == 0 {
+        return None;
+    }
+    out.push_str(&text[last..]);
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_c_name_char(c: char) -> bool { c.is_ascii_alphanumeric() || c == '_' }
+
+    #[test]
+    fn replace_whole_names() {
+        let text = "foo(); foo_bar(); bar_foo(); *foo = 1;\n";
+        assert_eq!(
+            replace_name(text, "foo", "baz", is_c_name_char).as_deref(),
+            Some("baz(); foo_bar(); bar_foo(); *baz = 1;\n")
+        );
+        assert_eq!(replace_name("foo_bar(); xfoo;", "foo", "baz", is_c_name_char), None);
+        assert_eq!(replace_name("", "foo", "baz", is_c_name_char), None);
+        // At the start and end of the text
+        assert_eq!(replace_name("foo", "foo", "baz", is_c_name_char).as_deref(), Some("baz"));
+
+        // Assembly names may contain '.', '$' and '?'
+        let is_asm_name_char =
+            |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '$' | '.' | '?');
+        let text = "bl foo\nbl foo.1\nbl foo$2\n.4byte foo\n";
+        assert_eq!(
+            replace_name(text, "foo", "bar", is_asm_name_char).as_deref(),
+            Some("bl bar\nbl foo.1\nbl foo$2\n.4byte bar\n")
+        );
+        // Quoted names
+        assert_eq!(
+            replace_name("bl \"@123\"\n", "\"@123\"", "\"@456\"", is_asm_name_char).as_deref(),
+            Some("bl \"@456\"\n")
+        );
+    }
+
+    #[test]
+    fn validate_names() {
+        assert!(validate_name("fn_80012345", "OSReport").is_ok());
+        assert!(validate_name("Update__6PlayerFv", "PlayerUpdate").is_ok());
+        assert!(validate_name("fn_80012345", "Update__6PlayerFv").is_ok());
+        // A different signature only warns
+        assert!(validate_name("Update__6PlayerFv", "Draw__6PlayerFi").is_ok());
+        assert!(validate_name("fn_80012345", "Player Update").is_err());
+        assert!(validate_name("fn_80012345", "1st").is_err());
+        assert!(validate_name("fn_80012345", "").is_err());
+    }
+}
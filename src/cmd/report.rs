@@ -0,0 +1,294 @@
+use std::{
+    fs,
+    io::{stdout, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use argp::FromArgs;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    cmd::dol::{OutputConfig, OutputModule, OutputUnit},
+    obj::{ObjInfo, ObjSectionKind, ObjSymbol, ObjSymbolKind, ObjUnitStatus},
+    util::{
+        diff::{match_percent, reloc_mask, RelocTarget},
+        elf::process_elf,
+        file::buf_reader,
+        output::{is_json, print_json},
+        split::is_strip_remnant_symbol,
+    },
+};
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Generates a progress report in the format used by objdiff-cli.
+#[argp(subcommand, name = "report")]
+pub struct Args {
+    #[argp(positional)]
+    /// output config written by `dol split` (out_dir/config.json)
+    config: PathBuf,
+    #[argp(positional)]
+    /// directory of objects built from source, mirroring the source tree (e.g. build/GAMEID/src)
+    build_dir: PathBuf,
+    #[argp(option, short = 'o')]
+    /// output file (default stdout)
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct Report {
+    fuzzy_match_percent: f32,
+    total_code: u64,
+    matched_code: u64,
+    matched_code_percent: f32,
+    total_data: u64,
+    matched_data: u64,
+    matched_data_percent: f32,
+    total_functions: u32,
+    matched_functions: u32,
+    matched_functions_percent: f32,
+    units: Vec<ReportUnit>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct ReportUnit {
+    name: String,
+    fuzzy_match_percent: f32,
+    total_code: u64,
+    matched_code: u64,
+    total_data: u64,
+    matched_data: u64,
+    total_functions: u32,
+    matched_functions: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    complete: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    module_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    module_id: Option<u32>,
+    sections: Vec<ReportItem>,
+    functions: Vec<ReportItem>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ReportItem {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    demangled_name: Option<String>,
+    size: u64,
+    fuzzy_match_percent: f32,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let config: OutputConfig = serde_json::from_reader(&mut buf_reader(&args.config)?)
+        .with_context(|| format!("While loading '{}'", args.config.display()))?;
+
+    let mut report = Report::default();
+    let mut fuzzy_code = 0.0;
+    for module in [&config.base].into_iter().chain(&config.modules).chain(&config.dols) {
+        for unit in &module.units {
+            let unit = report_unit(module, unit, &args.build_dir)
+                .with_context(|| format!("While processing unit '{}'", unit.name))?;
+            fuzzy_code += unit.fuzzy_match_percent as f64 * unit.total_code as f64;
+            report.total_code += unit.total_code;
+            report.matched_code += unit.matched_code;
+            report.total_data += unit.total_data;
+            report.matched_data += unit.matched_data;
+            report.total_functions += unit.total_functions;
+            report.matched_functions += unit.matched_functions;
+            report.units.push(unit);
+        }
+    }
+    report.fuzzy_match_percent = percent(fuzzy_code / 100.0, report.total_code as f64);
+    report.matched_code_percent = percent(report.matched_code as f64, report.total_code as f64);
+    report.matched_data_percent = percent(report.matched_data as f64, report.total_data as f64);
+    report.matched_functions_percent =
+        percent(report.matched_functions as f64, report.total_functions as f64);
+
+    let json = serde_json::to_string_pretty(&report)?;
+    match &args.output {
+        Some(path) => {
+            fs::write(path, json)
+                .with_context(|| format!("Failed to write '{}'", path.display()))?;
+            info!(
+                "Code: {:#X}/{:#X} bytes ({:.2}%, fuzzy {:.2}%), data: {:#X}/{:#X} bytes ({:.2}%)",
+                report.matched_code,
+                report.total_code,
+                report.matched_code_percent,
+                report.fuzzy_match_percent,
+                report.matched_data,
+                report.total_data,
+                report.matched_data_percent
+            );
+            info!(
+                "Functions: {}/{} ({:.2}%)",
+                report.matched_functions, report.total_functions, report.matched_functions_percent
+            );
+        }
+        None if is_json() => print_json(&report)?,
+        None => writeln!(stdout(), "{json}")?,
+    }
+    Ok(())
+}
+
+fn percent(value: f64, total: f64) -> f32 {
+    if total == 0.0 {
+        100.0
+    } else {
+        (value / total * 100.0) as f32
+    }
+}
+
+/// Compares a unit's split object with the object built from its source, if any.
+fn report_unit(module: &OutputModule, unit: &OutputUnit, build_dir: &Path) -> Result<ReportUnit> {
+    let target = process_elf(&unit.object)?;
+    let base = match &unit.source {
+        Some(source) => {
+            let path = build_dir.join(source).with_extension("o");
+            if path.is_file() {
+                Some(process_elf(&path)?)
+            } else {
+                None
+            }
+        }
+        None => None,
+    };
+
+    let mut out = ReportUnit {
+        name: unit.name.clone(),
+        complete: unit.status.map(|s| s != ObjUnitStatus::NonMatching),
+        module_name: (module.module_id != 0).then(|| module.name.clone()),
+        module_id: (module.module_id != 0).then_some(module.module_id),
+        ..Default::default()
+    };
+    let mut fuzzy_code = 0.0;
+    for (section_index, section) in target.sections.iter() {
+        let mut section_size = 0;
+        let mut section_fuzzy = 0.0;
+        for (_, symbol) in target.symbols.for_section(section_index) {
+            if symbol.size == 0
+                || !matches!(symbol.kind, ObjSymbolKind::Function | ObjSymbolKind::Object)
+                || is_strip_remnant_symbol(&symbol.name)
+            {
+                continue;
+            }
+            let base_symbol = base.as_ref().and_then(|base| {
+                let (_, base_symbol) = base.symbols.by_name(&symbol.name).ok()??;
+                Some((base, base_symbol))
+            });
+            let fuzzy = match base_symbol {
+                Some((base, base_symbol)) if symbol.kind == ObjSymbolKind::Function => {
+                    compare_code(&target, symbol, base, base_symbol)?
+                }
+                Some((base, base_symbol)) => {
+                    if compare_data(&target, symbol, base, base_symbol)? {
+                        100.0
+                    } else {
+                        0.0
+                    }
+                }
+                None => 0.0,
+            };
+            section_size += symbol.size;
+            section_fuzzy += fuzzy * symbol.size as f64;
+            if symbol.kind == ObjSymbolKind::Function {
+                out.total_code += symbol.size;
+                out.total_functions += 1;
+                fuzzy_code += fuzzy * symbol.size as f64;
+                if fuzzy == 100.0 {
+                    out.matched_code += symbol.size;
+                    out.matched_functions += 1;
+                }
+                out.functions.push(ReportItem {
+                    name: symbol.name.to_string(),
+                    demangled_name: symbol.demangled_name.map(String::from),
+                    size: symbol.size,
+                    fuzzy_match_percent: fuzzy as f32,
+                });
+            } else {
+                out.total_data += symbol.size;
+                if fuzzy == 100.0 {
+                    out.matched_data += symbol.size;
+                }
+            }
+        }
+        if section_size > 0 {
+            out.sections.push(ReportItem {
+                name: section.name.clone(),
+                demangled_name: None,
+                size: section_size,
+                fuzzy_match_percent: percent(section_fuzzy / 100.0, section_size as f64),
+            });
+        }
+    }
+    out.fuzzy_match_percent = percent(fuzzy_code / 100.0, out.total_code as f64);
+    Ok(out)
+}
+
+/// A symbol's bytes with relocated fields cleared, and its relocation targets by offset.
+fn masked_data<'a>(
+    obj: &'a ObjInfo,
+    symbol: &ObjSymbol,
+) -> Result<(Vec<u8>, Vec<(u32, RelocTarget<'a>)>)> {
+    let Some(section) = symbol.section.and_then(|i| obj.sections.get(i)) else {
+        return Ok((vec![], vec![]));
+    };
+    if section.kind == ObjSectionKind::Bss {
+        return Ok((vec![0; symbol.size as usize], vec![]));
+    }
+    let start = symbol.address as u32;
+    let end = start + symbol.size as u32;
+    let mut data = section.data_range(start, end)?.to_vec();
+    let mut targets = vec![];
+    for (address, reloc) in section.relocations.range(start..end) {
+        let offset = (address - start) as usize;
+        if let Some(word) = data.get_mut(offset..offset + 4) {
+            let value = u32::from_be_bytes(word.try_into().unwrap()) & !reloc_mask(reloc.kind);
+            word.copy_from_slice(&value.to_be_bytes());
+        }
+        targets.push((address - start, RelocTarget::new(obj, reloc)));
+    }
+    Ok((data, targets))
+}
+
+/// Whether a data symbol is identical in both objects.
+fn compare_data(
+    target: &ObjInfo,
+    target_symbol: &ObjSymbol,
+    base: &ObjInfo,
+    base_symbol: &ObjSymbol,
+) -> Result<bool> {
+    Ok(target_symbol.size == base_symbol.size
+        && masked_data(target, target_symbol)? == masked_data(base, base_symbol)?)
+}
+
+/// Percentage of matching instructions, from the edit distance between both functions'
+/// instructions. Relocated fields are compared by relocation target instead of value.
+fn compare_code(
+    target: &ObjInfo,
+    target_symbol: &ObjSymbol,
+    base: &ObjInfo,
+    base_symbol: &ObjSymbol,
+) -> Result<f64> {
+    let a = code_words(target, target_symbol)?;
+    let b = code_words(base, base_symbol)?;
+    Ok(match_percent(&a, &b))
+}
+
+/// A function's instructions, with relocated fields cleared, and their relocation targets.
+fn code_words<'a>(
+    obj: &'a ObjInfo,
+    symbol: &ObjSymbol,
+) -> Result<Vec<(u32, Option<RelocTarget<'a>>)>> {
+    let (data, targets) = masked_data(obj, symbol)?;
+    Ok(data
+        .chunks_exact(4)
+        .enumerate()
+        .map(|(i, word)| {
+            let offset = i as u32 * 4;
+            let target = targets.iter().find(|(o, _)| *o == offset).map(|(_, t)| t.clone());
+            (u32::from_be_bytes(word.try_into().unwrap()), target)
+        })
+        .collect())
+}
@@ -3,7 +3,11 @@ use std::path::PathBuf;
 use anyhow::Result;
 use argp::FromArgs;
 
-use crate::util::{file::map_file, rso::process_rso};
+use crate::util::{
+    file::map_file,
+    output::{is_json, print_json},
+    rso::process_rso,
+};
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// Commands for processing RSO files.
@@ -41,6 +45,10 @@ fn info(args: InfoArgs) -> Result<()> {
         #[allow(clippy::let_and_return)]
         obj
     };
-    println!("Read RSO module {}", rso.name);
+    if is_json() {
+        print_json(&serde_json::json!({ "name": rso.name }))?;
+    } else {
+        println!("Read RSO module {}", rso.name);
+    }
     Ok(())
 }
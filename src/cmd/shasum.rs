@@ -1,15 +1,22 @@
 use std::{
+    ffi::OsStr,
     fs::File,
     io::{BufRead, BufReader, Read},
-    path::{Path, PathBuf},
+    path::PathBuf,
+    str::FromStr,
 };
 
 use anyhow::{anyhow, bail, Context, Result};
-use argp::FromArgs;
+use argp::{FromArgValue, FromArgs};
 use owo_colors::{OwoColorize, Stream};
+use serde::{Deserialize, Serialize};
 use sha1::{Digest, Sha1};
 
-use crate::util::file::{open_file, process_rsp, touch};
+use crate::util::{
+    file::{open_file, process_rsp, touch},
+    output::{coded_error, is_json, print_json, ErrorCode},
+    progress::{byte_progress, item_progress},
+};
 
 #[derive(FromArgs, PartialEq, Eq, Debug)]
 /// Print or check SHA1 (160-bit) checksums.
@@ -27,17 +34,74 @@ pub struct Args {
     #[argp(switch, short = 'q')]
     /// only print failures and a summary
     quiet: bool,
+    #[argp(option, short = 'f')]
+    /// output format: gnu (default), bsd or json
+    format: Option<ShaFormat>,
+}
+
+/// Checksum list formats. All of them are accepted by `--check`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+enum ShaFormat {
+    /// `<hash>  <path>`, as written by `sha1sum`
+    #[default]
+    Gnu,
+    /// `SHA1 (<path>) = <hash>`, as written by `shasum --tag` and BSD `sha1`
+    Bsd,
+    /// One JSON object per line with `path`, `sha1` and `size`, also used for `--format json`
+    Json,
+}
+
+impl FromStr for ShaFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "gnu" => Self::Gnu,
+            "bsd" | "tag" => Self::Bsd,
+            "json" => Self::Json,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl FromArgValue for ShaFormat {
+    fn from_arg_value(value: &OsStr) -> Result<Self, String> {
+        String::from_arg_value(value)
+            .and_then(|s| Self::from_str(&s).map_err(|_| "Invalid checksum format".to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestEntry {
+    path: String,
+    sha1: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
 }
 
 const DEFAULT_BUF_SIZE: usize = 8192;
 
 pub fn run(args: Args) -> Result<()> {
+    let format = if is_json() { ShaFormat::Json } else { args.format.unwrap_or_default() };
     for path in process_rsp(&args.files)? {
         let mut file = open_file(&path)?;
         if args.check {
             check(&args, &mut BufReader::new(file))?
         } else {
-            hash(&mut file, &path)?
+            let size = file.limit();
+            let progress = byte_progress(size, path.display().to_string());
+            let result = file_sha1_string(&mut progress.wrap_read(&mut file));
+            progress.finish_and_clear();
+            let hash = result?;
+            match format {
+                ShaFormat::Gnu => println!("{}  {}", hash, path.display()),
+                ShaFormat::Bsd => println!("SHA1 ({}) = {}", path.display(), hash),
+                ShaFormat::Json => print_json(&ManifestEntry {
+                    path: path.to_string_lossy().into_owned(),
+                    sha1: hash,
+                    size: Some(size),
+                })?,
+            }
         }
     }
     if let Some(out_path) = args.output {
@@ -51,39 +115,68 @@ fn check<R>(args: &Args, reader: &mut R) -> Result<()>
 where R: BufRead + ?Sized {
     let mut matches = 0usize;
     let mut mismatches = 0usize;
-    for line in reader.lines() {
-        let line = match line {
-            Ok(line) => line,
-            Err(e) => bail!("File read failed: {e}"),
-        };
-        let (hash, file_name) =
-            line.split_once(' ').ok_or_else(|| anyhow!("Invalid line: {line}"))?;
-        let file_name = match file_name.chars().next() {
-            Some(' ') | Some('*') => &file_name[1..],
-            _ => bail!("Invalid line: {line}"),
-        };
+    let mut text = String::new();
+    if let Err(e) = reader.read_to_string(&mut text) {
+        bail!("File read failed: {e}");
+    }
+    let entries = parse_manifest(&text)?;
+    let progress = item_progress(entries.len() as u64, "Checking");
+    for (hash, file_name) in &entries {
+        progress.inc(1);
+        let file_name = file_name.as_str();
         let mut hash_bytes = [0u8; 20];
         hex::decode_to_slice(hash, &mut hash_bytes)
-            .with_context(|| format!("Invalid line: {line}"))?;
+            .with_context(|| format!("Invalid hash for '{file_name}': {hash}"))?;
 
         let found_hash = file_sha1(
             &mut File::open(file_name)
                 .with_context(|| format!("Failed to open file '{file_name}'"))?,
         )?;
-        if hash_bytes == found_hash.as_ref() {
+        let ok = hash_bytes == found_hash.as_ref();
+        if is_json() {
+            if !ok || !args.quiet {
+                print_json(&serde_json::json!({
+                    "path": file_name,
+                    "status": if ok { "ok" } else { "failed" },
+                }))?;
+            }
+            if ok {
+                matches += 1;
+            } else {
+                mismatches += 1;
+            }
+        } else if ok {
             if !args.quiet {
-                println!(
-                    "{}: {}",
-                    file_name,
-                    "OK".if_supports_color(Stream::Stdout, |t| t.green())
-                );
+                progress.suspend(|| {
+                    println!(
+                        "{}: {}",
+                        file_name,
+                        "OK".if_supports_color(Stream::Stdout, |t| t.green())
+                    )
+                });
             }
             matches += 1;
         } else {
-            println!("{}: {}", file_name, "FAILED".if_supports_color(Stream::Stdout, |t| t.red()));
+            progress.suspend(|| {
+                println!(
+                    "{}: {}",
+                    file_name,
+                    "FAILED".if_supports_color(Stream::Stdout, |t| t.red())
+                )
+            });
             mismatches += 1;
         }
     }
+    progress.finish_and_clear();
+    if is_json() {
+        if mismatches != 0 {
+            return Err(coded_error(
+                ErrorCode::ChecksumMismatch,
+                format!("{mismatches} computed checksum(s) did NOT match"),
+            ));
+        }
+        return Ok(());
+    }
     if args.quiet && matches > 0 {
         println!("{} files {}", matches, "OK".if_supports_color(Stream::Stdout, |t| t.green()));
     }
@@ -98,14 +191,31 @@ where R: BufRead + ?Sized {
     Ok(())
 }
 
-fn hash<R>(reader: &mut R, path: &Path) -> Result<()>
-where R: Read + ?Sized {
-    let hash = file_sha1(reader)?;
-    let mut hash_buf = [0u8; 40];
-    let hash_str = base16ct::lower::encode_str(&hash, &mut hash_buf)
-        .map_err(|e| anyhow!("Failed to encode hash: {e}"))?;
-    println!("{}  {}", hash_str, path.display());
-    Ok(())
+/// Parses a checksum list in any of the [`ShaFormat`]s into (hash, path) pairs.
+fn parse_manifest(text: &str) -> Result<Vec<(String, String)>> {
+    let mut entries = vec![];
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        if line.starts_with('{') {
+            let entry: ManifestEntry = serde_json::from_str(line)
+                .with_context(|| format!("Invalid JSON checksum entry: {line}"))?;
+            entries.push((entry.sha1, entry.path));
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("SHA1 (") {
+            let (file_name, hash) =
+                rest.rsplit_once(") = ").ok_or_else(|| anyhow!("Invalid line: {line}"))?;
+            entries.push((hash.trim_end().to_string(), file_name.to_string()));
+            continue;
+        }
+        let (hash, file_name) =
+            line.split_once(' ').ok_or_else(|| anyhow!("Invalid line: {line}"))?;
+        let file_name = match file_name.chars().next() {
+            Some(' ') | Some('*') => &file_name[1..],
+            _ => bail!("Invalid line: {line}"),
+        };
+        entries.push((hash.to_string(), file_name.to_string()));
+    }
+    Ok(entries)
 }
 
 pub fn file_sha1<R>(reader: &mut R) -> Result<sha1::digest::Output<Sha1>>
@@ -129,3 +239,45 @@ where R: Read + ?Sized {
         .map_err(|e| anyhow!("Failed to encode hash: {e}"))?;
     Ok(hash_str.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "da39a3ee5e6b4b0d3255bfef95601890afd80709";
+
+    #[test]
+    fn parse_manifest_formats() {
+        let entry = ManifestEntry {
+            path: "build/main.dol".to_string(),
+            sha1: HASH.to_string(),
+            size: Some(0),
+        };
+        let text = format!(
+            "{HASH}  build/main.dol\n{HASH} *orig/a b.rel\n\n\
+             SHA1 (build/(x) = y.bin) = {HASH}\n{}\n",
+            serde_json::to_string(&entry).unwrap()
+        );
+        let entries = parse_manifest(&text).unwrap();
+        let paths = entries.iter().map(|(hash, path)| {
+            assert_eq!(hash, HASH);
+            path.as_str()
+        });
+        assert_eq!(paths.collect::<Vec<_>>(), [
+            "build/main.dol",
+            "orig/a b.rel",
+            "build/(x) = y.bin",
+            "build/main.dol"
+        ]);
+    }
+
+    #[test]
+    fn parse_manifest_invalid() {
+        assert!(parse_manifest(&format!("{HASH}build/main.dol")).is_err());
+        assert!(parse_manifest(&format!("{HASH} -build/main.dol")).is_err());
+        assert!(parse_manifest("SHA1 (build/main.dol)").is_err());
+        assert!(parse_manifest("{\"path\": \"build/main.dol\"}").is_err());
+        // The former JSON array format
+        assert!(parse_manifest(&format!("[{{\"path\": \"a\", \"sha1\": \"{HASH}\"}}]")).is_err());
+    }
+}
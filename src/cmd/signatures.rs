@@ -0,0 +1,157 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context, Result};
+use argp::FromArgs;
+
+use crate::{
+    cmd::dol::ProjectConfig,
+    obj::ObjSymbol,
+    util::{
+        file::{buf_reader, process_rsp},
+        output::{is_json, print_json},
+        signatures::{generate_signatures, merge_signature_set, SignatureSet},
+        sigstore::{set_path, write_set, SignatureStore},
+    },
+};
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Commands for shared signature stores.
+#[argp(subcommand, name = "signatures")]
+pub struct Args {
+    #[argp(subcommand)]
+    command: SubCommand,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argp(subcommand)]
+enum SubCommand {
+    Push(PushArgs),
+    Pull(PullArgs),
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Adds signatures built from linked ELF files to a store.
+#[argp(subcommand, name = "push")]
+pub struct PushArgs {
+    #[argp(positional)]
+    /// signature store (directory or HTTP(S) URL)
+    store: String,
+    #[argp(positional)]
+    /// signature set, by library version (e.g. sdk-2002-12)
+    set: String,
+    #[argp(positional)]
+    /// linked ELF file(s)
+    files: Vec<PathBuf>,
+    #[argp(option, short = 's')]
+    /// function to add (can be repeated, default all global functions)
+    symbol: Vec<String>,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Lists or saves a signature set from a store, or pulls a project's sets.
+#[argp(subcommand, name = "pull")]
+pub struct PullArgs {
+    #[argp(positional)]
+    /// signature store (directory or HTTP(S) URL)
+    store: Option<String>,
+    #[argp(positional)]
+    /// signature set
+    set: Option<String>,
+    #[argp(option, short = 'o')]
+    /// output yml
+    out_file: Option<PathBuf>,
+    #[argp(option, short = 'c')]
+    /// project config, to pull its signature_sets to its signature_cache
+    config: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    match args.command {
+        SubCommand::Push(c_args) => push(c_args),
+        SubCommand::Pull(c_args) => pull(c_args),
+    }
+}
+
+fn push(args: PushArgs) -> Result<()> {
+    let filter = |symbol: &ObjSymbol| {
+        if args.symbol.is_empty() {
+            symbol.flags.is_global() && symbol.size > 0
+        } else {
+            args.symbol.iter().any(|name| *name == symbol.name)
+        }
+    };
+    let mut set = SignatureSet::new();
+    for path in process_rsp(&args.files)? {
+        log::info!("Processing {}", path.display());
+        let mut signatures = SignatureSet::new();
+        for signature in generate_signatures(&path, filter, false)? {
+            let symbol = &signature.symbols[signature.symbol];
+            // An empty signature would match anything
+            if symbol.size == 0 {
+                continue;
+            }
+            signatures.entry(symbol.name.clone()).or_default().push(signature);
+        }
+        merge_signature_set(&mut set, signatures);
+    }
+    let store = SignatureStore::new(&args.store);
+    let added = store.push(&args.set, set)?;
+    log::info!("Added {} signatures to {}", added, args.set);
+    Ok(())
+}
+
+fn pull(args: PullArgs) -> Result<()> {
+    if let Some(config_path) = &args.config {
+        ensure!(
+            args.store.is_none() && args.set.is_none() && args.out_file.is_none(),
+            "-c can't be combined with a store, set or output file"
+        );
+        return pull_project(config_path);
+    }
+    let (Some(store), Some(key)) = (&args.store, &args.set) else {
+        bail!("Expected a signature store and set, or -c");
+    };
+    let set = SignatureStore::new(store).pull(key)?;
+    if let Some(out_file) = &args.out_file {
+        return write_set(out_file, &set);
+    }
+    for (name, signatures) in &set {
+        if is_json() {
+            print_json(&serde_json::json!({ "name": name, "signatures": signatures.len() }))?;
+        } else {
+            println!("{} ({} signatures)", name, signatures.len());
+        }
+    }
+    Ok(())
+}
+
+/// Pulls the `signature_sets` of a project to its `signature_cache`, pinning them for analysis.
+fn pull_project(config_path: &Path) -> Result<()> {
+    let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(config_path)?)
+        .with_context(|| format!("While loading '{}'", config_path.display()))?;
+    let Some(location) = &config.signature_store else {
+        bail!("'{}' has no signature_store", config_path.display());
+    };
+    let Some(cache) = &config.signature_cache else {
+        bail!("'{}' has no signature_cache to pull to", config_path.display());
+    };
+    let store = SignatureStore::new(location);
+    for key in &config.signature_sets {
+        let set = store.pull(key)?;
+        if set.is_empty() {
+            log::warn!("Signature set {} is empty or missing from {}", key, location);
+        }
+        let path = set_path(cache, key)?;
+        write_set(&path, &set)?;
+        if is_json() {
+            print_json(&serde_json::json!({
+                "name": key,
+                "path": path,
+                "functions": set.len(),
+            }))?;
+        } else {
+            log::info!("Pulled {} ({} functions) to {}", key, set.len(), path.display());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,229 @@
+use std::{ffi::OsStr, io::Cursor, path::PathBuf, str::FromStr};
+
+use anyhow::Result;
+use argp::{FromArgValue, FromArgs};
+use serde::Serialize;
+
+use crate::{
+    obj::{ObjArchitecture, ObjInfo, ObjKind, ObjSection, ObjSectionKind},
+    util::{
+        config::parse_address,
+        dol::process_dol,
+        file::map_file,
+        output::{is_json, print_json},
+        rel::process_rel,
+    },
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Encoding {
+    #[default]
+    All,
+    Ascii,
+    ShiftJis,
+    Utf16,
+}
+
+impl FromStr for Encoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            "ascii" => Ok(Self::Ascii),
+            "sjis" | "shift-jis" => Ok(Self::ShiftJis),
+            "utf16" | "utf-16" => Ok(Self::Utf16),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromArgValue for Encoding {
+    fn from_arg_value(value: &OsStr) -> Result<Self, String> {
+        String::from_arg_value(value)
+            .and_then(|s| Self::from_str(&s).map_err(|_| "Invalid encoding".to_string()))
+    }
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Finds strings in a DOL, REL or raw binary file.
+#[argp(subcommand, name = "strings")]
+pub struct Args {
+    #[argp(positional)]
+    /// input file (.dol, .rel or raw binary)
+    in_file: PathBuf,
+    #[argp(option, short = 'e')]
+    /// string encoding (ascii, sjis, utf16, all)
+    encoding: Option<Encoding>,
+    #[argp(option, short = 'n')]
+    /// minimum string length in characters (default 4)
+    min_length: Option<usize>,
+    #[argp(option, short = 'a', from_str_fn(parse_address))]
+    /// load address of a raw binary file
+    address: Option<u32>,
+    #[argp(switch)]
+    /// also scan code sections
+    code: bool,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum StringKind {
+    Ascii,
+    ShiftJis,
+    Utf16,
+}
+
+impl StringKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            StringKind::Ascii => "ascii",
+            StringKind::ShiftJis => "sjis",
+            StringKind::Utf16 => "utf16",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct StringRecord<'a> {
+    section: &'a str,
+    address: u32,
+    encoding: StringKind,
+    string: &'a str,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let encoding = args.encoding.unwrap_or_default();
+    let min_length = args.min_length.unwrap_or(4).max(1);
+    let obj = load_object(&args)?;
+    for (_, section) in obj.sections.iter() {
+        match section.kind {
+            ObjSectionKind::Bss => continue,
+            ObjSectionKind::Code if !args.code => continue,
+            _ => {}
+        }
+        let mut hits = vec![];
+        if encoding != Encoding::Utf16 {
+            scan_narrow(&section.data, min_length, encoding, &mut hits);
+        }
+        if matches!(encoding, Encoding::All | Encoding::Utf16) {
+            scan_utf16(&section.data, min_length, &mut hits);
+        }
+        hits.sort_by_key(|&(offset, _, _)| offset);
+        for (offset, kind, string) in hits {
+            let address = section.address as u32 + offset as u32;
+            if is_json() {
+                print_json(&StringRecord {
+                    section: &section.name,
+                    address,
+                    encoding: kind,
+                    string: &string,
+                })?;
+            } else {
+                println!("{}:{:#010X} {: <5} {:?}", section.name, address, kind.as_str(), string);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn load_object(args: &Args) -> Result<ObjInfo> {
+    let file = map_file(&args.in_file)?;
+    let buf = file.as_slice();
+    let name = args.in_file.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let extension = args.in_file.extension().and_then(OsStr::to_str).unwrap_or_default();
+    Ok(match extension.to_ascii_lowercase().as_str() {
+        "dol" if args.address.is_none() => process_dol(buf, &name)?,
+        "rel" if args.address.is_none() => process_rel(&mut Cursor::new(buf), &name)?.1,
+        _ => ObjInfo::new(ObjKind::Executable, ObjArchitecture::PowerPc, name, vec![], vec![
+            ObjSection::raw(".data", ObjSectionKind::Data, args.address.unwrap_or(0), buf.to_vec()),
+        ]),
+    })
+}
+
+/// Length of the Shift-JIS character at the start of `data`, if it's a printable one.
+/// With `ascii_only`, only printable ASCII is accepted.
+fn narrow_char_len(data: &[u8], ascii_only: bool) -> Option<usize> {
+    match data[0] {
+        b'\t' | b'\n' | b'\r' | 0x20..=0x7E => Some(1),
+        _ if ascii_only => None,
+        // Half-width katakana
+        0xA1..=0xDF => Some(1),
+        0x81..=0x9F | 0xE0..=0xFC => match data.get(1) {
+            Some(0x40..=0x7E | 0x80..=0xFC) => Some(2),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Scans for ASCII and (NUL-terminated) Shift-JIS strings.
+/// Runs of plain ASCII are reported as ASCII.
+fn scan_narrow(
+    data: &[u8],
+    min_length: usize,
+    encoding: Encoding,
+    out: &mut Vec<(usize, StringKind, String)>,
+) {
+    let ascii_only = encoding == Encoding::Ascii;
+    let mut offset = 0;
+    while offset < data.len() {
+        let start = offset;
+        let mut chars = 0;
+        let mut is_ascii = true;
+        while offset < data.len() {
+            let Some(len) = narrow_char_len(&data[offset..], ascii_only) else { break };
+            is_ascii &= len == 1 && data[offset] < 0x80;
+            offset += len;
+            chars += 1;
+        }
+        if chars == 0 {
+            offset += 1;
+            continue;
+        }
+        if chars < min_length {
+            continue;
+        }
+        let bytes = &data[start..offset];
+        if is_ascii {
+            if encoding != Encoding::ShiftJis {
+                out.push((start, StringKind::Ascii, String::from_utf8_lossy(bytes).into_owned()));
+            }
+        } else if data.get(offset) == Some(&0) {
+            // Invalid or unmapped sequences aren't text
+            if let Some(string) =
+                encoding_rs::SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes)
+            {
+                out.push((start, StringKind::ShiftJis, string.into_owned()));
+            }
+        }
+    }
+}
+
+fn is_printable(c: char) -> bool { !c.is_control() || matches!(c, '\t' | '\n' | '\r') }
+
+/// Scans for 2-byte aligned, NUL-terminated big endian UTF-16 strings.
+fn scan_utf16(data: &[u8], min_length: usize, out: &mut Vec<(usize, StringKind, String)>) {
+    let units = data.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect::<Vec<_>>();
+    let mut index = 0;
+    while index < units.len() {
+        let start = index;
+        let mut string = String::new();
+        for result in std::char::decode_utf16(units[start..].iter().cloned()) {
+            match result {
+                Ok(c) if is_printable(c) => {
+                    string.push(c);
+                    index += c.len_utf16();
+                }
+                _ => break,
+            }
+        }
+        if string.is_empty() {
+            index += 1;
+            continue;
+        }
+        if string.chars().count() >= min_length && units.get(index) == Some(&0) {
+            out.push((start * 2, StringKind::Utf16, string));
+        }
+    }
+}
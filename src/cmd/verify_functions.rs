@@ -0,0 +1,162 @@
+use std::{collections::HashMap, io::Write, path::PathBuf};
+
+use anyhow::{Context, Result};
+use argp::FromArgs;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::{
+    cmd::dol::ProjectConfig,
+    obj::ObjSymbolKind,
+    util::{
+        config::apply_symbols_file,
+        dol::process_dol,
+        elf::process_elf,
+        file::{buf_reader, buf_writer, map_file, verify_hash},
+        output::{coded_error, is_json, print_json, ErrorCode},
+    },
+};
+
+#[derive(FromArgs, PartialEq, Eq, Debug)]
+/// Records which functions of the DOL a build reproduces, and detects regressions.
+#[argp(subcommand, name = "verify-functions")]
+pub struct Args {
+    #[argp(positional)]
+    /// project config (config.yml)
+    config: PathBuf,
+    #[argp(positional)]
+    /// linked ELF
+    elf_file: PathBuf,
+    #[argp(option, short = 'm')]
+    /// manifest file (default: functions.yml next to the config)
+    manifest: Option<PathBuf>,
+    #[argp(switch)]
+    /// update the manifest even if functions regressed
+    force: bool,
+}
+
+/// A manifest entry for one function of the original DOL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FunctionRecord {
+    name: String,
+    #[serde(with = "hex")]
+    address: u32,
+    #[serde(with = "hex")]
+    size: u32,
+    /// xxh3 hash of the original code
+    hash: String,
+    matched: bool,
+}
+
+mod hex {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(value: &u32, s: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        s.serialize_str(&format!("{:#X}", value))
+    }
+
+    pub fn deserialize<'de, D>(d: D) -> Result<u32, D::Error>
+    where D: Deserializer<'de> {
+        let s = String::deserialize(d)?;
+        let s = s.trim_start_matches("0x").trim_start_matches("0X");
+        u32::from_str_radix(s, 16).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Serialize)]
+struct ChangeRecord<'a> {
+    name: &'a str,
+    address: u32,
+    change: &'static str,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let config: ProjectConfig = serde_yaml::from_reader(&mut buf_reader(&args.config)?)
+        .with_context(|| format!("While loading '{}'", args.config.display()))?;
+    config.register_module_names();
+    let manifest_path = args.manifest.clone().unwrap_or_else(|| match args.config.parent() {
+        Some(parent) => parent.join("functions.yml"),
+        None => PathBuf::from("functions.yml"),
+    });
+
+    let mut obj = {
+        let file = map_file(&config.base.object)?;
+        if let Some(hash_str) = &config.base.hash {
+            verify_hash(file.as_slice(), hash_str)?;
+        }
+        process_dol(file.as_slice(), config.base.name().as_ref())?
+    };
+    if let Some(symbols_path) = &config.base.symbols {
+        apply_symbols_file(symbols_path, &mut obj)?;
+    }
+    let linked_obj = process_elf(&args.elf_file)?;
+
+    let mut functions = vec![];
+    for (_, symbol) in obj.symbols.iter_ordered() {
+        let Some(section_index) = symbol.section else { continue };
+        if symbol.kind != ObjSymbolKind::Function || symbol.size == 0 {
+            continue;
+        }
+        let (start, end) = (symbol.address as u32, (symbol.address + symbol.size) as u32);
+        let data = obj.sections[section_index].data_range(start, end)?;
+        let matched = linked_obj.symbols.for_name(&symbol.name).any(|(_, linked)| {
+            linked.size == symbol.size
+                && linked.section.is_some_and(|i| {
+                    let linked_section = &linked_obj.sections[i];
+                    let linked_start = linked.address as u32;
+                    linked_section
+                        .data_range(linked_start, linked_start + linked.size as u32)
+                        .is_ok_and(|linked_data| linked_data == data)
+                })
+        });
+        functions.push(FunctionRecord {
+            name: symbol.name.to_string(),
+            address: start,
+            size: symbol.size as u32,
+            hash: format!("{:016x}", xxh3_64(data)),
+            matched,
+        });
+    }
+
+    let previous: Vec<FunctionRecord> = if manifest_path.is_file() {
+        serde_yaml::from_reader(&mut buf_reader(&manifest_path)?)
+            .with_context(|| format!("While loading '{}'", manifest_path.display()))?
+    } else {
+        vec![]
+    };
+    let previous = previous.iter().map(|f| (f.name.as_str(), f)).collect::<HashMap<_, _>>();
+    let mut regressions = 0;
+    for function in &functions {
+        let change = match previous.get(function.name.as_str()) {
+            // A changed original hash means the symbol was moved or resized, not a regression
+            Some(prev) if prev.hash == function.hash && prev.matched && !function.matched => {
+                regressions += 1;
+                "regressed"
+            }
+            Some(prev) if prev.matched || !function.matched => continue,
+            _ if function.matched => "matched",
+            _ => continue,
+        };
+        if is_json() {
+            print_json(&ChangeRecord { name: &function.name, address: function.address, change })?;
+        } else {
+            println!("{:#010X} {} {}", function.address, function.name, change);
+        }
+    }
+
+    let matched = functions.iter().filter(|f| f.matched).count();
+    info!("{} of {} functions match", matched, functions.len());
+    if regressions > 0 && !args.force {
+        return Err(coded_error(
+            ErrorCode::ChecksumMismatch,
+            format!("{regressions} function(s) no longer match; not updating the manifest"),
+        ));
+    }
+    let mut w = buf_writer(&manifest_path)?;
+    serde_yaml::to_writer(&mut w, &functions)?;
+    w.flush()?;
+    info!("Wrote {}", manifest_path.display());
+    Ok(())
+}
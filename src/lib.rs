@@ -0,0 +1,36 @@
+//! Yet another GameCube/Wii decompilation toolkit.
+//!
+//! The `dtk` binary is a thin CLI wrapper around this crate. Other tools can use
+//! [`obj`], [`analysis`] and [`util`] directly to load DOL, REL and ELF files,
+//! run analysis passes and query symbols.
+//!
+//! ```no_run
+//! use decomp_toolkit::util::{dol::process_dol, file::map_file};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let file = map_file("main.dol")?;
+//! let obj = process_dol(file.as_slice(), "main")?;
+//! for symbol in obj.symbols.iter() {
+//!     println!("{:#010X} {}", symbol.address, symbol.name);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Game-specific analysis can be added without forking by implementing
+//! [`PipelinePass`](analysis::pipeline::PipelinePass), adding it to the default
+//! [`Pipeline`](analysis::pipeline::Pipeline) and running a command with it, such as
+//! [`cmd::dol::run_with_pipeline`]. Added passes run on every DOL and REL the command
+//! analyzes, after the built-in passes.
+//!
+//! The [`cmd`] module and its dependencies need the default `cli` feature. Without it, the
+//! library builds for `wasm32-unknown-unknown`, so browser tools can parse DOL, REL and RSO
+//! files, demangle and disassemble with the same code as the CLI.
+//!
+//! Python bindings are in the `decomp-toolkit-python` crate of the workspace.
+
+pub mod analysis;
+#[cfg(feature = "cli")]
+pub mod cmd;
+pub mod obj;
+pub mod util;
@@ -1,61 +1,19 @@
-use std::{env, ffi::OsStr, path::PathBuf, process::exit, str::FromStr};
+use std::{env, fs::File, path::PathBuf, process::exit, sync::Mutex};
 
 use anyhow::Error;
-use argp::{FromArgValue, FromArgs};
+use argp::FromArgs;
+use decomp_toolkit::{
+    cmd,
+    util::output::{
+        print_error, print_success, set_output_format, ErrorCode, OutputFormat, WarningCollector,
+    },
+};
 use enable_ansi_support::enable_ansi_support;
 use supports_color::Stream;
 use tracing::level_filters::LevelFilter;
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::{fmt::writer::BoxMakeWriter, prelude::*, EnvFilter};
 
-pub mod analysis;
-pub mod argp_version;
-pub mod cmd;
-pub mod obj;
-pub mod util;
-
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
-enum LogLevel {
-    Error,
-    Warn,
-    Info,
-    Debug,
-    Trace,
-}
-
-impl FromStr for LogLevel {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
-            "error" => Self::Error,
-            "warn" => Self::Warn,
-            "info" => Self::Info,
-            "debug" => Self::Debug,
-            "trace" => Self::Trace,
-            _ => return Err(()),
-        })
-    }
-}
-
-impl ToString for LogLevel {
-    fn to_string(&self) -> String {
-        match self {
-            LogLevel::Error => "error",
-            LogLevel::Warn => "warn",
-            LogLevel::Info => "info",
-            LogLevel::Debug => "debug",
-            LogLevel::Trace => "trace",
-        }
-        .to_string()
-    }
-}
-
-impl FromArgValue for LogLevel {
-    fn from_arg_value(value: &OsStr) -> Result<Self, String> {
-        String::from_arg_value(value)
-            .and_then(|s| Self::from_str(&s).map_err(|_| "Invalid log level".to_string()))
-    }
-}
+mod argp_version;
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// Yet another GameCube/Wii decompilation toolkit.
@@ -66,15 +24,26 @@ struct TopLevel {
     /// Change working directory.
     chdir: Option<PathBuf>,
     #[argp(option, short = 'L')]
-    /// Minimum logging level. (Default: info)
-    /// Possible values: error, warn, info, debug, trace
-    log_level: Option<LogLevel>,
+    /// Logging filter: a minimum level and/or comma-separated module=level directives.
+    /// (Default: info, env: RUST_LOG) Example: -L analysis=trace,info
+    /// Possible levels: error, warn, info, debug, trace
+    log_level: Option<String>,
+    #[argp(option)]
+    /// Also write logs to this file.
+    log_file: Option<PathBuf>,
     /// Print version information and exit.
     #[argp(switch, short = 'V')]
     version: bool,
     /// Disable color output. (env: NO_COLOR)
     #[argp(switch)]
     no_color: bool,
+    #[argp(option)]
+    /// Output format for results and errors. (Default: text)
+    /// Possible values: text, json. Commands that only print text fail with json.
+    format: Option<OutputFormat>,
+    #[argp(option)]
+    /// Number of threads to use. (Default: number of logical CPUs)
+    threads: Option<usize>,
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
@@ -82,18 +51,37 @@ struct TopLevel {
 enum SubCommand {
     Alf(cmd::alf::Args),
     Ar(cmd::ar::Args),
+    BuildInfo(cmd::buildinfo::Args),
+    Completions(cmd::completions::Args),
+    Config(cmd::config::Args),
     Demangle(cmd::demangle::Args),
+    Diff(cmd::diff::Args),
+    Disasm(cmd::disasm::Args),
+    Disc(cmd::disc::Args),
     Dol(cmd::dol::Args),
+    Dump(cmd::dump::Args),
+    Duplicates(cmd::duplicates::Args),
     Dwarf(cmd::dwarf::Args),
     Elf(cmd::elf::Args),
     Elf2Dol(cmd::elf2dol::Args),
+    Fingerprint(cmd::fingerprint::Args),
+    Inspect(cmd::inspect::Args),
+    M2c(cmd::m2c::Args),
     Map(cmd::map::Args),
-    MetroidBuildInfo(cmd::metroidbuildinfo::Args),
+    MetroidBuildInfo(cmd::buildinfo::MetroidArgs),
     Nlzss(cmd::nlzss::Args),
+    Nm(cmd::nm::Args),
+    Port(cmd::port::Args),
+    Ram(cmd::ram::Args),
     Rarc(cmd::rarc::Args),
     Rel(cmd::rel::Args),
+    Rename(cmd::rename::Args),
+    Report(cmd::report::Args),
     Rso(cmd::rso::Args),
     Shasum(cmd::shasum::Args),
+    Signatures(cmd::signatures::Args),
+    Strings(cmd::strings::Args),
+    VerifyFunctions(cmd::verify_functions::Args),
     Yaz0(cmd::yaz0::Args),
 }
 
@@ -105,9 +93,32 @@ fn env_no_color() -> bool {
     }
 }
 
+/// Expands bare module names relative to this crate, so that `analysis=trace`
+/// matches `decomp_toolkit::analysis`.
+fn expand_log_filter(filter: &str) -> String {
+    filter
+        .split(',')
+        .map(|directive| match directive.split_once('=') {
+            Some((target, level))
+                if !target.is_empty()
+                    && !target.contains("::")
+                    && !target.contains('[')
+                    && target != "decomp_toolkit" =>
+            {
+                format!("decomp_toolkit::{target}={level}")
+            }
+            _ => directive.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 fn main() {
     let args: TopLevel = argp_version::from_env();
-    let use_colors = if args.no_color || env_no_color() {
+    let format = args.format.unwrap_or_default();
+    set_output_format(format);
+    let json = format == OutputFormat::Json;
+    let use_colors = if json || args.no_color || env_no_color() {
         false
     } else {
         // Try to enable ANSI support on Windows.
@@ -121,28 +132,52 @@ fn main() {
     // in owo-colors removes set_override and if_supports_color entirely.
     owo_colors::set_override(use_colors);
 
-    let format =
+    let event_format =
         tracing_subscriber::fmt::format().with_ansi(use_colors).with_target(false).without_time();
-    let builder = tracing_subscriber::fmt().event_format(format);
-    if let Some(level) = args.log_level {
-        builder
-            .with_max_level(match level {
-                LogLevel::Error => LevelFilter::ERROR,
-                LogLevel::Warn => LevelFilter::WARN,
-                LogLevel::Info => LevelFilter::INFO,
-                LogLevel::Debug => LevelFilter::DEBUG,
-                LogLevel::Trace => LevelFilter::TRACE,
-            })
-            .init();
+    // Keep stdout free for JSON records
+    let writer = if json {
+        BoxMakeWriter::new(std::io::stderr)
     } else {
-        builder
-            .with_env_filter(
-                EnvFilter::builder()
-                    .with_default_directive(LevelFilter::INFO.into())
-                    .from_env_lossy(),
-            )
-            .init();
-    }
+        BoxMakeWriter::new(std::io::stdout)
+    };
+    let filter = match &args.log_level {
+        Some(directives) => match EnvFilter::builder().parse(expand_log_filter(directives)) {
+            Ok(filter) => filter,
+            Err(e) => {
+                eprintln!("Invalid log filter '{directives}': {e}");
+                exit(1);
+            }
+        },
+        None => {
+            EnvFilter::builder().with_default_directive(LevelFilter::INFO.into()).from_env_lossy()
+        }
+    };
+    let file_layer = match &args.log_file {
+        Some(path) => match File::create(path) {
+            Ok(file) => Some(
+                tracing_subscriber::fmt::layer().with_ansi(false).with_writer(Mutex::new(file)),
+            ),
+            Err(e) => {
+                eprintln!("Failed to create log file '{}': {e}", path.display());
+                exit(1);
+            }
+        },
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().event_format(event_format).with_writer(writer))
+        .with(file_layer)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_target(false)
+                .with_level(false)
+                .without_time()
+                .with_writer(|| WarningCollector)
+                .with_filter(LevelFilter::WARN),
+        )
+        .init();
 
     let mut result = Ok(());
     if let Some(dir) = &args.chdir {
@@ -151,25 +186,59 @@ fn main() {
                 .context(format!("Failed to change working directory to '{}'", dir.display()))
         });
     }
+    if let Some(threads) = args.threads {
+        result = result.and_then(|_| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build_global()
+                .map_err(|e| Error::new(e).context("Failed to configure thread pool"))
+        });
+    }
     result = result.and_then(|_| match args.command {
         SubCommand::Alf(c_args) => cmd::alf::run(c_args),
         SubCommand::Ar(c_args) => cmd::ar::run(c_args),
+        SubCommand::BuildInfo(c_args) => cmd::buildinfo::run(c_args),
+        SubCommand::Completions(c_args) => cmd::completions::run(c_args),
+        SubCommand::Config(c_args) => cmd::config::run(c_args),
         SubCommand::Demangle(c_args) => cmd::demangle::run(c_args),
+        SubCommand::Diff(c_args) => cmd::diff::run(c_args),
+        SubCommand::Disasm(c_args) => cmd::disasm::run(c_args),
+        SubCommand::Disc(c_args) => cmd::disc::run(c_args),
         SubCommand::Dol(c_args) => cmd::dol::run(c_args),
+        SubCommand::Dump(c_args) => cmd::dump::run(c_args),
+        SubCommand::Duplicates(c_args) => cmd::duplicates::run(c_args),
         SubCommand::Dwarf(c_args) => cmd::dwarf::run(c_args),
         SubCommand::Elf(c_args) => cmd::elf::run(c_args),
         SubCommand::Elf2Dol(c_args) => cmd::elf2dol::run(c_args),
+        SubCommand::Fingerprint(c_args) => cmd::fingerprint::run(c_args),
+        SubCommand::Inspect(c_args) => cmd::inspect::run(c_args),
+        SubCommand::M2c(c_args) => cmd::m2c::run(c_args),
         SubCommand::Map(c_args) => cmd::map::run(c_args),
-        SubCommand::MetroidBuildInfo(c_args) => cmd::metroidbuildinfo::run(c_args),
+        SubCommand::MetroidBuildInfo(c_args) => cmd::buildinfo::run_metroid(c_args),
         SubCommand::Nlzss(c_args) => cmd::nlzss::run(c_args),
+        SubCommand::Nm(c_args) => cmd::nm::run(c_args),
+        SubCommand::Port(c_args) => cmd::port::run(c_args),
+        SubCommand::Ram(c_args) => cmd::ram::run(c_args),
         SubCommand::Rarc(c_args) => cmd::rarc::run(c_args),
         SubCommand::Rel(c_args) => cmd::rel::run(c_args),
+        SubCommand::Rename(c_args) => cmd::rename::run(c_args),
+        SubCommand::Report(c_args) => cmd::report::run(c_args),
         SubCommand::Rso(c_args) => cmd::rso::run(c_args),
         SubCommand::Shasum(c_args) => cmd::shasum::run(c_args),
+        SubCommand::Signatures(c_args) => cmd::signatures::run(c_args),
+        SubCommand::Strings(c_args) => cmd::strings::run(c_args),
+        SubCommand::VerifyFunctions(c_args) => cmd::verify_functions::run(c_args),
         SubCommand::Yaz0(c_args) => cmd::yaz0::run(c_args),
     });
-    if let Err(e) = result {
-        eprintln!("Failed: {e:?}");
-        exit(1);
+    match result {
+        Ok(()) if json => {
+            let _ = print_success();
+        }
+        Ok(()) => {}
+        Err(e) if json => exit(print_error(&e)),
+        Err(e) => {
+            eprintln!("Failed: {e:?}");
+            exit(ErrorCode::from_error(&e).exit_code());
+        }
     }
 }
@@ -1,3 +1,4 @@
+mod names;
 mod relocations;
 mod sections;
 mod splits;
@@ -10,17 +11,19 @@ use std::{
 };
 
 use anyhow::{anyhow, bail, ensure, Result};
+pub use names::SymbolName;
 pub use relocations::{ObjReloc, ObjRelocKind, ObjRelocations};
-pub use sections::{ObjSection, ObjSectionKind, ObjSections};
+pub use sections::{AddressSpace, ObjSection, ObjSectionKind, ObjSections};
+use serde::{Deserialize, Serialize};
 pub use splits::{ObjSplit, ObjSplits};
 pub use symbols::{
     best_match_for_reloc, ObjDataKind, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind,
-    ObjSymbolScope, ObjSymbols, SymbolIndex,
+    ObjSymbolScope, ObjSymbolSource, ObjSymbols, SymbolIndex,
 };
 
 use crate::{
     analysis::cfa::SectionAddress,
-    util::{comment::MWComment, rel::RelReloc},
+    util::{comment::MWComment, rel::RelRelocations},
 };
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -36,14 +39,159 @@ pub enum ObjArchitecture {
     PowerPc,
 }
 
+/// Source language of a translation unit.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ObjUnitLanguage {
+    C,
+    Cpp,
+    Asm,
+}
+
+/// Decompilation status of a translation unit.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ObjUnitStatus {
+    /// Not yet decompiled, or not yet matching.
+    NonMatching,
+    /// Compiles to identical code and data.
+    Matching,
+    /// Functionally equivalent, but not an exact match.
+    Equivalent,
+}
+
+/// Target console, which determines the default memory ranges.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    #[default]
+    GameCube,
+    Wii,
+    WiiU,
+}
+
+impl Platform {
+    pub fn memory_ranges(self) -> Vec<MemoryRange> {
+        let range = |name: &str, start, end| MemoryRange { name: name.to_string(), start, end };
+        if self == Platform::WiiU {
+            return vec![
+                range("code", 0x02000000, 0x10000000),
+                range("data", 0x10000000, 0x50000000),
+            ];
+        }
+        let mut ranges = vec![range("MEM1", 0x80000000, 0x81800000)];
+        if self == Platform::Wii {
+            ranges.push(range("MEM2", 0x90000000, 0x94000000));
+        }
+        ranges.push(range("locked cache", 0xE0000000, 0xE0004000));
+        ranges
+    }
+}
+
+/// A range of addresses that pointers may refer to.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct MemoryRange {
+    pub name: String,
+    pub start: u32,
+    /// Exclusive
+    pub end: u32,
+}
+
+/// Fill between functions in code sections, which differs by compiler settings.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ObjUnitPadding {
+    /// Zeroes, as the linker pads with.
+    Zero,
+    /// `nop` instructions.
+    Nop,
+}
+
 /// Translation unit information.
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
 pub struct ObjUnit {
     pub name: String,
     /// Generated, replaceable by user.
     pub autogenerated: bool,
     /// MW `.comment` section version.
     pub comment_version: Option<u8>,
+    /// Compiler version, e.g. `GC/1.3.2`.
+    pub compiler: Option<String>,
+    /// Compiler flags.
+    pub cflags: Option<String>,
+    /// Source language.
+    pub lang: Option<ObjUnitLanguage>,
+    /// Source file path, relative to the project source directory.
+    pub source: Option<String>,
+    /// Decompilation status.
+    pub status: Option<ObjUnitStatus>,
+    /// decomp.me compiler preset ID.
+    pub preset: Option<u32>,
+    /// Whether decomp.me scratches can be created for the unit.
+    pub scratch: Option<bool>,
+    /// Fill between functions in code sections.
+    pub padding: Option<ObjUnitPadding>,
+    /// Static library the unit was linked from, e.g. `os` for `os.a`.
+    pub library: Option<String>,
+}
+
+/// Shared attributes for all translation units matching a glob pattern.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ObjUnitPattern {
+    pub pattern: glob::Pattern,
+    /// MW `.comment` section version.
+    pub comment_version: Option<u8>,
+    /// Compiler version, e.g. `GC/1.3.2`.
+    pub compiler: Option<String>,
+    /// Compiler flags.
+    pub cflags: Option<String>,
+    /// Source language.
+    pub lang: Option<ObjUnitLanguage>,
+    /// Decompilation status.
+    pub status: Option<ObjUnitStatus>,
+    /// decomp.me compiler preset ID.
+    pub preset: Option<u32>,
+    /// Whether decomp.me scratches can be created for the unit.
+    pub scratch: Option<bool>,
+    /// Fill between functions in code sections.
+    pub padding: Option<ObjUnitPadding>,
+    /// Static library the unit was linked from, e.g. `os` for `os.a`.
+    pub library: Option<String>,
+}
+
+impl ObjUnitPattern {
+    #[inline]
+    pub fn matches(&self, unit: &str) -> bool { self.pattern.matches(unit) }
+
+    /// Fills in any unset attributes of the unit.
+    pub fn apply_to(&self, unit: &mut ObjUnit) {
+        if unit.comment_version.is_none() {
+            unit.comment_version = self.comment_version;
+        }
+        if unit.compiler.is_none() {
+            unit.compiler = self.compiler.clone();
+        }
+        if unit.cflags.is_none() {
+            unit.cflags = self.cflags.clone();
+        }
+        if unit.lang.is_none() {
+            unit.lang = self.lang;
+        }
+        if unit.status.is_none() {
+            unit.status = self.status;
+        }
+        if unit.preset.is_none() {
+            unit.preset = self.preset;
+        }
+        if unit.padding.is_none() {
+            unit.padding = self.padding;
+        }
+        if unit.scratch.is_none() {
+            unit.scratch = self.scratch;
+        }
+        if unit.library.is_none() {
+            unit.library = self.library.clone();
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -64,21 +212,41 @@ pub struct ObjInfo {
     pub db_stack_addr: Option<u32>,
     pub arena_lo: Option<u32>,
     pub arena_hi: Option<u32>,
+    /// Valid pointer targets; analysis rejects pointers outside them. Unchecked if empty.
+    pub memory_ranges: Vec<MemoryRange>,
 
     // Extracted
     pub link_order: Vec<ObjUnit>,
+    pub unit_patterns: Vec<ObjUnitPattern>,
     pub blocked_ranges: BTreeMap<SectionAddress, u32>, // start -> end
+    /// Exception handler code that's copied to the exception vectors at runtime, such as the
+    /// MetroTRK vector table. It runs with arbitrary register state, so r2 and r13 aren't
+    /// assumed to hold the SDA bases.
+    pub vector_ranges: BTreeMap<SectionAddress, u32>, // start -> end
+    /// Local symbols scoped to a unit, so their names can repeat across units
+    pub unit_locals: BTreeMap<(SectionAddress, SymbolName), String>, // (address, name) -> unit
 
     // From .ctors, .dtors and extab
     pub known_functions: BTreeMap<SectionAddress, Option<u32>>,
 
+    // From analysis hints
+    pub known_jump_tables: BTreeMap<SectionAddress, u32>, // start -> size
+    pub non_terminal_returns: BTreeSet<SectionAddress>,
+    pub noreturn_functions: BTreeSet<SectionAddress>,
+
     // REL
     /// Module ID (0 for main)
     pub module_id: u32,
-    pub unresolved_relocations: Vec<RelReloc>,
+    pub unresolved_relocations: RelRelocations,
 }
 
 impl ObjInfo {
+    /// Whether an address falls within the valid memory ranges, if any are set.
+    pub fn is_valid_memory(&self, addr: u32) -> bool {
+        self.memory_ranges.is_empty()
+            || self.memory_ranges.iter().any(|r| addr >= r.start && addr < r.end)
+    }
+
     pub fn new(
         kind: ObjKind,
         architecture: ObjArchitecture,
@@ -101,11 +269,18 @@ impl ObjInfo {
             db_stack_addr: None,
             arena_lo: None,
             arena_hi: None,
+            memory_ranges: vec![],
             link_order: vec![],
+            unit_patterns: vec![],
             blocked_ranges: Default::default(),
+            vector_ranges: Default::default(),
+            unit_locals: Default::default(),
             known_functions: Default::default(),
+            known_jump_tables: Default::default(),
+            non_terminal_returns: Default::default(),
+            noreturn_functions: Default::default(),
             module_id: 0,
-            unresolved_relocations: vec![],
+            unresolved_relocations: Default::default(),
         }
     }
 
@@ -278,6 +453,8 @@ impl ObjInfo {
                 autogenerated: new_autogenerated,
                 skip: false,  // ?
                 rename: None, // ?
+                pad: None,
+                end_align: None,
             })?;
             return Ok(());
         }
@@ -287,6 +464,24 @@ impl ObjInfo {
         Ok(())
     }
 
+    /// Fills in unset unit attributes from matching unit patterns, in order.
+    pub fn apply_unit_patterns(&mut self) {
+        for unit in &mut self.link_order {
+            for pattern in self.unit_patterns.iter().filter(|p| p.matches(&unit.name)) {
+                pattern.apply_to(unit);
+            }
+        }
+    }
+
+    /// The attributes a unit would inherit from matching unit patterns alone.
+    pub fn inherited_unit(&self, name: &str) -> ObjUnit {
+        let mut unit = ObjUnit { name: name.to_string(), ..Default::default() };
+        for pattern in self.unit_patterns.iter().filter(|p| p.matches(name)) {
+            pattern.apply_to(&mut unit);
+        }
+        unit
+    }
+
     pub fn is_unit_autogenerated(&self, unit: &str) -> bool {
         self.sections
             .all_splits()
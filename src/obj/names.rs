@@ -0,0 +1,152 @@
+use std::{cmp::Ordering, collections::HashMap, fmt, num::NonZeroU32, ops::Deref, sync::RwLock};
+
+use once_cell::sync::Lazy;
+
+/// An interned symbol name.
+///
+/// Each distinct name is stored once per process, in a shared table, and referred to by its
+/// `u32` index. Copying a name is free, and so is comparing two for equality. Names are never
+/// removed from the table.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SymbolName(NonZeroU32);
+
+struct NameTable {
+    names: Vec<&'static str>,
+    ids: HashMap<&'static str, SymbolName>,
+}
+
+impl NameTable {
+    fn insert(&mut self, name: &str) -> SymbolName {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let name: &'static str = Box::leak(name.into());
+        self.names.push(name);
+        let id = u32::try_from(self.names.len())
+            .ok()
+            .and_then(NonZeroU32::new)
+            .map(SymbolName)
+            .expect("Too many symbol names");
+        self.ids.insert(name, id);
+        id
+    }
+}
+
+static NAMES: Lazy<RwLock<NameTable>> = Lazy::new(|| {
+    let mut table = NameTable { names: vec![], ids: HashMap::new() };
+    table.insert("");
+    RwLock::new(table)
+});
+
+impl SymbolName {
+    /// Interns a name, or returns the existing copy.
+    pub fn new(name: &str) -> Self {
+        if let Some(id) = Self::get(name) {
+            return id;
+        }
+        NAMES.write().unwrap().insert(name)
+    }
+
+    /// Looks up a name without interning it. Returns `None` if no symbol was ever given it.
+    pub fn get(name: &str) -> Option<Self> { NAMES.read().unwrap().ids.get(name).copied() }
+
+    pub fn as_str(self) -> &'static str { NAMES.read().unwrap().names[self.0.get() as usize - 1] }
+}
+
+impl Default for SymbolName {
+    fn default() -> Self { Self::new("") }
+}
+
+impl Deref for SymbolName {
+    type Target = str;
+
+    fn deref(&self) -> &str { self.as_str() }
+}
+
+impl AsRef<str> for SymbolName {
+    fn as_ref(&self) -> &str { self.as_str() }
+}
+
+/// Names are ordered by their text, not by when they were interned.
+impl Ord for SymbolName {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            Ordering::Equal
+        } else {
+            self.as_str().cmp(other.as_str())
+        }
+    }
+}
+
+impl PartialOrd for SymbolName {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl fmt::Display for SymbolName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { fmt::Display::fmt(self.as_str(), f) }
+}
+
+impl fmt::Debug for SymbolName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { fmt::Debug::fmt(self.as_str(), f) }
+}
+
+impl From<&str> for SymbolName {
+    fn from(name: &str) -> Self { Self::new(name) }
+}
+
+impl From<&String> for SymbolName {
+    fn from(name: &String) -> Self { Self::new(name) }
+}
+
+impl From<String> for SymbolName {
+    fn from(name: String) -> Self { Self::new(&name) }
+}
+
+impl From<SymbolName> for String {
+    fn from(name: SymbolName) -> Self { name.as_str().to_string() }
+}
+
+impl PartialEq<str> for SymbolName {
+    fn eq(&self, other: &str) -> bool { self.as_str() == other }
+}
+
+impl PartialEq<&str> for SymbolName {
+    fn eq(&self, other: &&str) -> bool { self.as_str() == *other }
+}
+
+impl PartialEq<String> for SymbolName {
+    fn eq(&self, other: &String) -> bool { self.as_str() == other }
+}
+
+impl PartialEq<SymbolName> for str {
+    fn eq(&self, other: &SymbolName) -> bool { self == other.as_str() }
+}
+
+impl PartialEq<SymbolName> for &str {
+    fn eq(&self, other: &SymbolName) -> bool { *self == other.as_str() }
+}
+
+impl PartialEq<SymbolName> for String {
+    fn eq(&self, other: &SymbolName) -> bool { self == other.as_str() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern_names() {
+        let a = SymbolName::new("intern_names_a");
+        let b = SymbolName::from("intern_names_b".to_string());
+        assert_eq!(a, SymbolName::new("intern_names_a"));
+        assert_ne!(a, b);
+        assert_eq!(a, "intern_names_a");
+        assert_eq!(b.len(), 14);
+        assert!(a < b);
+        assert_eq!(SymbolName::get("intern_names_b"), Some(b));
+        assert_eq!(SymbolName::get("intern_names_c"), None);
+        assert!(SymbolName::default().is_empty());
+        assert_eq!(format!("{a} {b:?}"), "intern_names_a \"intern_names_b\"");
+        assert_eq!(std::mem::size_of::<Option<SymbolName>>(), 4);
+    }
+}
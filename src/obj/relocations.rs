@@ -19,6 +19,17 @@ pub enum ObjRelocKind {
     PpcRel24,
     PpcRel14,
     PpcEmbSda21,
+    /// Offset from the relocation to the target (`R_PPC_REL32`)
+    PpcRel32,
+    /// Halves of the offset from the relocation to the target (`R_PPC_REL16_*`, and
+    /// `R_PPC_GHS_REL16_*` in Wii U executables)
+    PpcRel16Hi,
+    PpcRel16Ha,
+    PpcRel16Lo,
+    /// TLS module ID of the target (`R_PPC_DTPMOD32`)
+    PpcDtpMod32,
+    /// Offset of the target in its TLS block (`R_PPC_DTPREL32`)
+    PpcDtpRel32,
 }
 
 #[derive(Debug, Clone)]
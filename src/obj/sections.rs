@@ -17,6 +17,37 @@ pub enum ObjSectionKind {
     Bss,
 }
 
+/// Region of the console address map an address belongs to.
+/// Both the cached (`0x8`/`0x9`) and uncached (`0xC`/`0xD`) mirrors map to the same region.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AddressSpace {
+    /// Main memory, 24 MB.
+    Mem1,
+    /// Wii extended memory, 64 MB.
+    Mem2,
+    /// Hardware registers and the locked cache.
+    Hardware,
+    Unknown,
+}
+
+impl AddressSpace {
+    pub fn of(addr: u32) -> Self {
+        match addr {
+            0x80000000..=0x817FFFFF | 0xC0000000..=0xC17FFFFF => AddressSpace::Mem1,
+            0x90000000..=0x93FFFFFF | 0xD0000000..=0xD3FFFFFF => AddressSpace::Mem2,
+            0xCC000000..=0xCDFFFFFF | 0xE0000000..=0xE0003FFF => AddressSpace::Hardware,
+            _ => AddressSpace::Unknown,
+        }
+    }
+
+    /// Whether the address is accessed through the uncached mirror of main memory.
+    #[inline]
+    pub fn is_uncached(addr: u32) -> bool {
+        matches!(AddressSpace::of(addr), AddressSpace::Mem1 | AddressSpace::Mem2)
+            && matches!(addr >> 28, 0xC | 0xD)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ObjSection {
     pub name: String,
@@ -145,6 +176,24 @@ impl IndexMut<usize> for ObjSections {
 }
 
 impl ObjSection {
+    /// A section holding the contents of a raw binary loaded at `address`.
+    pub fn raw(name: &str, kind: ObjSectionKind, address: u32, data: Vec<u8>) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            address: address as u64,
+            size: data.len() as u64,
+            data,
+            align: 0,
+            elf_index: 0,
+            relocations: Default::default(),
+            original_address: 0,
+            file_offset: 0,
+            section_known: true,
+            splits: Default::default(),
+        }
+    }
+
     pub fn data_range(&self, start: u32, end: u32) -> Result<&[u8]> {
         if end == 0 {
             ensure!(
@@ -208,6 +257,9 @@ impl ObjSection {
         start_in_range && end_in_range
     }
 
+    #[inline]
+    pub fn address_space(&self) -> AddressSpace { AddressSpace::of(self.address as u32) }
+
     pub fn rename(&mut self, name: String) -> Result<()> {
         self.kind = section_kind_for_section(&name)?;
         self.name = name;
@@ -22,6 +22,10 @@ pub struct ObjSplit {
     pub skip: bool,
     /// Override the section name in the split object. (e.g. `.ctors$10`)
     pub rename: Option<String>,
+    /// Zero bytes to emit after `end` in the split object, following `end_align`.
+    pub pad: Option<u32>,
+    /// Pad the end of the split object's section to this alignment.
+    pub end_align: Option<u32>,
 }
 
 impl ObjSplit {
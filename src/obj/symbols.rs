@@ -12,7 +12,7 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::{
     analysis::cfa::SectionAddress,
-    obj::{ObjKind, ObjRelocKind},
+    obj::{ObjKind, ObjRelocKind, SymbolName},
     util::{config::is_auto_symbol, nested::NestedVec, split::is_linker_generated_label},
 };
 
@@ -26,9 +26,9 @@ pub enum ObjSymbolScope {
 }
 
 flags! {
-    #[repr(u32)]
+    #[repr(u16)]
     #[derive(Deserialize_repr, Serialize_repr)]
-    pub enum ObjSymbolFlags: u32 {
+    pub enum ObjSymbolFlags: u16 {
         Global,
         Local,
         Weak,
@@ -139,6 +139,7 @@ impl Hash for ObjSymbolFlagSet {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default, Serialize, Deserialize)]
+#[repr(u8)]
 pub enum ObjSymbolKind {
     #[default]
     Unknown,
@@ -147,7 +148,40 @@ pub enum ObjSymbolKind {
     Section,
 }
 
+/// Where a symbol's name came from, for judging how trustworthy it is.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+#[repr(u8)]
+pub enum ObjSymbolSource {
+    /// Generated or inferred by analysis.
+    #[default]
+    Analysis,
+    /// Matched by a function signature.
+    Signature,
+    /// Imported from a linker map.
+    Map,
+    /// Read from an ELF symbol table.
+    Elf,
+    /// Read from the input binary itself (ALF symbol tables, REL/RSO headers and exports).
+    Binary,
+    /// Provided by the user in a symbols or hints file.
+    Config,
+}
+
+impl ObjSymbolSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ObjSymbolSource::Analysis => "analysis",
+            ObjSymbolSource::Signature => "signature",
+            ObjSymbolSource::Map => "map",
+            ObjSymbolSource::Elf => "elf",
+            ObjSymbolSource::Binary => "binary",
+            ObjSymbolSource::Config => "config",
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[repr(u8)]
 pub enum ObjDataKind {
     #[default]
     Unknown,
@@ -165,8 +199,8 @@ pub enum ObjDataKind {
 
 #[derive(Debug, Clone, Default, Eq, PartialEq)]
 pub struct ObjSymbol {
-    pub name: String,
-    pub demangled_name: Option<String>,
+    pub name: SymbolName,
+    pub demangled_name: Option<SymbolName>,
     pub address: u64,
     pub section: Option<usize>,
     pub size: u64,
@@ -178,6 +212,7 @@ pub struct ObjSymbol {
     /// ALF hashes
     pub name_hash: Option<u32>,
     pub demangled_name_hash: Option<u32>,
+    pub source: ObjSymbolSource,
 }
 
 pub type SymbolIndex = usize;
@@ -187,7 +222,7 @@ pub struct ObjSymbols {
     obj_kind: ObjKind,
     symbols: Vec<ObjSymbol>,
     symbols_by_address: BTreeMap<u32, Vec<SymbolIndex>>,
-    symbols_by_name: HashMap<String, Vec<SymbolIndex>>,
+    symbols_by_name: HashMap<SymbolName, Vec<SymbolIndex>>,
     symbols_by_section: Vec<BTreeMap<u32, Vec<SymbolIndex>>>,
 }
 
@@ -195,7 +230,7 @@ impl ObjSymbols {
     pub fn new(obj_kind: ObjKind, symbols: Vec<ObjSymbol>) -> Self {
         let mut symbols_by_address = BTreeMap::<u32, Vec<SymbolIndex>>::new();
         let mut symbols_by_section: Vec<BTreeMap<u32, Vec<SymbolIndex>>> = vec![];
-        let mut symbols_by_name = HashMap::<String, Vec<SymbolIndex>>::new();
+        let mut symbols_by_name = HashMap::<SymbolName, Vec<SymbolIndex>>::new();
         for (idx, symbol) in symbols.iter().enumerate() {
             symbols_by_address.nested_push(symbol.address as u32, idx);
             if let Some(section_idx) = symbol.section {
@@ -212,7 +247,7 @@ impl ObjSymbols {
                 );
             }
             if !symbol.name.is_empty() {
-                symbols_by_name.nested_push(symbol.name.clone(), idx);
+                symbols_by_name.nested_push(symbol.name, idx);
             }
         }
         Self { obj_kind, symbols, symbols_by_address, symbols_by_name, symbols_by_section }
@@ -266,6 +301,14 @@ impl ObjSymbols {
                 }
                 return Ok(symbol_idx);
             }
+            let mut flags = ObjSymbolFlagSet(in_symbol.flags.0 | existing.flags.keep_flags());
+            // Analysis can't tell weak definitions from global ones, so keep a configured weak
+            if existing.flags.is_weak()
+                && existing.source == ObjSymbolSource::Config
+                && in_symbol.flags.scope() != ObjSymbolScope::Local
+            {
+                flags.set_scope(ObjSymbolScope::Weak);
+            }
             let new_symbol = ObjSymbol {
                 name: in_symbol.name,
                 demangled_name: in_symbol.demangled_name,
@@ -273,7 +316,7 @@ impl ObjSymbols {
                 section: in_symbol.section,
                 size,
                 size_known: existing.size_known || in_symbol.size != 0,
-                flags: ObjSymbolFlagSet(in_symbol.flags.0 | existing.flags.keep_flags()),
+                flags,
                 kind: in_symbol.kind,
                 align: in_symbol.align.or(existing.align),
                 data_kind: match in_symbol.data_kind {
@@ -282,6 +325,7 @@ impl ObjSymbols {
                 },
                 name_hash: in_symbol.name_hash.or(existing.name_hash),
                 demangled_name_hash: in_symbol.demangled_name_hash.or(existing.demangled_name_hash),
+                source: in_symbol.source,
             };
             if existing != &new_symbol {
                 log::debug!("Replacing {:?} with {:?}", existing, new_symbol);
@@ -303,6 +347,7 @@ impl ObjSymbols {
                 data_kind: in_symbol.data_kind,
                 name_hash: in_symbol.name_hash,
                 demangled_name_hash: in_symbol.demangled_name_hash,
+                source: in_symbol.source,
             })?;
             target_symbol_idx
         };
@@ -326,7 +371,7 @@ impl ObjSymbols {
             );
         }
         if !in_symbol.name.is_empty() {
-            self.symbols_by_name.nested_push(in_symbol.name.clone(), symbol_idx);
+            self.symbols_by_name.nested_push(in_symbol.name, symbol_idx);
         }
         self.symbols.push(in_symbol);
         Ok(symbol_idx)
@@ -428,15 +473,20 @@ impl ObjSymbols {
         &self,
         name: &str,
     ) -> impl DoubleEndedIterator<Item = (SymbolIndex, &ObjSymbol)> {
-        self.symbols_by_name
-            .get(name)
+        SymbolName::get(name)
+            .and_then(|name| self.symbols_by_name.get(&name))
             .into_iter()
             .flat_map(move |v| v.iter().map(move |u| (*u, &self.symbols[*u])))
     }
 
+    /// Looks up a symbol by name. Like the linker, a single strong definition takes
+    /// precedence over any weak ones, and the first of several weak definitions is used.
     pub fn by_name(&self, name: &str) -> Result<Option<(SymbolIndex, &ObjSymbol)>> {
-        let mut iter = self.for_name(name);
+        let mut iter = self.for_name(name).filter(|(_, s)| !s.flags.is_weak());
         let result = iter.next();
+        if result.is_none() {
+            return Ok(self.for_name(name).next());
+        }
         if let Some((index, symbol)) = result {
             if let Some((other_index, other_symbol)) = iter.next() {
                 bail!(
@@ -470,7 +520,7 @@ impl ObjSymbols {
                 self.symbols_by_name.nested_remove(&symbol_ref.name, &index);
             }
             if !symbol.name.is_empty() {
-                self.symbols_by_name.nested_push(symbol.name.clone(), index);
+                self.symbols_by_name.nested_push(symbol.name, index);
             }
         }
         *symbol_ref = symbol;
@@ -573,7 +623,13 @@ pub fn best_match_for_reloc(
                 ObjRelocKind::Absolute
                 | ObjRelocKind::PpcRel24
                 | ObjRelocKind::PpcRel14
-                | ObjRelocKind::PpcEmbSda21 => 2,
+                | ObjRelocKind::PpcEmbSda21
+                | ObjRelocKind::PpcRel32
+                | ObjRelocKind::PpcRel16Hi
+                | ObjRelocKind::PpcRel16Ha
+                | ObjRelocKind::PpcRel16Lo
+                | ObjRelocKind::PpcDtpMod32
+                | ObjRelocKind::PpcDtpRel32 => 2,
             },
             // Label
             ObjSymbolKind::Unknown => match reloc_kind {
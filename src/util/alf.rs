@@ -7,7 +7,7 @@ use anyhow::Result;
 use io::{Error, ErrorKind};
 
 use crate::{
-    obj::{ObjSymbol, ObjSymbolKind},
+    obj::{ObjSymbol, ObjSymbolKind, ObjSymbolSource, SymbolName},
     util::{
         dol::{DolLike, DolSection, DolSectionKind},
         reader::{
@@ -224,10 +224,10 @@ impl AlfSymbol {
             let hash = u32::from_str_radix(hash_str, 16)?;
             (None, Some(hash))
         } else {
-            (Some(self.demangled_name.clone()), None)
+            (Some(SymbolName::new(&self.demangled_name)), None)
         };
         Ok(ObjSymbol {
-            name,
+            name: name.into(),
             demangled_name,
             address: self.address as u64,
             section: Some(self.section as usize - 1),
@@ -239,6 +239,7 @@ impl AlfSymbol {
             data_kind: Default::default(),
             name_hash,
             demangled_name_hash,
+            source: ObjSymbolSource::Binary,
         })
     }
 }
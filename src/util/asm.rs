@@ -1,19 +1,23 @@
 use std::{
     cmp::{min, Ordering},
     collections::{btree_map, BTreeMap},
+    ffi::OsStr,
     io::Write,
+    str::FromStr,
 };
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
+use argp::FromArgValue;
 use itertools::Itertools;
-use ppc750cl::{disasm_iter, Argument, Ins, Opcode};
+use ppc750cl::{disasm_iter, Argument, Ins, Opcode, SimplifiedIns};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     obj::{
         ObjDataKind, ObjInfo, ObjReloc, ObjRelocKind, ObjSection, ObjSectionKind, ObjSymbol,
         ObjSymbolKind,
     },
-    util::nested::NestedVec,
+    util::{nested::NestedVec, split::is_gap_unit},
 };
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -29,9 +33,244 @@ struct SymbolEntry {
     kind: SymbolEntryKind,
 }
 
-pub fn write_asm<W>(w: &mut W, obj: &ObjInfo) -> Result<()>
+/// Comments describing the switch statements implemented by a section's jump tables.
+#[derive(Debug, Default)]
+struct SwitchComments {
+    /// Summary block written at the start of each table, by table address
+    headers: BTreeMap<u32, Vec<String>>,
+    /// Case values written after each table entry, by entry address
+    cases: BTreeMap<u32, String>,
+}
+
+/// The bounds check guarding a jump table.
+#[derive(Debug, Copy, Clone)]
+struct SwitchBounds {
+    /// Register holding the switch value
+    register: u8,
+    /// Case value of the first table entry
+    base: i32,
+    /// Branch target taken when the value is out of bounds
+    default: Option<u32>,
+}
+
+/// Assembly syntax flavor.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AsmFlavor {
+    /// GNU assembler (powerpc-eabi), using the macros from `macros.inc`.
+    #[default]
+    Gnu,
+    /// GNU assembler, using only the `glabel`/`dlabel` symbol macros
+    /// common to existing matching decompilation projects.
+    Macro,
+    /// CodeWarrior assembler (mwasmeppc).
+    CodeWarrior,
+    /// IDA-style listing, not intended to be assembled.
+    Listing,
+}
+
+impl FromStr for AsmFlavor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "gnu" => Self::Gnu,
+            "macro" | "glabel" => Self::Macro,
+            "codewarrior" | "mw" => Self::CodeWarrior,
+            "listing" | "ida" => Self::Listing,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl FromArgValue for AsmFlavor {
+    fn from_arg_value(value: &OsStr) -> Result<Self, String> {
+        String::from_arg_value(value)
+            .and_then(|s| Self::from_str(&s).map_err(|_| "Invalid assembly flavor".to_string()))
+    }
+}
+
+impl AsmFlavor {
+    /// Whether the output is intended for the GNU assembler.
+    #[inline]
+    fn is_gnu(self) -> bool { matches!(self, AsmFlavor::Gnu | AsmFlavor::Macro) }
+
+    #[inline]
+    fn comment(self) -> &'static str {
+        match self {
+            AsmFlavor::Gnu | AsmFlavor::Macro | AsmFlavor::CodeWarrior => "#",
+            AsmFlavor::Listing => ";",
+        }
+    }
+
+    #[inline]
+    fn data_directive(self, size: usize) -> &'static str {
+        match (self, size) {
+            (_, 1) => ".byte",
+            (flavor, 2) if flavor.is_gnu() => ".2byte",
+            (flavor, 4) if flavor.is_gnu() => ".4byte",
+            (_, 2) => ".short",
+            (_, 4) => ".long",
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// How Gekko/Broadway-specific instructions (paired singles and `dcbz_l`) are written.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GekkoMode {
+    /// Mnemonics, for assemblers with Gekko support.
+    #[default]
+    Mnemonic,
+    /// Raw `.4byte` words with the mnemonic in a comment, for assemblers without it.
+    Raw,
+}
+
+impl FromStr for GekkoMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "mnemonic" => Self::Mnemonic,
+            "raw" => Self::Raw,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl FromArgValue for GekkoMode {
+    fn from_arg_value(value: &OsStr) -> Result<Self, String> {
+        String::from_arg_value(value)
+            .and_then(|s| Self::from_str(&s).map_err(|_| "Invalid Gekko mode".to_string()))
+    }
+}
+
+/// Options for the assembly writer.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AsmOptions {
+    /// Assembly syntax flavor.
+    pub flavor: AsmFlavor,
+    /// Prefix register names with `%` (e.g. `%r3`).
+    pub register_prefix: bool,
+    /// Write raw instruction forms instead of simplified mnemonics (e.g. `addi` over `li`).
+    pub raw_mnemonics: bool,
+    /// How paired-single and other Gekko-specific instructions are written.
+    pub gekko: GekkoMode,
+    /// Write the GQR operand of `psq_*` instructions as `qr0`-`qr7` instead of a number.
+    pub gqr_names: bool,
+    /// Fail on Gekko instructions with reserved bits set, instead of writing them as raw words.
+    pub strict_gekko: bool,
+    /// Default options for data sections.
+    pub data: AsmDataOptions,
+    /// Per-section and per-symbol data option overrides.
+    /// Section overrides are applied first, followed by symbol overrides.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub data_overrides: Vec<AsmDataOverride>,
+    /// Annotate symbols with where their name came from
+    /// (analysis, signature, map, elf, binary or config).
+    pub provenance: bool,
+}
+
+impl AsmOptions {
+    /// Resolves the data options for a section and (optionally) a symbol within it.
+    pub fn data_options(&self, section: &str, symbol: Option<&str>) -> AsmDataOptions {
+        let mut options = self.data;
+        for data_override in
+            self.data_overrides.iter().filter(|o| o.symbol.is_none() && o.matches(section, symbol))
+        {
+            data_override.apply_to(&mut options);
+        }
+        for data_override in
+            self.data_overrides.iter().filter(|o| o.symbol.is_some() && o.matches(section, symbol))
+        {
+            data_override.apply_to(&mut options);
+        }
+        options
+    }
+}
+
+/// Options controlling how data is written.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AsmDataOptions {
+    /// Write all data as raw `.4byte` words, ignoring data types and relocations.
+    pub raw: bool,
+    /// Write floats and doubles as raw words instead of `.float`/`.double`.
+    pub raw_floats: bool,
+    /// Write strings as raw bytes instead of `.string`/`.asciz`.
+    pub raw_strings: bool,
+    /// Write pointers as raw addresses instead of symbol references.
+    pub raw_pointers: bool,
+}
+
+impl AsmDataOptions {
+    #[inline]
+    fn data_kind(&self, kind: ObjDataKind) -> ObjDataKind {
+        match kind {
+            _ if self.raw => ObjDataKind::Unknown,
+            ObjDataKind::Float if self.raw_floats => ObjDataKind::Byte4,
+            ObjDataKind::Double if self.raw_floats => ObjDataKind::Byte8,
+            ObjDataKind::String | ObjDataKind::StringTable if self.raw_strings => ObjDataKind::Byte,
+            ObjDataKind::String16 | ObjDataKind::String16Table if self.raw_strings => {
+                ObjDataKind::Byte2
+            }
+            kind => kind,
+        }
+    }
+
+    #[inline]
+    fn raw_relocations(&self) -> bool { self.raw || self.raw_pointers }
+}
+
+/// Overrides data options for matching sections or symbols.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AsmDataOverride {
+    /// Section name to match. If unset, matches all sections.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    /// Symbol name to match. If unset, applies to the whole section.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_floats: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_strings: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_pointers: Option<bool>,
+}
+
+impl AsmDataOverride {
+    fn matches(&self, section: &str, symbol: Option<&str>) -> bool {
+        self.section.as_deref().map_or(true, |s| s == section)
+            && self.symbol.as_deref().map_or(true, |s| symbol == Some(s))
+    }
+
+    fn apply_to(&self, options: &mut AsmDataOptions) {
+        if let Some(raw) = self.raw {
+            options.raw = raw;
+        }
+        if let Some(raw_floats) = self.raw_floats {
+            options.raw_floats = raw_floats;
+        }
+        if let Some(raw_strings) = self.raw_strings {
+            options.raw_strings = raw_strings;
+        }
+        if let Some(raw_pointers) = self.raw_pointers {
+            options.raw_pointers = raw_pointers;
+        }
+    }
+}
+
+pub fn write_asm<W>(w: &mut W, obj: &ObjInfo, options: &AsmOptions) -> Result<()>
 where W: Write + ?Sized {
-    writeln!(w, ".include \"macros.inc\"")?;
+    if options.flavor.is_gnu() {
+        writeln!(w, ".include \"macros.inc\"")?;
+    }
     if !obj.name.is_empty() {
         let name = obj
             .name
@@ -40,7 +279,19 @@ where W: Write + ?Sized {
             .or_else(|| obj.name.rsplit_once(' '))
             .map(|(_, b)| b)
             .unwrap_or(&obj.name);
-        writeln!(w, ".file \"{}\"", name.replace('\\', "\\\\"))?;
+        match options.flavor {
+            AsmFlavor::Gnu | AsmFlavor::Macro | AsmFlavor::CodeWarrior => {
+                writeln!(w, ".file \"{}\"", name.replace('\\', "\\\\"))?;
+            }
+            AsmFlavor::Listing => writeln!(w, "; File: {name}")?,
+        }
+        if is_gap_unit(name) {
+            writeln!(
+                w,
+                "{} Generated to cover data not claimed by any split. Assign it in splits.txt.",
+                options.flavor.comment()
+            )?;
+        }
     }
 
     // We'll append generated symbols to the end
@@ -67,7 +318,17 @@ where W: Write + ?Sized {
 
         // Generate local jump labels
         if section.kind == ObjSectionKind::Code {
+            // Data embedded in code is written as data, so skip it
+            let data_ranges = obj
+                .symbols
+                .for_section(section_idx)
+                .filter(|(_, s)| s.kind == ObjSymbolKind::Object && s.size > 0)
+                .map(|(_, s)| s.address as u32..(s.address + s.size) as u32)
+                .collect_vec();
             for ins in disasm_iter(&section.data, section.address as u32) {
+                if data_ranges.iter().any(|r| r.contains(&ins.addr)) {
+                    continue;
+                }
                 if let Some(address) = ins.branch_dest() {
                     if ins.field_AA() || !section.contains(address) {
                         continue;
@@ -92,7 +353,7 @@ where W: Write + ?Sized {
                         let display_address = address as u64 + section.original_address;
                         let symbol_idx = symbols.len();
                         symbols.push(ObjSymbol {
-                            name: format!(".L_{display_address:08X}"),
+                            name: format!(".L_{display_address:08X}").into(),
                             address: display_address,
                             section: Some(section_idx),
                             size_known: true,
@@ -151,7 +412,7 @@ where W: Write + ?Sized {
                 let display_address = address + target_section.original_address;
                 let symbol_idx = symbols.len();
                 symbols.push(ObjSymbol {
-                    name: format!(".L_{display_address:08X}"),
+                    name: format!(".L_{display_address:08X}").into(),
                     address: display_address,
                     section: Some(target_section_idx),
                     size_known: true,
@@ -162,6 +423,8 @@ where W: Write + ?Sized {
         }
     }
 
+    let section_switches = find_switches(obj, &symbols, &section_entries, &section_relocations);
+
     // Write common symbols
     let mut common_symbols = Vec::new();
     for symbol in symbols.iter().filter(|s| s.flags.is_common()) {
@@ -172,10 +435,10 @@ where W: Write + ?Sized {
         writeln!(w)?;
         for symbol in common_symbols {
             if let Some(name) = &symbol.demangled_name {
-                writeln!(w, "# {name}")?;
+                writeln!(w, "{} {name}", options.flavor.comment())?;
             }
             write!(w, ".comm ")?;
-            write_symbol_name(w, &symbol.name)?;
+            write_symbol_name(w, options, &symbol.name)?;
             writeln!(w, ", {:#X}, {}", symbol.size, symbol.align.unwrap_or(4))?;
         }
     }
@@ -194,11 +457,12 @@ where W: Write + ?Sized {
                 break;
             }
 
-            write_section_header(w, section, subsection, current_address, section_end)?;
+            write_section_header(w, options, section, subsection, current_address, section_end)?;
             match section.kind {
                 ObjSectionKind::Code | ObjSectionKind::Data | ObjSectionKind::ReadOnlyData => {
                     write_data(
                         w,
+                        options,
                         &symbols,
                         entries,
                         relocations,
@@ -206,10 +470,19 @@ where W: Write + ?Sized {
                         current_address,
                         section_end,
                         &section_entries,
+                        &section_switches[section_index],
                     )?;
                 }
                 ObjSectionKind::Bss => {
-                    write_bss(w, &symbols, entries, current_address, section_end)?;
+                    write_bss(
+                        w,
+                        options,
+                        &symbols,
+                        entries,
+                        section,
+                        current_address,
+                        section_end,
+                    )?;
                 }
             }
 
@@ -219,7 +492,7 @@ where W: Write + ?Sized {
                     if entry.kind != SymbolEntryKind::End {
                         continue;
                     }
-                    write_symbol_entry(w, &symbols, entry)?;
+                    write_symbol_entry(w, options, &symbols, entry, section, section_end)?;
                 }
             }
 
@@ -231,8 +504,145 @@ where W: Write + ?Sized {
     Ok(())
 }
 
+/// Name of the symbol or label written at an address.
+fn label_at<'a>(
+    symbols: &'a [ObjSymbol],
+    entries: &BTreeMap<u32, Vec<SymbolEntry>>,
+    address: u32,
+) -> Option<&'a str> {
+    let vec = entries.get(&address)?;
+    vec.iter()
+        .find(|e| e.kind == SymbolEntryKind::Label)
+        .or_else(|| vec.iter().find(|e| e.kind == SymbolEntryKind::Start))
+        .map(|e| symbols[e.index].name.as_str())
+}
+
+/// Finds jump tables (data objects made of pointers into a single function) and builds
+/// comments summarizing the switch statements they implement.
+fn find_switches(
+    obj: &ObjInfo,
+    symbols: &[ObjSymbol],
+    section_entries: &[BTreeMap<u32, Vec<SymbolEntry>>],
+    section_relocations: &[BTreeMap<u32, ObjReloc>],
+) -> Vec<SwitchComments> {
+    let mut switches = section_entries.iter().map(|_| SwitchComments::default()).collect_vec();
+    for (section_index, section) in obj
+        .sections
+        .iter()
+        .filter(|(_, s)| matches!(s.kind, ObjSectionKind::Data | ObjSectionKind::ReadOnlyData))
+    {
+        let relocations = &section_relocations[section_index];
+        'tables: for (table_index, table) in obj.symbols.for_section(section_index) {
+            let start = table.address as u32;
+            let end = (table.address + table.size) as u32;
+            if table.kind != ObjSymbolKind::Object || table.size < 8 || table.size % 4 != 0 {
+                continue;
+            }
+
+            // Every entry must point into the same function
+            let mut function = None;
+            let mut targets = vec![];
+            for address in (start..end).step_by(4) {
+                let Some(reloc) = relocations.get(&address) else { continue 'tables };
+                let target = &symbols[reloc.target_symbol];
+                let Some(target_section) = target.section else { continue 'tables };
+                if reloc.kind != ObjRelocKind::Absolute
+                    || target.kind != ObjSymbolKind::Function
+                    || obj.sections.get(target_section).map(|s| s.kind)
+                        != Some(ObjSectionKind::Code)
+                    || function.is_some_and(|f| f != reloc.target_symbol)
+                {
+                    continue 'tables;
+                }
+                function = Some(reloc.target_symbol);
+                targets.push((target.address as i64 + reloc.addend) as u32);
+            }
+            let Some(function) = function else { continue };
+            let function_symbol = &symbols[function];
+            let Some(code_section_index) = function_symbol.section else { continue };
+            let entries = &section_entries[code_section_index];
+            let Some(code_section) = obj.sections.get(code_section_index) else { continue };
+
+            // The table's address is loaded right before the bounds-checked branch
+            let bounds = section_relocations[code_section_index]
+                .iter()
+                .find(|(_, r)| r.target_symbol == table_index)
+                .and_then(|(&address, _)| find_switch_bounds(code_section, address));
+            let base = bounds.map_or(0, |b| b.base);
+            let default = bounds.and_then(|b| b.default);
+            let name = |address: u32| {
+                label_at(symbols, entries, address)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("{:#010X}", address))
+            };
+
+            let mut lines = vec![];
+            let last = base + targets.len() as i32 - 1;
+            let value = bounds.map_or_else(|| "?".to_string(), |b| format!("r{}", b.register));
+            lines.push(format!(
+                "switch ({value}) in {}: cases {base}..={last}",
+                function_symbol.name
+            ));
+            if let Some(default) = default {
+                lines.push(format!("  default: {}", name(default)));
+            }
+            let mut cases = BTreeMap::<u32, Vec<i32>>::new();
+            for (i, &target) in targets.iter().enumerate() {
+                let value = base + i as i32;
+                switches[section_index].cases.insert(
+                    start + i as u32 * 4,
+                    if Some(target) == default {
+                        format!("case {value} (default)")
+                    } else {
+                        format!("case {value}")
+                    },
+                );
+                if Some(target) != default {
+                    cases.nested_push(target, value);
+                }
+            }
+            for (target, values) in cases {
+                lines.push(format!("  case {}: {}", values.iter().join(", "), name(target)));
+            }
+            switches[section_index].headers.insert(start, lines);
+        }
+    }
+    switches
+}
+
+/// Locates the bounds check guarding a jump table, searching backwards from the instruction
+/// loading the table's address: `cmplwi rN, max` followed by `bgt default`, optionally preceded
+/// by `addi rN, rM, -base` for switches whose first case isn't 0.
+fn find_switch_bounds(section: &ObjSection, address: u32) -> Option<SwitchBounds> {
+    let start = address.saturating_sub(0x40).max(section.address as u32);
+    let data = section.data_range(start, address).ok()?;
+    let instructions = disasm_iter(data, start).collect_vec();
+    let cmp_index = instructions.iter().rposition(|ins| ins.op == Opcode::Cmpli)?;
+    let cmp = &instructions[cmp_index];
+    let crf = cmp.field_crfD() as u8;
+    let default = instructions[cmp_index + 1..]
+        .iter()
+        .find(|ins| {
+            ins.op == Opcode::Bc && ins.field_BO() == 12 && ins.field_BI() as u8 == crf * 4 + 1
+        })
+        .and_then(|ins| ins.branch_dest());
+    // The subtraction's source register holds the original switch value
+    let (register, base) = cmp_index
+        .checked_sub(1)
+        .map(|i| &instructions[i])
+        .filter(|ins| {
+            ins.op == Opcode::Addi && ins.field_rA() != 0 && ins.field_rD() == cmp.field_rA()
+        })
+        .map_or((cmp.field_rA() as u8, 0), |ins| {
+            (ins.field_rA() as u8, -(ins.field_simm() as i32))
+        });
+    Some(SwitchBounds { register, base, default })
+}
+
+#[allow(clippy::too_many_arguments)]
 fn write_code_chunk<W>(
     w: &mut W,
+    options: &AsmOptions,
     symbols: &[ObjSymbol],
     _entries: &BTreeMap<u32, Vec<SymbolEntry>>,
     relocations: &BTreeMap<u32, ObjReloc>,
@@ -246,55 +656,88 @@ where
     for ins in disasm_iter(data, address) {
         let reloc = relocations.get(&ins.addr);
         let file_offset = section.file_offset + (ins.addr as u64 - section.address);
-        write_ins(w, symbols, ins, reloc, file_offset, section.original_address)?;
+        write_ins(w, options, symbols, ins, reloc, file_offset, section)?;
     }
     Ok(())
 }
 
 fn write_ins<W>(
     w: &mut W,
+    options: &AsmOptions,
     symbols: &[ObjSymbol],
     mut ins: Ins,
     reloc: Option<&ObjReloc>,
     file_offset: u64,
-    section_address: u64,
+    section: &ObjSection,
 ) -> Result<()>
 where
     W: Write + ?Sized,
 {
-    write!(
-        w,
-        "/* {:08X} {:08X}  {:02X} {:02X} {:02X} {:02X} */\t",
-        ins.addr as u64 + section_address,
-        file_offset,
-        (ins.code >> 24) & 0xFF,
-        (ins.code >> 16) & 0xFF,
-        (ins.code >> 8) & 0xFF,
-        ins.code & 0xFF
-    )?;
+    let [b0, b1, b2, b3] = ins.code.to_be_bytes();
+    match options.flavor {
+        AsmFlavor::Gnu | AsmFlavor::Macro => write!(
+            w,
+            "/* {:08X} {:08X}  {:02X} {:02X} {:02X} {:02X} */\t",
+            ins.addr as u64 + section.original_address,
+            file_offset,
+            b0,
+            b1,
+            b2,
+            b3
+        )?,
+        AsmFlavor::CodeWarrior => write!(w, "\t")?,
+        AsmFlavor::Listing => {
+            write_line_start(w, options, section, ins.addr)?;
+            write!(w, "{:02X} {:02X} {:02X} {:02X}\t", b0, b1, b2, b3)?;
+        }
+    }
 
     if let Some(reloc) = reloc {
         // Zero out relocations
         ins.code = match reloc.kind {
-            ObjRelocKind::Absolute => 0,
+            ObjRelocKind::Absolute
+            | ObjRelocKind::PpcRel32
+            | ObjRelocKind::PpcDtpMod32
+            | ObjRelocKind::PpcDtpRel32 => 0,
             ObjRelocKind::PpcEmbSda21 => ins.code & !0x1FFFFF,
             ObjRelocKind::PpcRel24 => ins.code & !0x3FFFFFC,
             ObjRelocKind::PpcRel14 => ins.code & !0xFFFC,
-            ObjRelocKind::PpcAddr16Hi | ObjRelocKind::PpcAddr16Ha | ObjRelocKind::PpcAddr16Lo => {
-                ins.code & !0xFFFF
-            }
+            ObjRelocKind::PpcAddr16Hi
+            | ObjRelocKind::PpcAddr16Ha
+            | ObjRelocKind::PpcAddr16Lo
+            | ObjRelocKind::PpcRel16Hi
+            | ObjRelocKind::PpcRel16Ha
+            | ObjRelocKind::PpcRel16Lo => ins.code & !0xFFFF,
         };
     }
 
     if ins.op == Opcode::Illegal {
-        write!(w, ".4byte {:#010X} /* invalid */", ins.code)?;
+        write!(w, "{} {:#010X}", options.flavor.data_directive(4), ins.code)?;
+        write_trailing_comment(w, options, "invalid")?;
     } else if is_illegal_instruction(ins.code) {
         let sins = ins.simplified();
-        write!(w, ".4byte {:#010X} /* illegal: {} */", sins.ins.code, sins)?;
-    } else {
+        write!(w, "{} {:#010X}", options.flavor.data_directive(4), sins.ins.code)?;
+        write_trailing_comment(w, options, &format!("illegal: {}", sins))?;
+    } else if let Some(valid) = check_gekko(ins.code)
+        .filter(|&valid| !valid || (options.gekko == GekkoMode::Raw && reloc.is_none()))
+    {
+        // Assemblers would encode reserved bits as zero, so write the original word
+        ensure!(
+            valid || !options.strict_gekko,
+            "Invalid Gekko instruction {:#010X} at {:#010X}",
+            ins.code,
+            ins.addr
+        );
         let sins = ins.simplified();
+        write!(w, "{} {:#010X}", options.flavor.data_directive(4), sins.ins.code)?;
+        let comment = if valid { sins.to_string() } else { format!("invalid: {}", sins) };
+        write_trailing_comment(w, options, &comment)?;
+    } else {
+        let sins =
+            if options.raw_mnemonics { SimplifiedIns::basic_form(ins) } else { ins.simplified() };
         write!(w, "{}{}", sins.mnemonic, sins.ins.suffix())?;
 
+        let gqr = psq_gqr(sins.ins.code).filter(|_| options.gqr_names);
         let mut writing_offset = false;
         for (i, arg) in sins.args.iter().enumerate() {
             if !writing_offset {
@@ -304,17 +747,22 @@ where
                     write!(w, ", ")?;
                 }
             }
+            if let Some(gqr) = gqr.filter(|_| i == sins.args.len() - 1) {
+                let prefix = if options.register_prefix { "%" } else { "" };
+                write!(w, "{prefix}qr{gqr}")?;
+                continue;
+            }
             match arg {
                 Argument::Uimm(_) | Argument::Simm(_) | Argument::BranchDest(_) => {
                     if let Some(reloc) = reloc {
-                        write_reloc(w, symbols, reloc)?;
+                        write_reloc(w, options, symbols, reloc)?;
                     } else {
                         write!(w, "{arg}")?;
                     }
                 }
                 Argument::Offset(_) => {
                     if let Some(reloc) = reloc {
-                        write_reloc(w, symbols, reloc)?;
+                        write_reloc(w, options, symbols, reloc)?;
                     } else {
                         write!(w, "{arg}")?;
                     }
@@ -322,6 +770,11 @@ where
                     writing_offset = true;
                     continue;
                 }
+                Argument::GPR(_) | Argument::FPR(_) | Argument::CRField(_)
+                    if options.register_prefix =>
+                {
+                    write!(w, "%{arg}")?;
+                }
                 _ => {
                     write!(w, "{arg}")?;
                 }
@@ -336,31 +789,96 @@ where
     Ok(())
 }
 
-fn write_reloc<W>(w: &mut W, symbols: &[ObjSymbol], reloc: &ObjReloc) -> Result<()>
+fn write_trailing_comment<W>(w: &mut W, options: &AsmOptions, comment: &str) -> Result<()>
 where W: Write + ?Sized {
-    write_reloc_symbol(w, symbols, reloc)?;
+    match options.flavor {
+        AsmFlavor::Gnu | AsmFlavor::Macro => write!(w, " /* {comment} */")?,
+        AsmFlavor::CodeWarrior | AsmFlavor::Listing => {
+            write!(w, " {} {comment}", options.flavor.comment())?
+        }
+    }
+    Ok(())
+}
+
+/// Writes the address prefix of a line in listing output.
+fn write_line_start<W>(
+    w: &mut W,
+    options: &AsmOptions,
+    section: &ObjSection,
+    address: u32,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    if options.flavor == AsmFlavor::Listing {
+        write!(w, "{}:{:08X} ", section.name, address as u64 + section.original_address)?;
+    }
+    Ok(())
+}
+
+fn write_reloc<W>(
+    w: &mut W,
+    options: &AsmOptions,
+    symbols: &[ObjSymbol],
+    reloc: &ObjReloc,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    if matches!(
+        reloc.kind,
+        ObjRelocKind::PpcRel32
+            | ObjRelocKind::PpcRel16Hi
+            | ObjRelocKind::PpcRel16Ha
+            | ObjRelocKind::PpcRel16Lo
+    ) {
+        // Relative to the instruction or data word
+        write!(w, "(")?;
+        write_reloc_symbol(w, options, symbols, reloc)?;
+        write!(w, " - .)")?;
+    } else {
+        write_reloc_symbol(w, options, symbols, reloc)?;
+    }
     match reloc.kind {
-        ObjRelocKind::Absolute | ObjRelocKind::PpcRel24 | ObjRelocKind::PpcRel14 => {
+        ObjRelocKind::Absolute
+        | ObjRelocKind::PpcRel24
+        | ObjRelocKind::PpcRel14
+        | ObjRelocKind::PpcRel32 => {
             // pass
         }
-        ObjRelocKind::PpcAddr16Hi => {
+        ObjRelocKind::PpcAddr16Hi | ObjRelocKind::PpcRel16Hi => {
             write!(w, "@h")?;
         }
-        ObjRelocKind::PpcAddr16Ha => {
+        ObjRelocKind::PpcAddr16Ha | ObjRelocKind::PpcRel16Ha => {
             write!(w, "@ha")?;
         }
-        ObjRelocKind::PpcAddr16Lo => {
+        ObjRelocKind::PpcAddr16Lo | ObjRelocKind::PpcRel16Lo => {
             write!(w, "@l")?;
         }
         ObjRelocKind::PpcEmbSda21 => {
             write!(w, "@sda21")?;
         }
+        ObjRelocKind::PpcDtpMod32 => {
+            write!(w, "@dtpmod")?;
+        }
+        ObjRelocKind::PpcDtpRel32 => {
+            write!(w, "@dtprel")?;
+        }
     }
     Ok(())
 }
 
-fn write_symbol_entry<W>(w: &mut W, symbols: &[ObjSymbol], entry: &SymbolEntry) -> Result<()>
-where W: Write + ?Sized {
+fn write_symbol_entry<W>(
+    w: &mut W,
+    options: &AsmOptions,
+    symbols: &[ObjSymbol],
+    entry: &SymbolEntry,
+    section: &ObjSection,
+    address: u32,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
     let symbol = &symbols[entry.index];
 
     // Skip writing certain symbols
@@ -383,14 +901,27 @@ where W: Write + ?Sized {
         "global"
     };
 
+    match options.flavor {
+        AsmFlavor::Gnu => {}
+        AsmFlavor::Macro => {
+            return write_symbol_entry_macro(w, options, symbol, entry, section, address, scope)
+        }
+        AsmFlavor::CodeWarrior => {
+            return write_symbol_entry_mw(w, options, symbol, entry, section, address, scope)
+        }
+        AsmFlavor::Listing => {
+            return write_symbol_entry_listing(w, options, symbol, entry, section, address)
+        }
+    }
+
     match entry.kind {
         SymbolEntryKind::Label => {
             if symbol.name.starts_with(".L") {
-                write_symbol_name(w, &symbol.name)?;
+                write_symbol_name(w, options, &symbol.name)?;
                 writeln!(w, ":")?;
             } else {
                 write!(w, ".sym ")?;
-                write_symbol_name(w, &symbol.name)?;
+                write_symbol_name(w, options, &symbol.name)?;
                 writeln!(w, ", {scope}")?;
             }
         }
@@ -398,16 +929,15 @@ where W: Write + ?Sized {
             if symbol.kind != ObjSymbolKind::Unknown {
                 writeln!(w)?;
             }
-            if let Some(name) = &symbol.demangled_name {
-                writeln!(w, "# {name}")?;
-            }
+            write_symbol_align(w, options, symbol, address)?;
+            write_symbol_comments(w, options, symbol, section, address)?;
             write!(w, ".{symbol_kind} ")?;
-            write_symbol_name(w, &symbol.name)?;
+            write_symbol_name(w, options, &symbol.name)?;
             writeln!(w, ", {scope}")?;
         }
         SymbolEntryKind::End => {
             write!(w, ".end{symbol_kind} ")?;
-            write_symbol_name(w, &symbol.name)?;
+            write_symbol_name(w, options, &symbol.name)?;
             writeln!(w)?;
         }
     }
@@ -416,15 +946,218 @@ where W: Write + ?Sized {
         && symbol.flags.is_hidden()
     {
         write!(w, ".hidden ")?;
-        write_symbol_name(w, &symbol.name)?;
+        write_symbol_name(w, options, &symbol.name)?;
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+/// Global functions and objects are written using the one-argument `glabel` and `dlabel`
+/// macros. Everything else uses built-in directives, so no other macros are needed.
+fn write_symbol_entry_macro<W>(
+    w: &mut W,
+    options: &AsmOptions,
+    symbol: &ObjSymbol,
+    entry: &SymbolEntry,
+    section: &ObjSection,
+    address: u32,
+    scope: &str,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    let (label_macro, symbol_type) = match symbol.kind {
+        ObjSymbolKind::Function => ("glabel", Some("@function")),
+        ObjSymbolKind::Object => ("dlabel", Some("@object")),
+        ObjSymbolKind::Unknown => ("", None),
+        ObjSymbolKind::Section => bail!("Attempted to write section symbol: {symbol:?}"),
+    };
+    match entry.kind {
+        SymbolEntryKind::Label => {
+            if symbol.name.starts_with(".L") {
+                write_symbol_name(w, options, &symbol.name)?;
+                writeln!(w, ":")?;
+                return Ok(());
+            }
+            write_symbol_scope(w, options, symbol, scope)?;
+            write_symbol_name(w, options, &symbol.name)?;
+            writeln!(w, ":")?;
+        }
+        SymbolEntryKind::Start => {
+            if symbol.kind != ObjSymbolKind::Unknown {
+                writeln!(w)?;
+            }
+            write_symbol_align(w, options, symbol, address)?;
+            write_symbol_comments(w, options, symbol, section, address)?;
+            if scope == "global" && !label_macro.is_empty() {
+                write!(w, "{label_macro} ")?;
+                write_symbol_name(w, options, &symbol.name)?;
+                writeln!(w)?;
+            } else {
+                write_symbol_scope(w, options, symbol, scope)?;
+                if let Some(symbol_type) = symbol_type {
+                    write!(w, ".type ")?;
+                    write_symbol_name(w, options, &symbol.name)?;
+                    writeln!(w, ", {symbol_type}")?;
+                }
+                write_symbol_name(w, options, &symbol.name)?;
+                writeln!(w, ":")?;
+            }
+        }
+        SymbolEntryKind::End => {
+            // Not every macros.inc has end macros
+            write!(w, ".size ")?;
+            write_symbol_name(w, options, &symbol.name)?;
+            write!(w, ", . - ")?;
+            write_symbol_name(w, options, &symbol.name)?;
+            writeln!(w)?;
+            return Ok(());
+        }
+    }
+    if symbol.flags.is_hidden() {
+        write!(w, ".hidden ")?;
+        write_symbol_name(w, options, &symbol.name)?;
         writeln!(w)?;
     }
     Ok(())
 }
 
+/// Writes the `.global` or `.weak` directive for a symbol. Labels are local by default.
+fn write_symbol_scope<W>(
+    w: &mut W,
+    options: &AsmOptions,
+    symbol: &ObjSymbol,
+    scope: &str,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    if scope != "local" {
+        write!(w, ".{scope} ")?;
+        write_symbol_name(w, options, &symbol.name)?;
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+/// CodeWarrior has no sized symbol macros, so symbols are plain labels.
+fn write_symbol_entry_mw<W>(
+    w: &mut W,
+    options: &AsmOptions,
+    symbol: &ObjSymbol,
+    entry: &SymbolEntry,
+    section: &ObjSection,
+    address: u32,
+    scope: &str,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    match entry.kind {
+        SymbolEntryKind::Label | SymbolEntryKind::Start => {
+            if entry.kind == SymbolEntryKind::Start && symbol.kind != ObjSymbolKind::Unknown {
+                writeln!(w)?;
+            }
+            if entry.kind == SymbolEntryKind::Start {
+                write_symbol_align(w, options, symbol, address)?;
+            }
+            write_symbol_comments(w, options, symbol, section, address)?;
+            if !symbol.name.starts_with(".L") && scope != "local" {
+                write!(w, ".{scope} ")?;
+                write_symbol_name(w, options, &symbol.name)?;
+                writeln!(w)?;
+            }
+            write_symbol_name(w, options, &symbol.name)?;
+            writeln!(w, ":")?;
+        }
+        SymbolEntryKind::End => {}
+    }
+    Ok(())
+}
+
+/// Writes the alignment directive for a data symbol with a known alignment above 4.
+/// Only written when the address is already aligned, so it never changes the layout.
+fn write_symbol_align<W>(
+    w: &mut W,
+    options: &AsmOptions,
+    symbol: &ObjSymbol,
+    address: u32,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    let Some(align) = symbol.align else {
+        return Ok(());
+    };
+    if symbol.kind == ObjSymbolKind::Function || align <= 4 || address % align != 0 {
+        return Ok(());
+    }
+    if options.flavor.is_gnu() {
+        writeln!(w, ".balign {align}")?;
+    } else {
+        writeln!(w, ".align {align}")?;
+    }
+    Ok(())
+}
+
+/// Writes the comment lines preceding a symbol: its demangled name and, if enabled, its source.
+fn write_symbol_comments<W>(
+    w: &mut W,
+    options: &AsmOptions,
+    symbol: &ObjSymbol,
+    section: &ObjSection,
+    address: u32,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    let comment = options.flavor.comment();
+    if let Some(name) = &symbol.demangled_name {
+        write_line_start(w, options, section, address)?;
+        writeln!(w, "{comment} {name}")?;
+    }
+    if options.provenance && !symbol.name.starts_with(".L") {
+        write_line_start(w, options, section, address)?;
+        writeln!(w, "{comment} source: {}", symbol.source.as_str())?;
+    }
+    Ok(())
+}
+
+fn write_symbol_entry_listing<W>(
+    w: &mut W,
+    options: &AsmOptions,
+    symbol: &ObjSymbol,
+    entry: &SymbolEntry,
+    section: &ObjSection,
+    address: u32,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    match entry.kind {
+        SymbolEntryKind::Label | SymbolEntryKind::Start => {
+            if entry.kind == SymbolEntryKind::Start && symbol.kind != ObjSymbolKind::Unknown {
+                writeln!(w)?;
+            }
+            write_symbol_comments(w, options, symbol, section, address)?;
+            write_line_start(w, options, section, address)?;
+            write_symbol_name(w, options, &symbol.name)?;
+            writeln!(w, ":")?;
+        }
+        SymbolEntryKind::End => {
+            if symbol.kind == ObjSymbolKind::Function {
+                write_line_start(w, options, section, address)?;
+                writeln!(w, "; End of function {}", symbol.name)?;
+            }
+        }
+    }
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 fn write_data<W>(
     w: &mut W,
+    options: &AsmOptions,
     symbols: &[ObjSymbol],
     entries: &BTreeMap<u32, Vec<SymbolEntry>>,
     relocations: &BTreeMap<u32, ObjReloc>,
@@ -432,6 +1165,7 @@ fn write_data<W>(
     start: u32,
     end: u32,
     section_entries: &[BTreeMap<u32, Vec<SymbolEntry>>],
+    switches: &SwitchComments,
 ) -> Result<()>
 where
     W: Write + ?Sized,
@@ -442,6 +1176,7 @@ where
     let mut current_address = start;
     let mut current_symbol_kind = ObjSymbolKind::Unknown;
     let mut current_data_kind = ObjDataKind::Unknown;
+    let mut data_options = options.data_options(&section.name, None);
     let mut entry = entry_iter.next();
     let mut reloc = reloc_iter.next();
     let mut begin = true;
@@ -456,11 +1191,22 @@ where
                     if entry.kind == SymbolEntryKind::End && begin {
                         continue;
                     }
-                    write_symbol_entry(w, symbols, entry)?;
+                    write_symbol_entry(w, options, symbols, entry, section, sym_addr)?;
+                }
+                for line in switches.headers.get(&sym_addr).into_iter().flatten() {
+                    writeln!(w, "{} {line}", options.flavor.comment())?;
                 }
                 current_symbol_kind = find_symbol_kind(current_symbol_kind, symbols, vec)?;
                 current_data_kind = find_data_kind(current_data_kind, symbols, vec)
                     .with_context(|| format!("At address {:#010X}", sym_addr))?;
+                if let Some(symbol) = vec
+                    .iter()
+                    .filter(|e| e.kind == SymbolEntryKind::Start)
+                    .map(|e| &symbols[e.index])
+                    .find(|s| s.kind != ObjSymbolKind::Section)
+                {
+                    data_options = options.data_options(&section.name, Some(&symbol.name));
+                }
                 entry = entry_iter.next();
             } else if current_address > sym_addr {
                 let dbg_symbols = vec.iter().map(|e| &symbols[e.index]).collect_vec();
@@ -487,9 +1233,20 @@ where
             if current_address == reloc_addr {
                 reloc = reloc_iter.next();
                 match symbol_kind {
+                    ObjSymbolKind::Object if data_options.raw_relocations() => {
+                        // written as raw data below
+                    }
                     ObjSymbolKind::Object => {
-                        current_address =
-                            write_data_reloc(w, symbols, entries, reloc_addr, r, section_entries)?;
+                        current_address = write_data_reloc(
+                            w,
+                            options,
+                            symbols,
+                            section,
+                            reloc_addr,
+                            r,
+                            section_entries,
+                            switches.cases.get(&reloc_addr).map(String::as_str),
+                        )?;
                         continue;
                     }
                     ObjSymbolKind::Function => {
@@ -525,9 +1282,19 @@ where
                 entry,
                 reloc,
             );
-            write_code_chunk(w, symbols, entries, relocations, section, current_address, data)?;
+            write_code_chunk(
+                w,
+                options,
+                symbols,
+                entries,
+                relocations,
+                section,
+                current_address,
+                data,
+            )?;
         } else {
-            write_data_chunk(w, data, current_data_kind)?;
+            let data_kind = data_options.data_kind(current_data_kind);
+            write_data_chunk(w, options, section, current_address, data, data_kind)?;
         }
         current_address = until;
     }
@@ -598,13 +1365,24 @@ fn find_data_kind(
     Ok(kind)
 }
 
-fn write_string<W>(w: &mut W, data: &[u8]) -> Result<()>
-where W: Write + ?Sized {
+fn write_string<W>(
+    w: &mut W,
+    options: &AsmOptions,
+    section: &ObjSection,
+    address: u32,
+    data: &[u8],
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    write_line_start(w, options, section, address)?;
     let terminated = matches!(data.last(), Some(&b) if b == 0);
-    if terminated {
+    if !terminated {
+        write!(w, "\t.ascii \"")?;
+    } else if options.flavor.is_gnu() {
         write!(w, "\t.string \"")?;
     } else {
-        write!(w, "\t.ascii \"")?;
+        write!(w, "\t.asciz \"")?;
     }
     for &b in &data[..data.len() - if terminated { 1 } else { 0 }] {
         match b as char {
@@ -653,12 +1431,28 @@ where W: Write + ?Sized {
     Ok(())
 }
 
-fn write_data_chunk<W>(w: &mut W, data: &[u8], data_kind: ObjDataKind) -> Result<()>
-where W: Write + ?Sized {
+fn write_data_chunk<W>(
+    w: &mut W,
+    options: &AsmOptions,
+    section: &ObjSection,
+    address: u32,
+    data: &[u8],
+    data_kind: ObjDataKind,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
     let remain = data;
+    let data_kind = match data_kind {
+        // Only GNU assembler supports UTF-16 strings
+        ObjDataKind::String16 | ObjDataKind::String16Table if !options.flavor.is_gnu() => {
+            ObjDataKind::Byte2
+        }
+        kind => kind,
+    };
     match data_kind {
         ObjDataKind::String => {
-            return write_string(w, data);
+            return write_string(w, options, section, address, data);
         }
         ObjDataKind::String16 => {
             if data.len() % 2 != 0 {
@@ -671,8 +1465,10 @@ where W: Write + ?Sized {
             return write_string16(w, &data);
         }
         ObjDataKind::StringTable => {
+            let mut address = address;
             for slice in data.split_inclusive(|&b| b == 0) {
-                write_string(w, slice)?;
+                write_string(w, options, section, address, slice)?;
+                address += slice.len() as u32;
             }
             return Ok(());
         }
@@ -700,7 +1496,12 @@ where W: Write + ?Sized {
         | ObjDataKind::StringTable
         | ObjDataKind::String16Table => unreachable!(),
     };
+    let flavor = options.flavor;
+    let comment = flavor.comment();
+    let mut address = address;
     for chunk in remain.chunks(chunk_size) {
+        write_line_start(w, options, section, address)?;
+        address += chunk.len() as u32;
         if data_kind == ObjDataKind::Byte || matches!(chunk.len(), 1 | 3 | 5..=7) {
             let bytes = chunk.iter().map(|c| format!("{:#04X}", c)).collect::<Vec<String>>();
             writeln!(w, "\t.byte {}", bytes.join(", "))?;
@@ -709,31 +1510,36 @@ where W: Write + ?Sized {
                 8 if data_kind == ObjDataKind::Double => {
                     let data = f64::from_be_bytes(chunk.try_into().unwrap());
                     if data.is_nan() {
-                        let int_data = u64::from_be_bytes(chunk.try_into().unwrap());
-                        writeln!(w, "\t.8byte {int_data:#018X} # {data}")?;
+                        write_8byte(w, flavor, u64::from_be_bytes(chunk.try_into().unwrap()))?;
+                        writeln!(w, " {comment} {data}")?;
                     } else {
                         writeln!(w, "\t.double {data}")?;
                     }
                 }
                 8 => {
-                    let data = u64::from_be_bytes(chunk.try_into().unwrap());
-                    writeln!(w, "\t.8byte {data:#018X}")?;
+                    write_8byte(w, flavor, u64::from_be_bytes(chunk.try_into().unwrap()))?;
+                    writeln!(w)?;
                 }
                 4 if data_kind == ObjDataKind::Float => {
                     let data = f32::from_be_bytes(chunk.try_into().unwrap());
                     if data.is_nan() {
                         let int_data = u32::from_be_bytes(chunk.try_into().unwrap());
-                        writeln!(w, "\t.4byte {int_data:#010X} # {data}")?;
+                        writeln!(
+                            w,
+                            "\t{} {int_data:#010X} {comment} {data}",
+                            flavor.data_directive(4)
+                        )?;
                     } else {
                         writeln!(w, "\t.float {data}")?;
                     }
                 }
                 4 => {
                     let data = u32::from_be_bytes(chunk.try_into().unwrap());
-                    writeln!(w, "\t.4byte {data:#010X}")?;
+                    writeln!(w, "\t{} {data:#010X}", flavor.data_directive(4))?;
                 }
                 2 => {
-                    writeln!(w, "\t.2byte {:#06X}", u16::from_be_bytes(chunk.try_into().unwrap()))?;
+                    let data = u16::from_be_bytes(chunk.try_into().unwrap());
+                    writeln!(w, "\t{} {data:#06X}", flavor.data_directive(2))?;
                 }
                 _ => unreachable!(),
             }
@@ -742,21 +1548,38 @@ where W: Write + ?Sized {
     Ok(())
 }
 
+/// CodeWarrior has no 8-byte data directive, so the value is split into two words.
+fn write_8byte<W>(w: &mut W, flavor: AsmFlavor, data: u64) -> Result<()>
+where W: Write + ?Sized {
+    match flavor {
+        AsmFlavor::Gnu | AsmFlavor::Macro => write!(w, "\t.8byte {data:#018X}")?,
+        AsmFlavor::CodeWarrior | AsmFlavor::Listing => {
+            write!(w, "\t.long {:#010X}, {:#010X}", (data >> 32) as u32, data as u32)?
+        }
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 fn write_data_reloc<W>(
     w: &mut W,
+    options: &AsmOptions,
     symbols: &[ObjSymbol],
-    _entries: &BTreeMap<u32, Vec<SymbolEntry>>,
+    section: &ObjSection,
     reloc_address: u32,
     reloc: &ObjReloc,
     section_entries: &[BTreeMap<u32, Vec<SymbolEntry>>],
+    case: Option<&str>,
 ) -> Result<u32>
 where
     W: Write + ?Sized,
 {
+    let case = case.map(|c| format!(" {} {c}", options.flavor.comment())).unwrap_or_default();
     match reloc.kind {
         ObjRelocKind::Absolute => {
-            // Attempt to use .rel macro for relative relocations
-            if reloc.addend != 0 {
+            write_line_start(w, options, section, reloc_address)?;
+            // Attempt to use .rel macro for relative relocations. Only dtk's macros.inc has it.
+            if reloc.addend != 0 && options.flavor == AsmFlavor::Gnu {
                 let target = &symbols[reloc.target_symbol];
                 let target_addr = (target.address as i64 + reloc.addend) as u32;
                 if let Some(entry) = target
@@ -766,16 +1589,16 @@ where
                 {
                     let symbol = &symbols[entry.index];
                     write!(w, "\t.rel ")?;
-                    write_symbol_name(w, &target.name)?;
+                    write_symbol_name(w, options, &target.name)?;
                     write!(w, ", ")?;
-                    write_symbol_name(w, &symbol.name)?;
-                    writeln!(w)?;
+                    write_symbol_name(w, options, &symbol.name)?;
+                    writeln!(w, "{case}")?;
                     return Ok(reloc_address + 4);
                 }
             }
-            write!(w, "\t.4byte ")?;
-            write_reloc_symbol(w, symbols, reloc)?;
-            writeln!(w)?;
+            write!(w, "\t{} ", options.flavor.data_directive(4))?;
+            write_reloc_symbol(w, options, symbols, reloc)?;
+            writeln!(w, "{case}")?;
             Ok(reloc_address + 4)
         }
         _ => Err(anyhow!(
@@ -788,8 +1611,10 @@ where
 
 fn write_bss<W>(
     w: &mut W,
+    options: &AsmOptions,
     symbols: &[ObjSymbol],
     entries: &BTreeMap<u32, Vec<SymbolEntry>>,
+    section: &ObjSection,
     start: u32,
     end: u32,
 ) -> Result<()>
@@ -811,7 +1636,7 @@ where
                     if entry.kind == SymbolEntryKind::End && begin {
                         continue;
                     }
-                    write_symbol_entry(w, symbols, entry)?;
+                    write_symbol_entry(w, options, symbols, entry, section, *sym_addr)?;
                 }
                 entry = entry_iter.next();
             }
@@ -821,7 +1646,11 @@ where
         let until = entry.map(|(addr, _)| *addr).unwrap_or(end);
         let size = until - current_address;
         if size > 0 {
-            writeln!(w, "\t.skip {size:#X}")?;
+            write_line_start(w, options, section, current_address)?;
+            match options.flavor {
+                AsmFlavor::Gnu | AsmFlavor::Macro => writeln!(w, "\t.skip {size:#X}")?,
+                AsmFlavor::CodeWarrior | AsmFlavor::Listing => writeln!(w, "\t.space {size:#X}")?,
+            }
         }
         current_address = until;
     }
@@ -830,6 +1659,7 @@ where
 
 fn write_section_header<W>(
     w: &mut W,
+    options: &AsmOptions,
     section: &ObjSection,
     subsection: usize,
     start: u32,
@@ -838,12 +1668,24 @@ fn write_section_header<W>(
 where
     W: Write + ?Sized,
 {
+    if options.flavor == AsmFlavor::Listing {
+        writeln!(
+            w,
+            "\n; Segment {}\n; Range: {:#010X} - {:#010X}",
+            section.name,
+            start as u64 + section.original_address,
+            end as u64 + section.original_address
+        )?;
+        return Ok(());
+    }
     writeln!(
         w,
         "\n# {:#010X} - {:#010X}",
         start as u64 + section.original_address,
         end as u64 + section.original_address
     )?;
+    // No support for section types or unique sections in CodeWarrior
+    let gnu = options.flavor.is_gnu();
     match section.name.as_str() {
         ".text" if subsection == 0 => {
             write!(w, "{}", section.name)?;
@@ -866,11 +1708,17 @@ where
         }
         ".bss" | ".sbss" => {
             write!(w, ".section {}", section.name)?;
-            write!(w, ", \"wa\", @nobits")?;
+            write!(w, ", \"wa\"")?;
+            if gnu {
+                write!(w, ", @nobits")?;
+            }
         }
         ".sbss2" => {
             write!(w, ".section {}", section.name)?;
-            write!(w, ", \"a\", @nobits")?;
+            write!(w, ", \"a\"")?;
+            if gnu {
+                write!(w, ", @nobits")?;
+            }
         }
         ".ctors" | ".dtors" | ".ctors$10" | ".dtors$10" | ".dtors$15" | "extab" | "extabindex"
         | ".BINARY" => {
@@ -884,30 +1732,35 @@ where
         name => {
             log::warn!("Unknown section {name}");
             write!(w, ".section {}", section.name)?;
-            if section.kind == ObjSectionKind::Bss {
+            if section.kind == ObjSectionKind::Bss && gnu {
                 write!(w, ", \"\", @nobits")?;
             }
         }
     };
-    if subsection != 0 {
+    if subsection != 0 && gnu {
         write!(w, ", unique, {subsection}")?;
     }
     writeln!(w)?;
     if section.align != 0 {
-        writeln!(w, ".balign {}", section.align)?;
+        if gnu {
+            writeln!(w, ".balign {}", section.align)?;
+        } else {
+            writeln!(w, ".align {}", section.align)?;
+        }
     }
     Ok(())
 }
 
 fn write_reloc_symbol<W>(
     w: &mut W,
+    options: &AsmOptions,
     symbols: &[ObjSymbol],
     reloc: &ObjReloc,
 ) -> std::io::Result<()>
 where
     W: Write + ?Sized,
 {
-    write_symbol_name(w, &symbols[reloc.target_symbol].name)?;
+    write_symbol_name(w, options, &symbols[reloc.target_symbol].name)?;
     match reloc.addend.cmp(&0i64) {
         Ordering::Greater => write!(w, "+{:#X}", reloc.addend),
         Ordering::Less => write!(w, "-{:#X}", -reloc.addend),
@@ -915,14 +1768,9 @@ where
     }
 }
 
-fn write_symbol_name<W>(w: &mut W, name: &str) -> std::io::Result<()>
+fn write_symbol_name<W>(w: &mut W, options: &AsmOptions, name: &str) -> std::io::Result<()>
 where W: Write + ?Sized {
-    if name.contains('@')
-        || name.contains('<')
-        || name.contains('\\')
-        || name.contains('-')
-        || name.contains('+')
-    {
+    if needs_quotes(options, name) {
         write!(w, "\"{name}\"")?;
     } else {
         write!(w, "{name}")?;
@@ -930,7 +1778,221 @@ where W: Write + ?Sized {
     Ok(())
 }
 
+/// Whether a symbol name is quoted in the assembly output.
+pub fn needs_quotes(options: &AsmOptions, name: &str) -> bool {
+    options.flavor != AsmFlavor::Listing
+        && (name.contains('@')
+            || name.contains('<')
+            || name.contains('\\')
+            || name.contains('-')
+            || name.contains('+'))
+}
+
 #[inline]
 fn is_illegal_instruction(code: u32) -> bool {
     matches!(code, 0x43000000 /* bc 24, lt, 0x0 */ | 0xB8030000 /* lmw r0, 0(r3) */)
 }
+
+/// Checks a Gekko-specific instruction (paired singles and `dcbz_l`) for reserved bits.
+/// Returns `None` for other instructions, otherwise whether the encoding is valid.
+fn check_gekko(code: u32) -> Option<bool> {
+    const FR_D: u32 = 0x1F << 21;
+    const FR_A: u32 = 0x1F << 16;
+    const FR_B: u32 = 0x1F << 11;
+    const FR_C: u32 = 0x1F << 6;
+    const CR_RESERVED: u32 = 0x3 << 21;
+    const RC: u32 = 1;
+    let reserved = match code >> 26 {
+        // psq_l, psq_st
+        56 | 60 => 0,
+        // psq_lu, psq_stu: rA can't be r0
+        57 | 61 => return Some(code & FR_A != 0),
+        4 => match ((code >> 1) & 0x1F, (code >> 1) & 0x3F, (code >> 1) & 0x3FF) {
+            // ps_res, ps_rsqrte
+            (24 | 26, _, _) => FR_A | FR_C,
+            // ps_mul, ps_muls0, ps_muls1
+            (25 | 12 | 13, _, _) => FR_B,
+            // ps_sum*, ps_madds*, ps_div, ps_sub, ps_add, ps_sel, ps_*madd, ps_*msub
+            (10 | 11 | 14 | 15 | 18 | 20 | 21 | 23 | 28..=31, _, _) => 0,
+            // psq_lx, psq_stx, psq_lux, psq_stux
+            (_, 6 | 7 | 38 | 39, _) => RC,
+            // ps_cmpu0, ps_cmpo0, ps_cmpu1, ps_cmpo1
+            (_, _, 0 | 32 | 64 | 96) => CR_RESERVED | RC,
+            // ps_neg, ps_mr, ps_nabs, ps_abs
+            (_, _, 40 | 72 | 136 | 264) => FR_A,
+            // ps_merge*
+            (_, _, 528 | 560 | 592 | 624) => 0,
+            // dcbz_l
+            (_, _, 1014) => FR_D | RC,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(code & reserved == 0)
+}
+
+/// The GQR index of a `psq_*` instruction.
+fn psq_gqr(code: u32) -> Option<u32> {
+    match code >> 26 {
+        56 | 57 | 60 | 61 => Some((code >> 12) & 7),
+        4 if matches!((code >> 1) & 0x3F, 6 | 7 | 38 | 39) => Some((code >> 7) & 7),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::{ObjArchitecture, ObjKind, ObjSymbolFlagSet, ObjSymbolFlags};
+
+    /// The symbol macros of a typical project `macros.inc`.
+    const STOCK_MACROS: &str = r"
+.macro glabel label
+    .global \label
+    .type \label, @function
+\label:
+.endm
+
+.macro dlabel label
+    .global \label
+    .type \label, @object
+\label:
+.endm
+";
+
+    const BUILTIN_DIRECTIVES: &[&str] = &[
+        ".include", ".file", ".text", ".data", ".rodata", ".section", ".balign", ".global",
+        ".weak", ".type", ".size", ".hidden", ".byte", ".2byte", ".4byte", ".8byte", ".skip",
+        ".string", ".ascii",
+    ];
+
+    fn symbol(
+        name: &str,
+        section: usize,
+        address: u64,
+        size: u64,
+        kind: ObjSymbolKind,
+    ) -> ObjSymbol {
+        ObjSymbol {
+            name: name.into(),
+            address,
+            section: Some(section),
+            size,
+            size_known: true,
+            flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
+            kind,
+            ..Default::default()
+        }
+    }
+
+    fn section(name: &str, kind: ObjSectionKind, address: u64, data: Vec<u8>) -> ObjSection {
+        ObjSection {
+            name: name.to_string(),
+            kind,
+            address,
+            size: data.len() as u64,
+            data,
+            align: 4,
+            elf_index: 0,
+            relocations: Default::default(),
+            original_address: 0,
+            file_offset: 0,
+            section_known: true,
+            splits: Default::default(),
+        }
+    }
+
+    fn test_obj() -> ObjInfo {
+        let mut local_fn = symbol("local_fn", 0, 0x80003108, 4, ObjSymbolKind::Function);
+        local_fn.flags = ObjSymbolFlagSet(ObjSymbolFlags::Local.into());
+        let mut label = symbol("lbl_80003104", 0, 0x80003104, 0, ObjSymbolKind::Unknown);
+        label.flags = ObjSymbolFlagSet(ObjSymbolFlags::Local.into());
+        let mut weak_obj = symbol("weak_obj", 1, 0x80004008, 4, ObjSymbolKind::Object);
+        weak_obj.flags = ObjSymbolFlagSet(ObjSymbolFlags::Weak.into());
+        let symbols = vec![
+            symbol("global_fn", 0, 0x80003100, 8, ObjSymbolKind::Function),
+            label,
+            local_fn,
+            symbol("global_obj", 1, 0x80004000, 8, ObjSymbolKind::Object),
+            weak_obj,
+        ];
+        // nop; blr; blr
+        let text = [0x60000000u32, 0x4E800020, 0x4E800020]
+            .iter()
+            .flat_map(|ins| ins.to_be_bytes())
+            .collect();
+        let mut data = section(".data", ObjSectionKind::Data, 0x80004000, vec![0; 12]);
+        data.relocations
+            .insert(0x80004000, ObjReloc {
+                kind: ObjRelocKind::Absolute,
+                target_symbol: 0,
+                addend: 4,
+                module: None,
+            })
+            .unwrap();
+        ObjInfo::new(
+            ObjKind::Relocatable,
+            ObjArchitecture::PowerPc,
+            "test.o".to_string(),
+            symbols,
+            vec![section(".text", ObjSectionKind::Code, 0x80003100, text), data],
+        )
+    }
+
+    #[test]
+    fn macro_flavor_uses_stock_macros() {
+        let macros = STOCK_MACROS
+            .lines()
+            .filter_map(|line| line.strip_prefix(".macro "))
+            .map(|line| {
+                let (name, params) = line.split_once(' ').unwrap_or((line, ""));
+                (name, params.split(',').filter(|p| !p.trim().is_empty()).count())
+            })
+            .collect::<BTreeMap<_, _>>();
+        let options = AsmOptions { flavor: AsmFlavor::Macro, ..Default::default() };
+        let mut out = vec![];
+        write_asm(&mut out, &test_obj(), &options).unwrap();
+        let asm = String::from_utf8(out).unwrap();
+
+        for line in asm.lines().map(str::trim) {
+            // Instructions are preceded by an address comment
+            if line.is_empty() || line.starts_with('#') || line.starts_with("/*") {
+                continue;
+            }
+            let (token, args) = line.split_once([' ', '\t']).unwrap_or((line, ""));
+            if token.ends_with(':') {
+                continue;
+            }
+            if token.starts_with('.') {
+                assert!(BUILTIN_DIRECTIVES.contains(&token), "Unexpected directive: {line}");
+            } else {
+                let params = macros.get(token).unwrap_or_else(|| panic!("Unknown macro: {line}"));
+                assert_eq!(args.split(',').count(), *params, "Wrong macro arguments: {line}");
+            }
+        }
+        assert!(asm.contains("glabel global_fn\n"));
+        assert!(asm.contains("dlabel global_obj\n"));
+        assert!(asm.contains(".weak weak_obj\n"));
+        assert!(asm.contains(".size local_fn, . - local_fn\n"));
+    }
+
+    #[test]
+    fn gqr_register_prefix() {
+        // psq_l f1, 0(r3), 0, qr3; blr
+        let text = [0xE0233000u32, 0x4E800020].iter().flat_map(|ins| ins.to_be_bytes()).collect();
+        let obj = ObjInfo::new(
+            ObjKind::Relocatable,
+            ObjArchitecture::PowerPc,
+            "test.o".to_string(),
+            vec![symbol("load", 0, 0x80003100, 8, ObjSymbolKind::Function)],
+            vec![section(".text", ObjSectionKind::Code, 0x80003100, text)],
+        );
+        for (register_prefix, expected) in [(false, ", qr3\n"), (true, ", %qr3\n")] {
+            let options = AsmOptions { gqr_names: true, register_prefix, ..Default::default() };
+            let mut out = vec![];
+            write_asm(&mut out, &obj, &options).unwrap();
+            let asm = String::from_utf8(out).unwrap();
+            assert!(asm.contains(expected), "Missing '{}' in:\n{asm}", expected.trim());
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::obj::{ObjInfo, ObjSectionKind, ObjSymbolKind};
+
+/// A proposed split for a run of unsplit BSS symbols.
+#[derive(Debug, Clone, Serialize)]
+pub struct BssProposal {
+    #[serde(skip)]
+    pub section_index: usize,
+    pub section: String,
+    pub start: u32,
+    pub end: u32,
+    pub unit: String,
+    pub symbols: usize,
+    /// Lowest share of references to a symbol in the run made by `unit`, from 0 to 1.
+    pub confidence: f32,
+    /// The split is out of link order, or the unit already has a split in the section.
+    pub conflict: bool,
+}
+
+#[derive(Default)]
+struct BssSymbol {
+    end: u32,
+    /// Referencing unit -> reference count
+    units: BTreeMap<String, u32>,
+    assigned: Option<(String, f32)>,
+}
+
+/// Proposes splits for the symbols of BSS sections that aren't covered by a split yet.
+///
+/// BSS has no content to match, so each symbol is attributed to the unit whose code references
+/// it most, and symbols without references to the unit of the symbols around them. Adjacent
+/// symbols of the same unit form one split. `obj` needs relocations (see `Tracker`).
+pub fn propose_bss_splits(obj: &ObjInfo) -> Vec<BssProposal> {
+    let link_index = obj
+        .link_order
+        .iter()
+        .enumerate()
+        .map(|(i, unit)| (unit.name.as_str(), i))
+        .collect::<HashMap<_, _>>();
+
+    // Unsplit symbols of each BSS section
+    let mut sections = BTreeMap::<usize, BTreeMap<u32, BssSymbol>>::new();
+    for (section_index, section) in obj.sections.iter() {
+        if section.kind != ObjSectionKind::Bss {
+            continue;
+        }
+        let symbols = sections.entry(section_index).or_default();
+        for (_, symbol) in obj.symbols.for_section(section_index) {
+            let address = symbol.address as u32;
+            if symbol.kind == ObjSymbolKind::Section
+                || section.splits.for_address(address).is_some()
+            {
+                continue;
+            }
+            let entry = symbols.entry(address).or_default();
+            entry.end = entry.end.max(address + symbol.size as u32);
+        }
+    }
+
+    // Count references by unit
+    for (_, section) in obj.sections.iter() {
+        for (address, reloc) in section.relocations.iter() {
+            let Some((_, split)) = section.splits.for_address(address) else { continue };
+            let target = &obj.symbols[reloc.target_symbol];
+            let Some(symbols) = target.section.and_then(|i| sections.get_mut(&i)) else {
+                continue;
+            };
+            let target_address = (target.address as i64 + reloc.addend) as u32;
+            let Some((&start, symbol)) = symbols.range_mut(..=target_address).next_back() else {
+                continue;
+            };
+            if target_address == start || target_address < symbol.end {
+                *symbol.units.entry(split.unit.clone()).or_default() += 1;
+            }
+        }
+    }
+
+    let mut proposals = vec![];
+    for (section_index, mut symbols) in sections {
+        let section = &obj.sections[section_index];
+        for symbol in symbols.values_mut() {
+            let total = symbol.units.values().sum::<u32>();
+            symbol.assigned = symbol
+                .units
+                .iter()
+                .max_by_key(|&(_, &count)| count)
+                .map(|(unit, &count)| (unit.clone(), count as f32 / total as f32));
+        }
+
+        // Symbols without references between two symbols of the same unit
+        let addresses = symbols.keys().copied().collect::<Vec<_>>();
+        for (i, &address) in addresses.iter().enumerate() {
+            if symbols[&address].assigned.is_some() {
+                continue;
+            }
+            let assigned = |j: &usize| symbols[&addresses[*j]].assigned.clone().map(|a| (*j, a));
+            let prev = (0..i).rev().find_map(|j| assigned(&j));
+            let next = (i + 1..addresses.len()).find_map(|j| assigned(&j));
+            if let (Some((p, (prev_unit, prev_conf))), Some((n, (next_unit, next_conf)))) =
+                (prev, next)
+            {
+                if prev_unit == next_unit
+                    && section.splits.for_range(addresses[p]..addresses[n]).next().is_none()
+                {
+                    symbols.get_mut(&address).unwrap().assigned =
+                        Some((prev_unit, prev_conf.min(next_conf)));
+                }
+            }
+        }
+
+        // Runs of the same unit, not crossing existing splits
+        let mut i = 0;
+        let start_index = proposals.len();
+        while i < addresses.len() {
+            let Some((unit, mut confidence)) = symbols[&addresses[i]].assigned.clone() else {
+                i += 1;
+                continue;
+            };
+            let start = addresses[i];
+            let mut j = i + 1;
+            while j < addresses.len()
+                && symbols[&addresses[j]].assigned.as_ref().is_some_and(|(u, _)| *u == unit)
+                && section.splits.for_range(addresses[j - 1]..addresses[j]).next().is_none()
+            {
+                confidence = confidence.min(symbols[&addresses[j]].assigned.as_ref().unwrap().1);
+                j += 1;
+            }
+            let section_end = (section.address + section.size) as u32;
+            let next_split = section.splits.for_range(start..).next().map(|(a, _)| a);
+            let end = addresses
+                .get(j)
+                .copied()
+                .unwrap_or(section_end)
+                .min(next_split.unwrap_or(section_end));
+            proposals.push(BssProposal {
+                section_index,
+                section: section.name.clone(),
+                start,
+                end,
+                unit,
+                symbols: j - i,
+                confidence,
+                conflict: false,
+            });
+            i = j;
+        }
+
+        // BSS is laid out in link order, with one split per unit
+        let mut splits = section
+            .splits
+            .iter()
+            .map(|(address, split)| (address, split.unit.as_str(), None))
+            .chain(
+                proposals[start_index..]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| (p.start, p.unit.as_str(), Some(start_index + i))),
+            )
+            .collect::<Vec<_>>();
+        splits.sort_by_key(|&(address, _, _)| address);
+        let mut conflicts = vec![];
+        for (i, &(_, unit, proposal)) in splits.iter().enumerate() {
+            let Some(proposal) = proposal else { continue };
+            let index = link_index.get(unit);
+            let before = splits[..i].iter().filter_map(|(_, u, _)| link_index.get(u)).max();
+            let after = splits[i + 1..].iter().filter_map(|(_, u, _)| link_index.get(u)).min();
+            let out_of_order = index.is_none()
+                || before.is_some_and(|b| b > index.unwrap())
+                || after.is_some_and(|a| a < index.unwrap());
+            let duplicate = splits.iter().filter(|(_, u, _)| *u == unit).count() > 1;
+            if out_of_order || duplicate {
+                conflicts.push(proposal);
+            }
+        }
+        for proposal in conflicts {
+            proposals[proposal].conflict = true;
+        }
+    }
+    proposals
+}
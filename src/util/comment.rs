@@ -3,8 +3,9 @@ use std::{
     io::{Read, Seek, Write},
 };
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
+use serde::{Deserialize, Serialize};
 use tracing::warn;
 
 use crate::{
@@ -12,8 +13,11 @@ use crate::{
     util::reader::{skip_bytes, struct_size, Endian, FromReader, ToWriter},
 };
 
-#[derive(Debug, Copy, Clone, IntoPrimitive, TryFromPrimitive)]
+#[derive(
+    Debug, Copy, Clone, Eq, PartialEq, IntoPrimitive, TryFromPrimitive, Serialize, Deserialize,
+)]
 #[repr(u8)]
+#[serde(rename_all = "lowercase")]
 pub enum MWFloatKind {
     None = 0,
     Soft = 1,
@@ -188,6 +192,40 @@ impl MWComment {
     }
 }
 
+/// Overrides for the header of written `.comment` sections, for setups that need it to match
+/// the original compiler's.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct MWCommentConfig {
+    /// Compiler version tag, e.g. `2.4.2.1`. Defaults to the version for the format version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiler_version: Option<String>,
+    /// Whether data was pooled. Defaults to `true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_data: Option<bool>,
+    /// Floating point mode (`none`, `soft` or `hard`). Defaults to `hard`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub float: Option<MWFloatKind>,
+}
+
+impl MWCommentConfig {
+    pub fn apply(&self, comment: &mut MWComment) -> Result<()> {
+        if let Some(version) = &self.compiler_version {
+            let parts = version.split('.').map(|s| s.parse::<u8>()).collect::<Result<Vec<_>, _>>();
+            comment.compiler_version = parts
+                .ok()
+                .and_then(|p| p.try_into().ok())
+                .ok_or_else(|| anyhow!("Invalid compiler version '{}'", version))?;
+        }
+        if let Some(pool_data) = self.pool_data {
+            comment.pool_data = pool_data;
+        }
+        if let Some(float) = self.float {
+            comment.float = float;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct CommentSym {
     pub align: u32,
@@ -0,0 +1,138 @@
+use std::path::{Path, PathBuf};
+
+use path_slash::{PathBufExt, PathExt};
+use serde::Serialize;
+
+use crate::{
+    obj::ObjUnit,
+    util::scaffold::{is_cpp, source_path_for_unit},
+};
+
+/// An entry of a clang compilation database (`compile_commands.json`).
+#[derive(Debug, Clone, Serialize)]
+pub struct CompileCommand {
+    pub directory: String,
+    pub file: String,
+    pub arguments: Vec<String>,
+}
+
+/// Splits a command line into arguments, honoring double quotes.
+fn split_args(s: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut any = false;
+    for c in s.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                any = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if any {
+                    args.push(std::mem::take(&mut current));
+                    any = false;
+                }
+            }
+            c => {
+                current.push(c);
+                any = true;
+            }
+        }
+    }
+    if any {
+        args.push(current);
+    }
+    args
+}
+
+/// Translates the include paths, defines, prefix files and language of MWCC or GCC flags into
+/// clang arguments. Other flags (optimization, code generation) don't affect code intelligence
+/// and are dropped, since clang would reject most of them.
+fn translate_cflags(cflags: &str, cpp: &mut bool) -> Vec<String> {
+    let mut out = vec![];
+    let args = split_args(cflags);
+    let mut iter = args.iter().map(String::as_str);
+    while let Some(arg) = iter.next() {
+        match arg {
+            "-i" | "-I" | "-ir" | "-isystem" => {
+                if let Some(dir) = iter.next() {
+                    out.push(format!("-I{dir}"));
+                }
+            }
+            "-I-" | "-i-" => {}
+            "-d" | "-D" | "-define" => {
+                if let Some(define) = iter.next() {
+                    out.push(format!("-D{define}"));
+                }
+            }
+            "-U" => {
+                if let Some(define) = iter.next() {
+                    out.push(format!("-U{define}"));
+                }
+            }
+            "-prefix" | "-include" => {
+                if let Some(file) = iter.next() {
+                    out.push("-include".to_string());
+                    out.push(file.to_string());
+                }
+            }
+            "-lang" => match iter.next() {
+                Some("c++" | "cplus" | "cplusplus" | "ec++") => *cpp = true,
+                Some("c" | "c99") => *cpp = false,
+                _ => {}
+            },
+            _ if arg.starts_with("-I") || arg.starts_with("-D") || arg.starts_with("-U") => {
+                out.push(arg.to_string())
+            }
+            _ if arg.starts_with("-std=") => out.push(arg.to_string()),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Builds the compilation database entry for a unit's source file. Returns `None` for units
+/// without a C/C++ source.
+pub fn compile_command(
+    unit: &ObjUnit,
+    directory: &Path,
+    source_dir: &Path,
+    include_dirs: &[PathBuf],
+) -> Option<CompileCommand> {
+    let source = source_path_for_unit(unit)?;
+    let file = source_dir.join(source);
+    let mut cpp = is_cpp(unit);
+    let translated = unit.cflags.as_deref().map(|f| translate_cflags(f, &mut cpp));
+    let is_gcc = unit
+        .compiler
+        .as_deref()
+        .is_some_and(|c| c.starts_with("ProDG") || c.to_ascii_lowercase().contains("gcc"));
+
+    let mut arguments = vec![
+        "clang".to_string(),
+        "--target=powerpc-none-eabi".to_string(),
+        "-nostdinc".to_string(),
+        "-fno-builtin".to_string(),
+        "-x".to_string(),
+        if cpp { "c++" } else { "c" }.to_string(),
+        if cpp { "-std=c++98" } else { "-std=gnu99" }.to_string(),
+        "-D__PPCGEKKO__".to_string(),
+    ];
+    if !is_gcc {
+        arguments.push("-D__MWERKS__=0x4302".to_string());
+        // CodeWarrior allows `asm` functions and `__declspec` extensions
+        arguments.push("-fms-extensions".to_string());
+        arguments.push("-fasm-blocks".to_string());
+    }
+    arguments.extend(include_dirs.iter().map(|dir| format!("-I{}", dir.to_slash_lossy())));
+    arguments.extend(translated.into_iter().flatten());
+    arguments.push("-c".to_string());
+    arguments.push(file.to_slash_lossy().into_owned());
+
+    Some(CompileCommand {
+        directory: directory.to_slash_lossy().into_owned(),
+        file: file.to_slash_lossy().into_owned(),
+        arguments,
+    })
+}
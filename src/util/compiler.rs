@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::obj::{ObjInfo, ObjSectionKind, ObjSymbolKind};
+
+/// Compilers that can be told apart by their code.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompilerFamily {
+    /// MWCC up to GC/1.2.5
+    Mwcc1,
+    /// MWCC GC/1.3 and later, including GC/2.x
+    Mwcc2,
+    /// GCC, including ProDG (SN Systems)
+    Gcc,
+}
+
+impl CompilerFamily {
+    pub fn name(self) -> &'static str {
+        match self {
+            CompilerFamily::Mwcc1 => "mwcc1",
+            CompilerFamily::Mwcc2 => "mwcc2",
+            CompilerFamily::Gcc => "gcc",
+        }
+    }
+}
+
+/// The likely compiler of a unit.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnitCompiler {
+    pub unit: String,
+    pub family: CompilerFamily,
+    /// Functions with a recognized prologue.
+    pub functions: usize,
+    /// Share of those functions matching `family`, from 0 to 1.
+    pub confidence: f32,
+}
+
+const MFLR_R0: u32 = 0x7C0802A6;
+
+#[inline]
+fn is_stwu_r1(ins: u32) -> bool { ins & 0xFFFF8000 == 0x94218000 }
+
+/// `stw r0, d(r1)`, returning `d`
+#[inline]
+fn stw_r0_r1(ins: u32) -> Option<i16> { (ins & 0xFFFF0000 == 0x90010000).then_some(ins as i16) }
+
+/// Stores of callee-saved registers to the stack: `stw`, `stmw`, `stfd` or `psq_st`
+#[inline]
+fn is_callee_save(ins: u32) -> bool {
+    let (op, rs, ra) = (ins >> 26, (ins >> 21) & 31, (ins >> 16) & 31);
+    ra == 1 && rs >= 14 && matches!(op, 36 | 47 | 54 | 60)
+}
+
+/// Classifies a function by the order of its prologue, which differs between compilers:
+///
+/// - MWCC 1.x saves LR to the caller's frame first: `mflr r0; stw r0, 4(r1); stwu r1, -N(r1)`
+/// - MWCC 2.x allocates the frame first and saves LR right away:
+///   `stwu r1, -N(r1); mflr r0; stw r0, N+4(r1)`
+/// - GCC saves callee-saved registers before LR:
+///   `stwu r1, -N(r1); mflr r0; stmw r29, 12(r1); stw r0, N+4(r1)`
+///
+/// Returns `None` for leaf functions and unrecognized prologues.
+pub fn classify_function(code: &[u8]) -> Option<CompilerFamily> {
+    let ins = code
+        .chunks_exact(4)
+        .take(16)
+        .map(|c| u32::from_be_bytes(c.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    let mflr = ins.iter().position(|&i| i == MFLR_R0)?;
+    let stwu = ins.iter().position(|&i| is_stwu_r1(i))?;
+    let (save, offset) = ins[mflr..]
+        .iter()
+        .enumerate()
+        .find_map(|(n, &i)| stw_r0_r1(i).map(|offset| (mflr + n, offset)))?;
+    if save < stwu {
+        return (offset == 4).then_some(CompilerFamily::Mwcc1);
+    }
+    if stwu > mflr {
+        return None;
+    }
+    if ins[stwu + 1..save].iter().any(|&i| is_callee_save(i)) {
+        Some(CompilerFamily::Gcc)
+    } else {
+        Some(CompilerFamily::Mwcc2)
+    }
+}
+
+/// Detects the likely compiler of each unit from the prologues of its functions, in link order.
+/// Units without a classifiable function are omitted.
+pub fn detect_unit_compilers(obj: &ObjInfo) -> Vec<UnitCompiler> {
+    let mut units = HashMap::<&str, BTreeMap<CompilerFamily, usize>>::new();
+    for (section_index, section) in obj.sections.iter() {
+        if section.kind != ObjSectionKind::Code {
+            continue;
+        }
+        for (_, symbol) in obj.symbols.for_section(section_index) {
+            if symbol.kind != ObjSymbolKind::Function || symbol.size == 0 {
+                continue;
+            }
+            let start = symbol.address as u32;
+            let Some((_, split)) = section.splits.for_address(start) else { continue };
+            let Ok(code) = section.data_range(start, start + symbol.size as u32) else {
+                continue;
+            };
+            if let Some(family) = classify_function(code) {
+                *units.entry(&split.unit).or_default().entry(family).or_default() += 1;
+            }
+        }
+    }
+
+    let mut result = vec![];
+    for unit in &obj.link_order {
+        let Some(counts) = units.get(unit.name.as_str()) else { continue };
+        let functions = counts.values().sum::<usize>();
+        let Some((&family, &count)) = counts.iter().max_by_key(|&(_, &count)| count) else {
+            continue;
+        };
+        result.push(UnitCompiler {
+            unit: unit.name.clone(),
+            family,
+            functions,
+            confidence: count as f32 / functions as f32,
+        });
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(ins: &[u32]) -> Option<CompilerFamily> {
+        classify_function(&ins.iter().flat_map(|i| i.to_be_bytes()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn classify_prologues() {
+        assert_eq!(
+            classify(&[
+                0x7C0802A6, // mflr r0
+                0x90010004, // stw r0, 0x4(r1)
+                0x9421FFE0, // stwu r1, -0x20(r1)
+                0x93E1001C, // stw r31, 0x1c(r1)
+            ]),
+            Some(CompilerFamily::Mwcc1)
+        );
+        assert_eq!(
+            classify(&[
+                0x9421FFE0, // stwu r1, -0x20(r1)
+                0x7C0802A6, // mflr r0
+                0x90010024, // stw r0, 0x24(r1)
+                0x93E1001C, // stw r31, 0x1c(r1)
+            ]),
+            Some(CompilerFamily::Mwcc2)
+        );
+        assert_eq!(
+            classify(&[
+                0x9421FFE0, // stwu r1, -0x20(r1)
+                0x7C0802A6, // mflr r0
+                0xBFA10014, // stmw r29, 0x14(r1)
+                0x90010024, // stw r0, 0x24(r1)
+            ]),
+            Some(CompilerFamily::Gcc)
+        );
+    }
+
+    #[test]
+    fn classify_unrecognized() {
+        // Leaf function
+        assert_eq!(
+            classify(&[
+                0x38600000, // li r3, 0x0
+                0x4E800020, // blr
+            ]),
+            None
+        );
+        // LR saved to the caller's frame at the wrong offset
+        assert_eq!(
+            classify(&[
+                0x7C0802A6, // mflr r0
+                0x90010008, // stw r0, 0x8(r1)
+                0x9421FFE0, // stwu r1, -0x20(r1)
+            ]),
+            None
+        );
+        // LR read before the frame is allocated, but saved after
+        assert_eq!(
+            classify(&[
+                0x7C0802A6, // mflr r0
+                0x9421FFE0, // stwu r1, -0x20(r1)
+                0x90010024, // stw r0, 0x24(r1)
+            ]),
+            None
+        );
+        // No frame
+        assert_eq!(
+            classify(&[
+                0x7C0802A6, // mflr r0
+                0x90010004, // stw r0, 0x4(r1)
+                0x4E800020, // blr
+            ]),
+            None
+        );
+        assert_eq!(classify(&[]), None);
+    }
+}
@@ -1,8 +1,11 @@
 use std::{
-    fs,
+    borrow::Cow,
+    collections::{hash_map, BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt, fs,
     io::{BufRead, Write},
+    mem::take,
     num::ParseIntError,
-    path::Path,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
@@ -10,23 +13,27 @@ use anyhow::{anyhow, bail, ensure, Context, Result};
 use cwdemangle::{demangle, DemangleOptions};
 use filetime::FileTime;
 use once_cell::sync::Lazy;
+use path_slash::PathBufExt;
 use regex::{Captures, Regex};
+use serde::Serialize;
 use tracing::{debug, info, warn};
 use xxhash_rust::xxh3::xxh3_64;
 
 use crate::{
     analysis::cfa::SectionAddress,
     obj::{
-        ObjDataKind, ObjInfo, ObjKind, ObjSectionKind, ObjSplit, ObjSymbol, ObjSymbolFlagSet,
-        ObjSymbolFlags, ObjSymbolKind, ObjUnit,
+        ObjDataKind, ObjInfo, ObjKind, ObjSection, ObjSectionKind, ObjSplit, ObjSymbol,
+        ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind, ObjSymbolScope, ObjSymbolSource, ObjUnit,
+        ObjUnitLanguage, ObjUnitPadding, ObjUnitPattern, ObjUnitStatus, SymbolName,
     },
     util::{
+        align_up,
         file::{buf_writer, map_file, FileReadInfo},
         split::default_section_align,
     },
 };
 
-fn parse_hex(s: &str) -> Result<u32, ParseIntError> {
+pub fn parse_hex(s: &str) -> Result<u32, ParseIntError> {
     if s.starts_with("0x") {
         u32::from_str_radix(s.trim_start_matches("0x"), 16)
     } else {
@@ -34,35 +41,84 @@ fn parse_hex(s: &str) -> Result<u32, ParseIntError> {
     }
 }
 
+/// Parses a command-line address or value with [`parse_hex`].
+pub fn parse_address(s: &str) -> Result<u32, String> {
+    parse_hex(s).map_err(|e| format!("Invalid number '{s}': {e}"))
+}
+
+/// Maximum nesting depth of `include` directives.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+static SYMBOL_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        "^\\s*(?P<name>[^\\s=]+)\\s*=\\s*(?:(?P<section>[A-Za-z0-9.]+):)?(?P<addr>[0-9A-Fa-fXx]+);(?:\\s*//\\s*(?P<attrs>.*))?$",
+    )
+    .unwrap()
+});
+static UNIT_LINE: Lazy<Regex> =
+    Lazy::new(|| Regex::new("^\\s*(?P<name>[^\\s:]+)\\s*:\\s*(?P<attrs>.*)$").unwrap());
+static COMMENT_LINE: Lazy<Regex> = Lazy::new(|| Regex::new("^\\s*(?://|#).*$").unwrap());
+static INCLUDE_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new("^\\s*include\\s+(?:\"(?P<quoted>[^\"]+)\"|(?P<path>\\S+))\\s*$").unwrap()
+});
+
+/// Parses an `include` directive, resolving the path relative to the including file.
+fn parse_include_line(line: &str, base_dir: Option<&Path>) -> Option<PathBuf> {
+    let captures = INCLUDE_LINE.captures(line)?;
+    let path = PathBuf::from_slash(captures.name("quoted").or(captures.name("path"))?.as_str());
+    Some(match base_dir {
+        Some(base_dir) => base_dir.join(path),
+        None => path,
+    })
+}
+
 pub fn apply_symbols_file<P>(path: P, obj: &mut ObjInfo) -> Result<Option<FileReadInfo>>
 where P: AsRef<Path> {
-    Ok(if path.as_ref().is_file() {
+    let path = path.as_ref();
+    Ok(if path.is_file() {
         let file = map_file(path)?;
         let cached = FileReadInfo::new(&file)?;
-        for result in file.as_reader().lines() {
-            let line = match result {
-                Ok(line) => line,
-                Err(e) => bail!("Failed to process symbols file: {e:?}"),
-            };
-            if let Some(symbol) = parse_symbol_line(&line, obj)? {
-                obj.add_symbol(symbol, true)?;
-            }
-        }
+        apply_symbols(&mut file.as_reader(), path.parent(), obj, 0)?;
         Some(cached)
     } else {
         None
     })
 }
 
-pub fn parse_symbol_line(line: &str, obj: &mut ObjInfo) -> Result<Option<ObjSymbol>> {
-    static SYMBOL_LINE: Lazy<Regex> = Lazy::new(|| {
-        Regex::new(
-            "^\\s*(?P<name>[^\\s=]+)\\s*=\\s*(?:(?P<section>[A-Za-z0-9.]+):)?(?P<addr>[0-9A-Fa-fXx]+);(?:\\s*//\\s*(?P<attrs>.*))?$",
-        )
-        .unwrap()
-    });
-    static COMMENT_LINE: Lazy<Regex> = Lazy::new(|| Regex::new("^\\s*(?://|#).*$").unwrap());
+fn apply_symbols<R>(
+    r: &mut R,
+    base_dir: Option<&Path>,
+    obj: &mut ObjInfo,
+    depth: usize,
+) -> Result<()>
+where
+    R: BufRead + ?Sized,
+{
+    for result in r.lines() {
+        let line = match result {
+            Ok(line) => line,
+            Err(e) => bail!("Failed to process symbols file: {e:?}"),
+        };
+        if let Some(include_path) = parse_include_line(&line, base_dir) {
+            ensure!(
+                depth < MAX_INCLUDE_DEPTH,
+                "Include depth exceeded at '{}' (recursive include?)",
+                include_path.display()
+            );
+            let file = map_file(&include_path).with_context(|| {
+                format!("Failed to open included symbols file '{}'", include_path.display())
+            })?;
+            apply_symbols(&mut file.as_reader(), include_path.parent(), obj, depth + 1)?;
+            continue;
+        }
+        if let Some(symbol) = parse_symbol_line(&line, obj)? {
+            obj.add_symbol(symbol, true)?;
+        }
+    }
+    Ok(())
+}
 
+pub fn parse_symbol_line(line: &str, obj: &mut ObjInfo) -> Result<Option<ObjSymbol>> {
     if let Some(captures) = SYMBOL_LINE.captures(line) {
         let name = captures["name"].to_string();
         let addr = parse_hex(&captures["addr"])?;
@@ -80,76 +136,39 @@ pub fn parse_symbol_line(line: &str, obj: &mut ObjInfo) -> Result<Option<ObjSymb
         } else {
             bail!("Section {} not found", section_name)
         };
-        let demangled_name = demangle(&name, &DemangleOptions::default());
-        let mut symbol =
-            ObjSymbol { name, demangled_name, address: addr as u64, section, ..Default::default() };
+        let demangled_name = demangle(&name, &DemangleOptions::default()).map(SymbolName::from);
+        let mut symbol = ObjSymbol {
+            name: name.into(),
+            demangled_name,
+            address: addr as u64,
+            section,
+            ..Default::default()
+        };
+        // Symbols without a recorded source were named by the user, unless auto-generated
+        symbol.source = if is_auto_symbol(&symbol) || symbol.name.starts_with('@') {
+            ObjSymbolSource::Analysis
+        } else {
+            ObjSymbolSource::Config
+        };
         // TODO move somewhere common
         if symbol.name.starts_with("..") {
             symbol.flags.0 |= ObjSymbolFlags::ForceActive;
         }
-        let attrs = captures["attrs"].split(' ');
-        for attr in attrs {
-            if let Some((name, value)) = attr.split_once(':') {
-                match name {
-                    "type" => {
-                        symbol.kind = symbol_kind_from_str(value)
-                            .ok_or_else(|| anyhow!("Unknown symbol type '{}'", value))?;
-                    }
-                    "size" => {
-                        symbol.size = parse_hex(value)? as u64;
-                        symbol.size_known = true;
-                    }
-                    "scope" => {
-                        symbol.flags.0 |= symbol_flags_from_str(value)
-                            .ok_or_else(|| anyhow!("Unknown symbol scope '{}'", value))?;
-                    }
-                    "align" => {
-                        symbol.align = Some(parse_hex(value)?);
-                    }
-                    "data" => {
-                        symbol.data_kind = symbol_data_kind_from_str(value)
-                            .ok_or_else(|| anyhow!("Unknown symbol data type '{}'", value))?;
-                    }
-                    "hash" => {
-                        let hash = parse_hex(value)?;
-                        symbol.name_hash = Some(hash);
-                        if symbol.demangled_name_hash.is_none() {
-                            symbol.demangled_name_hash = Some(hash);
-                        }
-                    }
-                    "dhash" => {
-                        symbol.demangled_name_hash = Some(parse_hex(value)?);
-                    }
-                    _ => bail!("Unknown symbol attribute '{name}'"),
-                }
-            } else {
-                match attr {
-                    "hidden" => {
-                        symbol.flags.0 |= ObjSymbolFlags::Hidden;
-                    }
-                    "force_active" => {
-                        symbol.flags.0 |= ObjSymbolFlags::ForceActive;
-                    }
-                    "stripped" => {
-                        symbol.flags.0 |= ObjSymbolFlags::Stripped;
-                    }
-                    "noreloc" => {
-                        ensure!(
-                            symbol.size != 0,
-                            "Symbol {} requires size != 0 with noreloc",
-                            symbol.name
-                        );
-                        ensure!(
-                            section.is_some(),
-                            "Symbol {} requires section with noreloc",
-                            symbol.name
-                        );
-                        let addr = SectionAddress::new(section.unwrap(), symbol.address as u32);
-                        obj.blocked_ranges.insert(addr, addr.address + symbol.size as u32);
-                    }
-                    _ => bail!("Unknown symbol attribute '{attr}'"),
-                }
-            }
+        let attrs = parse_symbol_attrs(&mut symbol, &captures["attrs"])?;
+        if attrs.noreloc {
+            ensure!(symbol.size != 0, "Symbol {} requires size != 0 with noreloc", symbol.name);
+            let Some(section_index) = section else {
+                bail!("Symbol {} requires section with noreloc", symbol.name);
+            };
+            let addr = SectionAddress::new(section_index, symbol.address as u32);
+            obj.blocked_ranges.insert(addr, addr.address + symbol.size as u32);
+        }
+        if let Some(unit) = attrs.unit {
+            let Some(section_index) = section else {
+                bail!("Symbol {} requires section with unit", symbol.name);
+            };
+            let addr = SectionAddress::new(section_index, symbol.address as u32);
+            obj.unit_locals.insert((addr, symbol.name), unit);
         }
         Ok(Some(symbol))
     } else if line.is_empty() || COMMENT_LINE.is_match(line) {
@@ -159,6 +178,111 @@ pub fn parse_symbol_line(line: &str, obj: &mut ObjInfo) -> Result<Option<ObjSymb
     }
 }
 
+/// Whether the text of a symbols file defines the symbol `name`.
+pub fn defines_symbol(text: &str, name: &str) -> bool {
+    text.lines().any(|line| SYMBOL_LINE.captures(line).is_some_and(|c| &c["name"] == name))
+}
+
+/// Renames the symbol `old` in the text of a symbols file, keeping the rest of each line.
+/// Returns `None` if the file doesn't define the symbol.
+pub fn rename_symbol_text(text: &str, old: &str, new: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut renamed = false;
+    for line in text.split_inclusive('\n') {
+        match SYMBOL_LINE.captures(line.trim_end_matches(['\r', '\n'])).and_then(|c| c.name("name"))
+        {
+            Some(name) if name.as_str() == old => {
+                out.push_str(&line[..name.start()]);
+                out.push_str(new);
+                out.push_str(&line[name.end()..]);
+                renamed = true;
+            }
+            _ => out.push_str(line),
+        }
+    }
+    renamed.then_some(out)
+}
+
+/// Attributes of a symbol line that aren't part of [`ObjSymbol`].
+#[derive(Default)]
+struct SymbolAttrs {
+    noreloc: bool,
+    /// Unit the symbol is local to
+    unit: Option<String>,
+}
+
+/// Applies the attributes of a symbol line, returning the ones kept outside the symbol.
+fn parse_symbol_attrs(symbol: &mut ObjSymbol, attrs: &str) -> Result<SymbolAttrs> {
+    let mut result = SymbolAttrs::default();
+    for attr in attrs.split(' ') {
+        if let Some((name, value)) = attr.split_once(':') {
+            match name {
+                "type" => {
+                    symbol.kind = symbol_kind_from_str(value)
+                        .ok_or_else(|| anyhow!("Unknown symbol type '{}'", value))?;
+                }
+                "size" => {
+                    symbol.size = parse_hex(value)? as u64;
+                    symbol.size_known = true;
+                }
+                "scope" => {
+                    symbol.flags.0 |= symbol_flags_from_str(value)
+                        .ok_or_else(|| anyhow!("Unknown symbol scope '{}'", value))?;
+                }
+                "align" => {
+                    symbol.align = Some(parse_hex(value)?);
+                }
+                "data" => {
+                    symbol.data_kind = symbol_data_kind_from_str(value)
+                        .ok_or_else(|| anyhow!("Unknown symbol data type '{}'", value))?;
+                }
+                "hash" => {
+                    let hash = parse_hex(value)?;
+                    symbol.name_hash = Some(hash);
+                    if symbol.demangled_name_hash.is_none() {
+                        symbol.demangled_name_hash = Some(hash);
+                    }
+                }
+                "dhash" => {
+                    symbol.demangled_name_hash = Some(parse_hex(value)?);
+                }
+                "source" => {
+                    symbol.source = symbol_source_from_str(value)
+                        .ok_or_else(|| anyhow!("Unknown symbol source '{}'", value))?;
+                }
+                "unit" => {
+                    result.unit = Some(value.to_string());
+                }
+                _ => bail!("Unknown symbol attribute '{name}'"),
+            }
+        } else {
+            match attr {
+                "hidden" => {
+                    symbol.flags.0 |= ObjSymbolFlags::Hidden;
+                }
+                "force_active" => {
+                    symbol.flags.0 |= ObjSymbolFlags::ForceActive;
+                }
+                "stripped" => {
+                    symbol.flags.0 |= ObjSymbolFlags::Stripped;
+                }
+                "noreloc" => result.noreloc = true,
+                _ => bail!("Unknown symbol attribute '{attr}'"),
+            }
+        }
+    }
+    // Symbols scoped to a unit are always local
+    if let Some(unit) = &result.unit {
+        match symbol.flags.scope() {
+            ObjSymbolScope::Unknown | ObjSymbolScope::Local => {
+                symbol.flags.set_scope(ObjSymbolScope::Local)
+            }
+            _ => bail!("Symbol {} with unit:{} must be local", symbol.name, unit),
+        }
+    }
+    Ok(result)
+}
+
 pub fn is_skip_symbol(symbol: &ObjSymbol) -> bool {
     if symbol.flags.is_no_write() {
         return true;
@@ -215,7 +339,49 @@ where
     Ok(())
 }
 
-#[inline]
+/// Lists all files included (directly or transitively) by a symbols or splits file.
+pub fn config_include_paths<P>(path: P) -> Result<Vec<PathBuf>>
+where P: AsRef<Path> {
+    let path = path.as_ref();
+    let mut paths = Vec::new();
+    if !path.is_file() {
+        return Ok(paths);
+    }
+    let file = map_file(path)?;
+    for result in file.as_reader().lines() {
+        let line = result?;
+        if let Some(include_path) = parse_include_line(&line, path.parent()) {
+            collect_include_paths(include_path, &mut paths, 0)?;
+        }
+    }
+    Ok(paths)
+}
+
+fn collect_include_paths(path: PathBuf, out: &mut Vec<PathBuf>, depth: usize) -> Result<()> {
+    ensure!(
+        depth < MAX_INCLUDE_DEPTH,
+        "Include depth exceeded at '{}' (recursive include?)",
+        path.display()
+    );
+    let file = map_file(&path)
+        .with_context(|| format!("Failed to open included file '{}'", path.display()))?;
+    for result in file.as_reader().lines() {
+        let line = result?;
+        if let Some(include_path) = parse_include_line(&line, path.parent()) {
+            collect_include_paths(include_path, out, depth + 1)?;
+        }
+    }
+    out.push(path);
+    Ok(())
+}
+
+/// Reads the previous contents of a config file, if it was read before.
+/// The cached mtime guarantees it's unchanged; otherwise, the write is skipped anyway.
+fn read_original(path: &Path, cached_file: Option<FileReadInfo>) -> Option<String> {
+    cached_file?;
+    fs::read_to_string(path).ok()
+}
+
 pub fn write_symbols_file<P>(
     path: P,
     obj: &ObjInfo,
@@ -224,7 +390,32 @@ pub fn write_symbols_file<P>(
 where
     P: AsRef<Path>,
 {
-    write_if_unchanged(path, |w| write_symbols(w, obj), cached_file)
+    let path = path.as_ref();
+    let original = read_original(path, cached_file);
+    // Symbols defined by included files are not written back
+    let mut included = HashSet::new();
+    if original.is_some() {
+        for include_path in config_include_paths(path)? {
+            let file = map_file(&include_path)?;
+            for result in file.as_reader().lines() {
+                let line = result?;
+                if let Some(captures) = SYMBOL_LINE.captures(&line) {
+                    included.insert((
+                        SymbolName::new(&captures["name"]),
+                        parse_hex(&captures["addr"])?,
+                    ));
+                }
+            }
+        }
+    }
+    write_if_unchanged(
+        path,
+        |w| match &original {
+            Some(original) => write_symbols_preserving(w, obj, original, &included),
+            None => write_symbols(w, obj),
+        },
+        cached_file,
+    )
 }
 
 pub fn write_symbols<W>(w: &mut W, obj: &ObjInfo) -> Result<()>
@@ -238,6 +429,98 @@ where W: Write + ?Sized {
     Ok(())
 }
 
+/// Writes symbols while preserving the layout of the original file: comments, blank lines
+/// and manual ordering are kept, existing entries are updated in place, removed symbols are
+/// dropped, and new symbols are inserted after the closest preceding symbol by address.
+fn write_symbols_preserving<W>(
+    w: &mut W,
+    obj: &ObjInfo,
+    original: &str,
+    except: &HashSet<(SymbolName, u32)>,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    enum Entry<'a> {
+        Line(&'a str),
+        Symbol(usize),
+    }
+
+    // Symbols to write, in address order
+    let symbols = obj
+        .symbols
+        .iter_ordered()
+        .map(|(_, symbol)| symbol)
+        .filter(|symbol| {
+            symbol.kind != ObjSymbolKind::Section
+                && !is_skip_symbol(symbol)
+                && !except.contains(&(symbol.name, symbol.address as u32))
+        })
+        .collect::<Vec<_>>();
+    let mut by_key = HashMap::new();
+    for (rank, symbol) in symbols.iter().enumerate() {
+        by_key.entry((symbol.name.as_str(), symbol.address as u32)).or_insert(rank);
+    }
+
+    let mut written = vec![false; symbols.len()];
+    let mut entries = Vec::new();
+    for line in original.lines() {
+        let Some(captures) = SYMBOL_LINE.captures(line) else {
+            entries.push(Entry::Line(line));
+            continue;
+        };
+        let addr = parse_hex(&captures["addr"])?;
+        if let Some(&rank) = by_key.get(&(&captures["name"], addr)) {
+            if !written[rank] {
+                written[rank] = true;
+                entries.push(Entry::Symbol(rank));
+            }
+        }
+        // Otherwise, the symbol was removed
+    }
+
+    // Place each new symbol after the written symbol preceding it
+    let mut positions = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| match entry {
+            Entry::Symbol(rank) => Some((*rank, i)),
+            Entry::Line(_) => None,
+        })
+        .collect::<Vec<_>>();
+    positions.sort_unstable();
+    let mut leading = Vec::new();
+    let mut insert_after = vec![Vec::new(); entries.len()];
+    for rank in (0..symbols.len()).filter(|&rank| !written[rank]) {
+        match positions.partition_point(|&(r, _)| r < rank) {
+            0 => leading.push(rank),
+            idx => insert_after[positions[idx - 1].1].push(rank),
+        }
+    }
+
+    let first_symbol = positions.iter().map(|&(_, i)| i).min().unwrap_or(entries.len());
+    for (i, entry) in entries.iter().enumerate() {
+        if i == first_symbol {
+            for &rank in &leading {
+                write_symbol(w, obj, symbols[rank])?;
+            }
+        }
+        match entry {
+            Entry::Line(line) => writeln!(w, "{}", line)?,
+            Entry::Symbol(rank) => write_symbol(w, obj, symbols[*rank])?,
+        }
+        for &rank in &insert_after[i] {
+            write_symbol(w, obj, symbols[rank])?;
+        }
+    }
+    if first_symbol == entries.len() {
+        for &rank in &leading {
+            write_symbol(w, obj, symbols[rank])?;
+        }
+    }
+    Ok(())
+}
+
 fn write_symbol<W>(w: &mut W, obj: &ObjInfo, symbol: &ObjSymbol) -> Result<()>
 where W: Write + ?Sized {
     write!(w, "{} = ", symbol.name)?;
@@ -246,6 +529,24 @@ where W: Write + ?Sized {
         write!(w, "{}:", section.name)?;
     }
     write!(w, "{:#010X}; //", symbol.address)?;
+    let addr = symbol.section.map(|section| SectionAddress::new(section, symbol.address as u32));
+    let noreloc = addr.is_some_and(|addr| obj.blocked_ranges.contains_key(&addr));
+    let unit = addr.and_then(|addr| obj.unit_locals.get(&(addr, symbol.name)));
+    write_symbol_attrs(w, symbol, false, noreloc, unit.map(String::as_str))?;
+    writeln!(w)?;
+    Ok(())
+}
+
+fn write_symbol_attrs<W>(
+    w: &mut W,
+    symbol: &ObjSymbol,
+    force_active: bool,
+    noreloc: bool,
+    unit: Option<&str>,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
     write!(w, " type:{}", symbol_kind_to_str(symbol.kind))?;
     if symbol.size_known && symbol.size > 0 {
         write!(w, " size:{:#X}", symbol.size)?;
@@ -253,6 +554,9 @@ where W: Write + ?Sized {
     if let Some(scope) = symbol_flags_to_str(symbol.flags) {
         write!(w, " scope:{scope}")?;
     }
+    if let Some(unit) = unit {
+        write!(w, " unit:{unit}")?;
+    }
     if let Some(align) = symbol.align {
         write!(w, " align:{align}")?;
     }
@@ -267,21 +571,21 @@ where W: Write + ?Sized {
             write!(w, " dhash:{:#010X}", hash)?;
         }
     }
+    if let Some(source) = symbol_source_to_str(symbol.source) {
+        write!(w, " source:{source}")?;
+    }
     if symbol.flags.is_hidden() {
         write!(w, " hidden")?;
     }
-    // if symbol.flags.is_force_active() {
-    //     write!(w, " force_active")?;
-    // }
+    if force_active {
+        write!(w, " force_active")?;
+    }
     if symbol.flags.is_stripped() {
         write!(w, " stripped")?;
     }
-    if let Some(section) = symbol.section {
-        if obj.blocked_ranges.contains_key(&SectionAddress::new(section, symbol.address as u32)) {
-            write!(w, " noreloc")?;
-        }
+    if noreloc {
+        write!(w, " noreloc")?;
     }
-    writeln!(w)?;
     Ok(())
 }
 
@@ -364,6 +668,28 @@ fn symbol_data_kind_from_str(s: &str) -> Option<ObjDataKind> {
     }
 }
 
+/// Analysis and user-provided sources aren't written, as they're implied by the name.
+#[inline]
+fn symbol_source_to_str(source: ObjSymbolSource) -> Option<&'static str> {
+    match source {
+        ObjSymbolSource::Analysis | ObjSymbolSource::Config => None,
+        source => Some(source.as_str()),
+    }
+}
+
+#[inline]
+fn symbol_source_from_str(s: &str) -> Option<ObjSymbolSource> {
+    match s {
+        "analysis" => Some(ObjSymbolSource::Analysis),
+        "signature" => Some(ObjSymbolSource::Signature),
+        "map" => Some(ObjSymbolSource::Map),
+        "elf" => Some(ObjSymbolSource::Elf),
+        "binary" => Some(ObjSymbolSource::Binary),
+        "config" => Some(ObjSymbolSource::Config),
+        _ => None,
+    }
+}
+
 #[inline]
 fn section_kind_from_str(s: &str) -> Option<ObjSectionKind> {
     match s {
@@ -385,7 +711,6 @@ fn section_kind_to_str(kind: ObjSectionKind) -> &'static str {
     }
 }
 
-#[inline]
 pub fn write_splits_file<P>(
     path: P,
     obj: &ObjInfo,
@@ -395,99 +720,488 @@ pub fn write_splits_file<P>(
 where
     P: AsRef<Path>,
 {
-    write_if_unchanged(path, |w| write_splits(w, obj, all), cached_file)
+    let path = path.as_ref();
+    let original = read_original(path, cached_file);
+    // Units and unit patterns defined by included files are not written back
+    let mut included = HashSet::new();
+    if original.is_some() {
+        for include_path in config_include_paths(path)? {
+            let file = map_file(&include_path)?;
+            for result in file.as_reader().lines() {
+                let line = result?;
+                if let Some(captures) = UNIT_LINE.captures(&line) {
+                    included.insert(captures["name"].to_string());
+                }
+            }
+        }
+    }
+    write_if_unchanged(
+        path,
+        |w| match &original {
+            Some(original) => write_splits_preserving(w, obj, all, original, &included),
+            None => write_splits(w, obj, all),
+        },
+        cached_file,
+    )
 }
 
 pub fn write_splits<W>(w: &mut W, obj: &ObjInfo, all: bool) -> Result<()>
 where W: Write + ?Sized {
     writeln!(w, "Sections:")?;
     for (_, section) in obj.sections.iter() {
-        write!(w, "\t{:<11} type:{}", section.name, section_kind_to_str(section.kind))?;
-        if section.align > 0 {
-            write!(w, " align:{}", section.align)?;
-        }
+        write_section_def(w, section)?;
+    }
+    for pattern in &obj.unit_patterns {
         writeln!(w)?;
+        write_unit_pattern(w, pattern)?;
     }
+    let splits = splits_by_unit(obj);
     for unit in obj.link_order.iter().filter(|unit| all || !unit.autogenerated) {
-        write!(w, "\n{}:", unit.name)?;
-        if let Some(comment_version) = unit.comment_version {
-            write!(w, " comment:{}", comment_version)?;
-        }
         writeln!(w)?;
-        let mut split_iter = obj.sections.all_splits().peekable();
-        while let Some((_section_index, section, addr, split)) = split_iter.next() {
-            if split.unit != unit.name {
-                continue;
+        write_unit(w, obj, unit, unit_splits(&splits, &unit.name))?;
+    }
+    Ok(())
+}
+
+/// Writes splits while preserving the layout of the original file: comments and blank lines
+/// are kept, existing units and splits are updated in place, and new units are inserted
+/// after their predecessor in the link order.
+fn write_splits_preserving<W>(
+    w: &mut W,
+    obj: &ObjInfo,
+    all: bool,
+    original: &str,
+    except: &HashSet<String>,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    enum Header<'a> {
+        Sections,
+        Pattern(&'a str),
+        Unit(&'a str),
+    }
+    #[derive(Copy, Clone)]
+    enum Body<'a> {
+        Line(&'a str),
+        Item { section: &'a str, start: Option<u32> },
+    }
+    struct Block<'a> {
+        leading: Vec<&'a str>,
+        header: Header<'a>,
+        body: Vec<Body<'a>>,
+    }
+
+    let mut preamble = Vec::new();
+    let mut blocks = Vec::<Block>::new();
+    for line in original.lines() {
+        let in_block = !blocks.is_empty();
+        let body = match blocks.last_mut() {
+            Some(block) => &mut block.body,
+            None => &mut preamble,
+        };
+        if line.trim().is_empty() || COMMENT_LINE.is_match(line) || INCLUDE_LINE.is_match(line) {
+            body.push(Body::Line(line));
+        } else if let Some(captures) = UNIT_LINE.captures(line) {
+            // Comments directly above a header belong to it
+            let mut leading = Vec::new();
+            while let Some(&Body::Line(prev)) = body.last() {
+                if !COMMENT_LINE.is_match(prev) {
+                    break;
+                }
+                leading.insert(0, prev);
+                body.pop();
             }
-            let end = if split.end > 0 {
-                split.end
+            let name = captures.name("name").unwrap().as_str();
+            let header = if name == "Sections" {
+                Header::Sections
+            } else if is_unit_pattern(name) {
+                Header::Pattern(name)
             } else {
-                split_iter.peek().map(|&(_, _, addr, _)| addr).unwrap_or(0)
+                Header::Unit(name)
             };
-            write!(w, "\t{:<11} start:{:#010X} end:{:#010X}", section.name, addr, end)?;
-            if let Some(align) = split.align {
-                if align != default_section_align(section) as u32 {
-                    write!(w, " align:{}", align)?;
-                }
+            blocks.push(Block { leading, header, body: Vec::new() });
+        } else if !in_block {
+            body.push(Body::Line(line));
+        } else {
+            let mut parts = line.split_whitespace();
+            let section = parts.next().unwrap_or_default();
+            let start = parts.find_map(|attr| attr.strip_prefix("start:")).map(parse_hex);
+            body.push(Body::Item { section, start: start.transpose()? });
+        }
+    }
+
+    let splits = splits_by_unit(obj);
+    let units = obj
+        .link_order
+        .iter()
+        .filter(|unit| (all || !unit.autogenerated) && !except.contains(&unit.name))
+        .collect::<Vec<_>>();
+    let unit_names = units.iter().map(|unit| unit.name.as_str()).collect::<HashSet<_>>();
+    let mut unit_blocks = HashMap::new();
+    for block in &blocks {
+        if let Header::Unit(name) = block.header {
+            if unit_names.contains(name) {
+                unit_blocks.entry(name).or_insert(block);
+            }
+        }
+    }
+    let is_new_unit = |unit: &&&ObjUnit| !unit_blocks.contains_key(unit.name.as_str());
+    // The unit blocks of the original file are filled with existing units in link order
+    let mut existing =
+        (0..units.len()).filter(|&i| unit_blocks.contains_key(units[i].name.as_str()));
+
+    fn write_lines<W>(w: &mut W, lines: &[&str]) -> Result<()>
+    where W: Write + ?Sized {
+        for line in lines {
+            writeln!(w, "{}", line)?;
+        }
+        Ok(())
+    }
+    fn write_verbatim<W>(w: &mut W, body: &[Body]) -> Result<()>
+    where W: Write + ?Sized {
+        for line in body {
+            if let Body::Line(line) = line {
+                writeln!(w, "{}", line)?;
             }
-            if split.common {
-                write!(w, " common")?;
+        }
+        Ok(())
+    }
+
+    write_verbatim(w, &preamble)?;
+    let mut first_slot = true;
+    for block in &blocks {
+        let last_item = block.body.iter().rposition(|line| matches!(line, Body::Item { .. }));
+        match block.header {
+            Header::Sections => {
+                write_lines(w, &block.leading)?;
+                writeln!(w, "Sections:")?;
+                let mut sections = obj.sections.iter().map(|(_, section)| section);
+                if last_item.is_none() {
+                    for section in sections.by_ref() {
+                        write_section_def(w, section)?;
+                    }
+                }
+                for (i, line) in block.body.iter().enumerate() {
+                    match *line {
+                        Body::Line(line) => writeln!(w, "{}", line)?,
+                        Body::Item { .. } => {
+                            if let Some(section) = sections.next() {
+                                write_section_def(w, section)?;
+                            }
+                        }
+                    }
+                    if last_item == Some(i) {
+                        for section in sections.by_ref() {
+                            write_section_def(w, section)?;
+                        }
+                    }
+                }
             }
-            if let Some(name) = &split.rename {
-                write!(w, " rename:{}", name)?;
+            Header::Pattern(name) => {
+                write_lines(w, &block.leading)?;
+                if !except.contains(name) {
+                    if let Some(pattern) =
+                        obj.unit_patterns.iter().find(|p| p.pattern.as_str() == name)
+                    {
+                        write_unit_pattern(w, pattern)?;
+                    }
+                }
+                write_verbatim(w, &block.body)?;
             }
-            if split.skip {
-                write!(w, " skip")?;
+            Header::Unit(name) => {
+                let is_slot = unit_blocks.get(name).is_some_and(|b| std::ptr::eq(*b, block));
+                let Some(index) = is_slot.then(|| existing.next()).flatten() else {
+                    // Removed unit, only comments are kept
+                    write_lines(w, &block.leading)?;
+                    write_verbatim(w, &block.body)?;
+                    continue;
+                };
+                if first_slot {
+                    // New units at the start of the link order
+                    for unit in units.iter().take_while(is_new_unit) {
+                        write_unit(w, obj, unit, unit_splits(&splits, &unit.name))?;
+                        writeln!(w)?;
+                    }
+                    first_slot = false;
+                }
+                let unit = units[index];
+                let source = unit_blocks[unit.name.as_str()];
+                let last_item =
+                    source.body.iter().rposition(|line| matches!(line, Body::Item { .. }));
+                // New units following this one are inserted before trailing blank lines
+                let tail = source
+                    .body
+                    .iter()
+                    .rposition(|line| !matches!(line, Body::Line(line) if line.trim().is_empty()))
+                    .map_or(0, |i| i + 1);
+                let write_following = |w: &mut W| -> Result<()> {
+                    for unit in units[index + 1..].iter().take_while(is_new_unit) {
+                        writeln!(w)?;
+                        write_unit(w, obj, unit, unit_splits(&splits, &unit.name))?;
+                    }
+                    Ok(())
+                };
+
+                write_lines(w, &source.leading)?;
+                write_unit_header(w, obj, unit)?;
+                let current_splits = unit_splits(&splits, &unit.name);
+                let mut written = vec![false; current_splits.len()];
+                let write_remaining = |w: &mut W, written: &mut [bool]| -> Result<()> {
+                    for (split, written) in current_splits.iter().zip(written) {
+                        if !*written {
+                            *written = true;
+                            write_unit_split(w, split)?;
+                        }
+                    }
+                    Ok(())
+                };
+                if last_item.is_none() {
+                    write_remaining(w, &mut written)?;
+                }
+                for (i, line) in source.body.iter().enumerate() {
+                    if i == tail {
+                        write_following(w)?;
+                    }
+                    match *line {
+                        Body::Line(line) => writeln!(w, "{}", line)?,
+                        Body::Item { section, start } => {
+                            if let Some(j) = (0..current_splits.len()).find(|&j| {
+                                !written[j]
+                                    && current_splits[j].section.name == section
+                                    && Some(current_splits[j].addr) == start
+                            }) {
+                                written[j] = true;
+                                write_unit_split(w, &current_splits[j])?;
+                            }
+                            // Otherwise, the split was removed
+                        }
+                    }
+                    if last_item == Some(i) {
+                        write_remaining(w, &mut written)?;
+                    }
+                }
+                if tail == source.body.len() {
+                    write_following(w)?;
+                }
             }
+        }
+    }
+    if first_slot {
+        // No existing units, append all
+        for unit in &units {
             writeln!(w)?;
+            write_unit(w, obj, unit, unit_splits(&splits, &unit.name))?;
         }
     }
     Ok(())
 }
 
-struct SplitSection {
-    name: String,
-    start: u32,
+/// A split with its resolved end address.
+struct UnitSplit<'a> {
+    section: &'a ObjSection,
+    addr: u32,
     end: u32,
-    align: Option<u32>,
-    /// Whether this is a part of common BSS.
-    common: bool,
-    rename: Option<String>,
-    skip: bool,
+    split: &'a ObjSplit,
 }
 
-struct SplitUnit {
-    name: String,
-    /// MW `.comment` section version
-    comment_version: Option<u8>,
+fn splits_by_unit(obj: &ObjInfo) -> HashMap<&str, Vec<UnitSplit>> {
+    let mut result = HashMap::<&str, Vec<UnitSplit>>::new();
+    let mut split_iter = obj.sections.all_splits().peekable();
+    while let Some((_section_index, section, addr, split)) = split_iter.next() {
+        let end = if split.end > 0 {
+            split.end
+        } else {
+            split_iter.peek().map(|&(_, _, addr, _)| addr).unwrap_or(0)
+        };
+        result.entry(split.unit.as_str()).or_default().push(UnitSplit {
+            section,
+            addr,
+            end,
+            split,
+        });
+    }
+    result
 }
 
-pub struct SectionDef {
-    pub name: String,
-    pub kind: Option<ObjSectionKind>,
-    pub align: Option<u32>,
+#[inline]
+fn unit_splits<'a>(
+    splits: &'a HashMap<&str, Vec<UnitSplit<'a>>>,
+    unit: &str,
+) -> &'a [UnitSplit<'a>] {
+    splits.get(unit).map(Vec::as_slice).unwrap_or_default()
 }
 
-enum SplitLine {
-    Unit(SplitUnit),
-    UnitSection(SplitSection),
-    SectionsStart,
-    Section(SectionDef),
-    None,
+fn write_section_def<W>(w: &mut W, section: &ObjSection) -> Result<()>
+where W: Write + ?Sized {
+    write!(w, "\t{:<11} type:{}", section.name, section_kind_to_str(section.kind))?;
+    if section.align > 0 {
+        write!(w, " align:{}", section.align)?;
+    }
+    writeln!(w)?;
+    Ok(())
 }
 
-fn parse_split_line(line: &str, state: &SplitState) -> Result<SplitLine> {
-    static UNIT_LINE: Lazy<Regex> =
-        Lazy::new(|| Regex::new("^\\s*(?P<name>[^\\s:]+)\\s*:\\s*(?P<attrs>.*)$").unwrap());
-    static SECTION_LINE: Lazy<Regex> =
-        Lazy::new(|| Regex::new("^\\s*(?P<name>\\S+)\\s*(?P<attrs>.*)$").unwrap());
-    static COMMENT_LINE: Lazy<Regex> = Lazy::new(|| Regex::new("^\\s*(?://|#).*$").unwrap());
-
-    if line.is_empty() || COMMENT_LINE.is_match(line) {
-        Ok(SplitLine::None)
-    } else if let Some(captures) = UNIT_LINE.captures(line) {
-        parse_unit_line(captures).with_context(|| format!("While parsing split line: '{line}'"))
-    } else if let Some(captures) = SECTION_LINE.captures(line) {
+fn write_unit_pattern<W>(w: &mut W, pattern: &ObjUnitPattern) -> Result<()>
+where W: Write + ?Sized {
+    write!(w, "{}:", pattern.pattern.as_str())?;
+    if let Some(comment_version) = pattern.comment_version {
+        write!(w, " comment:{}", comment_version)?;
+    }
+    if let Some(compiler) = &pattern.compiler {
+        write!(w, " compiler:{}", quote(compiler))?;
+    }
+    if let Some(cflags) = &pattern.cflags {
+        write!(w, " cflags:{}", quote(cflags))?;
+    }
+    if let Some(lang) = pattern.lang {
+        write!(w, " lang:{}", unit_language_to_str(lang))?;
+    }
+    if let Some(status) = pattern.status {
+        write!(w, " status:{}", unit_status_to_str(status))?;
+    }
+    if let Some(preset) = pattern.preset {
+        write!(w, " preset:{}", preset)?;
+    }
+    if let Some(scratch) = pattern.scratch {
+        write!(w, " scratch:{}", scratch)?;
+    }
+    if let Some(padding) = pattern.padding {
+        write!(w, " padding:{}", unit_padding_to_str(padding))?;
+    }
+    if let Some(library) = &pattern.library {
+        write!(w, " library:{}", quote(library))?;
+    }
+    writeln!(w)?;
+    Ok(())
+}
+
+fn write_unit_header<W>(w: &mut W, obj: &ObjInfo, unit: &ObjUnit) -> Result<()>
+where W: Write + ?Sized {
+    // Attributes provided by unit patterns are omitted
+    write_unit_attrs(w, unit, &obj.inherited_unit(&unit.name))
+}
+
+/// Writes a unit line, omitting attributes equal to those in `inherited`.
+fn write_unit_attrs<W>(w: &mut W, unit: &ObjUnit, inherited: &ObjUnit) -> Result<()>
+where W: Write + ?Sized {
+    write!(w, "{}:", unit.name)?;
+    if let Some(comment_version) = unit.comment_version {
+        if inherited.comment_version != Some(comment_version) {
+            write!(w, " comment:{}", comment_version)?;
+        }
+    }
+    if let Some(compiler) =
+        unit.compiler.as_ref().filter(|&c| inherited.compiler.as_ref() != Some(c))
+    {
+        write!(w, " compiler:{}", quote(compiler))?;
+    }
+    if let Some(cflags) = unit.cflags.as_ref().filter(|&c| inherited.cflags.as_ref() != Some(c)) {
+        write!(w, " cflags:{}", quote(cflags))?;
+    }
+    if let Some(lang) = unit.lang.filter(|&l| inherited.lang != Some(l)) {
+        write!(w, " lang:{}", unit_language_to_str(lang))?;
+    }
+    if let Some(source) = &unit.source {
+        write!(w, " source:{}", quote(source))?;
+    }
+    if let Some(status) = unit.status.filter(|&s| inherited.status != Some(s)) {
+        write!(w, " status:{}", unit_status_to_str(status))?;
+    }
+    if let Some(preset) = unit.preset.filter(|&p| inherited.preset != Some(p)) {
+        write!(w, " preset:{}", preset)?;
+    }
+    if let Some(scratch) = unit.scratch.filter(|&s| inherited.scratch != Some(s)) {
+        write!(w, " scratch:{}", scratch)?;
+    }
+    if let Some(padding) = unit.padding.filter(|&p| inherited.padding != Some(p)) {
+        write!(w, " padding:{}", unit_padding_to_str(padding))?;
+    }
+    if let Some(library) = unit.library.as_ref().filter(|&l| inherited.library.as_ref() != Some(l))
+    {
+        write!(w, " library:{}", quote(library))?;
+    }
+    writeln!(w)?;
+    Ok(())
+}
+
+fn write_unit_split<W>(w: &mut W, unit_split: &UnitSplit) -> Result<()>
+where W: Write + ?Sized {
+    let UnitSplit { section, addr, end, split } = unit_split;
+    write!(w, "\t{:<11} start:{:#010X} end:{:#010X}", section.name, addr, end)?;
+    if let Some(align) = split.align {
+        if align != default_section_align(section) as u32 {
+            write!(w, " align:{}", align)?;
+        }
+    }
+    if split.common {
+        write!(w, " common")?;
+    }
+    if let Some(name) = &split.rename {
+        write!(w, " rename:{}", name)?;
+    }
+    if let Some(end_align) = split.end_align {
+        write!(w, " end_align:{}", end_align)?;
+    }
+    if let Some(pad) = split.pad {
+        write!(w, " pad:{:#X}", pad)?;
+    }
+    if split.skip {
+        write!(w, " skip")?;
+    }
+    writeln!(w)?;
+    Ok(())
+}
+
+fn write_unit<W>(w: &mut W, obj: &ObjInfo, unit: &ObjUnit, splits: &[UnitSplit]) -> Result<()>
+where W: Write + ?Sized {
+    write_unit_header(w, obj, unit)?;
+    for split in splits {
+        write_unit_split(w, split)?;
+    }
+    Ok(())
+}
+
+struct SplitSection {
+    name: String,
+    start: u32,
+    end: u32,
+    align: Option<u32>,
+    /// Whether this is a part of common BSS.
+    common: bool,
+    rename: Option<String>,
+    pad: Option<u32>,
+    end_align: Option<u32>,
+    skip: bool,
+}
+
+pub struct SectionDef {
+    pub name: String,
+    pub kind: Option<ObjSectionKind>,
+    pub align: Option<u32>,
+}
+
+enum SplitLine {
+    Unit(ObjUnit),
+    UnitPattern(ObjUnitPattern),
+    UnitSection(SplitSection),
+    SectionsStart,
+    Section(SectionDef),
+    Include(PathBuf),
+    None,
+}
+
+fn parse_split_line(line: &str, base_dir: Option<&Path>, state: &SplitState) -> Result<SplitLine> {
+    static SECTION_LINE: Lazy<Regex> =
+        Lazy::new(|| Regex::new("^\\s*(?P<name>\\S+)\\s*(?P<attrs>.*)$").unwrap());
+
+    if line.is_empty() || COMMENT_LINE.is_match(line) {
+        Ok(SplitLine::None)
+    } else if let Some(path) = parse_include_line(line, base_dir) {
+        Ok(SplitLine::Include(path))
+    } else if let Some(captures) = UNIT_LINE.captures(line) {
+        parse_unit_line(captures).with_context(|| format!("While parsing split line: '{line}'"))
+    } else if let Some(captures) = SECTION_LINE.captures(line) {
         parse_section_line(captures, state)
             .with_context(|| format!("While parsing split line: '{line}'"))
     } else {
@@ -500,12 +1214,45 @@ fn parse_unit_line(captures: Captures) -> Result<SplitLine> {
     if name == "Sections" {
         return Ok(SplitLine::SectionsStart);
     }
-    let mut unit = SplitUnit { name: name.to_string(), comment_version: None };
+    let mut unit = ObjUnit { name: name.to_string(), ..Default::default() };
 
-    for attr in captures["attrs"].split(' ').filter(|&s| !s.is_empty()) {
+    for attr in split_attrs(&captures["attrs"]) {
         if let Some((attr, value)) = attr.split_once(':') {
+            let value = unquote(value);
             match attr {
                 "comment" => unit.comment_version = Some(u8::from_str(value)?),
+                "compiler" => unit.compiler = Some(value.to_string()),
+                "cflags" => unit.cflags = Some(value.to_string()),
+                "lang" => {
+                    unit.lang = Some(
+                        unit_language_from_str(value)
+                            .ok_or_else(|| anyhow!("Unknown unit language '{}'", value))?,
+                    );
+                }
+                "source" => unit.source = Some(value.to_string()),
+                "status" => {
+                    unit.status = Some(
+                        unit_status_from_str(value)
+                            .ok_or_else(|| anyhow!("Unknown unit status '{}'", value))?,
+                    );
+                }
+                "preset" => unit.preset = Some(u32::from_str(value)?),
+                "scratch" => unit.scratch = Some(bool::from_str(value)?),
+                "library" => {
+                    // Names the archive written to `lib/<library>.a`
+                    ensure!(
+                        !value.is_empty() && !value.contains(['/', '\\']) && value != "..",
+                        "Invalid library name '{}'",
+                        value
+                    );
+                    unit.library = Some(value.to_string());
+                }
+                "padding" => {
+                    unit.padding = Some(
+                        unit_padding_from_str(value)
+                            .ok_or_else(|| anyhow!("Unknown unit padding '{}'", value))?,
+                    );
+                }
                 _ => bail!("Unknown unit attribute '{}'", attr),
             }
         } else {
@@ -513,9 +1260,125 @@ fn parse_unit_line(captures: Captures) -> Result<SplitLine> {
         }
     }
 
+    if is_unit_pattern(name) {
+        ensure!(unit.source.is_none(), "Unit pattern '{}' can't specify a source path", name);
+        let pattern =
+            glob::Pattern::new(name).with_context(|| format!("Invalid unit pattern '{}'", name))?;
+        return Ok(SplitLine::UnitPattern(ObjUnitPattern {
+            pattern,
+            comment_version: unit.comment_version,
+            compiler: unit.compiler,
+            cflags: unit.cflags,
+            lang: unit.lang,
+            status: unit.status,
+            preset: unit.preset,
+            scratch: unit.scratch,
+            padding: unit.padding,
+            library: unit.library,
+        }));
+    }
+
     Ok(SplitLine::Unit(unit))
 }
 
+/// Splits unit attributes on whitespace, keeping double-quoted values intact.
+fn split_attrs(attrs: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    for (i, c) in attrs.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ' ' | '\t' if !quoted => {
+                if let Some(start) = start.take() {
+                    result.push(&attrs[start..i]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(start) = start {
+        result.push(&attrs[start..]);
+    }
+    result
+}
+
+#[inline]
+fn unquote(value: &str) -> &str {
+    value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value)
+}
+
+/// Quotes an attribute value containing whitespace.
+#[inline]
+fn quote(value: &str) -> Cow<'_, str> {
+    if value.contains(char::is_whitespace) {
+        Cow::Owned(format!("\"{}\"", value))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+#[inline]
+fn unit_language_from_str(s: &str) -> Option<ObjUnitLanguage> {
+    match s {
+        "c" => Some(ObjUnitLanguage::C),
+        "cpp" | "c++" => Some(ObjUnitLanguage::Cpp),
+        "asm" => Some(ObjUnitLanguage::Asm),
+        _ => None,
+    }
+}
+
+#[inline]
+fn unit_language_to_str(lang: ObjUnitLanguage) -> &'static str {
+    match lang {
+        ObjUnitLanguage::C => "c",
+        ObjUnitLanguage::Cpp => "cpp",
+        ObjUnitLanguage::Asm => "asm",
+    }
+}
+
+#[inline]
+fn unit_status_from_str(s: &str) -> Option<ObjUnitStatus> {
+    match s {
+        "nonmatching" => Some(ObjUnitStatus::NonMatching),
+        "matching" => Some(ObjUnitStatus::Matching),
+        "equivalent" => Some(ObjUnitStatus::Equivalent),
+        _ => None,
+    }
+}
+
+#[inline]
+fn unit_status_to_str(status: ObjUnitStatus) -> &'static str {
+    match status {
+        ObjUnitStatus::NonMatching => "nonmatching",
+        ObjUnitStatus::Matching => "matching",
+        ObjUnitStatus::Equivalent => "equivalent",
+    }
+}
+
+#[inline]
+fn unit_padding_from_str(s: &str) -> Option<ObjUnitPadding> {
+    match s {
+        "zero" => Some(ObjUnitPadding::Zero),
+        "nop" => Some(ObjUnitPadding::Nop),
+        _ => None,
+    }
+}
+
+#[inline]
+fn unit_padding_to_str(padding: ObjUnitPadding) -> &'static str {
+    match padding {
+        ObjUnitPadding::Zero => "zero",
+        ObjUnitPadding::Nop => "nop",
+    }
+}
+
+/// Whether the unit name is a glob pattern (e.g. `JSystem/*`).
+#[inline]
+fn is_unit_pattern(name: &str) -> bool { name.contains(|c: char| matches!(c, '*' | '?' | '[')) }
+
 fn parse_section_line(captures: Captures, state: &SplitState) -> Result<SplitLine> {
     if matches!(state, SplitState::Sections(_)) {
         let name = &captures["name"];
@@ -552,6 +1415,8 @@ fn parse_section_line(captures: Captures, state: &SplitState) -> Result<SplitLin
         align: None,
         common: false,
         rename: None,
+        pad: None,
+        end_align: None,
         skip: false,
     };
 
@@ -562,6 +1427,12 @@ fn parse_section_line(captures: Captures, state: &SplitState) -> Result<SplitLin
                 "end" => end = Some(parse_hex(value)?),
                 "align" => section.align = Some(parse_hex(value)?),
                 "rename" => section.rename = Some(value.to_string()),
+                "pad" => section.pad = Some(parse_hex(value)?),
+                "end_align" => {
+                    let align = parse_hex(value)?;
+                    ensure!(align.is_power_of_two(), "Invalid end_align {}", value);
+                    section.end_align = Some(align);
+                }
                 _ => bail!("Unknown split attribute '{attr}'"),
             }
         } else {
@@ -590,14 +1461,17 @@ enum SplitState {
     None,
     Sections(usize),
     Unit(String),
+    UnitPattern,
 }
 
 pub fn apply_splits_file<P>(path: P, obj: &mut ObjInfo) -> Result<Option<FileReadInfo>>
 where P: AsRef<Path> {
-    Ok(if path.as_ref().is_file() {
+    let path = path.as_ref();
+    Ok(if path.is_file() {
         let file = map_file(path)?;
         let cached = FileReadInfo::new(&file)?;
-        apply_splits(&mut file.as_reader(), obj)?;
+        apply_splits_inner(&mut file.as_reader(), path.parent(), obj, 0)?;
+        obj.apply_unit_patterns();
         Some(cached)
     } else {
         None
@@ -606,29 +1480,70 @@ where P: AsRef<Path> {
 
 pub fn apply_splits<R>(r: &mut R, obj: &mut ObjInfo) -> Result<()>
 where R: BufRead + ?Sized {
+    apply_splits_inner(r, None, obj, 0)?;
+    obj.apply_unit_patterns();
+    Ok(())
+}
+
+fn apply_splits_inner<R>(
+    r: &mut R,
+    base_dir: Option<&Path>,
+    obj: &mut ObjInfo,
+    depth: usize,
+) -> Result<()>
+where
+    R: BufRead + ?Sized,
+{
     let mut state = SplitState::None;
     for result in r.lines() {
         let line = match result {
             Ok(line) => line,
             Err(e) => return Err(e.into()),
         };
-        let split_line = parse_split_line(&line, &state)?;
+        let split_line = parse_split_line(&line, base_dir, &state)?;
         match (&mut state, split_line) {
+            (_, SplitLine::Include(include_path)) => {
+                ensure!(
+                    depth < MAX_INCLUDE_DEPTH,
+                    "Include depth exceeded at '{}' (recursive include?)",
+                    include_path.display()
+                );
+                let file = map_file(&include_path).with_context(|| {
+                    format!("Failed to open included splits file '{}'", include_path.display())
+                })?;
+                apply_splits_inner(&mut file.as_reader(), include_path.parent(), obj, depth + 1)?;
+                state = SplitState::None;
+            }
             (
-                SplitState::None | SplitState::Unit(_) | SplitState::Sections(_),
-                SplitLine::Unit(SplitUnit { name, comment_version }),
+                SplitState::None
+                | SplitState::Unit(_)
+                | SplitState::UnitPattern
+                | SplitState::Sections(_),
+                SplitLine::Unit(unit),
             ) => {
-                obj.link_order.push(ObjUnit {
-                    name: name.clone(),
-                    autogenerated: false,
-                    comment_version,
-                });
-                state = SplitState::Unit(name);
+                state = SplitState::Unit(unit.name.clone());
+                obj.link_order.push(unit);
+            }
+            (
+                SplitState::None
+                | SplitState::Unit(_)
+                | SplitState::UnitPattern
+                | SplitState::Sections(_),
+                SplitLine::UnitPattern(pattern),
+            ) => {
+                obj.unit_patterns.push(pattern);
+                state = SplitState::UnitPattern;
             }
             (SplitState::None, SplitLine::UnitSection(SplitSection { name, .. })) => {
                 bail!("Section {} defined outside of unit", name);
             }
-            (SplitState::None | SplitState::Unit(_), SplitLine::SectionsStart) => {
+            (SplitState::UnitPattern, SplitLine::UnitSection(SplitSection { name, .. })) => {
+                bail!("Section {} defined under unit pattern", name);
+            }
+            (
+                SplitState::None | SplitState::Unit(_) | SplitState::UnitPattern,
+                SplitLine::SectionsStart,
+            ) => {
                 state = SplitState::Sections(0);
             }
             (SplitState::Sections(index), SplitLine::Section(SectionDef { name, kind, align })) => {
@@ -661,6 +1576,8 @@ where R: BufRead + ?Sized {
                     align,
                     common,
                     rename,
+                    pad,
+                    end_align,
                     skip,
                 }),
             ) => {
@@ -694,6 +1611,8 @@ where R: BufRead + ?Sized {
                     autogenerated: false,
                     skip,
                     rename,
+                    pad,
+                    end_align,
                 });
             }
             _ => {}
@@ -704,7 +1623,8 @@ where R: BufRead + ?Sized {
 
 pub fn read_splits_sections<P>(path: P) -> Result<Option<Vec<SectionDef>>>
 where P: AsRef<Path> {
-    if !path.as_ref().is_file() {
+    let path = path.as_ref();
+    if !path.is_file() {
         return Ok(None);
     }
     let file = map_file(path)?;
@@ -716,7 +1636,7 @@ where P: AsRef<Path> {
             Ok(line) => line,
             Err(e) => return Err(e.into()),
         };
-        let split_line = parse_split_line(&line, &state)?;
+        let split_line = parse_split_line(&line, path.parent(), &state)?;
         match (&mut state, split_line) {
             (SplitState::None | SplitState::Unit(_), SplitLine::SectionsStart) => {
                 state = SplitState::Sections(0);
@@ -741,3 +1661,1170 @@ where P: AsRef<Path> {
         Ok(Some(sections))
     }
 }
+
+/// Whether the text is a symbols file, rather than a splits file.
+pub fn is_symbols_text(text: &str) -> bool { text.lines().any(|line| SYMBOL_LINE.is_match(line)) }
+
+/// Formats a symbols file in the canonical style: attributes in a fixed order, addresses in
+/// hex, and symbols sorted by section and address. Comments move with the symbol after them,
+/// blank lines are removed, and `include` lines stay in place, with sorting only between them.
+/// Sections are ordered by `section_order` (e.g. the `Sections:` of splits.txt), then by first
+/// appearance.
+pub fn format_symbols(text: &str, section_order: &[String]) -> Result<String> {
+    struct Entry<'a> {
+        comments: Vec<&'a str>,
+        section: usize,
+        address: u32,
+        line: String,
+    }
+
+    fn flush(run: &mut Vec<Entry>, out: &mut String) {
+        run.sort_by_key(|e| (e.section, e.address));
+        for entry in run.drain(..) {
+            for comment in entry.comments {
+                out.push_str(comment);
+                out.push('\n');
+            }
+            out.push_str(&entry.line);
+        }
+    }
+
+    let mut out = String::new();
+    let mut sections = section_order.to_vec();
+    let mut run = Vec::new();
+    let mut comments = Vec::new();
+    for (line_idx, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            continue;
+        } else if COMMENT_LINE.is_match(line) {
+            comments.push(line);
+        } else if INCLUDE_LINE.is_match(line) {
+            flush(&mut run, &mut out);
+            for comment in comments.drain(..) {
+                out.push_str(comment);
+                out.push('\n');
+            }
+            out.push_str(line.trim());
+            out.push('\n');
+        } else if let Some(captures) = SYMBOL_LINE.captures(line) {
+            let mut symbol = ObjSymbol {
+                name: SymbolName::new(&captures["name"]),
+                address: parse_hex(&captures["addr"])? as u64,
+                ..Default::default()
+            };
+            if !is_auto_symbol(&symbol) && !symbol.name.starts_with('@') {
+                symbol.source = ObjSymbolSource::Config;
+            }
+            let attrs = captures.name("attrs").map_or("", |m| m.as_str());
+            let symbol_attrs = parse_symbol_attrs(&mut symbol, attrs.trim())
+                .with_context(|| format!("Line {}: '{}'", line_idx + 1, line))?;
+            let section = captures.name("section").map(|m| m.as_str());
+            let section_rank = match section {
+                Some(name) => match sections.iter().position(|s| s == name) {
+                    Some(rank) => rank,
+                    None => {
+                        sections.push(name.to_string());
+                        sections.len() - 1
+                    }
+                },
+                None => usize::MAX,
+            };
+            let mut buf = Vec::new();
+            write!(buf, "{} = ", symbol.name)?;
+            if let Some(section) = section {
+                write!(buf, "{section}:")?;
+            }
+            write!(buf, "{:#010X}; //", symbol.address)?;
+            let force_active = symbol.flags.is_force_active() && !symbol.name.starts_with("..");
+            write_symbol_attrs(
+                &mut buf,
+                &symbol,
+                force_active,
+                symbol_attrs.noreloc,
+                symbol_attrs.unit.as_deref(),
+            )?;
+            writeln!(buf)?;
+            run.push(Entry {
+                comments: take(&mut comments),
+                section: section_rank,
+                address: symbol.address as u32,
+                line: String::from_utf8(buf)?,
+            });
+        } else {
+            bail!("Line {}: failed to parse symbol line '{}'", line_idx + 1, line);
+        }
+    }
+    flush(&mut run, &mut out);
+    for comment in comments {
+        out.push_str(comment);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Formats a splits file in the canonical style: tab-indented sections, attributes in a fixed
+/// order, one blank line between units, and each unit's sections in `Sections:` order.
+/// Comments move with the line after them; units keep their order, as it's the link order.
+pub fn format_splits(text: &str) -> Result<String> {
+    struct Block<'a> {
+        comments: Vec<&'a str>,
+        header: String,
+        is_include: bool,
+        lines: Vec<(Vec<&'a str>, usize, String)>,
+    }
+
+    let no_attrs = ObjUnit::default();
+    let mut blocks = Vec::<Block>::new();
+    let mut sections = Vec::<String>::new();
+    let mut comments = Vec::new();
+    let mut state = SplitState::None;
+    for (line_idx, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        let split_line = parse_split_line(line, None, &state)
+            .with_context(|| format!("Line {}", line_idx + 1))?;
+        let mut buf = Vec::new();
+        let (is_include, rank) = match split_line {
+            SplitLine::None => {
+                if COMMENT_LINE.is_match(line) {
+                    comments.push(line);
+                }
+                continue;
+            }
+            SplitLine::Include(_) => {
+                writeln!(buf, "{}", line.trim())?;
+                state = SplitState::None;
+                (true, None)
+            }
+            SplitLine::SectionsStart => {
+                writeln!(buf, "Sections:")?;
+                state = SplitState::Sections(0);
+                (false, None)
+            }
+            SplitLine::Unit(unit) => {
+                write_unit_attrs(&mut buf, &unit, &no_attrs)?;
+                state = SplitState::Unit(unit.name);
+                (false, None)
+            }
+            SplitLine::UnitPattern(pattern) => {
+                write_unit_pattern(&mut buf, &pattern)?;
+                state = SplitState::UnitPattern;
+                (false, None)
+            }
+            SplitLine::Section(def) => {
+                write!(buf, "\t{:<11}", def.name)?;
+                if let Some(kind) = def.kind {
+                    write!(buf, " type:{}", section_kind_to_str(kind))?;
+                }
+                if let Some(align) = def.align {
+                    write!(buf, " align:{}", align)?;
+                }
+                writeln!(buf)?;
+                sections.push(def.name);
+                (false, Some(0))
+            }
+            SplitLine::UnitSection(split) => {
+                write!(
+                    buf,
+                    "\t{:<11} start:{:#010X} end:{:#010X}",
+                    split.name, split.start, split.end
+                )?;
+                // `common` implies 4-byte alignment
+                if let Some(align) = split.align.filter(|&a| !split.common || a != 4) {
+                    write!(buf, " align:{}", align)?;
+                }
+                if split.common {
+                    write!(buf, " common")?;
+                }
+                if let Some(name) = &split.rename {
+                    write!(buf, " rename:{}", name)?;
+                }
+                if let Some(end_align) = split.end_align {
+                    write!(buf, " end_align:{}", end_align)?;
+                }
+                if let Some(pad) = split.pad {
+                    write!(buf, " pad:{:#X}", pad)?;
+                }
+                if split.skip {
+                    write!(buf, " skip")?;
+                }
+                writeln!(buf)?;
+                (false, Some(sections.iter().position(|s| *s == split.name).unwrap_or(usize::MAX)))
+            }
+        };
+        let formatted = String::from_utf8(buf)?;
+        match rank {
+            Some(rank) => {
+                let Some(block) = blocks.last_mut().filter(|b| !b.is_include) else {
+                    bail!("Line {}: section outside of a unit", line_idx + 1);
+                };
+                block.lines.push((take(&mut comments), rank, formatted));
+            }
+            None => blocks.push(Block {
+                comments: take(&mut comments),
+                header: formatted,
+                is_include,
+                lines: vec![],
+            }),
+        }
+    }
+
+    let mut out = String::new();
+    let mut prev_include = None;
+    for mut block in blocks {
+        // Consecutive includes are kept together
+        if prev_include.is_some_and(|prev| !(prev && block.is_include)) {
+            out.push('\n');
+        }
+        prev_include = Some(block.is_include);
+        for comment in block.comments {
+            out.push_str(comment);
+            out.push('\n');
+        }
+        out.push_str(&block.header);
+        block.lines.sort_by_key(|(_, rank, _)| *rank);
+        for (comments, _, line) in block.lines {
+            for comment in comments {
+                out.push_str(comment);
+                out.push('\n');
+            }
+            out.push_str(&line);
+        }
+    }
+    if !comments.is_empty() && !out.is_empty() {
+        out.push('\n');
+    }
+    for comment in comments {
+        out.push_str(comment);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// A problem found while checking configuration files.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigProblem {
+    pub file: PathBuf,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file.display(), self.line, self.message)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ConfigLocation {
+    file: PathBuf,
+    line: usize,
+}
+
+impl fmt::Display for ConfigLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.line)
+    }
+}
+
+struct CheckedSplit {
+    loc: ConfigLocation,
+    unit: String,
+    section: String,
+    start: u32,
+    end: u32,
+    align: Option<u32>,
+}
+
+struct CheckedSymbol {
+    loc: ConfigLocation,
+    section: Option<String>,
+    symbol: ObjSymbol,
+    unit: Option<String>,
+}
+
+#[derive(Default)]
+struct ConfigChecker {
+    sections: Vec<SectionDef>,
+    units: HashMap<String, ConfigLocation>,
+    splits: Vec<CheckedSplit>,
+    symbols: Vec<CheckedSymbol>,
+    problems: Vec<ConfigProblem>,
+    /// Contents of every file read, for applying fixes.
+    lines: BTreeMap<PathBuf, Vec<String>>,
+    changed: BTreeSet<PathBuf>,
+}
+
+/// The result of [`check_config_files`].
+#[derive(Debug, Clone, Default)]
+pub struct ConfigCheck {
+    /// Problems remaining after any fixes were applied.
+    pub problems: Vec<ConfigProblem>,
+    /// Changes made by fixing, where the message describes the change.
+    pub fixes: Vec<ConfigProblem>,
+}
+
+/// Function sizes found by function detection, by section name and address.
+pub type FunctionSizes = HashMap<(String, u32), u32>;
+
+/// Checks a splits and symbols file for consistency without loading the object they describe.
+/// Results are sorted by file and line.
+///
+/// With `fix`, safe fixes are applied and the changed files written back before checking:
+/// entries are re-sorted, function sizes are clamped to the detected sizes in `fix` and to the
+/// next function and padded to 4 bytes, and invalid alignments are replaced with the largest
+/// alignment the address allows.
+pub fn check_config_files(
+    splits_path: Option<&Path>,
+    symbols_path: Option<&Path>,
+    fix: Option<&FunctionSizes>,
+) -> Result<ConfigCheck> {
+    let read = || -> Result<ConfigChecker> {
+        let mut checker = ConfigChecker::default();
+        if let Some(path) = splits_path.filter(|p| p.is_file()) {
+            checker.read_splits(path, 0)?;
+        }
+        if let Some(path) = symbols_path.filter(|p| p.is_file()) {
+            checker.read_symbols(path, 0)?;
+        }
+        Ok(checker)
+    };
+    let mut checker = read()?;
+    let mut fixes = vec![];
+    if let Some(detected) = fix {
+        fixes = checker.fix(detected);
+        if !checker.changed.is_empty() {
+            checker.write_changes()?;
+            checker = read()?;
+        }
+    }
+    checker.check();
+    let mut problems = checker.problems;
+    problems.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
+    fixes.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
+    Ok(ConfigCheck { problems, fixes })
+}
+
+/// Sets a `key:value` attribute in a config line, appending it if not present.
+fn set_line_attr(line: &str, key: &str, value: &str) -> String {
+    // Symbol attributes follow a comment marker
+    let attrs_start = line.find("//").map_or(0, |i| i + 2);
+    let prefix = format!("{key}:");
+    let (head, attrs) = line.split_at(attrs_start);
+    let mut found = false;
+    let attrs = attrs
+        .split(' ')
+        .map(|attr| {
+            if attr.trim_start().starts_with(&prefix) {
+                found = true;
+                let indent = &attr[..attr.len() - attr.trim_start().len()];
+                format!("{indent}{prefix}{value}")
+            } else {
+                attr.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    if found {
+        format!("{head}{attrs}")
+    } else {
+        format!("{} {prefix}{value}", line.trim_end())
+    }
+}
+
+/// The largest power of two not above `align` that `address` is aligned to.
+fn fixed_align(address: u32, align: u32) -> u32 {
+    let max = if align.is_power_of_two() { align } else { align.next_power_of_two() >> 1 };
+    max.max(1).min(1 << address.trailing_zeros().min(31))
+}
+
+impl ConfigChecker {
+    fn report(&mut self, loc: &ConfigLocation, message: impl Into<String>) {
+        self.problems.push(ConfigProblem {
+            file: loc.file.clone(),
+            line: loc.line,
+            message: message.into(),
+        });
+    }
+
+    /// Reports an `include` that can't be followed, returning whether it can.
+    fn check_include(&mut self, loc: &ConfigLocation, path: &Path, depth: usize) -> bool {
+        if depth >= MAX_INCLUDE_DEPTH {
+            self.report(loc, format!("Include depth exceeded at '{}'", path.display()));
+            false
+        } else if !path.is_file() {
+            self.report(loc, format!("Included file '{}' not found", path.display()));
+            false
+        } else {
+            true
+        }
+    }
+
+    fn read_splits(&mut self, path: &Path, depth: usize) -> Result<()> {
+        let file = map_file(path)?;
+        let mut state = SplitState::None;
+        for (line_idx, result) in file.as_reader().lines().enumerate() {
+            let line = result?;
+            let loc = ConfigLocation { file: path.to_path_buf(), line: line_idx + 1 };
+            self.lines.entry(path.to_path_buf()).or_default().push(line.clone());
+            let split_line = match parse_split_line(&line, path.parent(), &state) {
+                Ok(split_line) => split_line,
+                Err(e) => {
+                    self.report(&loc, format!("{e:#}"));
+                    continue;
+                }
+            };
+            match (&mut state, split_line) {
+                (_, SplitLine::Include(include_path)) => {
+                    if self.check_include(&loc, &include_path, depth) {
+                        self.read_splits(&include_path, depth + 1)?;
+                    }
+                    state = SplitState::None;
+                }
+                (_, SplitLine::Unit(unit)) => {
+                    if let Some(prev) = self.units.get(&unit.name) {
+                        let message = format!("Unit {} already defined at {}", unit.name, prev);
+                        self.report(&loc, message);
+                    } else {
+                        self.units.insert(unit.name.clone(), loc);
+                    }
+                    state = SplitState::Unit(unit.name);
+                }
+                (_, SplitLine::UnitPattern(_)) => state = SplitState::UnitPattern,
+                (_, SplitLine::SectionsStart) => state = SplitState::Sections(0),
+                (SplitState::Sections(index), SplitLine::Section(def)) => {
+                    self.sections.push(def);
+                    *index += 1;
+                }
+                (SplitState::Unit(unit), SplitLine::UnitSection(split)) => {
+                    self.splits.push(CheckedSplit {
+                        loc,
+                        unit: unit.clone(),
+                        section: split.name,
+                        start: split.start,
+                        end: split.end,
+                        align: split.align,
+                    });
+                }
+                (SplitState::UnitPattern, SplitLine::UnitSection(split)) => {
+                    self.report(&loc, format!("Section {} defined under unit pattern", split.name));
+                }
+                (_, SplitLine::UnitSection(split)) => {
+                    self.report(&loc, format!("Section {} defined outside of unit", split.name));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn read_symbols(&mut self, path: &Path, depth: usize) -> Result<()> {
+        let file = map_file(path)?;
+        for (line_idx, result) in file.as_reader().lines().enumerate() {
+            let line = result?;
+            let loc = ConfigLocation { file: path.to_path_buf(), line: line_idx + 1 };
+            self.lines.entry(path.to_path_buf()).or_default().push(line.clone());
+            if let Some(include_path) = parse_include_line(&line, path.parent()) {
+                if self.check_include(&loc, &include_path, depth) {
+                    self.read_symbols(&include_path, depth + 1)?;
+                }
+                continue;
+            }
+            if line.trim().is_empty() || COMMENT_LINE.is_match(&line) {
+                continue;
+            }
+            let Some(captures) = SYMBOL_LINE.captures(&line) else {
+                self.report(&loc, format!("Failed to parse symbol line '{line}'"));
+                continue;
+            };
+            let address = match parse_hex(&captures["addr"]) {
+                Ok(address) => address,
+                Err(e) => {
+                    self.report(&loc, format!("Invalid symbol address: {e}"));
+                    continue;
+                }
+            };
+            let mut symbol = ObjSymbol {
+                name: SymbolName::new(&captures["name"]),
+                address: address as u64,
+                ..Default::default()
+            };
+            let mut unit = None;
+            if let Some(attrs) = captures.name("attrs") {
+                match parse_symbol_attrs(&mut symbol, attrs.as_str()) {
+                    Ok(attrs) => unit = attrs.unit,
+                    Err(e) => {
+                        self.report(&loc, format!("{e:#}"));
+                        continue;
+                    }
+                }
+            }
+            let section = captures
+                .name("section")
+                .map(|m| m.as_str())
+                .filter(|&s| s != "ABS")
+                .map(String::from);
+            self.symbols.push(CheckedSymbol { loc, section, symbol, unit });
+        }
+        Ok(())
+    }
+
+    fn check(&mut self) {
+        let mut problems = vec![];
+        let section_known = |name: &str| {
+            // Without a section list, any name may be discovered from the object
+            self.sections.is_empty() || self.sections.iter().any(|s| s.name == name)
+        };
+
+        for split in &self.splits {
+            let name = format!("Split {} {}", split.unit, split.section);
+            if !section_known(&split.section) {
+                problems.push((&split.loc, format!("Section {} is not defined", split.section)));
+            }
+            if split.end < split.start {
+                problems.push((
+                    &split.loc,
+                    format!(
+                        "{} end {:#010X} is before start {:#010X}",
+                        name, split.end, split.start
+                    ),
+                ));
+            }
+            if let Some(align) = split.align {
+                if !align.is_power_of_two() {
+                    problems.push((
+                        &split.loc,
+                        format!("{} alignment {} is not a power of two", name, align),
+                    ));
+                } else if split.start & (align - 1) != 0 {
+                    problems.push((
+                        &split.loc,
+                        format!("{} start {:#010X} is not aligned to {}", name, split.start, align),
+                    ));
+                }
+            }
+        }
+
+        // Overlapping splits
+        let mut splits_by_section = BTreeMap::<&str, Vec<&CheckedSplit>>::new();
+        for split in &self.splits {
+            splits_by_section.entry(split.section.as_str()).or_default().push(split);
+        }
+        for splits in splits_by_section.values_mut() {
+            splits.sort_by_key(|s| (s.start, s.end));
+            let mut prev: Option<&CheckedSplit> = None;
+            for &split in splits.iter() {
+                if let Some(prev) = prev.filter(|p| split.start < p.end) {
+                    problems.push((
+                        &split.loc,
+                        format!(
+                            "Split {} {} {:#010X}..{:#010X} overlaps {} {:#010X}..{:#010X} ({})",
+                            split.unit,
+                            split.section,
+                            split.start,
+                            split.end,
+                            prev.unit,
+                            prev.start,
+                            prev.end,
+                            prev.loc
+                        ),
+                    ));
+                }
+                if prev.map_or(true, |p| split.end > p.end) {
+                    prev = Some(split);
+                }
+            }
+        }
+
+        let mut names = HashMap::<&str, &ConfigLocation>::new();
+        let mut unit_names = HashMap::<(&str, &str), &ConfigLocation>::new();
+        for CheckedSymbol { loc, section, symbol, unit } in &self.symbols {
+            if let Some(section) = section.as_deref().filter(|&s| !section_known(s)) {
+                problems.push((loc, format!("Section {} is not defined", section)));
+            }
+            if let Some(unit) = unit.as_deref() {
+                let in_unit = self.splits.iter().any(|s| {
+                    s.unit == unit
+                        && Some(&s.section) == section.as_ref()
+                        && (s.start..s.end).contains(&(symbol.address as u32))
+                });
+                if !self.units.contains_key(unit) {
+                    problems.push((
+                        loc,
+                        format!("Symbol {} is local to undefined unit {}", symbol.name, unit),
+                    ));
+                } else if !in_unit {
+                    problems.push((
+                        loc,
+                        format!(
+                            "Symbol {} at {:#010X} is outside of unit {}",
+                            symbol.name, symbol.address, unit
+                        ),
+                    ));
+                }
+                match unit_names.entry((unit, symbol.name.as_str())) {
+                    hash_map::Entry::Occupied(e) => problems.push((
+                        loc,
+                        format!(
+                            "Duplicate symbol {} in unit {} (also defined at {})",
+                            symbol.name,
+                            unit,
+                            e.get()
+                        ),
+                    )),
+                    hash_map::Entry::Vacant(e) => {
+                        e.insert(loc);
+                    }
+                }
+            }
+            if !symbol.flags.is_local() && !symbol.flags.is_weak() {
+                match names.entry(symbol.name.as_str()) {
+                    hash_map::Entry::Occupied(e) => problems.push((
+                        loc,
+                        format!("Duplicate symbol {} (also defined at {})", symbol.name, e.get()),
+                    )),
+                    hash_map::Entry::Vacant(e) => {
+                        e.insert(loc);
+                    }
+                }
+            }
+            if let Some(align) = symbol.align {
+                if !align.is_power_of_two() {
+                    problems.push((
+                        loc,
+                        format!(
+                            "Symbol {} alignment {:#X} is not a power of two",
+                            symbol.name, align
+                        ),
+                    ));
+                } else if symbol.address as u32 & (align - 1) != 0 {
+                    problems.push((
+                        loc,
+                        format!(
+                            "Symbol {} at {:#010X} is not aligned to {:#X}",
+                            symbol.name, symbol.address, align
+                        ),
+                    ));
+                }
+            }
+            if symbol.kind == ObjSymbolKind::Function {
+                if symbol.address & 3 != 0 {
+                    problems.push((
+                        loc,
+                        format!(
+                            "Function {} at {:#010X} is not 4-byte aligned",
+                            symbol.name, symbol.address
+                        ),
+                    ));
+                }
+                if symbol.size_known && symbol.size & 3 != 0 {
+                    problems.push((
+                        loc,
+                        format!(
+                            "Function {} size {:#X} is not a multiple of 4",
+                            symbol.name, symbol.size
+                        ),
+                    ));
+                }
+            }
+
+            // Symbols must not extend past the end of the unit they start in
+            let (Some(section), true) = (section, symbol.size_known && symbol.size > 0) else {
+                continue;
+            };
+            let start = symbol.address as u32;
+            let end = start.saturating_add(symbol.size as u32);
+            if let Some(split) = self
+                .splits
+                .iter()
+                .find(|s| &s.section == section && s.start <= start && start < s.end)
+            {
+                if end > split.end {
+                    problems.push((
+                        loc,
+                        format!(
+                            "Symbol {} ({:#010X}..{:#010X}) extends past unit {} ({:#010X})",
+                            symbol.name, start, end, split.unit, split.end
+                        ),
+                    ));
+                }
+            }
+        }
+
+        // Overlapping functions
+        let mut functions_by_section = BTreeMap::<&str, Vec<&CheckedSymbol>>::new();
+        for checked in &self.symbols {
+            if let (Some(section), ObjSymbolKind::Function, true) =
+                (&checked.section, checked.symbol.kind, checked.symbol.size_known)
+            {
+                functions_by_section.entry(section.as_str()).or_default().push(checked);
+            }
+        }
+        for functions in functions_by_section.values_mut() {
+            functions.sort_by_key(|s| s.symbol.address);
+            for pair in functions.windows(2) {
+                let (prev, next) = (&pair[0].symbol, &pair[1].symbol);
+                if next.address < prev.address + prev.size {
+                    problems.push((
+                        &pair[1].loc,
+                        format!(
+                            "Function {} at {:#010X} overlaps {} ({:#010X}..{:#010X}, {})",
+                            next.name,
+                            next.address,
+                            prev.name,
+                            prev.address,
+                            prev.address + prev.size,
+                            pair[0].loc
+                        ),
+                    ));
+                }
+            }
+        }
+
+        let problems =
+            problems.into_iter().map(|(loc, message)| (loc.clone(), message)).collect::<Vec<_>>();
+        for (loc, message) in problems {
+            self.report(&loc, message);
+        }
+    }
+}
+
+impl ConfigChecker {
+    fn section_rank(&self, name: Option<&str>) -> usize {
+        match name {
+            Some(name) => self.sections.iter().position(|s| s.name == name).unwrap_or(usize::MAX),
+            None => usize::MAX,
+        }
+    }
+
+    fn set_attr(&mut self, loc: &ConfigLocation, key: &str, value: &str) {
+        if let Some(line) =
+            self.lines.get_mut(&loc.file).and_then(|lines| lines.get_mut(loc.line - 1))
+        {
+            *line = set_line_attr(line, key, value);
+            self.changed.insert(loc.file.clone());
+        }
+    }
+
+    /// Sorts a run of consecutive lines by key, returning whether the order changed.
+    fn sort_lines(&mut self, file: &Path, line_keys: &[(usize, (usize, u32))]) -> bool {
+        let Some(lines) = self.lines.get_mut(file) else {
+            return false;
+        };
+        let mut order = (0..line_keys.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| line_keys[i].1);
+        if order.iter().enumerate().all(|(i, &j)| i == j) {
+            return false;
+        }
+        let old = line_keys.iter().map(|&(line, _)| lines[line - 1].clone()).collect::<Vec<_>>();
+        for (&(line, _), &j) in line_keys.iter().zip(&order) {
+            lines[line - 1] = old[j].clone();
+        }
+        self.changed.insert(file.to_path_buf());
+        true
+    }
+
+    /// Applies safe fixes to the file contents, returning a description of each change.
+    fn fix(&mut self, detected: &FunctionSizes) -> Vec<ConfigProblem> {
+        let mut fixes = vec![];
+        let mut edits = vec![];
+
+        // Clamp function sizes to the detected size and the next function, then pad them to
+        // 4 bytes
+        let mut functions_by_section = BTreeMap::<&str, Vec<&CheckedSymbol>>::new();
+        for checked in &self.symbols {
+            if let (Some(section), ObjSymbolKind::Function, true) =
+                (&checked.section, checked.symbol.kind, checked.symbol.size_known)
+            {
+                functions_by_section.entry(section.as_str()).or_default().push(checked);
+            }
+        }
+        for (&section, functions) in functions_by_section.iter_mut() {
+            functions.sort_by_key(|s| s.symbol.address);
+            for (i, checked) in functions.iter().enumerate() {
+                let symbol = &checked.symbol;
+                let address = symbol.address as u32;
+                let limit = functions[i + 1..]
+                    .iter()
+                    .map(|s| s.symbol.address as u32)
+                    .find(|&next| next > address);
+                let mut size = symbol.size as u32;
+                let detected = detected.get(&(section.to_string(), address)).filter(|&&s| s > 0);
+                if let Some(&detected) = detected {
+                    size = size.min(detected);
+                }
+                if let Some(limit) = limit.filter(|&limit| address + size > limit) {
+                    size = limit - address;
+                }
+                if size & 3 != 0 && limit.map_or(true, |limit| address + align_up(size, 4) <= limit)
+                {
+                    size = align_up(size, 4);
+                }
+                if size != symbol.size as u32 {
+                    let message = format!(
+                        "Changed size of function {} from {:#X} to {:#X}",
+                        symbol.name, symbol.size, size
+                    );
+                    edits.push((checked.loc.clone(), "size", format!("{:#X}", size), message));
+                }
+            }
+        }
+
+        // Replace invalid alignments
+        for CheckedSymbol { loc, symbol, .. } in &self.symbols {
+            let Some(align) = symbol.align else {
+                continue;
+            };
+            let fixed = fixed_align(symbol.address as u32, align);
+            if fixed != align {
+                let message = format!(
+                    "Changed alignment of symbol {} from {} to {}",
+                    symbol.name, align, fixed
+                );
+                edits.push((loc.clone(), "align", fixed.to_string(), message));
+            }
+        }
+        for split in &self.splits {
+            let Some(align) = split.align else {
+                continue;
+            };
+            let fixed = fixed_align(split.start, align);
+            if fixed != align {
+                let message = format!(
+                    "Changed alignment of split {} {} from {} to {}",
+                    split.unit, split.section, align, fixed
+                );
+                edits.push((split.loc.clone(), "align", fixed.to_string(), message));
+            }
+        }
+
+        for (loc, key, value, message) in edits {
+            self.set_attr(&loc, key, &value);
+            fixes.push(ConfigProblem { file: loc.file, line: loc.line, message });
+        }
+
+        // Sort runs of consecutive symbols by section and address
+        let mut runs = Vec::<(PathBuf, Vec<(usize, (usize, u32))>)>::new();
+        for CheckedSymbol { loc, section, symbol, .. } in &self.symbols {
+            let key = (self.section_rank(section.as_deref()), symbol.address as u32);
+            match runs.last_mut() {
+                Some((file, run))
+                    if file == &loc.file && run.last().is_some_and(|&(l, _)| l + 1 == loc.line) =>
+                {
+                    run.push((loc.line, key))
+                }
+                _ => runs.push((loc.file.clone(), vec![(loc.line, key)])),
+            }
+        }
+        // Sort the sections within each unit, when the section order is known
+        if !self.sections.is_empty() {
+            let mut prev: Option<&CheckedSplit> = None;
+            for split in &self.splits {
+                let key = (self.section_rank(Some(&split.section)), 0);
+                let consecutive = prev.is_some_and(|p| {
+                    p.loc.file == split.loc.file
+                        && p.loc.line + 1 == split.loc.line
+                        && p.unit == split.unit
+                });
+                match runs.last_mut() {
+                    Some((_, run)) if consecutive => run.push((split.loc.line, key)),
+                    _ => runs.push((split.loc.file.clone(), vec![(split.loc.line, key)])),
+                }
+                prev = Some(split);
+            }
+        }
+        for (file, run) in runs {
+            if run.len() > 1 && self.sort_lines(&file, &run) {
+                fixes.push(ConfigProblem {
+                    file,
+                    line: run[0].0,
+                    message: format!("Sorted {} entries", run.len()),
+                });
+            }
+        }
+        fixes
+    }
+
+    fn write_changes(&self) -> Result<()> {
+        for path in &self.changed {
+            let mut contents = self.lines[path].join("\n");
+            contents.push('\n');
+            fs::write(path, contents)
+                .with_context(|| format!("Failed to write '{}'", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::ObjArchitecture;
+
+    #[test]
+    fn set_line_attrs() {
+        let line = "fn_80001000 = .text:0x80001000; // type:function size:0x10 scope:global";
+        assert_eq!(
+            set_line_attr(line, "size", "0x20"),
+            "fn_80001000 = .text:0x80001000; // type:function size:0x20 scope:global"
+        );
+        assert_eq!(
+            set_line_attr(line, "align", "8"),
+            "fn_80001000 = .text:0x80001000; // type:function size:0x10 scope:global align:8"
+        );
+        // Only attributes after the comment marker, and only whole keys
+        assert_eq!(
+            set_line_attr("lbl_80001000 = .data:0x80001000; // aligned:4 ", "align", "4"),
+            "lbl_80001000 = .data:0x80001000; // aligned:4 align:4"
+        );
+        // Split lines have no comment marker
+        assert_eq!(
+            set_line_attr("\t.text       start:0x80003100 end:0x80005000", "end", "0x80006000"),
+            "\t.text       start:0x80003100 end:0x80006000"
+        );
+    }
+
+    #[test]
+    fn fix_function_sizes() {
+        let path = PathBuf::from("symbols.txt");
+        let lines = [
+            "fn_80003000 = .text:0x80003000; // type:function size:0x40",
+            "fn_80003020 = .text:0x80003020; // type:function size:0x12",
+            "fn_80003040 = .text:0x80003040; // type:function size:0x20",
+        ];
+        let mut checker = ConfigChecker::default();
+        checker.lines.insert(path.clone(), lines.iter().map(|l| l.to_string()).collect());
+        for (line, (address, size)) in
+            [(0x80003000, 0x40), (0x80003020, 0x12), (0x80003040, 0x20)].into_iter().enumerate()
+        {
+            let name = format!("fn_{address:08X}");
+            checker.symbols.push(CheckedSymbol {
+                loc: ConfigLocation { file: path.clone(), line: line + 1 },
+                section: Some(".text".to_string()),
+                symbol: function(&name, address, size),
+                unit: None,
+            });
+        }
+        let detected = FunctionSizes::from([
+            ((".text".to_string(), 0x80003000), 0x18),
+            ((".text".to_string(), 0x80003040), 0x30),
+        ]);
+        let fixes = checker.fix(&detected);
+        let messages = fixes.iter().map(|f| f.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, [
+            "Changed size of function fn_80003000 from 0x40 to 0x18",
+            "Changed size of function fn_80003020 from 0x12 to 0x14",
+        ]);
+        assert_eq!(checker.lines[&path], [
+            "fn_80003000 = .text:0x80003000; // type:function size:0x18",
+            "fn_80003020 = .text:0x80003020; // type:function size:0x14",
+            lines[2],
+        ]);
+    }
+
+    #[test]
+    fn fixed_aligns() {
+        assert_eq!(fixed_align(0x80001000, 8), 8);
+        assert_eq!(fixed_align(0x80001004, 8), 4);
+        assert_eq!(fixed_align(0x80001000, 12), 8);
+        assert_eq!(fixed_align(0x80001002, 0), 1);
+        assert_eq!(fixed_align(0, 16), 16);
+    }
+
+    fn empty_obj() -> ObjInfo {
+        ObjInfo::new(
+            ObjKind::Executable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            vec![],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn unit_patterns() {
+        assert!(is_unit_pattern("JSystem/*"));
+        assert!(is_unit_pattern("os/OS?.c"));
+        assert!(is_unit_pattern("[ab].c"));
+        assert!(!is_unit_pattern("JSystem/JKernel/JKRHeap.cpp"));
+
+        let text = "\
+JSystem/*: comment:0 library:JSystem
+JSystem/JKernel/*: comment:1 cflags:\"-O4,p -inline auto\"
+JSystem/JKernel/JKRHeap.cpp: comment:2
+main.c:
+";
+        let mut obj = empty_obj();
+        apply_splits(&mut text.as_bytes(), &mut obj).unwrap();
+        assert_eq!(obj.unit_patterns.len(), 2);
+        assert_eq!(obj.link_order.len(), 2);
+
+        // Explicit attributes win, then the first matching pattern
+        let heap = &obj.link_order[0];
+        assert_eq!(heap.comment_version, Some(2));
+        assert_eq!(heap.cflags.as_deref(), Some("-O4,p -inline auto"));
+        assert_eq!(heap.library.as_deref(), Some("JSystem"));
+        assert_eq!(obj.link_order[1], ObjUnit { name: "main.c".to_string(), ..Default::default() });
+
+        let inherited = obj.inherited_unit("JSystem/JKernel/JKRHeap.cpp");
+        assert_eq!(inherited.comment_version, Some(0));
+        assert_eq!(inherited.cflags.as_deref(), Some("-O4,p -inline auto"));
+        assert_eq!(obj.inherited_unit("JSystem/J3D/J3DModel.cpp").cflags, None);
+
+        let source = apply_splits(&mut "os/*: source:os/OS.c\n".as_bytes(), &mut empty_obj());
+        assert!(source.is_err());
+    }
+
+    #[test]
+    fn split_includes() {
+        let dir = std::env::temp_dir().join(format!("dtk-config-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("libs")).unwrap();
+        fs::write(dir.join("splits.txt"), "include libs/os.txt\nmain.c:\n").unwrap();
+        fs::write(dir.join("libs/os.txt"), "os/*: library:os\ninclude \"units.txt\"\n").unwrap();
+        fs::write(dir.join("libs/units.txt"), "os/OS.c:\n").unwrap();
+        fs::write(dir.join("a.txt"), "include b.txt\n").unwrap();
+        fs::write(dir.join("b.txt"), "include a.txt\n").unwrap();
+
+        let mut obj = empty_obj();
+        let result = apply_splits_file(dir.join("splits.txt"), &mut obj);
+        let paths = config_include_paths(dir.join("splits.txt"));
+        let cycle = apply_splits_file(dir.join("a.txt"), &mut empty_obj());
+        let cycle_paths = config_include_paths(dir.join("a.txt"));
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(result.unwrap().is_some());
+        let names = obj.link_order.iter().map(|u| u.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["os/OS.c", "main.c"]);
+        assert_eq!(obj.link_order[0].library.as_deref(), Some("os"));
+        // Included files are listed after the files they include
+        assert_eq!(paths.unwrap(), [dir.join("libs").join("units.txt"), dir.join("libs/os.txt")]);
+
+        for result in [cycle.map(|_| ()), cycle_paths.map(|_| ())] {
+            let message = format!("{:#}", result.unwrap_err());
+            assert!(message.contains("Include depth exceeded"), "{message}");
+        }
+    }
+
+    fn function(name: &str, address: u64, size: u64) -> ObjSymbol {
+        ObjSymbol {
+            name: name.into(),
+            address,
+            section: Some(0),
+            size,
+            size_known: true,
+            flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
+            kind: ObjSymbolKind::Function,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn symbols_preserving() {
+        let original = "\
+// Functions
+fn_80003100 = .text:0x80003100; // type:function size:0x10 scope:global
+// fn_80003160 is gone
+fn_80003160 = .text:0x80003160; // type:function size:0x10 scope:global
+fn_80003180 = .text:0x80003180; // type:function size:0x10 scope:global
+";
+        let obj = ObjInfo::new(
+            ObjKind::Executable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            vec![
+                function("fn_80003000", 0x80003000, 0x10),
+                function("fn_80003100", 0x80003100, 0x20),
+                function("fn_80003140", 0x80003140, 0x10),
+                function("fn_80003180", 0x80003180, 0x10),
+            ],
+            vec![ObjSection::raw(".text", ObjSectionKind::Code, 0x80003000, vec![0; 0x200])],
+        );
+        let mut out = Vec::new();
+        write_symbols_preserving(&mut out, &obj, original, &HashSet::new()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+// Functions
+fn_80003000 = .text:0x80003000; // type:function size:0x10 scope:global
+fn_80003100 = .text:0x80003100; // type:function size:0x20 scope:global
+fn_80003140 = .text:0x80003140; // type:function size:0x10 scope:global
+// fn_80003160 is gone
+fn_80003180 = .text:0x80003180; // type:function size:0x10 scope:global
+"
+        );
+    }
+
+    #[test]
+    fn splits_preserving() {
+        let original = "\
+// Project splits
+Sections:
+\t.text       type:code align:4
+\t.data       type:data align:8
+
+// Runtime
+a.c:
+\t.text       start:0x80003100 end:0x80003140
+
+// Removed below
+d.c:
+\t.text       start:0x80003140 end:0x80003180
+
+c.c:
+\t.text       start:0x80003180 end:0x800031C0
+\t.data       start:0x80004000 end:0x80004010
+";
+        let mut text = ObjSection::raw(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x100]);
+        text.align = 4;
+        let mut data = ObjSection::raw(".data", ObjSectionKind::Data, 0x80004000, vec![0; 0x10]);
+        data.align = 8;
+        let mut obj = ObjInfo::new(
+            ObjKind::Executable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            vec![],
+            vec![text, data],
+        );
+        for (unit, start, end) in [
+            ("a.c", 0x80003100, 0x80003140),
+            ("b.c", 0x80003140, 0x80003180),
+            ("c.c", 0x80003180, 0x80003200),
+        ] {
+            obj.link_order.push(ObjUnit { name: unit.to_string(), ..Default::default() });
+            obj.sections[0].splits.push(start, ObjSplit {
+                unit: unit.to_string(),
+                end,
+                align: None,
+                common: false,
+                autogenerated: false,
+                skip: false,
+                rename: None,
+                pad: None,
+                end_align: None,
+            });
+        }
+        let mut out = Vec::new();
+        write_splits_preserving(&mut out, &obj, false, original, &HashSet::new()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+// Project splits
+Sections:
+\t.text       type:code align:4
+\t.data       type:data align:8
+
+// Runtime
+a.c:
+\t.text       start:0x80003100 end:0x80003140
+
+b.c:
+\t.text       start:0x80003140 end:0x80003180
+
+// Removed below
+
+c.c:
+\t.text       start:0x80003180 end:0x80003200
+"
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use crate::obj::{ObjInfo, ObjReloc, ObjRelocKind, ObjSymbol};
+
+/// The bits of an instruction or data word a relocation writes to.
+pub fn reloc_mask(kind: ObjRelocKind) -> u32 {
+    match kind {
+        ObjRelocKind::Absolute
+        | ObjRelocKind::PpcRel32
+        | ObjRelocKind::PpcDtpMod32
+        | ObjRelocKind::PpcDtpRel32 => 0xFFFFFFFF,
+        ObjRelocKind::PpcAddr16Hi
+        | ObjRelocKind::PpcAddr16Ha
+        | ObjRelocKind::PpcAddr16Lo
+        | ObjRelocKind::PpcRel16Hi
+        | ObjRelocKind::PpcRel16Ha
+        | ObjRelocKind::PpcRel16Lo => 0xFFFF,
+        ObjRelocKind::PpcRel24 => 0x03FFFFFC,
+        ObjRelocKind::PpcRel14 => 0xFFFC,
+        ObjRelocKind::PpcEmbSda21 => 0x1FFFFF,
+    }
+}
+
+/// Whether a symbol's name was generated by the compiler or by analysis, such as `@123`,
+/// `.L_80001234` or `lbl_80001234`.
+pub fn is_generated_name(symbol: &ObjSymbol) -> bool {
+    symbol.name.starts_with('@')
+        || symbol.name.starts_with(".L")
+        || symbol.name.starts_with("lbl_")
+        || symbol.name.is_empty()
+}
+
+/// A relocation target, compared by name. Generated names differ between the original and
+/// the build, so they only compare by relocation kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelocTarget<'a> {
+    pub kind: ObjRelocKind,
+    pub name: Option<&'a str>,
+    pub addend: i64,
+}
+
+impl<'a> RelocTarget<'a> {
+    pub fn new(obj: &'a ObjInfo, reloc: &ObjReloc) -> Self {
+        let symbol = &obj.symbols[reloc.target_symbol];
+        let generated = is_generated_name(symbol);
+        Self {
+            kind: reloc.kind,
+            name: (!generated).then_some(symbol.name.as_str()),
+            addend: if generated { 0 } else { reloc.addend },
+        }
+    }
+}
+
+/// Levenshtein distance between two sequences.
+pub fn edit_distance<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, x) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(x != y);
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+/// Percentage of matching items, from the edit distance between both sequences.
+pub fn match_percent<T: PartialEq>(a: &[T], b: &[T]) -> f64 {
+    if a == b {
+        return 100.0;
+    }
+    let len = a.len().max(b.len());
+    (1.0 - edit_distance(a, b) as f64 / len as f64) * 100.0
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DiffOp {
+    Equal(usize, usize),
+    Replace(usize, usize),
+    /// Only in the first sequence.
+    Delete(usize),
+    /// Only in the second sequence.
+    Insert(usize),
+}
+
+/// Aligns both sequences by their edit distance. On ties, replacing is preferred over
+/// inserting and deleting. The number of operations other than [`DiffOp::Equal`] is the
+/// [`edit_distance`].
+pub fn align<T: PartialEq>(a: &[T], b: &[T]) -> Vec<DiffOp> {
+    let width = b.len() + 1;
+    let mut cost = vec![0u32; (a.len() + 1) * width];
+    for (j, cost) in cost.iter_mut().take(width).enumerate() {
+        *cost = j as u32;
+    }
+    for i in 1..=a.len() {
+        cost[i * width] = i as u32;
+        for j in 1..=b.len() {
+            let substitution = cost[(i - 1) * width + j - 1] + u32::from(a[i - 1] != b[j - 1]);
+            let delete = cost[(i - 1) * width + j] + 1;
+            let insert = cost[i * width + j - 1] + 1;
+            cost[i * width + j] = substitution.min(delete).min(insert);
+        }
+    }
+
+    let mut ops = vec![];
+    let (mut i, mut j) = (a.len(), b.len());
+    while i > 0 || j > 0 {
+        let current = cost[i * width + j];
+        if i > 0 && j > 0 {
+            let equal = a[i - 1] == b[j - 1];
+            if current == cost[(i - 1) * width + j - 1] + u32::from(!equal) {
+                i -= 1;
+                j -= 1;
+                ops.push(if equal { DiffOp::Equal(i, j) } else { DiffOp::Replace(i, j) });
+                continue;
+            }
+        }
+        if i > 0 && current == cost[(i - 1) * width + j] + 1 {
+            i -= 1;
+            ops.push(DiffOp::Delete(i));
+        } else {
+            j -= 1;
+            ops.push(DiffOp::Insert(j));
+        }
+    }
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn align_matches_edit_distance() {
+        let cases: [(&[u8], &[u8]); 4] =
+            [(b"kitten", b"sitting"), (b"", b"abc"), (b"abc", b""), (b"flaw", b"lawn")];
+        for (a, b) in cases {
+            let differences =
+                align(a, b).iter().filter(|op| !matches!(op, DiffOp::Equal(..))).count();
+            assert_eq!(differences, edit_distance(a, b));
+        }
+        assert_eq!(edit_distance(b"kitten", b"sitting"), 3);
+        assert_eq!(align(b"ab", b"b"), [DiffOp::Delete(0), DiffOp::Equal(1, 0)]);
+    }
+
+    #[test]
+    fn match_percent_of_empty() {
+        assert_eq!(match_percent::<u8>(&[], &[]), 100.0);
+        assert_eq!(match_percent(b"ab", b"ab"), 100.0);
+        assert_eq!(match_percent(b"ab", b"ac"), 50.0);
+    }
+}
@@ -1,7 +1,8 @@
 use std::{
     collections::BTreeMap,
-    io,
-    io::{Cursor, Read, Seek},
+    fmt, io,
+    io::{Cursor, Read, Seek, Write},
+    str::FromStr,
 };
 
 use anyhow::{anyhow, bail, ensure, Result};
@@ -10,12 +11,12 @@ use crate::{
     analysis::cfa::{locate_bss_memsets, locate_sda_bases, SectionAddress},
     array_ref,
     obj::{
-        ObjArchitecture, ObjInfo, ObjKind, ObjSection, ObjSectionKind, ObjSymbol, ObjSymbolFlagSet,
-        ObjSymbolFlags, ObjSymbolKind,
+        AddressSpace, ObjArchitecture, ObjInfo, ObjKind, ObjSection, ObjSectionKind, ObjSymbol,
+        ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind,
     },
     util::{
         alf::{AlfFile, AlfSymbol, ALF_MAGIC},
-        reader::{skip_bytes, Endian, FromReader},
+        reader::{skip_bytes, Endian, FromReader, ToWriter},
     },
 };
 
@@ -59,7 +60,7 @@ pub trait DolLike {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum DolSectionKind {
     Text,
     Data,
@@ -166,6 +167,28 @@ impl FromReader for DolHeader {
     }
 }
 
+impl ToWriter for DolHeader {
+    fn to_writer<W>(&self, writer: &mut W, e: Endian) -> io::Result<()>
+    where W: Write + ?Sized {
+        for value in self.text_offs.iter().chain(&self.data_offs) {
+            value.to_writer(writer, e)?;
+        }
+        for value in self.text_addrs.iter().chain(&self.data_addrs) {
+            value.to_writer(writer, e)?;
+        }
+        for value in self.text_sizes.iter().chain(&self.data_sizes) {
+            value.to_writer(writer, e)?;
+        }
+        self.bss_addr.to_writer(writer, e)?;
+        self.bss_size.to_writer(writer, e)?;
+        self.entry_point.to_writer(writer, e)?;
+        [0u8; 0x1C].to_writer(writer, e)?; // padding
+        Ok(())
+    }
+
+    fn write_size(&self) -> usize { Self::STATIC_SIZE }
+}
+
 impl DolLike for DolFile {
     fn sections(&self) -> &[DolSection] { &self.sections }
 
@@ -174,6 +197,296 @@ impl DolLike for DolFile {
     fn has_unified_bss(&self) -> bool { true }
 }
 
+/// Alignment of DOL section addresses, sizes and file offsets, as required by DVD reads.
+pub const DOL_SECTION_ALIGN: u32 = 32;
+
+/// A section slot in the DOL header: `text0`-`text6`, `data0`-`data10` or `bss`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DolSlot {
+    pub kind: DolSectionKind,
+    pub index: usize,
+}
+
+impl FromStr for DolSlot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, index) = if s == "bss" {
+            (DolSectionKind::Bss, "0")
+        } else if let Some(index) = s.strip_prefix("text") {
+            (DolSectionKind::Text, index)
+        } else if let Some(index) = s.strip_prefix("data") {
+            (DolSectionKind::Data, index)
+        } else {
+            return Err(format!("Invalid DOL section '{s}': expected textN, dataN or bss"));
+        };
+        let max = match kind {
+            DolSectionKind::Text => MAX_TEXT_SECTIONS,
+            DolSectionKind::Data => MAX_DATA_SECTIONS,
+            DolSectionKind::Bss => 1,
+        };
+        match index.parse::<usize>() {
+            Ok(index) if index < max => Ok(Self { kind, index }),
+            _ => Err(format!("Invalid DOL section '{s}'")),
+        }
+    }
+}
+
+impl fmt::Display for DolSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            DolSectionKind::Text => write!(f, "text{}", self.index),
+            DolSectionKind::Data => write!(f, "data{}", self.index),
+            DolSectionKind::Bss => write!(f, "bss"),
+        }
+    }
+}
+
+/// A DOL's header and section data, for adding and resizing sections. Writing lays the
+/// sections out again in their original file order, so the header always matches the data.
+#[derive(Debug, Clone)]
+pub struct DolImage {
+    pub header: DolHeader,
+    /// Section data in file order.
+    sections: Vec<(DolSlot, Vec<u8>)>,
+}
+
+impl DolImage {
+    pub fn new(buf: &[u8]) -> Result<Self> {
+        let header = DolHeader::from_reader(&mut Cursor::new(buf), Endian::Big)?;
+        let mut sections = vec![];
+        for kind in [DolSectionKind::Text, DolSectionKind::Data] {
+            let (offs, sizes) = match kind {
+                DolSectionKind::Text => (&header.text_offs[..], &header.text_sizes[..]),
+                _ => (&header.data_offs[..], &header.data_sizes[..]),
+            };
+            for (index, (&offset, &size)) in offs.iter().zip(sizes).enumerate() {
+                if size == 0 {
+                    continue;
+                }
+                let slot = DolSlot { kind, index };
+                let data = offset
+                    .checked_add(size)
+                    .and_then(|end| buf.get(offset as usize..end as usize))
+                    .ok_or_else(|| {
+                        anyhow!("DOL section {} extends past the end of the file", slot)
+                    })?;
+                sections.push((offset, slot, data.to_vec()));
+            }
+        }
+        sections.sort_by_key(|&(offset, slot, _)| (offset, slot));
+        let image = Self {
+            header,
+            sections: sections.into_iter().map(|(_, slot, data)| (slot, data)).collect(),
+        };
+        let bss = DolSlot { kind: DolSectionKind::Bss, index: 0 };
+        for slot in image.sections.iter().map(|(slot, _)| *slot).chain([bss]) {
+            let (address, size) = image.slot_range(slot);
+            ensure!(
+                address.checked_add(size).is_some(),
+                "DOL section {} at {:#010X} with size {:#X} extends past the address space",
+                slot,
+                address,
+                size
+            );
+        }
+        Ok(image)
+    }
+
+    /// Address and size of a slot in the header.
+    fn slot_range(&self, slot: DolSlot) -> (u32, u32) {
+        match slot.kind {
+            DolSectionKind::Text => {
+                (self.header.text_addrs[slot.index], self.header.text_sizes[slot.index])
+            }
+            DolSectionKind::Data => {
+                (self.header.data_addrs[slot.index], self.header.data_sizes[slot.index])
+            }
+            DolSectionKind::Bss => (self.header.bss_addr, self.header.bss_size),
+        }
+    }
+
+    /// Address range of a slot, or `None` if it's unused. Ranges past the end of the address
+    /// space are rejected when reading, appending and resizing.
+    pub fn range(&self, slot: DolSlot) -> Option<(u32, u32)> {
+        let (address, size) = self.slot_range(slot);
+        if size == 0 {
+            return None;
+        }
+        Some((address, address.checked_add(size)?))
+    }
+
+    /// End address of a section at `address` with `size`, if it fits in the address space.
+    fn end_address(slot: DolSlot, address: u32, size: u32) -> Result<u32> {
+        address.checked_add(size).ok_or_else(|| {
+            anyhow!(
+                "{} at {:#010X} with size {:#X} would extend past the address space",
+                slot,
+                address,
+                size
+            )
+        })
+    }
+
+    fn set_range(&mut self, slot: DolSlot, address: u32, size: u32) {
+        match slot.kind {
+            DolSectionKind::Text => {
+                self.header.text_addrs[slot.index] = address;
+                self.header.text_sizes[slot.index] = size;
+            }
+            DolSectionKind::Data => {
+                self.header.data_addrs[slot.index] = address;
+                self.header.data_sizes[slot.index] = size;
+            }
+            DolSectionKind::Bss => {
+                self.header.bss_addr = address;
+                self.header.bss_size = size;
+            }
+        }
+    }
+
+    /// Fails if a text or data section would overlap another one. Overlapping the BSS range
+    /// only warns, since it commonly spans the small data sections.
+    fn check_range(&self, slot: DolSlot, start: u32, end: u32) -> Result<()> {
+        for (other, _) in &self.sections {
+            if *other == slot {
+                continue;
+            }
+            if let Some((other_start, other_end)) = self.range(*other) {
+                ensure!(
+                    end <= other_start || start >= other_end,
+                    "{} at {:#010X}-{:#010X} would overlap {} at {:#010X}-{:#010X}",
+                    slot,
+                    start,
+                    end,
+                    other,
+                    other_start,
+                    other_end
+                );
+            }
+        }
+        if let Some((bss_start, bss_end)) =
+            self.range(DolSlot { kind: DolSectionKind::Bss, index: 0 })
+        {
+            if slot.kind != DolSectionKind::Bss && start < bss_end && end > bss_start {
+                log::warn!(
+                    "{} at {:#010X}-{:#010X} overlaps the BSS range {:#010X}-{:#010X}",
+                    slot,
+                    start,
+                    end,
+                    bss_start,
+                    bss_end
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The lowest aligned address past every section, including BSS.
+    pub fn free_address(&self) -> Result<u32> {
+        let end = self.sections.iter().filter_map(|(slot, _)| self.range(*slot)).map(|(_, e)| e);
+        let bss_end = self.range(DolSlot { kind: DolSectionKind::Bss, index: 0 }).map(|(_, e)| e);
+        let end = end.chain(bss_end).max().unwrap_or(0x80003100);
+        checked_align_up(end)
+            .ok_or_else(|| anyhow!("No free address past the end of the DOL at {:#010X}", end))
+    }
+
+    /// Adds a text or data section in the first unused slot, at `address` or otherwise past
+    /// every other section. Returns the slot and address.
+    pub fn append_section(
+        &mut self,
+        kind: DolSectionKind,
+        mut data: Vec<u8>,
+        address: Option<u32>,
+    ) -> Result<(DolSlot, u32)> {
+        ensure!(kind != DolSectionKind::Bss, "Can't append a BSS section");
+        ensure!(!data.is_empty(), "Section data is empty");
+        let max = if kind == DolSectionKind::Text { MAX_TEXT_SECTIONS } else { MAX_DATA_SECTIONS };
+        let slot = (0..max)
+            .map(|index| DolSlot { kind, index })
+            .find(|&slot| self.range(slot).is_none())
+            .ok_or_else(|| anyhow!("No free {:?} section slot in the DOL header", kind))?;
+        let address = match address {
+            Some(address) => address,
+            None => self.free_address()?,
+        };
+        ensure!(
+            address % DOL_SECTION_ALIGN == 0,
+            "Section address {:#010X} is not aligned to {:#X}",
+            address,
+            DOL_SECTION_ALIGN
+        );
+        let size = u32::try_from(data.len())
+            .ok()
+            .and_then(checked_align_up)
+            .ok_or_else(|| anyhow!("Section data of {:#X} bytes is too large", data.len()))?;
+        data.resize(size as usize, 0);
+        let end = Self::end_address(slot, address, size)?;
+        self.check_range(slot, address, end)?;
+        self.set_range(slot, address, size);
+        self.sections.push((slot, data));
+        Ok((slot, address))
+    }
+
+    /// Grows (padding with zeroes) or shrinks a section. A size of 0 removes it.
+    pub fn resize_section(&mut self, slot: DolSlot, size: u32) -> Result<()> {
+        let (start, _) = self.range(slot).ok_or_else(|| anyhow!("{} is not in use", slot))?;
+        let end = Self::end_address(slot, start, size)?;
+        if slot.kind == DolSectionKind::Bss {
+            self.set_range(slot, start, size);
+            return Ok(());
+        }
+        ensure!(
+            size % DOL_SECTION_ALIGN == 0,
+            "Size {:#X} of {} is not a multiple of {:#X}",
+            size,
+            slot,
+            DOL_SECTION_ALIGN
+        );
+        if size == 0 {
+            self.sections.retain(|(s, _)| *s != slot);
+            self.set_range(slot, 0, 0);
+            return Ok(());
+        }
+        self.check_range(slot, start, end)?;
+        if let Some((_, data)) = self.sections.iter_mut().find(|(s, _)| *s == slot) {
+            data.resize(size as usize, 0);
+        }
+        self.set_range(slot, start, size);
+        Ok(())
+    }
+
+    /// Writes the DOL, with each section at the next aligned file offset.
+    pub fn write(&self) -> Result<Vec<u8>> {
+        let mut header = self.header.clone();
+        header.text_offs = [0; MAX_TEXT_SECTIONS];
+        header.data_offs = [0; MAX_DATA_SECTIONS];
+        let mut out = vec![0u8; DolHeader::STATIC_SIZE];
+        for (slot, data) in &self.sections {
+            let offset = u32::try_from(out.len())
+                .ok()
+                .and_then(checked_align_up)
+                .ok_or_else(|| anyhow!("DOL is too large to place {}", slot))?;
+            out.resize(offset as usize, 0);
+            out.extend_from_slice(data);
+            match slot.kind {
+                DolSectionKind::Text => header.text_offs[slot.index] = offset,
+                DolSectionKind::Data => header.data_offs[slot.index] = offset,
+                DolSectionKind::Bss => unreachable!(),
+            }
+        }
+        let mut header_data = Vec::with_capacity(DolHeader::STATIC_SIZE);
+        header.to_writer(&mut header_data, Endian::Big)?;
+        out[..header_data.len()].copy_from_slice(&header_data);
+        Ok(out)
+    }
+}
+
+/// Aligns up to [`DOL_SECTION_ALIGN`], or `None` past the end of the address space.
+fn checked_align_up(value: u32) -> Option<u32> {
+    Some(value.checked_add(DOL_SECTION_ALIGN - 1)? & !(DOL_SECTION_ALIGN - 1))
+}
+
 fn read_u32(buf: &[u8], dol: &dyn DolLike, addr: u32) -> Result<u32> {
     Ok(u32::from_be_bytes(dol.virtual_data_at(buf, addr, 4)?.try_into()?))
 }
@@ -280,6 +593,13 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
         None => None,
     };
 
+    if rom_copy_info_end.is_some() {
+        validate_rom_copy_info(dol.as_ref(), &rom_sections);
+    }
+    if bss_init_info_end.is_some() {
+        validate_bss_init_info(bss_section, &bss_sections);
+    }
+
     // Locate _eti_init_info
     let num_text_sections =
         dol.sections().iter().filter(|section| section.kind == DolSectionKind::Text).count();
@@ -553,6 +873,28 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
         // Assume the original ELF section index is +1
         // ELF files start with a NULL section
         section.elf_index = idx + 1;
+
+        // Wii DOLs may place sections in MEM2, but a section can't span address spaces
+        let space = section.address_space();
+        if section.size > 0 {
+            let end = (section.address + section.size - 1) as u32;
+            ensure!(
+                AddressSpace::of(end) == space,
+                "Section {} ({:#010X}-{:#010X}) spans multiple address spaces",
+                section.name,
+                section.address,
+                section.address + section.size
+            );
+        }
+        if matches!(space, AddressSpace::Hardware | AddressSpace::Unknown)
+            || AddressSpace::is_uncached(section.address as u32)
+        {
+            log::warn!(
+                "Section {} @ {:#010X} is outside of cached MEM1/MEM2",
+                section.name,
+                section.address
+            );
+        }
     }
 
     // Create object
@@ -565,13 +907,30 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
     );
     obj.entry = Some(dol.entry_point() as u64);
 
+    // Name the entry point. Symbols from a map or symbols file will replace this.
+    if let Ok((section_index, section)) = obj.sections.at_address(dol.entry_point()) {
+        if section.kind == ObjSectionKind::Code {
+            obj.add_symbol(
+                ObjSymbol {
+                    name: "__start".into(),
+                    address: dol.entry_point() as u64,
+                    section: Some(section_index),
+                    flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
+                    kind: ObjSymbolKind::Function,
+                    ..Default::default()
+                },
+                false,
+            )?;
+        }
+    }
+
     // Generate _rom_copy_info symbol
     if let (Some(rom_copy_info_addr), Some(rom_copy_info_end)) =
         (rom_copy_info_addr, rom_copy_info_end)
     {
         obj.add_symbol(
             ObjSymbol {
-                name: "_rom_copy_info".to_string(),
+                name: "_rom_copy_info".into(),
                 address: rom_copy_info_addr as u64,
                 section: init_section_index,
                 size: (rom_copy_info_end - rom_copy_info_addr) as u64,
@@ -590,7 +949,7 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
     {
         obj.add_symbol(
             ObjSymbol {
-                name: "_bss_init_info".to_string(),
+                name: "_bss_init_info".into(),
                 address: bss_init_info_addr as u64,
                 section: init_section_index,
                 size: (bss_init_info_end - bss_init_info_addr) as u64,
@@ -607,7 +966,7 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
     if let Some((eti_init_info_addr, eti_init_info_end)) = eti_init_info_range {
         obj.add_symbol(
             ObjSymbol {
-                name: "_eti_init_info".to_string(),
+                name: "_eti_init_info".into(),
                 address: eti_init_info_addr as u64,
                 section: extabindex_section,
                 size: (eti_init_info_end - eti_init_info_addr) as u64,
@@ -652,7 +1011,7 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
             }
             obj.add_symbol(
                 ObjSymbol {
-                    name: format!("@eti_{:08X}", entry.address),
+                    name: format!("@eti_{:08X}", entry.address).into(),
                     address: entry.address as u64,
                     section: Some(extabindex_section_index),
                     size: 12,
@@ -677,7 +1036,7 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
             };
             obj.add_symbol(
                 ObjSymbol {
-                    name: format!("@etb_{:08X}", addr),
+                    name: format!("@etb_{:08X}", addr).into(),
                     address: addr as u64,
                     section: Some(extab_section_index),
                     size: size as u64,
@@ -723,30 +1082,7 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
 
     // Locate _SDA2_BASE_ & _SDA_BASE_
     match locate_sda_bases(&mut obj) {
-        Ok(true) => {
-            let sda2_base = obj.sda2_base.unwrap();
-            let sda_base = obj.sda_base.unwrap();
-            obj.add_symbol(
-                ObjSymbol {
-                    name: "_SDA2_BASE_".to_string(),
-                    address: sda2_base as u64,
-                    size_known: true,
-                    flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
-                    ..Default::default()
-                },
-                true,
-            )?;
-            obj.add_symbol(
-                ObjSymbol {
-                    name: "_SDA_BASE_".to_string(),
-                    address: sda_base as u64,
-                    size_known: true,
-                    flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
-                    ..Default::default()
-                },
-                true,
-            )?;
-        }
+        Ok(true) => add_sda_base_symbols(&mut obj)?,
         Ok(false) => {
             log::warn!("Unable to locate SDA bases");
         }
@@ -763,6 +1099,53 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
     Ok(obj)
 }
 
+/// Defines `_SDA2_BASE_` and `_SDA_BASE_` at the small data base addresses.
+fn add_sda_base_symbols(obj: &mut ObjInfo) -> Result<()> {
+    for (name, address) in [("_SDA2_BASE_", obj.sda2_base), ("_SDA_BASE_", obj.sda_base)] {
+        let Some(address) = address else { continue };
+        obj.add_symbol(
+            ObjSymbol {
+                name: name.into(),
+                address: address as u64,
+                size_known: true,
+                flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
+                ..Default::default()
+            },
+            true,
+        )?;
+    }
+    Ok(())
+}
+
+/// Overrides the small data base addresses recovered from `__init_registers` with configured
+/// values, warning where they disagree.
+pub fn apply_sda_bases(
+    obj: &mut ObjInfo,
+    sda_base: Option<u32>,
+    sda2_base: Option<u32>,
+) -> Result<()> {
+    for (name, configured, detected) in
+        [("_SDA_BASE_", sda_base, obj.sda_base), ("_SDA2_BASE_", sda2_base, obj.sda2_base)]
+    {
+        if let (Some(configured), Some(detected)) = (configured, detected) {
+            if configured != detected {
+                log::warn!(
+                    "Configured {} {:#010X} differs from the detected {:#010X}",
+                    name,
+                    configured,
+                    detected
+                );
+            }
+        }
+    }
+    if sda_base.is_none() && sda2_base.is_none() {
+        return Ok(());
+    }
+    obj.sda_base = sda_base.or(obj.sda_base);
+    obj.sda2_base = sda2_base.or(obj.sda2_base);
+    add_sda_base_symbols(obj)
+}
+
 #[derive(Debug, Clone)]
 struct EtiInitInfo {
     eti_start: u32,
@@ -786,6 +1169,66 @@ struct EtiEntry {
     extab_addr: u32,
 }
 
+/// Checks the `_rom_copy_info` entries against the DOL's text and data sections. Each entry
+/// should start a section, and the section should only be padded past the entry's size.
+fn validate_rom_copy_info(dol: &dyn DolLike, rom_sections: &BTreeMap<u32, u32>) {
+    let mut prev_end = 0;
+    for (&addr, &size) in rom_sections {
+        if addr < prev_end {
+            log::warn!("_rom_copy_info entry {:#010X} overlaps the previous entry", addr);
+        }
+        prev_end = addr + size;
+        let Some(section) = dol
+            .sections()
+            .iter()
+            .find(|section| section.kind != DolSectionKind::Bss && section.address == addr)
+        else {
+            log::warn!(
+                "_rom_copy_info entry {:#010X}-{:#010X} doesn't start a DOL section",
+                addr,
+                addr + size
+            );
+            continue;
+        };
+        if size > section.size {
+            log::warn!(
+                "_rom_copy_info entry {:#010X} ({:#X}) is larger than DOL section {} ({:#X})",
+                addr,
+                size,
+                section.index,
+                section.size
+            );
+        } else if section.size - size >= 32 {
+            log::warn!(
+                "DOL section {} ({:#010X}) has {:#X} bytes past its _rom_copy_info size",
+                section.index,
+                addr,
+                section.size - size
+            );
+        }
+    }
+}
+
+/// Checks the `_bss_init_info` entries against the DOL's BSS range. Entries must be in range
+/// (which is enforced when adding the BSS sections), and should cover it up to alignment.
+fn validate_bss_init_info(bss_section: &DolSection, bss_sections: &BTreeMap<u32, u32>) {
+    let mut prev_end = bss_section.address;
+    for (&addr, &size) in bss_sections {
+        if addr < prev_end {
+            log::warn!("_bss_init_info entry {:#010X} overlaps the previous entry", addr);
+        }
+        prev_end = prev_end.max(addr + size);
+    }
+    let bss_end = bss_section.address + bss_section.size;
+    if prev_end < bss_end && bss_end - prev_end >= 32 {
+        log::warn!(
+            "_bss_init_info ends at {:#010X}, but DOL BSS extends to {:#010X}",
+            prev_end,
+            bss_end
+        );
+    }
+}
+
 fn read_eti_init_info(buf: &[u8], dol: &dyn DolLike, addr: u32) -> Result<EtiInitInfo> {
     let eti_start = read_u32(buf, dol, addr)?;
     let eti_end = read_u32(buf, dol, addr + 4)?;
@@ -825,3 +1268,100 @@ fn validate_eti_init_info(
     }
     Ok(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot(s: &str) -> DolSlot { s.parse().unwrap() }
+
+    /// A DOL with 0x20-byte text0 and data0 sections and a 0x40-byte BSS after them.
+    fn dol() -> Vec<u8> {
+        let mut header = DolHeader {
+            text_offs: [0; MAX_TEXT_SECTIONS],
+            data_offs: [0; MAX_DATA_SECTIONS],
+            text_addrs: [0; MAX_TEXT_SECTIONS],
+            data_addrs: [0; MAX_DATA_SECTIONS],
+            text_sizes: [0; MAX_TEXT_SECTIONS],
+            data_sizes: [0; MAX_DATA_SECTIONS],
+            bss_addr: 0x80003140,
+            bss_size: 0x40,
+            entry_point: 0x80003100,
+        };
+        (header.text_offs[0], header.text_addrs[0], header.text_sizes[0]) =
+            (0x100, 0x80003100, 0x20);
+        (header.data_offs[0], header.data_addrs[0], header.data_sizes[0]) =
+            (0x120, 0x80003120, 0x20);
+        let mut out = vec![];
+        header.to_writer(&mut out, Endian::Big).unwrap();
+        out.extend([0x11; 0x20]);
+        out.extend([0x22; 0x20]);
+        out
+    }
+
+    #[test]
+    fn parse_slots() {
+        assert_eq!(slot("text0"), DolSlot { kind: DolSectionKind::Text, index: 0 });
+        assert_eq!(slot("data10"), DolSlot { kind: DolSectionKind::Data, index: 10 });
+        assert_eq!(slot("bss"), DolSlot { kind: DolSectionKind::Bss, index: 0 });
+        for s in ["text6", "data10", "bss"] {
+            assert_eq!(slot(s).to_string(), s);
+        }
+        for s in ["text7", "data11", "text", "bss0", "sdata0", "text-1"] {
+            assert!(s.parse::<DolSlot>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn image_round_trip() {
+        let buf = dol();
+        let image = DolImage::new(&buf).unwrap();
+        assert_eq!(image.range(slot("text0")), Some((0x80003100, 0x80003120)));
+        assert_eq!(image.range(slot("bss")), Some((0x80003140, 0x80003180)));
+        assert_eq!(image.range(slot("data1")), None);
+        assert_eq!(image.write().unwrap(), buf);
+    }
+
+    #[test]
+    fn image_edit() {
+        let mut image = DolImage::new(&dol()).unwrap();
+        // Placed after the BSS, padded to the section alignment
+        let (data1, address) =
+            image.append_section(DolSectionKind::Data, vec![0x33; 5], None).unwrap();
+        assert_eq!((data1, address), (slot("data1"), 0x80003180));
+
+        let out = DolImage::new(&image.write().unwrap()).unwrap();
+        assert_eq!(out.header.data_offs[1], 0x140);
+        assert_eq!(out.range(data1), Some((0x80003180, 0x800031A0)));
+        let mut expected = vec![0u8; 0x20];
+        expected[..5].fill(0x33);
+        assert_eq!(&image.write().unwrap()[0x140..0x160], expected);
+
+        // text0 can't grow into data0, or to an unaligned size, but the BSS can grow past it
+        assert!(image.resize_section(slot("text0"), 0x40).is_err());
+        assert!(image.resize_section(slot("text0"), 0x30).is_err());
+        image.resize_section(slot("bss"), 0x80).unwrap();
+        image.resize_section(data1, 0).unwrap();
+        assert_eq!(image.range(data1), None);
+        assert_eq!(image.write().unwrap().len(), 0x140);
+        assert!(image.append_section(DolSectionKind::Bss, vec![0; 0x20], None).is_err());
+    }
+
+    #[test]
+    fn image_overflow() {
+        let mut buf = dol();
+        // data0 address wraps around
+        buf[0x64..0x68].copy_from_slice(&0xFFFFFFF0u32.to_be_bytes());
+        assert!(DolImage::new(&buf).is_err());
+
+        let mut buf = dol();
+        // data0 file range wraps around
+        buf[0x1C..0x20].copy_from_slice(&0xFFFFFFF0u32.to_be_bytes());
+        assert!(DolImage::new(&buf).is_err());
+
+        let mut image = DolImage::new(&dol()).unwrap();
+        let result = image.append_section(DolSectionKind::Text, vec![0; 0x40], Some(0xFFFFFFE0));
+        assert!(result.is_err());
+        assert!(image.resize_section(slot("bss"), 0xFFFFFFE0).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     cmp::max,
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     convert::TryFrom,
     fmt::{Display, Formatter, Write},
     io::{BufRead, Cursor, Seek, SeekFrom},
@@ -1518,6 +1518,247 @@ pub fn union_def_string(info: &DwarfInfo, typedefs: &TypedefMap, t: &UnionType)
     Ok(out)
 }
 
+/// `name` as a C identifier, e.g. `TVec<int>` as `TVec_int_` and `A::B` as `A__B`.
+pub fn c_identifier(name: &str) -> String {
+    let mut out = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect::<String>();
+    if !out.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        out.insert(0, '_');
+    }
+    out
+}
+
+/// Whether a structure, union or enumeration name is a compiler-generated (`@123`) or missing
+/// name, and the type has to be defined where it's used.
+fn is_anonymous(name: &Option<String>) -> bool {
+    name.as_ref().map_or(true, |name| name.starts_with('@'))
+}
+
+fn c_fund_type_string(ft: FundType) -> Result<TypeString> {
+    let (prefix, suffix) = match ft {
+        FundType::WideChar => ("unsigned short", ""),
+        FundType::Boolean => ("unsigned char", ""),
+        FundType::Int128 => ("unsigned int", "[4]"),
+        FundType::Vec2x32Float => ("float", "[2]"),
+        _ => (ft.name()?, ""),
+    };
+    Ok(TypeString { prefix: prefix.to_string(), suffix: suffix.to_string(), ..Default::default() })
+}
+
+/// Like [`type_string`], but as C: structures, unions and enumerations are referred to by
+/// their bare name (declared with a typedef of the same name), anonymous ones are defined
+/// inline, and references become pointers.
+pub fn c_type_string(info: &DwarfInfo, typedefs: &TypedefMap, t: &Type) -> Result<TypeString> {
+    let str = match t.kind {
+        TypeKind::Fundamental(ft) => c_fund_type_string(ft)?,
+        TypeKind::UserDefined(key) => {
+            if let Some(&td_key) = typedefs.get(&key).and_then(|v| v.first()) {
+                let td_name = info
+                    .tags
+                    .get(&td_key)
+                    .and_then(|tag| tag.string_attribute(AttributeKind::Name))
+                    .ok_or_else(|| anyhow!("Failed to locate typedef {}", td_key))?;
+                TypeString { prefix: c_identifier(td_name), ..Default::default() }
+            } else {
+                let tag = info
+                    .tags
+                    .get(&key)
+                    .ok_or_else(|| anyhow!("Failed to locate user defined type {}", key))?;
+                c_ud_type_string(info, typedefs, &ud_type(info, tag)?)?
+            }
+        }
+    };
+    let modifiers = t
+        .modifiers
+        .iter()
+        .map(|&m| if m == Modifier::ReferenceTo { Modifier::PointerTo } else { m })
+        .collect::<Vec<_>>();
+    apply_modifiers(str, &modifiers)
+}
+
+fn c_ud_type_string(
+    info: &DwarfInfo,
+    typedefs: &TypedefMap,
+    t: &UserDefinedType,
+) -> Result<TypeString> {
+    let prefix = match t {
+        UserDefinedType::Array(t) => {
+            let mut out = c_type_string(info, typedefs, &t.element_type)?;
+            for dim in &t.dimensions {
+                match dim.size {
+                    None => out.suffix.insert_str(0, "[]"),
+                    Some(size) => out.suffix = format!("[{}]{}", size, out.suffix),
+                };
+            }
+            return Ok(out);
+        }
+        UserDefinedType::Subroutine(t) => {
+            let mut out = c_type_string(info, typedefs, &t.return_type)?;
+            out.suffix = format!("({}){}", c_parameters_string(info, typedefs, t)?, out.suffix);
+            return Ok(out);
+        }
+        // Pointers to member functions are a 12-byte structure, pointers to data members an
+        // offset
+        UserDefinedType::PtrToMember(t) => {
+            let function = match t.kind.kind {
+                TypeKind::UserDefined(key) => info.tags.get(&key).is_some_and(|tag| {
+                    matches!(tag.kind, TagKind::SubroutineType | TagKind::GlobalSubroutine)
+                }),
+                TypeKind::Fundamental(_) => false,
+            };
+            let suffix = if function { "[3]" } else { "" };
+            return Ok(TypeString {
+                prefix: "int".to_string(),
+                suffix: suffix.to_string(),
+                ..Default::default()
+            });
+        }
+        UserDefinedType::Structure(t) if is_anonymous(&t.name) => {
+            c_struct_def_string(info, typedefs, t)?
+        }
+        UserDefinedType::Union(t) if is_anonymous(&t.name) => {
+            c_union_def_string(info, typedefs, t)?
+        }
+        UserDefinedType::Enumeration(t) if is_anonymous(&t.name) => c_enum_def_string(t)?,
+        _ => c_identifier(&t.name().unwrap_or_default()),
+    };
+    Ok(TypeString { prefix, ..Default::default() })
+}
+
+fn c_parameters_string(
+    info: &DwarfInfo,
+    typedefs: &TypedefMap,
+    t: &SubroutineType,
+) -> Result<String> {
+    if t.parameters.is_empty() {
+        return Ok(if t.var_args {
+            "...".to_string()
+        } else if t.prototyped {
+            "void".to_string()
+        } else {
+            String::new()
+        });
+    }
+    let mut out = String::new();
+    for (idx, parameter) in t.parameters.iter().enumerate() {
+        if idx > 0 {
+            out.push_str(", ");
+        }
+        let ts = c_type_string(info, typedefs, &parameter.kind)?;
+        match &parameter.name {
+            Some(name) => write!(out, "{} {}{}", ts.prefix, c_identifier(name), ts.suffix)?,
+            None => write!(out, "{}{}", ts.prefix, ts.suffix)?,
+        }
+    }
+    if t.var_args {
+        out.push_str(", ...");
+    }
+    Ok(out)
+}
+
+/// A structure definition in C. Base classes become leading members named `base`, ordered
+/// by offset with the other members.
+pub fn c_struct_def_string(
+    info: &DwarfInfo,
+    typedefs: &TypedefMap,
+    t: &StructureType,
+) -> Result<String> {
+    let mut out = "struct".to_string();
+    if !is_anonymous(&t.name) {
+        write!(out, " {}", c_identifier(t.name.as_deref().unwrap_or_default()))?;
+    }
+    out.push_str(" {\n");
+    let mut fields = vec![];
+    for (idx, base) in t.bases.iter().enumerate() {
+        let name = if t.bases.len() == 1 { "base".to_string() } else { format!("base{}", idx) };
+        fields.push((base.offset, name, &base.base_type, None));
+    }
+    for member in &t.members {
+        let bits = member.bit.as_ref().map(|bit| bit.bit_size);
+        fields.push((member.offset, c_identifier(&member.name), &member.kind, bits));
+    }
+    // Members are in declaration order, which bit fields sharing an offset keep
+    fields.sort_by_key(|&(offset, ..)| offset);
+    let mut var_out = String::new();
+    for (_, name, kind, bits) in fields {
+        let ts = c_type_string(info, typedefs, kind)?;
+        write!(var_out, "{} {}{}", ts.prefix, name, ts.suffix)?;
+        if let Some(bits) = bits {
+            write!(var_out, " : {}", bits)?;
+        }
+        writeln!(var_out, ";")?;
+    }
+    // C doesn't allow empty structures
+    if var_out.is_empty() {
+        writeln!(var_out, "char pad[{}];", t.byte_size.unwrap_or(1).max(1))?;
+    }
+    out.push_str(&indent_all_by(4, var_out));
+    out.push('}');
+    Ok(out)
+}
+
+pub fn c_union_def_string(
+    info: &DwarfInfo,
+    typedefs: &TypedefMap,
+    t: &UnionType,
+) -> Result<String> {
+    let mut out = "union".to_string();
+    if !is_anonymous(&t.name) {
+        write!(out, " {}", c_identifier(t.name.as_deref().unwrap_or_default()))?;
+    }
+    out.push_str(" {\n");
+    let mut var_out = String::new();
+    for member in &t.members {
+        let ts = c_type_string(info, typedefs, &member.kind)?;
+        writeln!(var_out, "{} {}{};", ts.prefix, c_identifier(&member.name), ts.suffix)?;
+    }
+    if var_out.is_empty() {
+        writeln!(var_out, "char pad[{}];", t.byte_size.max(1))?;
+    }
+    out.push_str(&indent_all_by(4, var_out));
+    out.push('}');
+    Ok(out)
+}
+
+pub fn c_enum_def_string(t: &EnumerationType) -> Result<String> {
+    let mut out = "enum".to_string();
+    if !is_anonymous(&t.name) {
+        write!(out, " {}", c_identifier(t.name.as_deref().unwrap_or_default()))?;
+    }
+    out.push_str(" {\n");
+    for member in &t.members {
+        writeln!(out, "    {} = {},", c_identifier(&member.name), member.value)?;
+    }
+    out.push('}');
+    Ok(out)
+}
+
+/// A declaration of a variable or function for a C context, by its mangled name if it has
+/// one. `None` if the name isn't a C identifier, e.g. a C++ operator without a mangled name.
+pub fn c_declaration_string(
+    info: &DwarfInfo,
+    typedefs: &TypedefMap,
+    tag_type: &TagType,
+) -> Result<Option<(String, String)>> {
+    let (name, ts) = match tag_type {
+        TagType::Variable(v) => {
+            (v.mangled_name.as_ref().or(v.name.as_ref()), c_type_string(info, typedefs, &v.kind)?)
+        }
+        TagType::UserDefined(UserDefinedType::Subroutine(t)) => {
+            let mut ts = c_type_string(info, typedefs, &t.return_type)?;
+            ts.suffix = format!("({}){}", c_parameters_string(info, typedefs, t)?, ts.suffix);
+            (t.mangled_name.as_ref().or(t.name.as_ref()), ts)
+        }
+        _ => return Ok(None),
+    };
+    let Some(name) = name.filter(|name| c_identifier(name) == **name) else {
+        return Ok(None);
+    };
+    Ok(Some((name.clone(), format!("{} {}{};", ts.prefix, name, ts.suffix))))
+}
+
 pub fn process_offset(block: &[u8], e: Endian) -> Result<u32> {
     if block.len() == 6 && block[0] == LocationOp::Const as u8 && block[5] == LocationOp::Add as u8
     {
@@ -2581,3 +2822,252 @@ fn process_variable_tag(info: &DwarfInfo, tag: &Tag) -> Result<VariableTag> {
     let local = tag.kind == TagKind::LocalVariable;
     Ok(VariableTag { name, mangled_name, kind, address, local })
 }
+
+/// A named type definition. MWCC emits types again in every unit using them, so types are
+/// identified across units by name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TypeId {
+    Struct(String),
+    Class(String),
+    Union(String),
+    Enum(String),
+    Typedef(String),
+}
+
+impl TypeId {
+    /// The type a root tag defines, if it's a named type.
+    pub fn of_tag(tag_type: &TagType) -> Option<TypeId> {
+        match tag_type {
+            TagType::Typedef(t) => Some(TypeId::Typedef(t.name.clone())),
+            TagType::UserDefined(t) => Self::of_ud_type(t),
+            TagType::Variable(_) => None,
+        }
+    }
+
+    /// Compiler-generated names (`@123`) are anonymous types, defined where they're used.
+    pub fn of_ud_type(t: &UserDefinedType) -> Option<TypeId> {
+        let id = match t {
+            UserDefinedType::Structure(t) => match t.kind {
+                StructureKind::Struct => TypeId::Struct(t.name.clone()?),
+                StructureKind::Class => TypeId::Class(t.name.clone()?),
+            },
+            UserDefinedType::Union(t) => TypeId::Union(t.name.clone()?),
+            UserDefinedType::Enumeration(t) => TypeId::Enum(t.name.clone()?),
+            _ => return None,
+        };
+        (!id.name().starts_with('@')).then_some(id)
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            TypeId::Struct(name)
+            | TypeId::Class(name)
+            | TypeId::Union(name)
+            | TypeId::Enum(name)
+            | TypeId::Typedef(name) => name,
+        }
+    }
+
+    /// Forward declaration of the type. Enums and typedefs can't be forward declared.
+    pub fn forward_declaration(&self) -> Option<String> {
+        match self {
+            TypeId::Struct(name) => Some(format!("struct {};", name)),
+            TypeId::Class(name) => Some(format!("class {};", name)),
+            TypeId::Union(name) => Some(format!("union {};", name)),
+            TypeId::Enum(_) | TypeId::Typedef(_) => None,
+        }
+    }
+}
+
+/// Named types a tag refers to. Types used by value need their definition, while structures,
+/// classes and unions only used through a pointer or reference need a forward declaration.
+#[derive(Debug, Clone, Default)]
+pub struct TypeDependencies {
+    pub definitions: BTreeSet<TypeId>,
+    pub declarations: BTreeSet<TypeId>,
+}
+
+impl TypeDependencies {
+    pub fn extend(&mut self, other: TypeDependencies) {
+        self.definitions.extend(other.definitions);
+        self.declarations.extend(other.declarations);
+    }
+}
+
+/// Collects the named types a root tag refers to, as printed by [`tag_type_string`] with the
+/// same typedef map.
+pub fn tag_type_dependencies(
+    info: &DwarfInfo,
+    typedefs: &TypedefMap,
+    tag_type: &TagType,
+) -> Result<TypeDependencies> {
+    let mut deps = TypeDependencies::default();
+    match tag_type {
+        // A typedef of a structure only needs it declared
+        TagType::Typedef(t) => type_dependencies(info, typedefs, &t.kind, true, &mut deps)?,
+        TagType::Variable(v) => type_dependencies(info, typedefs, &v.kind, false, &mut deps)?,
+        TagType::UserDefined(t) => {
+            ud_type_dependencies(info, typedefs, t, false, &mut deps)?;
+            if let UserDefinedType::Subroutine(t) = t {
+                if let Some(member_of) = t.member_of {
+                    let tag = info
+                        .tags
+                        .get(&member_of)
+                        .ok_or_else(|| anyhow!("Failed to locate member_of tag {}", member_of))?;
+                    if let Some(id) = TypeId::of_ud_type(&ud_type(info, tag)?) {
+                        deps.definitions.insert(id);
+                    }
+                }
+                for variable in &t.variables {
+                    type_dependencies(info, typedefs, &variable.kind, false, &mut deps)?;
+                }
+                let mut blocks = t.blocks.iter().collect::<Vec<_>>();
+                while let Some(block) = blocks.pop() {
+                    for variable in &block.variables {
+                        type_dependencies(info, typedefs, &variable.kind, false, &mut deps)?;
+                    }
+                    blocks.extend(&block.blocks);
+                }
+            }
+        }
+    }
+    Ok(deps)
+}
+
+fn type_dependencies(
+    info: &DwarfInfo,
+    typedefs: &TypedefMap,
+    t: &Type,
+    indirect: bool,
+    deps: &mut TypeDependencies,
+) -> Result<()> {
+    let TypeKind::UserDefined(key) = t.kind else {
+        return Ok(());
+    };
+    let indirect = indirect
+        || t.modifiers.iter().any(|m| matches!(m, Modifier::PointerTo | Modifier::ReferenceTo));
+    if let Some(&td_key) = typedefs.get(&key).and_then(|v| v.first()) {
+        let name = info
+            .tags
+            .get(&td_key)
+            .and_then(|tag| tag.string_attribute(AttributeKind::Name))
+            .ok_or_else(|| anyhow!("Failed to locate typedef {}", td_key))?;
+        deps.definitions.insert(TypeId::Typedef(name.clone()));
+        return Ok(());
+    }
+    let tag =
+        info.tags.get(&key).ok_or_else(|| anyhow!("Failed to locate user defined type {}", key))?;
+    let ud = ud_type(info, tag)?;
+    match TypeId::of_ud_type(&ud) {
+        Some(id) if indirect && id.forward_declaration().is_some() => {
+            deps.declarations.insert(id);
+        }
+        Some(id) => {
+            deps.definitions.insert(id);
+        }
+        // Anonymous definitions are printed inline
+        None => ud_type_dependencies(info, typedefs, &ud, indirect, deps)?,
+    }
+    Ok(())
+}
+
+fn ud_type_dependencies(
+    info: &DwarfInfo,
+    typedefs: &TypedefMap,
+    t: &UserDefinedType,
+    indirect: bool,
+    deps: &mut TypeDependencies,
+) -> Result<()> {
+    match t {
+        UserDefinedType::Array(t) => {
+            type_dependencies(info, typedefs, &t.element_type, indirect, deps)?;
+        }
+        UserDefinedType::Structure(t) => {
+            for member in &t.members {
+                type_dependencies(info, typedefs, &member.kind, false, deps)?;
+            }
+            for base in &t.bases {
+                type_dependencies(info, typedefs, &base.base_type, false, deps)?;
+            }
+        }
+        UserDefinedType::Union(t) => {
+            for member in &t.members {
+                type_dependencies(info, typedefs, &member.kind, false, deps)?;
+            }
+        }
+        UserDefinedType::Enumeration(_) => {}
+        UserDefinedType::Subroutine(t) => {
+            type_dependencies(info, typedefs, &t.return_type, indirect, deps)?;
+            for parameter in &t.parameters {
+                type_dependencies(info, typedefs, &parameter.kind, indirect, deps)?;
+            }
+        }
+        UserDefinedType::PtrToMember(t) => {
+            type_dependencies(info, typedefs, &t.kind, true, deps)?;
+            let tag = info
+                .tags
+                .get(&t.containing_type)
+                .ok_or_else(|| anyhow!("Failed to locate containing type {}", t.containing_type))?;
+            if let Some(id) = TypeId::of_ud_type(&ud_type(info, tag)?) {
+                deps.declarations.insert(id);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(
+        name: &str,
+        kind: FundType,
+        modifiers: Vec<Modifier>,
+        offset: u32,
+    ) -> StructureMember {
+        StructureMember {
+            name: name.to_string(),
+            kind: Type { kind: TypeKind::Fundamental(kind), modifiers },
+            offset,
+            bit: None,
+            visibility: Visibility::Private,
+            byte_size: None,
+        }
+    }
+
+    #[test]
+    fn c_struct_def() {
+        let info = DwarfInfo { e: Endian::Big, tags: Default::default() };
+        let mut flags = member("flags", FundType::UnsignedInteger, vec![], 8);
+        flags.bit = Some(BitData { bit_size: 3, bit_offset: 0 });
+        let t = StructureType {
+            kind: StructureKind::Class,
+            name: Some("TVec<int>".to_string()),
+            byte_size: Some(12),
+            members: vec![
+                member("count", FundType::Integer, vec![], 4),
+                member("ref", FundType::Float, vec![Modifier::ReferenceTo], 0),
+                flags,
+            ],
+            bases: vec![],
+        };
+        assert_eq!(
+            c_struct_def_string(&info, &TypedefMap::new(), &t).unwrap(),
+            "struct TVec_int_ {\n    float * ref;\n    int count;\n    unsigned int flags : 3;\n}"
+        );
+
+        let empty = StructureType { name: None, members: vec![], byte_size: Some(1), ..t };
+        assert_eq!(
+            c_struct_def_string(&info, &TypedefMap::new(), &empty).unwrap(),
+            "struct {\n    char pad[1];\n}"
+        );
+    }
+
+    #[test]
+    fn c_identifiers() {
+        assert_eq!(c_identifier("Foo::Bar"), "Foo__Bar");
+        assert_eq!(c_identifier("1st"), "_1st");
+        assert_eq!(c_identifier("__ct__3FooFv"), "__ct__3FooFv");
+    }
+}
@@ -24,12 +24,14 @@ use crate::{
     array_ref,
     obj::{
         ObjArchitecture, ObjInfo, ObjKind, ObjReloc, ObjRelocKind, ObjSection, ObjSectionKind,
-        ObjSplit, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind, ObjUnit,
+        ObjSplit, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind, ObjSymbolSource,
+        ObjUnit, SymbolName,
     },
     util::{
         comment::{CommentSym, MWComment},
         file::map_file,
         reader::{Endian, FromReader, ToWriter},
+        rpl::{decompress_rpl, is_rpl},
     },
 };
 
@@ -45,7 +47,20 @@ enum BoundaryState {
 pub fn process_elf<P>(path: P) -> Result<ObjInfo>
 where P: AsRef<Path> {
     let file = map_file(path)?;
-    let obj_file = object::read::File::parse(file.as_slice())?;
+    process_elf_data(file.as_slice())
+}
+
+/// Processes an ELF already loaded into memory, such as an archive member.
+/// Wii U RPX and RPL files are decompressed first.
+pub fn process_elf_data(data: &[u8]) -> Result<ObjInfo> {
+    let rpl_data;
+    let data = if is_rpl(data) {
+        rpl_data = decompress_rpl(data).context("While reading RPL")?;
+        rpl_data.as_slice()
+    } else {
+        data
+    };
+    let obj_file = object::read::File::parse(data)?;
     let architecture = match obj_file.architecture() {
         Architecture::PowerPc => ObjArchitecture::PowerPc,
         arch => bail!("Unexpected architecture: {arch:?}"),
@@ -283,11 +298,7 @@ where P: AsRef<Path> {
     if kind == ObjKind::Executable {
         // Link order is trivially deduced
         for file_name in section_starts.keys() {
-            link_order.push(ObjUnit {
-                name: file_name.clone(),
-                autogenerated: false,
-                comment_version: None,
-            });
+            link_order.push(ObjUnit { name: file_name.clone(), ..Default::default() });
         }
 
         // Create a map of address -> file splits
@@ -309,6 +320,8 @@ where P: AsRef<Path> {
                     autogenerated: false,
                     skip: false,
                     rename: None,
+                    pad: None,
+                    end_align: None,
                 });
             }
         }
@@ -672,10 +685,32 @@ pub fn write_elf(obj: &ObjInfo) -> Result<Vec<u8>> {
                     r_offset &= !3;
                     elf::R_PPC_EMB_SDA21
                 }
+                ObjRelocKind::PpcRel32 => elf::R_PPC_REL32,
+                ObjRelocKind::PpcRel16Hi => {
+                    r_offset = (r_offset & !3) + 2;
+                    elf::R_PPC_REL16_HI
+                }
+                ObjRelocKind::PpcRel16Ha => {
+                    r_offset = (r_offset & !3) + 2;
+                    elf::R_PPC_REL16_HA
+                }
+                ObjRelocKind::PpcRel16Lo => {
+                    r_offset = (r_offset & !3) + 2;
+                    elf::R_PPC_REL16_LO
+                }
+                ObjRelocKind::PpcDtpMod32 => elf::R_PPC_DTPMOD32,
+                ObjRelocKind::PpcDtpRel32 => elf::R_PPC_DTPREL32,
             };
             let r_sym = symbol_map[reloc.target_symbol]
                 .ok_or_else(|| anyhow!("Relocation against stripped symbol"))?;
-            writer.write_relocation(true, &Rel { r_offset, r_sym, r_type, r_addend: reloc.addend });
+            let r_addend = match reloc.kind {
+                // Relative to the instruction, but applied to its immediate
+                ObjRelocKind::PpcRel16Hi | ObjRelocKind::PpcRel16Ha | ObjRelocKind::PpcRel16Lo => {
+                    reloc.addend + 2
+                }
+                _ => reloc.addend,
+            };
+            writer.write_relocation(true, &Rel { r_offset, r_sym, r_type, r_addend });
         }
     }
 
@@ -794,8 +829,8 @@ fn to_obj_symbol(
     }
     let section_idx = section.as_ref().and_then(|section| section_indexes[section.index().0]);
     Ok(ObjSymbol {
-        name: name.to_string(),
-        demangled_name: demangle(name, &Default::default()),
+        name: name.into(),
+        demangled_name: demangle(name, &Default::default()).map(SymbolName::from),
         address: symbol.address(),
         section: section_idx,
         size: symbol.size(),
@@ -809,6 +844,7 @@ fn to_obj_symbol(
             _ => bail!("Unsupported symbol kind: {:?}", symbol),
         },
         align,
+        source: ObjSymbolSource::Elf,
         ..Default::default()
     })
 }
@@ -823,8 +859,15 @@ pub fn to_obj_reloc_kind(kind: RelocationKind) -> Result<ObjRelocKind> {
             elf::R_PPC_REL24 => ObjRelocKind::PpcRel24,
             elf::R_PPC_REL14 => ObjRelocKind::PpcRel14,
             elf::R_PPC_EMB_SDA21 => ObjRelocKind::PpcEmbSda21,
+            elf::R_PPC_REL32 => ObjRelocKind::PpcRel32,
+            elf::R_PPC_REL16_HI => ObjRelocKind::PpcRel16Hi,
+            elf::R_PPC_REL16_HA => ObjRelocKind::PpcRel16Ha,
+            elf::R_PPC_REL16_LO => ObjRelocKind::PpcRel16Lo,
+            elf::R_PPC_DTPMOD32 => ObjRelocKind::PpcDtpMod32,
+            elf::R_PPC_DTPREL32 => ObjRelocKind::PpcDtpRel32,
             _ => bail!("Unhandled ELF relocation type: {kind}"),
         },
+        RelocationKind::Relative => ObjRelocKind::PpcRel32,
         _ => bail!("Unhandled relocation type: {:?}", kind),
     })
 }
@@ -872,6 +915,13 @@ fn to_obj_reloc(
         }
         _ => Err(anyhow!("Unhandled relocation symbol type {:?}", symbol.kind())),
     }?;
+    let addend = match reloc_kind {
+        // Applied to the immediate, but kept relative to the instruction
+        ObjRelocKind::PpcRel16Hi | ObjRelocKind::PpcRel16Ha | ObjRelocKind::PpcRel16Lo => {
+            addend - (address & 3) as i64
+        }
+        _ => addend,
+    };
     Ok(Some(ObjReloc { kind: reloc_kind, target_symbol, addend, module: None }))
 }
 
@@ -883,10 +933,18 @@ fn write_relocatable_section_data(w: &mut Writer, section: &ObjSection) -> Resul
         w.write(&section.data[current_address..addr as usize]);
         let mut ins = u32::from_be_bytes(*array_ref!(section.data, addr as usize, 4));
         match reloc.kind {
-            ObjRelocKind::Absolute => {
+            ObjRelocKind::Absolute
+            | ObjRelocKind::PpcRel32
+            | ObjRelocKind::PpcDtpMod32
+            | ObjRelocKind::PpcDtpRel32 => {
                 ins = 0;
             }
-            ObjRelocKind::PpcAddr16Hi | ObjRelocKind::PpcAddr16Ha | ObjRelocKind::PpcAddr16Lo => {
+            ObjRelocKind::PpcAddr16Hi
+            | ObjRelocKind::PpcAddr16Ha
+            | ObjRelocKind::PpcAddr16Lo
+            | ObjRelocKind::PpcRel16Hi
+            | ObjRelocKind::PpcRel16Ha
+            | ObjRelocKind::PpcRel16Lo => {
                 ins &= !0xFFFF;
             }
             ObjRelocKind::PpcRel24 => {
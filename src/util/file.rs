@@ -8,6 +8,7 @@ use std::{
 
 use anyhow::{anyhow, Context, Result};
 use filetime::{set_file_mtime, FileTime};
+#[cfg(not(target_arch = "wasm32"))]
 use memmap2::{Mmap, MmapOptions};
 use path_slash::PathBufExt;
 use sha1::{Digest, Sha1};
@@ -25,8 +26,24 @@ use crate::{
     },
 };
 
+/// Memory mapped file contents. wasm32 has no memory mapping, so files are read instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub type FileData = Mmap;
+#[cfg(target_arch = "wasm32")]
+pub type FileData = Vec<u8>;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn map(file: &File) -> std::io::Result<FileData> { unsafe { MmapOptions::new().map(file) } }
+
+#[cfg(target_arch = "wasm32")]
+fn map(mut file: &File) -> std::io::Result<FileData> {
+    let mut data = vec![];
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
 pub struct MappedFile {
-    mmap: Mmap,
+    mmap: FileData,
     mtime: FileTime,
     offset: u64,
     len: u64,
@@ -43,7 +60,7 @@ impl MappedFile {
 
     pub fn is_empty(&self) -> bool { self.len == 0 }
 
-    pub fn into_inner(self) -> Mmap { self.mmap }
+    pub fn into_inner(self) -> FileData { self.mmap }
 }
 
 pub fn split_path<P>(path: P) -> Result<(PathBuf, Option<PathBuf>)>
@@ -75,8 +92,8 @@ where P: AsRef<Path> {
     let file = File::open(&base_path)
         .with_context(|| format!("Failed to open file '{}'", base_path.display()))?;
     let mtime = FileTime::from_last_modification_time(&file.metadata()?);
-    let mmap = unsafe { MmapOptions::new().map(&file) }
-        .with_context(|| format!("Failed to mmap file: '{}'", base_path.display()))?;
+    let mmap =
+        map(&file).with_context(|| format!("Failed to mmap file: '{}'", base_path.display()))?;
     let (offset, len) = if let Some(sub_path) = sub_path {
         let mut reader = Cursor::new(&*mmap);
         if sub_path.as_os_str() == OsStr::new("nlzss") {
@@ -0,0 +1,564 @@
+use std::hash::Hasher;
+
+use anyhow::Result;
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+use serde::Serialize;
+
+use crate::{
+    analysis::cfa::SectionAddress,
+    obj::{ObjInfo, ObjSectionKind, ObjSymbolKind},
+};
+
+/// A function's normalized instructions.
+///
+/// Fields that depend on where things were linked (call targets, absolute address halves and
+/// small data offsets) are masked, so the same code at a different address has the same hash.
+#[derive(Debug, Clone)]
+pub struct FunctionFingerprint {
+    pub address: SectionAddress,
+    pub size: u32,
+    /// Normalized instruction words.
+    pub words: Vec<u32>,
+    /// Hash of the normalized instruction words.
+    pub hash: u64,
+}
+
+/// How a function match was found.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMethod {
+    /// Identical normalized instructions, unique in both objects.
+    Exact,
+    /// Identical normalized instructions shared by several functions, paired in address order.
+    Ordered,
+    /// Paired by position between other matches, scored by instruction similarity.
+    Similar,
+}
+
+impl MatchMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MatchMethod::Exact => "exact",
+            MatchMethod::Ordered => "ordered",
+            MatchMethod::Similar => "similar",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionMatch {
+    /// Index into the first object's fingerprints.
+    pub from: usize,
+    /// Index into the second object's fingerprints.
+    pub to: usize,
+    /// From 0.0 to 1.0.
+    pub confidence: f32,
+    pub method: MatchMethod,
+}
+
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    /// (object index, fingerprint index) of each function, in input order.
+    pub members: Vec<(usize, usize)>,
+    /// Lowest similarity of a member to the first, from 0.0 to 1.0.
+    pub similarity: f32,
+}
+
+/// Functions this short match other code by chance too often to be trusted fully.
+pub const MIN_CONFIDENT_WORDS: usize = 8;
+/// Gaps between anchors with more candidate pairs than this aren't searched.
+const MAX_GAP_PAIRS: usize = 10000;
+/// Minimum similarity for a [`MatchMethod::Similar`] match.
+const MIN_SIMILARITY: f32 = 0.5;
+/// Near-identical functions aren't searched among more distinct functions of one size than this.
+const MAX_SIMILAR_BUCKET: usize = 1000;
+
+/// Fingerprints every function of known size in the object's code sections.
+pub fn fingerprint_functions(obj: &ObjInfo) -> Result<Vec<FunctionFingerprint>> {
+    let mut out = Vec::<FunctionFingerprint>::new();
+    for (_, symbol) in obj.symbols.iter_ordered() {
+        let Some(section_index) = symbol.section else { continue };
+        let section = &obj.sections[section_index];
+        if symbol.kind != ObjSymbolKind::Function
+            || section.kind != ObjSectionKind::Code
+            || !symbol.size_known
+            || symbol.size == 0
+        {
+            continue;
+        }
+        let address = SectionAddress::new(section_index, symbol.address as u32);
+        if out.last().is_some_and(|f| f.address == address) {
+            // Aliases of the same function
+            continue;
+        }
+        let words = normalize(section.symbol_data(symbol)?);
+        let mut hasher = FxHasher::default();
+        for &word in &words {
+            hasher.write_u32(word);
+        }
+        out.push(FunctionFingerprint {
+            address,
+            size: symbol.size as u32,
+            words,
+            hash: hasher.finish(),
+        });
+    }
+    Ok(out)
+}
+
+/// Masks the address-dependent fields of each instruction.
+fn normalize(data: &[u8]) -> Vec<u32> {
+    // GPRs loaded with `lis`, holding the high half of an address
+    let mut address_regs = 0u32;
+    data.chunks_exact(4)
+        .map(|c| {
+            let ins = u32::from_be_bytes([c[0], c[1], c[2], c[3]]);
+            let rd = (ins >> 21) & 0x1F;
+            let ra = (ins >> 16) & 0x1F;
+            // r2 and r13 are the small data base registers
+            let is_address_base = |r: u32| r == 2 || r == 13 || address_regs & (1 << r) != 0;
+            match ins >> 26 {
+                // b, bl: the target is another function
+                18 => ins & 0xFC00_0003,
+                // addis: the high half of an address
+                15 => {
+                    if ra == 0 {
+                        address_regs |= 1 << rd;
+                    }
+                    ins & 0xFFFF_0000
+                }
+                // addi, or D-form loads and stores off an address base: the low half
+                14 | 32..=55 if is_address_base(ra) => ins & 0xFFFF_0000,
+                // ori rA, rS, lo (rS is in the rD field)
+                24 if address_regs & (1 << rd) != 0 => ins & 0xFFFF_0000,
+                // psq_l, psq_lu, psq_st, psq_stu: 12-bit offset
+                56 | 57 | 60 | 61 if is_address_base(ra) => ins & 0xFFFF_F000,
+                _ => ins,
+            }
+        })
+        .collect()
+}
+
+/// Instruction similarity of two functions, from 0.0 to 1.0.
+pub fn similarity(a: &[u32], b: &[u32]) -> f32 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let common = if a.len() * b.len() <= 4_000_000 {
+        // Longest common subsequence
+        let mut prev = vec![0u32; b.len() + 1];
+        let mut cur = vec![0u32; b.len() + 1];
+        for &x in a {
+            for (j, &y) in b.iter().enumerate() {
+                cur[j + 1] = if x == y { prev[j] + 1 } else { prev[j + 1].max(cur[j]) };
+            }
+            std::mem::swap(&mut prev, &mut cur);
+        }
+        prev[b.len()] as usize
+    } else {
+        // Too large, fall back to comparing instruction counts
+        let mut counts = FxHashMap::<u32, i32>::default();
+        for &x in a {
+            *counts.entry(x).or_default() += 1;
+        }
+        b.iter()
+            .filter(|&&y| match counts.get_mut(&y) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    true
+                }
+                _ => false,
+            })
+            .count()
+    };
+    (2 * common) as f32 / (a.len() + b.len()) as f32
+}
+
+/// Matches functions between two objects, such as two versions of the same game.
+///
+/// `section_pairs` maps section indices from the first object to the second;
+/// only functions in paired sections are matched.
+pub fn match_functions(
+    a: &[FunctionFingerprint],
+    b: &[FunctionFingerprint],
+    section_pairs: &[(usize, usize)],
+) -> Vec<FunctionMatch> {
+    let mut matches = vec![];
+    for &(a_section, b_section) in section_pairs {
+        let a_indices = (0..a.len()).filter(|&i| a[i].address.section == a_section).collect();
+        let b_indices = (0..b.len()).filter(|&i| b[i].address.section == b_section).collect();
+        match_section(a, b, a_indices, b_indices, &mut matches);
+    }
+    matches.sort_by_key(|m| m.from);
+    matches
+}
+
+/// Fingerprints and matches the functions of two objects, pairing sections by name.
+pub fn match_objects(
+    a_obj: &ObjInfo,
+    b_obj: &ObjInfo,
+) -> Result<(Vec<FunctionFingerprint>, Vec<FunctionFingerprint>, Vec<FunctionMatch>)> {
+    let a = fingerprint_functions(a_obj)?;
+    let b = fingerprint_functions(b_obj)?;
+    let matches = match_functions(&a, &b, &section_pairs_by_name(a_obj, b_obj));
+    Ok((a, b, matches))
+}
+
+/// Pairs sections of two objects by name.
+pub fn section_pairs_by_name(a_obj: &ObjInfo, b_obj: &ObjInfo) -> Vec<(usize, usize)> {
+    a_obj
+        .sections
+        .iter()
+        .filter(|(_, s)| s.kind == ObjSectionKind::Code)
+        .filter_map(|(a_index, a_section)| {
+            b_obj
+                .sections
+                .iter()
+                .find(|(_, s)| s.kind == ObjSectionKind::Code && s.name == a_section.name)
+                .map(|(b_index, _)| (a_index, b_index))
+        })
+        .collect()
+}
+
+/// Groups identical functions across several objects, such as the DOL and RELs of a game.
+///
+/// Functions with at least `min_words` instructions are grouped by fingerprint. With
+/// `min_similarity` below 1.0, groups of the same size are then merged when their
+/// instructions are at least that similar.
+pub fn find_duplicates(
+    objects: &[Vec<FunctionFingerprint>],
+    min_words: usize,
+    min_similarity: f32,
+) -> Vec<DuplicateGroup> {
+    // Identical functions
+    let mut groups = Vec::<Vec<(usize, usize)>>::new();
+    let mut by_hash = FxHashMap::<u64, usize>::default();
+    for (obj_index, fingerprints) in objects.iter().enumerate() {
+        for (index, f) in fingerprints.iter().enumerate() {
+            if f.words.len() < min_words {
+                continue;
+            }
+            let group = *by_hash.entry(f.hash).or_insert_with(|| {
+                groups.push(vec![]);
+                groups.len() - 1
+            });
+            groups[group].push((obj_index, index));
+        }
+    }
+    let words = |(obj_index, index): (usize, usize)| objects[obj_index][index].words.as_slice();
+
+    // Near-identical functions: each group joins the first cluster of the same size whose
+    // leader is similar enough
+    let mut clusters = Vec::<(Vec<usize>, f32)>::new();
+    if min_similarity < 1.0 {
+        let mut by_len = FxHashMap::<usize, Vec<usize>>::default();
+        for (group, members) in groups.iter().enumerate() {
+            by_len.entry(words(members[0]).len()).or_default().push(group);
+        }
+        let mut buckets = by_len.into_values().collect::<Vec<_>>();
+        buckets.sort_by_key(|b| b[0]);
+        for bucket in buckets {
+            if bucket.len() > MAX_SIMILAR_BUCKET {
+                clusters.extend(bucket.into_iter().map(|group| (vec![group], 1.0)));
+                continue;
+            }
+            let first = clusters.len();
+            for group in bucket {
+                let leader = clusters[first..].iter_mut().find_map(|cluster| {
+                    let score = similarity(words(groups[cluster.0[0]][0]), words(groups[group][0]));
+                    (score >= min_similarity).then_some((cluster, score))
+                });
+                match leader {
+                    Some((cluster, score)) => {
+                        cluster.0.push(group);
+                        cluster.1 = cluster.1.min(score);
+                    }
+                    None => clusters.push((vec![group], 1.0)),
+                }
+            }
+        }
+    } else {
+        clusters.extend((0..groups.len()).map(|group| (vec![group], 1.0)));
+    }
+
+    let mut out = clusters
+        .into_iter()
+        .map(|(cluster, similarity)| {
+            let mut members = cluster
+                .into_iter()
+                .flat_map(|group| groups[group].iter().cloned())
+                .collect::<Vec<_>>();
+            members.sort_unstable();
+            DuplicateGroup { members, similarity }
+        })
+        .filter(|g| g.members.len() > 1)
+        .collect::<Vec<_>>();
+    out.sort_by(|a, b| b.members.len().cmp(&a.members.len()).then(a.members.cmp(&b.members)));
+    out
+}
+
+/// Matches functions within one pair of sections. Indices are in address order.
+fn match_section(
+    a: &[FunctionFingerprint],
+    b: &[FunctionFingerprint],
+    a_indices: Vec<usize>,
+    b_indices: Vec<usize>,
+    out: &mut Vec<FunctionMatch>,
+) {
+    let mut a_by_hash = FxHashMap::<u64, Vec<usize>>::default();
+    for &i in &a_indices {
+        a_by_hash.entry(a[i].hash).or_default().push(i);
+    }
+    let mut b_by_hash = FxHashMap::<u64, Vec<usize>>::default();
+    for &i in &b_indices {
+        b_by_hash.entry(b[i].hash).or_default().push(i);
+    }
+
+    // Identical functions
+    let mut anchors = vec![];
+    for (hash, a_group) in &a_by_hash {
+        let Some(b_group) = b_by_hash.get(hash) else { continue };
+        let short = a[a_group[0]].words.len() < MIN_CONFIDENT_WORDS;
+        if a_group.len() == 1 && b_group.len() == 1 {
+            let confidence = if short { 0.8 } else { 1.0 };
+            anchors.push(FunctionMatch {
+                from: a_group[0],
+                to: b_group[0],
+                confidence,
+                method: MatchMethod::Exact,
+            });
+        } else if a_group.len() == b_group.len() {
+            let confidence = if short { 0.5 } else { 0.7 };
+            for (&from, &to) in a_group.iter().zip(b_group) {
+                anchors.push(FunctionMatch { from, to, confidence, method: MatchMethod::Ordered });
+            }
+        }
+    }
+    anchors.sort_by_key(|m| m.from);
+
+    // Keep anchors that agree on the function order, so gaps between them line up
+    let mut ordered = Vec::<FunctionMatch>::with_capacity(anchors.len());
+    let mut a_matched = FxHashSet::<usize>::default();
+    let mut b_matched = FxHashSet::<usize>::default();
+    for anchor in anchors {
+        let in_order = ordered
+            .last()
+            .map_or(true, |last| b[anchor.to].address.address > b[last.to].address.address);
+        if in_order || anchor.method == MatchMethod::Exact {
+            a_matched.insert(anchor.from);
+            b_matched.insert(anchor.to);
+        }
+        if in_order {
+            ordered.push(anchor);
+        } else if anchor.method == MatchMethod::Exact {
+            // Moved, but unique in both
+            out.push(anchor);
+        }
+    }
+
+    // Functions between two anchors in one object are paired with those between the same
+    // anchors in the other
+    let a_position =
+        a_indices.iter().enumerate().map(|(pos, &i)| (i, pos)).collect::<FxHashMap<_, _>>();
+    let b_position =
+        b_indices.iter().enumerate().map(|(pos, &i)| (i, pos)).collect::<FxHashMap<_, _>>();
+    let mut a_start = 0;
+    let mut b_start = 0;
+    for gap in 0..=ordered.len() {
+        let (a_end, b_end) = match ordered.get(gap) {
+            Some(m) => (a_position[&m.from], b_position[&m.to]),
+            None => (a_indices.len(), b_indices.len()),
+        };
+        let a_gap = a_indices[a_start..a_end]
+            .iter()
+            .filter(|i| !a_matched.contains(i))
+            .cloned()
+            .collect::<Vec<_>>();
+        let b_gap = b_indices[b_start..b_end]
+            .iter()
+            .filter(|i| !b_matched.contains(i))
+            .cloned()
+            .collect::<Vec<_>>();
+        match_gap(a, b, &a_gap, &b_gap, out);
+        a_start = a_end + 1;
+        b_start = b_end + 1;
+    }
+    out.extend(ordered);
+}
+
+fn match_gap(
+    a: &[FunctionFingerprint],
+    b: &[FunctionFingerprint],
+    a_gap: &[usize],
+    b_gap: &[usize],
+    out: &mut Vec<FunctionMatch>,
+) {
+    if a_gap.is_empty() || b_gap.is_empty() || a_gap.len() * b_gap.len() > MAX_GAP_PAIRS {
+        return;
+    }
+    if a_gap.len() == b_gap.len() {
+        // Same number of functions: most likely the same functions, changed
+        for (&from, &to) in a_gap.iter().zip(b_gap) {
+            let score = similarity(&a[from].words, &b[to].words);
+            if score >= MIN_SIMILARITY {
+                out.push(FunctionMatch {
+                    from,
+                    to,
+                    confidence: score * 0.9,
+                    method: MatchMethod::Similar,
+                });
+            }
+        }
+        return;
+    }
+    // Otherwise, pick the most similar function, keeping the order
+    let mut b_next = 0;
+    for &from in a_gap {
+        let best = b_gap[b_next..]
+            .iter()
+            .enumerate()
+            .map(|(offset, &to)| (offset, to, similarity(&a[from].words, &b[to].words)))
+            .max_by(|x, y| x.2.total_cmp(&y.2));
+        let Some((offset, to, score)) = best else { break };
+        if score >= MIN_SIMILARITY {
+            // Less sure than an equal-sized gap
+            out.push(FunctionMatch {
+                from,
+                to,
+                confidence: score * 0.8,
+                method: MatchMethod::Similar,
+            });
+            b_next += offset + 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::{
+        ObjArchitecture, ObjKind, ObjSection, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags,
+    };
+
+    fn bytes(words: &[u32]) -> Vec<u8> { words.iter().flat_map(|w| w.to_be_bytes()).collect() }
+
+    #[test]
+    fn normalize_masks_addresses() {
+        let words = [
+            0x3C608034, // lis r3, 0x8034
+            0x38631234, // addi r3, r3, 0x1234
+            0x80830010, // lwz r4, 0x10(r3)
+            0x60635678, // ori r3, r3, 0x5678
+            0x80AD9000, // lwz r5, -0x7000(r13)
+            0xC0220008, // lfs f1, 0x8(r2)
+            0xE0230008, // psq_l f1, 0x8(r3), 0, qr0
+            0x48001235, // bl 0x1234
+            0x38C10008, // addi r6, r1, 0x8
+            0x80010014, // lwz r0, 0x14(r1)
+            0x38E00010, // li r7, 0x10
+        ];
+        assert_eq!(normalize(&bytes(&words)), [
+            0x3C600000, 0x38630000, 0x80830000, 0x60630000, 0x80AD0000, 0xC0220000, 0xE0230000,
+            0x48000001, 0x38C10008, 0x80010014, 0x38E00010,
+        ]);
+    }
+
+    #[test]
+    fn normalize_tracks_lis_registers() {
+        let words = [
+            0x3C830001, // addis r4, r3, 0x1
+            0x80040004, // lwz r0, 0x4(r4)
+            0x3CA08000, // lis r5, 0x8000
+            0x80050004, // lwz r0, 0x4(r5)
+        ];
+        // Only lis (addis from r0) marks a register as an address base
+        assert_eq!(normalize(&bytes(&words)), [0x3C830000, 0x80040004, 0x3CA00000, 0x80050000]);
+    }
+
+    /// Instructions with no address fields, distinct per seed.
+    fn code(seed: u32, len: u32) -> Vec<u32> {
+        (0..len).map(|i| 0x7C000214 | (seed << 16) | (i << 11)).collect()
+    }
+
+    /// An object with one function per entry, laid out from `address`.
+    fn object(address: u32, functions: &[Vec<u32>]) -> ObjInfo {
+        let mut data = vec![];
+        let mut symbols = vec![];
+        for (i, words) in functions.iter().enumerate() {
+            symbols.push(ObjSymbol {
+                name: format!("fn_{i}").into(),
+                address: (address + data.len() as u32) as u64,
+                section: Some(0),
+                size: words.len() as u64 * 4,
+                size_known: true,
+                flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
+                kind: ObjSymbolKind::Function,
+                ..Default::default()
+            });
+            data.extend(bytes(words));
+        }
+        let text = ObjSection::raw(".text", ObjSectionKind::Code, address, data);
+        ObjInfo::new(
+            ObjKind::Executable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            symbols,
+            vec![text],
+        )
+    }
+
+    #[test]
+    fn match_objects_methods() {
+        let shared = code(2, 10);
+        let mut changed = code(4, 12);
+        let a_obj = object(0x80003000, &[
+            code(1, 10),
+            shared.clone(),
+            shared.clone(),
+            changed.clone(),
+            code(5, 10),
+        ]);
+        changed[3] = 0x7C000378; // mr r0, r0
+                                 // Moved, with a new function that only fuzzy matches would pair
+        let b_obj = object(0x80005000, &[
+            code(1, 10),
+            shared.clone(),
+            shared,
+            changed,
+            code(6, 3),
+            code(5, 10),
+        ]);
+        let (a, b, matches) = match_objects(&a_obj, &b_obj).unwrap();
+        assert_eq!(a.len(), 5);
+        assert_eq!(b.len(), 6);
+        let pairs = matches.iter().map(|m| (m.from, m.to, m.method)).collect::<Vec<_>>();
+        assert_eq!(pairs, [
+            (0, 0, MatchMethod::Exact),
+            (1, 1, MatchMethod::Ordered),
+            (2, 2, MatchMethod::Ordered),
+            (3, 3, MatchMethod::Similar),
+            (4, 5, MatchMethod::Exact),
+        ]);
+        assert_eq!(matches[0].confidence, 1.0);
+        assert_eq!(matches[1].confidence, 0.7);
+        let similar = &matches[3];
+        assert!(similar.confidence > 0.5 && similar.confidence < 0.9);
+    }
+
+    #[test]
+    fn match_objects_skips_short_and_unordered() {
+        // Short unique functions are exact matches with less confidence
+        let a_obj = object(0x80003000, &[code(1, 2), code(2, 10), code(3, 10)]);
+        // Swapped functions: the later anchor is dropped from the order but kept, since it's
+        // unique in both
+        let b_obj = object(0x80003000, &[code(1, 2), code(3, 10), code(2, 10)]);
+        let (_, _, matches) = match_objects(&a_obj, &b_obj).unwrap();
+        let pairs = matches.iter().map(|m| (m.from, m.to, m.method)).collect::<Vec<_>>();
+        assert_eq!(pairs, [
+            (0, 0, MatchMethod::Exact),
+            (1, 2, MatchMethod::Exact),
+            (2, 1, MatchMethod::Exact),
+        ]);
+        assert_eq!(matches[0].confidence, 0.8);
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    collections::HashMap,
+    fs,
+    fs::File,
+    io,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use encoding_rs::SHIFT_JIS;
+use tracing::warn;
+
+use crate::util::{file::buf_writer, progress::byte_progress};
+
+/// Size of a full GameCube disc.
+pub const GCM_SIZE: u64 = 0x57058000;
+
+const BOOT_SIZE: usize = 0x440;
+const BI2_SIZE: usize = 0x2000;
+const APPLOADER_OFFSET: u32 = 0x2440;
+/// Alignment of the DOL and FST.
+const SYS_ALIGN: u32 = 0x100;
+
+/// Offsets and sizes of the DOL, FST and files of an existing disc.
+#[derive(Debug, Clone, Default)]
+pub struct GcmLayout {
+    pub dol: (u32, u32),
+    pub fst: (u32, u32),
+    pub max_fst_size: u32,
+    /// Disc path -> (offset, size)
+    pub files: HashMap<String, (u32, u32)>,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct GcmOptions<'a> {
+    /// Alignment of file data.
+    pub align: u32,
+    /// Layout to keep offsets from, where the DOL, FST and files still fit.
+    pub original: Option<&'a GcmLayout>,
+}
+
+#[inline]
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| anyhow!("Offset {:#X} out of bounds", offset))
+}
+
+/// Reads the layout of a GCM image.
+pub fn read_gcm_layout(data: &[u8]) -> Result<GcmLayout> {
+    ensure!(data.len() >= APPLOADER_OFFSET as usize, "Disc image too small");
+    let dol_offset = read_u32(data, 0x420)?;
+    let fst_offset = read_u32(data, 0x424)?;
+    let fst_size = read_u32(data, 0x428)?;
+    let max_fst_size = read_u32(data, 0x42C)?;
+
+    // DOL size from its section table
+    let dol = data.get(dol_offset as usize..).ok_or_else(|| anyhow!("Invalid DOL offset"))?;
+    let mut dol_size = 0x100;
+    for i in 0..18 {
+        let offset = read_u32(dol, i * 4)?;
+        let size = read_u32(dol, 0x90 + i * 4)?;
+        if size > 0 {
+            let end = offset.checked_add(size).ok_or_else(|| {
+                anyhow!("Invalid DOL section {} offset {:#X} size {:#X}", i, offset, size)
+            })?;
+            dol_size = dol_size.max(end);
+        }
+    }
+
+    let fst = fst_offset
+        .checked_add(fst_size)
+        .and_then(|end| data.get(fst_offset as usize..end as usize))
+        .ok_or_else(|| anyhow!("Invalid FST offset {:#X} size {:#X}", fst_offset, fst_size))?;
+    let files =
+        parse_fst(fst)?.into_iter().map(|(path, offset, size)| (path, (offset, size))).collect();
+    Ok(GcmLayout {
+        dol: (dol_offset, dol_size),
+        fst: (fst_offset, fst_size),
+        max_fst_size,
+        files,
+        size: data.len() as u64,
+    })
+}
+
+/// Parses an FST, returning the path, offset and size of each file.
+fn parse_fst(fst: &[u8]) -> Result<Vec<(String, u32, u32)>> {
+    let count = read_u32(fst, 8)? as usize;
+    let strings = fst.get(count * 12..).ok_or_else(|| anyhow!("Invalid FST entry count"))?;
+    let mut files = vec![];
+    // (end index, path) of the enclosing directories
+    let mut dirs = vec![(count, String::new())];
+    for i in 1..count {
+        while dirs.last().is_some_and(|&(end, _)| i >= end) {
+            dirs.pop();
+        }
+        let entry = &fst[i * 12..i * 12 + 12];
+        let name_offset = (read_u32(entry, 0)? & 0xFFFFFF) as usize;
+        let name = strings.get(name_offset..).ok_or_else(|| anyhow!("Invalid FST name offset"))?;
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+        let name = SHIFT_JIS.decode_without_bom_handling(name).0;
+        let path = match dirs.last() {
+            Some((_, parent)) if !parent.is_empty() => format!("{parent}/{name}"),
+            _ => name.into_owned(),
+        };
+        if entry[0] != 0 {
+            dirs.push((read_u32(entry, 8)? as usize, path));
+        } else {
+            files.push((path, read_u32(entry, 4)?, read_u32(entry, 8)?));
+        }
+    }
+    Ok(files)
+}
+
+enum Node {
+    File { name: String, source: PathBuf, size: u32 },
+    Dir { name: String, children: Vec<Node> },
+}
+
+impl Node {
+    fn name(&self) -> &str {
+        match self {
+            Node::File { name, .. } | Node::Dir { name, .. } => name,
+        }
+    }
+}
+
+fn read_tree(dir: &Path) -> Result<Vec<Node>> {
+    let mut nodes = vec![];
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read '{}'", dir.display()))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            nodes.push(Node::Dir { name, children: read_tree(&path)? });
+        } else {
+            let size = entry.metadata()?.len();
+            ensure!(size <= u32::MAX as u64, "File too large: '{}'", path.display());
+            nodes.push(Node::File { name, source: path, size: size as u32 });
+        }
+    }
+    // Nintendo's tools sort files and directories together, ignoring case
+    nodes.sort_by(|a, b| a.name().to_ascii_lowercase().cmp(&b.name().to_ascii_lowercase()));
+    Ok(nodes)
+}
+
+struct FstFile {
+    path: String,
+    source: PathBuf,
+    size: u32,
+    entry: usize,
+}
+
+/// Flattens the tree into FST entries (flags and name offset, offset or parent, size or
+/// next index) and a string table.
+fn flatten(
+    nodes: &[Node],
+    parent: usize,
+    prefix: &str,
+    entries: &mut Vec<[u32; 3]>,
+    strings: &mut Vec<u8>,
+    files: &mut Vec<FstFile>,
+) -> Result<()> {
+    for node in nodes {
+        let name_offset = strings.len() as u32;
+        ensure!(name_offset < 0x1000000, "FST string table too large");
+        strings.extend_from_slice(&SHIFT_JIS.encode(node.name()).0);
+        strings.push(0);
+        let path = if prefix.is_empty() {
+            node.name().to_string()
+        } else {
+            format!("{prefix}/{}", node.name())
+        };
+        let index = entries.len();
+        match node {
+            Node::File { source, size, .. } => {
+                entries.push([name_offset, 0, *size]);
+                files.push(FstFile { path, source: source.clone(), size: *size, entry: index });
+            }
+            Node::Dir { children, .. } => {
+                entries.push([0x1000000 | name_offset, parent as u32, 0]);
+                flatten(children, index, &path, entries, strings, files)?;
+                entries[index][2] = entries.len() as u32;
+            }
+        }
+    }
+    Ok(())
+}
+
+enum Source<'a> {
+    Data(&'a [u8]),
+    File(&'a Path, u32),
+}
+
+/// Builds a GCM image from an extracted disc: `sys_dir` with `boot.bin`, `bi2.bin`,
+/// `apploader.img` and `main.dol`, and `files_dir` with the file system.
+///
+/// The DOL, FST and files are placed in that order after the apploader, unless they fit at
+/// their offsets in the original layout. Gaps are zero-filled.
+pub fn build_gcm(
+    sys_dir: &Path,
+    files_dir: &Path,
+    out_path: &Path,
+    options: &GcmOptions,
+) -> Result<()> {
+    let read = |name: &str| {
+        let path = sys_dir.join(name);
+        fs::read(&path).with_context(|| format!("Failed to read '{}'", path.display()))
+    };
+    let mut boot = read("boot.bin")?;
+    ensure!(boot.len() == BOOT_SIZE, "boot.bin must be {:#X} bytes", BOOT_SIZE);
+    let bi2 = read("bi2.bin")?;
+    ensure!(bi2.len() == BI2_SIZE, "bi2.bin must be {:#X} bytes", BI2_SIZE);
+    let apploader = read("apploader.img")?;
+    let dol = read("main.dol")?;
+
+    let tree = read_tree(files_dir)?;
+    let mut entries = vec![[0x1000000, 0, 0]];
+    let mut strings = vec![];
+    let mut files = vec![];
+    flatten(&tree, 0, "", &mut entries, &mut strings, &mut files)?;
+    entries[0][2] = entries.len() as u32;
+    let fst_size = (entries.len() * 12 + strings.len()) as u32;
+
+    // Keep original offsets where the data still fits
+    let fits = |original: Option<(u32, u32)>, size: u32| original.filter(|&(_, s)| size <= s);
+    let original = options.original;
+    let dol_offset = fits(original.map(|o| o.dol), dol.len() as u32).map(|(o, _)| o);
+    let fst_offset = fits(original.map(|o| o.fst), fst_size).map(|(o, _)| o);
+    let mut file_offsets = files
+        .iter()
+        .map(|f| fits(original.and_then(|o| o.files.get(&f.path).copied()), f.size).map(|(o, _)| o))
+        .collect::<Vec<_>>();
+    // In u64, since the original layout's offsets and sizes come from the disc header
+    let mut cursor = APPLOADER_OFFSET as u64 + apploader.len() as u64;
+    if let Some(original) = original {
+        let kept = [(dol_offset, original.dol.1), (fst_offset, original.fst.1)]
+            .into_iter()
+            .chain(file_offsets.iter().zip(&files).map(|(&o, f)| (o, f.size)))
+            .filter_map(|(offset, size)| offset.map(|o| o as u64 + size as u64));
+        cursor = cursor.max(kept.max().unwrap_or(0));
+    }
+    let mut place = |size: u32, align: u32| -> Result<u32> {
+        let align = align as u64;
+        let offset = (cursor + align - 1) & !(align - 1);
+        cursor = offset + size as u64;
+        u32::try_from(offset).map_err(|_| anyhow!("Disc image is too large: offset {:#X}", offset))
+    };
+    let dol_offset = match dol_offset {
+        Some(offset) => offset,
+        None => place(dol.len() as u32, SYS_ALIGN)?,
+    };
+    let fst_offset = match fst_offset {
+        Some(offset) => offset,
+        None => place(fst_size, SYS_ALIGN)?,
+    };
+    for (offset, file) in file_offsets.iter_mut().zip(&files) {
+        if offset.is_none() {
+            *offset = Some(place(file.size, options.align)?);
+        }
+    }
+
+    // Header and FST
+    let max_fst_size = match original {
+        Some(original) if fst_offset == original.fst.0 => original.max_fst_size.max(fst_size),
+        _ => fst_size,
+    };
+    boot[0x420..0x424].copy_from_slice(&dol_offset.to_be_bytes());
+    boot[0x424..0x428].copy_from_slice(&fst_offset.to_be_bytes());
+    boot[0x428..0x42C].copy_from_slice(&fst_size.to_be_bytes());
+    boot[0x42C..0x430].copy_from_slice(&max_fst_size.to_be_bytes());
+    for (file, offset) in files.iter().zip(&file_offsets) {
+        entries[file.entry][1] = offset.unwrap();
+    }
+    let mut fst = Vec::with_capacity(fst_size as usize);
+    for entry in &entries {
+        for value in entry {
+            fst.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+    fst.extend_from_slice(&strings);
+
+    let mut items = vec![
+        (0, Source::Data(&boot)),
+        (BOOT_SIZE as u32, Source::Data(&bi2)),
+        (APPLOADER_OFFSET, Source::Data(&apploader)),
+        (dol_offset, Source::Data(&dol)),
+        (fst_offset, Source::Data(&fst)),
+    ];
+    for (file, offset) in files.iter().zip(&file_offsets) {
+        items.push((offset.unwrap(), Source::File(&file.source, file.size)));
+    }
+    items.sort_by_key(|&(offset, _)| offset);
+    let end = items
+        .iter()
+        .map(|(offset, source)| {
+            *offset as u64
+                + match source {
+                    Source::Data(data) => data.len() as u64,
+                    Source::File(_, size) => *size as u64,
+                }
+        })
+        .max()
+        .unwrap_or(0);
+    let total = end.max(original.map_or(0, |o| o.size));
+    if total > GCM_SIZE {
+        warn!("Disc image is larger than a GameCube disc ({:#X} > {:#X})", total, GCM_SIZE);
+    }
+
+    let progress = byte_progress(total, "Writing");
+    let mut w = buf_writer(out_path)?;
+    let mut position = 0u64;
+    for (offset, source) in items {
+        let offset = offset as u64;
+        if offset < position {
+            bail!("Overlapping data at {:#X}", offset);
+        }
+        io::copy(&mut io::repeat(0).take(offset - position), &mut w)?;
+        position = match source {
+            Source::Data(data) => {
+                w.write_all(data)?;
+                offset + data.len() as u64
+            }
+            Source::File(path, size) => {
+                let file = File::open(path)
+                    .with_context(|| format!("Failed to open '{}'", path.display()))?;
+                let copied = io::copy(&mut file.take(size as u64), &mut w)?;
+                ensure!(copied == size as u64, "'{}' changed while writing", path.display());
+                offset + copied
+            }
+        };
+        progress.set_position(position);
+    }
+    io::copy(&mut io::repeat(0).take(total - position), &mut w)?;
+    w.flush()?;
+    progress.finish_and_clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, data: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    /// A DOL with a single 0x20-byte text section.
+    fn dol() -> Vec<u8> {
+        let mut dol = vec![0u8; 0x120];
+        dol[0..4].copy_from_slice(&0x100u32.to_be_bytes());
+        dol[0x90..0x94].copy_from_slice(&0x20u32.to_be_bytes());
+        dol
+    }
+
+    fn file_data<'a>(image: &'a [u8], layout: &GcmLayout, path: &str) -> &'a [u8] {
+        let (offset, size) = layout.files[path];
+        &image[offset as usize..(offset + size) as usize]
+    }
+
+    #[test]
+    fn build_and_read_layout() {
+        let dir = std::env::temp_dir().join(format!("dtk-gcm-test-{}", std::process::id()));
+        let (sys, files) = (dir.join("sys"), dir.join("files"));
+        write(&sys.join("boot.bin"), &[0; BOOT_SIZE]);
+        write(&sys.join("bi2.bin"), &[0; BI2_SIZE]);
+        write(&sys.join("apploader.img"), &[0; 0x20]);
+        write(&sys.join("main.dol"), &dol());
+        write(&files.join("a.txt"), b"hello");
+        write(&files.join("Dir/b.bin"), b"abc");
+
+        let out = dir.join("out.iso");
+        build_gcm(&sys, &files, &out, &GcmOptions { align: 4, original: None }).unwrap();
+        let image = fs::read(&out).unwrap();
+        let layout = read_gcm_layout(&image).unwrap();
+        assert_eq!(layout.dol, (0x2500, 0x120));
+        assert_eq!(layout.fst.0, 0x2700);
+        assert_eq!(layout.files.len(), 2);
+        assert_eq!(file_data(&image, &layout, "a.txt"), b"hello");
+        assert_eq!(file_data(&image, &layout, "Dir/b.bin"), b"abc");
+
+        // A file that no longer fits moves past everything else, the rest stays in place
+        write(&files.join("a.txt"), b"hello, world");
+        let options = GcmOptions { align: 4, original: Some(&layout) };
+        build_gcm(&sys, &files, &out, &options).unwrap();
+        let image = fs::read(&out).unwrap();
+        let rebuilt = read_gcm_layout(&image).unwrap();
+        assert_eq!(rebuilt.dol, layout.dol);
+        assert_eq!(rebuilt.fst, layout.fst);
+        assert_eq!(rebuilt.files["Dir/b.bin"], layout.files["Dir/b.bin"]);
+        assert!(rebuilt.files["a.txt"].0 > layout.files["Dir/b.bin"].0);
+        assert_eq!(file_data(&image, &rebuilt, "a.txt"), b"hello, world");
+        assert_eq!(image.len() as u64, layout.size.max(rebuilt.files["a.txt"].0 as u64 + 12));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn read_layout_overflow() {
+        let mut image = vec![0u8; APPLOADER_OFFSET as usize + 0x120];
+        image[0x424..0x428].copy_from_slice(&0xFFFFFFF0u32.to_be_bytes());
+        image[0x428..0x42C].copy_from_slice(&0x20u32.to_be_bytes());
+        image[0x420..0x424].copy_from_slice(&APPLOADER_OFFSET.to_be_bytes());
+        assert!(read_gcm_layout(&image).is_err());
+
+        // DOL section that wraps around
+        let dol = APPLOADER_OFFSET as usize;
+        image[0x424..0x428].copy_from_slice(&0u32.to_be_bytes());
+        image[dol..dol + 4].copy_from_slice(&0xFFFFFFF0u32.to_be_bytes());
+        image[dol + 0x90..dol + 0x94].copy_from_slice(&0x20u32.to_be_bytes());
+        assert!(read_gcm_layout(&image).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+};
+
+use anyhow::Result;
+
+use crate::{
+    obj::{ObjDataKind, ObjInfo, ObjSymbol, ObjSymbolKind},
+    util::{config::is_auto_symbol, scaffold::is_c_identifier},
+};
+
+/// Declarations of one unit, split by linkage.
+#[derive(Default)]
+struct UnitDecls {
+    c: Vec<String>,
+    cpp: Vec<String>,
+}
+
+/// Generates a header of extern declarations for the global symbols of `objs`, grouped by unit
+/// in link order.
+///
+/// `types` maps symbol names to full declarations (e.g. `void OSReport(const char*, ...)`),
+/// which take precedence. Otherwise, functions are declared without a prototype and objects
+/// by their data kind and size. Mangled names use the demangled signature of free functions,
+/// with an unknown (`void`) return type; other mangled names become comments.
+/// Auto-generated names (`fn_`, `lbl_`) are skipped.
+pub fn generate_header(
+    objs: &[(&str, &ObjInfo)],
+    types: &HashMap<String, String>,
+    guard: &str,
+) -> Result<String> {
+    let groups = group_declarations(objs, types)?;
+    let mut out = String::new();
+    writeln!(out, "#ifndef {guard}")?;
+    writeln!(out, "#define {guard}")?;
+    writeln!(out)?;
+    writeln!(out, "#ifdef __cplusplus")?;
+    writeln!(out, "extern \"C\" {{")?;
+    writeln!(out, "#endif")?;
+    write_c_declarations(&mut out, &groups)?;
+    writeln!(out)?;
+    writeln!(out, "#ifdef __cplusplus")?;
+    writeln!(out, "}}")?;
+    writeln!(out, "#endif")?;
+    if groups.iter().any(|(_, d)| !d.cpp.is_empty()) {
+        writeln!(out)?;
+        writeln!(out, "#ifdef __cplusplus")?;
+        for (name, decls) in groups.iter().filter(|(_, d)| !d.cpp.is_empty()) {
+            writeln!(out)?;
+            writeln!(out, "// {name}")?;
+            for decl in &decls.cpp {
+                writeln!(out, "{decl}")?;
+            }
+        }
+        writeln!(out)?;
+        writeln!(out, "#endif")?;
+    }
+    writeln!(out)?;
+    writeln!(out, "#endif // {guard}")?;
+    Ok(out)
+}
+
+/// Generates the C declarations of [`generate_header`] without the include guard and
+/// preprocessor blocks, for tools that parse plain C (e.g. an m2c context).
+pub fn generate_c_declarations(
+    objs: &[(&str, &ObjInfo)],
+    types: &HashMap<String, String>,
+) -> Result<String> {
+    let mut out = String::new();
+    write_c_declarations(&mut out, &group_declarations(objs, types)?)?;
+    Ok(out)
+}
+
+fn write_c_declarations(out: &mut String, groups: &[(String, UnitDecls)]) -> Result<()> {
+    for (name, decls) in groups.iter().filter(|(_, d)| !d.c.is_empty()) {
+        writeln!(out)?;
+        writeln!(out, "// {name}")?;
+        for decl in &decls.c {
+            writeln!(out, "{decl}")?;
+        }
+    }
+    Ok(())
+}
+
+fn group_declarations(
+    objs: &[(&str, &ObjInfo)],
+    types: &HashMap<String, String>,
+) -> Result<Vec<(String, UnitDecls)>> {
+    let mut groups = Vec::<(String, UnitDecls)>::new();
+    let mut seen = HashSet::new();
+    for &(object, obj) in objs {
+        let mut units = BTreeMap::<&str, UnitDecls>::new();
+        let mut no_unit = UnitDecls::default();
+        for (_, symbol) in obj.symbols.iter_ordered() {
+            let Some(section_index) = symbol.section else { continue };
+            if symbol.kind == ObjSymbolKind::Section
+                || symbol.flags.is_local()
+                || is_auto_symbol(symbol)
+                || symbol.name.starts_with('@')
+                || !seen.insert(symbol.name.as_str())
+            {
+                continue;
+            }
+            let decls = match obj.sections[section_index].splits.for_address(symbol.address as u32)
+            {
+                Some((_, split)) => units.entry(&split.unit).or_default(),
+                None => &mut no_unit,
+            };
+            add_declaration(decls, symbol, types.get(symbol.name.as_str()))?;
+        }
+        for unit in &obj.link_order {
+            if let Some(decls) = units.remove(unit.name.as_str()) {
+                groups.push((format!("{}: {}", object, unit.name), decls));
+            }
+        }
+        // Units missing from the link order
+        for (unit, decls) in units {
+            groups.push((format!("{}: {}", object, unit), decls));
+        }
+        groups.push((format!("{}: no unit", object), no_unit));
+    }
+    Ok(groups)
+}
+
+fn add_declaration(decls: &mut UnitDecls, symbol: &ObjSymbol, ty: Option<&String>) -> Result<()> {
+    let mangled = !is_c_identifier(&symbol.name);
+    let list = if mangled { &mut decls.cpp } else { &mut decls.c };
+    if let Some(ty) = ty {
+        list.push(format!("extern {};", ty.trim().trim_end_matches(';')));
+        return Ok(());
+    }
+    if mangled {
+        let demangled = symbol.demangled_name.as_deref().unwrap_or(&symbol.name);
+        // Only free functions can be declared without their class or namespace
+        let free_function = symbol.kind == ObjSymbolKind::Function
+            && demangled.split_once('(').is_some_and(|(name, _)| is_c_identifier(name));
+        if free_function {
+            list.push(format!("extern void {demangled};"));
+        } else {
+            list.push(format!("// {demangled}"));
+        }
+        return Ok(());
+    }
+    match symbol.kind {
+        ObjSymbolKind::Function => list.push(format!("extern void {}();", symbol.name)),
+        _ => list.push(format!("extern {};", object_declaration(symbol))),
+    }
+    Ok(())
+}
+
+/// Declares an object by its data kind, as an array unless its size is a single element.
+fn object_declaration(symbol: &ObjSymbol) -> String {
+    let (ty, elem_size, is_string) = match symbol.data_kind {
+        ObjDataKind::Unknown | ObjDataKind::Byte => ("unsigned char", 1, false),
+        ObjDataKind::Byte2 => ("unsigned short", 2, false),
+        ObjDataKind::Byte4 => ("unsigned int", 4, false),
+        ObjDataKind::Byte8 => ("unsigned long long", 8, false),
+        ObjDataKind::Float => ("float", 4, false),
+        ObjDataKind::Double => ("double", 8, false),
+        ObjDataKind::String => ("char", 1, true),
+        ObjDataKind::String16 => ("unsigned short", 2, true),
+        ObjDataKind::StringTable => ("char*", 4, false),
+        ObjDataKind::String16Table => ("unsigned short*", 4, false),
+    };
+    let name = &symbol.name;
+    if !symbol.size_known || symbol.size == 0 || symbol.size % elem_size != 0 {
+        format!("{ty} {name}[]")
+    } else if symbol.size == elem_size && !is_string && symbol.data_kind != ObjDataKind::Unknown {
+        format!("{ty} {name}")
+    } else {
+        format!("{ty} {name}[{}]", symbol.size / elem_size)
+    }
+}
+
+/// Header guard for a header file name, e.g. `symbols.h` -> `SYMBOLS_H`.
+pub fn header_guard(file_name: &str) -> String {
+    let mut guard = file_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect::<String>();
+    if guard.starts_with(|c: char| c.is_ascii_digit()) {
+        guard.insert(0, '_');
+    }
+    guard
+}
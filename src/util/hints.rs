@@ -0,0 +1,141 @@
+use std::{io::BufRead, path::Path};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{
+    analysis::cfa::SectionAddress,
+    obj::{
+        ObjInfo, ObjKind, ObjSectionKind, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags,
+        ObjSymbolKind, ObjSymbolSource,
+    },
+    util::{config::parse_hex, file::map_file},
+};
+
+static HINT_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        "^\\s*(?P<kind>[a-z_]+)\\s+(?:(?P<section>[A-Za-z0-9._]+):)?(?P<addr>[0-9A-Fa-fXx]+)(?P<attrs>(?:\\s+[^\\s/]+)*)\\s*(?://.*)?$",
+    )
+    .unwrap()
+});
+static COMMENT_LINE: Lazy<Regex> = Lazy::new(|| Regex::new("^\\s*(?://|#).*$").unwrap());
+
+/// Applies an analysis hints file, asserting facts the analyzer can't infer.
+/// Hints are applied to the object before analysis runs.
+///
+/// ```text
+/// // A function, with optional exact size
+/// function .text:0x80003100 size:0x40
+/// // A jump table with a known number of entries
+/// jump_table .data:0x803A1230 entries:12
+/// // An unconditional blr that doesn't end its function
+/// not_end .text:0x80003120
+/// // A function that never returns, so code after calls to it isn't reachable
+/// noreturn .text:0x80003300
+/// // Data embedded in a code section
+/// data .text:0x80003200 size:0x40
+/// ```
+///
+/// The section may be omitted for DOL addresses.
+pub fn apply_hints_file<P>(path: P, obj: &mut ObjInfo) -> Result<()>
+where P: AsRef<Path> {
+    let path = path.as_ref();
+    let file = map_file(path)?;
+    for (line_num, result) in file.as_reader().lines().enumerate() {
+        let line = match result {
+            Ok(line) => line,
+            Err(e) => bail!("Failed to process hints file: {e:?}"),
+        };
+        apply_hint_line(&line, obj).with_context(|| {
+            format!("While processing '{}' line {}", path.display(), line_num + 1)
+        })?;
+    }
+    Ok(())
+}
+
+fn apply_hint_line(line: &str, obj: &mut ObjInfo) -> Result<()> {
+    if line.trim().is_empty() || COMMENT_LINE.is_match(line) {
+        return Ok(());
+    }
+    let captures =
+        HINT_LINE.captures(line).ok_or_else(|| anyhow!("Failed to parse hint '{line}'"))?;
+    let address = parse_hex(&captures["addr"])?;
+    let addr = match captures.name("section") {
+        Some(section_name) => match obj.sections.by_name(section_name.as_str())? {
+            Some((section_index, _)) => SectionAddress::new(section_index, address),
+            None => bail!("Section {} not found", section_name.as_str()),
+        },
+        None if obj.kind == ObjKind::Executable => {
+            let (section_index, _) = obj.sections.at_address(address)?;
+            SectionAddress::new(section_index, address)
+        }
+        None => bail!("Hint requires a section name"),
+    };
+
+    let mut size = None;
+    let mut entries = None;
+    for attr in captures["attrs"].split_whitespace() {
+        match attr.split_once(':') {
+            Some(("size", value)) => size = Some(parse_hex(value)?),
+            Some(("entries", value)) => entries = Some(parse_hex(value)?),
+            _ => bail!("Unknown hint attribute '{attr}'"),
+        }
+    }
+
+    let section = &obj.sections[addr.section];
+    match &captures["kind"] {
+        "function" => {
+            ensure!(section.kind == ObjSectionKind::Code, "Function hint outside of code section");
+            obj.known_functions.insert(addr, size);
+        }
+        "jump_table" => {
+            ensure!(
+                !matches!(section.kind, ObjSectionKind::Code | ObjSectionKind::Bss),
+                "Jump table hint must be in a data section"
+            );
+            let entries = entries.ok_or_else(|| anyhow!("Jump table hint requires entries"))?;
+            ensure!(entries > 0, "Jump table hint requires entries > 0");
+            obj.known_jump_tables.insert(addr, entries * 4);
+        }
+        "data" => {
+            ensure!(section.kind == ObjSectionKind::Code, "Data hint outside of code section");
+            let size = size.ok_or_else(|| anyhow!("Data hint requires size"))?;
+            ensure!(size > 0, "Data hint requires size > 0");
+            let name = if obj.module_id == 0 {
+                format!("lbl_{:08X}", addr.address)
+            } else {
+                format!(
+                    "lbl_{}_{}_{:X}",
+                    obj.module_id,
+                    section.name.trim_start_matches('.'),
+                    addr.address
+                )
+            };
+            obj.add_symbol(
+                ObjSymbol {
+                    name: name.into(),
+                    address: addr.address as u64,
+                    section: Some(addr.section),
+                    size: size as u64,
+                    size_known: true,
+                    flags: ObjSymbolFlagSet(ObjSymbolFlags::Local.into()),
+                    kind: ObjSymbolKind::Object,
+                    source: ObjSymbolSource::Config,
+                    ..Default::default()
+                },
+                false,
+            )?;
+        }
+        "not_end" => {
+            ensure!(section.kind == ObjSectionKind::Code, "not_end hint outside of code section");
+            obj.non_terminal_returns.insert(addr);
+        }
+        "noreturn" => {
+            ensure!(section.kind == ObjSectionKind::Code, "noreturn hint outside of code section");
+            obj.noreturn_functions.insert(addr);
+        }
+        kind => bail!("Unknown hint '{kind}'"),
+    }
+    Ok(())
+}
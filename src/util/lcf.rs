@@ -1,12 +1,15 @@
-use std::path::PathBuf;
+use std::{collections::HashSet, path::PathBuf};
 
 use anyhow::{bail, Result};
 use itertools::Itertools;
 use path_slash::PathBufExt;
 
 use crate::{
-    obj::{ObjInfo, ObjKind},
-    util::align_up,
+    obj::{AddressSpace, ObjInfo, ObjKind, ObjSymbol, ObjSymbolKind},
+    util::{
+        align_up,
+        split::{is_linker_generated_label, is_linker_generated_object},
+    },
 };
 
 const LCF_TEMPLATE: &str = include_str!("../../assets/ldscript.lcf");
@@ -30,8 +33,16 @@ pub fn generate_ldscript(
     // Guess section alignment
     let mut alignments = Vec::with_capacity(obj.sections.count());
     let mut last_section_end = origin as u32;
+    let mut last_space = AddressSpace::of(origin as u32);
     for (_, section) in obj.sections.iter() {
         let section_start = section.address as u32;
+        if section.address_space() != last_space {
+            // First section in another address space (e.g. MEM2) is placed explicitly
+            last_space = section.address_space();
+            last_section_end = section_start + section.size as u32;
+            alignments.push(format!("BIND({:#X})", section_start));
+            continue;
+        }
         let mut align = 0x20;
         while align_up(last_section_end, align) < section_start {
             align = (align + 1).next_power_of_two();
@@ -45,14 +56,14 @@ pub fn generate_ldscript(
             );
         }
         last_section_end = section_start + section.size as u32;
-        alignments.push(align);
+        alignments.push(format!("ALIGN({:#X})", align));
     }
 
     let section_defs = obj
         .sections
         .iter()
         .zip(alignments)
-        .map(|((_, s), align)| format!("{} ALIGN({:#X}):{{}}", s.name, align))
+        .map(|((_, s), align)| format!("{} {}:{{}}", s.name, align))
         .join("\n        ");
 
     let mut force_files = Vec::with_capacity(obj.link_order.len());
@@ -61,13 +72,7 @@ pub fn generate_ldscript(
         force_files.push(obj_path.file_name().unwrap().to_str().unwrap().to_string());
     }
 
-    let mut force_active = force_active.to_vec();
-    for symbol in obj.symbols.iter() {
-        if symbol.flags.is_force_active() && symbol.flags.is_global() && !symbol.flags.is_no_write()
-        {
-            force_active.push(symbol.name.clone());
-        }
-    }
+    let force_active = force_active_names(obj, force_active);
 
     // Hack to handle missing .sbss2 section... what's the proper way?
     let last_section_name = obj.sections.iter().next_back().unwrap().1.name.clone();
@@ -81,35 +86,192 @@ pub fn generate_ldscript(
         .replace("$LAST_SECTION_NAME", &last_section_name)
         .replace("$STACKSIZE", &format!("{:#X}", stack_size))
         .replace("$FORCEACTIVE", &force_active.join("\n    "))
-        .replace("$ARENAHI", &format!("{:#X}", obj.arena_hi.unwrap_or(0x81700000)));
+        .replace("$ARENAHI", &format!("{:#X}", obj.arena_hi.unwrap_or(0x81700000)))
+        .replace("$SDA_BASES", &sda_base_defs(obj).join("\n    "));
     Ok(out)
 }
 
+/// Definitions of `_SDA_BASE_` and `_SDA2_BASE_`, relative to the start of their small data
+/// section so they follow it if the layout shifts.
+fn sda_base_defs(obj: &ObjInfo) -> Vec<String> {
+    let mut out = vec![];
+    for (name, address, section_names) in [
+        ("_SDA_BASE_", obj.sda_base, [".sdata", ".sbss"]),
+        ("_SDA2_BASE_", obj.sda2_base, [".sdata2", ".sbss2"]),
+    ] {
+        let Some(address) = address else { continue };
+        let section = section_names.iter().find_map(|n| obj.sections.by_name(n).ok().flatten());
+        out.push(match section {
+            Some((_, section)) => {
+                let offset = address as i64 - section.address as i64;
+                let sign = if offset < 0 { '-' } else { '+' };
+                format!("{name} = ADDR({}) {sign} {:#X};", section.name, offset.unsigned_abs())
+            }
+            None => format!("{name} = {address:#X};"),
+        });
+    }
+    out
+}
+
 pub fn generate_ldscript_partial(
     obj: &ObjInfo,
     template: Option<&str>,
     force_active: &[String],
 ) -> Result<String> {
+    // Sections are placed in their original order, with the alignment from splits.txt
+    let section_defs = obj
+        .sections
+        .iter()
+        .map(|(_, s)| {
+            let inner = if s.name == ".data" { " *(.data) *(extabindex) *(extab) " } else { "" };
+            if s.align > 0 {
+                format!("{:<7} ALIGN({:#X}):{{{}}}", s.name, s.align, inner)
+            } else {
+                format!("{:<7} :{{{}}}", s.name, inner)
+            }
+        })
+        .join("\n        ");
+
     let mut force_files = Vec::with_capacity(obj.link_order.len());
     for unit in &obj.link_order {
         let obj_path = obj_path_for_unit(&unit.name);
         force_files.push(obj_path.file_name().unwrap().to_str().unwrap().to_string());
     }
 
-    let mut force_active = force_active.to_vec();
-    for symbol in obj.symbols.iter() {
-        if symbol.flags.is_force_active() && symbol.flags.is_global() && !symbol.flags.is_no_write()
-        {
-            force_active.push(symbol.name.clone());
+    // Module entry points are called by the OS, so they must never be stripped
+    let mut names = vec![];
+    for name in ["_prolog", "_epilog", "_unresolved"] {
+        if obj.symbols.by_name(name)?.is_some() {
+            names.push(name.to_string());
         }
     }
+    names.extend(force_active_names(obj, force_active));
+    let force_active = names.into_iter().unique().collect_vec();
 
     let out = template
         .unwrap_or(LCF_PARTIAL_TEMPLATE)
+        .replace("$SECTIONS", &section_defs)
         .replace("$FORCEACTIVE", &force_active.join("\n    "));
     Ok(out)
 }
 
+/// Configured force active symbols, followed by symbols flagged force active (including
+/// symbols referenced by other modules) and unreferenced symbols.
+fn force_active_names(obj: &ObjInfo, force_active: &[String]) -> Vec<String> {
+    let mut out = force_active.to_vec();
+    for symbol in obj.symbols.iter() {
+        if symbol.flags.is_force_active() && symbol.flags.is_global() && !symbol.flags.is_no_write()
+        {
+            out.push(symbol.name.to_string());
+        }
+    }
+    let unreferenced = unreferenced_symbols(obj);
+    if !unreferenced.is_empty() {
+        log::debug!("Marking {} unreferenced symbols as force active", unreferenced.len());
+    }
+    out.extend(unreferenced.into_iter().map(|symbol| symbol.name.to_string()));
+    out
+}
+
+/// Global symbols with no incoming relocations.
+///
+/// Anything that survived the original link was kept alive by something: the
+/// entry point, an address computed at runtime, a REL import, or a FORCEACTIVE
+/// directive. None of that is visible in the relocations, so without this the
+/// linker would dead-strip these symbols when relinking the split objects.
+///
+/// They're only added to the linker script, not flagged, so that the symbols file
+/// doesn't pick them up.
+fn unreferenced_symbols(obj: &ObjInfo) -> Vec<&ObjSymbol> {
+    let mut referenced = HashSet::new();
+    for (_, section) in obj.sections.iter() {
+        for (_, reloc) in section.relocations.iter() {
+            if reloc.module.is_none() {
+                referenced.insert(reloc.target_symbol);
+            }
+        }
+    }
+
+    let mut unreferenced = vec![];
+    for (symbol_idx, symbol) in obj.symbols.iter_ordered() {
+        let Some(section_index) = symbol.section else {
+            continue;
+        };
+        if referenced.contains(&symbol_idx)
+            || !matches!(symbol.kind, ObjSymbolKind::Function | ObjSymbolKind::Object)
+            || symbol.flags.is_local()
+            || symbol.flags.is_stripped()
+            || symbol.flags.is_no_write()
+            || symbol.flags.is_force_active()
+            || is_linker_generated_label(&symbol.name)
+            || is_linker_generated_object(&symbol.name)
+        {
+            continue;
+        }
+        // Exception tables and constructor lists are kept by the linker itself
+        let section = &obj.sections[section_index];
+        if matches!(section.name.as_str(), "extab" | "extabindex" | ".ctors" | ".dtors") {
+            continue;
+        }
+        unreferenced.push(symbol);
+    }
+    unreferenced
+}
+
 pub fn obj_path_for_unit(unit: &str) -> PathBuf { PathBuf::from_slash(unit).with_extension("o") }
 
 pub fn asm_path_for_unit(unit: &str) -> PathBuf { PathBuf::from_slash(unit).with_extension("s") }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::{
+        ObjArchitecture, ObjReloc, ObjRelocKind, ObjSection, ObjSectionKind, ObjSymbolFlagSet,
+        ObjSymbolFlags,
+    };
+
+    fn function(name: &str, address: u64, flags: ObjSymbolFlags) -> ObjSymbol {
+        ObjSymbol {
+            name: name.into(),
+            address,
+            section: Some(0),
+            size: 0x10,
+            size_known: true,
+            flags: ObjSymbolFlagSet(flags.into()),
+            kind: ObjSymbolKind::Function,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unreferenced_symbols_force_active() {
+        let mut text = ObjSection::raw(".text", ObjSectionKind::Code, 0x80003000, vec![0; 0x40]);
+        text.relocations
+            .insert(0x80003000, ObjReloc {
+                kind: ObjRelocKind::PpcRel24,
+                target_symbol: 1,
+                addend: 0,
+                module: None,
+            })
+            .unwrap();
+        let obj = ObjInfo::new(
+            ObjKind::Relocatable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            vec![
+                function("caller", 0x80003000, ObjSymbolFlags::Global),
+                function("callee", 0x80003010, ObjSymbolFlags::Global),
+                function("local", 0x80003020, ObjSymbolFlags::Local),
+                function("entry", 0x80003030, ObjSymbolFlags::Global),
+            ],
+            vec![text],
+        );
+        let names = unreferenced_symbols(&obj).iter().map(|s| s.name.as_str()).collect_vec();
+        assert_eq!(names, ["caller", "entry"]);
+        // Not flagged, so the symbols file is unchanged
+        assert!(obj.symbols.iter().all(|s| !s.flags.is_force_active()));
+
+        let config = ["extra".to_string()];
+        assert_eq!(force_active_names(&obj, &config), ["extra", "caller", "entry"]);
+    }
+}
@@ -3,7 +3,7 @@
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
     hash::Hash,
-    io::BufRead,
+    io::{BufRead, Write},
     mem::{replace, take},
     path::Path,
 };
@@ -15,16 +15,18 @@ use itertools::Itertools;
 use multimap::MultiMap;
 use once_cell::sync::Lazy;
 use regex::{Captures, Regex};
+use serde::Serialize;
 
 use crate::{
     obj::{
-        ObjInfo, ObjKind, ObjSplit, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind,
-        ObjUnit,
+        ObjInfo, ObjKind, ObjSectionKind, ObjSplit, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags,
+        ObjSymbolKind, ObjSymbolSource, ObjUnit, SymbolName,
     },
     util::{file::map_file, nested::NestedVec},
 };
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SymbolKind {
     Function,
     Object,
@@ -32,7 +34,8 @@ pub enum SymbolKind {
     NoType,
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SymbolVisibility {
     Unknown,
     Global,
@@ -129,6 +132,8 @@ pub struct MapInfo {
     pub link_map_symbols: HashMap<SymbolRef, SymbolEntry>,
     pub section_symbols: HashMap<String, BTreeMap<u32, Vec<SymbolEntry>>>,
     pub section_units: HashMap<String, Vec<(u32, String)>>,
+    /// Symbols listed as `UNUSED` (dead-stripped), by section.
+    pub unused_symbols: HashMap<String, Vec<SymbolEntry>>,
     // For common BSS inflation correction
     pub common_bss_start: Option<u32>,
     pub mw_comment_version: Option<u8>,
@@ -532,13 +537,20 @@ impl StateMachine {
         }
 
         if !state.symbols.is_empty() {
-            // Remove "unused" symbols
+            // Move "unused" symbols out of the layout
+            let mut unused = vec![];
             for symbols in state.symbols.values_mut() {
-                symbols.retain(|e| {
+                let (keep, stripped) = take(symbols).into_iter().partition(|e: &SymbolEntry| {
                     !e.unused ||
                         // Except for unused common BSS symbols needed to match the inflated size
                         (check_common_bss_inflation && e.address >= entries.common_bss_start.unwrap())
                 });
+                *symbols = keep;
+                unused.extend::<Vec<_>>(stripped);
+            }
+            state.symbols.retain(|_, symbols| !symbols.is_empty());
+            if !unused.is_empty() {
+                entries.unused_symbols.insert(state.current_section.clone(), unused);
             }
             entries.section_symbols.insert(state.current_section.clone(), state.symbols);
         }
@@ -572,7 +584,13 @@ impl StateMachine {
             // are corrected in end_section_layout
             (state.last_address, true)
         } else {
-            let address = u32::from_str_radix(captures["addr"].trim(), 16)?;
+            let addr_str = captures["addr"].trim();
+            let address = if addr_str.starts_with('.') {
+                // Partial links have no virtual addresses; use the section offset
+                u32::from_str_radix(captures["rom_addr"].trim(), 16)?
+            } else {
+                u32::from_str_radix(addr_str, 16)?
+            };
             state.last_address = address;
             (address, false)
         };
@@ -646,9 +664,11 @@ impl StateMachine {
             // Stripped from DOL
             return Ok(());
         }
-        let address = u32::from_str_radix(addr_str, 16)?;
-        let size = u32::from_str_radix(&captures["size"], 16)?;
-        let file_offset = u32::from_str_radix(&captures["offset"], 16)?;
+        // Partial links have no addresses
+        let parse = |s: &str| if s.starts_with('.') { Ok(0) } else { u32::from_str_radix(s, 16) };
+        let address = parse(addr_str)?;
+        let size = parse(&captures["size"])?;
+        let file_offset = parse(&captures["offset"])?;
         // log::info!("Memory map entry: {section} {address:#010X} {size:#010X} {file_offset:#010X}");
         entries.sections.push(SectionInfo {
             name: section.to_string(),
@@ -767,6 +787,14 @@ pub fn apply_map(result: &MapInfo, obj: &mut ObjInfo) -> Result<()> {
         }
     }
 
+    // Add unused symbols as stripped, so they're known for reconstructing units
+    for (section_name, symbols) in &result.unused_symbols {
+        let Some((section_index, _)) = obj.sections.by_name(section_name)? else { continue };
+        for symbol_entry in symbols {
+            add_symbol(obj, symbol_entry, Some(section_index))?;
+        }
+    }
+
     // Add absolute symbols
     // TODO
     // for symbol_entry in result.link_map_symbols.values().filter(|s| s.unit.is_none()) {
@@ -794,8 +822,8 @@ pub fn apply_map(result: &MapInfo, obj: &mut ObjInfo) -> Result<()> {
             if unit.ends_with(".s") && !obj.link_order.iter().any(|u| u.name == unit) {
                 obj.link_order.push(ObjUnit {
                     name: unit.clone(),
-                    autogenerated: false,
                     comment_version: Some(0),
+                    ..Default::default()
                 });
             }
 
@@ -807,6 +835,8 @@ pub fn apply_map(result: &MapInfo, obj: &mut ObjInfo) -> Result<()> {
                 autogenerated: false,
                 skip: false,
                 rename: None,
+                pad: None,
+                end_align: None,
             });
         }
     }
@@ -830,8 +860,8 @@ fn add_symbol(obj: &mut ObjInfo, symbol_entry: &SymbolEntry, section: Option<usi
     }
     obj.add_symbol(
         ObjSymbol {
-            name: symbol_entry.name.clone(),
-            demangled_name,
+            name: SymbolName::new(&symbol_entry.name),
+            demangled_name: demangled_name.map(SymbolName::from),
             address: symbol_entry.address as u64,
             section,
             size: symbol_entry.size as u64,
@@ -844,9 +874,92 @@ fn add_symbol(obj: &mut ObjInfo, symbol_entry: &SymbolEntry, section: Option<usi
                 SymbolKind::NoType => ObjSymbolKind::Unknown,
             },
             align: symbol_entry.align,
+            source: ObjSymbolSource::Map,
             ..Default::default()
         },
         true,
     )?;
     Ok(())
 }
+
+/// Writes a CodeWarrior-style map with the section layouts and memory map of an object, from
+/// its splits and symbols. Symbols outside of a split are omitted, so gaps should be filled
+/// first (see `update_splits`). There's no link map, as the references between symbols aren't
+/// known; readers guess symbol visibility in that case.
+pub fn write_map<W>(w: &mut W, obj: &ObjInfo) -> Result<()>
+where W: Write + ?Sized {
+    for (section_index, section) in obj.sections.iter() {
+        let mut splits = section.splits.iter().peekable();
+        if splits.peek().is_none() {
+            continue;
+        }
+        writeln!(w, "\n\n{} section layout", section.name)?;
+        writeln!(w, "  Starting        Virtual  File")?;
+        writeln!(w, "  address  Size   address  offset")?;
+        writeln!(w, "  ---------------------------------")?;
+        let section_end = (section.address + section.size) as u32;
+        for (start, split) in splits {
+            let end = if split.end == 0 { section_end } else { split.end };
+            let file_offset = |address: u32| match section.kind {
+                ObjSectionKind::Bss => 0,
+                _ => section.file_offset as u32 + (address - section.address as u32),
+            };
+            writeln!(
+                w,
+                "  {:08x} {:06x} {:08x} {:08x} {:>2} {} \t{}",
+                start - section.address as u32,
+                end - start,
+                start,
+                file_offset(start),
+                split.alignment(obj, section_index, section, start),
+                section.name,
+                split.unit
+            )?;
+            for (_, symbol) in obj.symbols.for_section_range(section_index, start..end) {
+                if symbol.kind == ObjSymbolKind::Section || symbol.name.is_empty() {
+                    continue;
+                }
+                let address = symbol.address as u32;
+                if symbol.flags.is_stripped() {
+                    writeln!(
+                        w,
+                        "  UNUSED   {:06x} ........ ........    {} \t{}",
+                        symbol.size, symbol.name, split.unit
+                    )?;
+                    continue;
+                }
+                let align = symbol.align.unwrap_or(match symbol.kind {
+                    ObjSymbolKind::Function => 4,
+                    _ => 1,
+                });
+                writeln!(
+                    w,
+                    "  {:08x} {:06x} {:08x} {:08x} {:>2} {} \t{}",
+                    address - section.address as u32,
+                    symbol.size,
+                    address,
+                    file_offset(address),
+                    align,
+                    symbol.name,
+                    split.unit
+                )?;
+            }
+        }
+    }
+
+    writeln!(w, "\n\nMemory map:")?;
+    writeln!(w, "                   Starting Size     File")?;
+    writeln!(w, "                   address           Offset")?;
+    for (_, section) in obj.sections.iter() {
+        let file_offset = match section.kind {
+            ObjSectionKind::Bss => 0,
+            _ => section.file_offset as u32,
+        };
+        writeln!(
+            w,
+            "{:>17}  {:08x} {:08x} {:08x}",
+            section.name, section.address as u32, section.size as u32, file_offset
+        )?;
+    }
+    Ok(())
+}
@@ -3,23 +3,41 @@ use std::{borrow::Cow, ops::Deref};
 pub mod alf;
 pub mod asm;
 pub mod bin2c;
+pub mod bss;
 pub mod comment;
+pub mod compile_commands;
+pub mod compiler;
 pub mod config;
 pub mod dep;
+pub mod diff;
 pub mod dol;
 pub mod dwarf;
 pub mod elf;
 pub mod file;
+pub mod fingerprint;
+pub mod gcm;
+pub mod header;
+pub mod hints;
 pub mod lcf;
 pub mod map;
 pub mod nested;
+pub mod output;
+pub mod port;
+pub mod progress;
+pub mod ram;
 pub mod rarc;
 pub mod reader;
 pub mod rel;
+pub mod rel_recover;
+pub mod rpl;
 pub mod rso;
+pub mod scaffold;
 pub mod signatures;
+pub mod sigstore;
 pub mod split;
+pub mod suggest;
 pub mod take_seek;
+pub mod timings;
 pub mod yaz0;
 
 #[inline]
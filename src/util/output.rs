@@ -0,0 +1,181 @@
+use std::{
+    ffi::OsStr,
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::{Error, Result};
+use argp::FromArgValue;
+use serde::Serialize;
+
+/// Output format for command results and errors.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    /// One JSON object per line on stdout. Logs are written to stderr.
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromArgValue for OutputFormat {
+    fn from_arg_value(value: &OsStr) -> Result<Self, String> {
+        String::from_arg_value(value)
+            .and_then(|s| Self::from_str(&s).map_err(|_| "Invalid output format".to_string()))
+    }
+}
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+pub fn set_output_format(format: OutputFormat) {
+    JSON_OUTPUT.store(format == OutputFormat::Json, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn is_json() -> bool { JSON_OUTPUT.load(Ordering::Relaxed) }
+
+/// Fails under `--format json` for commands whose output is itself a source file or script
+/// (headers, DWARF dumps, shell completions) or that prompt interactively, rather than mixing
+/// text into the JSON output.
+pub fn ensure_text_output(command: &str) -> Result<()> {
+    if is_json() {
+        return Err(coded_error(
+            ErrorCode::Error,
+            format!("{command} doesn't support --format json"),
+        ));
+    }
+    Ok(())
+}
+
+/// Writes a result record as a single line of JSON to stdout.
+pub fn print_json<T>(value: &T) -> Result<()>
+where T: Serialize + ?Sized {
+    let mut out = io::stdout().lock();
+    serde_json::to_writer(&mut out, value)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Log writer that keeps each formatted warning, so commands can report them afterwards.
+pub struct WarningCollector;
+
+impl Write for WarningCollector {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let message = String::from_utf8_lossy(buf).trim_end().to_string();
+        WARNINGS.lock().unwrap().push(message);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+/// Returns the warnings logged since the last call.
+pub fn take_warnings() -> Vec<String> { std::mem::take(&mut *WARNINGS.lock().unwrap()) }
+
+/// Stable error codes, reported in JSON diagnostics and as the process exit code.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Error,
+    FileNotFound,
+    PermissionDenied,
+    Io,
+    InvalidConfig,
+    ChecksumMismatch,
+}
+
+impl ErrorCode {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorCode::Error => 1,
+            ErrorCode::FileNotFound => 2,
+            ErrorCode::PermissionDenied => 3,
+            ErrorCode::Io => 4,
+            ErrorCode::InvalidConfig => 5,
+            ErrorCode::ChecksumMismatch => 6,
+        }
+    }
+
+    /// Classifies an error by the first recognized cause in its chain.
+    pub fn from_error(e: &Error) -> Self {
+        for cause in e.chain() {
+            if let Some(e) = cause.downcast_ref::<CodedError>() {
+                return e.code;
+            } else if let Some(e) = cause.downcast_ref::<io::Error>() {
+                return match e.kind() {
+                    io::ErrorKind::NotFound => ErrorCode::FileNotFound,
+                    io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+                    _ => ErrorCode::Io,
+                };
+            } else if cause.is::<serde_yaml::Error>() || cause.is::<serde_json::Error>() {
+                return ErrorCode::InvalidConfig;
+            }
+        }
+        ErrorCode::Error
+    }
+}
+
+/// An error with an explicit [`ErrorCode`].
+#[derive(Debug)]
+pub struct CodedError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.message) }
+}
+
+impl std::error::Error for CodedError {}
+
+pub fn coded_error(code: ErrorCode, message: impl Into<String>) -> Error {
+    Error::new(CodedError { code, message: message.into() })
+}
+
+#[derive(Serialize)]
+struct ErrorRecord {
+    status: &'static str,
+    code: ErrorCode,
+    exit_code: i32,
+    message: String,
+    causes: Vec<String>,
+}
+
+/// Writes the final status record for a successful command.
+pub fn print_success() -> Result<()> {
+    print_json(&serde_json::json!({ "status": "ok", "exit_code": 0 }))
+}
+
+/// Writes an error diagnostic record, returning the exit code to use.
+pub fn print_error(e: &Error) -> i32 {
+    let code = ErrorCode::from_error(e);
+    let record = ErrorRecord {
+        status: "error",
+        code,
+        exit_code: code.exit_code(),
+        message: e.to_string(),
+        causes: e.chain().skip(1).map(|c| c.to_string()).collect(),
+    };
+    if print_json(&record).is_err() {
+        eprintln!("Failed: {e:?}");
+    }
+    code.exit_code()
+}
@@ -0,0 +1,444 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    analysis::cfa::SectionAddress,
+    obj::{ObjInfo, ObjSplit, ObjSymbol, ObjSymbolKind},
+    util::{
+        config::is_auto_symbol,
+        fingerprint::{match_objects, MatchMethod},
+    },
+};
+
+/// Why a symbol or split needs manual review after porting.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewReason {
+    /// No match with enough confidence; not ported.
+    Unmatched,
+    /// Ported, but the function's code changed between versions.
+    Changed,
+    /// A split boundary couldn't be mapped; not ported.
+    SplitBoundary,
+}
+
+impl ReviewReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReviewReason::Unmatched => "unmatched",
+            ReviewReason::Changed => "changed",
+            ReviewReason::SplitBoundary => "split boundary",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReviewItem {
+    /// Symbol or unit name.
+    pub name: String,
+    pub section: String,
+    pub from_address: u32,
+    pub to_address: Option<u32>,
+    pub confidence: Option<f32>,
+    pub reason: ReviewReason,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PortResult {
+    pub symbols: usize,
+    pub splits: usize,
+    pub review: Vec<ReviewItem>,
+}
+
+/// Ports the user-named symbols and the splits of `from_obj` to `to_obj`, another version
+/// of the same object.
+///
+/// Functions are mapped by fingerprint. Other symbols are mapped through the references
+/// made by matched functions of the same size, so both objects need relocations.
+pub fn port_object(
+    from_obj: &ObjInfo,
+    to_obj: &mut ObjInfo,
+    min_confidence: f32,
+) -> Result<PortResult> {
+    let (from, to, matches) = match_objects(from_obj, to_obj)?;
+    let mut result = PortResult::default();
+
+    let mut functions = BTreeMap::<SectionAddress, MappedFunction>::new();
+    for m in matches.iter().filter(|m| m.confidence >= min_confidence) {
+        functions.insert(from[m.from].address, MappedFunction {
+            from_size: from[m.from].size,
+            to_address: to[m.to].address,
+            to_size: to[m.to].size,
+            confidence: m.confidence,
+            method: m.method,
+        });
+    }
+
+    // Votes from the references of matched functions: from target -> to target -> count
+    let mut votes = BTreeMap::<SectionAddress, BTreeMap<SectionAddress, u32>>::new();
+    for m in matches.iter().filter(|m| m.confidence >= min_confidence) {
+        let (f, t) = (&from[m.from], &to[m.to]);
+        if f.size != t.size {
+            continue;
+        }
+        let f_section = &from_obj.sections[f.address.section];
+        let t_section = &to_obj.sections[t.address.section];
+        let f_start = f.address.address;
+        for (address, f_reloc) in f_section.relocations.range(f_start..f_start + f.size) {
+            let Some(t_reloc) = t_section.relocations.at(t.address.address + (address - f_start))
+            else {
+                continue;
+            };
+            if f_reloc.kind != t_reloc.kind || f_reloc.module.is_some() || t_reloc.module.is_some()
+            {
+                continue;
+            }
+            let f_target = &from_obj.symbols[f_reloc.target_symbol];
+            let t_target = &to_obj.symbols[t_reloc.target_symbol];
+            let (Some(f_target_section), Some(t_target_section)) =
+                (f_target.section, t_target.section)
+            else {
+                continue;
+            };
+            let f_addr = SectionAddress::new(
+                f_target_section,
+                (f_target.address as i64 + f_reloc.addend) as u32,
+            );
+            let t_addr = SectionAddress::new(
+                t_target_section,
+                (t_target.address as i64 + t_reloc.addend) as u32,
+            );
+            *votes.entry(f_addr).or_default().entry(t_addr).or_default() += 1;
+        }
+    }
+
+    // Port symbols
+    let mut ported = BTreeMap::<SectionAddress, (SectionAddress, u32)>::new();
+    let mut new_symbols = vec![];
+    for (_, symbol) in from_obj.symbols.iter_ordered() {
+        let Some(section_index) = symbol.section else { continue };
+        if symbol.kind == ObjSymbolKind::Section {
+            continue;
+        }
+        let section_name = &from_obj.sections[section_index].name;
+        let address = SectionAddress::new(section_index, symbol.address as u32);
+        let mapped = map_address(&functions, &votes, address);
+        if let Some((to_address, to_size, _)) = mapped {
+            ported.insert(address, (to_address, to_size.unwrap_or(symbol.size as u32)));
+        }
+        if is_auto_symbol(symbol) {
+            // Regenerated by analysis
+            continue;
+        }
+        let Some((to_address, to_size, confidence)) =
+            mapped.filter(|&(_, _, c)| c >= min_confidence)
+        else {
+            result.review.push(ReviewItem {
+                name: symbol.name.to_string(),
+                section: section_name.clone(),
+                from_address: address.address,
+                to_address: None,
+                confidence: None,
+                reason: ReviewReason::Unmatched,
+            });
+            continue;
+        };
+        if symbol.kind == ObjSymbolKind::Function
+            && functions.get(&address).is_some_and(|f| f.method != MatchMethod::Exact)
+        {
+            result.review.push(ReviewItem {
+                name: symbol.name.to_string(),
+                section: section_name.clone(),
+                from_address: address.address,
+                to_address: Some(to_address.address),
+                confidence: Some(confidence),
+                reason: ReviewReason::Changed,
+            });
+        }
+        new_symbols.push(ObjSymbol {
+            address: to_address.address as u64,
+            section: Some(to_address.section),
+            size: to_size.map(|s| s as u64).unwrap_or(symbol.size),
+            ..symbol.clone()
+        });
+    }
+    result.symbols = new_symbols.len();
+    for symbol in new_symbols {
+        to_obj.add_symbol(symbol, true)?;
+    }
+
+    // Port splits
+    let mut units = vec![];
+    for (section_index, section, start, split) in from_obj.sections.all_splits() {
+        let Some((to_section_index, to_section)) = to_obj.sections.by_name(&section.name)? else {
+            continue;
+        };
+        let section_end = (section.address + section.size) as u32;
+        let to_section_end = (to_section.address + to_section.size) as u32;
+        let map_boundary = |address: u32, is_end: bool| -> Option<u32> {
+            if is_end && address == 0 {
+                return Some(0);
+            } else if is_end && address == section_end {
+                return Some(to_section_end);
+            } else if !is_end && address == section.address as u32 {
+                return Some(to_section.address as u32);
+            }
+            let key = SectionAddress::new(section_index, address);
+            if let Some(&(to_address, _)) = ported.get(&key) {
+                return (to_address.section == to_section_index).then_some(to_address.address);
+            }
+            if !is_end {
+                return None;
+            }
+            // Symbol in the split ending at the boundary
+            let split_start = SectionAddress::new(section_index, start);
+            ported.range(split_start..key).rev().find_map(|(from_address, &(to_address, size))| {
+                let from_end = from_obj
+                    .symbols
+                    .at_section_address(section_index, from_address.address)
+                    .map(|(_, s)| from_address.address + s.size as u32)
+                    .max()?;
+                (from_end == address && to_address.section == to_section_index)
+                    .then_some(to_address.address + size)
+            })
+        };
+        match (map_boundary(start, false), map_boundary(split.end, true)) {
+            (Some(to_start), Some(to_end)) if to_end == 0 || to_start < to_end => {
+                to_obj.add_split(to_section_index, to_start, ObjSplit {
+                    end: to_end,
+                    ..split.clone()
+                })?;
+                if !units.contains(&split.unit) {
+                    units.push(split.unit.clone());
+                }
+                result.splits += 1;
+            }
+            (to_start, _) => result.review.push(ReviewItem {
+                name: split.unit.clone(),
+                section: section.name.clone(),
+                from_address: start,
+                to_address: to_start,
+                confidence: None,
+                reason: ReviewReason::SplitBoundary,
+            }),
+        }
+    }
+    for unit in &from_obj.link_order {
+        if units.contains(&unit.name) && !to_obj.link_order.iter().any(|u| u.name == unit.name) {
+            to_obj.link_order.push(unit.clone());
+        }
+    }
+    for pattern in &from_obj.unit_patterns {
+        if !to_obj.unit_patterns.iter().any(|p| p.pattern == pattern.pattern) {
+            to_obj.unit_patterns.push(pattern.clone());
+        }
+    }
+    Ok(result)
+}
+
+struct MappedFunction {
+    from_size: u32,
+    to_address: SectionAddress,
+    to_size: u32,
+    confidence: f32,
+    method: MatchMethod,
+}
+
+/// Maps an address to the other object, returning the new address, size (if known) and
+/// confidence.
+fn map_address(
+    functions: &BTreeMap<SectionAddress, MappedFunction>,
+    votes: &BTreeMap<SectionAddress, BTreeMap<SectionAddress, u32>>,
+    address: SectionAddress,
+) -> Option<(SectionAddress, Option<u32>, f32)> {
+    // Start of a matched function
+    if let Some(f) = functions.get(&address) {
+        return Some((f.to_address, Some(f.to_size), f.confidence));
+    }
+    // Referenced by matched functions
+    if let Some(targets) = votes.get(&address) {
+        let total = targets.values().sum::<u32>();
+        let (&to_address, &count) = targets.iter().max_by_key(|&(_, &count)| count)?;
+        return Some((to_address, None, count as f32 / total as f32));
+    }
+    // Inside a matched function that didn't change size
+    let (&start, f) = functions.range(..address).next_back()?;
+    (start.section == address.section
+        && address.address < start.address + f.from_size
+        && f.from_size == f.to_size)
+        .then(|| {
+            (f.to_address.offset((address.address - start.address) as i32), None, f.confidence)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::{
+        ObjArchitecture, ObjKind, ObjReloc, ObjRelocKind, ObjSection, ObjSectionKind,
+        ObjSymbolFlagSet, ObjSymbolFlags, ObjUnit,
+    };
+
+    /// Ten instructions with no address fields, distinct per seed.
+    fn code(seed: u32) -> Vec<u8> {
+        (0..10u32).flat_map(|i| (0x7C000214 | (seed << 16) | (i << 11)).to_be_bytes()).collect()
+    }
+
+    fn symbol(name: &str, section: usize, address: u32, kind: ObjSymbolKind) -> ObjSymbol {
+        let size = if kind == ObjSymbolKind::Function { 0x28 } else { 0x10 };
+        ObjSymbol {
+            name: name.into(),
+            address: address as u64,
+            section: Some(section),
+            size,
+            size_known: true,
+            flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
+            kind,
+            ..Default::default()
+        }
+    }
+
+    /// An object whose functions are laid out from `text`, the first referencing the data
+    /// symbol at `target` through a pair of `@ha`/`@l` relocations.
+    fn object(
+        text: u32,
+        data: u32,
+        seeds: &[u32],
+        symbols: Vec<ObjSymbol>,
+        target: usize,
+    ) -> ObjInfo {
+        let mut text_section = ObjSection::raw(
+            ".text",
+            ObjSectionKind::Code,
+            text,
+            seeds.iter().flat_map(|&seed| code(seed)).collect(),
+        );
+        for (offset, kind) in [(0, ObjRelocKind::PpcAddr16Ha), (4, ObjRelocKind::PpcAddr16Lo)] {
+            let reloc = ObjReloc { kind, target_symbol: target, addend: 0, module: None };
+            text_section.relocations.insert(text + offset, reloc).unwrap();
+        }
+        let data_section = ObjSection::raw(".data", ObjSectionKind::Data, data, vec![0; 0x40]);
+        ObjInfo::new(
+            ObjKind::Executable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            symbols,
+            vec![text_section, data_section],
+        )
+    }
+
+    fn split(unit: &str, end: u32) -> ObjSplit {
+        ObjSplit {
+            unit: unit.to_string(),
+            end,
+            align: None,
+            common: false,
+            autogenerated: false,
+            skip: false,
+            rename: None,
+            pad: None,
+            end_align: None,
+        }
+    }
+
+    #[test]
+    fn port_symbols_and_splits() {
+        let function = ObjSymbolKind::Function;
+        let mut from_obj = object(
+            0x80003000,
+            0x80100000,
+            &[1, 2, 3],
+            vec![
+                symbol("PlayerUpdate", 0, 0x80003000, function),
+                symbol("PlayerDraw", 0, 0x80003028, function),
+                symbol("OldFunc", 0, 0x80003050, function),
+                symbol("gPlayer", 1, 0x80100010, ObjSymbolKind::Object),
+            ],
+            3,
+        );
+        for (section, start, unit, end) in [
+            (0, 0x80003000, "a.c", 0x80003028),
+            (0, 0x80003028, "b.c", 0x80003078),
+            (1, 0x80100010, "a.c", 0x80100020),
+        ] {
+            from_obj.add_split(section, start, split(unit, end)).unwrap();
+        }
+        for unit in ["a.c", "b.c"] {
+            from_obj.link_order.push(ObjUnit { name: unit.to_string(), ..Default::default() });
+        }
+        // The next version dropped OldFunc and moved everything
+        let mut to_obj = object(
+            0x80004000,
+            0x80200000,
+            &[1, 2],
+            vec![
+                symbol("fn_80004000", 0, 0x80004000, function),
+                symbol("fn_80004028", 0, 0x80004028, function),
+                symbol("lbl_80200020", 1, 0x80200020, ObjSymbolKind::Object),
+            ],
+            2,
+        );
+
+        let result = port_object(&from_obj, &mut to_obj, 0.5).unwrap();
+        assert_eq!(result.symbols, 3);
+        let address = |name: &str| {
+            let (_, symbol) = to_obj.symbols.by_name(name).unwrap().unwrap();
+            (symbol.section.unwrap(), symbol.address as u32)
+        };
+        assert_eq!(address("PlayerUpdate"), (0, 0x80004000));
+        assert_eq!(address("PlayerDraw"), (0, 0x80004028));
+        // Found through the votes of PlayerUpdate's relocations
+        assert_eq!(address("gPlayer"), (1, 0x80200020));
+        assert!(to_obj.symbols.by_name("OldFunc").unwrap().is_none());
+        let review = result.review.iter().map(|r| (r.name.as_str(), r.reason)).collect::<Vec<_>>();
+        assert_eq!(review, [("OldFunc", ReviewReason::Unmatched)]);
+
+        // Boundaries at section ends, ported functions and the end of a ported symbol
+        assert_eq!(result.splits, 3);
+        let splits = to_obj
+            .sections
+            .all_splits()
+            .map(|(index, _, start, split)| (index, split.unit.as_str(), start, split.end))
+            .collect::<Vec<_>>();
+        assert_eq!(splits, [
+            (0, "a.c", 0x80004000, 0x80004028),
+            (0, "b.c", 0x80004028, 0x80004050),
+            (1, "a.c", 0x80200020, 0x80200030),
+        ]);
+        let units = to_obj.link_order.iter().map(|u| u.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(units, ["a.c", "b.c"]);
+    }
+
+    #[test]
+    fn map_address_by_votes() {
+        let mut functions = BTreeMap::new();
+        functions.insert(SectionAddress::new(0, 0x80003000), MappedFunction {
+            from_size: 0x20,
+            to_address: SectionAddress::new(0, 0x80004000),
+            to_size: 0x20,
+            confidence: 1.0,
+            method: MatchMethod::Exact,
+        });
+        let mut votes = BTreeMap::new();
+        votes.insert(
+            SectionAddress::new(1, 0x80100000),
+            BTreeMap::from([
+                (SectionAddress::new(1, 0x80200000), 3),
+                (SectionAddress::new(1, 0x80200010), 1),
+            ]),
+        );
+        // The most common target wins, with its share of the votes as confidence
+        assert_eq!(
+            map_address(&functions, &votes, SectionAddress::new(1, 0x80100000)),
+            Some((SectionAddress::new(1, 0x80200000), None, 0.75))
+        );
+        // Inside a function of the same size
+        assert_eq!(
+            map_address(&functions, &votes, SectionAddress::new(0, 0x80003010)),
+            Some((SectionAddress::new(0, 0x80004010), None, 1.0))
+        );
+        assert_eq!(map_address(&functions, &votes, SectionAddress::new(0, 0x80003020)), None);
+    }
+}
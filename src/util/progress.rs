@@ -0,0 +1,32 @@
+use std::{borrow::Cow, io::IsTerminal};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::util::output::is_json;
+
+/// Progress is only drawn for interactive terminals, and never in JSON mode.
+fn enabled() -> bool { !is_json() && std::io::stderr().is_terminal() }
+
+fn new_bar(len: u64, template: &str, message: Cow<'static, str>) -> ProgressBar {
+    if !enabled() {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template(template)
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> ");
+    ProgressBar::new(len).with_style(style).with_message(message)
+}
+
+/// Creates a progress bar counting discrete items (modules, files, entries).
+pub fn item_progress(len: u64, message: impl Into<Cow<'static, str>>) -> ProgressBar {
+    new_bar(len, "{msg} [{bar:40}] {pos}/{len} ({elapsed}, ETA {eta})", message.into())
+}
+
+/// Creates a progress bar counting bytes processed.
+pub fn byte_progress(len: u64, message: impl Into<Cow<'static, str>>) -> ProgressBar {
+    new_bar(
+        len,
+        "{msg} [{bar:40}] {bytes}/{total_bytes} ({binary_bytes_per_sec}, ETA {eta})",
+        message.into(),
+    )
+}
@@ -0,0 +1,96 @@
+use tracing::{info, warn};
+
+use crate::array_ref;
+
+pub const MEM1_ADDRESS: u32 = 0x80000000;
+pub const MEM2_ADDRESS: u32 = 0x90000000;
+/// `OSModuleQueue`, the list of linked modules
+const MODULE_QUEUE_ADDRESS: u32 = 0x800030C8;
+
+/// Dumped memory regions, by start address.
+pub struct Memory {
+    pub regions: Vec<(u32, Vec<u8>)>,
+}
+
+impl Memory {
+    /// Reads `len` bytes at a cached or uncached address.
+    pub fn read(&self, address: u32, len: u32) -> Option<&[u8]> {
+        let address = address & !0x40000000;
+        self.regions.iter().find_map(|(start, data)| {
+            let offset = address.checked_sub(*start)? as usize;
+            data.get(offset..offset.checked_add(len as usize)?)
+        })
+    }
+
+    pub fn read_u32(&self, address: u32) -> Option<u32> {
+        self.read(address, 4).map(|data| u32::from_be_bytes(*array_ref!(data, 0, 4)))
+    }
+}
+
+/// A module linked in memory.
+pub struct LinkedModule {
+    /// Address of the module header
+    pub address: u32,
+    pub module_id: u32,
+    /// Address and size of each section (BSS included), indexed like the REL's section table.
+    pub sections: Vec<(u32, u32)>,
+}
+
+/// Reads a linked module's header, whose section table holds addresses once linked.
+fn read_module(memory: &Memory, address: u32) -> Option<LinkedModule> {
+    let module_id = memory.read_u32(address)?;
+    let num_sections = memory.read_u32(address + 0xC)?;
+    let section_info = memory.read_u32(address + 0x10)?;
+    if num_sections > 64 || section_info < MEM1_ADDRESS {
+        return None;
+    }
+    let mut sections = Vec::with_capacity(num_sections as usize);
+    for idx in 0..num_sections {
+        let offset = memory.read_u32(section_info + idx * 8)? & !1;
+        let size = memory.read_u32(section_info + idx * 8 + 4)?;
+        sections.push((offset, size));
+    }
+    Some(LinkedModule { address, module_id, sections })
+}
+
+/// Follows the OS module list. If it's empty, searches for headers of the given modules.
+pub fn find_linked_modules(memory: &Memory, module_ids: &[u32]) -> Vec<LinkedModule> {
+    let mut modules = vec![];
+    let mut next = memory.read_u32(MODULE_QUEUE_ADDRESS).unwrap_or(0);
+    while next != 0 && modules.len() < 256 {
+        let Some(module) = read_module(memory, next) else {
+            warn!("Invalid module in the module list at {:#010X}", next);
+            break;
+        };
+        modules.push(module);
+        next = memory.read_u32(next + 4).unwrap_or(0);
+    }
+    if !modules.is_empty() {
+        return modules;
+    }
+
+    info!("Module list is empty, searching for module headers");
+    for (start, data) in &memory.regions {
+        for offset in (0..data.len() as u32).step_by(32) {
+            let address = start + offset;
+            let Some(module_id) = memory.read_u32(address) else { break };
+            if module_id == 0 || !module_ids.contains(&module_id) {
+                continue;
+            }
+            // The section table directly follows the header
+            let header_size = match memory.read_u32(address + 0x1C) {
+                Some(1) => 0x40,
+                Some(2) => 0x48,
+                Some(3) => 0x4C,
+                _ => continue,
+            };
+            if memory.read_u32(address + 0x10) != Some(address + header_size) {
+                continue;
+            }
+            if let Some(module) = read_module(memory, address) {
+                modules.push(module);
+            }
+        }
+    }
+    modules
+}
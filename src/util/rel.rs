@@ -1,19 +1,26 @@
 use std::{
-    cmp::Ordering,
-    io,
-    io::{Read, Seek, SeekFrom, Write},
+    collections::BTreeMap,
+    ffi::OsStr,
+    fmt, io,
+    io::{Cursor, Read, Seek, SeekFrom, Write},
+    str::FromStr,
+    sync::RwLock,
 };
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
+use argp::FromArgValue;
 use itertools::Itertools;
 use object::{elf, Object, ObjectSection, ObjectSymbol};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
 use tracing::warn;
 
 use crate::{
+    analysis::cfa::SectionAddress,
     array_ref_mut,
     obj::{
         ObjArchitecture, ObjInfo, ObjKind, ObjRelocKind, ObjSection, ObjSectionKind, ObjSymbol,
-        ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind,
+        ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind, ObjSymbolSource,
     },
     util::{
         align_up,
@@ -229,10 +236,13 @@ impl ToWriter for RelHeader {
     }
 }
 
+/// Import table entry, pointing to the relocations targeting one module.
 #[derive(Copy, Clone, Debug)]
-struct RelImport {
-    module_id: u32,
-    offset: u32,
+pub struct RelImport {
+    /// Module the relocations target. 0 is the DOL.
+    pub module_id: u32,
+    /// Offset to the start of the module's relocation list.
+    pub offset: u32,
 }
 
 impl FromReader for RelImport {
@@ -307,11 +317,13 @@ impl RelSectionHeader {
 }
 
 #[derive(Copy, Clone, Debug)]
-struct RelRelocRaw {
-    offset: u16,
-    kind: u8,
-    section: u8,
-    addend: u32,
+pub struct RelRelocRaw {
+    /// Offset from the previous relocation.
+    pub offset: u16,
+    pub kind: u8,
+    /// Section of the target module.
+    pub section: u8,
+    pub addend: u32,
 }
 
 impl FromReader for RelRelocRaw {
@@ -348,6 +360,35 @@ impl ToWriter for RelRelocRaw {
     fn write_size(&self) -> usize { Self::STATIC_SIZE }
 }
 
+static MODULE_NAMES: RwLock<BTreeMap<u32, String>> = RwLock::new(BTreeMap::new());
+
+/// Sets the display name for a module ID, used in logs and diagnostics.
+/// If `overwrite` is false, an existing name is kept.
+pub fn set_module_name(module_id: u32, name: impl Into<String>, overwrite: bool) {
+    let mut names = MODULE_NAMES.write().unwrap();
+    if overwrite || !names.contains_key(&module_id) {
+        names.insert(module_id, name.into());
+    }
+}
+
+/// Returns the display name for a module ID, if one is known.
+pub fn module_name(module_id: u32) -> Option<String> {
+    MODULE_NAMES.read().unwrap().get(&module_id).cloned()
+}
+
+/// Displays a module ID as `name (ID n)` if its name is known, otherwise `module n`.
+#[derive(Debug, Copy, Clone)]
+pub struct DisplayModule(pub u32);
+
+impl fmt::Display for DisplayModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match module_name(self.0) {
+            Some(name) => write!(f, "{} (ID {})", name, self.0),
+            None => write!(f, "module {}", self.0),
+        }
+    }
+}
+
 pub fn process_rel_header<R>(reader: &mut R) -> Result<RelHeader>
 where R: Read + Seek + ?Sized {
     RelHeader::from_reader(reader, Endian::Big).context("Failed to read REL header")
@@ -370,6 +411,19 @@ where
     Ok(sections)
 }
 
+pub fn process_rel_imports<R>(reader: &mut R, header: &RelHeader) -> Result<Vec<RelImport>>
+where R: Read + Seek + ?Sized {
+    let count = header.imp_size / RelImport::STATIC_SIZE as u32;
+    let mut imports = Vec::with_capacity(count as usize);
+    reader.seek(SeekFrom::Start(header.imp_offset as u64))?;
+    for idx in 0..count {
+        let import = RelImport::from_reader(reader, Endian::Big)
+            .with_context(|| format!("Failed to read REL import {}", idx))?;
+        imports.push(import);
+    }
+    Ok(imports)
+}
+
 pub fn process_rel<R>(reader: &mut R, name: &str) -> Result<(RelHeader, ObjInfo)>
 where R: Read + Seek + ?Sized {
     let header = process_rel_header(reader)?;
@@ -437,22 +491,30 @@ where R: Read + Seek + ?Sized {
     let mut add_symbol =
         |rel_section_idx: u8, offset: u32, name: &str, force_active: bool| -> Result<()> {
             if rel_section_idx > 0 {
-                let (section_index, _) = sections
+                let (section_index, section) = sections
                     .iter()
                     .enumerate()
                     .find(|&(_, section)| section.elf_index == rel_section_idx as usize)
                     .ok_or_else(|| anyhow!("Failed to locate {name} section {rel_section_idx}"))?;
+                if section.kind != ObjSectionKind::Code || offset as u64 >= section.size {
+                    warn!(
+                        "Ignoring {name} at section {rel_section_idx} offset {offset:#X}: \
+                         not within a code section"
+                    );
+                    return Ok(());
+                }
                 log::debug!("Adding {name} section {rel_section_idx} offset {offset:#X}");
                 let mut flags = ObjSymbolFlagSet(ObjSymbolFlags::Global.into());
                 if force_active {
                     flags.set_force_active(true);
                 }
                 symbols.push(ObjSymbol {
-                    name: name.to_string(),
+                    name: name.into(),
                     address: offset as u64,
                     section: Some(section_index),
                     flags,
                     kind: ObjSymbolKind::Function,
+                    source: ObjSymbolSource::Binary,
                     ..Default::default()
                 });
             }
@@ -462,25 +524,186 @@ where R: Read + Seek + ?Sized {
     add_symbol(header.epilog_section, header.epilog_offset, "_epilog", true)?;
     add_symbol(header.unresolved_section, header.unresolved_offset, "_unresolved", true)?;
 
-    let mut unresolved_relocations = Vec::new();
-    let mut imp_idx = 0;
-    let imp_end = (header.imp_offset + header.imp_size) as u64;
-    reader.seek(SeekFrom::Start(header.imp_offset as u64))?;
-    while reader.stream_position()? < imp_end {
-        let import = RelImport::from_reader(reader, Endian::Big)?;
+    let unresolved_relocations = RelRelocations::read(reader, &header)?;
+
+    log::debug!("Read REL ID {}", header.module_id);
+    let mut obj = ObjInfo::new(
+        ObjKind::Relocatable,
+        ObjArchitecture::PowerPc,
+        name.to_string(),
+        symbols,
+        sections,
+    );
+    obj.module_id = header.module_id;
+    obj.unresolved_relocations = unresolved_relocations;
+
+    // Add functions from extabindex entries as known function bounds
+    let functions = extabindex_functions(&obj, obj.unresolved_relocations.index()?);
+    obj.known_functions.extend(functions.into_iter().map(|(addr, size)| (addr, Some(size))));
+    Ok((header, obj))
+}
 
-        if imp_idx == 0 {
+/// Functions and their sizes from the extabindex section of a REL, by its relocations (by
+/// section and address). Only absolute relocations within the module are considered.
+///
+/// REL sections have no names, so extabindex is the one data section made up entirely of
+/// entries pointing into the code section and to exception table data.
+fn extabindex_functions(
+    obj: &ObjInfo,
+    relocations: &BTreeMap<(u8, u32), RelReloc>,
+) -> Vec<(SectionAddress, u32)> {
+    let Some((text_index, text)) = obj.sections.by_kind(ObjSectionKind::Code).next() else {
+        return vec![];
+    };
+    let target = |section: &ObjSection, address: u32| {
+        let reloc = relocations.get(&(section.elf_index as u8, address))?;
+        if reloc.module_id != obj.module_id || reloc.kind != ObjRelocKind::Absolute {
+            return None;
+        }
+        obj.sections
+            .iter()
+            .find(|(_, s)| s.elf_index == reloc.target_section as usize)
+            .map(|(_, target)| (target, reloc.addend))
+    };
+    let mut found = obj.sections.iter().filter_map(|(_, section)| {
+        if section.section_known
+            || section.kind != ObjSectionKind::Data
+            || section.size == 0
+            || section.size % 12 != 0
+        {
+            return None;
+        }
+        let mut entries = vec![];
+        for (i, entry) in section.data.chunks_exact(12).enumerate() {
+            let address = section.address as u32 + i as u32 * 12;
+            let (function, function_addr) = target(section, address)?;
+            let (extab, _) = target(section, address + 8)?;
+            let size = u32::from_be_bytes(entry[4..8].try_into().unwrap());
+            if function.elf_index != text.elf_index
+                || extab.kind != ObjSectionKind::Data
+                || extab.elf_index == section.elf_index
+                || size == 0
+                || size & 3 != 0
+                || function_addr as u64 + size as u64 > text.size
+            {
+                return None;
+            }
+            entries.push((SectionAddress::new(text_index, function_addr), size));
+        }
+        Some(entries)
+    });
+    match (found.next(), found.next()) {
+        (Some(entries), None) => entries,
+        (Some(_), Some(_)) => {
+            log::debug!("Found multiple possible extabindex sections");
+            vec![]
+        }
+        _ => vec![],
+    }
+}
+
+/// Streams relocations from a REL's import table, one at a time.
+pub struct RelRelocReader<'a, R>
+where R: Read + Seek
+{
+    reader: R,
+    header: &'a RelHeader,
+    /// Position of the next import table entry.
+    imp_position: u64,
+    imp_end: u64,
+    imp_idx: usize,
+    /// Current import, if reading its relocation list.
+    import: Option<RelImport>,
+    address: u32,
+    section: u8,
+    /// File size, once known.
+    file_size: Option<u64>,
+    /// Keep the exact offset of 16-bit relocations instead of their instruction's.
+    exact: bool,
+    /// Don't warn about discarded relocations.
+    quiet: bool,
+    /// Only read the relocations against this module.
+    module_id: Option<u32>,
+}
+
+impl<'a, R> RelRelocReader<'a, R>
+where R: Read + Seek
+{
+    pub fn new(reader: R, header: &'a RelHeader) -> Self {
+        Self {
+            reader,
+            header,
+            imp_position: header.imp_offset as u64,
+            imp_end: (header.imp_offset + header.imp_size) as u64,
+            imp_idx: 0,
+            import: None,
+            address: 0,
+            section: u8::MAX,
+            file_size: None,
+            exact: false,
+            quiet: false,
+            module_id: None,
+        }
+    }
+
+    /// Reports relocation addresses as stored, instead of aligned to the instruction. Needed
+    /// to write the relocations back unchanged.
+    pub fn exact_addresses(mut self) -> Self {
+        self.exact = true;
+        self
+    }
+
+    /// Skips the warnings for discarded relocations, for data that's been read before.
+    pub fn quiet(mut self) -> Self {
+        self.quiet = true;
+        self
+    }
+
+    /// Skips the imports of all modules but `module_id`, without reading their relocations.
+    pub fn for_module(mut self, module_id: u32) -> Self {
+        self.module_id = Some(module_id);
+        self
+    }
+
+    /// Whether relocation data at `position` is missing, as in a module that was truncated
+    /// after `OSLinkFixed` discarded the data past `fix_size`.
+    fn is_discarded(&mut self, position: u64) -> Result<bool> {
+        let Some(fix_size) = self.header.fix_size else { return Ok(false) };
+        if position < fix_size as u64 {
+            return Ok(false);
+        }
+        let file_size = match self.file_size {
+            Some(size) => size,
+            None => {
+                let current = self.reader.stream_position()?;
+                let size = self.reader.seek(SeekFrom::End(0))?;
+                self.reader.seek(SeekFrom::Start(current))?;
+                *self.file_size.insert(size)
+            }
+        };
+        Ok(position + RelRelocRaw::STATIC_SIZE as u64 > file_size)
+    }
+
+    fn next_import(&mut self) -> Result<Option<RelImport>> {
+        if self.imp_position >= self.imp_end {
+            return Ok(None);
+        }
+        self.reader.seek(SeekFrom::Start(self.imp_position))?;
+        let import = RelImport::from_reader(&mut self.reader, Endian::Big)?;
+        self.imp_position = self.reader.stream_position()?;
+
+        if self.imp_idx == 0 {
             ensure!(
-                import.offset == header.rel_offset,
+                import.offset == self.header.rel_offset,
                 "imp index 0 offset mismatch: {:#X} != {:#X}",
                 import.offset,
-                header.rel_offset
+                self.header.rel_offset
             );
         }
-        imp_idx += 1;
+        self.imp_idx += 1;
 
-        if import.module_id == header.module_id {
-            if let Some(fix_size) = header.fix_size {
+        if import.module_id == self.header.module_id {
+            if let Some(fix_size) = self.header.fix_size {
                 ensure!(
                     fix_size == import.offset,
                     "fix_size mismatch: {:#X} != {:#X}",
@@ -490,12 +713,50 @@ where R: Read + Seek + ?Sized {
             }
         }
 
-        let position = reader.stream_position()?;
-        reader.seek(SeekFrom::Start(import.offset as u64))?;
-        let mut address = 0u32;
-        let mut section = u8::MAX;
+        if self.is_discarded(import.offset as u64)? {
+            if !self.quiet {
+                warn!(
+                    "Relocations against module {} at {:#X} were discarded after fix_size {:#X}",
+                    import.module_id,
+                    import.offset,
+                    self.header.fix_size.unwrap_or_default()
+                );
+            }
+            return self.next_import();
+        }
+        if self.module_id.is_some_and(|id| id != import.module_id) {
+            return self.next_import();
+        }
+        self.reader.seek(SeekFrom::Start(import.offset as u64))?;
+        self.address = 0;
+        self.section = u8::MAX;
+        Ok(Some(import))
+    }
+
+    fn next_reloc(&mut self) -> Result<Option<RelReloc>> {
         loop {
-            let reloc = RelRelocRaw::from_reader(reader, Endian::Big)?;
+            let import = match self.import {
+                Some(import) => import,
+                None => match self.next_import()? {
+                    Some(import) => {
+                        self.import = Some(import);
+                        import
+                    }
+                    None => return Ok(None),
+                },
+            };
+            let position = self.reader.stream_position()?;
+            if self.is_discarded(position)? {
+                if !self.quiet {
+                    warn!(
+                        "Relocations against module {} are truncated at {:#X}",
+                        import.module_id, position
+                    );
+                }
+                self.import = None;
+                continue;
+            }
+            let reloc = RelRelocRaw::from_reader(&mut self.reader, Endian::Big)?;
             let kind = match reloc.kind as u32 {
                 elf::R_PPC_NONE => continue,
                 elf::R_PPC_ADDR32 | elf::R_PPC_UADDR32 => ObjRelocKind::Absolute,
@@ -512,45 +773,132 @@ where R: Read + Seek + ?Sized {
                 // elf::R_PPC_REL14_BRTAKEN => ObjRelocKind::PpcRel14BrTaken,
                 // elf::R_PPC_REL14_BRNTAKEN => ObjRelocKind::PpcRel14BrnTaken,
                 R_DOLPHIN_NOP => {
-                    address += reloc.offset as u32;
+                    self.address += reloc.offset as u32;
                     continue;
                 }
                 R_DOLPHIN_SECTION => {
-                    address = 0;
-                    section = reloc.section;
+                    self.address = 0;
+                    self.section = reloc.section;
+                    continue;
+                }
+                R_DOLPHIN_END => {
+                    self.import = None;
                     continue;
                 }
-                R_DOLPHIN_END => break,
                 // R_DOLPHIN_MRKREF => ?
                 reloc_type => bail!("Unhandled REL relocation type {reloc_type}"),
             };
-            address += reloc.offset as u32;
-            let reloc = RelReloc {
+            self.address += reloc.offset as u32;
+            return Ok(Some(RelReloc {
                 kind,
-                section,
-                address: address & !3,
+                section: self.section,
+                address: if self.exact { self.address } else { self.address & !3 },
                 module_id: import.module_id,
                 target_section: reloc.section,
                 addend: reloc.addend,
-                original_section: section,
+                original_section: self.section,
                 original_target_section: reloc.section,
-            };
-            unresolved_relocations.push(reloc);
+            }));
         }
-        reader.seek(SeekFrom::Start(position))?;
     }
+}
 
-    log::debug!("Read REL ID {}", header.module_id);
-    let mut obj = ObjInfo::new(
-        ObjKind::Relocatable,
-        ObjArchitecture::PowerPc,
-        name.to_string(),
-        symbols,
-        sections,
-    );
-    obj.module_id = header.module_id;
-    obj.unresolved_relocations = unresolved_relocations;
-    Ok((header, obj))
+impl<R> Iterator for RelRelocReader<'_, R>
+where R: Read + Seek
+{
+    type Item = Result<RelReloc>;
+
+    fn next(&mut self) -> Option<Self::Item> { self.next_reloc().transpose() }
+}
+
+/// A module's relocations, kept in their file encoding and decoded each time they're iterated.
+///
+/// Decoded, a relocation takes more than twice the space, and a game can load dozens of modules
+/// with tens of thousands of relocations each. Only the modules being analyzed need lookups by
+/// address, so the index for those is built on first use.
+#[derive(Debug, Clone, Default)]
+pub struct RelRelocations {
+    header: Option<RelHeader>,
+    /// File offset of `data`.
+    base: u64,
+    /// The import table and relocation data, through the end of the file.
+    data: Vec<u8>,
+    /// Relocations by source section and address.
+    index: OnceCell<BTreeMap<(u8, u32), RelReloc>>,
+}
+
+impl RelRelocations {
+    pub fn read<R>(reader: &mut R, header: &RelHeader) -> Result<Self>
+    where R: Read + Seek + ?Sized {
+        let base = header.imp_offset.min(header.rel_offset) as u64;
+        reader.seek(SeekFrom::Start(base))?;
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+        let relocations = Self { header: Some(header.clone()), base, data, index: OnceCell::new() };
+        // Decode everything once, so that errors and warnings are reported on load
+        for result in RelRelocReader::new(relocations.reader(), header) {
+            result?;
+        }
+        Ok(relocations)
+    }
+
+    fn reader(&self) -> OffsetReader<'_> {
+        OffsetReader { inner: Cursor::new(self.data.as_slice()), base: self.base }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Result<RelReloc>> + '_ {
+        self.header
+            .iter()
+            .flat_map(move |header| RelRelocReader::new(self.reader(), header).quiet())
+    }
+
+    /// The relocations against `module_id`, skipping those against other modules.
+    pub fn iter_module(&self, module_id: u32) -> impl Iterator<Item = Result<RelReloc>> + '_ {
+        self.header.iter().flat_map(move |header| {
+            RelRelocReader::new(self.reader(), header).quiet().for_module(module_id)
+        })
+    }
+
+    /// The relocations by source section and address, decoded once.
+    pub fn index(&self) -> Result<&BTreeMap<(u8, u32), RelReloc>> {
+        self.index.get_or_try_init(|| {
+            let mut index = BTreeMap::new();
+            for result in self.iter() {
+                let reloc = result?;
+                index.entry((reloc.section, reloc.address)).or_insert(reloc);
+            }
+            Ok(index)
+        })
+    }
+
+    /// The relocation at `address` in the section with ELF index `section`.
+    pub fn at(&self, section: u8, address: u32) -> Result<Option<&RelReloc>> {
+        Ok(self.index()?.get(&(section, address)))
+    }
+}
+
+/// Reads data taken from `base` onwards in a file at its original file offsets.
+struct OffsetReader<'a> {
+    inner: Cursor<&'a [u8]>,
+    base: u64,
+}
+
+impl Read for OffsetReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> { self.inner.read(buf) }
+}
+
+impl Seek for OffsetReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => {
+                SeekFrom::Start(offset.checked_sub(self.base).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "Seek before relocation data")
+                })?)
+            }
+            pos => pos,
+        };
+        Ok(self.inner.seek(pos)? + self.base)
+    }
 }
 
 pub fn print_relocations<R>(reader: &mut R, header: &RelHeader) -> Result<()>
@@ -600,6 +948,311 @@ where R: Read + Seek + ?Sized {
     Ok(())
 }
 
+/// Number of buckets in a section's relocation density heatmap.
+pub const HEATMAP_BUCKETS: usize = 64;
+
+/// Relocation counts of a REL, by kind, target module and target section.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RelRelocStats {
+    pub total: usize,
+    pub by_kind: BTreeMap<String, usize>,
+    pub by_module: BTreeMap<u32, usize>,
+    pub by_target_section: Vec<TargetSectionCount>,
+    pub sections: Vec<RelSectionStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetSectionCount {
+    pub module_id: u32,
+    pub section: u8,
+    pub count: usize,
+}
+
+/// Relocations applied to one section of the REL.
+#[derive(Debug, Clone, Serialize)]
+pub struct RelSectionStats {
+    pub index: u8,
+    pub size: u32,
+    pub count: usize,
+    /// Relocation counts for `HEATMAP_BUCKETS` equal parts of the section.
+    pub density: Vec<usize>,
+}
+
+pub fn rel_reloc_stats<R>(reader: &mut R) -> Result<RelRelocStats>
+where R: Read + Seek + ?Sized {
+    reader.seek(SeekFrom::Start(0))?;
+    let header = process_rel_header(reader)?;
+    let section_headers = process_rel_sections(reader, &header)?;
+    let mut stats = RelRelocStats::default();
+    let mut by_target_section = BTreeMap::<(u32, u8), usize>::new();
+    let mut sections = BTreeMap::<u8, RelSectionStats>::new();
+    for result in RelRelocReader::new(&mut *reader, &header) {
+        let reloc = result?;
+        stats.total += 1;
+        *stats.by_kind.entry(format!("{:?}", reloc.kind)).or_default() += 1;
+        *stats.by_module.entry(reloc.module_id).or_default() += 1;
+        *by_target_section.entry((reloc.module_id, reloc.target_section)).or_default() += 1;
+        let size = section_headers.get(reloc.section as usize).map_or(0, |s| s.size());
+        let section = sections.entry(reloc.section).or_insert_with(|| RelSectionStats {
+            index: reloc.section,
+            size,
+            count: 0,
+            density: vec![0; HEATMAP_BUCKETS],
+        });
+        section.count += 1;
+        if size > 0 {
+            let bucket = (reloc.address as u64 * HEATMAP_BUCKETS as u64 / size as u64) as usize;
+            section.density[bucket.min(HEATMAP_BUCKETS - 1)] += 1;
+        }
+    }
+    stats.by_target_section = by_target_section
+        .into_iter()
+        .map(|((module_id, section), count)| TargetSectionCount { module_id, section, count })
+        .collect();
+    stats.sections = sections.into_values().collect();
+    Ok(stats)
+}
+
+/// Validates the internal consistency of a REL, returning a description of each problem found.
+///
+/// Errors are only returned for I/O failures; malformed data is reported as a problem.
+pub fn check_rel<R>(reader: &mut R) -> Result<Vec<String>>
+where R: Read + Seek + ?Sized {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let header = process_rel_header(reader)?;
+    let mut problems = vec![];
+
+    let table_end = header.section_info_offset as u64
+        + header.num_sections as u64 * RelSectionHeader::STATIC_SIZE as u64;
+    if table_end > file_size {
+        problems.push(format!(
+            "Section table {:#X}..{:#X} extends past end of file ({:#X})",
+            header.section_info_offset, table_end, file_size
+        ));
+        return Ok(problems);
+    }
+    let sections = process_rel_sections(reader, &header)?;
+
+    // Sections
+    let mut total_bss_size = 0;
+    let mut prev_end = None;
+    for (idx, section) in sections.iter().enumerate() {
+        let (offset, size) = (section.offset(), section.size());
+        if size == 0 {
+            continue;
+        }
+        if offset == 0 {
+            if section.exec() {
+                problems.push(format!("Section {idx}: BSS section marked executable"));
+            }
+            if total_bss_size != 0 {
+                problems.push(format!("Section {idx}: multiple BSS sections"));
+            }
+            total_bss_size += size;
+            continue;
+        }
+        if offset as u64 + size as u64 > file_size {
+            problems.push(format!(
+                "Section {idx}: data {:#X}..{:#X} extends past end of file ({:#X})",
+                offset,
+                offset as u64 + size as u64,
+                file_size
+            ));
+        }
+        // Section alignment is never below 4 (see update_rel_section_alignment)
+        if offset % 4 != 0 {
+            problems.push(format!("Section {idx}: offset {offset:#X} is not 4-byte aligned"));
+        }
+        if let Some((prev_idx, prev_end)) = prev_end {
+            if offset < prev_end {
+                problems.push(format!(
+                    "Section {idx}: offset {offset:#X} overlaps section {prev_idx} (ends at \
+                     {prev_end:#X})"
+                ));
+            }
+        }
+        prev_end = Some((idx, offset.saturating_add(size)));
+    }
+    for (name, align) in [("align", header.align), ("bss_align", header.bss_align)] {
+        if let Some(align) = align.filter(|a| !a.is_power_of_two()) {
+            problems.push(format!("Header {name} {align:#X} is not a power of two"));
+        }
+    }
+    if total_bss_size != header.bss_size {
+        problems.push(format!(
+            "BSS size mismatch: sections total {:#X}, header {:#X}",
+            total_bss_size, header.bss_size
+        ));
+    }
+
+    // Special functions
+    for (name, section, offset) in [
+        ("_prolog", header.prolog_section, header.prolog_offset),
+        ("_epilog", header.epilog_section, header.epilog_offset),
+        ("_unresolved", header.unresolved_section, header.unresolved_offset),
+    ] {
+        if section == 0 {
+            continue;
+        }
+        match sections.get(section as usize) {
+            Some(s) if !s.exec() => {
+                problems.push(format!("{name}: section {section} is not executable"))
+            }
+            Some(s) if offset >= s.size() => problems.push(format!(
+                "{name}: offset {offset:#X} outside of section {section} (size {:#X})",
+                s.size()
+            )),
+            Some(_) => {}
+            None => problems.push(format!("{name}: section {section} does not exist")),
+        }
+    }
+
+    // Import table
+    if header.imp_size % RelImport::STATIC_SIZE as u32 != 0 {
+        problems.push(format!("Import table size {:#X} is not a multiple of 8", header.imp_size));
+    }
+    let imp_end = header.imp_offset as u64 + header.imp_size as u64;
+    if imp_end > file_size {
+        problems.push(format!(
+            "Import table {:#X}..{:#X} extends past end of file ({:#X})",
+            header.imp_offset, imp_end, file_size
+        ));
+        return Ok(problems);
+    }
+    let mut imports = vec![];
+    reader.seek(SeekFrom::Start(header.imp_offset as u64))?;
+    while reader.stream_position()? + RelImport::STATIC_SIZE as u64 <= imp_end {
+        imports.push(RelImport::from_reader(reader, Endian::Big)?);
+    }
+    if let Some(first) = imports.first() {
+        if first.offset != header.rel_offset {
+            problems.push(format!(
+                "Import 0 offset {:#X} does not match header rel_offset {:#X}",
+                first.offset, header.rel_offset
+            ));
+        }
+    }
+    // Same order as write_rel: version 3 puts self-relocations and then the DOL last.
+    let order_key = |module_id: u32| -> (u8, u32) {
+        if header.version < 3 {
+            (0, module_id)
+        } else if module_id == 0 {
+            (2, 0)
+        } else if module_id == header.module_id {
+            (1, 0)
+        } else {
+            (0, module_id)
+        }
+    };
+    for (idx, pair) in imports.windows(2).enumerate() {
+        let (a, b) = (pair[0].module_id, pair[1].module_id);
+        if a == b {
+            problems.push(format!("Import {}: duplicate entry for module {b}", idx + 1));
+        } else if order_key(a) > order_key(b) {
+            problems
+                .push(format!("Import {}: module {b} is out of order after module {a}", idx + 1));
+        }
+    }
+    if let (Some(fix_size), Some(import)) =
+        (header.fix_size, imports.iter().find(|i| i.module_id == header.module_id))
+    {
+        if fix_size != import.offset {
+            problems.push(format!(
+                "fix_size {:#X} does not match self-relocation offset {:#X}",
+                fix_size, import.offset
+            ));
+        }
+    }
+
+    // Relocations
+    for import in &imports {
+        let module_id = import.module_id;
+        if import.offset as u64 >= file_size {
+            problems.push(format!(
+                "Module {module_id}: relocations at {:#X} start past end of file",
+                import.offset
+            ));
+            continue;
+        }
+        reader.seek(SeekFrom::Start(import.offset as u64))?;
+        let mut address = 0u32;
+        let mut section = None;
+        loop {
+            if reader.stream_position()? + RelRelocRaw::STATIC_SIZE as u64 > file_size {
+                problems.push(format!("Module {module_id}: relocation list is not terminated"));
+                break;
+            }
+            let reloc = RelRelocRaw::from_reader(reader, Endian::Big)?;
+            let width = match reloc.kind as u32 {
+                elf::R_PPC_NONE => continue,
+                elf::R_PPC_ADDR32 | elf::R_PPC_UADDR32 | elf::R_PPC_REL24 | elf::R_PPC_REL14 => 4,
+                elf::R_PPC_ADDR16_LO | elf::R_PPC_ADDR16_HI | elf::R_PPC_ADDR16_HA => 2,
+                R_DOLPHIN_NOP => {
+                    address += reloc.offset as u32;
+                    continue;
+                }
+                R_DOLPHIN_SECTION => {
+                    address = 0;
+                    section = match sections.get(reloc.section as usize) {
+                        Some(s) if s.offset() != 0 => Some((reloc.section, s.size())),
+                        _ => {
+                            problems.push(format!(
+                                "Module {module_id}: relocations applied to invalid section {}",
+                                reloc.section
+                            ));
+                            None
+                        }
+                    };
+                    continue;
+                }
+                R_DOLPHIN_END => break,
+                reloc_type => {
+                    problems.push(format!(
+                        "Module {module_id}: unhandled relocation type {reloc_type}"
+                    ));
+                    break;
+                }
+            };
+            address += reloc.offset as u32;
+            let Some((section_idx, section_size)) = section else {
+                problems.push(format!(
+                    "Module {module_id}: relocation at {address:#X} has no valid source section"
+                ));
+                continue;
+            };
+            if address as u64 + width > section_size as u64 {
+                problems.push(format!(
+                    "Module {module_id}: relocation at {section_idx}:{address:#X} outside of \
+                     section (size {section_size:#X})"
+                ));
+            }
+            if module_id == header.module_id {
+                match sections.get(reloc.section as usize) {
+                    Some(target) if target.size() != 0 => {
+                        if reloc.addend > target.size() {
+                            problems.push(format!(
+                                "Module {module_id}: relocation at {section_idx}:{address:#X} \
+                                 targets {}:{:#X}, outside of section (size {:#X})",
+                                reloc.section,
+                                reloc.addend,
+                                target.size()
+                            ));
+                        }
+                    }
+                    _ => problems.push(format!(
+                        "Module {module_id}: relocation at {section_idx}:{address:#X} targets \
+                         invalid section {}",
+                        reloc.section
+                    )),
+                }
+            }
+        }
+    }
+
+    Ok(problems)
+}
+
 /// REL relocation.
 #[derive(Debug, Clone)]
 pub struct RelReloc {
@@ -688,6 +1341,152 @@ pub struct RelWriteInfo {
     pub quiet: bool,
     /// Override individual section alignment in the file.
     pub section_align: Option<Vec<u32>>,
+    /// Order of the relocation lists, by target module.
+    pub module_order: RelModuleOrder,
+    /// Order of the import table entries.
+    pub import_order: RelImportOrder,
+    /// Import table of the original REL, for [`RelModuleOrder::Original`]
+    /// and [`RelImportOrder::Original`].
+    pub original_imports: Vec<RelImport>,
+    /// Byte used to pad section data to its alignment.
+    pub pad_value: u8,
+    /// Lay out the module for `OSLinkFixed`, putting relocations against the DOL and the module
+    /// itself after `fix_size` regardless of `module_order`. (Version 3 only)
+    pub fixed: bool,
+}
+
+/// Order of the relocation lists in a written REL, by target module.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RelModuleOrder {
+    /// Version 3 RELs put module ID 0 and self-relocations last, others use ascending order.
+    Auto,
+    /// Ascending module ID, regardless of version.
+    Ascending,
+    /// The order of the relocation lists in the original REL.
+    Original,
+}
+
+/// Order of the import table entries in a written REL.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RelImportOrder {
+    /// The same order as the relocation lists.
+    Relocations,
+    /// Ascending module ID.
+    Ascending,
+    /// The order of the import table in the original REL.
+    Original,
+}
+
+/// Linker behaviour that a [`RelLayout`] defaults to.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RelLayoutPreset {
+    /// The Dolphin SDK's makerel.
+    #[default]
+    Makerel,
+    /// Relocation lists and import table in ascending module order.
+    Ascending,
+    /// Orders copied from the original REL.
+    Original,
+}
+
+impl FromStr for RelLayoutPreset {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "makerel" => Ok(Self::Makerel),
+            "ascending" => Ok(Self::Ascending),
+            "original" => Ok(Self::Original),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromArgValue for RelLayoutPreset {
+    fn from_arg_value(value: &OsStr) -> Result<Self, String> {
+        String::from_arg_value(value)
+            .and_then(|s| Self::from_str(&s).map_err(|_| "Invalid REL layout preset".to_string()))
+    }
+}
+
+/// REL layout options, for reproducing the output of the linker a game was built with.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
+#[serde(default)]
+pub struct RelLayout {
+    /// Defaults for the options below.
+    pub preset: RelLayoutPreset,
+    /// Order of the relocation lists, by target module.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module_order: Option<RelModuleOrder>,
+    /// Order of the import table entries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub import_order: Option<RelImportOrder>,
+    /// Byte used to pad section data to its alignment.
+    pub pad_value: u8,
+    /// Lay out modules for `OSLinkFixed`, like the modules shipped with most games.
+    pub fixed: bool,
+}
+
+impl RelLayout {
+    pub fn module_order(&self) -> RelModuleOrder {
+        self.module_order.unwrap_or(match self.preset {
+            RelLayoutPreset::Makerel => RelModuleOrder::Auto,
+            RelLayoutPreset::Ascending => RelModuleOrder::Ascending,
+            RelLayoutPreset::Original => RelModuleOrder::Original,
+        })
+    }
+
+    pub fn import_order(&self) -> RelImportOrder {
+        self.import_order.unwrap_or(match self.preset {
+            RelLayoutPreset::Makerel => RelImportOrder::Relocations,
+            RelLayoutPreset::Ascending => RelImportOrder::Ascending,
+            RelLayoutPreset::Original => RelImportOrder::Original,
+        })
+    }
+
+    /// Whether the layout depends on the original REL.
+    pub fn needs_original(&self) -> bool {
+        self.module_order() == RelModuleOrder::Original
+            || self.import_order() == RelImportOrder::Original
+    }
+}
+
+/// Target modules of the relocation lists, in the order they're written.
+fn relocation_module_order(info: &RelWriteInfo, relocations: &[RelReloc]) -> Vec<u32> {
+    let mut modules = relocations.iter().map(|r| r.module_id).collect::<Vec<_>>();
+    modules.sort_unstable();
+    modules.dedup();
+    // Version 3 RELs put module ID 0 and self-relocations last,
+    // so that the space can be reclaimed via OSLinkFixed. (See fix_size)
+    let auto_rank = |id: u32| match id {
+        _ if info.version < 3 => 0,
+        0 => 2,
+        id if id == info.module_id => 1,
+        _ => 0,
+    };
+    match info.module_order {
+        RelModuleOrder::Auto => modules.sort_by_key(|&id| auto_rank(id)),
+        RelModuleOrder::Ascending => {}
+        RelModuleOrder::Original => {
+            let mut original = info.original_imports.clone();
+            original.sort_by_key(|import| import.offset);
+            let rank = |id: u32| original.iter().position(|import| import.module_id == id);
+            for &id in modules.iter().filter(|&&id| rank(id).is_none()) {
+                if !info.quiet {
+                    warn!(module = %DisplayModule(id), "Module not in the original import table");
+                }
+            }
+            modules.sort_by_key(|&id| (rank(id).unwrap_or(usize::MAX), auto_rank(id)));
+        }
+    }
+    if info.fixed {
+        // Stable, so the order of the other modules is kept
+        modules.sort_by_key(|&id| auto_rank(id));
+    }
+    modules
 }
 
 pub const PERMITTED_SECTIONS: [&str; 7] =
@@ -701,6 +1500,90 @@ pub fn should_write_section(section: &object::Section) -> bool {
     section.kind() != object::SectionKind::UninitializedData
 }
 
+fn do_relocation_layout(
+    relocations: &[RelReloc],
+    header: &mut RelHeader,
+    imp_entries: &mut Vec<RelImport>,
+    raw_relocations: &mut Vec<RelRelocRaw>,
+    offset: &mut u32,
+    fixed: bool,
+) -> Result<()> {
+    let mut address = 0u32;
+    let mut section = u8::MAX;
+    let mut last_module_id = u32::MAX;
+    for reloc in relocations {
+        if reloc.module_id != last_module_id {
+            if last_module_id != u32::MAX {
+                raw_relocations.push(RelRelocRaw {
+                    offset: 0,
+                    kind: R_DOLPHIN_END as u8,
+                    section: 0,
+                    addend: 0,
+                });
+                *offset += 8;
+            }
+            imp_entries.push(RelImport { module_id: reloc.module_id, offset: *offset });
+            section = u8::MAX;
+            last_module_id = reloc.module_id;
+            if header.version >= 3 {
+                if reloc.module_id == 0 || reloc.module_id == header.module_id {
+                    header.fix_size.get_or_insert(*offset);
+                } else if fixed {
+                    // OSLinkFixed discards everything after fix_size, which can only be
+                    // the trailing relocations against the DOL and the module itself
+                    header.fix_size = None;
+                }
+            }
+        }
+        if reloc.section != section {
+            raw_relocations.push(RelRelocRaw {
+                offset: 0,
+                kind: R_DOLPHIN_SECTION as u8,
+                section: reloc.section,
+                addend: 0,
+            });
+            *offset += 8;
+            address = 0;
+            section = reloc.section;
+        }
+        let mut reloc_offset = reloc.address - address;
+        while reloc_offset > 0xffff {
+            raw_relocations.push(RelRelocRaw {
+                offset: 0xffff,
+                kind: R_DOLPHIN_NOP as u8,
+                section: 0,
+                addend: 0,
+            });
+            *offset += 8;
+            reloc_offset -= 0xffff;
+        }
+        raw_relocations.push(RelRelocRaw {
+            offset: reloc_offset as u16,
+            kind: match reloc.kind {
+                ObjRelocKind::Absolute => elf::R_PPC_ADDR32,
+                ObjRelocKind::PpcAddr16Lo => elf::R_PPC_ADDR16_LO,
+                ObjRelocKind::PpcAddr16Hi => elf::R_PPC_ADDR16_HI,
+                ObjRelocKind::PpcAddr16Ha => elf::R_PPC_ADDR16_HA,
+                ObjRelocKind::PpcRel24 => elf::R_PPC_REL24,
+                ObjRelocKind::PpcRel14 => elf::R_PPC_REL14,
+                _ => bail!("Unsupported relocation kind {:?}", reloc.kind),
+            } as u8,
+            section: reloc.target_section,
+            addend: reloc.addend,
+        });
+        address = reloc.address;
+        *offset += 8;
+    }
+    raw_relocations.push(RelRelocRaw {
+        offset: 0,
+        kind: R_DOLPHIN_END as u8,
+        section: 0,
+        addend: 0,
+    });
+    *offset += 8;
+    Ok(())
+}
+
 pub fn write_rel<W>(
     w: &mut W,
     info: &RelWriteInfo,
@@ -710,41 +1593,11 @@ pub fn write_rel<W>(
 where
     W: Write + Seek + ?Sized,
 {
-    if info.version >= 3 {
-        // Version 3 RELs put module ID 0 and self-relocations last,
-        // so that the space can be reclaimed via OSLinkFixed. (See fix_size)
-        relocations.sort_by(|a, b| {
-            if a.module_id == 0 {
-                if b.module_id == 0 {
-                    Ordering::Equal
-                } else {
-                    Ordering::Greater
-                }
-            } else if a.module_id == info.module_id {
-                if b.module_id == 0 {
-                    Ordering::Less
-                } else if b.module_id == info.module_id {
-                    Ordering::Equal
-                } else {
-                    Ordering::Greater
-                }
-            } else if b.module_id == 0 || b.module_id == info.module_id {
-                Ordering::Less
-            } else {
-                a.module_id.cmp(&b.module_id)
-            }
-            .then(a.section.cmp(&b.section))
-            .then(a.address.cmp(&b.address))
-        });
-    } else {
-        // Version 1 and 2 RELs use simple ascending order.
-        relocations.sort_by(|a, b| {
-            a.module_id
-                .cmp(&b.module_id)
-                .then(a.section.cmp(&b.section))
-                .then(a.address.cmp(&b.address))
-        });
-    }
+    ensure!(!info.fixed || info.version >= 3, "OSLinkFixed layout requires REL version 3");
+    let module_order = relocation_module_order(info, &relocations);
+    relocations.sort_by_key(|r| {
+        (module_order.iter().position(|&id| id == r.module_id), r.section, r.address)
+    });
 
     let mut apply_relocations = vec![];
     relocations.retain(|r| {
@@ -849,86 +1702,6 @@ where
         offset += section.size() as u32;
     }
 
-    fn do_relocation_layout(
-        relocations: &[RelReloc],
-        header: &mut RelHeader,
-        imp_entries: &mut Vec<RelImport>,
-        raw_relocations: &mut Vec<RelRelocRaw>,
-        offset: &mut u32,
-    ) -> Result<()> {
-        let mut address = 0u32;
-        let mut section = u8::MAX;
-        let mut last_module_id = u32::MAX;
-        for reloc in relocations {
-            if reloc.module_id != last_module_id {
-                if last_module_id != u32::MAX {
-                    raw_relocations.push(RelRelocRaw {
-                        offset: 0,
-                        kind: R_DOLPHIN_END as u8,
-                        section: 0,
-                        addend: 0,
-                    });
-                    *offset += 8;
-                }
-                imp_entries.push(RelImport { module_id: reloc.module_id, offset: *offset });
-                section = u8::MAX;
-                last_module_id = reloc.module_id;
-            }
-            if header.version >= 3
-                && header.fix_size.is_none()
-                && (reloc.module_id == 0 || reloc.module_id == header.module_id)
-            {
-                header.fix_size = Some(*offset);
-            }
-            if reloc.section != section {
-                raw_relocations.push(RelRelocRaw {
-                    offset: 0,
-                    kind: R_DOLPHIN_SECTION as u8,
-                    section: reloc.section,
-                    addend: 0,
-                });
-                *offset += 8;
-                address = 0;
-                section = reloc.section;
-            }
-            let mut reloc_offset = reloc.address - address;
-            while reloc_offset > 0xffff {
-                raw_relocations.push(RelRelocRaw {
-                    offset: 0xffff,
-                    kind: R_DOLPHIN_NOP as u8,
-                    section: 0,
-                    addend: 0,
-                });
-                *offset += 8;
-                reloc_offset -= 0xffff;
-            }
-            raw_relocations.push(RelRelocRaw {
-                offset: reloc_offset as u16,
-                kind: match reloc.kind {
-                    ObjRelocKind::Absolute => elf::R_PPC_ADDR32,
-                    ObjRelocKind::PpcAddr16Lo => elf::R_PPC_ADDR16_LO,
-                    ObjRelocKind::PpcAddr16Hi => elf::R_PPC_ADDR16_HI,
-                    ObjRelocKind::PpcAddr16Ha => elf::R_PPC_ADDR16_HA,
-                    ObjRelocKind::PpcRel24 => elf::R_PPC_REL24,
-                    ObjRelocKind::PpcRel14 => elf::R_PPC_REL14,
-                    _ => bail!("Unsupported relocation kind {:?}", reloc.kind),
-                } as u8,
-                section: reloc.target_section,
-                addend: reloc.addend,
-            });
-            address = reloc.address;
-            *offset += 8;
-        }
-        raw_relocations.push(RelRelocRaw {
-            offset: 0,
-            kind: R_DOLPHIN_END as u8,
-            section: 0,
-            addend: 0,
-        });
-        *offset += 8;
-        Ok(())
-    }
-
     let imp_count = relocations.iter().map(|r| r.module_id).dedup().count();
     let mut imp_entries = Vec::<RelImport>::with_capacity(imp_count);
     let mut raw_relocations = vec![];
@@ -942,6 +1715,7 @@ where
                 &mut imp_entries,
                 &mut raw_relocations,
                 &mut offset,
+                info.fixed,
             )?;
         }
         header.imp_offset = offset;
@@ -957,8 +1731,24 @@ where
                 &mut imp_entries,
                 &mut raw_relocations,
                 &mut offset,
+                info.fixed,
             )?;
         }
+        match info.import_order {
+            RelImportOrder::Relocations => {}
+            RelImportOrder::Ascending => imp_entries.sort_by_key(|entry| entry.module_id),
+            RelImportOrder::Original => imp_entries.sort_by_key(|entry| {
+                info.original_imports
+                    .iter()
+                    .position(|import| import.module_id == entry.module_id)
+                    .unwrap_or(usize::MAX)
+            }),
+        }
+    }
+
+    if info.version >= 3 && header.fix_size.is_none() {
+        // Nothing can be discarded
+        header.fix_size = Some(offset);
     }
 
     for symbol in file.symbols().filter(|s| s.is_definition()) {
@@ -1024,7 +1814,7 @@ where
         }
         let position = w.stream_position()?;
         let align = section_align(idx, &section, info);
-        w.write_all(&vec![0u8; calculate_padding(position, align as u64) as usize])?;
+        w.write_all(&vec![info.pad_value; calculate_padding(position, align as u64) as usize])?;
 
         let section_index = section.index().0 as u8;
         let mut section_data = section.uncompressed_data()?;
@@ -1061,6 +1851,88 @@ where
     Ok(())
 }
 
+/// Appends an import table and relocation lists to a REL whose header and section data are
+/// already in place, with relocations against the DOL and the module itself last, as makerel
+/// lays out version 3 RELs. Updates the header to match.
+pub fn append_rel_relocations(
+    data: &mut Vec<u8>,
+    header: &mut RelHeader,
+    mut relocations: Vec<RelReloc>,
+) -> Result<()> {
+    let module_id = header.module_id;
+    let rank = |id: u32| match id {
+        0 => 2,
+        id if id == module_id => 1,
+        _ => 0,
+    };
+    relocations.sort_by_key(|r| (rank(r.module_id), r.module_id, r.section, r.address));
+
+    data.resize(align_up(data.len() as u32, 4) as usize, 0);
+    let mut offset = data.len() as u32;
+    header.rel_offset = 0;
+    header.imp_offset = 0;
+    header.imp_size = 0;
+    header.fix_size = None;
+    let mut imp_entries = vec![];
+    let mut raw_relocations = vec![];
+    if !relocations.is_empty() {
+        header.imp_offset = offset;
+        let imp_count = relocations.iter().map(|r| r.module_id).dedup().count();
+        header.imp_size = imp_count as u32 * RelImport::STATIC_SIZE as u32;
+        offset += header.imp_size;
+        header.rel_offset = offset;
+        do_relocation_layout(
+            &relocations,
+            header,
+            &mut imp_entries,
+            &mut raw_relocations,
+            &mut offset,
+            false,
+        )?;
+    }
+    if header.version >= 3 && header.fix_size.is_none() {
+        header.fix_size = Some(offset);
+    }
+    for entry in &imp_entries {
+        entry.to_writer(data, Endian::Big)?;
+    }
+    for reloc in &raw_relocations {
+        reloc.to_writer(data, Endian::Big)?;
+    }
+    ensure!(data.len() as u32 == offset);
+
+    let mut header_data = Vec::with_capacity(header.write_size());
+    header.to_writer(&mut header_data, Endian::Big)?;
+    data[..header_data.len()].copy_from_slice(&header_data);
+    Ok(())
+}
+
+/// Rewrites a REL's import table and relocation lists, keeping its header and section data.
+/// The relocation tables must follow the section data, as they do in RELs built by makerel.
+pub fn replace_rel_relocations(rel: &[u8], relocations: Vec<RelReloc>) -> Result<Vec<u8>> {
+    let mut reader = io::Cursor::new(rel);
+    let mut header = process_rel_header(&mut reader)?;
+    let sections = process_rel_sections(&mut reader, &header)?;
+    let mut data_end = header.section_info_offset + header.num_sections * 8;
+    data_end = data_end.max(header.write_size() as u32);
+    for section in sections.iter().filter(|s| s.offset() != 0) {
+        data_end = data_end.max(section.offset() + section.size());
+    }
+    let mut tables_start = rel.len() as u32;
+    if header.imp_size > 0 {
+        tables_start = tables_start.min(header.imp_offset).min(header.rel_offset);
+    }
+    ensure!(
+        tables_start >= data_end,
+        "Relocation tables at {:#X} overlap the header or section data (up to {:#X})",
+        tables_start,
+        data_end
+    );
+    let mut data = rel[..tables_start as usize].to_vec();
+    append_rel_relocations(&mut data, &mut header, relocations)?;
+    Ok(data)
+}
+
 /// Determines REL section alignment based on its file offset.
 pub fn update_rel_section_alignment(obj: &mut ObjInfo, header: &RelHeader) -> Result<()> {
     let mut last_offset = header.section_info_offset + header.num_sections * 8;
@@ -1103,3 +1975,176 @@ pub fn update_rel_section_alignment(obj: &mut ObjInfo, header: &RelHeader) -> Re
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(elf_index: usize, kind: ObjSectionKind, data: Vec<u8>) -> ObjSection {
+        let mut section = ObjSection::raw(&format!(".section{elf_index}"), kind, 0, data);
+        section.elf_index = elf_index;
+        section.section_known = false;
+        section
+    }
+
+    fn reloc(section: u8, address: u32, target_section: u8, addend: u32) -> RelReloc {
+        RelReloc {
+            kind: ObjRelocKind::Absolute,
+            section,
+            address,
+            module_id: 1,
+            target_section,
+            addend,
+            original_section: section,
+            original_target_section: target_section,
+        }
+    }
+
+    fn header(module_id: u32) -> RelHeader {
+        RelHeader {
+            module_id,
+            num_sections: 0,
+            section_info_offset: 0,
+            name_offset: 0,
+            name_size: 0,
+            version: 3,
+            bss_size: 0,
+            rel_offset: 0,
+            imp_offset: 0,
+            imp_size: 0,
+            prolog_section: 0,
+            epilog_section: 0,
+            unresolved_section: 0,
+            prolog_offset: 0,
+            epilog_offset: 0,
+            unresolved_offset: 0,
+            align: None,
+            bss_align: None,
+            fix_size: None,
+        }
+    }
+
+    #[test]
+    fn extabindex_from_relocations() {
+        let mut extabindex = vec![0u8; 24];
+        extabindex[4..8].copy_from_slice(&0x10u32.to_be_bytes());
+        extabindex[16..20].copy_from_slice(&0x20u32.to_be_bytes());
+        let mut obj = ObjInfo::new(
+            ObjKind::Relocatable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            vec![],
+            vec![
+                section(1, ObjSectionKind::Code, vec![0; 0x30]),
+                section(2, ObjSectionKind::Data, extabindex),
+                section(3, ObjSectionKind::Data, vec![0; 0x10]),
+                // Pointers to functions, but not extabindex entries
+                section(4, ObjSectionKind::Data, vec![0; 12]),
+            ],
+        );
+        obj.module_id = 1;
+        let mut relocations = BTreeMap::new();
+        for reloc in [
+            reloc(2, 0x0, 1, 0x0),
+            reloc(2, 0x8, 3, 0x0),
+            reloc(2, 0xC, 1, 0x10),
+            reloc(2, 0x14, 3, 0x8),
+            reloc(4, 0x0, 1, 0x0),
+            reloc(4, 0x4, 1, 0x10),
+            reloc(4, 0x8, 1, 0x20),
+        ] {
+            relocations.insert((reloc.section, reloc.address), reloc);
+        }
+        assert_eq!(extabindex_functions(&obj, &relocations), [
+            (SectionAddress::new(0, 0x0), 0x10),
+            (SectionAddress::new(0, 0x10), 0x20),
+        ]);
+
+        // Entries past the end of the code section
+        relocations.insert((2, 0xC), reloc(2, 0xC, 1, 0x20));
+        assert!(extabindex_functions(&obj, &relocations).is_empty());
+
+        // Relocations against other modules
+        relocations.insert((2, 0xC), RelReloc { module_id: 2, ..reloc(2, 0xC, 1, 0x10) });
+        assert!(extabindex_functions(&obj, &relocations).is_empty());
+    }
+
+    #[test]
+    fn relocation_lookups() {
+        fn raw(offset: u16, kind: u32, section: u8, addend: u32) -> Vec<u8> {
+            let mut raw = offset.to_be_bytes().to_vec();
+            raw.extend([kind as u8, section]);
+            raw.extend(addend.to_be_bytes());
+            raw
+        }
+        let mut data = vec![0u8; 0x100];
+        for (module_id, offset) in [(0u32, 0x110u32), (1, 0x128)] {
+            data.extend(module_id.to_be_bytes());
+            data.extend(offset.to_be_bytes());
+        }
+        for raw in [
+            raw(0, R_DOLPHIN_SECTION, 1, 0),
+            raw(4, elf::R_PPC_ADDR32, 0, 0x80001000),
+            raw(0, R_DOLPHIN_END, 0, 0),
+            raw(0, R_DOLPHIN_SECTION, 1, 0),
+            raw(8, elf::R_PPC_REL24, 1, 0x20),
+            raw(0, R_DOLPHIN_END, 0, 0),
+        ] {
+            data.extend(raw);
+        }
+        let header =
+            RelHeader { rel_offset: 0x110, imp_offset: 0x100, imp_size: 0x10, ..header(1) };
+        let relocations = RelRelocations::read(&mut Cursor::new(data), &header).unwrap();
+
+        let own = relocations.iter_module(1).collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(own.len(), 1);
+        assert_eq!((own[0].kind, own[0].section, own[0].address), (ObjRelocKind::PpcRel24, 1, 8));
+        let reloc = relocations.at(1, 4).unwrap().unwrap();
+        assert_eq!((reloc.module_id, reloc.addend), (0, 0x80001000));
+        assert!(relocations.at(1, 0).unwrap().is_none());
+        assert_eq!(relocations.index().unwrap().len(), 2);
+    }
+
+    /// Lays out one relocation against each module, in order, returning the import table
+    /// and the `fix_size` of module 2.
+    fn layout(modules: &[u32], version: u32, fixed: bool) -> (Vec<(u32, u32)>, Option<u32>) {
+        let relocations =
+            modules.iter().map(|&module_id| RelReloc { module_id, ..reloc(1, 4, 1, 0) });
+        let mut header = RelHeader { version, ..header(2) };
+        let (mut imp_entries, mut raw_relocations, mut offset) = (vec![], vec![], 0x100);
+        do_relocation_layout(
+            &relocations.collect::<Vec<_>>(),
+            &mut header,
+            &mut imp_entries,
+            &mut raw_relocations,
+            &mut offset,
+            fixed,
+        )
+        .unwrap();
+        // Each list has a section change, a relocation and an end marker
+        assert_eq!(offset, 0x100 + modules.len() as u32 * 0x18);
+        let imports = imp_entries.iter().map(|i| (i.module_id, i.offset)).collect();
+        (imports, header.fix_size)
+    }
+
+    #[test]
+    fn fix_size_layout() {
+        // As makerel lays out version 3 RELs: other modules, then the module itself and the DOL
+        let (imports, fix_size) = layout(&[1, 2, 0], 3, false);
+        assert_eq!(imports, [(1, 0x100), (2, 0x118), (0, 0x130)]);
+        assert_eq!(fix_size, Some(0x118));
+        assert_eq!(layout(&[1, 2, 0], 3, true).1, Some(0x118));
+        assert_eq!(layout(&[0], 3, false).1, Some(0x100));
+
+        // Another module after the DOL, as with `RelModuleOrder::Original`. Only the
+        // OSLinkFixed layout refuses a fix_size that would discard it
+        let (imports, fix_size) = layout(&[0, 1], 3, false);
+        assert_eq!(imports, [(0, 0x100), (1, 0x118)]);
+        assert_eq!(fix_size, Some(0x100));
+        assert_eq!(layout(&[0, 1], 3, true).1, None);
+
+        // Nothing to discard, or no fix_size at all
+        assert_eq!(layout(&[1], 3, false).1, None);
+        assert_eq!(layout(&[1, 2, 0], 2, false).1, None);
+    }
+}
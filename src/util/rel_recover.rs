@@ -0,0 +1,524 @@
+use std::{collections::HashSet, io::Cursor, ops::Range};
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::{
+    array_ref,
+    obj::ObjRelocKind,
+    util::rel::{
+        append_rel_relocations, process_rel_header, process_rel_sections, RelReloc, RelRelocReader,
+    },
+};
+
+/// Options for [`recover_rel`].
+pub struct RecoverOptions<'a> {
+    /// Address the module was loaded at. Determined from the header if not given.
+    pub address: Option<u32>,
+    /// The module as stored on disc, to take the relocations from.
+    pub original: Option<&'a [u8]>,
+    /// Address ranges of the DOL's sections, for finding references into the DOL.
+    pub dol_ranges: Vec<Range<u32>>,
+}
+
+/// A REL reconstructed from a memory dump.
+pub struct RecoveredRel {
+    /// The module with relocations unapplied and relocation tables restored.
+    pub data: Vec<u8>,
+    /// Address the module was loaded at.
+    pub address: u32,
+    /// Address of the module's BSS, if it had been allocated.
+    pub bss_address: Option<u32>,
+    pub relocations: usize,
+    /// Words changed at runtime, as (section, offset). Only known with the original module.
+    pub modified: Vec<(u8, u32)>,
+    /// Relocations whose dumped value doesn't match their target, which suggests the wrong
+    /// load address. Only checked with the original module.
+    pub mismatched: usize,
+}
+
+/// A section of the loaded module.
+struct LoadedSection {
+    index: u8,
+    offset: u32,
+    size: u32,
+    exec: bool,
+    address: u32,
+}
+
+#[inline]
+fn read_u32(data: &[u8], offset: usize) -> u32 { u32::from_be_bytes(*array_ref!(data, offset, 4)) }
+
+#[inline]
+fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+}
+
+/// Bits of the (aligned) word at a relocation that it modifies.
+fn reloc_mask(kind: ObjRelocKind) -> Result<u32> {
+    Ok(match kind {
+        ObjRelocKind::Absolute => 0xFFFFFFFF,
+        ObjRelocKind::PpcAddr16Lo | ObjRelocKind::PpcAddr16Hi | ObjRelocKind::PpcAddr16Ha => 0xFFFF,
+        ObjRelocKind::PpcRel24 => 0x3FFFFFC,
+        ObjRelocKind::PpcRel14 => 0xFFFC,
+        kind => bail!("Unsupported relocation kind {:?}", kind),
+    })
+}
+
+/// The bits a relocation at `site` against `target` produces.
+fn reloc_value(kind: ObjRelocKind, site: u32, target: u32) -> u32 {
+    match kind {
+        ObjRelocKind::Absolute => target,
+        ObjRelocKind::PpcAddr16Lo => target & 0xFFFF,
+        ObjRelocKind::PpcAddr16Hi => target >> 16,
+        ObjRelocKind::PpcAddr16Ha => target.wrapping_add(0x8000) >> 16,
+        ObjRelocKind::PpcRel24 => target.wrapping_sub(site) & 0x3FFFFFC,
+        ObjRelocKind::PpcRel14 => target.wrapping_sub(site) & 0xFFFC,
+        _ => 0,
+    }
+}
+
+/// Undoes the changes `OSLink` makes to the header and section table, which turn file offsets
+/// into addresses. Returns the load address and the BSS address.
+fn unload_header(image: &mut [u8], address: Option<u32>) -> Result<(u32, Option<u32>)> {
+    ensure!(image.len() >= 0x4C, "Dump is too small to contain a REL header");
+    let version = read_u32(image, 0x1C);
+    let header_size = match version {
+        1 => 0x40,
+        2 => 0x48,
+        3 => 0x4C,
+        _ => bail!("Unsupported REL version {}", version),
+    };
+    let section_info = read_u32(image, 0x10);
+    if section_info < 0x80000000 {
+        bail!("REL header is not relocated; use the module itself instead of a dump");
+    }
+    // The section table directly follows the header
+    let address = address.unwrap_or(section_info - header_size);
+    ensure!(section_info >= address, "Section table is below the load address {:#010X}", address);
+    let section_info_offset = section_info - address;
+    write_u32(image, 0x4, 0); // next
+    write_u32(image, 0x8, 0); // prev
+    write_u32(image, 0x10, section_info_offset);
+
+    let num_sections = read_u32(image, 0xC);
+    ensure!(
+        section_info_offset as u64 + num_sections as u64 * 8 <= image.len() as u64,
+        "Section table extends past the end of the dump"
+    );
+    let bss_section = image[0x33];
+    let mut bss_address = None;
+    let mut section_offsets = vec![0u32; num_sections as usize];
+    for idx in 0..num_sections {
+        let pos = (section_info_offset + idx * 8) as usize;
+        let offset_and_flags = read_u32(image, pos);
+        let offset = offset_and_flags & !1;
+        if bss_section != 0 && idx == bss_section as u32 {
+            bss_address = Some(offset);
+            write_u32(image, pos, 0);
+        } else if offset != 0 {
+            ensure!(
+                offset >= address,
+                "Section {} at {:#010X} is below the load address",
+                idx,
+                offset
+            );
+            section_offsets[idx as usize] = offset - address;
+            write_u32(image, pos, offset_and_flags - address);
+        }
+    }
+    image[0x33] = 0;
+    for pos in [0x24, 0x28] {
+        // rel_offset and imp_offset
+        let value = read_u32(image, pos);
+        if value >= address {
+            write_u32(image, pos, value - address);
+        }
+    }
+    for (section_pos, offset_pos) in [(0x30, 0x34), (0x31, 0x38), (0x32, 0x3C)] {
+        // prolog, epilog and unresolved are made absolute
+        let section = image[section_pos] as usize;
+        let Some(&section_offset) = section_offsets.get(section).filter(|_| section != 0) else {
+            continue;
+        };
+        let value = read_u32(image, offset_pos);
+        let start = address + section_offset;
+        if value >= start {
+            write_u32(image, offset_pos, value - start);
+        }
+    }
+    Ok((address, bss_address))
+}
+
+/// Reconstructs a REL from a dump of the loaded module, whose relocations have been applied and
+/// whose relocation tables may have been discarded by `OSLinkFixed`.
+///
+/// With the original module, its relocations are used and the dumped data is copied over the
+/// rest. Otherwise, relocations are guessed from pointers into the module and the DOL:
+/// absolute pointers in data, branches out of a section, and `lis` pairs in code.
+pub fn recover_rel(dump: &[u8], options: &RecoverOptions) -> Result<RecoveredRel> {
+    let mut image = dump.to_vec();
+    let (address, bss_address) = unload_header(&mut image, options.address)?;
+    let mut header = process_rel_header(&mut Cursor::new(&image))?;
+    let section_headers = process_rel_sections(&mut Cursor::new(&image), &header)?;
+    let bss_index = section_headers
+        .iter()
+        .position(|s| s.offset() == 0 && s.size() != 0)
+        .map(|idx| idx as u8)
+        .filter(|_| bss_address.is_some());
+    let mut sections = vec![];
+    for (idx, section) in section_headers.iter().enumerate() {
+        if section.offset() == 0 || section.size() == 0 {
+            continue;
+        }
+        let end = section.offset().checked_add(section.size());
+        ensure!(
+            end.is_some_and(|end| end as usize <= image.len()),
+            "Section {} extends past the end of the dump",
+            idx
+        );
+        // Ranges of section addresses are used below, so the end must be addressable
+        let section_address = address.checked_add(section.offset());
+        ensure!(
+            section_address.and_then(|a| a.checked_add(section.size())).is_some(),
+            "Section {} is past the end of the address space",
+            idx
+        );
+        sections.push(LoadedSection {
+            index: idx as u8,
+            offset: section.offset(),
+            size: section.size(),
+            exec: section.exec(),
+            address: section_address.unwrap(),
+        });
+    }
+    let section_address = |index: u8| -> Option<u32> {
+        match sections.iter().find(|s| s.index == index) {
+            Some(section) => Some(section.address),
+            None => bss_address.filter(|_| bss_index == Some(index)),
+        }
+    };
+
+    if let Some(original) = options.original {
+        let mut reader = Cursor::new(original);
+        let original_header =
+            process_rel_header(&mut reader).context("Failed to read original REL")?;
+        ensure!(
+            original_header.module_id == header.module_id,
+            "Original REL is module {}, but the dump is module {}",
+            original_header.module_id,
+            header.module_id
+        );
+        let original_sections = process_rel_sections(&mut reader, &original_header)?;
+        let relocations = RelRelocReader::new(&mut reader, &original_header)
+            .collect::<Result<Vec<RelReloc>>>()
+            .context("Failed to read original REL relocations")?;
+        let mut data = original.to_vec();
+        for section in &sections {
+            let original_section = original_sections
+                .get(section.index as usize)
+                .filter(|s| {
+                    s.offset() != 0
+                        && s.size() == section.size
+                        && s.offset() as u64 + s.size() as u64 <= data.len() as u64
+                })
+                .with_context(|| {
+                    format!("Section {} doesn't match the original REL", section.index)
+                })?;
+            let (src, dst) = (section.offset as usize, original_section.offset() as usize);
+            data[dst..dst + section.size as usize]
+                .copy_from_slice(&image[src..src + section.size as usize]);
+        }
+
+        let mut mismatched = 0;
+        let mut reloc_words = HashSet::new();
+        for reloc in &relocations {
+            let (Some(section), Some(original_section)) = (
+                sections.iter().find(|s| s.index == reloc.section),
+                original_sections.get(reloc.section as usize),
+            ) else {
+                continue;
+            };
+            let pos = original_section.offset() as u64 + reloc.address as u64;
+            ensure!(
+                reloc.address < section.size && pos + 4 <= data.len() as u64,
+                "Relocation at {}:{:#X} is outside of the section",
+                reloc.section,
+                reloc.address
+            );
+            let pos = pos as usize;
+            let mask = reloc_mask(reloc.kind)?;
+            let dumped = read_u32(&data, pos);
+            let target = match reloc.module_id {
+                0 => Some(reloc.addend),
+                id if id == header.module_id => {
+                    section_address(reloc.target_section).map(|a| a.wrapping_add(reloc.addend))
+                }
+                // Other modules may have been loaded anywhere
+                _ => None,
+            };
+            if let Some(target) = target {
+                let site = section.address + reloc.address;
+                if dumped & mask != reloc_value(reloc.kind, site, target) & mask {
+                    mismatched += 1;
+                }
+            }
+            write_u32(&mut data, pos, (dumped & !mask) | (read_u32(original, pos) & mask));
+            reloc_words.insert((reloc.section, reloc.address));
+        }
+
+        // Anything else that differs was changed at runtime
+        let mut modified = vec![];
+        for section in &sections {
+            let offset = original_sections[section.index as usize].offset();
+            for word in (0..section.size & !3).step_by(4) {
+                let pos = (offset + word) as usize;
+                if !reloc_words.contains(&(section.index, word))
+                    && read_u32(&data, pos) != read_u32(original, pos)
+                {
+                    modified.push((section.index, word));
+                }
+            }
+        }
+        return Ok(RecoveredRel {
+            data,
+            address,
+            bss_address,
+            relocations: relocations.len(),
+            modified,
+            mismatched,
+        });
+    }
+
+    // Module and section containing an address, and the addend to it
+    let find_target = |target: u32| -> Option<(u32, u8, u32)> {
+        for section in &sections {
+            if (section.address..section.address + section.size).contains(&target) {
+                return Some((header.module_id, section.index, target - section.address));
+            }
+        }
+        if let (Some(bss), Some(index)) = (bss_address, bss_index) {
+            if (bss..bss.saturating_add(header.bss_size)).contains(&target) {
+                return Some((header.module_id, index, target - bss));
+            }
+        }
+        if options.dol_ranges.iter().any(|r| r.contains(&target)) {
+            return Some((0, 0, target));
+        }
+        None
+    };
+
+    let data_end = sections
+        .iter()
+        .map(|s| s.offset + s.size)
+        .max()
+        .unwrap_or(0)
+        .max(header.section_info_offset + header.num_sections * 8);
+    let mut data = image[..data_end as usize].to_vec();
+    let mut relocations = vec![];
+    let mut add_reloc = |kind: ObjRelocKind,
+                         section: &LoadedSection,
+                         offset: u32,
+                         (module_id, target, addend): (u32, u8, u32)| {
+        relocations.push(RelReloc {
+            kind,
+            section: section.index,
+            address: offset,
+            module_id,
+            target_section: target,
+            addend,
+            original_section: section.index,
+            original_target_section: target,
+        });
+    };
+    for section in &sections {
+        let read = |data: &[u8], word: u32| read_u32(data, (section.offset + word) as usize);
+        let words = section.size / 4;
+        if !section.exec {
+            for word in (0..words).map(|i| i * 4) {
+                let value = read(&data, word);
+                if value == 0 {
+                    continue;
+                }
+                if let Some(target) = find_target(value) {
+                    add_reloc(ObjRelocKind::Absolute, section, word, target);
+                    write_u32(&mut data, (section.offset + word) as usize, 0);
+                }
+            }
+            continue;
+        }
+        for i in 0..words {
+            let word = i * 4;
+            let ins = read(&data, word);
+            match ins >> 26 {
+                // b/bl, leaving branches within the section, which makerel resolves
+                18 if ins & 2 == 0 => {
+                    let disp = ((ins & 0x3FFFFFC) << 6) as i32 >> 6;
+                    let site = section.address + word;
+                    let dest = site.wrapping_add(disp as u32);
+                    if (section.address..section.address + section.size).contains(&dest) {
+                        continue;
+                    }
+                    if let Some(target) = find_target(dest) {
+                        add_reloc(ObjRelocKind::PpcRel24, section, word, target);
+                        write_u32(&mut data, (section.offset + word) as usize, ins & !0x3FFFFFC);
+                    }
+                }
+                // lis rD, hi
+                15 if (ins >> 16) & 31 == 0 => {
+                    let rd = (ins >> 21) & 31;
+                    for j in i + 1..(i + 9).min(words) {
+                        let next = read(&data, j * 4);
+                        let (op, rt, ra) = (next >> 26, (next >> 21) & 31, (next >> 16) & 31);
+                        if ra == rd && (op == 14 || (32..=55).contains(&op)) {
+                            let value = (ins << 16).wrapping_add(next as i16 as i32 as u32);
+                            if let Some(target) = find_target(value) {
+                                add_reloc(ObjRelocKind::PpcAddr16Ha, section, word, target);
+                                add_reloc(ObjRelocKind::PpcAddr16Lo, section, j * 4, target);
+                                write_u32(
+                                    &mut data,
+                                    (section.offset + word) as usize,
+                                    ins & !0xFFFF,
+                                );
+                                write_u32(
+                                    &mut data,
+                                    (section.offset + j * 4) as usize,
+                                    next & !0xFFFF,
+                                );
+                            }
+                            break;
+                        }
+                        // Stop at branches, or once rD is overwritten (other than by stores)
+                        let is_store = matches!(op, 36..=39 | 44 | 45 | 47 | 52..=55);
+                        if matches!(op, 16 | 18 | 19) || (rt == rd && !is_store) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let count = relocations.len();
+    append_rel_relocations(&mut data, &mut header, relocations)?;
+    Ok(RecoveredRel {
+        data,
+        address,
+        bss_address,
+        relocations: count,
+        modified: vec![],
+        mismatched: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: u32 = 0x80500000;
+    const BSS: u32 = 0x80600000;
+    const DOL_FUNCTION: u32 = 0x80003000;
+    const TEXT: u32 = 0x80;
+    const DATA: u32 = 0x90;
+
+    /// A loaded version 3 module with text, data and BSS sections: `bl` into the DOL,
+    /// `lis`/`addi` of the data section, and pointers to the text and BSS sections.
+    fn dump() -> Vec<u8> {
+        let mut dump = vec![0u8; 0x98];
+        write_u32(&mut dump, 0x00, 5); // module_id
+        write_u32(&mut dump, 0x0C, 4); // num_sections
+        write_u32(&mut dump, 0x10, ADDRESS + 0x4C); // section_info_offset
+        write_u32(&mut dump, 0x1C, 3); // version
+        write_u32(&mut dump, 0x20, 0x20); // bss_size
+        dump[0x33] = 3; // bss_section
+        write_u32(&mut dump, 0x4C + 8, (ADDRESS + TEXT) | 1);
+        write_u32(&mut dump, 0x4C + 12, 0x10);
+        write_u32(&mut dump, 0x4C + 16, ADDRESS + DATA);
+        write_u32(&mut dump, 0x4C + 20, 0x8);
+        write_u32(&mut dump, 0x4C + 24, BSS);
+        write_u32(&mut dump, 0x4C + 28, 0x20);
+
+        let site = ADDRESS + TEXT;
+        let data = ADDRESS + DATA;
+        let code = [
+            0x48000001 | (DOL_FUNCTION.wrapping_sub(site) & 0x3FFFFFC), // bl
+            0x3C600000 | (data.wrapping_add(0x8000) >> 16),             // lis r3, data@ha
+            0x38630000 | (data & 0xFFFF),                               // addi r3, r3, data@l
+            0x4E800020,                                                 // blr
+        ];
+        for (i, ins) in code.into_iter().enumerate() {
+            write_u32(&mut dump, (TEXT + i as u32 * 4) as usize, ins);
+        }
+        write_u32(&mut dump, DATA as usize, ADDRESS + TEXT);
+        write_u32(&mut dump, DATA as usize + 4, BSS + 4);
+        dump
+    }
+
+    fn options(original: Option<&[u8]>) -> RecoverOptions<'_> {
+        RecoverOptions { address: None, original, dol_ranges: vec![0x80003000..0x80004000] }
+    }
+
+    fn relocations(data: &[u8]) -> Vec<RelReloc> {
+        let header = process_rel_header(&mut Cursor::new(data)).unwrap();
+        RelRelocReader::new(Cursor::new(data), &header).collect::<Result<Vec<_>>>().unwrap()
+    }
+
+    #[test]
+    fn recover_guessed() {
+        let rel = recover_rel(&dump(), &options(None)).unwrap();
+        assert_eq!(rel.address, ADDRESS);
+        assert_eq!(rel.bss_address, Some(BSS));
+        assert_eq!(rel.relocations, 5);
+
+        let header = process_rel_header(&mut Cursor::new(&rel.data)).unwrap();
+        assert_eq!(header.section_info_offset, 0x4C);
+        let sections = process_rel_sections(&mut Cursor::new(&rel.data), &header).unwrap();
+        assert_eq!(
+            (sections[1].offset(), sections[2].offset(), sections[3].offset()),
+            (TEXT, DATA, 0)
+        );
+
+        let mut found = relocations(&rel.data)
+            .iter()
+            .map(|r| (r.section, r.address, r.kind, r.module_id, r.target_section, r.addend))
+            .collect::<Vec<_>>();
+        found.sort_by_key(|&(section, address, ..)| (section, address));
+        assert_eq!(found, [
+            (1, 0x0, ObjRelocKind::PpcRel24, 0, 0, DOL_FUNCTION),
+            (1, 0x4, ObjRelocKind::PpcAddr16Ha, 5, 2, 0),
+            (1, 0x8, ObjRelocKind::PpcAddr16Lo, 5, 2, 0),
+            (2, 0x0, ObjRelocKind::Absolute, 5, 1, 0),
+            (2, 0x4, ObjRelocKind::Absolute, 5, 3, 4),
+        ]);
+        // Relocated fields are cleared
+        assert_eq!(read_u32(&rel.data, TEXT as usize), 0x48000001);
+        assert_eq!(read_u32(&rel.data, DATA as usize), 0);
+    }
+
+    #[test]
+    fn recover_with_original() {
+        let original = recover_rel(&dump(), &options(None)).unwrap().data;
+        let rel = recover_rel(&dump(), &options(Some(&original))).unwrap();
+        assert_eq!((rel.relocations, rel.mismatched), (5, 0));
+        assert!(rel.modified.is_empty());
+        assert_eq!(rel.data, original);
+
+        // blr -> nop at runtime
+        let mut patched = dump();
+        write_u32(&mut patched, TEXT as usize + 0xC, 0x60000000);
+        let rel = recover_rel(&patched, &options(Some(&original))).unwrap();
+        assert_eq!(rel.modified, [(1, 0xC)]);
+
+        // Loaded somewhere else than the header says
+        let options = RecoverOptions { address: Some(ADDRESS - 0x100), ..options(Some(&original)) };
+        assert!(recover_rel(&dump(), &options).is_err());
+    }
+
+    #[test]
+    fn recover_truncated() {
+        let mut dump = dump();
+        write_u32(&mut dump, 0x0C, 0x10000000); // num_sections
+        assert!(recover_rel(&dump, &options(None)).is_err());
+        assert!(recover_rel(&dump[..0x40], &options(None)).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use std::{collections::BTreeMap, io::Read};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use flate2::read::ZlibDecoder;
+use object::{
+    elf,
+    elf::{FileHeader32, Rela32, SectionHeader32},
+    pod,
+    read::elf::{FileHeader, SectionHeader},
+    BigEndian,
+};
+
+/// OS/ABI of Wii U (Cafe OS) executables.
+pub const ELFOSABI_CAFE: u8 = 0xCA;
+/// ELF type of both RPX executables and RPL libraries.
+pub const ET_CAFE_RPL: u16 = 0xFE01;
+
+/// Section data is a big-endian uncompressed size followed by a zlib stream.
+pub const SHF_RPL_ZLIB: u32 = 0x08000000;
+
+pub const SHT_RPL_EXPORTS: u32 = 0x80000001;
+pub const SHT_RPL_IMPORTS: u32 = 0x80000002;
+pub const SHT_RPL_CRCS: u32 = 0x80000003;
+pub const SHT_RPL_FILEINFO: u32 = 0x80000004;
+
+pub const R_PPC_GHS_REL16_HA: u32 = 251;
+pub const R_PPC_GHS_REL16_HI: u32 = 252;
+pub const R_PPC_GHS_REL16_LO: u32 = 253;
+
+const ELF_HEADER_SIZE: usize = 0x34;
+const ELF_SECTION_HEADER_SIZE: usize = 0x28;
+
+/// Whether a file is a Wii U RPX or RPL.
+pub fn is_rpl(data: &[u8]) -> bool {
+    data.len() >= ELF_HEADER_SIZE
+        && data[0..4] == elf::ELFMAG
+        && data[elf::EI_CLASS] == elf::ELFCLASS32
+        && data[elf::EI_DATA] == elf::ELFDATA2MSB
+        && data[elf::EI_OSABI] == ELFOSABI_CAFE
+}
+
+/// Rebuilds an RPX or RPL as a standard ELF executable, so it can be read like any other ELF:
+///
+/// - Compressed sections are decompressed.
+/// - Import sections become read-only data, so that relocations against imported symbols can
+///   be resolved. Export, CRC and file info sections are left as they are, and are ignored
+///   when loading.
+/// - The `.comment` section is emptied, since Green Hills' isn't in the CodeWarrior format.
+/// - Green Hills' `R_PPC_GHS_REL16_*` relocations are renumbered to the standard
+///   `R_PPC_REL16_*`, which use the same numbers in a different order.
+/// - Relocations that dtk doesn't model are removed, with a warning. They're already applied
+///   to the data, but the references they make aren't tracked.
+pub fn decompress_rpl(data: &[u8]) -> Result<Vec<u8>> {
+    let e = BigEndian;
+    let header = FileHeader32::<BigEndian>::parse(data)?;
+    ensure!(header.e_type(e) == ET_CAFE_RPL, "Unexpected RPL type {:#06X}", header.e_type(e));
+    ensure!(header.e_machine(e) == elf::EM_PPC, "Expected PowerPC, got {}", header.e_machine(e));
+    let section_headers = header.section_headers(e, data)?;
+
+    let mut section_data = Vec::with_capacity(section_headers.len());
+    for (index, section) in section_headers.iter().enumerate() {
+        if section.sh_type(e) == elf::SHT_NOBITS || section.sh_type(e) == elf::SHT_NULL {
+            section_data.push(vec![]);
+            continue;
+        }
+        let raw =
+            section.data(e, data).map_err(|_| anyhow!("Section {} data out of bounds", index))?;
+        if section.sh_flags(e) & SHF_RPL_ZLIB == 0 {
+            section_data.push(raw.to_vec());
+            continue;
+        }
+        ensure!(raw.len() >= 4, "Compressed section {} is too small", index);
+        let size = u32::from_be_bytes(raw[0..4].try_into().unwrap()) as usize;
+        // The size comes from the file, so only trust it as far as the file goes
+        let mut out = Vec::with_capacity(size.min(data.len()));
+        ZlibDecoder::new(&raw[4..])
+            .take(size as u64 + 1)
+            .read_to_end(&mut out)
+            .with_context(|| format!("While decompressing section {}", index))?;
+        ensure!(
+            out.len() == size,
+            "Section {} decompressed to {:#X} bytes, expected {:#X}",
+            index,
+            out.len(),
+            size
+        );
+        section_data.push(out);
+    }
+
+    let shstrndx = header.e_shstrndx(e) as usize;
+    let section_name = |section: &SectionHeader32<BigEndian>| -> &[u8] {
+        let Some(names) = section_data.get(shstrndx) else {
+            return &[];
+        };
+        let start = section.sh_name(e) as usize;
+        let name = names.get(start..).unwrap_or_default();
+        &name[..name.iter().position(|&c| c == 0).unwrap_or(name.len())]
+    };
+    if let Some(index) = section_headers.iter().position(|s| section_name(s) == b".comment") {
+        section_data[index].clear();
+    }
+
+    let mut dropped = BTreeMap::<u32, usize>::new();
+    for (section, data) in section_headers.iter().zip(&mut section_data) {
+        if section.sh_type(e) != elf::SHT_RELA {
+            continue;
+        }
+        let relocations: &[Rela32<BigEndian>] = pod::slice_from_all_bytes(data)
+            .map_err(|_| anyhow!("Invalid relocation section size {:#X}", data.len()))?;
+        let mut out = Vec::with_capacity(data.len());
+        for reloc in relocations {
+            let r_type = reloc.r_type(e);
+            let r_type = match r_type {
+                elf::R_PPC_ADDR32
+                | elf::R_PPC_ADDR16_LO
+                | elf::R_PPC_ADDR16_HI
+                | elf::R_PPC_ADDR16_HA
+                | elf::R_PPC_REL24
+                | elf::R_PPC_REL14
+                | elf::R_PPC_EMB_SDA21
+                | elf::R_PPC_REL32
+                | elf::R_PPC_DTPMOD32
+                | elf::R_PPC_DTPREL32 => r_type,
+                R_PPC_GHS_REL16_HA => elf::R_PPC_REL16_HA,
+                R_PPC_GHS_REL16_HI => elf::R_PPC_REL16_HI,
+                R_PPC_GHS_REL16_LO => elf::R_PPC_REL16_LO,
+                _ => {
+                    *dropped.entry(r_type).or_default() += 1;
+                    continue;
+                }
+            };
+            let mut reloc = *reloc;
+            reloc.set_r_info(e, reloc.r_sym(e), r_type as u8);
+            out.extend_from_slice(pod::bytes_of(&reloc));
+        }
+        *data = out;
+    }
+    for (kind, count) in dropped {
+        log::warn!(
+            "Removed {} relocations of type {}, their references won't be tracked",
+            count,
+            kind
+        );
+    }
+
+    // Lay out the section data after the ELF header, followed by the section headers
+    let mut out = vec![0u8; ELF_HEADER_SIZE];
+    let mut out_sections = Vec::with_capacity(section_headers.len());
+    for (section, data) in section_headers.iter().zip(&section_data) {
+        let mut section = *section;
+        section.sh_flags.set(e, section.sh_flags(e) & !SHF_RPL_ZLIB);
+        match section.sh_type(e) {
+            elf::SHT_NULL => {}
+            elf::SHT_NOBITS => section.sh_offset.set(e, 0),
+            kind => {
+                if kind == SHT_RPL_IMPORTS {
+                    section.sh_type.set(e, elf::SHT_PROGBITS);
+                    let flags = section.sh_flags(e) & !(elf::SHF_EXECINSTR | elf::SHF_WRITE);
+                    section.sh_flags.set(e, flags);
+                }
+                out.resize((out.len() + 0x3F) & !0x3F, 0);
+                section.sh_offset.set(e, out.len() as u32);
+                section.sh_size.set(e, data.len() as u32);
+                out.extend_from_slice(data);
+            }
+        }
+        out_sections.push(section);
+    }
+    out.resize((out.len() + 3) & !3, 0);
+    let section_header_offset = out.len();
+    for section in &out_sections {
+        out.extend_from_slice(pod::bytes_of(section));
+    }
+
+    let mut header = *header;
+    header.e_type.set(e, elf::ET_EXEC);
+    header.e_phoff.set(e, 0);
+    header.e_phentsize.set(e, 0);
+    header.e_phnum.set(e, 0);
+    header.e_shoff.set(e, section_header_offset as u32);
+    header.e_ehsize.set(e, ELF_HEADER_SIZE as u16);
+    header.e_shentsize.set(e, ELF_SECTION_HEADER_SIZE as u16);
+    out[..ELF_HEADER_SIZE].copy_from_slice(pod::bytes_of(&header));
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::ZlibEncoder, Compression};
+
+    use super::*;
+
+    fn push_u16(out: &mut Vec<u8>, value: u16) { out.extend_from_slice(&value.to_be_bytes()); }
+
+    fn push_u32(out: &mut Vec<u8>, value: u32) { out.extend_from_slice(&value.to_be_bytes()); }
+
+    /// An RPL with a compressed `.text` section and its relocations.
+    fn rpl(text: &[u8], relocations: &[(u32, u32)]) -> Vec<u8> {
+        let mut compressed = (text.len() as u32).to_be_bytes().to_vec();
+        let mut encoder = ZlibEncoder::new(&mut compressed, Compression::default());
+        encoder.write_all(text).unwrap();
+        encoder.finish().unwrap();
+        let mut rela = vec![];
+        for &(offset, r_type) in relocations {
+            push_u32(&mut rela, offset);
+            push_u32(&mut rela, (1 << 8) | r_type);
+            push_u32(&mut rela, 0);
+        }
+        let names = b"\0.text\0.rela.text\0.shstrtab\0";
+
+        let mut out = vec![0x7F, b'E', b'L', b'F', 1, 2, 1, ELFOSABI_CAFE];
+        out.resize(16, 0);
+        push_u16(&mut out, ET_CAFE_RPL);
+        push_u16(&mut out, elf::EM_PPC);
+        push_u32(&mut out, 1);
+        push_u32(&mut out, 0);
+        push_u32(&mut out, 0);
+        let shoff = ELF_HEADER_SIZE + compressed.len() + rela.len() + names.len();
+        push_u32(&mut out, shoff as u32);
+        push_u32(&mut out, 0);
+        push_u16(&mut out, ELF_HEADER_SIZE as u16);
+        push_u16(&mut out, 0);
+        push_u16(&mut out, 0);
+        push_u16(&mut out, ELF_SECTION_HEADER_SIZE as u16);
+        push_u16(&mut out, 4);
+        push_u16(&mut out, 3);
+        let mut offset = out.len() as u32;
+        let mut sections = vec![[0; 10]];
+        let text_flags = SHF_RPL_ZLIB | elf::SHF_ALLOC | elf::SHF_EXECINSTR;
+        for (name, kind, flags, data, info) in [
+            (1, elf::SHT_PROGBITS, text_flags, compressed.as_slice(), 0),
+            (7, elf::SHT_RELA, 0, rela.as_slice(), 1),
+            (18, elf::SHT_STRTAB, 0, names.as_slice(), 0),
+        ] {
+            sections.push([name, kind, flags, 0, offset, data.len() as u32, 0, info, 4, 0]);
+            offset += data.len() as u32;
+            out.extend_from_slice(data);
+        }
+        for section in sections {
+            for value in section {
+                push_u32(&mut out, value);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn decompress_and_renumber() {
+        let text = [0x3C, 0x60, 0x00, 0x00, 0x38, 0x63, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let data = rpl(&text, &[
+            (0, elf::R_PPC_ADDR16_HA),
+            (2, R_PPC_GHS_REL16_HA),
+            (4, R_PPC_GHS_REL16_LO),
+            (8, elf::R_PPC_REL32),
+            (8, 200),
+        ]);
+        assert!(is_rpl(&data));
+        assert!(!is_rpl(&text));
+
+        let out = decompress_rpl(&data).unwrap();
+        let e = BigEndian;
+        let header = FileHeader32::<BigEndian>::parse(out.as_slice()).unwrap();
+        assert_eq!(header.e_type(e), elf::ET_EXEC);
+        let sections = header.section_headers(e, out.as_slice()).unwrap();
+        assert_eq!(sections.len(), 4);
+        assert_eq!(sections[1].sh_flags(e) & SHF_RPL_ZLIB, 0);
+        assert_eq!(sections[1].data(e, out.as_slice()).unwrap(), text);
+        let rela = sections[2].data(e, out.as_slice()).unwrap();
+        let relocations: &[Rela32<BigEndian>] = pod::slice_from_all_bytes(rela).unwrap();
+        let relocations: Vec<_> =
+            relocations.iter().map(|r| (r.r_offset.get(e), r.r_sym(e), r.r_type(e))).collect();
+        // The unknown relocation is removed
+        assert_eq!(relocations, [
+            (0, 1, elf::R_PPC_ADDR16_HA),
+            (2, 1, elf::R_PPC_REL16_HA),
+            (4, 1, elf::R_PPC_REL16_LO),
+            (8, 1, elf::R_PPC_REL32),
+        ]);
+    }
+}
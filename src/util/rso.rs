@@ -9,7 +9,7 @@ use cwdemangle::{demangle, DemangleOptions};
 use crate::{
     obj::{
         ObjArchitecture, ObjInfo, ObjKind, ObjSection, ObjSectionKind, ObjSymbol, ObjSymbolFlagSet,
-        ObjSymbolFlags, ObjSymbolKind,
+        ObjSymbolFlags, ObjSymbolKind, ObjSymbolSource, SymbolName,
     },
     util::{
         file::{read_c_string, read_string},
@@ -255,7 +255,7 @@ impl RsoSectionHeader {
     pub fn exec(&self) -> bool { self.offset_and_flags & 1 != 0 }
 }
 
-struct RsoRelocation {
+pub struct RsoRelocation {
     /// Absolute offset of this relocation (relative to the start of the RSO file).
     offset: u32,
     /// For internal relocations, this is the section index of the symbol being patched to.
@@ -315,22 +315,22 @@ impl RsoRelocation {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum RsoSymbolKind {
+pub enum RsoSymbolKind {
     Import,
     Export,
 }
 
-struct RsoSymbol {
+pub struct RsoSymbol {
     /// Relative offset into the name table pointed to in the header,
     /// which points to the name of this symbol.
-    name_offset: u32,
+    pub name_offset: u32,
     /// The section-relative offset to the symbol. This is always 0 for imports.
-    offset: u32,
+    pub offset: u32,
     /// For exports, index of the section that contains this symbol.
     /// For imports, appears to be an offset?
-    section_index: u32,
+    pub section_index: u32,
     /// A hash of the symbol name. Only present for exports.
-    hash: Option<u32>,
+    pub hash: Option<u32>,
 }
 
 impl FromReader for RsoSymbol {
@@ -429,18 +429,28 @@ where R: Read + Seek + ?Sized {
     let mut symbols = Vec::new();
     let mut add_symbol = |rel_section_idx: u8, offset: u32, name: &str| -> Result<()> {
         if rel_section_idx > 0 {
-            let (section_index, _) = sections
+            let (section_index, section) = sections
                 .iter()
                 .enumerate()
                 .find(|&(_, section)| section.elf_index == rel_section_idx as usize)
                 .ok_or_else(|| anyhow!("Failed to locate {name} section {rel_section_idx}"))?;
+            if section.kind != ObjSectionKind::Code || offset as u64 >= section.size {
+                log::warn!(
+                    "Ignoring {name} at section {rel_section_idx} offset {offset:#X}: \
+                     not within a code section"
+                );
+                return Ok(());
+            }
             log::debug!("Adding {name} section {rel_section_idx} offset {offset:#X}");
+            let mut flags = ObjSymbolFlagSet(ObjSymbolFlags::Global.into());
+            flags.set_force_active(true);
             symbols.push(ObjSymbol {
-                name: name.to_string(),
+                name: name.into(),
                 address: offset as u64,
                 section: Some(section_index),
-                flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
+                flags,
                 kind: ObjSymbolKind::Function,
+                source: ObjSymbolSource::Binary,
                 ..Default::default()
             });
         }
@@ -494,10 +504,11 @@ where R: Read + Seek + ?Sized {
             hash_n
         );
         symbols.push(ObjSymbol {
-            name,
-            demangled_name,
+            name: name.into(),
+            demangled_name: demangled_name.map(SymbolName::from),
             address: symbol.offset as u64,
             section: Some(section),
+            source: ObjSymbolSource::Binary,
             ..Default::default()
         });
     }
@@ -524,7 +535,8 @@ where R: Read + Seek + ?Sized {
     Ok(obj)
 }
 
-fn symbol_hash(s: &str) -> u32 {
+/// ELF hash of a symbol name, as stored in the export table.
+pub fn symbol_hash(s: &str) -> u32 {
     s.bytes().fold(0u32, |hash, c| {
         let mut m = (hash << 4).wrapping_add(c as u32);
         let n = m & 0xF0000000;
@@ -0,0 +1,133 @@
+use std::{fmt::Write, path::PathBuf};
+
+use anyhow::Result;
+use path_slash::PathBufExt;
+use serde::{Deserialize, Serialize};
+
+use crate::obj::{ObjInfo, ObjSymbolKind, ObjUnit, ObjUnitLanguage};
+
+/// Function stub style for generated source files.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScaffoldStyle {
+    /// `INCLUDE_ASM("asm/unit.s", name);`
+    #[default]
+    IncludeAsm,
+    /// `#pragma GLOBAL_ASM("asm/unit.s", "name")`
+    Pragma,
+}
+
+const PROLOGUE: &str = r#"
+#ifndef INCLUDE_ASM
+#error "INCLUDE_ASM must be defined to include functions from their assembly"
+#endif
+
+"#;
+
+/// Returns the source path for a unit, or `None` if the unit shouldn't have a source file
+/// generated (assembly or autogenerated units). An explicit `source` attribute takes precedence.
+pub fn source_path_for_unit(unit: &ObjUnit) -> Option<PathBuf> {
+    if let Some(source) = &unit.source {
+        return Some(PathBuf::from_slash(source));
+    }
+    if unit.autogenerated || unit.lang == Some(ObjUnitLanguage::Asm) {
+        return None;
+    }
+    let path = PathBuf::from_slash(&unit.name);
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("c" | "cp" | "cpp" | "cxx" | "cc") => Some(path),
+        Some("s") => None,
+        _ => Some(path.with_extension(if is_cpp(unit) { "cpp" } else { "c" })),
+    }
+}
+
+pub fn is_cpp(unit: &ObjUnit) -> bool {
+    match unit.lang {
+        Some(lang) => lang == ObjUnitLanguage::Cpp,
+        None => PathBuf::from_slash(&unit.name)
+            .extension()
+            .is_some_and(|e| matches!(e.to_str(), Some("cp" | "cpp" | "cxx" | "cc"))),
+    }
+}
+
+pub fn is_c_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Generates a skeleton source file for a split unit, containing extern declarations
+/// for all referenced symbols and a stub for every function.
+///
+/// `obj` is the original (unsplit) object, used to look up the kind of extern symbols.
+pub fn generate_scaffold(
+    obj: &ObjInfo,
+    split_obj: &ObjInfo,
+    unit: &ObjUnit,
+    asm_path: &str,
+    style: ScaffoldStyle,
+) -> Result<String> {
+    let cpp = is_cpp(unit);
+    let mut output = String::new();
+    if style == ScaffoldStyle::IncludeAsm {
+        output.push_str(PROLOGUE);
+    }
+
+    let mut externs = String::new();
+    for symbol in split_obj.symbols.iter().filter(|s| s.section.is_none()) {
+        let kind = obj
+            .symbols
+            .for_name(&symbol.name)
+            .next()
+            .map(|(_, s)| s.kind)
+            .unwrap_or(ObjSymbolKind::Unknown);
+        if let Some(name) = &symbol.demangled_name {
+            writeln!(externs, "// {name}")?;
+        }
+        if !is_c_identifier(&symbol.name) {
+            // Mangled names can't be declared without type information
+            writeln!(externs, "// extern {};", symbol.name)?;
+            continue;
+        }
+        if cpp {
+            externs.push_str("extern \"C\" ");
+        } else {
+            externs.push_str("extern ");
+        }
+        match kind {
+            ObjSymbolKind::Function => writeln!(externs, "void {}();", symbol.name)?,
+            _ => writeln!(externs, "unsigned char {}[];", symbol.name)?,
+        }
+    }
+    if !externs.is_empty() {
+        output.push_str(&externs);
+        output.push('\n');
+    }
+
+    for symbol in split_obj
+        .symbols
+        .iter()
+        .filter(|s| s.section.is_some() && s.kind == ObjSymbolKind::Function)
+    {
+        let section = &split_obj.sections[symbol.section.unwrap()];
+        writeln!(
+            output,
+            "// {} (size: {:#X}, address: {:#X}, section: {})",
+            symbol.demangled_name.as_deref().unwrap_or(&symbol.name),
+            symbol.size,
+            symbol.address + section.original_address,
+            section.name
+        )?;
+        match style {
+            // INCLUDE_ASM takes the name as a token, so fall back to #pragma for mangled names
+            ScaffoldStyle::IncludeAsm if is_c_identifier(&symbol.name) => {
+                writeln!(output, "INCLUDE_ASM(\"{asm_path}\", {});", symbol.name)?;
+            }
+            ScaffoldStyle::IncludeAsm | ScaffoldStyle::Pragma => {
+                writeln!(output, "#pragma GLOBAL_ASM(\"{asm_path}\", \"{}\")", symbol.name)?;
+            }
+        }
+        output.push('\n');
+    }
+    Ok(output)
+}
@@ -18,7 +18,7 @@ use crate::{
     array_ref,
     obj::{
         ObjInfo, ObjKind, ObjReloc, ObjRelocKind, ObjSection, ObjSymbol, ObjSymbolFlagSet,
-        ObjSymbolKind,
+        ObjSymbolKind, ObjSymbolSource, SymbolName,
     },
     util::elf::process_elf,
 };
@@ -131,10 +131,11 @@ pub fn apply_symbol(
         // Hack to mark linker generated symbols as ABS
         target_section_index = None;
     }
-    let demangled_name = demangle(&sig_symbol.name, &DemangleOptions::default());
+    let demangled_name =
+        demangle(&sig_symbol.name, &DemangleOptions::default()).map(SymbolName::from);
     let target_symbol_idx = obj.add_symbol(
         ObjSymbol {
-            name: sig_symbol.name.clone(),
+            name: SymbolName::new(&sig_symbol.name),
             demangled_name,
             address: target.address as u64,
             section: target_section_index,
@@ -142,6 +143,7 @@ pub fn apply_symbol(
             size_known: sig_symbol.size > 0 || sig_symbol.kind == ObjSymbolKind::Unknown,
             flags: sig_symbol.flags,
             kind: sig_symbol.kind,
+            source: ObjSymbolSource::Signature,
             ..Default::default()
         },
         false,
@@ -246,12 +248,40 @@ pub fn compare_signature(existing: &mut FunctionSignature, new: &FunctionSignatu
     Ok(())
 }
 
+/// Signatures of a library version, by function name.
+pub type SignatureSet = BTreeMap<String, Vec<FunctionSignature>>;
+
+/// Adds the signatures of `other` that aren't already in `set`. Returns the number added.
+pub fn merge_signature_set(set: &mut SignatureSet, other: SignatureSet) -> usize {
+    let mut added = 0;
+    for (name, signatures) in other {
+        let existing = set.entry(name).or_default();
+        for signature in signatures {
+            if !existing.iter().any(|s| s.hash == signature.hash) {
+                existing.push(signature);
+                added += 1;
+            }
+        }
+    }
+    added
+}
+
 pub fn generate_signature<P>(path: P, symbol_name: &str) -> Result<Option<FunctionSignature>>
 where P: AsRef<Path> {
-    let mut out_symbols: Vec<OutSymbol> = Vec::new();
-    let mut out_relocs: Vec<OutReloc> = Vec::new();
-    let mut symbol_map: BTreeMap<usize, usize> = BTreeMap::new();
+    let alt_name = symbol_name.replace("TRK", "TRK_");
+    let signatures =
+        generate_signatures(path, |s| s.name == symbol_name || s.name == alt_name, true)?;
+    Ok(signatures.into_iter().next())
+}
 
+/// Builds signatures for the functions matching `filter`. With `first`, stops at the
+/// first match.
+pub fn generate_signatures<P, F>(path: P, filter: F, first: bool) -> Result<Vec<FunctionSignature>>
+where
+    P: AsRef<Path>,
+    F: Fn(&ObjSymbol) -> bool,
+{
+    let mut out = vec![];
     let mut obj = process_elf(path)?;
     if obj.kind == ObjKind::Executable
         && (obj.sda2_base.is_none()
@@ -270,27 +300,33 @@ where P: AsRef<Path> {
             obj.arena_hi,
             obj.arena_lo
         );
-        return Ok(None);
+        return Ok(out);
     }
     let mut tracker = Tracker::new(&obj);
     // tracker.ignore_addresses.insert(0x80004000);
     for (_, symbol) in obj.symbols.by_kind(ObjSymbolKind::Function) {
-        if symbol.name != symbol_name && symbol.name != symbol_name.replace("TRK", "TRK_") {
+        if !filter(symbol) {
             continue;
         }
         tracker.process_function(&obj, symbol)?;
+        if first {
+            break;
+        }
     }
     tracker.apply(&mut obj, true)?; // true
     for (_, symbol) in obj.symbols.by_kind(ObjSymbolKind::Function) {
-        if symbol.name != symbol_name && symbol.name != symbol_name.replace("TRK", "TRK_") {
+        if !filter(symbol) {
             continue;
         }
+        let mut out_symbols: Vec<OutSymbol> = Vec::new();
+        let mut out_relocs: Vec<OutReloc> = Vec::new();
+        let mut symbol_map: BTreeMap<usize, usize> = BTreeMap::new();
         let section_idx = symbol.section.unwrap();
         let section = &obj.sections[section_idx];
         // let out_symbol_idx = out_symbols.len();
         out_symbols.push(OutSymbol {
             kind: symbol.kind,
-            name: symbol.name.clone(),
+            name: symbol.name.to_string(),
             size: symbol.size as u32,
             flags: symbol.flags,
             section: Some(section.name.clone()),
@@ -316,7 +352,7 @@ where P: AsRef<Path> {
                         e.insert(symbol_idx);
                         out_symbols.push(OutSymbol {
                             kind: target.kind,
-                            name: target.name.clone(),
+                            name: target.name.to_string(),
                             size: if target.kind == ObjSymbolKind::Function {
                                 0
                             } else {
@@ -333,13 +369,19 @@ where P: AsRef<Path> {
                     btree_map::Entry::Occupied(e) => *e.get(),
                 };
                 match reloc.kind {
-                    ObjRelocKind::Absolute => {
+                    ObjRelocKind::Absolute
+                    | ObjRelocKind::PpcRel32
+                    | ObjRelocKind::PpcDtpMod32
+                    | ObjRelocKind::PpcDtpRel32 => {
                         *ins = 0;
                         *pat = 0;
                     }
                     ObjRelocKind::PpcAddr16Hi
                     | ObjRelocKind::PpcAddr16Ha
-                    | ObjRelocKind::PpcAddr16Lo => {
+                    | ObjRelocKind::PpcAddr16Lo
+                    | ObjRelocKind::PpcRel16Hi
+                    | ObjRelocKind::PpcRel16Ha
+                    | ObjRelocKind::PpcRel16Lo => {
                         *ins &= !0xFFFF;
                         *pat = !0xFFFF;
                     }
@@ -378,13 +420,16 @@ where P: AsRef<Path> {
         let mut hash_buf = [0u8; 40];
         let hash_str = base16ct::lower::encode_str(&hash, &mut hash_buf)
             .map_err(|e| anyhow!("Failed to encode hash: {e}"))?;
-        return Ok(Some(FunctionSignature {
+        out.push(FunctionSignature {
             symbol: 0,
             hash: hash_str.to_string(),
             signature: encoded,
             symbols: out_symbols,
             relocations: out_relocs,
-        }));
+        });
+        if first {
+            break;
+        }
     }
-    Ok(None)
+    Ok(out)
 }
@@ -0,0 +1,305 @@
+use std::{
+    collections::HashMap,
+    fs,
+    fs::OpenOptions,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use once_cell::sync::Lazy;
+
+use crate::util::{
+    file::buf_writer,
+    signatures::{merge_signature_set, SignatureSet},
+};
+
+/// A shared store of signature sets, keyed by library version (e.g. `sdk-2002-12` or
+/// `msl-2.4.2`). Each set is a YAML file `<key>.yml` in a directory, or under an HTTP(S) URL.
+///
+/// Analysis doesn't read remote stores. `dtk signatures pull -c` copies a project's sets to its
+/// `signature_cache`, where they stay pinned until they're pulled again.
+#[derive(Debug, Clone)]
+pub enum SignatureStore {
+    Local(PathBuf),
+    Remote(String),
+}
+
+/// Attempts at pushing to a remote set that keeps changing underneath.
+const PUSH_ATTEMPTS: usize = 5;
+
+impl SignatureStore {
+    pub fn new(location: &str) -> Self {
+        if location.starts_with("http://") || location.starts_with("https://") {
+            Self::Remote(location.trim_end_matches('/').to_string())
+        } else {
+            Self::Local(PathBuf::from(location))
+        }
+    }
+
+    /// Reads a set. A set that doesn't exist yet is empty.
+    pub fn pull(&self, key: &str) -> Result<SignatureSet> {
+        match self {
+            Self::Local(dir) => read_set(&set_path(dir, key)?),
+            Self::Remote(url) => {
+                check_key(key)?;
+                match fetch(url, key)? {
+                    Some((text, _)) => {
+                        parse_set(&text).with_context(|| format!("While parsing '{url}/{key}.yml'"))
+                    }
+                    None => Ok(SignatureSet::new()),
+                }
+            }
+        }
+    }
+
+    /// Adds signatures to a set. Returns the number of new signatures.
+    ///
+    /// Local sets are locked while they're updated. Remote sets are only replaced if they're
+    /// unchanged since they were read (`If-Match`, or `If-None-Match` for a new set), and
+    /// merged again otherwise.
+    pub fn push(&self, key: &str, signatures: SignatureSet) -> Result<usize> {
+        match self {
+            Self::Local(dir) => {
+                let path = set_path(dir, key)?;
+                let _lock = FileLock::acquire(&path)?;
+                let mut set = read_set(&path)?;
+                let added = merge_signature_set(&mut set, signatures);
+                if added > 0 {
+                    write_set(&path, &set)?;
+                }
+                Ok(added)
+            }
+            Self::Remote(url) => {
+                check_key(key)?;
+                for _ in 0..PUSH_ATTEMPTS {
+                    let (mut set, precondition) = match fetch(url, key)? {
+                        Some((text, etag)) => {
+                            let set = parse_set(&text)
+                                .with_context(|| format!("While parsing '{url}/{key}.yml'"))?;
+                            (set, etag.map(|etag| ("If-Match", etag)))
+                        }
+                        None => (SignatureSet::new(), Some(("If-None-Match", "*".to_string()))),
+                    };
+                    if precondition.is_none() {
+                        log::warn!("{url} doesn't send an ETag; concurrent pushes may be lost");
+                    }
+                    let added = merge_signature_set(&mut set, signatures.clone());
+                    if added == 0 {
+                        return Ok(0);
+                    }
+                    let text = serde_yaml::to_string(&set)?;
+                    let precondition =
+                        precondition.as_ref().map(|(name, value)| (*name, value.as_str()));
+                    if upload(url, key, &text, precondition)? {
+                        return Ok(added);
+                    }
+                    log::info!("{key} was changed by another push; merging again");
+                }
+                bail!("Failed to push to '{url}/{key}.yml': the set kept changing")
+            }
+        }
+    }
+}
+
+/// Path of a set in a local store or cache.
+pub fn set_path(dir: &Path, key: &str) -> Result<PathBuf> {
+    check_key(key)?;
+    Ok(dir.join(format!("{key}.yml")))
+}
+
+/// Sets already read by [`read_pinned_set`], by path.
+static PINNED: Lazy<Mutex<HashMap<PathBuf, SignatureSet>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Reads a set for analysis, once per process. Unlike [`SignatureStore::pull`], a missing set
+/// is an error.
+pub fn read_pinned_set(path: &Path) -> Result<SignatureSet> {
+    if let Some(set) = PINNED.lock().unwrap().get(path) {
+        return Ok(set.clone());
+    }
+    ensure!(path.is_file(), "Signature set '{}' not found", path.display());
+    let set = read_set(path)?;
+    PINNED.lock().unwrap().insert(path.to_path_buf(), set.clone());
+    Ok(set)
+}
+
+/// Writes a set, replacing the file only once it's complete.
+pub fn write_set(path: &Path, set: &SignatureSet) -> Result<()> {
+    let tmp_path = path.with_extension("yml.tmp");
+    let mut w = buf_writer(&tmp_path)?;
+    serde_yaml::to_writer(&mut w, set)?;
+    w.flush()?;
+    drop(w);
+    fs::rename(&tmp_path, path).with_context(|| format!("Failed to write '{}'", path.display()))?;
+    Ok(())
+}
+
+/// Keys become file names, so they can't contain path separators.
+fn check_key(key: &str) -> Result<()> {
+    ensure!(
+        !key.is_empty() && !key.starts_with('.') && !key.contains(['/', '\\', ':']),
+        "Invalid signature set name '{}'",
+        key
+    );
+    Ok(())
+}
+
+fn parse_set(text: &str) -> Result<SignatureSet> { Ok(serde_yaml::from_str(text)?) }
+
+fn read_set(path: &Path) -> Result<SignatureSet> {
+    if !path.is_file() {
+        return Ok(SignatureSet::new());
+    }
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Failed to read '{}'", path.display()))?;
+    parse_set(&text).with_context(|| format!("While parsing '{}'", path.display()))
+}
+
+/// `<set>.yml.lock` next to a local set, held while it's updated.
+struct FileLock(PathBuf);
+
+impl FileLock {
+    fn acquire(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let lock_path = path.with_extension("yml.lock");
+        for _ in 0..100 {
+            match OpenOptions::new().write(true).create_new(true).open(&lock_path) {
+                Ok(_) => return Ok(Self(lock_path)),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    thread::sleep(Duration::from_millis(100))
+                }
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to create '{}'", lock_path.display()))
+                }
+            }
+        }
+        bail!(
+            "Timed out waiting for '{}'; remove it if no other push is running",
+            lock_path.display()
+        )
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) { fs::remove_file(&self.0).ok(); }
+}
+
+/// Fetches a set and its ETag, or `None` if it doesn't exist.
+#[cfg(feature = "cli")]
+fn fetch(url: &str, key: &str) -> Result<Option<(String, Option<String>)>> {
+    match ureq::get(&format!("{url}/{key}.yml")).call() {
+        Ok(response) => {
+            let etag = response.header("ETag").map(str::to_string);
+            Ok(Some((response.into_string()?, etag)))
+        }
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to fetch '{url}/{key}.yml'")),
+    }
+}
+
+/// Uploads a set if the precondition header holds. Returns `false` if it doesn't.
+#[cfg(feature = "cli")]
+fn upload(url: &str, key: &str, text: &str, precondition: Option<(&str, &str)>) -> Result<bool> {
+    let mut request =
+        ureq::put(&format!("{url}/{key}.yml")).set("Content-Type", "application/yaml");
+    if let Some((name, value)) = precondition {
+        request = request.set(name, value);
+    }
+    match request.send_string(text) {
+        Ok(_) => Ok(true),
+        Err(ureq::Error::Status(412, _)) => Ok(false),
+        Err(e) => Err(e).with_context(|| format!("Failed to upload '{url}/{key}.yml'")),
+    }
+}
+
+#[cfg(not(feature = "cli"))]
+fn fetch(url: &str, _key: &str) -> Result<Option<(String, Option<String>)>> {
+    bail!("Remote signature store '{}' requires the `cli` feature", url)
+}
+
+#[cfg(not(feature = "cli"))]
+fn upload(url: &str, _key: &str, _text: &str, _precondition: Option<(&str, &str)>) -> Result<bool> {
+    bail!("Remote signature store '{}' requires the `cli` feature", url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::signatures::FunctionSignature;
+
+    fn set(signatures: &[(&str, &str)]) -> SignatureSet {
+        let mut set = SignatureSet::new();
+        for &(name, hash) in signatures {
+            set.entry(name.to_string()).or_default().push(FunctionSignature {
+                symbol: 0,
+                hash: hash.to_string(),
+                signature: String::new(),
+                symbols: vec![],
+                relocations: vec![],
+            });
+        }
+        set
+    }
+
+    fn hashes(set: &SignatureSet) -> Vec<(&str, &str)> {
+        set.iter()
+            .flat_map(|(name, signatures)| signatures.iter().map(|s| (name.as_str(), &*s.hash)))
+            .collect()
+    }
+
+    #[test]
+    fn local_push_and_pull() {
+        let dir = std::env::temp_dir().join(format!("dtk-sigstore-test-{}", std::process::id()));
+        let store = SignatureStore::new(dir.to_str().unwrap());
+        let missing = store.pull("sdk-2002-12");
+        let first = store.push("sdk-2002-12", set(&[("OSInit", "a"), ("DVDInit", "b")]));
+        // Only signatures with new hashes are added
+        let second = store.push("sdk-2002-12", set(&[("OSInit", "a"), ("OSInit", "c")]));
+        let unchanged = store.push("sdk-2002-12", set(&[("DVDInit", "b")]));
+        let pulled = store.pull("sdk-2002-12");
+        let leftovers = fs::read_dir(&dir).unwrap().count();
+        let invalid = store.pull("../sdk");
+        let pinned = read_pinned_set(&dir.join("sdk-2002-12.yml"));
+        let pinned_missing = read_pinned_set(&dir.join("msl-2.4.2.yml"));
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(missing.unwrap().is_empty());
+        assert_eq!(first.unwrap(), 2);
+        assert_eq!(second.unwrap(), 1);
+        assert_eq!(unchanged.unwrap(), 0);
+        assert_eq!(hashes(&pulled.unwrap()), [("DVDInit", "b"), ("OSInit", "a"), ("OSInit", "c")]);
+        // No lock or temporary file is left behind
+        assert_eq!(leftovers, 1);
+        assert!(invalid.is_err());
+        assert_eq!(hashes(&pinned.unwrap()).len(), 3);
+        assert!(pinned_missing.is_err());
+    }
+
+    #[test]
+    fn local_push_waits_for_lock() {
+        let dir = std::env::temp_dir().join(format!("dtk-sigstore-lock-{}", std::process::id()));
+        let store = SignatureStore::new(dir.to_str().unwrap());
+        let lock = FileLock::acquire(&dir.join("msl.yml")).unwrap();
+        let pusher = {
+            let store = store.clone();
+            thread::spawn(move || store.push("msl", set(&[("memcpy", "a")])))
+        };
+        thread::sleep(Duration::from_millis(250));
+        let before = dir.join("msl.yml").exists();
+        drop(lock);
+        let added = pusher.join().unwrap();
+        let pulled = store.pull("msl");
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(!before);
+        assert_eq!(added.unwrap(), 1);
+        assert_eq!(hashes(&pulled.unwrap()), [("memcpy", "a")]);
+    }
+}
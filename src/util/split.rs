@@ -1,6 +1,6 @@
 use std::{
     cmp::{max, min, Ordering},
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
 };
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
@@ -14,9 +14,9 @@ use crate::{
     obj::{
         ObjArchitecture, ObjInfo, ObjKind, ObjReloc, ObjRelocations, ObjSection, ObjSectionKind,
         ObjSplit, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind, ObjSymbolScope,
-        ObjUnit,
+        ObjSymbolSource, ObjUnit, ObjUnitPadding, SymbolIndex,
     },
-    util::{align_up, comment::MWComment},
+    util::{align_up, comment::MWComment, config::is_auto_symbol, scaffold::source_path_for_unit},
 };
 
 /// Create splits for function pointers in the given section.
@@ -85,6 +85,8 @@ fn split_ctors_dtors(obj: &mut ObjInfo, start: SectionAddress, end: SectionAddre
                     autogenerated: true,
                     skip: false,
                     rename: None,
+                    pad: None,
+                    end_align: None,
                 });
             }
             if function_split.is_none() {
@@ -97,6 +99,8 @@ fn split_ctors_dtors(obj: &mut ObjInfo, start: SectionAddress, end: SectionAddre
                     autogenerated: true,
                     skip: false,
                     rename: None,
+                    pad: None,
+                    end_align: None,
                 });
             }
         }
@@ -116,6 +120,68 @@ fn split_ctors_dtors(obj: &mut ObjInfo, start: SectionAddress, end: SectionAddre
     Ok(())
 }
 
+/// Names the static initializers in `.ctors` after their unit's source file, as MWCC does
+/// (`__sinit_JKRHeap_cpp` for `JKRHeap.cpp`). Generated names follow the unit's source path when
+/// it changes; names from a map, ELF or signature are kept.
+fn name_static_initializers(obj: &mut ObjInfo) -> Result<()> {
+    let Some((_, ctors_section)) = obj.sections.by_name(".ctors")? else {
+        return Ok(());
+    };
+    let mut start = ctors_section.address as u32;
+    let end = start + (ctors_section.size as u32).saturating_sub(4);
+    if obj.kind == ObjKind::Executable {
+        // Skip __init_cpp_exceptions_reference
+        start += 4;
+    }
+    let mut renames = vec![];
+    for address in (start..end).step_by(4) {
+        let function_addr = read_address(obj, ctors_section, address)?;
+        let Some((symbol_idx, symbol)) = obj.symbols.kind_at_section_address(
+            function_addr.section,
+            function_addr.address,
+            ObjSymbolKind::Function,
+        )?
+        else {
+            continue;
+        };
+        let generated = symbol.name.starts_with("__sinit_")
+            && matches!(symbol.source, ObjSymbolSource::Analysis | ObjSymbolSource::Config);
+        if !is_auto_symbol(symbol) && !generated {
+            continue;
+        }
+        let Some((_, split)) =
+            obj.sections[function_addr.section].splits.for_address(function_addr.address)
+        else {
+            continue;
+        };
+        let Some(file_name) = obj
+            .link_order
+            .iter()
+            .find(|unit| unit.name == split.unit)
+            .and_then(source_path_for_unit)
+            .and_then(|path| path.file_name().map(|n| n.to_string_lossy().into_owned()))
+        else {
+            continue;
+        };
+        let name = format!("__sinit_{}", file_name.replace('.', "_"));
+        if symbol.name != name {
+            renames.push((symbol_idx, name));
+        }
+    }
+
+    for (symbol_idx, name) in renames {
+        let mut symbol = obj.symbols[symbol_idx].clone();
+        log::debug!("Renaming static initializer {} to {}", symbol.name, name);
+        symbol.name = name.into();
+        symbol.demangled_name = None;
+        symbol.source = ObjSymbolSource::Analysis;
+        // Units with the same file name each have their own
+        symbol.flags.set_scope(ObjSymbolScope::Local);
+        obj.symbols.replace(symbol_idx, symbol)?;
+    }
+    Ok(())
+}
+
 /// Create splits for extabindex + extab entries.
 fn split_extabindex(obj: &mut ObjInfo, start: SectionAddress) -> Result<()> {
     let section = &obj.sections[start.section];
@@ -264,6 +330,8 @@ fn split_extabindex(obj: &mut ObjInfo, start: SectionAddress) -> Result<()> {
                     autogenerated: true,
                     skip: false,
                     rename: None,
+                    pad: None,
+                    end_align: None,
                 });
             }
             if extab_split.is_none() {
@@ -277,6 +345,8 @@ fn split_extabindex(obj: &mut ObjInfo, start: SectionAddress) -> Result<()> {
                     autogenerated: true,
                     skip: false,
                     rename: None,
+                    pad: None,
+                    end_align: None,
                 });
             }
             if function_split.is_none() {
@@ -290,6 +360,8 @@ fn split_extabindex(obj: &mut ObjInfo, start: SectionAddress) -> Result<()> {
                     autogenerated: true,
                     skip: false,
                     rename: None,
+                    pad: None,
+                    end_align: None,
                 });
             }
         }
@@ -304,9 +376,81 @@ fn split_extabindex(obj: &mut ObjInfo, start: SectionAddress) -> Result<()> {
     Ok(())
 }
 
+/// Name of the unit covering unclaimed data starting at `address`.
+/// Derived from the address only, so it stays stable while other splits change.
+pub fn gap_unit_name(section_index: usize, address: u32, section: &ObjSection) -> String {
+    format!("auto_{:02}_{:08X}_{}", section_index, address, section.name.trim_start_matches('.'))
+}
+
+/// Whether a unit was generated by `create_gap_splits` to cover data not claimed by any split.
+pub fn is_gap_unit(name: &str) -> bool {
+    name.strip_prefix("auto_").is_some_and(is_generated_suffix)
+}
+
+/// Name of the placeholder symbol covering a likely dead-strip remnant at `address`.
+fn strip_remnant_name(section_index: usize, address: u32, section: &ObjSection) -> String {
+    format!(
+        "stripped_{:02}_{:08X}_{}",
+        section_index,
+        address,
+        section.name.trim_start_matches('.')
+    )
+}
+
+/// Whether a symbol is a placeholder generated for a likely dead-strip remnant.
+pub fn is_strip_remnant_symbol(name: &str) -> bool {
+    name.strip_prefix("stripped_").is_some_and(is_generated_suffix)
+}
+
+/// Matches the `{section index}_{address}_{section name}` suffix of generated names.
+fn is_generated_suffix(rest: &str) -> bool {
+    let bytes = rest.as_bytes();
+    bytes.len() > 12
+        && bytes[..2].iter().all(u8::is_ascii_digit)
+        && bytes[2] == b'_'
+        && bytes[3..11].iter().all(|b| b.is_ascii_digit() || (b'A'..=b'F').contains(b))
+        && bytes[11] == b'_'
+}
+
+/// Addresses targeted by relocations, including any addend.
+fn referenced_addresses(obj: &ObjInfo) -> BTreeSet<SectionAddress> {
+    let mut out = BTreeSet::new();
+    for (_, section) in obj.sections.iter() {
+        for (_, reloc) in section.relocations.iter() {
+            let target = &obj.symbols[reloc.target_symbol];
+            if let Some(section_index) = target.section {
+                let address = (target.address as i64 + reloc.addend) as u32;
+                out.insert(SectionAddress::new(section_index, address));
+            }
+        }
+    }
+    out
+}
+
+/// Whether `start..end` looks like what's left of dead-stripped code or data, or of a linker
+/// gap: not zero or `nop` fill, and neither covered by a symbol nor referenced by a relocation.
+fn is_strip_remnant(
+    obj: &ObjInfo,
+    section_index: usize,
+    referenced: &BTreeSet<SectionAddress>,
+    start: u32,
+    end: u32,
+) -> Result<bool> {
+    if start >= end || obj.symbols.for_section_range(section_index, start..end).next().is_some() {
+        return Ok(false);
+    }
+    let range = SectionAddress::new(section_index, start)..SectionAddress::new(section_index, end);
+    if referenced.range(range).next().is_some() {
+        return Ok(false);
+    }
+    let data = obj.sections[section_index].data_range(start, end)?;
+    Ok(!data.iter().all(|&b| b == 0) && !is_nop_fill(data))
+}
+
 /// Create splits for gaps between existing splits.
 fn create_gap_splits(obj: &mut ObjInfo) -> Result<()> {
     let mut new_splits = BTreeMap::<SectionAddress, ObjSplit>::new();
+    let mut gap_size = 0u32;
 
     for (section_index, section) in obj.sections.iter() {
         let mut current_address = SectionAddress::new(section_index, section.address as u32);
@@ -367,7 +511,7 @@ fn create_gap_splits(obj: &mut ObjInfo) -> Result<()> {
                     .collect_vec();
                 let mut existing_symbols = HashSet::new();
                 for &(_, symbol) in &symbols {
-                    if !existing_symbols.insert(symbol.name.clone()) {
+                    if !existing_symbols.insert(symbol.name) {
                         log::debug!(
                             "Found duplicate symbol {} at {:#010X}",
                             symbol.name,
@@ -400,12 +544,8 @@ fn create_gap_splits(obj: &mut ObjInfo) -> Result<()> {
                     current_address,
                     new_split_end
                 );
-                let unit = format!(
-                    "auto_{:02}_{:08X}_{}",
-                    current_address.section,
-                    current_address.address,
-                    section.name.trim_start_matches('.')
-                );
+                let unit = gap_unit_name(current_address.section, current_address.address, section);
+                gap_size += new_split_end.address - current_address.address;
                 new_splits.insert(current_address, ObjSplit {
                     unit: unit.clone(),
                     end: new_split_end.address,
@@ -414,6 +554,8 @@ fn create_gap_splits(obj: &mut ObjInfo) -> Result<()> {
                     autogenerated: true,
                     skip: false,
                     rename: None,
+                    pad: None,
+                    end_align: None,
                 });
                 current_address = new_split_end;
                 continue;
@@ -432,6 +574,15 @@ fn create_gap_splits(obj: &mut ObjInfo) -> Result<()> {
         }
     }
 
+    if !new_splits.is_empty() {
+        log::info!(
+            "{:#X} bytes not claimed by any split, covered by {} gap unit{}",
+            gap_size,
+            new_splits.len(),
+            if new_splits.len() == 1 { "" } else { "s" }
+        );
+    }
+
     // Add new splits
     for (addr, split) in new_splits {
         obj.add_split(addr.section, addr.address, split)?;
@@ -440,6 +591,113 @@ fn create_gap_splits(obj: &mut ObjInfo) -> Result<()> {
     Ok(())
 }
 
+/// Remnants smaller than this are left in place, since alignment padding could absorb them.
+const MIN_STRIP_REMNANT_SIZE: u32 = 0x20;
+
+/// Moves likely dead-strip remnants at the start or end of a split out of it, so that
+/// `create_gap_splits` covers them with gap units instead of mixing them into the unit's own
+/// code and data. Each gets a `stripped_` placeholder symbol.
+fn isolate_strip_remnants(obj: &mut ObjInfo) -> Result<()> {
+    let referenced = referenced_addresses(obj);
+    // Section index, split address, unit, new start and new end
+    let mut trims = vec![];
+    let mut remnants = vec![];
+    for (section_index, section) in obj.sections.iter() {
+        if section.kind == ObjSectionKind::Bss
+            || matches!(section.name.as_str(), ".ctors" | ".dtors" | "extab" | "extabindex")
+        {
+            continue;
+        }
+        for (addr, split) in section.splits.iter() {
+            if split.autogenerated
+                || split.common
+                || split.end == 0
+                || split.pad.is_some()
+                || split.end_align.is_some()
+            {
+                continue;
+            }
+            let symbols = obj
+                .symbols
+                .for_section_range(section_index, addr..split.end)
+                .filter(|(_, s)| s.size > 0)
+                .collect_vec();
+            let (Some(first_start), Some(last_end)) = (
+                symbols.iter().map(|(_, s)| s.address as u32).min(),
+                symbols.iter().map(|(_, s)| (s.address + s.size) as u32).max(),
+            ) else {
+                continue;
+            };
+            let (mut start, mut end) = (addr, split.end);
+            if first_start - addr >= MIN_STRIP_REMNANT_SIZE
+                && is_strip_remnant(obj, section_index, &referenced, addr, first_start)?
+            {
+                remnants.push((section_index, addr, first_start));
+                start = first_start;
+            }
+            let tail = align_up(last_end, 4);
+            if split.end >= tail + MIN_STRIP_REMNANT_SIZE
+                && is_strip_remnant(obj, section_index, &referenced, tail, split.end)?
+            {
+                remnants.push((section_index, tail, split.end));
+                end = tail;
+            }
+            if (start, end) != (addr, split.end) {
+                trims.push((section_index, addr, split.unit.clone(), start, end));
+            }
+        }
+    }
+
+    for (section_index, addr, unit, start, end) in trims {
+        let section = &mut obj.sections[section_index];
+        let Some(splits) = section.splits.remove(addr) else { continue };
+        for mut split in splits {
+            if split.unit == unit {
+                split.end = end;
+                section.splits.push(start, split);
+            } else {
+                section.splits.push(addr, split);
+            }
+        }
+    }
+    let mut size = 0;
+    for &(section_index, start, end) in &remnants {
+        let section = &obj.sections[section_index];
+        log::debug!(
+            "Moving likely dead-strip remnant {:#010X}..{:#010X} out of its split",
+            start,
+            end
+        );
+        size += end - start;
+        obj.symbols.add_direct(ObjSymbol {
+            name: strip_remnant_name(section_index, start, section).into(),
+            address: start as u64,
+            section: Some(section_index),
+            size: (end - start) as u64,
+            size_known: true,
+            flags: ObjSymbolFlagSet(
+                ObjSymbolFlags::Local | ObjSymbolFlags::ForceActive | ObjSymbolFlags::NoWrite,
+            ),
+            kind: match section.kind {
+                ObjSectionKind::Code => ObjSymbolKind::Function,
+                ObjSectionKind::Data | ObjSectionKind::ReadOnlyData | ObjSectionKind::Bss => {
+                    ObjSymbolKind::Object
+                }
+            },
+            ..Default::default()
+        })?;
+    }
+    if !remnants.is_empty() {
+        log::info!(
+            "{:#X} bytes of likely dead-strip remnants moved out of splits into {} gap unit{}",
+            size,
+            remnants.len(),
+            if remnants.len() == 1 { "" } else { "s" }
+        );
+    }
+    Ok(())
+}
+
 /// Ensures that all .bss splits following a common split are also marked as common.
 fn update_common_splits(obj: &mut ObjInfo, common_start: Option<u32>) -> Result<()> {
     let Some((bss_section_index, common_bss_start)) = (match common_start {
@@ -526,6 +784,51 @@ fn validate_splits(obj: &ObjInfo) -> Result<()> {
     Ok(())
 }
 
+const NOP: [u8; 4] = 0x60000000u32.to_be_bytes();
+
+/// Whether `data` is entirely `nop` instructions.
+fn is_nop_fill(data: &[u8]) -> bool {
+    !data.is_empty() && data.len() % 4 == 0 && data.chunks_exact(4).all(|c| c == NOP)
+}
+
+/// Units whose code is padded with `nop`s, between functions or up to the next split.
+fn nop_padded_units(obj: &ObjInfo) -> Result<HashSet<String>> {
+    let mut units = HashSet::new();
+    for (section_index, section) in obj.sections.iter() {
+        if section.kind != ObjSectionKind::Code {
+            continue;
+        }
+        let section_end = (section.address + section.size) as u32;
+        let mut split_iter = section.splits.iter().peekable();
+        while let Some((addr, split)) = split_iter.next() {
+            if units.contains(&split.unit) {
+                continue;
+            }
+            let next_addr = split_iter.peek().map(|&(a, _)| a).unwrap_or(section_end);
+            let mut gaps = vec![];
+            let mut current = addr;
+            for (_, symbol) in obj
+                .symbols
+                .for_section_range(section_index, addr..split.end)
+                .filter(|(_, s)| s.kind == ObjSymbolKind::Function && s.size > 0)
+            {
+                if symbol.address as u32 > current {
+                    gaps.push((current, symbol.address as u32));
+                }
+                current = current.max((symbol.address + symbol.size) as u32);
+            }
+            gaps.push((current, next_addr));
+            for (start, end) in gaps {
+                if start < end && is_nop_fill(section.data_range(start, end)?) {
+                    units.insert(split.unit.clone());
+                    break;
+                }
+            }
+        }
+    }
+    Ok(units)
+}
+
 /// Add padding symbols to fill in gaps between splits and symbols.
 fn add_padding_symbols(obj: &mut ObjInfo) -> Result<()> {
     for (section_index, section, addr, _split) in obj.sections.all_splits() {
@@ -557,7 +860,7 @@ fn add_padding_symbols(obj: &mut ObjInfo) -> Result<()> {
             );
             log::debug!("Adding padding symbol {} at {:#010X}", symbol_name, addr);
             obj.symbols.add_direct(ObjSymbol {
-                name: symbol_name,
+                name: symbol_name.into(),
                 address: addr as u64,
                 section: Some(section_index),
                 size: next_symbol_address - addr as u64,
@@ -577,6 +880,7 @@ fn add_padding_symbols(obj: &mut ObjInfo) -> Result<()> {
     }
 
     // Add padding symbols for gaps between symbols
+    let referenced = referenced_addresses(obj);
     let common_bss = obj.sections.common_bss_start();
     for (section_index, section) in obj.sections.iter() {
         if section.name == ".ctors" || section.name == ".dtors" {
@@ -600,15 +904,22 @@ fn add_padding_symbols(obj: &mut ObjInfo) -> Result<()> {
                 align_up((symbol.address + symbol.size) as u32, next_symbol.align.unwrap_or(1));
             match aligned_end.cmp(&(next_symbol.address as u32)) {
                 Ordering::Less => {
-                    let symbol_name = format!(
-                        "gap_{:02}_{:08X}_{}",
-                        section_index,
-                        aligned_end,
-                        section.name.trim_start_matches('.')
-                    );
+                    let end = next_symbol.address as u32;
+                    let symbol_name = if section.kind != ObjSectionKind::Bss
+                        && is_strip_remnant(obj, section_index, &referenced, aligned_end, end)?
+                    {
+                        strip_remnant_name(section_index, aligned_end, section)
+                    } else {
+                        format!(
+                            "gap_{:02}_{:08X}_{}",
+                            section_index,
+                            aligned_end,
+                            section.name.trim_start_matches('.')
+                        )
+                    };
                     log::debug!("Adding gap symbol {} at {:#010X}", symbol_name, aligned_end);
                     to_add.push(ObjSymbol {
-                        name: symbol_name,
+                        name: symbol_name.into(),
                         address: aligned_end as u64,
                         section: Some(section_index),
                         size: next_symbol.address - aligned_end as u64,
@@ -766,6 +1077,9 @@ pub fn update_splits(obj: &mut ObjInfo, common_start: Option<u32>, fill_gaps: bo
     // Remove linker generated symbols from splits
     trim_linker_generated_symbols(obj)?;
 
+    // Move likely dead-strip remnants out of their splits, into gap units
+    isolate_strip_remnants(obj)?;
+
     // Create gap splits
     create_gap_splits(obj)?;
 
@@ -779,6 +1093,9 @@ pub fn update_splits(obj: &mut ObjInfo, common_start: Option<u32>, fill_gaps: bo
     // TODO figure out mwld pooled data alignment
     // trim_split_alignment(obj)?;
 
+    // Detect padding before gaps are filled with symbols
+    let nop_units = nop_padded_units(obj)?;
+
     if fill_gaps {
         // Add symbols to beginning of any split that doesn't start with a symbol
         add_padding_symbols(obj)?;
@@ -787,6 +1104,19 @@ pub fn update_splits(obj: &mut ObjInfo, common_start: Option<u32>, fill_gaps: bo
     // Resolve link order
     obj.link_order = resolve_link_order(obj)?;
 
+    // Apply unit patterns to any newly created units
+    obj.apply_unit_patterns();
+
+    // Name static initializers after their unit's source file
+    name_static_initializers(obj)?;
+
+    // Record detected padding, unless set in the config
+    for unit in &mut obj.link_order {
+        if unit.padding.is_none() && nop_units.contains(&unit.name) {
+            unit.padding = Some(ObjUnitPadding::Nop);
+        }
+    }
+
     Ok(())
 }
 
@@ -812,6 +1142,9 @@ fn resolve_link_order(obj: &ObjInfo) -> Result<Vec<ObjUnit>> {
         *index = graph.add_node(unit.clone());
     }
 
+    // Common BSS order is only a preference: split_obj can reproduce it with extra
+    // common declarations when the link order can't.
+    let mut common_edges = vec![];
     for (_section_index, section) in obj.sections.iter() {
         let mut iter = section.splits.iter().peekable();
         if section.name == ".ctors" || section.name == ".dtors" {
@@ -826,6 +1159,13 @@ fn resolve_link_order(obj: &ObjInfo) -> Result<Vec<ObjUnit>> {
             }
 
             if a.unit != b.unit {
+                let a_index = *unit_to_index_map.get(&a.unit).unwrap();
+                let b_index = *unit_to_index_map.get(&b.unit).unwrap();
+                let edge = SplitEdge { from: a_addr, to: b_addr };
+                if a.common && b.common {
+                    common_edges.push((a_index, b_index, edge));
+                    continue;
+                }
                 log::debug!(
                     "Adding dependency {} ({:#010X}) -> {} ({:#010X})",
                     a.unit,
@@ -833,12 +1173,23 @@ fn resolve_link_order(obj: &ObjInfo) -> Result<Vec<ObjUnit>> {
                     b.unit,
                     b_addr
                 );
-                let a_index = *unit_to_index_map.get(&a.unit).unwrap();
-                let b_index = *unit_to_index_map.get(&b.unit).unwrap();
-                graph.add_edge(a_index, b_index, SplitEdge { from: a_addr, to: b_addr });
+                graph.add_edge(a_index, b_index, edge);
             }
         }
     }
+    for (a_index, b_index, edge) in common_edges {
+        if petgraph::algo::has_path_connecting(&graph, b_index, a_index, None) {
+            log::debug!(
+                "Ignoring common dependency {} ({:#010X}) -> {} ({:#010X})",
+                graph[a_index],
+                edge.from,
+                graph[b_index],
+                edge.to
+            );
+            continue;
+        }
+        graph.add_edge(a_index, b_index, edge);
+    }
 
     // use petgraph::{
     //     dot::{Config, Dot},
@@ -868,7 +1219,7 @@ fn resolve_link_order(obj: &ObjInfo) -> Result<Vec<ObjUnit>> {
                     ObjUnit {
                         name: name.clone(),
                         autogenerated: obj.is_unit_autogenerated(name),
-                        comment_version: None,
+                        ..Default::default()
                     }
                 }
             })
@@ -880,9 +1231,74 @@ fn resolve_link_order(obj: &ObjInfo) -> Result<Vec<ObjUnit>> {
     }
 }
 
+/// The linker allocates common symbols in the order it first encounters them while walking
+/// the link order, rather than grouping them by the object that defines them. Where the link
+/// order alone can't reproduce the original common BSS layout, this returns the object each
+/// out-of-order symbol must also be declared in, so that it's encountered at the right point.
+fn common_bss_placement(
+    obj: &ObjInfo,
+    name_to_obj: &HashMap<String, usize>,
+) -> Result<HashMap<SymbolIndex, usize>> {
+    let mut placement = HashMap::new();
+    let Some((bss_section_index, common_bss_start)) = obj.sections.common_bss_start() else {
+        return Ok(placement);
+    };
+    let bss_section = &obj.sections[bss_section_index];
+
+    let mut symbols = vec![];
+    for (addr, split) in bss_section.splits.for_range(common_bss_start..) {
+        if !split.common || split.skip {
+            continue;
+        }
+        let obj_idx = *name_to_obj
+            .get(&split.unit)
+            .ok_or_else(|| anyhow!("Unit '{}' not in link order", split.unit))?;
+        for (symbol_idx, _) in
+            obj.symbols.for_section_range(bss_section_index, addr..split.end).filter(|&(_, s)| {
+                s.section == Some(bss_section_index) && !is_linker_generated_label(&s.name)
+            })
+        {
+            symbols.push((symbol_idx, obj_idx));
+        }
+    }
+
+    // Walking backwards, each symbol must be encountered no later than the one after it
+    let mut limit = usize::MAX;
+    for (symbol_idx, obj_idx) in symbols.into_iter().rev() {
+        if obj_idx <= limit {
+            limit = obj_idx;
+        } else {
+            placement.insert(symbol_idx, limit);
+        }
+    }
+    if !placement.is_empty() {
+        log::info!(
+            "Reordering {} common BSS symbol(s) with additional common declarations",
+            placement.len()
+        );
+    }
+    Ok(placement)
+}
+
 /// Split an object into multiple relocatable objects.
 #[instrument(level = "debug", skip(obj))]
 pub fn split_obj(obj: &ObjInfo) -> Result<Vec<ObjInfo>> {
+    for ((addr, name), unit) in &obj.unit_locals {
+        let split = obj.sections[addr.section].splits.for_address(addr.address);
+        match split {
+            Some((_, split)) if split.unit == *unit => {}
+            Some((_, split)) => bail!(
+                "Symbol {} at {:#010X} is local to {}, but is split into {}",
+                name,
+                addr,
+                unit,
+                split.unit
+            ),
+            None => {
+                bail!("Symbol {} at {:#010X} is local to {}, but isn't split", name, addr, unit)
+            }
+        }
+    }
     let mut objects: Vec<ObjInfo> = vec![];
     let mut object_symbols: Vec<Vec<Option<usize>>> = vec![];
     let mut name_to_obj: HashMap<String, usize> = HashMap::new();
@@ -905,6 +1321,7 @@ pub fn split_obj(obj: &ObjInfo) -> Result<Vec<ObjInfo>> {
         }
         objects.push(split_obj);
     }
+    let common_placement = common_bss_placement(obj, &name_to_obj)?;
 
     for (section_index, section) in obj.sections.iter() {
         let mut current_address = SectionAddress::new(section_index, section.address as u32);
@@ -933,8 +1350,29 @@ pub fn split_obj(obj: &ObjInfo) -> Result<Vec<ObjInfo>> {
 
             let split_end = SectionAddress::new(section_index, split.end);
             let next_addr = split_iter.peek().map(|&(addr, _)| addr).unwrap_or(section_end);
-            if next_addr > split_end
-                && section.data_range(split_end.address, next_addr.address)?.iter().any(|&b| b != 0)
+
+            // Padding emitted by the original compiler, which the linker wouldn't recreate
+            let mut padded_end =
+                align_up(split.end, split.end_align.unwrap_or(1)) + split.pad.unwrap_or(0);
+            ensure!(
+                padded_end <= next_addr.address,
+                "Padding of {} {} to {:#010X} extends past next split {}",
+                split.unit,
+                section.name,
+                padded_end,
+                next_addr
+            );
+            // The linker pads with zeroes, so `nop` padding has to be part of the object
+            let unit_padding =
+                name_to_obj.get(&split.unit).and_then(|&i| obj.link_order[i].padding);
+            if section.kind == ObjSectionKind::Code
+                && unit_padding == Some(ObjUnitPadding::Nop)
+                && is_nop_fill(section.data_range(padded_end, next_addr.address)?)
+            {
+                padded_end = next_addr.address;
+            }
+            if next_addr.address > padded_end
+                && section.data_range(padded_end, next_addr.address)?.iter().any(|&b| b != 0)
             {
                 bail!(
                     "Unsplit data in {} from {} {} to next split {}",
@@ -959,6 +1397,7 @@ pub fn split_obj(obj: &ObjInfo) -> Result<Vec<ObjInfo>> {
                 .get(&split.unit)
                 .and_then(|&idx| object_symbols.get_mut(idx))
                 .ok_or_else(|| anyhow!("Unit '{}' not in link order", split.unit))?;
+            let mut common_duplicates = vec![];
 
             // Calculate & verify section alignment
             let mut align = split.align.unwrap_or_else(|| {
@@ -1037,9 +1476,9 @@ pub fn split_obj(obj: &ObjInfo) -> Result<Vec<ObjInfo>> {
                     continue;
                 }
 
-                symbol_idxs[symbol_idx] = Some(split_obj.symbols.add_direct(ObjSymbol {
-                    name: symbol.name.clone(),
-                    demangled_name: symbol.demangled_name.clone(),
+                let out_symbol = ObjSymbol {
+                    name: symbol.name,
+                    demangled_name: symbol.demangled_name,
                     address: if split.common {
                         symbol.align.unwrap_or(4) as u64
                     } else {
@@ -1058,7 +1497,14 @@ pub fn split_obj(obj: &ObjInfo) -> Result<Vec<ObjInfo>> {
                     data_kind: symbol.data_kind,
                     name_hash: symbol.name_hash,
                     demangled_name_hash: symbol.demangled_name_hash,
-                })?);
+                    source: symbol.source,
+                };
+                if split.common {
+                    if let Some(&obj_idx) = common_placement.get(&symbol_idx) {
+                        common_duplicates.push((obj_idx, symbol_idx, out_symbol.clone()));
+                    }
+                }
+                symbol_idxs[symbol_idx] = Some(split_obj.symbols.add_direct(out_symbol)?);
             }
 
             // For mwldeppc 2.7 and above, a .comment section is required to link without error
@@ -1071,14 +1517,14 @@ pub fn split_obj(obj: &ObjInfo) -> Result<Vec<ObjInfo>> {
                 let data = match section.kind {
                     ObjSectionKind::Bss => vec![],
                     _ => section.data[(current_address.address as u64 - section.address) as usize
-                        ..(split_end.address as u64 - section.address) as usize]
+                        ..(padded_end as u64 - section.address) as usize]
                         .to_vec(),
                 };
                 split_obj.sections.push(ObjSection {
                     name: split.rename.as_ref().unwrap_or(&section.name).clone(),
                     kind: section.kind,
                     address: 0,
-                    size: split_end.address as u64 - current_address.address as u64,
+                    size: padded_end as u64 - current_address.address as u64,
                     data,
                     align,
                     elf_index: out_section_idx + 1,
@@ -1091,6 +1537,19 @@ pub fn split_obj(obj: &ObjInfo) -> Result<Vec<ObjInfo>> {
                 });
             }
 
+            // Declare common symbols early enough for the linker to allocate them in order
+            for (obj_idx, symbol_idx, symbol) in common_duplicates {
+                let out_obj = &mut objects[obj_idx];
+                if object_symbols[obj_idx][symbol_idx].is_some() {
+                    continue;
+                }
+                log::debug!("Declaring common symbol {} in {}", symbol.name, out_obj.name);
+                object_symbols[obj_idx][symbol_idx] = Some(out_obj.symbols.add_direct(symbol)?);
+                if out_obj.mw_comment.is_none() {
+                    out_obj.mw_comment = Some(MWComment::new(8)?);
+                }
+            }
+
             current_address = next_addr;
         }
     }
@@ -1113,6 +1572,17 @@ pub fn split_obj(obj: &ObjInfo) -> Result<Vec<ObjInfo>> {
                         // If the symbol is local, we'll upgrade the scope to global
                         // and rename it to avoid conflicts
                         if target_sym.flags.is_local() {
+                            if let Some(unit) = target_sym.section.and_then(|section| {
+                                let addr = SectionAddress::new(section, target_sym.address as u32);
+                                obj.unit_locals.get(&(addr, target_sym.name))
+                            }) {
+                                log::warn!(
+                                    "Symbol {} is local to {}, but is referenced from {}",
+                                    target_sym.name,
+                                    unit,
+                                    out_obj.name
+                                );
+                            }
                             let address_str = if obj.module_id == 0 {
                                 format!("{:08X}", target_sym.address)
                             } else if let Some(section_index) = target_sym.section {
@@ -1127,17 +1597,17 @@ pub fn split_obj(obj: &ObjInfo) -> Result<Vec<ObjInfo>> {
                                 bail!("Local symbol {} has no section", target_sym.name);
                             };
                             let new_name = if target_sym.name.ends_with(&address_str) {
-                                target_sym.name.clone()
+                                target_sym.name
                             } else {
-                                format!("{}_{}", target_sym.name, address_str)
+                                format!("{}_{}", target_sym.name, address_str).into()
                             };
                             globalize_symbols.push((reloc.target_symbol, new_name));
                         }
 
                         symbol_idxs[reloc.target_symbol] = Some(out_sym_idx);
                         out_obj.symbols.add_direct(ObjSymbol {
-                            name: target_sym.name.clone(),
-                            demangled_name: target_sym.demangled_name.clone(),
+                            name: target_sym.name,
+                            demangled_name: target_sym.demangled_name,
                             ..Default::default()
                         })?;
                         reloc.target_symbol = out_sym_idx;
@@ -1180,7 +1650,7 @@ pub fn split_obj(obj: &ObjInfo) -> Result<Vec<ObjInfo>> {
         for (globalize_idx, new_name) in &globalize_symbols {
             if let Some(symbol_idx) = symbol_map[*globalize_idx] {
                 let mut symbol = obj.symbols[symbol_idx].clone();
-                symbol.name = new_name.clone();
+                symbol.name = *new_name;
                 if symbol.flags.is_local() {
                     log::debug!("Globalizing {} in {}", symbol.name, obj.name);
                     symbol.flags.set_scope(ObjSymbolScope::Global);
@@ -1197,8 +1667,8 @@ pub fn split_obj(obj: &ObjInfo) -> Result<Vec<ObjInfo>> {
             if is_linker_generated_label(&symbol.name) && symbol.section.is_some() {
                 log::debug!("Externing {:?} in {}", symbol, obj.name);
                 replace_symbols.push((symbol_idx, ObjSymbol {
-                    name: symbol.name.clone(),
-                    demangled_name: symbol.demangled_name.clone(),
+                    name: symbol.name,
+                    demangled_name: symbol.demangled_name,
                     ..Default::default()
                 }));
             }
@@ -1384,3 +1854,22 @@ fn unit_exists(
             .any(|(_, split)| split.unit.eq_ignore_ascii_case(unit_name))
         || new_splits.values().any(|split| split.unit.eq_ignore_ascii_case(unit_name))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gap_unit_names() {
+        let section = ObjSection::raw(".data", ObjSectionKind::Data, 0x80001000, vec![]);
+        let name = gap_unit_name(3, 0x80001000, &section);
+        assert_eq!(name, "auto_03_80001000_data");
+        assert!(is_gap_unit(&name));
+        assert!(is_gap_unit("auto_12_8000ABCD_sdata2"));
+        assert!(!is_gap_unit("auto_main.cpp"));
+        assert!(!is_gap_unit("auto_03_8000100_data"));
+        assert!(!is_gap_unit("auto_03_8000abcd_data"));
+        assert!(!is_gap_unit("auto_03_80001000_"));
+        assert!(!is_gap_unit("src/auto_03_80001000_data.c"));
+    }
+}
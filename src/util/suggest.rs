@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{
+    analysis::signatures::is_signature_symbol,
+    obj::{ObjInfo, ObjRelocKind, ObjSection, ObjSectionKind, ObjSplit, ObjSymbolKind},
+    util::{
+        config::is_auto_symbol,
+        split::{gap_unit_name, is_gap_unit},
+    },
+};
+
+/// What a suggestion's unit name is based on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionSource {
+    /// References between the range and the unit's splits in other sections.
+    References,
+    /// A source file name among the strings the range references, such as an assert's
+    /// `__FILE__`.
+    FileName,
+    /// The class of a C++ function in the range.
+    ClassName,
+    /// Nothing to go by. The name is a placeholder to replace.
+    Placeholder,
+}
+
+impl SuggestionSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::References => "references",
+            Self::FileName => "file name",
+            Self::ClassName => "class name",
+            Self::Placeholder => "placeholder",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SuggestionSymbol {
+    pub address: u32,
+    pub name: String,
+    /// Identified by one of the built-in signatures.
+    pub signature: bool,
+}
+
+/// A proposed split for a run of symbols that aren't covered by a split yet, with the context
+/// it was based on.
+#[derive(Debug, Clone, Serialize)]
+pub struct SplitSuggestion {
+    #[serde(skip)]
+    pub section_index: usize,
+    pub section: String,
+    pub start: u32,
+    pub end: u32,
+    pub unit: String,
+    pub source: SuggestionSource,
+    /// The unit already has a split in the section that isn't adjacent to this one, and
+    /// adding it would merge the two.
+    pub conflict: bool,
+    /// Units of the splits before and after the range.
+    pub prev_unit: Option<String>,
+    pub next_unit: Option<String>,
+    /// Units referencing or referenced by the range, by reference count.
+    pub references: Vec<(String, u32)>,
+    /// Named symbols in the range.
+    pub symbols: Vec<SuggestionSymbol>,
+    /// Strings referenced by the range.
+    pub strings: Vec<String>,
+}
+
+#[derive(Default)]
+struct Item {
+    end: u32,
+    /// Referencing or referenced unit -> reference count
+    units: BTreeMap<String, u32>,
+    strings: Vec<String>,
+}
+
+impl Item {
+    fn hint(&self) -> Option<(String, SuggestionSource)> {
+        if let Some((unit, _)) = self.units.iter().max_by_key(|&(_, &count)| count) {
+            return Some((unit.clone(), SuggestionSource::References));
+        }
+        self.strings
+            .iter()
+            .find_map(|s| source_file_name(s))
+            .map(|name| (name.to_string(), SuggestionSource::FileName))
+    }
+}
+
+fn is_user_split(split: &ObjSplit) -> bool { !split.autogenerated && !is_gap_unit(&split.unit) }
+
+/// The unit of the split covering an address, ignoring generated splits.
+fn assigned_unit(section: &ObjSection, address: u32) -> Option<&str> {
+    section
+        .splits
+        .for_address(address)
+        .filter(|(_, split)| is_user_split(split))
+        .map(|(_, split)| split.unit.as_str())
+}
+
+/// Suggests splits for the symbols of code and data sections that aren't covered by a split.
+///
+/// Calls cross units freely, but other references rarely do: a function's strings, constants
+/// and jump tables, or a vtable's functions, usually belong to its own unit. Each symbol is
+/// attributed to the unit it has the most such references with, across sections, and to a
+/// source file name it references otherwise. Symbols without either join the run of symbols
+/// around them, and a split ends where the hint changes. `obj` needs relocations (see
+/// `Tracker`). BSS is left to `propose_bss_splits`.
+pub fn suggest_splits(obj: &ObjInfo) -> Vec<SplitSuggestion> {
+    // Unsplit symbols of each section
+    let mut sections = BTreeMap::<usize, BTreeMap<u32, Item>>::new();
+    for (section_index, section) in obj.sections.iter() {
+        if section.kind == ObjSectionKind::Bss {
+            continue;
+        }
+        let items = sections.entry(section_index).or_default();
+        let mut last = (0, 0);
+        for (_, symbol) in obj.symbols.for_section(section_index) {
+            let address = symbol.address as u32;
+            // Labels within functions and objects can't start a split
+            if symbol.kind == ObjSymbolKind::Section
+                || (section.kind == ObjSectionKind::Code && symbol.kind != ObjSymbolKind::Function)
+                || (address != last.0 && address < last.1)
+                || assigned_unit(section, address).is_some()
+            {
+                continue;
+            }
+            let item = items.entry(address).or_default();
+            item.end = item.end.max(address + symbol.size as u32);
+            last = (address, item.end);
+        }
+    }
+
+    // Collect hints from references across sections
+    for (source_index, section) in obj.sections.iter() {
+        for (address, reloc) in section.relocations.iter() {
+            if matches!(reloc.kind, ObjRelocKind::PpcRel24 | ObjRelocKind::PpcRel14) {
+                continue;
+            }
+            let target = &obj.symbols[reloc.target_symbol];
+            let Some(target_index) = target.section.filter(|&i| i != source_index) else {
+                continue;
+            };
+            let target_section = &obj.sections[target_index];
+            let target_address = (target.address as i64 + reloc.addend) as u32;
+            let source_unit = assigned_unit(section, address);
+            let target_unit = assigned_unit(target_section, target_address);
+            match (source_unit, target_unit) {
+                (None, Some(unit)) => {
+                    if let Some(item) = item_at(&mut sections, source_index, address) {
+                        *item.units.entry(unit.to_string()).or_default() += 1;
+                    }
+                }
+                (Some(unit), None) => {
+                    if let Some(item) = item_at(&mut sections, target_index, target_address) {
+                        *item.units.entry(unit.to_string()).or_default() += 1;
+                    }
+                }
+                _ => {}
+            }
+            if source_unit.is_none() {
+                if let Some(string) = read_string(target_section, target_address) {
+                    if let Some(item) = item_at(&mut sections, source_index, address) {
+                        if !item.strings.contains(&string) {
+                            item.strings.push(string);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut suggestions = vec![];
+    for (section_index, items) in sections {
+        let section = &obj.sections[section_index];
+        let section_end = (section.address + section.size) as u32;
+        let addresses = items.keys().copied().collect::<Vec<_>>();
+        let hints = items.values().map(Item::hint).collect::<Vec<_>>();
+        let mut i = 0;
+        while i < addresses.len() {
+            let start = addresses[i];
+            let limit = section
+                .splits
+                .for_range(start..)
+                .find(|(_, split)| is_user_split(split))
+                .map_or(section_end, |(address, _)| address);
+            let count = addresses[i..].iter().take_while(|&&a| a < limit).count();
+            let hint = hints[i..i + count].iter().flatten().next().cloned();
+            let mut j = i + 1;
+            while j < i + count
+                && match &hints[j] {
+                    Some((unit, _)) => hint.as_ref().is_some_and(|(h, _)| h == unit),
+                    None => true,
+                }
+            {
+                j += 1;
+            }
+            let end = if j < i + count { addresses[j] } else { limit };
+
+            let (unit, source) = hint
+                .or_else(|| {
+                    obj.symbols
+                        .for_section_range(section_index, start..end)
+                        .find_map(|(_, s)| class_name(s.demangled_name.as_deref()?))
+                        .map(|class| (format!("{class}.cpp"), SuggestionSource::ClassName))
+                })
+                .unwrap_or_else(|| {
+                    (gap_unit_name(section_index, start, section), SuggestionSource::Placeholder)
+                });
+            let conflict = section
+                .splits
+                .for_unit(&unit)
+                .ok()
+                .flatten()
+                .is_some_and(|(address, split)| split.end != start && address != end);
+
+            let mut references = BTreeMap::<String, u32>::new();
+            let mut strings = Vec::<String>::new();
+            for item in addresses[i..j].iter().map(|a| &items[a]) {
+                for (unit, count) in &item.units {
+                    *references.entry(unit.clone()).or_default() += count;
+                }
+                for string in &item.strings {
+                    if !strings.contains(string) {
+                        strings.push(string.clone());
+                    }
+                }
+            }
+            let mut references = references.into_iter().collect::<Vec<_>>();
+            references.sort_by(|a, b| b.1.cmp(&a.1));
+            let symbols = obj
+                .symbols
+                .for_section_range(section_index, start..end)
+                .filter(|(_, s)| {
+                    s.kind != ObjSymbolKind::Section
+                        && !s.name.is_empty()
+                        && !s.name.starts_with('@')
+                        && !is_auto_symbol(s)
+                })
+                .map(|(_, s)| SuggestionSymbol {
+                    address: s.address as u32,
+                    name: s.demangled_name.unwrap_or(s.name).to_string(),
+                    signature: is_signature_symbol(&s.name),
+                })
+                .collect();
+
+            suggestions.push(SplitSuggestion {
+                section_index,
+                section: section.name.clone(),
+                start,
+                end,
+                unit,
+                source,
+                conflict,
+                prev_unit: section
+                    .splits
+                    .for_range(..start)
+                    .rev()
+                    .find(|(_, split)| is_user_split(split))
+                    .map(|(_, split)| split.unit.clone()),
+                next_unit: section
+                    .splits
+                    .for_range(end..)
+                    .find(|(_, split)| is_user_split(split))
+                    .map(|(_, split)| split.unit.clone()),
+                references,
+                symbols,
+                strings,
+            });
+            i = j;
+        }
+    }
+    suggestions
+}
+
+fn item_at(
+    sections: &mut BTreeMap<usize, BTreeMap<u32, Item>>,
+    section_index: usize,
+    address: u32,
+) -> Option<&mut Item> {
+    sections
+        .get_mut(&section_index)?
+        .range_mut(..=address)
+        .next_back()
+        .filter(|(&start, item)| address == start || address < item.end)
+        .map(|(_, item)| item)
+}
+
+/// A printable NUL-terminated string at an address in a data section.
+fn read_string(section: &ObjSection, address: u32) -> Option<String> {
+    if matches!(section.kind, ObjSectionKind::Code | ObjSectionKind::Bss) {
+        return None;
+    }
+    let offset = address.checked_sub(section.address as u32)? as usize;
+    let data = section.data.get(offset..)?;
+    let len = data.iter().take(256).position(|&c| c == 0)?;
+    let bytes = &data[..len];
+    (len >= 4 && bytes.iter().all(|&c| c.is_ascii_graphic() || matches!(c, b' ' | b'\t' | b'\n')))
+        .then(|| String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// The file name of a string that looks like a C or C++ source path.
+fn source_file_name(s: &str) -> Option<&str> {
+    let name = s.rsplit(['/', '\\']).next()?;
+    let (stem, extension) = name.rsplit_once('.')?;
+    let valid = !stem.is_empty()
+        && matches!(extension, "c" | "cpp" | "cp" | "cxx" | "cc")
+        && stem.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    valid.then_some(name)
+}
+
+/// The class of a demangled C++ member function, e.g. `CGuiLight` for
+/// `CGuiLight::BuildLight() const`.
+fn class_name(demangled: &str) -> Option<&str> {
+    let (path, _) = demangled.split('(').next()?.rsplit_once("::")?;
+    let class = path.rsplit("::").next()?;
+    let class = class.split('<').next()?;
+    (!class.is_empty()).then_some(class)
+}
@@ -0,0 +1,33 @@
+use std::time::{Duration, Instant};
+
+/// Time spent in each stage of processing a module, in order.
+#[derive(Debug, Clone)]
+pub struct Timings {
+    pub stages: Vec<(&'static str, Duration)>,
+    last: Instant,
+}
+
+impl Default for Timings {
+    fn default() -> Self { Self::new() }
+}
+
+impl Timings {
+    pub fn new() -> Self { Self { stages: vec![], last: Instant::now() } }
+
+    /// Records the time since the previous stage (or the last restart) under `name`.
+    /// Stages recorded more than once are summed.
+    pub fn record(&mut self, name: &'static str) {
+        let now = Instant::now();
+        let elapsed = now - self.last;
+        self.last = now;
+        match self.stages.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, duration)) => *duration += elapsed,
+            None => self.stages.push((name, elapsed)),
+        }
+    }
+
+    /// Restarts the clock without recording, e.g. after waiting on other modules.
+    pub fn restart(&mut self) { self.last = Instant::now(); }
+
+    pub fn total(&self) -> Duration { self.stages.iter().map(|(_, d)| *d).sum() }
+}